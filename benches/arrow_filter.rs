@@ -0,0 +1,117 @@
+// Benchmark of a worker reading its range of a 5M-event Arrow subscriber history: read everything and filter
+// against a set of the range's MSISDNs, or skip other ranges' rows while reading. Peak heap use of one read
+// of each is printed before the timings
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rs_cdr_generator::identity::{Imei, Imsi, Mccmnc, Msisdn};
+use rs_cdr_generator::subscriber_db::{MsisdnIndexRange, SubscriberEvent, SubscriberEventType};
+use rs_cdr_generator::subscriber_db_arrow::{read_events_from_arrow, read_events_from_arrow_in_range, write_events_to_arrow};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const EVENTS: usize = 5_000_000;
+const SUBSCRIBERS: usize = 1_000_000;
+const WORKERS: usize = 8;
+
+/// System allocator keeping track of the bytes in use and their peak
+struct PeakAlloc {
+    current: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+unsafe impl GlobalAlloc for PeakAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let current = self.current.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            self.peak.fetch_max(current, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        self.current.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOC: PeakAlloc = PeakAlloc { current: AtomicUsize::new(0), peak: AtomicUsize::new(0) };
+
+/// Bytes allocated at the peak of `f`, above what was in use before it
+fn peak_bytes<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOC.current.load(Ordering::Relaxed);
+    ALLOC.peak.store(before, Ordering::Relaxed);
+    let result = f();
+    (result, ALLOC.peak.load(Ordering::Relaxed) - before)
+}
+
+fn prefixes() -> Vec<String> {
+    vec!["31612".to_string(), "31613".to_string()]
+}
+
+/// Five events per subscriber on the worker index scheme, the way `generate-subscribers` exports them
+fn write_history(path: &Path) {
+    let prefixes = [31612u64, 31613];
+    let events: Vec<SubscriberEvent> = (0..EVENTS)
+        .map(|i| {
+            let idx = i % SUBSCRIBERS;
+            let msisdn = prefixes[idx % prefixes.len()] * 10_000_000 + idx as u64;
+            SubscriberEvent {
+                timestamp_ms: 1_704_067_200_000 + (i / SUBSCRIBERS) as i64 * 86_400_000,
+                event_type: if i < SUBSCRIBERS { SubscriberEventType::NewSubscriber } else { SubscriberEventType::ChangeDevice },
+                imsi: Imsi::new(204_080_000_000_000 + idx as u64).unwrap(),
+                msisdn: Some(Msisdn::new(msisdn).unwrap()),
+                imei: Some(Imei::new(350_000_000_000_000 + i as u64).unwrap()),
+                mccmnc: Mccmnc::new(20408).unwrap(),
+                sv: (i % 99) as u8,
+            }
+        })
+        .collect();
+    write_events_to_arrow(&events, path).unwrap();
+}
+
+/// The first worker's share: every event parsed, then kept if its MSISDN is in a set of the range's MSISDNs
+fn read_then_filter(path: &Path, start_u: usize, end_u: usize) -> Vec<SubscriberEvent> {
+    let prefixes = [31612u64, 31613];
+    let msisdns: HashSet<u64> =
+        (start_u..end_u).map(|idx| prefixes[idx % prefixes.len()] * 10_000_000 + (idx % 10_000_000) as u64).collect();
+    let mut events = read_events_from_arrow(path).unwrap();
+    events.retain(|e| e.msisdn.is_some_and(|m| msisdns.contains(&m.get())));
+    events
+}
+
+fn read_in_range(path: &Path, range: &MsisdnIndexRange) -> Vec<SubscriberEvent> {
+    read_events_from_arrow_in_range(path, range).unwrap()
+}
+
+fn bench_arrow_filter(c: &mut Criterion) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("events.arrow");
+    write_history(&path);
+    let (start_u, end_u) = (0, SUBSCRIBERS / WORKERS);
+    let range = MsisdnIndexRange::new(start_u, end_u, &prefixes()).unwrap();
+
+    let (old, old_peak) = peak_bytes(|| read_then_filter(&path, start_u, end_u));
+    let (new, new_peak) = peak_bytes(|| read_in_range(&path, &range));
+    assert_eq!(old, new);
+    println!(
+        "{} of {} events kept; peak heap: read then filter {:.1} MB, filter while reading {:.1} MB",
+        new.len(),
+        EVENTS,
+        old_peak as f64 / 1e6,
+        new_peak as f64 / 1e6
+    );
+    drop((old, new));
+
+    let mut group = c.benchmark_group("arrow_worker_range");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(EVENTS as u64));
+    group.bench_function(BenchmarkId::from_parameter("read_then_filter"), |b| b.iter(|| read_then_filter(&path, start_u, end_u)));
+    group.bench_function(BenchmarkId::from_parameter("filter_while_reading"), |b| b.iter(|| read_in_range(&path, &range)));
+    group.finish();
+}
+
+criterion_group!(benches, bench_arrow_filter);
+criterion_main!(benches);
@@ -0,0 +1,239 @@
+// Criterion benchmarks for the hot paths of CDR generation
+use chrono::{NaiveDate, TimeZone};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::async_writer::{EventBatch, WriterMessage};
+use rs_cdr_generator::cells::{generate_cells, RatMix};
+use rs_cdr_generator::compression::CompressionType;
+use rs_cdr_generator::config::{load_config, Config, ContactsConfig};
+use rs_cdr_generator::generators::{sample_placement_time, CallGenerator, DataGenerator, PlacementCurve, SmsGenerator};
+use rs_cdr_generator::identity::{build_contacts, build_subscribers, gen_imei, SubscriberIdentity};
+use rs_cdr_generator::record::CdrRecord;
+use rs_cdr_generator::subscriber_db::SubscriberSnapshot;
+use rs_cdr_generator::subscriber_db_generator::{generate_database, GeneratorConfig};
+use rs_cdr_generator::subscriber_db_redb::{DbDurability, DbWriteOptions, SubscriberDbRedb};
+use rs_cdr_generator::timezone_utils::{local_day_bounds, tz_from_name};
+use rs_cdr_generator::writer::{EventRow, EventWriter, WriterOptions};
+use std::path::Path;
+
+/// Load benches/configs/benchmark_micro.yaml, falling back to defaults if it is missing
+fn create_test_config() -> Config {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("benches/configs/benchmark_micro.yaml");
+    if path.exists() {
+        load_config(Some(&path)).unwrap_or_default()
+    } else {
+        Config::default()
+    }
+}
+
+fn bench_identity(c: &mut Criterion) {
+    let cfg = create_test_config();
+
+    c.bench_function("gen_imei", |b| {
+        let mut rng = StdRng::seed_from_u64(42);
+        b.iter(|| black_box(gen_imei(&mut rng)))
+    });
+
+    let mut group = c.benchmark_group("build_subscribers");
+    for count in [1_000usize, 10_000] {
+        group.bench_with_input(BenchmarkId::from_parameter(count), &count, |b, &count| {
+            let mut rng = StdRng::seed_from_u64(42);
+            b.iter(|| black_box(build_subscribers(count, &cfg.prefixes, &cfg.mccmnc_pool, &cfg.prefix_operator_map, &mut rng)))
+        });
+    }
+    group.finish();
+
+    c.bench_function("build_contacts_1000", |b| {
+        let mut rng = StdRng::seed_from_u64(42);
+        b.iter(|| black_box(build_contacts(1_000, &ContactsConfig::default(), &mut rng)))
+    });
+}
+
+fn bench_cells(c: &mut Criterion) {
+    let cfg = create_test_config();
+    let rat_mix = RatMix::new(&cfg.rat_mix).unwrap();
+    c.bench_function("generate_cells", |b| {
+        b.iter(|| black_box(generate_cells(cfg.cells, cfg.center_lat, cfg.center_lon, cfg.radius_km, 42, &rat_mix)))
+    });
+}
+
+fn bench_event_generators(c: &mut Criterion) {
+    let cfg = create_test_config();
+    let mut rng = StdRng::seed_from_u64(42);
+    let subs = build_subscribers(2, &cfg.prefixes, &cfg.mccmnc_pool, &cfg.prefix_operator_map, &mut rng).unwrap();
+    let start = tz_from_name(&cfg.tz_name).with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+
+    let call_gen = CallGenerator::new(&cfg).unwrap();
+    let sms_gen = SmsGenerator::new(&cfg).unwrap();
+    let data_gen = DataGenerator::new(None, RatMix::new(&cfg.rat_mix).unwrap(), &cfg.apn_mix, &cfg.apn_profiles).unwrap();
+    let mut event = EventRow::default();
+
+    c.bench_function("call_generate", |b| {
+        b.iter(|| CdrRecord::from(call_gen.generate(&subs[0], start, subs[1].msisdn.get(), "Europe/Amsterdam", 12345, &mut rng)).write_row(&mut event))
+    });
+    c.bench_function("sms_generate", |b| {
+        b.iter(|| CdrRecord::from(sms_gen.generate(&subs[0], start, subs[1].msisdn.get(), "Europe/Amsterdam", 12345, &mut rng)).write_row(&mut event))
+    });
+    c.bench_function("data_generate", |b| {
+        b.iter(|| CdrRecord::from(data_gen.generate(&subs[0], start, "Europe/Amsterdam", &mut rng)).write_row(&mut event))
+    });
+}
+
+fn bench_sample_time(c: &mut Criterion) {
+    let cfg = create_test_config();
+    let (start, end) = local_day_bounds(tz_from_name(&cfg.tz_name), NaiveDate::from_ymd_opt(2025, 1, 1).unwrap()).unwrap();
+    let secs = (end - start).num_seconds();
+    let mut rng = StdRng::seed_from_u64(42);
+
+    // Before: the diurnal multiplier evaluated per attempt; after: one pick from the day's tabulated curve
+    let mut group = c.benchmark_group("sample_time");
+    group.bench_function("rejection", |b| {
+        b.iter(|| black_box(sample_placement_time(&mut rng, &cfg, "2025-01-01", &start, secs, 0.0)))
+    });
+    let curve = PlacementCurve::new(&cfg, "2025-01-01", &start, secs, 0.0);
+    group.bench_function("placement_curve", |b| b.iter(|| black_box(curve.sample(&mut rng))));
+    group.finish();
+}
+
+fn bench_writer(c: &mut Criterion) {
+    let cfg = create_test_config();
+    let dir = tempfile::tempdir().unwrap();
+    let compression = CompressionType::from_str(&cfg.compression_type).unwrap_or(CompressionType::None);
+
+    let mut rng = StdRng::seed_from_u64(42);
+    let subs = build_subscribers(2, &cfg.prefixes, &cfg.mccmnc_pool, &cfg.prefix_operator_map, &mut rng).unwrap();
+    let start = tz_from_name(&cfg.tz_name).with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+    let row = CdrRecord::from(CallGenerator::new(&cfg).unwrap().generate(&subs[0], start, subs[1].msisdn.get(), "Europe/Amsterdam", 12345, &mut rng)).to_row();
+
+    let mut writer = EventWriter::new(dir.path(), "2025-01-01", cfg.rotate_bytes, 0, compression, cfg.compression_level, WriterOptions::default()).unwrap();
+    c.bench_function("event_writer_write_row", |b| b.iter(|| writer.write_row(black_box(&row)).unwrap()));
+
+    // Worker side: keep rows vs serialize them; writer side: serialize rows vs only copy bytes
+    let fill = |mut batch: EventBatch| {
+        for _ in 0..1_000 {
+            batch.push(black_box(&row));
+        }
+        batch
+    };
+    let rows = fill(EventBatch::new(1_000));
+    let WriterMessage::Serialized(serialized) = fill(EventBatch::serialized(1_000)).into_message() else {
+        unreachable!()
+    };
+    let mut group = c.benchmark_group("event_batch_1000");
+    group.throughput(Throughput::Bytes(serialized.bytes.len() as u64));
+    group.bench_function("push_rows", |b| b.iter(|| fill(EventBatch::new(1_000))));
+    group.bench_function("push_serialized", |b| b.iter(|| fill(EventBatch::serialized(1_000))));
+    group.bench_function("write_rows", |b| b.iter(|| writer.write_batch(black_box(&rows)).unwrap()));
+    group.bench_function("write_serialized", |b| b.iter(|| writer.write_serialized(black_box(&serialized)).unwrap()));
+    group.finish();
+    writer.close().unwrap();
+
+    // Every codec compiled into this build, on the same pre-serialized rows
+    let mut group = c.benchmark_group("compression_1000_rows");
+    group.throughput(Throughput::Bytes(serialized.bytes.len() as u64));
+    for compression in CompressionType::ALL.into_iter().filter(|c| c.is_available()) {
+        let level = compression.effective_level(None);
+        let dir = tempfile::tempdir().unwrap();
+        let mut writer = EventWriter::new(dir.path(), "2025-01-01", cfg.rotate_bytes, 0, compression, Some(level), WriterOptions::default()).unwrap();
+        group.bench_function(BenchmarkId::from_parameter(format!("{:?}", compression).to_lowercase()), |b| {
+            b.iter(|| writer.write_serialized(black_box(&serialized)).unwrap())
+        });
+        writer.close().unwrap();
+    }
+    group.finish();
+}
+
+fn bench_subscriber_db(c: &mut Criterion) {
+    let cfg = create_test_config();
+    let gen_config = GeneratorConfig {
+        initial_subscribers: 1_000,
+        history_days: 30,
+        prefixes: cfg.prefixes.clone(),
+        mccmnc_pool: cfg.mccmnc_pool.clone(),
+        seed: cfg.seed,
+        ..GeneratorConfig::default()
+    };
+
+    let mut group = c.benchmark_group("subscriber_db");
+    group.sample_size(10);
+    group.bench_function("generate_database_1000x30d", |b| {
+        b.iter(|| black_box(generate_database(&gen_config).unwrap()))
+    });
+    group.finish();
+
+    // Bulk load of 1M subscribers: fsync per 10k batch (previous behaviour) vs one fsync at the end
+    let entries: Vec<(u64, Vec<SubscriberSnapshot>)> = (0..1_000_000u64)
+        .map(|i| {
+            let identity = SubscriberIdentity::from_numbers(31_612_000_000 + i, 204_080_000_000_000 + i, 350_000_000_000_000 + i, 20408).unwrap();
+            (identity.msisdn.get(), vec![SubscriberSnapshot { identity, valid_from: 0, valid_to: None }])
+        })
+        .collect();
+    let mut group = c.benchmark_group("redb_load_1m");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(entries.len() as u64));
+    for (label, batch_size, durability) in [
+        ("safe_10k", 10_000, DbDurability::Safe),
+        ("fast_10k", 10_000, DbDurability::Fast),
+        ("fast_100k", 100_000, DbDurability::Fast),
+    ] {
+        let options = DbWriteOptions { batch_size, durability, compact: false };
+        group.bench_function(label, |b| {
+            b.iter(|| {
+                let dir = tempfile::tempdir().unwrap();
+                let db = SubscriberDbRedb::new(&dir.path().join("subs.redb")).unwrap();
+                db.insert_all(black_box(&entries), &options).unwrap();
+            })
+        });
+    }
+    group.finish();
+}
+
+/// IoT-like day over 1M database subscribers: a mostly-zero Poisson draw per subscriber vs the range totals
+/// spread over the few subscribers with events (`sparse_threshold`)
+fn bench_sparse_generation(c: &mut Criterion) {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("subs.redb");
+    let entries: Vec<(u64, Vec<SubscriberSnapshot>)> = (0..1_000_000u64)
+        .map(|i| {
+            let identity = SubscriberIdentity::from_numbers(316_120_000_000 + i, 204_080_000_000_000 + i, 350_000_000_000_000 + i, 20408).unwrap();
+            (identity.msisdn.get(), vec![SubscriberSnapshot { identity, valid_from: 0, valid_to: None }])
+        })
+        .collect();
+    let options = DbWriteOptions { batch_size: 100_000, durability: DbDurability::Fast, compact: false };
+    SubscriberDbRedb::new(&db_path).unwrap().insert_all(&entries, &options).unwrap();
+    drop(entries);
+    let db = std::sync::Arc::new(SubscriberDbRedb::open(&db_path).unwrap());
+
+    let day = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+    let mut group = c.benchmark_group("iot_day_1m");
+    group.sample_size(10);
+    for (label, sparse_threshold) in [("dense", 0.0), ("sparse", 0.1)] {
+        let cfg = Config {
+            prefixes: vec!["31612".to_string()],
+            workers: 4,
+            avg_calls_per_user: 0.002,
+            avg_sms_per_user: 0.001,
+            avg_data_sessions_per_user: 0.005,
+            sparse_threshold,
+            check_db: false,
+            ..create_test_config()
+        };
+        let mut generator = DayGenerator::builder().config(cfg).subscriber_db(db.clone()).sink(|_| {}).build().unwrap();
+        group.bench_function(label, |b| b.iter(|| generator.generate(day).unwrap()));
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_identity,
+    bench_cells,
+    bench_event_generators,
+    bench_sample_time,
+    bench_writer,
+    bench_subscriber_db,
+    bench_sparse_generation
+);
+criterion_main!(benches);
@@ -0,0 +1,50 @@
+// End-to-end benchmark of the multi-threaded pipeline: workers -> writer channels -> compressed part files
+use chrono::NaiveDate;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::config::{load_config, Config, OverwritePolicy};
+use std::path::Path;
+
+/// benchmark_micro.yaml scaled up to several workers feeding half as many writer tasks; every iteration
+/// regenerates the same day, so each replaces the previous one's part files
+fn pipeline_config(serialize_in_workers: bool) -> Config {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("benches/configs/benchmark_micro.yaml");
+    let cfg = load_config(Some(&path)).unwrap_or_default();
+    Config {
+        subscribers: 8_000,
+        workers: 8,
+        writer_tasks: 4,
+        compression_type: "gzip".to_string(),
+        compression_level: Some(1),
+        serialize_in_workers,
+        overwrite_policy: OverwritePolicy::Clean,
+        ..cfg
+    }
+}
+
+fn bench_pipeline(c: &mut Criterion) {
+    let day = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+    let mut group = c.benchmark_group("pipeline_8_workers");
+    group.sample_size(10);
+
+    for serialize_in_workers in [false, true] {
+        let dir = tempfile::tempdir().unwrap();
+        let mut generator = DayGenerator::builder()
+            .config(pipeline_config(serialize_in_workers))
+            .out_dir(dir.path())
+            .bundle(false)
+            .build()
+            .unwrap();
+
+        // Events per day, so results read as records/s
+        let summary = generator.generate(day).unwrap();
+        group.throughput(Throughput::Elements((summary.total_calls + summary.total_sms + summary.total_data) as u64));
+
+        let label = if serialize_in_workers { "serialize_in_workers" } else { "serialize_in_writers" };
+        group.bench_function(BenchmarkId::from_parameter(label), |b| b.iter(|| generator.generate(day).unwrap()));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_pipeline);
+criterion_main!(benches);
@@ -0,0 +1,19 @@
+// Build script: record the git commit of the build for the run provenance in summary.json
+use std::path::Path;
+use std::process::Command;
+
+fn main() {
+    // A hash handed in by the build environment (e.g. a CI build from a source tarball) wins over asking git
+    println!("cargo:rerun-if-env-changed=RS_CDR_GIT_HASH");
+    // Only paths that exist: a missing one would rerun the script on every build, as in a source tarball without .git
+    for path in [".git/HEAD", ".git/refs/heads"].into_iter().filter(|path| Path::new(path).exists()) {
+        println!("cargo:rerun-if-changed={}", path);
+    }
+    let hash = std::env::var("RS_CDR_GIT_HASH").ok().or_else(|| {
+        let output = Command::new("git").args(["rev-parse", "--short=12", "HEAD"]).output().ok()?;
+        output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    });
+    if let Some(hash) = hash.filter(|hash| !hash.is_empty()) {
+        println!("cargo:rustc-env=RS_CDR_GIT_HASH={}", hash);
+    }
+}
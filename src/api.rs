@@ -0,0 +1,1181 @@
+// Library facade: generate whole days of CDRs without wiring config, cells, channels and workers by hand
+#[cfg(feature = "redb")]
+use anyhow::Context;
+use crate::async_writer::{BatchSender, EventBatch, WriterMessage, WriterMetrics, WriterRuntime};
+use crate::cells::{
+    cells_catalog, ensure_cells_catalog, generate_cells, load_cells_catalog, Cell, CellsCatalog, CellsMeta, RatMix,
+};
+use crate::compression::CompressionType;
+use crate::config::{
+    validate_event_pool_size, validate_mccmnc_pool, validate_output_strings, validate_prefix_operator_map, validate_qci_tables,
+    validate_rat_mix, Config, OutputFormat, OverwritePolicy,
+};
+use crate::cores::available_cores;
+use crate::daily_targets::target_deviations;
+use crate::day_over_day::{DayActivity, DayOverDayTracker};
+#[cfg(feature = "redb")]
+use crate::db_check::check_db_consistency;
+use crate::db_check::DbCheckReport;
+#[cfg(not(feature = "redb"))]
+use crate::generators::SubscriberDbRedb;
+use crate::generators::{day_tz, output_label, placement_window, worker_generate, ShardStats, WorkerContext};
+use crate::late_delivery::{remove_staged_files, stage_late_files, staged_files};
+#[cfg(feature = "redb")]
+use crate::provenance::redb_fingerprint;
+use crate::provenance::{file_fingerprint, write_run_config, RunProvenance};
+#[cfg(feature = "redb")]
+use crate::provisioning::{export_provisioning, PROVISIONING_FILE};
+use crate::realtime::{RealtimeReplay, ReplayClock, ReplayTime};
+use crate::schema::write_schema;
+use crate::subscriber_db::load_msisdn_file;
+#[cfg(feature = "redb")]
+use crate::subscriber_db_redb::SubscriberDbRedb;
+use crate::timezone_utils::{local_day_bounds, tz_from_name};
+use crate::utils::{
+    aggregate_shard_stats, bundle_day, check_miss_rate, clean_day_dir, existing_bundles, existing_ground_truth_files,
+    existing_part_files, incomplete_part_files, recompress_day, replaced_part_files, sparkline, write_summary, writer_shard_map,
+    ThroughputMetrics, WriterShards,
+};
+use crate::writer::{EventRow, Rotation, WriterOptions};
+use chrono::{DateTime, NaiveDate};
+use chrono_tz::Tz;
+use rayon::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::{info, warn};
+
+pub use crate::utils::DailySummary as DaySummary;
+
+/// Consumer of generated event batches, used instead of the CSV part files
+pub trait RecordWriter: Send {
+    fn write_batch(&mut self, batch: EventBatch) -> anyhow::Result<()>;
+
+    /// Called once after the last batch of each day
+    fn finish_day(&mut self, _day_str: &str) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// Adapter behind `DayGeneratorBuilder::sink`
+struct FnSink<F>(F);
+
+impl<F: FnMut(EventBatch) + Send> RecordWriter for FnSink<F> {
+    fn write_batch(&mut self, batch: EventBatch) -> anyhow::Result<()> {
+        (self.0)(batch);
+        Ok(())
+    }
+}
+
+/// Where the generator takes its subscribers from
+pub enum SubscriberSource {
+    /// Random subscribers, `config.subscribers` of them
+    Random,
+    /// CSV subscriber database (or `.arrow` event export), filtered per worker range
+    Csv(PathBuf),
+    /// redb subscriber database, opened on `build()`
+    RedbPath(PathBuf),
+    /// Already opened redb subscriber database
+    Redb(Arc<SubscriberDbRedb>),
+}
+
+impl From<PathBuf> for SubscriberSource {
+    /// `.csv` and `.arrow` files are event histories loaded by the workers, anything else is opened as redb
+    fn from(path: PathBuf) -> Self {
+        if path.extension().is_some_and(|ext| ext == "csv" || ext == "arrow") {
+            SubscriberSource::Csv(path)
+        } else {
+            SubscriberSource::RedbPath(path)
+        }
+    }
+}
+
+impl From<&Path> for SubscriberSource {
+    fn from(path: &Path) -> Self {
+        path.to_path_buf().into()
+    }
+}
+
+#[cfg(feature = "redb")]
+impl From<Arc<SubscriberDbRedb>> for SubscriberSource {
+    fn from(db: Arc<SubscriberDbRedb>) -> Self {
+        SubscriberSource::Redb(db)
+    }
+}
+
+#[cfg(feature = "redb")]
+impl From<SubscriberDbRedb> for SubscriberSource {
+    fn from(db: SubscriberDbRedb) -> Self {
+        SubscriberSource::Redb(Arc::new(db))
+    }
+}
+
+/// Where the cells catalog comes from
+pub enum CellsSource {
+    /// Existing cells.csv
+    Path(PathBuf),
+    /// Cells built by the caller
+    Cells(Vec<Cell>),
+}
+
+impl From<PathBuf> for CellsSource {
+    fn from(path: PathBuf) -> Self {
+        CellsSource::Path(path)
+    }
+}
+
+impl From<&Path> for CellsSource {
+    fn from(path: &Path) -> Self {
+        CellsSource::Path(path.to_path_buf())
+    }
+}
+
+impl From<Vec<Cell>> for CellsSource {
+    fn from(cells: Vec<Cell>) -> Self {
+        CellsSource::Cells(cells)
+    }
+}
+
+/// Builder for [`DayGenerator`], see [`DayGenerator::builder`]
+#[derive(Default)]
+pub struct DayGeneratorBuilder {
+    config: Config,
+    subscribers: Option<SubscriberSource>,
+    cells: Option<CellsSource>,
+    seed: Option<u64>,
+    out_dir: Option<PathBuf>,
+    writer: Option<Box<dyn RecordWriter>>,
+    bundle: Option<bool>,
+    provisioning: bool,
+    replay_time: ReplayTime,
+}
+
+impl DayGeneratorBuilder {
+    /// Base configuration (defaults to `Config::default()`)
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Subscriber database: a path (`.csv` or redb) or an opened redb handle.
+    /// Without it the paths in the config are used, falling back to random subscribers
+    pub fn subscriber_db(mut self, source: impl Into<SubscriberSource>) -> Self {
+        self.subscribers = Some(source.into());
+        self
+    }
+
+    /// Cells catalog: a cells.csv path or cells in memory.
+    /// Without it the catalog is created in the output directory from the config
+    pub fn cells(mut self, source: impl Into<CellsSource>) -> Self {
+        self.cells = Some(source.into());
+        self
+    }
+
+    /// Seed for deterministic generation (overrides `config.seed`)
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Output directory for part files, manifests and summary.json
+    pub fn out_dir(mut self, out_dir: impl Into<PathBuf>) -> Self {
+        self.out_dir = Some(out_dir.into());
+        self
+    }
+
+    /// Send batches to a custom writer instead of CSV part files
+    pub fn writer(mut self, writer: Box<dyn RecordWriter>) -> Self {
+        self.writer = Some(writer);
+        self
+    }
+
+    /// Send batches to a closure instead of CSV part files
+    pub fn sink<F: FnMut(EventBatch) + Send + 'static>(self, sink: F) -> Self {
+        self.writer(Box::new(FnSink(sink)))
+    }
+
+    /// Combine each day's part files into `out_dir/cdr_<day>.csv[.ext]`, optionally removing the parts
+    pub fn bundle(mut self, cleanup_after_archive: bool) -> Self {
+        self.bundle = Some(cleanup_after_archive);
+        self
+    }
+
+    /// Also write each day's provisioning delta (activations, releases, device changes) from the redb
+    /// subscriber database to `<day dir>/provisioning.csv`
+    pub fn provisioning(mut self) -> Self {
+        self.provisioning = true;
+        self
+    }
+
+    /// Time `realtime` replay is paced against (defaults to the wall clock); virtual time replays at once
+    pub fn replay_time(mut self, time: ReplayTime) -> Self {
+        self.replay_time = time;
+        self
+    }
+
+    /// Resolve subscribers and cells and check the output settings
+    pub fn build(self) -> anyhow::Result<DayGenerator> {
+        let mut config = self.config;
+        if let Some(seed) = self.seed {
+            config.seed = seed;
+        }
+        if config.workers == 0 {
+            config.workers = available_cores();
+        }
+        if config.writer_tasks > config.workers {
+            anyhow::bail!(
+                "writer_tasks ({}) must not exceed workers ({}): every writer task needs a worker feeding it",
+                config.writer_tasks,
+                config.workers
+            );
+        }
+
+        let parse_codec = |name: &str| {
+            let codec = CompressionType::from_str(name).ok_or_else(|| {
+                anyhow::anyhow!("Invalid compression type: {}. Expected gzip, zstd, bzip2, xz or none.", name)
+            })?;
+            codec.ensure_available()?;
+            anyhow::Ok(codec)
+        };
+        let (part_codec, part_level) = config.part_compression();
+        let (compression_type, compression_level) = (parse_codec(part_codec)?, part_level);
+        let (bundle_codec, bundle_level) = config.bundle_compression();
+        let bundle_compression = (parse_codec(bundle_codec)?, bundle_level);
+        validate_output_strings(&config)?;
+        validate_mccmnc_pool(&config)?;
+        validate_prefix_operator_map(&config)?;
+        validate_qci_tables(&config)?;
+        validate_event_pool_size(&config)?;
+        validate_rat_mix(&config)?;
+
+        if self.writer.is_none() && self.out_dir.is_none() {
+            anyhow::bail!("DayGenerator needs an output directory or a writer/sink");
+        }
+        if config.realtime && !(config.realtime_speedup.is_finite() && config.realtime_speedup > 0.0) {
+            anyhow::bail!("realtime_speedup must be a positive number, got {}", config.realtime_speedup);
+        }
+        if config.write_shard_stats && self.out_dir.is_none() {
+            anyhow::bail!("write_shard_stats requires an output directory");
+        }
+        if self.bundle.is_some() && self.writer.is_some() {
+            anyhow::bail!("bundle() only applies to CSV part files, not to a custom writer/sink");
+        }
+        if config.output_format == OutputFormat::Arrow {
+            if cfg!(not(feature = "arrow")) {
+                anyhow::bail!("output_format arrow requires building with `--features arrow`");
+            }
+            if self.writer.is_some() {
+                anyhow::bail!("output_format arrow applies to part files, not to a custom writer/sink");
+            }
+            if self.bundle.is_some() {
+                anyhow::bail!("bundle() joins CSV part files; Arrow part files are not bundled");
+            }
+        }
+
+        let source = self.subscribers.unwrap_or_else(|| {
+            if let Some(ref path) = config.subscriber_db_redb_path {
+                SubscriberSource::RedbPath(path.clone())
+            } else if let Some(ref path) = config.subscriber_db_path {
+                SubscriberSource::Csv(path.clone())
+            } else {
+                SubscriberSource::Random
+            }
+        });
+
+        let (redb, csv_db, subscribers) = match source {
+            SubscriberSource::Random => (None, None, config.subscribers),
+            SubscriberSource::Csv(path) => (None, Some(path), config.subscribers),
+            #[cfg(feature = "redb")]
+            SubscriberSource::RedbPath(path) => {
+                info!("Loading subscriber database: {:?}", path);
+                let db = Arc::new(SubscriberDbRedb::open(&path)?);
+                config.subscriber_db_redb_path = Some(path);
+                let count = db.count_msisdns()?;
+                info!("Loaded {} subscribers from database", count);
+                (Some(db), None, count)
+            }
+            #[cfg(feature = "redb")]
+            SubscriberSource::Redb(db) => {
+                let count = db.count_msisdns()?;
+                (Some(db), None, count)
+            }
+            #[cfg(not(feature = "redb"))]
+            SubscriberSource::RedbPath(_) | SubscriberSource::Redb(_) => {
+                anyhow::bail!("A redb subscriber database requires building with `--features redb`")
+            }
+        };
+        // Targeted run: the listed MSISDNs take the place of the index-derived subscribers
+        let targets: Option<Arc<[u64]>> = match config.msisdn_file {
+            Some(ref path) => {
+                if redb.is_none() {
+                    anyhow::bail!("msisdn_file needs a redb subscriber database to look the listed MSISDNs up in");
+                }
+                let msisdns = load_msisdn_file(path)?;
+                info!("Targeted run: {} MSISDNs from {:?}", msisdns.len(), path);
+                Some(msisdns.into())
+            }
+            None if config.closed_user_group => anyhow::bail!("closed_user_group only applies with msisdn_file"),
+            None => None,
+        };
+        let subscribers = targets.as_ref().map_or(subscribers, |targets| targets.len());
+        if self.provisioning && (redb.is_none() || self.out_dir.is_none()) {
+            anyhow::bail!("provisioning() needs a redb subscriber database and an output directory");
+        }
+        #[cfg(feature = "redb")]
+        let earliest_snapshot_ms = match redb {
+            Some(ref db) => db.earliest_snapshot_ms()?,
+            None => None,
+        };
+        #[cfg(not(feature = "redb"))]
+        let earliest_snapshot_ms = None;
+
+        let cells = match self.cells {
+            Some(CellsSource::Path(path)) => load_cells_catalog(&path)?,
+            Some(CellsSource::Cells(cells)) => cells_catalog(&cells),
+            None => match self.out_dir {
+                Some(ref out_dir) => {
+                    let path = ensure_cells_catalog(out_dir, &CellsMeta::from_config(&config), config.regen_cells)?;
+                    load_cells_catalog(&path)?
+                }
+                None => cells_catalog(&generate_cells(
+                    config.cells,
+                    config.center_lat,
+                    config.center_lon,
+                    config.radius_km,
+                    config.seed,
+                    &RatMix::new(&config.rat_mix)?,
+                )),
+            },
+        };
+
+        // Generators, contacts and parsed settings of each worker are the same every day
+        let mut workers =
+            WorkerContext::for_ranges(&split_ranges(subscribers, config.workers), &config, &cells, csv_db.as_deref(), redb.is_some())?;
+        if let Some(targets) = targets {
+            workers = workers.into_iter().map(|ctx| ctx.with_targets(targets.clone())).collect();
+        }
+
+        let db_check = match redb {
+            #[cfg(feature = "redb")]
+            Some(ref db) if config.check_db => {
+                let report = check_db_consistency(db, &workers, config.db_check_samples)?;
+                if report.hit_rate() < config.db_check_min_hit_rate {
+                    warn!(
+                        "Subscriber database check: {}. Workers look subscribers up as prefix × 10^7 + index; \
+                         check that --prefixes and the number width match the database",
+                        report
+                    );
+                } else {
+                    info!("Subscriber database check: {}", report);
+                }
+                Some(report)
+            }
+            _ => None,
+        };
+
+        // Fingerprints of the effective config and the subscriber database for every day's summary
+        let provenance = match (&redb, csv_db) {
+            #[cfg(feature = "redb")]
+            (Some(db), _) => RunProvenance::new(&config, config.subscriber_db_redb_path.clone(), Some(redb_fingerprint(db)?))?,
+            (None, Some(path)) => {
+                let fingerprint = file_fingerprint(&path)?;
+                RunProvenance::new(&config, Some(path), Some(fingerprint))?
+            }
+            _ => RunProvenance::new(&config, None, None)?,
+        };
+
+        let day_over_day = config.day_over_day;
+        Ok(DayGenerator {
+            config,
+            compression_type,
+            compression_level,
+            bundle_compression,
+            redb,
+            earliest_snapshot_ms,
+            subscribers,
+            workers,
+            cells,
+            out_dir: self.out_dir,
+            writer: self.writer,
+            bundle: self.bundle,
+            provisioning: self.provisioning,
+            replay_time: self.replay_time,
+            run_throughput: None,
+            day_over_day: DayOverDayTracker::new(day_over_day, subscribers),
+            db_check,
+            provenance,
+        })
+    }
+}
+
+/// Generates whole days of CDRs with the same workers as the CLI.
+///
+/// By default rows go to CSV part files under `out_dir/<day>/`, followed by summary.json,
+/// late-file staging and optional bundling. With `writer()` / `sink()` the batches go to the
+/// caller instead.
+///
+/// ```
+/// use chrono::NaiveDate;
+/// use rs_cdr_generator::api::DayGenerator;
+/// use rs_cdr_generator::config::Config;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::sync::Arc;
+///
+/// let rows = Arc::new(AtomicUsize::new(0));
+/// let counter = rows.clone();
+///
+/// let mut generator = DayGenerator::builder()
+///     .config(Config { subscribers: 50, workers: 2, cells: 20, ..Config::default() })
+///     .seed(7)
+///     .sink(move |batch| {
+///         counter.fetch_add(batch.len(), Ordering::Relaxed);
+///     })
+///     .build()?;
+///
+/// let summary = generator.generate(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap())?;
+/// assert_eq!(
+///     rows.load(Ordering::Relaxed),
+///     summary.total_calls + summary.total_sms + summary.total_data
+/// );
+/// # anyhow::Ok(())
+/// ```
+///
+/// Writing files from a redb subscriber database, as `generate-cdr` does:
+///
+/// ```no_run
+/// use chrono::NaiveDate;
+/// use rs_cdr_generator::api::DayGenerator;
+/// use rs_cdr_generator::config::load_config;
+/// use std::path::Path;
+///
+/// let mut generator = DayGenerator::builder()
+///     .config(load_config(Some(Path::new("config.yaml")))?)
+///     .subscriber_db(Path::new("subscriber_db.redb"))
+///     .out_dir("out")
+///     .bundle(false)
+///     .build()?;
+/// generator.write_schema()?;
+/// generator.generate(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap())?;
+/// # anyhow::Ok(())
+/// ```
+pub struct DayGenerator {
+    config: Config,
+    /// Codec and level of the part files
+    compression_type: CompressionType,
+    compression_level: Option<i32>,
+    /// Codec and level of the day bundle; anything other than the parts' codec and level is re-encoded
+    bundle_compression: (CompressionType, Option<i32>),
+    redb: Option<Arc<SubscriberDbRedb>>,
+    /// Start of the redb database's history, from its metadata table
+    earliest_snapshot_ms: Option<i64>,
+    subscribers: usize,
+    /// One per worker range, reused every day
+    workers: Vec<WorkerContext>,
+    cells: CellsCatalog,
+    out_dir: Option<PathBuf>,
+    writer: Option<Box<dyn RecordWriter>>,
+    bundle: Option<bool>,
+    /// Write the day's provisioning delta next to summary.json
+    provisioning: bool,
+    /// Time `realtime` replay is paced against
+    replay_time: ReplayTime,
+    /// Throughput summed over the days generated so far
+    run_throughput: Option<ThroughputMetrics>,
+    /// Active MSISDNs of the last generated day, for `day_over_day` in the next day's summary
+    day_over_day: DayOverDayTracker,
+    /// Startup lookups of index-derived MSISDNs in the redb database (`check_db`)
+    db_check: Option<DbCheckReport>,
+    /// Generator version and config/database fingerprints recorded in every summary.json
+    provenance: RunProvenance,
+}
+
+impl DayGenerator {
+    pub fn builder() -> DayGeneratorBuilder {
+        DayGeneratorBuilder::default()
+    }
+
+    /// Effective configuration (seed and worker count resolved)
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Number of subscribers split across the workers
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers
+    }
+
+    /// Cells catalog resolved on build
+    pub fn cells(&self) -> &CellsCatalog {
+        &self.cells
+    }
+
+    /// Result of the startup database consistency check; None without a redb database or with `check_db` off
+    pub fn db_check(&self) -> Option<&DbCheckReport> {
+        self.db_check.as_ref()
+    }
+
+    /// Generator version, config hash, seed and subscriber database of the run
+    pub fn provenance(&self) -> &RunProvenance {
+        &self.provenance
+    }
+
+    /// Throughput over all days so far; None with `metrics` off or a custom writer
+    pub fn run_throughput(&self) -> Option<&ThroughputMetrics> {
+        self.run_throughput.as_ref()
+    }
+
+    /// Write `schema.json`, the authoritative column list of the CSV part files, into the output root.
+    /// Arrow files carry their own schema, and a custom writer has no output root
+    pub fn write_schema(&self) -> anyhow::Result<PathBuf> {
+        match (&self.writer, &self.out_dir, self.config.output_format) {
+            (None, Some(out_dir), OutputFormat::Csv) => write_schema(out_dir, &WriterOptions::from_config(&self.config)),
+            _ => anyhow::bail!("schema.json describes CSV part files in an output directory"),
+        }
+    }
+
+    /// Generate one calendar day: local in the configured timezone, or UTC per `day_folder_basis`
+    pub fn generate(&mut self, date: NaiveDate) -> anyhow::Result<DaySummary> {
+        // First instant of the local (or UTC, per `day_folder_basis`) day, past a DST gap at midnight
+        let (day, day_end) = local_day_bounds(day_tz(&self.config, tz_from_name(&self.config.tz_name)), date)?;
+        let day_str = output_label(&self.config, date);
+        let ranges = split_ranges(self.subscribers, self.config.workers);
+
+        // Subscribers are only resolvable from their first snapshot on
+        if let Some(earliest) = self.earliest_snapshot_ms {
+            if day.timestamp_millis() < earliest {
+                warn!(
+                    "{} starts before the subscriber database's earliest snapshot ({}); events before it will miss subscribers",
+                    day_str,
+                    DateTime::from_timestamp_millis(earliest).unwrap_or_default().format("%Y-%m-%d %H:%M UTC")
+                );
+            }
+        }
+
+        let (mut overwrite_warnings, merged_parts) = match (&self.writer, &self.out_dir) {
+            (None, Some(out_dir)) => self.apply_overwrite_policy(out_dir, &day_str)?,
+            _ => (Vec::new(), Vec::new()),
+        };
+
+        // Live replay starts from the first instant of the generated window
+        let replay = if self.config.realtime {
+            let (origin, _, _) = placement_window(&self.config, tz_from_name(&self.config.tz_name), date)?;
+            let clock = ReplayClock::new(origin.timestamp_millis(), self.config.realtime_speedup);
+            Some(ReplayClock { time: self.replay_time.clone(), ..clock })
+        } else {
+            None
+        };
+
+        let started = Instant::now();
+        let activity = self.day_over_day.begin_day();
+        let (mut shard_stats, writer_metrics) = if self.writer.is_some() {
+            (self.run_with_writer(day, &day_str, &activity, replay)?, None)
+        } else {
+            let (shard_stats, writer_metrics) = self.run_with_files(day, &day_str, &ranges, &activity, replay)?;
+            (shard_stats, self.config.metrics.then_some(writer_metrics))
+        };
+        if let (false, Some(out_dir)) = (merged_parts.is_empty(), &self.out_dir) {
+            overwrite_warnings.extend(self.merge_warnings(out_dir, &day_str, &merged_parts, ranges.len())?);
+        }
+
+        let mut summary = aggregate_shard_stats(&shard_stats, ranges.len(), overwrite_warnings);
+        summary.provenance = Some(self.provenance.clone());
+        if self.writer.is_none() {
+            summary.writers = writer_shard_map(ranges.len(), self.writer_task_count(ranges.len()));
+        }
+        summary.day_over_day = self
+            .day_over_day
+            .end_day(activity, shard_stats.iter_mut().map(|stats| std::mem::take(&mut stats.activity)));
+        if self.config.daily_targets.is_enabled() {
+            summary.daily_targets = target_deviations(&self.config, tz_from_name(&self.config.tz_name), date, &summary)?;
+            for (key, deviation) in &summary.daily_targets {
+                info!(
+                    "Day {}: {} {} of target {:.0} ({:+.2}%)",
+                    day_str, deviation.achieved, key, deviation.target, deviation.deviation_pct
+                );
+            }
+        }
+        if let Some(overlap) = summary.day_over_day {
+            info!(
+                "Day {}: {} MSISDNs active again, {} new, {} gone since the previous day{}",
+                day_str,
+                overlap.repeat,
+                overlap.new,
+                overlap.gone,
+                if overlap.approximate { " (approximate)" } else { "" }
+            );
+        }
+        if let Some(writer_metrics) = writer_metrics {
+            let throughput = ThroughputMetrics::new(started.elapsed().as_secs_f64(), writer_metrics);
+            info!("Day {} throughput: {}", day_str, throughput.report());
+            match self.run_throughput {
+                Some(ref mut run) => run.add(&throughput),
+                None => self.run_throughput = Some(throughput.clone()),
+            }
+            summary.throughput = Some(throughput);
+        }
+        if let Some(ref out_dir) = self.out_dir {
+            let day_dir = self.config.partition_layout.day_dir(out_dir, &day_str);
+            std::fs::create_dir_all(&day_dir)?;
+            // Leftovers of a crashed earlier run; the manifests only list completed parts
+            for file in incomplete_part_files(&day_dir, self.config.partition_layout)? {
+                warn!("Day {}: incomplete part file {}", day_str, file);
+                summary.warnings.push(format!("incomplete part file {}", file));
+            }
+            write_summary(&summary, &day_dir)?;
+            write_run_config(&day_dir, &self.provenance, &self.config)?;
+            if let (true, Some(db)) = (self.provisioning, &self.redb) {
+                #[cfg(feature = "redb")]
+                {
+                    let path = day_dir.join(PROVISIONING_FILE);
+                    let file = std::fs::File::create(&path).with_context(|| format!("Failed to create {:?}", path))?;
+                    let rows = export_provisioning(db, &self.config, date, std::io::BufWriter::new(file))?;
+                    info!("Day {}: {} provisioning rows → {:?}", day_str, rows, path);
+                }
+                #[cfg(not(feature = "redb"))]
+                match **db {}
+            }
+        }
+        info!(
+            "Day {}: {} active subscribers, {} inactive, {} skipped",
+            day_str, summary.active_subscribers, summary.inactive_subscribers, summary.skipped_subscribers
+        );
+        info!("Day {} calls by hour  |{}|", day_str, sparkline(&summary.calls_by_hour));
+        info!("Day {} SMS by hour    |{}|", day_str, sparkline(&summary.sms_by_hour));
+        info!("Day {} data by hour   |{}|", day_str, sparkline(&summary.data_by_hour));
+        if summary.dirty_rows > 0 {
+            info!("Day {}: {} rows written with dirty_data faults", day_str, summary.dirty_rows);
+        }
+        if summary.self_check_violations > 0 {
+            warn!(
+                "Day {}: self-check found {} violations in {} checked rows",
+                day_str, summary.self_check_violations, summary.self_check_rows
+            );
+        } else if summary.self_check_rows > 0 {
+            info!("Day {}: self-check passed on {} rows", day_str, summary.self_check_rows);
+        }
+        if summary.sms_unreachable > 0 {
+            info!(
+                "Day {}: {} MT SMS retried with {} extra attempt rows",
+                day_str, summary.sms_unreachable, summary.sms_retry_rows
+            );
+        }
+        check_miss_rate(&summary, &day_str, self.config.miss_rate_warn_threshold, self.config.fail_on_miss_rate)?;
+
+        if let (None, Some(out_dir)) = (&self.writer, &self.out_dir) {
+            stage_late_files(
+                out_dir,
+                &day_str,
+                self.config.partition_layout,
+                self.config.late_file_rate,
+                self.config.late_file_delay_hours,
+                day_end.timestamp_millis(),
+                self.config.seed ^ day.timestamp() as u64,
+            )?;
+            if let Some(cleanup) = self.bundle {
+                let layout = self.config.partition_layout;
+                // A merged day bundles the earlier run's parts too, whatever writer tasks wrote them
+                let writer_tasks = match self.config.overwrite_policy {
+                    OverwritePolicy::Merge => None,
+                    OverwritePolicy::Fail | OverwritePolicy::Clean => Some(self.writer_task_count(ranges.len())),
+                };
+                let bundle_path = match self.bundle_compression {
+                    // Same codec and level: the compressed parts concatenate into a valid stream
+                    (codec, level)
+                        if codec == self.compression_type
+                            && codec.effective_level(level) == codec.effective_level(self.compression_level) =>
+                    {
+                        bundle_day(out_dir, &day_str, layout, cleanup, codec.extension(), writer_tasks)?
+                    }
+                    (codec, level) => recompress_day(out_dir, &day_str, layout, cleanup, codec, level, writer_tasks)?.path,
+                };
+                info!("Day {} done → {:?}", day_str, bundle_path);
+                return Ok(summary);
+            }
+        }
+
+        info!("Day {} done", day_str);
+        Ok(summary)
+    }
+
+    /// Rows of database subscriber `msisdn` on `date` as a run with the same config, seed and workers
+    /// writes them, in generation order, without writing anything. The subscribers of a worker share its
+    /// per-day RNG stream, so the worker range holding `msisdn` is regenerated in memory and its rows are
+    /// filtered to those with `msisdn` as source or destination. Writer-side `record_seq` numbers and
+    /// injected duplicates are not replayed
+    pub fn replay_subscriber(&self, date: NaiveDate, msisdn: u64) -> anyhow::Result<Vec<EventRow>> {
+        if self.redb.is_none() && !self.workers.iter().any(WorkerContext::has_csv_subscribers) {
+            anyhow::bail!("Replaying a subscriber needs a subscriber database; random subscribers have no fixed MSISDN");
+        }
+        let ctx = self
+            .workers
+            .iter()
+            .find(|ctx| ctx.serves_msisdn(msisdn))
+            .ok_or_else(|| anyhow::anyhow!("MSISDN {} is not one of the {} subscribers of this run", msisdn, self.subscribers))?;
+        let (day, _) = local_day_bounds(day_tz(&self.config, tz_from_name(&self.config.tz_name)), date)?;
+        let out_dir = self.out_dir.clone().unwrap_or_default();
+        // Rows are filtered here, so workers must not serialize them
+        let config = &Config {
+            serialize_in_workers: false,
+            write_shard_stats: false,
+            ..self.config.clone()
+        };
+
+        let (tx, rx) = crossbeam_channel::unbounded::<WriterMessage>();
+        let sender = BatchSender::new(tx, Arc::new(AtomicBool::new(false)));
+
+        // Filter batches as they come, so only the subscriber's rows are held rather than the whole range's
+        let (worker_result, rows) = std::thread::scope(|scope| {
+            let consumer = scope.spawn(|| {
+                let mut rows = Vec::new();
+                // Ends once the worker returns and drops its sender
+                for msg in rx.iter() {
+                    if let WriterMessage::Batch(batch) = msg {
+                        rows.extend(batch.events.into_iter().filter(|row| row.msisdn_src == msisdn || row.msisdn_dst == msisdn));
+                    }
+                }
+                rows
+            });
+            let worker_result =
+                worker_generate(day, ctx, config, &self.cells, &out_dir, self.redb.as_ref(), &DayActivity::off(), sender);
+            (worker_result, consumer.join())
+        });
+        worker_result?;
+        rows.map_err(|_| anyhow::anyhow!("Replay consumer thread panicked"))
+    }
+
+    /// Part files already in the day directory are an earlier run's: refuse the day, remove them with the rest of
+    /// that run's per-day files and what it staged in `late/<day>/`, or keep them, per `overwrite_policy`.
+    /// Returns warnings for the summary and the part files kept to merge
+    fn apply_overwrite_policy(&self, out_dir: &Path, day_str: &str) -> anyhow::Result<(Vec<String>, Vec<String>)> {
+        let layout = self.config.partition_layout;
+        let day_dir = layout.day_dir(out_dir, day_str);
+        let bundles = existing_bundles(out_dir, day_str);
+        if self.config.overwrite_policy == OverwritePolicy::Clean {
+            let mut removed = clean_day_dir(&day_dir, layout)? + remove_staged_files(out_dir, day_str)?;
+            for bundle in &bundles {
+                std::fs::remove_file(bundle).map_err(|e| anyhow::anyhow!("Failed to remove {:?}: {}", bundle, e))?;
+                removed += 1;
+            }
+            if removed > 0 {
+                info!("Day {}: removed {} files of an earlier run from {:?}", day_str, removed, day_dir);
+            }
+            return Ok((Vec::new(), Vec::new()));
+        }
+
+        // Only a bundling run replaces an earlier day bundle
+        let bundles = if self.bundle.is_some() { bundles } else { Vec::new() };
+        let existing = existing_part_files(&day_dir, layout)?;
+        let staged = staged_files(out_dir, day_str)?;
+        if self.config.overwrite_policy == OverwritePolicy::Fail {
+            if let Some(first) = existing.first() {
+                anyhow::bail!(
+                    "Day {}: {:?} already holds {} part files of an earlier run ({}, ...); use overwrite policy clean or merge",
+                    day_str,
+                    day_dir,
+                    existing.len(),
+                    first
+                );
+            }
+            if let Some(first) = existing_ground_truth_files(&day_dir)?.first() {
+                anyhow::bail!(
+                    "Day {}: {:?} already holds the ground truth {} of an earlier run; use overwrite policy clean or merge",
+                    day_str,
+                    day_dir,
+                    first
+                );
+            }
+            if let Some(first) = staged.first() {
+                anyhow::bail!(
+                    "Day {}: an earlier run staged {} late files ({:?}, ...); use overwrite policy clean or merge",
+                    day_str,
+                    staged.len(),
+                    first
+                );
+            }
+            if let Some(bundle) = bundles.first() {
+                anyhow::bail!("Day {}: {:?} is the bundle of an earlier run; use overwrite policy clean or merge", day_str, bundle);
+            }
+            return Ok((Vec::new(), Vec::new()));
+        }
+
+        // Merged parts are reported once this run has written its own, which may reuse their names
+        let mut warnings = Vec::new();
+        // deliver-late moves them into this run's day directory
+        if !staged.is_empty() {
+            let late_dir = staged[0].parent().unwrap_or(out_dir);
+            warn!("Day {}: KEEPING {} late files an earlier run staged in {:?}", day_str, staged.len(), late_dir);
+            warnings.push(format!("kept {} late files an earlier run staged", staged.len()));
+        }
+        // With its parts cleaned up after bundling, the earlier run's rows are only in its bundle
+        if let (true, Some(bundle)) = (existing.is_empty(), bundles.first()) {
+            warn!("Day {}: REPLACING the bundle of an earlier run {:?}; its rows are lost", day_str, bundle);
+            warnings.push(format!("replaced the bundle of an earlier run {:?}", bundle));
+        }
+        Ok((warnings, existing))
+    }
+
+    /// Warnings for the part files `merged` from an earlier run: those this run wrote again under the same
+    /// `writerNNN_partNNN` name were replaced, only the rest are merged into its output and bundle
+    fn merge_warnings(&self, out_dir: &Path, day_str: &str, merged: &[String], ranges: usize) -> anyhow::Result<Vec<String>> {
+        let day_dir = self.config.partition_layout.day_dir(out_dir, day_str);
+        let replaced = replaced_part_files(&day_dir, merged, self.writer_task_count(ranges))?;
+        let kept = merged.len() - replaced.len();
+        let mut warnings = Vec::new();
+        if kept > 0 {
+            warn!("Day {}: MERGING {} part files of an earlier run in {:?} into this run's output and bundle", day_str, kept, day_dir);
+            warnings.push(format!("merged {} part files of an earlier run", kept));
+        }
+        if !replaced.is_empty() {
+            let names = replaced.join(", ");
+            warn!("Day {}: REPLACED {} part files of an earlier run with this run's: {}", day_str, replaced.len(), names);
+            warnings.push(format!("replaced {} part files of an earlier run: {}", replaced.len(), names));
+        }
+        Ok(warnings)
+    }
+
+    /// Writer tasks of a file run (default: workers / 2), never more than there are ranges to feed them
+    fn writer_task_count(&self, ranges: usize) -> usize {
+        let writer_tasks = if self.config.writer_tasks > 0 {
+            self.config.writer_tasks
+        } else {
+            (ranges / 2).max(1)
+        };
+        writer_tasks.min(ranges.max(1))
+    }
+
+    /// Workers feed async file writers round-robin; part files are named after the writer task, not the worker
+    fn run_with_files(
+        &self,
+        day: DateTime<Tz>,
+        day_str: &str,
+        ranges: &[(usize, usize)],
+        activity: &DayActivity,
+        replay: Option<ReplayClock>,
+    ) -> anyhow::Result<(Vec<ShardStats>, Vec<WriterMetrics>)> {
+        let out_dir = self
+            .out_dir
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("CSV output requires an output directory"))?;
+        let writers = WriterRuntime::new()?;
+
+        let writer_tasks = self.writer_task_count(ranges.len());
+        if writer_tasks < self.config.writer_tasks {
+            info!(
+                "writer_tasks {} exceeds the {} non-empty worker ranges; using {} writer tasks",
+                self.config.writer_tasks,
+                ranges.len(),
+                writer_tasks
+            );
+        }
+
+        // Set by a failing writer so every worker stops generating
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut writer_channels = Vec::with_capacity(writer_tasks);
+        let mut writer_handles = Vec::with_capacity(writer_tasks);
+        for WriterShards { writer: writer_id, worker_shards } in writer_shard_map(ranges.len(), writer_tasks) {
+            let (tx, rx) = crossbeam_channel::unbounded();
+            writer_channels.push(tx);
+            writer_handles.push(writers.spawn(
+                rx,
+                out_dir.to_path_buf(),
+                day_str.to_string(),
+                writer_id,
+                worker_shards,
+                Rotation::from_config(&self.config),
+                self.compression_type,
+                self.compression_level,
+                WriterOptions::from_config(&self.config),
+                stop.clone(),
+                self.config.metrics,
+                replay.clone(),
+            ));
+        }
+
+        let worker_result = run_workers(
+            &self.config,
+            &self.cells,
+            self.redb.as_ref(),
+            day,
+            &self.workers,
+            out_dir,
+            activity,
+            |i| BatchSender::new(writer_channels[i % writer_tasks].clone(), stop.clone()),
+        );
+
+        // Close writers even if a worker failed so their files are finished
+        for tx in writer_channels {
+            let _ = tx.send(WriterMessage::Close);
+        }
+        // A writer failure is the root cause of workers stopping, so it is reported first
+        let mut writer_result = Ok(());
+        let mut writer_metrics = Vec::with_capacity(writer_tasks);
+        for handle in writer_handles {
+            match writers.join(handle) {
+                Ok(metrics) => writer_metrics.push(metrics),
+                Err(e) if writer_result.is_ok() => writer_result = Err(e),
+                Err(_) => {}
+            }
+        }
+        writer_result?;
+
+        Ok((worker_result?, writer_metrics))
+    }
+
+    /// Workers feed a single channel drained by the custom writer on its own thread.
+    /// With `replay` set the batches are held until the workers are done and replayed to the writer in start time order
+    fn run_with_writer(
+        &mut self,
+        day: DateTime<Tz>,
+        day_str: &str,
+        activity: &DayActivity,
+        replay: Option<ReplayClock>,
+    ) -> anyhow::Result<Vec<ShardStats>> {
+        let mut writer = self
+            .writer
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("No record writer configured"))?;
+        let out_dir = self.out_dir.clone().unwrap_or_default();
+        // Record writers receive rows, so workers must not serialize them
+        let config = &Config {
+            serialize_in_workers: false,
+            ..self.config.clone()
+        };
+        let (cells, redb, workers) = (&self.cells, self.redb.as_ref(), &self.workers);
+        let (tx, rx) = crossbeam_channel::unbounded::<WriterMessage>();
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let (worker_result, writer_result) = std::thread::scope(|scope| {
+            let consumer = scope.spawn(|| -> anyhow::Result<()> {
+                let mut replay = replay.map(RealtimeReplay::new);
+                let mut result = Ok(());
+                while let Ok(msg) = rx.recv() {
+                    match msg {
+                        WriterMessage::Batch(batch) => {
+                            if let Some(replay) = replay.as_mut() {
+                                replay.push(batch);
+                            } else if result.is_ok() && !batch.is_empty() {
+                                result = writer.write_batch(batch);
+                                // Keep draining after a failure so no worker waits on the channel
+                                if result.is_err() {
+                                    stop.store(true, Ordering::Relaxed);
+                                }
+                            }
+                        }
+                        WriterMessage::Serialized(_) => {
+                            result = Err(anyhow::anyhow!("Record writers cannot take pre-serialized batches"));
+                            stop.store(true, Ordering::Relaxed);
+                        }
+                        WriterMessage::Close => break,
+                    }
+                }
+                result?;
+                if let Some(replay) = replay {
+                    replay.replay(|batch| writer.write_batch(batch))?;
+                }
+                writer.finish_day(day_str)
+            });
+
+            let worker_result = run_workers(config, cells, redb, day, workers, &out_dir, activity, |_| {
+                BatchSender::new(tx.clone(), stop.clone())
+            });
+            let _ = tx.send(WriterMessage::Close);
+            let writer_result = consumer
+                .join()
+                .unwrap_or_else(|_| Err(anyhow::anyhow!("Record writer thread panicked")));
+            (worker_result, writer_result)
+        });
+
+        self.writer = Some(writer);
+        writer_result?;
+        worker_result
+    }
+}
+
+/// Run one worker per context in parallel; `channel_for` picks the writer channel of worker `i`
+#[allow(clippy::too_many_arguments)]
+fn run_workers<F>(
+    config: &Config,
+    cells: &CellsCatalog,
+    redb: Option<&Arc<SubscriberDbRedb>>,
+    day: DateTime<Tz>,
+    workers: &[WorkerContext],
+    out_dir: &Path,
+    activity: &DayActivity,
+    channel_for: F,
+) -> anyhow::Result<Vec<ShardStats>>
+where
+    F: Fn(usize) -> BatchSender + Sync,
+{
+    workers
+        .par_iter()
+        .map(|ctx| worker_generate(day, ctx, config, cells, out_dir, redb, activity, channel_for(ctx.shard_id())))
+        .collect()
+}
+
+/// Split `subscribers` uniformly across `workers`; the last range takes the remainder.
+/// There are never more ranges than subscribers, so no worker gets an empty range
+fn split_ranges(subscribers: usize, workers: usize) -> Vec<(usize, usize)> {
+    let workers = workers.min(subscribers).max(1);
+    let shard_size = subscribers / workers;
+    let mut ranges = Vec::with_capacity(workers);
+    let mut start = 0;
+    for i in 0..workers {
+        let end = if i < workers - 1 { start + shard_size } else { subscribers };
+        ranges.push((start, end));
+        start = end;
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use tempfile::tempdir;
+
+    fn small_config() -> Config {
+        Config {
+            subscribers: 200,
+            workers: 4,
+            cells: 50,
+            compression_type: "none".to_string(),
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn test_split_ranges() {
+        assert_eq!(split_ranges(10, 3), vec![(0, 3), (3, 6), (6, 10)]);
+        assert_eq!(split_ranges(2, 4), vec![(0, 1), (1, 2)]);
+        assert_eq!(split_ranges(0, 4), vec![(0, 0)]);
+    }
+
+    #[test]
+    fn test_generate_day_into_memory_sink() {
+        let rows: Arc<Mutex<Vec<EventRow>>> = Arc::new(Mutex::new(Vec::new()));
+        let sink_rows = rows.clone();
+
+        let mut generator = DayGenerator::builder()
+            .config(small_config())
+            .seed(11)
+            .sink(move |batch| sink_rows.lock().unwrap().extend(batch.events))
+            .build()
+            .unwrap();
+        assert_eq!(generator.subscriber_count(), 200);
+
+        let summary = generator.generate(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap()).unwrap();
+        let rows = rows.lock().unwrap();
+        assert_eq!(summary.shards, 4);
+        assert!(summary.total_data > 0);
+        assert_eq!(rows.len(), summary.total_calls + summary.total_sms + summary.total_data);
+        assert_eq!(rows.iter().filter(|r| r.event_type == "DATA").count(), summary.total_data);
+        assert_eq!(rows.iter().filter(|r| r.event_type == "SMS").count(), summary.total_sms);
+    }
+
+    #[test]
+    fn test_generate_day_to_files() {
+        let dir = tempdir().unwrap();
+        let mut generator = DayGenerator::builder()
+            .config(small_config())
+            .out_dir(dir.path())
+            .bundle(false)
+            .build()
+            .unwrap();
+
+        generator.generate(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap()).unwrap();
+        assert!(dir.path().join("cells.csv").exists());
+        assert!(dir.path().join("2025-01-01").join("summary.json").exists());
+        assert!(dir.path().join("cdr_2025-01-01.csv").exists());
+    }
+
+    #[test]
+    fn test_more_workers_than_subscribers() {
+        use std::io::Read;
+
+        let dir = tempdir().unwrap();
+        let config = Config {
+            subscribers: 5,
+            workers: 16,
+            compression_type: "gzip".to_string(),
+            ..small_config()
+        };
+        let mut generator = DayGenerator::builder().config(config).out_dir(dir.path()).bundle(false).build().unwrap();
+        let summary = generator.generate(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap()).unwrap();
+        assert_eq!(summary.shards, 5);
+        assert!(summary.warnings.is_empty(), "{:?}", summary.warnings);
+
+        let parts: Vec<PathBuf> = std::fs::read_dir(dir.path().join("2025-01-01"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.file_name().unwrap().to_string_lossy().starts_with("cdr_"))
+            .collect();
+        assert!(!parts.is_empty() && parts.len() <= 5, "{:?}", parts);
+        for part in &parts {
+            let mut csv = String::new();
+            flate2::read::MultiGzDecoder::new(std::fs::File::open(part).unwrap())
+                .read_to_string(&mut csv)
+                .unwrap();
+            assert!(csv.lines().count() > 1, "{:?} has no rows", part);
+        }
+    }
+
+    #[test]
+    fn test_writer_failure_stops_generation() {
+        let dir = tempdir().unwrap();
+        let config = Config {
+            fail_writes_after_bytes: 20_000,
+            ..small_config()
+        };
+        let mut generator = DayGenerator::builder().config(config).out_dir(dir.path()).bundle(false).build().unwrap();
+        let err = generator.generate(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap()).unwrap_err();
+        assert!(err.to_string().starts_with("Out of space while writing writer "), "{:#}", err);
+        assert!(!dir.path().join("2025-01-01").join("summary.json").exists());
+    }
+
+    fn manifest_worker_shards(day_dir: &std::path::Path) -> Vec<(usize, Vec<usize>)> {
+        let mut manifests: Vec<(usize, Vec<usize>)> = std::fs::read_dir(day_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.file_name().unwrap().to_string_lossy().starts_with("manifest_writer"))
+            .map(|path| {
+                let manifest: crate::writer::ShardManifest = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+                (manifest.writer, manifest.worker_shards)
+            })
+            .collect();
+        manifests.sort();
+        manifests
+    }
+
+    #[test]
+    fn test_manifests_list_worker_shards_per_writer_task() {
+        let dir = tempdir().unwrap();
+        let config = Config { writer_tasks: 2, ..small_config() };
+        let mut generator = DayGenerator::builder().config(config).out_dir(dir.path()).bundle(false).build().unwrap();
+        generator.generate(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap()).unwrap();
+        assert_eq!(manifest_worker_shards(&dir.path().join("2025-01-01")), vec![(0, vec![0, 2]), (1, vec![1, 3])]);
+    }
+
+    #[test]
+    fn test_writer_tasks_shrink_to_non_empty_ranges() {
+        let dir = tempdir().unwrap();
+        // 3 subscribers leave 3 of the 8 worker ranges non-empty
+        let config = Config { subscribers: 3, workers: 8, writer_tasks: 8, ..small_config() };
+        let mut generator = DayGenerator::builder().config(config).out_dir(dir.path()).bundle(false).build().unwrap();
+        generator.generate(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap()).unwrap();
+        assert_eq!(manifest_worker_shards(&dir.path().join("2025-01-01")), vec![(0, vec![0]), (1, vec![1]), (2, vec![2])]);
+    }
+
+    #[test]
+    fn test_writer_tasks_above_workers_is_rejected() {
+        let dir = tempdir().unwrap();
+        let config = Config { writer_tasks: 64, ..small_config() };
+        let err = DayGenerator::builder().config(config).out_dir(dir.path()).build().err().unwrap();
+        assert!(err.to_string().starts_with("writer_tasks (64) must not exceed workers (4)"), "{}", err);
+    }
+
+    #[test]
+    fn test_builder_requires_output() {
+        assert!(DayGenerator::builder().config(small_config()).build().is_err());
+    }
+}
@@ -0,0 +1,442 @@
+// Arrow IPC part files of the day's events (`output_format: arrow`, `arrow` feature)
+use crate::async_writer::{EventBatch, SerializedBatch};
+use crate::config::RotationPolicy;
+use crate::writer::{
+    sync_dir, EventRow, ManifestEntry, ShardManifest, WriterOptions, BEARER_FIELD_NONE, CORRELATION_ID_NONE, IMEISV_NONE,
+    MIXED_STREAM, PART_TMP_SUFFIX, PREV_EVENT_TS_NONE,
+};
+use anyhow::Context;
+use arrow_array::types::Int32Type;
+use arrow_array::{ArrayRef, DictionaryArray, Int32Array, Int64Array, RecordBatch, StringArray, UInt32Array, UInt64Array};
+use arrow_ipc::writer::FileWriter;
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Rows per record batch in an Arrow part file
+const ARROW_BATCH_ROWS: usize = 65_536;
+
+/// Columns stored dictionary-encoded (Int32 keys, Utf8 values): a handful of values repeated on every row
+pub const DICTIONARY_COLUMNS: [&str; 5] = ["event_type", "direction", "record_type", "rat", "apn"];
+
+fn dictionary_field(name: &str, nullable: bool) -> Field {
+    Field::new(name, DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)), nullable)
+}
+
+/// Schema of every Arrow part file: the `EventRow` fields in CSV column order, numbers as integers.
+/// Cells the CSV leaves empty are null; the optional CSV columns (`tz_*`, `imeisv`, `node_id`, `qci`, `bearer_id`, `correlation_id`,
+/// `prev_event_ts_ms`) are always there, `record_seq` never is. MSISDNs are the E.164 numbers whatever `msisdn_format`
+pub fn cdr_arrow_schema() -> Schema {
+    Schema::new(vec![
+        dictionary_field("event_type", false),
+        Field::new("msisdn_src", DataType::UInt64, true),
+        Field::new("msisdn_dst", DataType::UInt64, true),
+        dictionary_field("direction", false),
+        Field::new("start_ts_ms", DataType::Int64, false),
+        Field::new("end_ts_ms", DataType::Int64, false),
+        Field::new("tz_name", DataType::Utf8, false),
+        Field::new("tz_offset_min", DataType::Int32, false),
+        Field::new("duration_sec", DataType::Int64, false),
+        Field::new("mccmnc", DataType::UInt32, true),
+        Field::new("imsi", DataType::UInt64, true),
+        Field::new("imei", DataType::UInt64, true),
+        Field::new("imeisv", DataType::UInt64, true),
+        Field::new("cell_id", DataType::UInt32, false),
+        dictionary_field("record_type", false),
+        Field::new("node_id", DataType::Utf8, true),
+        Field::new("cause_for_record_closing", DataType::Utf8, true),
+        Field::new("sms_segments", DataType::UInt32, true),
+        Field::new("sms_status", DataType::Utf8, true),
+        Field::new("data_bytes_in", DataType::UInt64, true),
+        Field::new("data_bytes_out", DataType::UInt64, true),
+        Field::new("data_duration_sec", DataType::Int64, true),
+        dictionary_field("apn", true),
+        dictionary_field("rat", true),
+        Field::new("qci", DataType::UInt32, true),
+        Field::new("bearer_id", DataType::UInt32, true),
+        Field::new("correlation_id", DataType::UInt64, true),
+        Field::new("prev_event_ts_ms", DataType::Int64, true),
+    ])
+}
+
+/// Values of a row's `DICTIONARY_COLUMNS`
+fn dictionary_values(row: &EventRow) -> [&'static str; 5] {
+    [row.event_type, row.direction, row.record_type, row.rat, row.apn]
+}
+
+fn non_empty(value: &'static str) -> Option<&'static str> {
+    (!value.is_empty()).then_some(value)
+}
+
+/// `value` unless it is 0 or the `none` marker of its column
+fn present(value: u64, none: u64) -> Option<u64> {
+    (value != 0 && value != none).then_some(value)
+}
+
+/// Dictionary values of the open file, in order of first appearance. An IPC file holds a single dictionary
+/// per column, so a value first seen after the file's first record batch starts a new file
+#[derive(Debug, Clone, Default)]
+struct Vocabulary {
+    values: [Vec<&'static str>; 5],
+}
+
+impl Vocabulary {
+    /// Add the values of `rows`; true if any was new
+    fn extend(&mut self, rows: &[EventRow]) -> bool {
+        let mut grew = false;
+        for row in rows {
+            for (values, value) in self.values.iter_mut().zip(dictionary_values(row)) {
+                if !value.is_empty() && !values.contains(&value) {
+                    values.push(value);
+                    grew = true;
+                }
+            }
+        }
+        grew
+    }
+
+    /// Dictionary column `idx` of `rows`; empty strings are null
+    fn column(&self, idx: usize, rows: &[EventRow]) -> anyhow::Result<ArrayRef> {
+        let values = &self.values[idx];
+        let keys: Int32Array = rows
+            .iter()
+            .map(|row| {
+                let value = dictionary_values(row)[idx];
+                values.iter().position(|v| *v == value).map(|key| key as i32)
+            })
+            .collect();
+        Ok(Arc::new(DictionaryArray::<Int32Type>::try_new(keys, Arc::new(StringArray::from(values.clone())))?))
+    }
+}
+
+/// Arrow file being written, under its `.tmp` name
+struct OpenFile {
+    writer: FileWriter<BufWriter<File>>,
+    rows: u64,
+}
+
+/// Writes the rows of one writer task to `cdr_<day>_writerNNN_partNNN.arrow` files in the day directory,
+/// `arrow_rotate_rows` rows per file, and lists them in `manifest_writerNNN.json` like the CSV writer.
+/// Files are written uncompressed, as `<name>.tmp` until complete
+pub struct ArrowEventWriter {
+    day_str: String,
+    day_dir: PathBuf,
+    // Writer task id, not a worker shard; several workers may feed one writer
+    writer_id: usize,
+    worker_shards: Vec<usize>,
+    rotate_rows: u64,
+    schema: SchemaRef,
+    vocabulary: Vocabulary,
+    // Rows waiting for a full record batch
+    pending: Vec<EventRow>,
+    file: Option<OpenFile>,
+    part_num: u32,
+    files_written: u32,
+    bytes_written: u64,
+    manifest: Vec<ManifestEntry>,
+    closed: bool,
+}
+
+impl ArrowEventWriter {
+    pub fn new(out_dir: &Path, day_str: &str, writer_id: usize, writer_options: WriterOptions) -> anyhow::Result<Self> {
+        let day_dir = writer_options.partition_layout.day_dir(out_dir, day_str);
+        std::fs::create_dir_all(&day_dir)?;
+
+        Ok(ArrowEventWriter {
+            day_str: day_str.to_string(),
+            day_dir,
+            writer_id,
+            worker_shards: Vec::new(),
+            rotate_rows: writer_options.arrow_rotate_rows,
+            schema: Arc::new(cdr_arrow_schema()),
+            vocabulary: Vocabulary::default(),
+            pending: Vec::with_capacity(ARROW_BATCH_ROWS),
+            file: None,
+            part_num: 1,
+            files_written: 0,
+            bytes_written: 0,
+            manifest: Vec::new(),
+            closed: false,
+        })
+    }
+
+    /// Record the worker shards feeding this writer in its manifest
+    pub fn set_worker_shards(&mut self, worker_shards: Vec<usize>) {
+        self.worker_shards = worker_shards;
+    }
+
+    /// Part file being written, relative to the day directory
+    fn current_file(&self) -> String {
+        format!("cdr_{}_writer{:03}_part{:03}.arrow", self.day_str, self.writer_id, self.part_num)
+    }
+
+    fn tmp_path(&self) -> PathBuf {
+        self.day_dir.join(format!("{}{}", self.current_file(), PART_TMP_SUFFIX))
+    }
+
+    /// Queue the rows of a batch, writing a record batch whenever enough are queued
+    pub fn write_batch(&mut self, batch: &EventBatch) -> anyhow::Result<()> {
+        if self.closed {
+            return Ok(());
+        }
+        for row in &batch.events {
+            self.pending.push(row.clone());
+            if self.pending.len() >= ARROW_BATCH_ROWS {
+                self.flush_pending()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Arrow files are built from rows; workers must not serialize them to CSV
+    pub fn write_serialized(&mut self, _batch: &SerializedBatch) -> anyhow::Result<()> {
+        anyhow::bail!("Arrow part files take rows, not CSV lines serialized by the workers")
+    }
+
+    /// Write the queued rows, split where a file reaches `rotate_rows`
+    fn flush_pending(&mut self) -> anyhow::Result<()> {
+        let pending = std::mem::take(&mut self.pending);
+        let mut start = 0;
+        let mut result = Ok(());
+        while start < pending.len() && result.is_ok() {
+            let written = self.file.as_ref().map_or(0, |file| file.rows);
+            let room = match self.rotate_rows {
+                0 => pending.len() - start,
+                limit => (limit - written) as usize,
+            };
+            let end = pending.len().min(start + room);
+            result = self.write_rows(&pending[start..end]);
+            start = end;
+        }
+        self.pending = pending;
+        self.pending.clear();
+        result
+    }
+
+    fn write_rows(&mut self, rows: &[EventRow]) -> anyhow::Result<()> {
+        if self.vocabulary.extend(rows) && self.file.is_some() {
+            self.finish_file()?;
+        }
+        if self.file.is_none() {
+            let path = self.tmp_path();
+            let file = File::create(&path).with_context(|| format!("Failed to create {:?}", path))?;
+            let writer = FileWriter::try_new(BufWriter::with_capacity(1 << 20, file), &self.schema)?;
+            self.file = Some(OpenFile { writer, rows: 0 });
+        }
+
+        let batch = self.record_batch(rows)?;
+        let Some(file) = self.file.as_mut() else {
+            return Ok(());
+        };
+        file.writer
+            .write(&batch)
+            .with_context(|| format!("I/O error while writing writer {} part {}", self.writer_id, self.part_num))?;
+        file.rows += rows.len() as u64;
+        if self.rotate_rows > 0 && file.rows >= self.rotate_rows {
+            self.finish_file()?;
+        }
+        Ok(())
+    }
+
+    fn record_batch(&self, rows: &[EventRow]) -> anyhow::Result<RecordBatch> {
+        let u64s = |value: fn(&EventRow) -> Option<u64>| -> ArrayRef { Arc::new(rows.iter().map(value).collect::<UInt64Array>()) };
+        let i64s = |value: fn(&EventRow) -> Option<i64>| -> ArrayRef { Arc::new(rows.iter().map(value).collect::<Int64Array>()) };
+        let u32s = |value: fn(&EventRow) -> Option<u32>| -> ArrayRef { Arc::new(rows.iter().map(value).collect::<UInt32Array>()) };
+        let strings =
+            |value: fn(&EventRow) -> Option<&'static str>| -> ArrayRef { Arc::new(rows.iter().map(value).collect::<StringArray>()) };
+        let dictionary = |name: &str| {
+            let idx = DICTIONARY_COLUMNS.iter().position(|column| *column == name).unwrap_or_default();
+            self.vocabulary.column(idx, rows)
+        };
+
+        let columns = vec![
+            dictionary("event_type")?,
+            u64s(|row| present(row.msisdn_src, 0)),
+            u64s(|row| present(row.msisdn_dst, 0)),
+            dictionary("direction")?,
+            i64s(|row| Some(row.start_ts_ms)),
+            i64s(|row| Some(row.end_ts_ms)),
+            strings(|row| Some(row.tz_name)),
+            Arc::new(rows.iter().map(|row| row.tz_offset_min).collect::<Int32Array>()),
+            i64s(|row| Some(row.duration_sec)),
+            u32s(|row| (row.mccmnc != 0).then_some(row.mccmnc)),
+            u64s(|row| present(row.imsi, 0)),
+            u64s(|row| present(row.imei, 0)),
+            u64s(|row| present(row.imeisv, IMEISV_NONE)),
+            u32s(|row| Some(row.cell_id)),
+            dictionary("record_type")?,
+            strings(|row| non_empty(row.node_id)),
+            strings(|row| non_empty(row.cause_for_record_closing)),
+            u32s(|row| (row.sms_segments != 0).then_some(row.sms_segments)),
+            strings(|row| non_empty(row.sms_status)),
+            u64s(|row| row.data_bytes_in),
+            u64s(|row| row.data_bytes_out),
+            i64s(|row| row.data_duration_sec),
+            dictionary("apn")?,
+            dictionary("rat")?,
+            u32s(|row| (row.qci != 0 && row.qci != BEARER_FIELD_NONE).then_some(row.qci)),
+            u32s(|row| (row.bearer_id != 0 && row.bearer_id != BEARER_FIELD_NONE).then_some(row.bearer_id)),
+            u64s(|row| present(row.correlation_id, CORRELATION_ID_NONE)),
+            i64s(|row| (row.prev_event_ts_ms != 0 && row.prev_event_ts_ms != PREV_EVENT_TS_NONE).then_some(row.prev_event_ts_ms)),
+        ];
+        Ok(RecordBatch::try_new(self.schema.clone(), columns)?)
+    }
+
+    /// Write the file footer, sync the file and give it its final name
+    fn finish_file(&mut self) -> anyhow::Result<()> {
+        let Some(OpenFile { mut writer, rows }) = self.file.take() else {
+            return Ok(());
+        };
+        writer.finish()?;
+        let file = writer.into_inner()?.into_inner().map_err(|e| e.into_error())?;
+        file.sync_all()?;
+        let bytes = file.metadata()?.len();
+        drop(file);
+
+        let name = self.current_file();
+        std::fs::rename(self.tmp_path(), self.day_dir.join(&name))?;
+        sync_dir(&self.day_dir)?;
+        self.files_written += 1;
+        self.bytes_written += bytes;
+        self.manifest.push(ManifestEntry {
+            file: name,
+            writer: self.writer_id,
+            worker_shards: self.worker_shards.clone(),
+            file_seq: self.part_num,
+            event_type: None,
+            records: rows,
+            duplicates: 0,
+            bytes,
+            // Arrow files are written uncompressed
+            compression_ratio: 1.0,
+        });
+        self.part_num += 1;
+        Ok(())
+    }
+
+    /// Write the queued rows, finish the current file and write `manifest_writerNNN.json`
+    pub fn close(&mut self) -> anyhow::Result<()> {
+        if self.closed {
+            return Ok(());
+        }
+        self.flush_pending()?;
+        self.finish_file()?;
+
+        let manifest = ShardManifest {
+            day: self.day_str.clone(),
+            writer: self.writer_id,
+            worker_shards: self.worker_shards.clone(),
+            columns: self.schema.fields().iter().map(|field| field.name().clone()).collect(),
+            schema: Vec::new(),
+            rotation: BTreeMap::from([(
+                MIXED_STREAM.to_string(),
+                RotationPolicy { rows: self.rotate_rows, ..RotationPolicy::default() },
+            )]),
+            files: std::mem::take(&mut self.manifest),
+        };
+        let path = self.day_dir.join(format!("manifest_writer{:03}.json", self.writer_id));
+        std::fs::write(path, serde_json::to_string_pretty(&manifest)?)?;
+        self.closed = true;
+        Ok(())
+    }
+
+    /// Give up after a write failure: drop the unfinished file and the queued rows, record the completed files
+    pub fn abort(&mut self) {
+        if self.file.take().is_some() {
+            let _ = std::fs::remove_file(self.tmp_path());
+        }
+        self.pending.clear();
+        let _ = self.close();
+    }
+
+    /// Number of Arrow files completed so far
+    pub fn files_written(&self) -> u32 {
+        self.files_written
+    }
+
+    /// Total size of the completed Arrow files
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// Arrow files are not compressed: the same as `bytes_written`
+    pub fn uncompressed_bytes(&self) -> u64 {
+        self.bytes_written
+    }
+}
+
+impl Drop for ArrowEventWriter {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::cast::AsArray;
+    use arrow_array::Array;
+    use arrow_ipc::reader::FileReader;
+    use tempfile::tempdir;
+
+    fn row(event_type: &'static str, rat: &'static str, start_ts_ms: i64) -> EventRow {
+        EventRow {
+            event_type,
+            msisdn_src: 31612000001,
+            direction: "MO",
+            start_ts_ms,
+            tz_name: "UTC",
+            imsi: 204080000000001,
+            record_type: "pgwRecord",
+            rat,
+            imeisv: IMEISV_NONE,
+            ..EventRow::default()
+        }
+    }
+
+    fn batch(rows: Vec<EventRow>) -> EventBatch {
+        let mut batch = EventBatch::new(rows.len());
+        rows.iter().for_each(|row| batch.push(row));
+        batch
+    }
+
+    fn read(path: &Path) -> Vec<RecordBatch> {
+        FileReader::try_new(File::open(path).unwrap(), None).unwrap().map(|batch| batch.unwrap()).collect()
+    }
+
+    #[test]
+    fn test_rotates_by_rows_and_on_new_dictionary_values() {
+        let dir = tempdir().unwrap();
+        let options = WriterOptions { arrow_rotate_rows: 3, ..WriterOptions::default() };
+        let mut writer = ArrowEventWriter::new(dir.path(), "2025-01-01", 0, options).unwrap();
+        writer.write_batch(&batch((0..4).map(|i| row("DATA", "LTE", i)).collect())).unwrap();
+        writer.flush_pending().unwrap();
+        // A RAT the open file's dictionary does not have
+        writer.write_batch(&batch(vec![row("DATA", "NR", 10)])).unwrap();
+        writer.flush_pending().unwrap();
+        writer.write_batch(&batch(vec![row("DATA", "", 11)])).unwrap();
+        writer.close().unwrap();
+
+        let day_dir = dir.path().join("2025-01-01");
+        let manifest: ShardManifest =
+            serde_json::from_str(&std::fs::read_to_string(day_dir.join("manifest_writer000.json")).unwrap()).unwrap();
+        let records: Vec<u64> = manifest.files.iter().map(|entry| entry.records).collect();
+        assert_eq!(records, vec![3, 1, 2]);
+        assert_eq!(manifest.files[0].file, "cdr_2025-01-01_writer000_part001.arrow");
+        assert_eq!(writer.files_written(), 3);
+
+        // The third file: one record batch per flush
+        let batches = read(&day_dir.join(&manifest.files[2].file));
+        assert_eq!(batches.len(), 2);
+        let rat = batches[1].column_by_name("rat").unwrap().as_dictionary::<Int32Type>();
+        assert_eq!(rat.values().as_string::<i32>().iter().flatten().collect::<Vec<_>>(), vec!["LTE", "NR"]);
+        // Empty strings and the none markers are null
+        assert!(rat.is_null(0));
+        assert!(batches[1].column_by_name("imeisv").unwrap().is_null(0));
+        let start = batches[1].column_by_name("start_ts_ms").unwrap();
+        assert_eq!(start.as_primitive::<arrow_array::types::Int64Type>().value(0), 11);
+    }
+}
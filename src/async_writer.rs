@@ -1,103 +1,525 @@
 // Async batched writer for CDR events using Tokio
+#[cfg(feature = "arrow")]
+use crate::arrow_writer::ArrowEventWriter;
 use crate::compression::CompressionType;
-use crate::writer::{EventRow, EventWriter};
+use crate::config::OutputFormat;
+use crate::realtime::{RealtimeReplay, ReplayClock};
+use crate::writer::{row_serializer, serialize_row, EventRow, EventWriter, Rotation, WriterOptions};
 use anyhow::Result;
-use crossbeam_channel::Receiver;
+use crossbeam_channel::{Receiver, Sender};
+use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{error, info};
 
-/// Batch of EventRow objects ready to be written
+/// Estimated CSV bytes of a row, for sizing batches that keep rows
+pub const ESTIMATED_ROW_BYTES: usize = 230;
+
+/// Rows a batch of `batch_size_bytes` holds, at `ESTIMATED_ROW_BYTES` a row
+pub fn batch_capacity(batch_size_bytes: usize) -> usize {
+    batch_size_bytes / ESTIMATED_ROW_BYTES
+}
+
+/// Batch of EventRow objects ready to be written.
+/// A batch from `EventBatch::serialized` encodes rows to CSV as they are pushed and keeps only the bytes,
+/// so it knows its exact size and the writer task only compresses. A plain batch keeps the rows
+/// for record sinks and the `serialize_in_workers: false` comparison path.
 pub struct EventBatch {
     pub events: Vec<EventRow>,
-    pub estimated_size: usize,
+    // Some in serialized mode; boxed so WriterMessage stays small on the channel
+    serializer: Option<Box<csv::Writer<Vec<u8>>>>,
+    // Serialized mode only: the columns and MSISDN format of the writer's part files
+    options: WriterOptions,
+    rows: usize,
+    estimated_size: usize,
+    // `batch_max_age_ms`: the batch is due once its first row is this old
+    max_age: Option<Duration>,
+    // When the first row was pushed; only tracked with `max_age`
+    opened_at: Option<Instant>,
+    // Age when `into_message` handed the batch to the writer channel
+    sent_age: Option<Duration>,
 }
 
 impl EventBatch {
     pub fn new(capacity: usize) -> Self {
         EventBatch {
             events: Vec::with_capacity(capacity),
+            serializer: None,
+            options: WriterOptions::default(),
+            rows: 0,
+            estimated_size: 0,
+            max_age: None,
+            opened_at: None,
+            sent_age: None,
+        }
+    }
+
+    /// Batch that serializes rows at push time instead of keeping them
+    pub fn serialized(capacity: usize) -> Self {
+        EventBatch {
+            events: Vec::new(),
+            serializer: Some(Box::new(row_serializer(Vec::with_capacity(capacity * 256)))),
+            options: WriterOptions::default(),
+            rows: 0,
             estimated_size: 0,
+            max_age: None,
+            opened_at: None,
+            sent_age: None,
+        }
+    }
+
+    /// Plain batch holding `events`
+    pub fn from_rows(events: Vec<EventRow>) -> Self {
+        EventBatch {
+            rows: events.len(),
+            estimated_size: events.len() * ESTIMATED_ROW_BYTES,
+            events,
+            ..EventBatch::new(0)
+        }
+    }
+
+    /// Serialized unless `serialize_in_workers` is off, the part files are Arrow, which are built from rows,
+    /// or the writers replay rows in real time, which sorts them by start time first
+    pub fn for_config(cfg: &crate::config::Config, capacity: usize) -> Self {
+        let batch = if cfg.serialize_in_workers && cfg.output_format == OutputFormat::Csv && !cfg.realtime {
+            EventBatch {
+                options: WriterOptions::from_config(cfg),
+                ..EventBatch::serialized(capacity)
+            }
+        } else {
+            EventBatch::new(capacity)
+        };
+        EventBatch { max_age: (cfg.batch_max_age_ms > 0).then(|| Duration::from_millis(cfg.batch_max_age_ms)), ..batch }
+    }
+
+    pub fn push(&mut self, event: &EventRow) {
+        match self.serializer.as_mut() {
+            Some(serializer) => {
+                // Serializing plain numbers and static strings into a Vec cannot fail
+                serialize_row(serializer, event, &self.options).expect("EventRow serializes to CSV");
+                serializer.flush().expect("flush into Vec");
+            }
+            None => {
+                self.estimated_size += ESTIMATED_ROW_BYTES;
+                self.events.push(event.clone());
+            }
         }
+        if self.rows == 0 && self.max_age.is_some() {
+            self.opened_at = Some(Instant::now());
+        }
+        self.rows += 1;
     }
 
-    pub fn push(&mut self, event: EventRow) {
-        self.estimated_size += 230; // Estimated row size
-        self.events.push(event);
+    /// Size of the batch in bytes: exact when serialized, estimated otherwise
+    pub fn size_bytes(&self) -> usize {
+        match self.serializer.as_ref() {
+            Some(serializer) => serializer.get_ref().len(),
+            None => self.estimated_size,
+        }
     }
 
     pub fn is_full(&self, max_size: usize) -> bool {
-        self.estimated_size >= max_size
+        self.size_bytes() >= max_size
+    }
+
+    /// Time since the first row was pushed; None while empty or without `batch_max_age_ms`
+    pub fn age(&self) -> Option<Duration> {
+        self.opened_at.map(|opened_at| opened_at.elapsed())
+    }
+
+    /// Age the batch had when it was handed to the writer channel; None like `age`
+    pub fn sent_age(&self) -> Option<Duration> {
+        self.sent_age
+    }
+
+    /// Ready to send: `max_size` reached, or the first row older than `batch_max_age_ms`
+    pub fn is_due(&self, max_size: usize) -> bool {
+        self.is_full(max_size) || self.max_age.is_some_and(|max_age| self.age().is_some_and(|age| age >= max_age))
     }
 
     pub fn clear(&mut self) {
         self.events.clear();
+        self.rows = 0;
         self.estimated_size = 0;
+        self.opened_at = None;
+        if let Some(serializer) = self.serializer.as_mut() {
+            // Reuse the byte buffer; csv::Writer has no way to truncate its target in place
+            let mut buf = std::mem::replace(&mut **serializer, row_serializer(Vec::new()))
+                .into_inner()
+                .unwrap_or_default();
+            buf.clear();
+            **serializer = row_serializer(buf);
+        }
     }
 
     pub fn len(&self) -> usize {
-        self.events.len()
+        self.rows
     }
 
     pub fn is_empty(&self) -> bool {
-        self.events.is_empty()
+        self.rows == 0
+    }
+
+    /// Message for the writer channel: the bytes of a serialized batch, or the batch itself
+    pub fn into_message(self) -> WriterMessage {
+        match self.serializer {
+            Some(serializer) => WriterMessage::Serialized(SerializedBatch {
+                bytes: serializer.into_inner().unwrap_or_default(),
+                rows: self.rows,
+            }),
+            None => WriterMessage::Batch(EventBatch { sent_age: self.age(), ..self }),
+        }
+    }
+}
+
+/// CSV lines (no header) encoded by a worker; the writer adds the header and only compresses
+pub struct SerializedBatch {
+    pub bytes: Vec<u8>,
+    pub rows: usize,
+}
+
+impl SerializedBatch {
+    /// One line per row, each ending in `\n`
+    pub fn lines(&self) -> impl Iterator<Item = &[u8]> + '_ {
+        self.bytes.split_inclusive(|&b| b == b'\n')
     }
 }
 
 /// Message types for async writer communication
 pub enum WriterMessage {
     Batch(EventBatch),
+    Serialized(SerializedBatch),
     Close,
 }
 
-/// Async writer task that processes batches of events
+/// Sending half of a writer channel shared by workers.
+/// Once any writer has failed (`stop` is set) sends are refused so workers stop generating.
+#[derive(Clone)]
+pub struct BatchSender {
+    tx: Sender<WriterMessage>,
+    stop: Arc<AtomicBool>,
+}
+
+impl BatchSender {
+    pub fn new(tx: Sender<WriterMessage>, stop: Arc<AtomicBool>) -> Self {
+        BatchSender { tx, stop }
+    }
+
+    pub fn send(&self, msg: WriterMessage) -> Result<()> {
+        if self.stop.load(Ordering::Relaxed) {
+            anyhow::bail!("Writer failed, stopping generation");
+        }
+        self.tx
+            .send(msg)
+            .map_err(|_| anyhow::anyhow!("Writer channel closed, stopping generation"))
+    }
+}
+
+impl From<Sender<WriterMessage>> for BatchSender {
+    fn from(tx: Sender<WriterMessage>) -> Self {
+        BatchSender::new(tx, Arc::default())
+    }
+}
+
+/// What one writer task did during a day, for the throughput report
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WriterMetrics {
+    pub writer: usize,
+    pub events: usize,
+    // CSV bytes before compression
+    pub uncompressed_bytes: u64,
+    // Part file sizes on disk
+    pub compressed_bytes: u64,
+    // Time spent writing batches and finishing files
+    pub busy_sec: f64,
+    // Time spent waiting on the channel for the next batch
+    pub idle_sec: f64,
+    // Most batches queued on the channel at once
+    pub peak_queue_depth: usize,
+}
+
+/// Seconds since `mark`, moving `mark` to now; always 0 when timing is off
+fn lap(mark: &mut Option<Instant>) -> f64 {
+    let Some(last) = mark else {
+        return 0.0;
+    };
+    let now = Instant::now();
+    let elapsed = now.duration_since(*last).as_secs_f64();
+    *last = now;
+    elapsed
+}
+
+/// Async writer task that processes batches of events (`async` feature)
+/// `writer_id` names its part files (`writerNNN`); `worker_shards` are the workers sending to it
+/// With `replay` set the rows are held until Close and then written in start time order at its pace
 /// OPTIMIZATION #5: Reuse EventWriter across batches instead of creating new files
+#[cfg(feature = "async")]
+#[allow(clippy::too_many_arguments)]
 pub async fn writer_task(
     rx: Receiver<WriterMessage>,
     out_dir: PathBuf,
     day_str: String,
-    shard_id: usize,
-    rotate_bytes: u64,
+    writer_id: usize,
+    worker_shards: Vec<usize>,
+    rotation: Rotation,
     compression_type: CompressionType,
-) -> Result<()> {
+    compression_level: Option<i32>,
+    writer_options: WriterOptions,
+    stop: Arc<AtomicBool>,
+    metrics: bool,
+    replay: Option<ReplayClock>,
+) -> Result<WriterMetrics> {
     // Run in spawn_blocking since we're doing sync I/O with persistent writer
     tokio::task::spawn_blocking(move || {
-        writer_task_blocking(rx, out_dir, day_str, shard_id, rotate_bytes, compression_type)
+        writer_task_blocking(
+            rx,
+            out_dir,
+            day_str,
+            writer_id,
+            worker_shards,
+            rotation,
+            compression_type,
+            compression_level,
+            writer_options,
+            stop,
+            metrics,
+            replay,
+        )
     })
     .await?
 }
 
+/// Runs writer tasks on a tokio runtime with the `async` feature, on plain threads without it
+pub struct WriterRuntime {
+    #[cfg(feature = "async")]
+    rt: tokio::runtime::Runtime,
+}
+
+/// A writer task started by `WriterRuntime::spawn`
+pub struct WriterHandle {
+    #[cfg(feature = "async")]
+    inner: tokio::task::JoinHandle<Result<WriterMetrics>>,
+    #[cfg(not(feature = "async"))]
+    inner: std::thread::JoinHandle<Result<WriterMetrics>>,
+}
+
+impl WriterRuntime {
+    pub fn new() -> Result<Self> {
+        Ok(WriterRuntime {
+            #[cfg(feature = "async")]
+            rt: tokio::runtime::Runtime::new()?,
+        })
+    }
+
+    /// Start a writer task; same arguments as `writer_task`
+    #[allow(clippy::too_many_arguments)]
+    pub fn spawn(
+        &self,
+        rx: Receiver<WriterMessage>,
+        out_dir: PathBuf,
+        day_str: String,
+        writer_id: usize,
+        worker_shards: Vec<usize>,
+        rotation: Rotation,
+        compression_type: CompressionType,
+        compression_level: Option<i32>,
+        writer_options: WriterOptions,
+        stop: Arc<AtomicBool>,
+        metrics: bool,
+        replay: Option<ReplayClock>,
+    ) -> WriterHandle {
+        let task = move || {
+            writer_task_blocking(
+                rx,
+                out_dir,
+                day_str,
+                writer_id,
+                worker_shards,
+                rotation,
+                compression_type,
+                compression_level,
+                writer_options,
+                stop,
+                metrics,
+                replay,
+            )
+        };
+        #[cfg(feature = "async")]
+        let inner = self.rt.spawn_blocking(task);
+        #[cfg(not(feature = "async"))]
+        let inner = std::thread::spawn(task);
+        WriterHandle { inner }
+    }
+
+    /// Wait for a writer task and return its metrics or error
+    pub fn join(&self, handle: WriterHandle) -> Result<WriterMetrics> {
+        #[cfg(feature = "async")]
+        let result = self.rt.block_on(handle.inner)?;
+        #[cfg(not(feature = "async"))]
+        let result = handle.inner.join().map_err(|_| anyhow::anyhow!("Writer thread panicked"))?;
+        result
+    }
+}
+
+/// Part file writer of a writer task, by `output_format`
+enum PartWriter {
+    Csv(Box<EventWriter>),
+    #[cfg(feature = "arrow")]
+    Arrow(Box<ArrowEventWriter>),
+}
+
+impl PartWriter {
+    fn write_batch(&mut self, batch: &EventBatch) -> Result<()> {
+        match self {
+            PartWriter::Csv(writer) => writer.write_batch(batch),
+            #[cfg(feature = "arrow")]
+            PartWriter::Arrow(writer) => writer.write_batch(batch),
+        }
+    }
+
+    fn write_serialized(&mut self, batch: &SerializedBatch) -> Result<()> {
+        match self {
+            PartWriter::Csv(writer) => writer.write_serialized(batch),
+            #[cfg(feature = "arrow")]
+            PartWriter::Arrow(writer) => writer.write_serialized(batch),
+        }
+    }
+
+    fn close(&mut self) -> Result<()> {
+        match self {
+            PartWriter::Csv(writer) => writer.close(),
+            #[cfg(feature = "arrow")]
+            PartWriter::Arrow(writer) => writer.close(),
+        }
+    }
+
+    fn abort(&mut self) {
+        match self {
+            PartWriter::Csv(writer) => writer.abort(),
+            #[cfg(feature = "arrow")]
+            PartWriter::Arrow(writer) => writer.abort(),
+        }
+    }
+
+    fn files_written(&self) -> u32 {
+        match self {
+            PartWriter::Csv(writer) => writer.files_written(),
+            #[cfg(feature = "arrow")]
+            PartWriter::Arrow(writer) => writer.files_written(),
+        }
+    }
+
+    fn bytes_written(&self) -> u64 {
+        match self {
+            PartWriter::Csv(writer) => writer.bytes_written(),
+            #[cfg(feature = "arrow")]
+            PartWriter::Arrow(writer) => writer.bytes_written(),
+        }
+    }
+
+    fn uncompressed_bytes(&self) -> u64 {
+        match self {
+            PartWriter::Csv(writer) => writer.uncompressed_bytes(),
+            #[cfg(feature = "arrow")]
+            PartWriter::Arrow(writer) => writer.uncompressed_bytes(),
+        }
+    }
+}
+
 /// Blocking writer task that reuses EventWriter for all batches (OPTIMIZATION #5)
+/// With `metrics` set it also times recv (idle) against write (busy) and tracks queue depth
+#[allow(clippy::too_many_arguments)]
 fn writer_task_blocking(
     rx: Receiver<WriterMessage>,
     out_dir: PathBuf,
     day_str: String,
-    shard_id: usize,
-    rotate_bytes: u64,
+    writer_id: usize,
+    worker_shards: Vec<usize>,
+    rotation: Rotation,
     compression_type: CompressionType,
-) -> Result<()> {
+    compression_level: Option<i32>,
+    writer_options: WriterOptions,
+    stop: Arc<AtomicBool>,
+    metrics: bool,
+    replay: Option<ReplayClock>,
+) -> Result<WriterMetrics> {
     // Create EventWriter once and reuse it for all batches (OPTIMIZATION #5)
-    let mut writer = EventWriter::new(&out_dir, &day_str, rotate_bytes, shard_id, compression_type)?;
+    let mut writer = match writer_options.output_format {
+        OutputFormat::Csv => {
+            let mut writer = EventWriter::new(
+                &out_dir,
+                &day_str,
+                rotation.default.bytes,
+                writer_id,
+                compression_type,
+                compression_level,
+                writer_options,
+            )?;
+            writer.set_worker_shards(worker_shards);
+            writer.set_rotation(rotation);
+            PartWriter::Csv(Box::new(writer))
+        }
+        #[cfg(feature = "arrow")]
+        OutputFormat::Arrow => {
+            let mut writer = ArrowEventWriter::new(&out_dir, &day_str, writer_id, writer_options)?;
+            writer.set_worker_shards(worker_shards);
+            PartWriter::Arrow(Box::new(writer))
+        }
+        #[cfg(not(feature = "arrow"))]
+        OutputFormat::Arrow => anyhow::bail!("output_format arrow requires building with `--features arrow`"),
+    };
 
     let mut total_written = 0usize;
+    let mut stats = WriterMetrics {
+        writer: writer_id,
+        ..WriterMetrics::default()
+    };
+    let mut mark = metrics.then(Instant::now);
 
     // Process batches from channel
-    loop {
-        let msg = match rx.recv() {
-            Ok(msg) => msg,
-            Err(_) => break, // Channel closed
-        };
-
+    // Loop ends when the channel is closed
+    let mut replay = replay.map(RealtimeReplay::new);
+    let mut result = Ok(());
+    while let Ok(msg) = rx.recv() {
+        if mark.is_some() {
+            stats.idle_sec += lap(&mut mark);
+            // Count the batch just taken off the queue too
+            stats.peak_queue_depth = stats.peak_queue_depth.max(rx.len() + 1);
+        }
         match msg {
             WriterMessage::Batch(batch) => {
                 if batch.is_empty() {
                     continue;
                 }
+                if let Some(replay) = replay.as_mut() {
+                    replay.push(batch);
+                    stats.busy_sec += lap(&mut mark);
+                    continue;
+                }
 
                 // Write all events in batch using persistent writer (OPTIMIZATION #5)
-                for event in &batch.events {
-                    writer.write_row(event)?;
+                result = writer.write_batch(&batch);
+                if result.is_err() {
+                    break;
                 }
 
                 total_written += batch.len();
+                stats.busy_sec += lap(&mut mark);
+            }
+            WriterMessage::Serialized(batch) => {
+                if replay.is_some() {
+                    result = Err(anyhow::anyhow!("Real-time replay needs rows, not pre-serialized batches"));
+                    break;
+                }
+                result = writer.write_serialized(&batch);
+                if result.is_err() {
+                    break;
+                }
+
+                total_written += batch.rows;
+                stats.busy_sec += lap(&mut mark);
             }
             WriterMessage::Close => {
                 break;
@@ -105,15 +527,48 @@ fn writer_task_blocking(
         }
     }
 
+    // Workers are done: write the held rows in start time order, sleeping between them
+    if let (true, Some(replay)) = (result.is_ok(), replay.take()) {
+        info!(writer = writer_id, events = replay.len(), "Replaying rows in real time");
+        result = replay.replay(|batch| {
+            stats.idle_sec += lap(&mut mark);
+            writer.write_batch(&batch)?;
+            total_written += batch.len();
+            stats.busy_sec += lap(&mut mark);
+            Ok(())
+        });
+    }
+
     // Close writer (flushes and finishes compression)
-    writer.close()?;
+    if result.is_ok() {
+        result = writer.close();
+        stats.busy_sec += lap(&mut mark);
+    }
+    if let Err(e) = result {
+        // Stop the workers, then drain what they already queued so nothing waits on this shard
+        stop.store(true, Ordering::Relaxed);
+        error!(writer = writer_id, "Writer failed: {:#}", e);
+        while let Ok(msg) = rx.recv() {
+            if let WriterMessage::Close = msg {
+                break;
+            }
+        }
+        writer.abort();
+        return Err(e);
+    }
 
-    println!(
-        "Writer task for shard {} completed: {} events written",
-        shard_id, total_written
+    info!(
+        writer = writer_id,
+        events = total_written,
+        files = writer.files_written(),
+        bytes = writer.bytes_written(),
+        "Writer task completed"
     );
 
-    Ok(())
+    stats.events = total_written;
+    stats.uncompressed_bytes = writer.uncompressed_bytes();
+    stats.compressed_bytes = writer.bytes_written();
+    Ok(stats)
 }
 
 
@@ -128,22 +583,72 @@ mod tests {
         assert!(batch.is_empty());
 
         let event = EventRow::default();
-        batch.push(event);
+        batch.push(&event);
         assert_eq!(batch.len(), 1);
         assert!(!batch.is_empty());
-        assert_eq!(batch.estimated_size, 230);
+        assert_eq!(batch.size_bytes(), 230);
+    }
+
+    #[test]
+    fn test_serialized_batch() {
+        let mut batch = EventBatch::serialized(100);
+        let event = EventRow {
+            event_type: "DATA",
+            data_bytes_in: Some(12_345_678_901),
+            ..EventRow::default()
+        };
+        batch.push(&event);
+        batch.push(&EventRow::default());
+        assert_eq!(batch.len(), 2);
+        assert!(batch.events.is_empty());
+        let size = batch.size_bytes();
+
+        // Exact serialized length, one line per row
+        let WriterMessage::Serialized(serialized) = batch.into_message() else {
+            panic!("expected a serialized batch");
+        };
+        let lines: Vec<&[u8]> = serialized.lines().collect();
+        assert_eq!(serialized.rows, 2);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with(b"DATA;"));
+        assert!(lines.iter().all(|line| line.ends_with(b"\n")));
+        assert_eq!(size, serialized.bytes.len());
     }
 
     #[test]
     fn test_batch_full() {
-        let mut batch = EventBatch::new(10);
-        let max_size = 1000;
+        for mut batch in [EventBatch::new(10), EventBatch::serialized(10)] {
+            let max_size = 1000;
+
+            // Add events until full
+            while !batch.is_full(max_size) {
+                batch.push(&EventRow::default());
+            }
 
-        // Add events until full
-        for _ in 0..5 {
-            batch.push(EventRow::default());
+            assert!(batch.size_bytes() >= max_size);
+            batch.clear();
+            assert!(batch.is_empty());
+            assert_eq!(batch.size_bytes(), 0);
         }
+    }
+
+    #[test]
+    fn test_batch_due_by_age() {
+        let cfg = crate::config::Config { batch_max_age_ms: 20, ..crate::config::Config::default() };
+        let mut batch = EventBatch::for_config(&cfg, 10);
+        assert!(!batch.is_due(1 << 20) && batch.age().is_none());
+        batch.push(&EventRow::default());
+        assert!(!batch.is_due(1 << 20));
+        std::thread::sleep(Duration::from_millis(25));
+        // Due by age long before it is full; clearing starts the clock over
+        assert!(batch.is_due(1 << 20));
+        batch.clear();
+        assert!(!batch.is_due(1 << 20) && batch.age().is_none());
 
-        assert!(batch.is_full(max_size));
+        // Off by default: an old batch waits until it is full
+        let mut batch = EventBatch::for_config(&crate::config::Config::default(), 10);
+        batch.push(&EventRow::default());
+        std::thread::sleep(Duration::from_millis(25));
+        assert!(!batch.is_due(1 << 20) && batch.age().is_none());
     }
 }
@@ -1,12 +1,15 @@
 // Cell tower (base station) generation and management
 use csv::{Writer, Reader};
+use rand::distributions::{Distribution, WeightedIndex};
 use rand::Rng;
 use rand::rngs::StdRng;
 use rand::SeedableRng;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::f64::consts::PI;
 use std::path::{Path, PathBuf};
+use anyhow::Context;
+use tracing::{info, warn};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Cell {
@@ -16,6 +19,60 @@ pub struct Cell {
     pub rat: String,
 }
 
+/// Radio access technologies a cell or data session can use
+pub const KNOWN_RATS: [&str; 4] = ["GSM", "WCDMA", "LTE", "NR"];
+
+/// Default RAT weights: 3G 30%, 4G 50%, 5G 20%
+pub fn default_rat_mix() -> BTreeMap<String, f64> {
+    BTreeMap::from([
+        ("WCDMA".to_string(), 0.3),
+        ("LTE".to_string(), 0.5),
+        ("NR".to_string(), 0.2),
+    ])
+}
+
+/// RAT sampler shared by cell generation and DATA sessions so both follow the same mix
+#[derive(Debug, Clone)]
+pub struct RatMix {
+    rats: Vec<&'static str>,
+    dist: WeightedIndex<f64>,
+}
+
+impl RatMix {
+    /// Build from a RAT -> weight map; RATs must be in `KNOWN_RATS` and weights must not all be zero.
+    /// RATs weighted zero are dropped
+    pub fn new(mix: &BTreeMap<String, f64>) -> anyhow::Result<Self> {
+        let mut rats = Vec::with_capacity(mix.len());
+        let mut weights = Vec::with_capacity(mix.len());
+        for (rat, &weight) in mix {
+            let known = KNOWN_RATS
+                .iter()
+                .find(|known| known.eq_ignore_ascii_case(rat))
+                .ok_or_else(|| anyhow::anyhow!("Unknown RAT in rat_mix: {} (expected one of {:?})", rat, KNOWN_RATS))?;
+            if !(weight >= 0.0 && weight.is_finite()) {
+                anyhow::bail!("Invalid rat_mix weight for {}: {}", rat, weight);
+            }
+            if weight == 0.0 {
+                continue;
+            }
+            rats.push(*known);
+            weights.push(weight);
+        }
+        let dist = WeightedIndex::new(&weights)
+            .map_err(|e| anyhow::anyhow!("Invalid rat_mix {:?}: {}", mix, e))?;
+        Ok(RatMix { rats, dist })
+    }
+
+    /// RATs that can be sampled
+    pub fn rats(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.rats.iter().copied()
+    }
+
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> &'static str {
+        self.rats[self.dist.sample(rng)]
+    }
+}
+
 fn deg_per_km_lat() -> f64 {
     1.0 / 111.0
 }
@@ -24,114 +81,291 @@ fn deg_per_km_lon(lat_deg: f64) -> f64 {
     1.0 / (111.320 * lat_deg.to_radians().cos()).max(1e-9)
 }
 
-/// Generate cell towers distributed in a circular area
+/// Placement scheme of the cells in a catalog, recorded in cells.meta.json.
+/// 1: one RNG stream over all cells (positions depend on the cell count); 2: one stream per `(seed, cell_id)`
+pub const CELLS_SCHEME_VERSION: u32 = 2;
+
+/// Catalogs fingerprinted before the scheme was recorded
+fn legacy_cells_scheme() -> u32 {
+    1
+}
+
+/// RNG of one cell, independent of how many cells the catalog holds
+fn cell_rng(seed: u64, cell_id: u32) -> StdRng {
+    // SplitMix64 finalizer over the combined key
+    let mut z = seed ^ (cell_id as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    StdRng::seed_from_u64(z ^ (z >> 31))
+}
+
+/// Generate one cell tower in a circular area, drawn from its own `(seed, cell_id)` stream
 /// Uses sqrt for uniform distribution in circle
+pub fn generate_cell(cell_id: u32, center_lat: f64, center_lon: f64, radius_km: f64, seed: u64, rat_mix: &RatMix) -> Cell {
+    let mut rng = cell_rng(seed, cell_id);
+
+    // Uniform distribution in circle: sqrt for radius
+    let r = radius_km * rng.gen::<f64>().sqrt();
+    let theta = rng.gen::<f64>() * 2.0 * PI;
+
+    let lat = center_lat + r * theta.sin() * deg_per_km_lat();
+    let lon = center_lon + r * theta.cos() * deg_per_km_lon(center_lat);
+
+    // Weighted random choice for RAT
+    let rat = rat_mix.sample(&mut rng);
+
+    Cell {
+        cell_id,
+        lat: (lat * 1_000_000.0).round() / 1_000_000.0,
+        lon: (lon * 1_000_000.0).round() / 1_000_000.0,
+        rat: rat.to_string(),
+    }
+}
+
+/// Generate cell towers 1..=n_cells distributed in a circular area.
+/// Cell N is the same whatever `n_cells` is, so a larger catalog extends a smaller one. The exception is a
+/// catalog small enough to miss a weighted RAT by chance: see `cover_rats`
 pub fn generate_cells(
     n_cells: usize,
     center_lat: f64,
     center_lon: f64,
     radius_km: f64,
     seed: u64,
+    rat_mix: &RatMix,
 ) -> Vec<Cell> {
-    let mut rng = StdRng::seed_from_u64(seed);
-    let rats = vec!["WCDMA", "LTE", "NR"];
-    let rat_weights = [0.3, 0.5, 0.2]; // 3G, 4G, 5G distribution
-
-    let lat_step = deg_per_km_lat();
-    let lon_step = deg_per_km_lon(center_lat);
-
-    let mut cells = Vec::with_capacity(n_cells);
-
-    for cid in 1..=n_cells {
-        // Uniform distribution in circle: sqrt for radius
-        let r = radius_km * rng.gen::<f64>().sqrt();
-        let theta = rng.gen::<f64>() * 2.0 * PI;
-
-        let dlat = r * theta.sin() * lat_step;
-        let dlon = r * theta.cos() * lon_step;
-
-        let lat = center_lat + dlat;
-        let lon = center_lon + dlon;
-
-        // Weighted random choice for RAT
-        let rat_choice = rng.gen::<f64>();
-        let rat = if rat_choice < rat_weights[0] {
-            rats[0]
-        } else if rat_choice < rat_weights[0] + rat_weights[1] {
-            rats[1]
-        } else {
-            rats[2]
+    let mut cells: Vec<Cell> = (1..=n_cells as u32)
+        .map(|cid| generate_cell(cid, center_lat, center_lon, radius_km, seed, rat_mix))
+        .collect();
+    cover_rats(&mut cells, rat_mix);
+    cells
+}
+
+/// Give every RAT of `rat_mix` at least one cell, since DATA sessions of that RAT need one: each missing RAT
+/// takes over the highest-numbered cell of the most common RAT. Needs at least as many cells as weighted RATs
+/// (`config::validate_rat_mix`); catalogs that already hold every RAT are left as drawn
+fn cover_rats(cells: &mut [Cell], rat_mix: &RatMix) {
+    for rat in rat_mix.rats() {
+        if cells.iter().any(|cell| cell.rat == rat) {
+            continue;
+        }
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for cell in cells.iter() {
+            *counts.entry(cell.rat.as_str()).or_default() += 1;
+        }
+        // Ties go to the first RAT by name, so the catalog stays deterministic
+        let Some((common, count)) = counts.into_iter().max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(a.0))) else {
+            return;
         };
+        if count < 2 {
+            return;
+        }
+        let common = common.to_string();
+        if let Some(cell) = cells.iter_mut().rev().find(|cell| cell.rat == common) {
+            cell.rat = rat.to_string();
+        }
+    }
+}
 
-        cells.push(Cell {
-            cell_id: cid as u32,
-            lat: (lat * 1_000_000.0).round() / 1_000_000.0,
-            lon: (lon * 1_000_000.0).round() / 1_000_000.0,
-            rat: rat.to_string(),
-        });
+/// Parameters a cells.csv was generated with, stored next to it as cells.meta.json
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CellsMeta {
+    pub cells: usize,
+    pub center_lat: f64,
+    pub center_lon: f64,
+    pub radius_km: f64,
+    pub seed: u64,
+    // Catalogs from before rat_mix was configurable used the default mix
+    #[serde(default = "default_rat_mix")]
+    pub rat_mix: BTreeMap<String, f64>,
+    /// `CELLS_SCHEME_VERSION` the catalog was generated with
+    #[serde(default = "legacy_cells_scheme")]
+    pub scheme: u32,
+}
+
+impl CellsMeta {
+    /// Catalog parameters taken from the config
+    pub fn from_config(cfg: &crate::config::Config) -> Self {
+        CellsMeta {
+            cells: cfg.cells,
+            center_lat: cfg.center_lat,
+            center_lon: cfg.center_lon,
+            radius_km: cfg.radius_km,
+            seed: cfg.seed,
+            rat_mix: cfg.rat_mix.clone(),
+            scheme: CELLS_SCHEME_VERSION,
+        }
     }
 
-    cells
+    /// Human-readable list of parameters that differ from `other`
+    fn differences(&self, other: &CellsMeta) -> Vec<String> {
+        let mut diffs = Vec::new();
+        if self.cells != other.cells {
+            diffs.push(format!("cells {} vs {}", self.cells, other.cells));
+        }
+        if self.center_lat != other.center_lat || self.center_lon != other.center_lon {
+            diffs.push(format!(
+                "center {},{} vs {},{}",
+                self.center_lat, self.center_lon, other.center_lat, other.center_lon
+            ));
+        }
+        if self.radius_km != other.radius_km {
+            diffs.push(format!("radius_km {} vs {}", self.radius_km, other.radius_km));
+        }
+        if self.seed != other.seed {
+            diffs.push(format!("seed {} vs {}", self.seed, other.seed));
+        }
+        if self.rat_mix != other.rat_mix {
+            diffs.push(format!("rat_mix {:?} vs {:?}", self.rat_mix, other.rat_mix));
+        }
+        if self.scheme != other.scheme {
+            diffs.push(format!("scheme v{} vs v{}", self.scheme, other.scheme));
+        }
+        diffs
+    }
 }
 
-/// Create cells.csv if it doesn't exist, return path
-pub fn ensure_cells_catalog(
-    out_dir: &Path,
-    n_cells: usize,
-    center_lat: f64,
-    center_lon: f64,
-    radius_km: f64,
-    seed: u64,
-) -> anyhow::Result<PathBuf> {
+/// Create cells.csv unless one generated with the same parameters exists, return path.
+/// An existing catalog with different parameters (per cells.meta.json) is an error unless `regen` is set
+pub fn ensure_cells_catalog(out_dir: &Path, meta: &CellsMeta, regen: bool) -> anyhow::Result<PathBuf> {
     std::fs::create_dir_all(out_dir)?;
     let cells_path = out_dir.join("cells.csv");
+    let meta_path = out_dir.join("cells.meta.json");
+    let rat_mix = RatMix::new(&meta.rat_mix)?;
 
-    if !cells_path.exists() {
-        let cells = generate_cells(n_cells, center_lat, center_lon, radius_km, seed);
-        let mut wtr = Writer::from_path(&cells_path)?;
-
-        wtr.write_record(&["cell_id", "lat", "lon", "rat"])?;
-        for c in cells {
-            wtr.write_record(&[
-                c.cell_id.to_string(),
-                c.lat.to_string(),
-                c.lon.to_string(),
-                c.rat,
-            ])?;
+    if cells_path.exists() && !regen {
+        if !meta_path.exists() {
+            // Catalog from before fingerprinting: nothing to compare against
+            warn!("{:?} has no cells.meta.json; reusing it unchecked (use --regen-cells to rebuild)", cells_path);
+            return Ok(cells_path);
+        }
+        let existing: CellsMeta = serde_json::from_str(&std::fs::read_to_string(&meta_path)?)
+            .with_context(|| format!("Failed to parse {:?}", meta_path))?;
+        let diffs = existing.differences(meta);
+        if diffs.is_empty() {
+            return Ok(cells_path);
         }
-        wtr.flush()?;
+        anyhow::bail!(
+            "Cells catalog {:?} was generated with different parameters ({}); use --regen-cells to rebuild it or choose another output directory",
+            cells_path,
+            diffs.join(", ")
+        );
     }
 
+    if regen && cells_path.exists() {
+        info!("Regenerating cells catalog {:?}", cells_path);
+    }
+
+    let cells = generate_cells(meta.cells, meta.center_lat, meta.center_lon, meta.radius_km, meta.seed, &rat_mix);
+    let mut wtr = Writer::from_path(&cells_path)?;
+
+    wtr.write_record(["cell_id", "lat", "lon", "rat"])?;
+    write_cells(&mut wtr, cells)?;
+    std::fs::write(&meta_path, serde_json::to_string_pretty(meta)?)?;
+
     Ok(cells_path)
 }
 
+fn write_cells<W: std::io::Write>(wtr: &mut Writer<W>, cells: Vec<Cell>) -> anyhow::Result<()> {
+    for c in cells {
+        wtr.write_record([
+            c.cell_id.to_string(),
+            c.lat.to_string(),
+            c.lon.to_string(),
+            c.rat,
+        ])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Append `extra` cells to the catalog in `out_dir`, leaving the existing ones untouched.
+/// The catalog must have been generated with the parameters of `meta` (its cell count aside)
+/// under the current scheme; returns the updated fingerprint
+pub fn extend_cells_catalog(out_dir: &Path, meta: &CellsMeta, extra: usize) -> anyhow::Result<CellsMeta> {
+    let cells_path = out_dir.join("cells.csv");
+    let meta_path = out_dir.join("cells.meta.json");
+    if !cells_path.exists() || !meta_path.exists() {
+        anyhow::bail!("No cells catalog with cells.meta.json to extend in {:?}", out_dir);
+    }
+    let existing: CellsMeta = serde_json::from_str(&std::fs::read_to_string(&meta_path)?)
+        .with_context(|| format!("Failed to parse {:?}", meta_path))?;
+    if existing.scheme != CELLS_SCHEME_VERSION {
+        anyhow::bail!(
+            "Cells catalog {:?} uses placement scheme v{} and cannot be extended; rebuild it with --regen-cells",
+            cells_path,
+            existing.scheme
+        );
+    }
+    let diffs = existing.differences(&CellsMeta { cells: existing.cells, ..meta.clone() });
+    if !diffs.is_empty() {
+        anyhow::bail!(
+            "Cells catalog {:?} was generated with different parameters ({}); it can only be extended with the same ones",
+            cells_path,
+            diffs.join(", ")
+        );
+    }
+
+    let rat_mix = RatMix::new(&existing.rat_mix)?;
+    let first = existing.cells as u32 + 1;
+    let cells: Vec<Cell> = (first..first + extra as u32)
+        .map(|cid| generate_cell(cid, existing.center_lat, existing.center_lon, existing.radius_km, existing.seed, &rat_mix))
+        .collect();
+    let file = std::fs::OpenOptions::new().append(true).open(&cells_path)?;
+    write_cells(&mut csv::WriterBuilder::new().has_headers(false).from_writer(file), cells)?;
+
+    let extended = CellsMeta { cells: existing.cells + extra, ..existing };
+    std::fs::write(&meta_path, serde_json::to_string_pretty(&extended)?)?;
+    info!("Extended cells catalog {:?} with {} cells (now {})", cells_path, extra, extended.cells);
+    Ok(extended)
+}
+
+/// All cell IDs plus a RAT -> cell IDs index
+pub type CellsCatalog = (Vec<u32>, HashMap<String, Vec<u32>>);
+
 /// Load cells catalog and return:
 /// - List of all cell IDs
 /// - HashMap mapping RAT -> list of cell IDs
-pub fn load_cells_catalog(cells_path: &Path) -> anyhow::Result<(Vec<u32>, HashMap<String, Vec<u32>>)> {
-    let mut cells = Vec::new();
+pub fn load_cells_catalog(cells_path: &Path) -> anyhow::Result<CellsCatalog> {
+    let mut rdr = Reader::from_path(cells_path)?;
+    let cells = rdr.deserialize().collect::<Result<Vec<Cell>, _>>()?;
+    Ok(cells_catalog(&cells))
+}
+
+/// Build the catalog from cells already in memory
+pub fn cells_catalog(cells: &[Cell]) -> CellsCatalog {
+    let mut ids = Vec::with_capacity(cells.len());
     let mut by_rat: HashMap<String, Vec<u32>> = HashMap::new();
 
-    let mut rdr = Reader::from_path(cells_path)?;
-    for result in rdr.deserialize() {
-        let cell: Cell = result?;
-        cells.push(cell.cell_id);
+    for cell in cells {
+        ids.push(cell.cell_id);
         by_rat
             .entry(cell.rat.clone())
-            .or_insert_with(Vec::new)
+            .or_default()
             .push(cell.cell_id);
     }
 
-    Ok((cells, by_rat))
+    (ids, by_rat)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn meta(cells: usize, center_lat: f64, center_lon: f64, radius_km: f64, seed: u64) -> CellsMeta {
+        CellsMeta {
+            cells,
+            center_lat,
+            center_lon,
+            radius_km,
+            seed,
+            rat_mix: default_rat_mix(),
+            scheme: CELLS_SCHEME_VERSION,
+        }
+    }
+
     #[test]
     fn test_generate_cells() {
-        let cells = generate_cells(100, 52.37, 4.895, 50.0, 42);
+        let cells = generate_cells(100, 52.37, 4.895, 50.0, 42, &RatMix::new(&default_rat_mix()).unwrap());
         assert_eq!(cells.len(), 100);
 
         for cell in &cells {
@@ -147,11 +381,153 @@ mod tests {
         use tempfile::tempdir;
 
         let dir = tempdir().unwrap();
-        let path = ensure_cells_catalog(dir.path(), 50, 52.37, 4.895, 10.0, 123).unwrap();
+        let path = ensure_cells_catalog(dir.path(), &meta(50, 52.37, 4.895, 10.0, 123), false).unwrap();
         assert!(path.exists());
 
         let (cells, by_rat) = load_cells_catalog(&path).unwrap();
         assert_eq!(cells.len(), 50);
         assert!(!by_rat.is_empty());
     }
+
+    #[test]
+    fn test_cells_meta_match_reuses_catalog() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = ensure_cells_catalog(dir.path(), &meta(50, 52.37, 4.895, 10.0, 123), false).unwrap();
+        let stored: CellsMeta = serde_json::from_str(&std::fs::read_to_string(dir.path().join("cells.meta.json")).unwrap()).unwrap();
+        assert_eq!(stored.cells, 50);
+        assert_eq!(stored.seed, 123);
+
+        // Same parameters: the existing file is reused, not rewritten
+        std::fs::write(&path, "cell_id,lat,lon,rat\n1,52.37,4.895,LTE\n").unwrap();
+        ensure_cells_catalog(dir.path(), &meta(50, 52.37, 4.895, 10.0, 123), false).unwrap();
+        let (cells, _) = load_cells_catalog(&path).unwrap();
+        assert_eq!(cells, vec![1]);
+    }
+
+    #[test]
+    fn test_cells_meta_mismatch_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        ensure_cells_catalog(dir.path(), &meta(50, 52.37, 4.895, 10.0, 123), false).unwrap();
+
+        // Moscow instead of Amsterdam
+        let err = ensure_cells_catalog(dir.path(), &meta(50, 55.75, 37.62, 10.0, 123), false)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("center 52.37,4.895 vs 55.75,37.62"), "{}", err);
+        assert!(err.contains("--regen-cells"), "{}", err);
+
+        let err = ensure_cells_catalog(dir.path(), &meta(80, 52.37, 4.895, 10.0, 7), false)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("cells 50 vs 80") && err.contains("seed 123 vs 7"), "{}", err);
+    }
+
+    #[test]
+    fn test_cells_forced_regeneration() {
+        let dir = tempfile::tempdir().unwrap();
+        ensure_cells_catalog(dir.path(), &meta(50, 52.37, 4.895, 10.0, 123), false).unwrap();
+
+        let path = ensure_cells_catalog(dir.path(), &meta(80, 55.75, 37.62, 10.0, 123), true).unwrap();
+        let (cells, _) = load_cells_catalog(&path).unwrap();
+        assert_eq!(cells.len(), 80);
+        let mut rdr = Reader::from_path(&path).unwrap();
+        for cell in rdr.deserialize::<Cell>() {
+            let cell = cell.unwrap();
+            assert!(cell.lat > 55.0 && cell.lat < 56.5);
+        }
+
+        // The new fingerprint is now the reference
+        ensure_cells_catalog(dir.path(), &meta(80, 55.75, 37.62, 10.0, 123), false).unwrap();
+        assert!(ensure_cells_catalog(dir.path(), &meta(50, 52.37, 4.895, 10.0, 123), false).is_err());
+    }
+
+    #[test]
+    fn test_rat_mix_drives_cells() {
+        let mix = BTreeMap::from([("LTE".to_string(), 3.0), ("nr".to_string(), 1.0), ("GSM".to_string(), 0.0)]);
+        let cells = generate_cells(4000, 52.37, 4.895, 10.0, 1, &RatMix::new(&mix).unwrap());
+        let lte = cells.iter().filter(|c| c.rat == "LTE").count() as f64 / cells.len() as f64;
+        assert!((lte - 0.75).abs() < 0.03, "LTE share {}", lte);
+        assert!(cells.iter().all(|c| c.rat == "LTE" || c.rat == "NR"));
+    }
+
+    #[test]
+    fn test_small_catalog_has_every_weighted_rat() {
+        let mix = RatMix::new(&BTreeMap::from([("LTE".to_string(), 0.9), ("NR".to_string(), 0.05), ("WCDMA".to_string(), 0.05)]))
+            .unwrap();
+        for seed in 0..50 {
+            for n_cells in 3..8 {
+                let cells = generate_cells(n_cells, 52.37, 4.895, 10.0, seed, &mix);
+                for rat in mix.rats() {
+                    assert!(cells.iter().any(|c| c.rat == rat), "seed {}, {} cells: no {} cell", seed, n_cells, rat);
+                }
+            }
+        }
+        // Big enough to hold every RAT by chance: exactly the cells drawn one by one
+        let cells = generate_cells(500, 52.37, 4.895, 10.0, 1, &mix);
+        assert!(cells.iter().all(|c| c.rat == generate_cell(c.cell_id, 52.37, 4.895, 10.0, 1, &mix).rat));
+    }
+
+    #[test]
+    fn test_rat_mix_rejects_bad_input() {
+        assert!(RatMix::new(&BTreeMap::from([("6G".to_string(), 1.0)])).is_err());
+        assert!(RatMix::new(&BTreeMap::from([("LTE".to_string(), 0.0)])).is_err());
+        assert!(RatMix::new(&BTreeMap::from([("LTE".to_string(), -1.0)])).is_err());
+        assert!(RatMix::new(&BTreeMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_rat_mix_change_is_a_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        ensure_cells_catalog(dir.path(), &meta(50, 52.37, 4.895, 10.0, 123), false).unwrap();
+
+        let mut other = meta(50, 52.37, 4.895, 10.0, 123);
+        other.rat_mix = BTreeMap::from([("LTE".to_string(), 1.0)]);
+        let err = ensure_cells_catalog(dir.path(), &other, false).unwrap_err().to_string();
+        assert!(err.contains("rat_mix"), "{}", err);
+    }
+
+    #[test]
+    fn test_cells_stable_across_catalog_sizes() {
+        let small = tempfile::tempdir().unwrap();
+        let large = tempfile::tempdir().unwrap();
+        let small_path = ensure_cells_catalog(small.path(), &meta(1000, 52.37, 4.895, 50.0, 42), false).unwrap();
+        let large_path = ensure_cells_catalog(large.path(), &meta(1500, 52.37, 4.895, 50.0, 42), false).unwrap();
+
+        let small_csv = std::fs::read_to_string(small_path).unwrap();
+        let large_csv = std::fs::read_to_string(large_path).unwrap();
+        assert_eq!(small_csv.lines().count(), 1001);
+        assert!(large_csv.starts_with(&small_csv));
+    }
+
+    #[test]
+    fn test_extend_cells_catalog() {
+        let dir = tempfile::tempdir().unwrap();
+        let full = tempfile::tempdir().unwrap();
+        let path = ensure_cells_catalog(dir.path(), &meta(1000, 52.37, 4.895, 50.0, 42), false).unwrap();
+
+        let extended = extend_cells_catalog(dir.path(), &meta(1000, 52.37, 4.895, 50.0, 42), 500).unwrap();
+        assert_eq!(extended.cells, 1500);
+        // Same file as generating 1500 cells at once, and the new fingerprint matches it
+        let full_path = ensure_cells_catalog(full.path(), &meta(1500, 52.37, 4.895, 50.0, 42), false).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), std::fs::read(full_path).unwrap());
+        ensure_cells_catalog(dir.path(), &meta(1500, 52.37, 4.895, 50.0, 42), false).unwrap();
+
+        let err = extend_cells_catalog(dir.path(), &meta(1500, 52.37, 4.895, 50.0, 7), 10).unwrap_err().to_string();
+        assert!(err.contains("seed 42 vs 7"), "{}", err);
+    }
+
+    #[test]
+    fn test_legacy_scheme_is_a_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        ensure_cells_catalog(dir.path(), &meta(50, 52.37, 4.895, 10.0, 123), false).unwrap();
+        // Fingerprint written before the scheme was recorded
+        let meta_path = dir.path().join("cells.meta.json");
+        let mut legacy: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&meta_path).unwrap()).unwrap();
+        legacy.as_object_mut().unwrap().remove("scheme");
+        std::fs::write(&meta_path, legacy.to_string()).unwrap();
+
+        let err = ensure_cells_catalog(dir.path(), &meta(50, 52.37, 4.895, 10.0, 123), false).unwrap_err().to_string();
+        assert!(err.contains("scheme v1 vs v2"), "{}", err);
+        assert!(extend_cells_catalog(dir.path(), &meta(50, 52.37, 4.895, 10.0, 123), 10).is_err());
+    }
 }
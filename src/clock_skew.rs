@@ -0,0 +1,311 @@
+// Network elements writing the records: the MSC/SGSN/PGW element of each row (`node_id`) and its clock, which can be off
+// and drift, for mediation alignment testing
+use crate::config::{ClockSkewConfig, NodeClockSkew};
+use crate::generators::{intern, RecordTypes};
+use crate::writer::EventRow;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rand_distr::{Distribution, Normal};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Pool of network elements writing a kind of record
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodePool {
+    /// Voice and SMS records: the MSC, with the SMSC behind it for SMS
+    Msc,
+    /// SGSN-closed DATA sessions
+    Sgsn,
+    /// PGW-closed DATA sessions
+    Pgw,
+}
+
+impl NodePool {
+    pub const ALL: [NodePool; 3] = [NodePool::Msc, NodePool::Sgsn, NodePool::Pgw];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NodePool::Msc => "msc",
+            NodePool::Sgsn => "sgsn",
+            NodePool::Pgw => "pgw",
+        }
+    }
+
+    /// Pool that wrote `row`, by event type and, for DATA, the record type of the closing node
+    pub fn of(row: &EventRow, record_types: &RecordTypes) -> Self {
+        match row.event_type {
+            "DATA" if row.record_type == record_types.data_pgw => NodePool::Pgw,
+            "DATA" => NodePool::Sgsn,
+            _ => NodePool::Msc,
+        }
+    }
+
+    fn settings(self, cfg: &ClockSkewConfig) -> &NodeClockSkew {
+        match self {
+            NodePool::Msc => &cfg.msc,
+            NodePool::Sgsn => &cfg.sgsn,
+            NodePool::Pgw => &cfg.pgw,
+        }
+    }
+}
+
+/// Element of `nodes` the subscriber with `imsi` is registered on, spread evenly by a hash of the IMSI.
+/// Every row names the subscriber it serves by IMSI, so the MO and MT legs of a call are written by the elements
+/// of the two parties and differ by the relative skew of those clocks
+pub fn node_of(imsi: u64, nodes: u32) -> u32 {
+    (imsi.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 32) as u32 % nodes.max(1)
+}
+
+/// `node_id` of element `node` (from 0) of `pool`: "msc01", "sgsn02", ...
+pub fn node_name(pool: NodePool, node: u32) -> String {
+    format!("{}{:02}", pool.as_str(), node + 1)
+}
+
+/// Ground-truth line for one skewed row: the written row's key fields and its true times
+#[derive(Debug, Serialize)]
+struct SkewRecord<'a> {
+    event_type: &'a str,
+    direction: &'a str,
+    msisdn_src: u64,
+    record_type: &'a str,
+    pool: NodePool,
+    node: &'a str,
+    start_ts_ms: i64,
+    true_start_ts_ms: i64,
+    true_end_ts_ms: i64,
+    skew_ms: i64,
+}
+
+/// Clocks of every network element on one day, the same in every worker
+pub struct ClockSkew {
+    cfg: ClockSkewConfig,
+    record_types: RecordTypes,
+    day_start_ms: i64,
+    // Offset of each element, by pool in `NodePool::ALL` order
+    offsets_ms: [Vec<f64>; 3],
+    // `node_id` of each element, by pool like `offsets_ms`; empty unless `emit_node_id`
+    node_names: [Vec<&'static str>; 3],
+    log_path: Option<PathBuf>,
+    log: Option<BufWriter<File>>,
+}
+
+impl ClockSkew {
+    /// No skew and no `node_id`: rows are left as they are
+    pub fn off() -> Self {
+        ClockSkew {
+            cfg: ClockSkewConfig::default(),
+            record_types: RecordTypes::default(),
+            day_start_ms: 0,
+            offsets_ms: Default::default(),
+            node_names: Default::default(),
+            log_path: None,
+            log: None,
+        }
+    }
+
+    /// Element offsets drawn from the day's seed, so all workers see the same clocks; drift runs from `day_start_ms`.
+    /// With `emit_node_id` every row is stamped with its element, skewed or not.
+    /// `day_dir` is where `clock_skew_shardNNN.jsonl` goes when `ground_truth` is on
+    pub fn new(
+        cfg: &ClockSkewConfig,
+        emit_node_id: bool,
+        seed: u64,
+        day_start_ms: i64,
+        record_types: RecordTypes,
+        shard_id: usize,
+        day_dir: &Path,
+    ) -> anyhow::Result<Self> {
+        if !cfg.is_enabled() && !emit_node_id {
+            return Ok(ClockSkew::off());
+        }
+        let mut rng = StdRng::seed_from_u64(seed ^ 0xC10C_5CE3 ^ day_start_ms as u64);
+        let mut offsets_ms: [Vec<f64>; 3] = Default::default();
+        let mut node_names: [Vec<&'static str>; 3] = Default::default();
+        for ((offsets, names), pool) in offsets_ms.iter_mut().zip(&mut node_names).zip(NodePool::ALL) {
+            let settings = pool.settings(cfg);
+            let normal = Normal::new(settings.mean_ms, settings.sigma_ms).map_err(|e| {
+                anyhow::anyhow!("Config key clock_skew.{}: invalid sigma_ms {}: {}", pool.as_str(), settings.sigma_ms, e)
+            })?;
+            *offsets = (0..settings.nodes.max(1)).map(|_| normal.sample(&mut rng)).collect();
+            if emit_node_id {
+                *names = (0..settings.nodes.max(1)).map(|node| intern(&node_name(pool, node))).collect();
+            }
+        }
+
+        Ok(ClockSkew {
+            cfg: *cfg,
+            record_types,
+            day_start_ms,
+            offsets_ms,
+            node_names,
+            log_path: cfg.ground_truth.then(|| day_dir.join(format!("clock_skew_shard{:03}.jsonl", shard_id))),
+            log: None,
+        })
+    }
+
+    /// `row` as its element writes it: with the element's `node_id` when emitted, and timestamps as its clock stamps them.
+    /// None when neither changes the row. Start and end move together, by the offset plus the drift at the true start
+    pub fn apply(&mut self, row: &EventRow) -> anyhow::Result<Option<EventRow>> {
+        let emit_node_id = !self.node_names[0].is_empty();
+        if !self.cfg.is_enabled() && !emit_node_id {
+            return Ok(None);
+        }
+        let pool = NodePool::of(row, &self.record_types);
+        let settings = pool.settings(&self.cfg);
+        let offsets = &self.offsets_ms[pool as usize];
+        let node = node_of(row.imsi, offsets.len() as u32);
+        let skew_ms = if settings.is_enabled() {
+            let hours = (row.start_ts_ms - self.day_start_ms) as f64 / 3_600_000.0;
+            (offsets[node as usize] + settings.drift_ms_per_hour * hours).round() as i64
+        } else {
+            0
+        };
+        if skew_ms == 0 && !emit_node_id {
+            return Ok(None);
+        }
+
+        let written = EventRow {
+            start_ts_ms: row.start_ts_ms + skew_ms,
+            end_ts_ms: row.end_ts_ms + skew_ms,
+            node_id: self.node_names[pool as usize].get(node as usize).copied().unwrap_or(row.node_id),
+            ..row.clone()
+        };
+        if skew_ms != 0 {
+            self.log_skew(&written, row, pool, node, skew_ms)?;
+        }
+        Ok(Some(written))
+    }
+
+    fn log_skew(&mut self, skewed: &EventRow, row: &EventRow, pool: NodePool, node: u32, skew_ms: i64) -> anyhow::Result<()> {
+        let Some(path) = self.log_path.as_ref() else {
+            return Ok(());
+        };
+        if self.log.is_none() {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            self.log = Some(BufWriter::new(File::create(path)?));
+        }
+        let record = SkewRecord {
+            event_type: skewed.event_type,
+            direction: skewed.direction,
+            msisdn_src: skewed.msisdn_src,
+            record_type: skewed.record_type,
+            pool,
+            node: &node_name(pool, node),
+            start_ts_ms: skewed.start_ts_ms,
+            true_start_ts_ms: row.start_ts_ms,
+            true_end_ts_ms: row.end_ts_ms,
+            skew_ms,
+        };
+        if let Some(log) = self.log.as_mut() {
+            serde_json::to_writer(&mut *log, &record)?;
+            log.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    /// Flush the ground-truth log
+    pub fn finish(&mut self) -> anyhow::Result<()> {
+        if let Some(mut log) = self.log.take() {
+            log.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const DAY_START_MS: i64 = 1_735_689_600_000;
+
+    fn row(event_type: &'static str, record_type: &'static str, hour: i64) -> EventRow {
+        EventRow {
+            event_type,
+            record_type,
+            direction: "MO",
+            msisdn_src: 31_612_000_001,
+            start_ts_ms: DAY_START_MS + hour * 3_600_000,
+            end_ts_ms: DAY_START_MS + hour * 3_600_000 + 60_000,
+            ..EventRow::default()
+        }
+    }
+
+    #[test]
+    fn test_zero_skew_keeps_rows() {
+        let dir = tempdir().unwrap();
+        let cfg = ClockSkewConfig::default();
+        let mut skew = ClockSkew::new(&cfg, false, 1, DAY_START_MS, RecordTypes::default(), 0, dir.path()).unwrap();
+        assert!(skew.apply(&row("CALL", "mscVoiceRecord", 0)).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_skew_by_pool_with_drift_and_ground_truth() {
+        let dir = tempdir().unwrap();
+        let cfg = ClockSkewConfig {
+            msc: NodeClockSkew { mean_ms: 2_000.0, sigma_ms: 0.0, drift_ms_per_hour: 100.0, nodes: 2 },
+            pgw: NodeClockSkew { mean_ms: -500.0, sigma_ms: 50.0, drift_ms_per_hour: 0.0, nodes: 4 },
+            ground_truth: true,
+            ..ClockSkewConfig::default()
+        };
+        let mut skew = ClockSkew::new(&cfg, false, 1, DAY_START_MS, RecordTypes::default(), 3, dir.path()).unwrap();
+
+        // MSC: fixed offset plus drift, duration kept
+        let call = row("CALL", "mscVoiceRecord", 5);
+        let skewed = skew.apply(&call).unwrap().unwrap();
+        assert_eq!(skewed.start_ts_ms - call.start_ts_ms, 2_500);
+        assert_eq!(skewed.end_ts_ms - skewed.start_ts_ms, 60_000);
+        // SGSN has no skew configured
+        assert!(skew.apply(&row("DATA", "sgsnPDPRecord", 5)).unwrap().is_none());
+        // PGW elements each have their own offset around the mean
+        let pgw = skew.apply(&row("DATA", "pgwRecord", 5)).unwrap().unwrap();
+        let offset = pgw.start_ts_ms - (DAY_START_MS + 5 * 3_600_000);
+        assert!((-800..=-200).contains(&offset), "{}", offset);
+        skew.finish().unwrap();
+
+        let log = std::fs::read_to_string(dir.path().join("clock_skew_shard003.jsonl")).unwrap();
+        let lines: Vec<serde_json::Value> = log.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["pool"], "msc");
+        assert_eq!(lines[0]["true_start_ts_ms"], call.start_ts_ms);
+        assert_eq!(lines[0]["skew_ms"], 2_500);
+
+        // Every worker draws the same clocks for the day
+        let mut other = ClockSkew::new(&cfg, false, 1, DAY_START_MS, RecordTypes::default(), 0, dir.path()).unwrap();
+        assert_eq!(other.apply(&row("DATA", "pgwRecord", 5)).unwrap().unwrap().start_ts_ms, pgw.start_ts_ms);
+    }
+
+    #[test]
+    fn test_node_id_by_pool_and_imsi() {
+        let dir = tempdir().unwrap();
+        let cfg = ClockSkewConfig {
+            msc: NodeClockSkew { nodes: 3, ..NodeClockSkew::default() },
+            ..ClockSkewConfig::default()
+        };
+        let mut skew = ClockSkew::new(&cfg, true, 1, DAY_START_MS, RecordTypes::default(), 0, dir.path()).unwrap();
+        let stamp = |skew: &mut ClockSkew, event_type: &'static str, record_type: &'static str, imsi: u64| {
+            let row = EventRow { imsi, ..row(event_type, record_type, 5) };
+            let written = skew.apply(&row).unwrap().unwrap();
+            // Without skew only the element is added
+            assert_eq!(written.start_ts_ms, row.start_ts_ms);
+            written.node_id
+        };
+
+        // SMS is written by the subscriber's MSC, like its calls
+        for imsi in 204_080_000_000_001..204_080_000_000_050 {
+            let call = stamp(&mut skew, "CALL", "mscVoiceRecord", imsi);
+            assert!(["msc01", "msc02", "msc03"].contains(&call), "{}", call);
+            assert_eq!(stamp(&mut skew, "SMS", "sgsnSMORecord", imsi), call);
+        }
+        assert!(stamp(&mut skew, "DATA", "sgsnPDPRecord", 1).starts_with("sgsn"));
+        assert!(stamp(&mut skew, "DATA", "pgwRecord", 1).starts_with("pgw"));
+        // Not emitted without the flag and no skew
+        let mut off = ClockSkew::new(&cfg, false, 1, DAY_START_MS, RecordTypes::default(), 0, dir.path()).unwrap();
+        assert!(off.apply(&row("CALL", "mscVoiceRecord", 5)).unwrap().is_none());
+    }
+}
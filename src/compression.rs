@@ -4,33 +4,104 @@ use std::fs::File;
 use std::io::BufWriter;
 use flate2::write::GzEncoder;
 use flate2::Compression as GzCompression;
+#[cfg(feature = "zstd")]
 use zstd::stream::write::Encoder as ZstdEncoder;
 
 /// Compression type enum for configuration
+/// Zstd, Bzip2 and Xz are only usable when built with the `zstd` / `bzip2` / `xz` cargo features
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CompressionType {
     Gzip,
     Zstd,
+    Bzip2,
+    Xz,
     None,
 }
 
 impl CompressionType {
+    /// Every codec, including the ones not compiled into this build
+    pub const ALL: [CompressionType; 5] = [
+        CompressionType::Gzip,
+        CompressionType::Zstd,
+        CompressionType::Bzip2,
+        CompressionType::Xz,
+        CompressionType::None,
+    ];
+
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s.to_lowercase().as_str() {
             "gzip" | "gz" => Some(CompressionType::Gzip),
             "zstd" | "zst" => Some(CompressionType::Zstd),
+            "bzip2" | "bz2" => Some(CompressionType::Bzip2),
+            "xz" => Some(CompressionType::Xz),
             "none" | "uncompressed" => Some(CompressionType::None),
             _ => None,
         }
     }
 
+    /// Name as `compression_type` takes it
+    pub fn name(&self) -> &'static str {
+        match self {
+            CompressionType::Gzip => "gzip",
+            CompressionType::Zstd => "zstd",
+            CompressionType::Bzip2 => "bzip2",
+            CompressionType::Xz => "xz",
+            CompressionType::None => "none",
+        }
+    }
+
     pub fn extension(&self) -> &'static str {
         match self {
             CompressionType::Gzip => ".gz",
             CompressionType::Zstd => ".zst",
+            CompressionType::Bzip2 => ".bz2",
+            CompressionType::Xz => ".xz",
             CompressionType::None => "",
         }
     }
+
+    /// Cargo feature gating the codec, if any
+    fn feature(&self) -> Option<&'static str> {
+        match self {
+            CompressionType::Zstd => Some("zstd"),
+            CompressionType::Bzip2 => Some("bzip2"),
+            CompressionType::Xz => Some("xz"),
+            _ => None,
+        }
+    }
+
+    /// Whether this build can write the codec
+    pub fn is_available(&self) -> bool {
+        match self {
+            CompressionType::Zstd => cfg!(feature = "zstd"),
+            CompressionType::Bzip2 => cfg!(feature = "bzip2"),
+            CompressionType::Xz => cfg!(feature = "xz"),
+            CompressionType::Gzip | CompressionType::None => true,
+        }
+    }
+
+    /// Error out early for a codec whose cargo feature is off
+    pub fn ensure_available(&self) -> anyhow::Result<()> {
+        match self.feature() {
+            Some(feature) if !self.is_available() => {
+                anyhow::bail!("{} compression requires building with `--features {}`", feature, feature)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Clamp a requested compression level into the range supported by the codec
+    /// None selects the codec default (gzip 6, zstd 3, bzip2 6, xz 6)
+    pub fn effective_level(&self, level: Option<i32>) -> i32 {
+        match self {
+            CompressionType::Gzip => level.unwrap_or(6).clamp(0, 9),
+            CompressionType::Zstd => level.unwrap_or(3).clamp(1, 22),
+            CompressionType::Bzip2 => level.unwrap_or(6).clamp(1, 9),
+            CompressionType::Xz => level.unwrap_or(6).clamp(0, 9),
+            CompressionType::None => 0,
+        }
+    }
 }
 
 /// Trait for compressed writers that can be used with CSV writer
@@ -41,41 +112,101 @@ pub trait CompressedWriter: Write + Send {
 }
 
 /// Gzip compression writer (single-threaded, compatible with existing code)
+/// With `member_flush_bytes` set the file is written as concatenated gzip members,
+/// each holding that many uncompressed bytes, so multi-member aware readers can split it.
 pub struct GzipWriter {
-    encoder: GzEncoder<BufWriter<File>>,
+    // Always Some outside of a member restart
+    encoder: Option<GzEncoder<BufWriter<File>>>,
+    level: u32,
+    member_flush_bytes: u64,
+    member_bytes: u64,
+    members: usize,
 }
 
 impl GzipWriter {
     pub fn new(file: File, buffer_size: usize) -> io::Result<Self> {
+        Self::with_level(file, buffer_size, GzCompression::default().level())
+    }
+
+    /// Create a gzip writer with an explicit compression level (0-9)
+    pub fn with_level(file: File, buffer_size: usize, level: u32) -> io::Result<Self> {
+        Self::with_member_flush(file, buffer_size, level, 0)
+    }
+
+    /// Create a gzip writer that starts a new member every `member_flush_bytes` uncompressed bytes (0 = single member)
+    pub fn with_member_flush(file: File, buffer_size: usize, level: u32, member_flush_bytes: u64) -> io::Result<Self> {
         let buffered = BufWriter::with_capacity(buffer_size, file);
-        let encoder = GzEncoder::new(buffered, GzCompression::default());
-        Ok(GzipWriter { encoder })
+        let encoder = GzEncoder::new(buffered, GzCompression::new(level));
+        Ok(GzipWriter {
+            encoder: Some(encoder),
+            level,
+            member_flush_bytes,
+            member_bytes: 0,
+            members: 1,
+        })
+    }
+
+    /// Number of gzip members started so far
+    pub fn members(&self) -> usize {
+        self.members
+    }
+
+    fn encoder(&mut self) -> &mut GzEncoder<BufWriter<File>> {
+        self.encoder.as_mut().expect("gzip encoder is only absent during a member restart")
+    }
+
+    /// Finish the current member and start a new one on the same file
+    fn restart_member(&mut self) -> io::Result<()> {
+        let encoder = self.encoder.take().expect("gzip encoder is only absent during a member restart");
+        let inner = encoder.finish()?;
+        self.encoder = Some(GzEncoder::new(inner, GzCompression::new(self.level)));
+        self.member_bytes = 0;
+        self.members += 1;
+        Ok(())
     }
 }
 
 impl Write for GzipWriter {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.encoder.write(buf)
+        if self.member_flush_bytes == 0 {
+            return self.encoder().write(buf);
+        }
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.member_bytes >= self.member_flush_bytes {
+            self.restart_member()?;
+        }
+
+        // Never let a single write cross a member boundary
+        let room = (self.member_flush_bytes - self.member_bytes) as usize;
+        let len = buf.len().min(room);
+        let written = self.encoder().write(&buf[..len])?;
+        self.member_bytes += written as u64;
+        Ok(written)
     }
 
     fn flush(&mut self) -> io::Result<()> {
-        self.encoder.flush()
+        self.encoder().flush()
     }
 }
 
 impl CompressedWriter for GzipWriter {
     fn finish_compression(&mut self) -> io::Result<()> {
-        self.encoder.flush()?;
-        self.encoder.try_finish()?;
+        let encoder = self.encoder();
+        encoder.flush()?;
+        encoder.try_finish()?;
         Ok(())
     }
 }
 
-/// Zstd compression writer with multi-threaded support
+/// Zstd compression writer with multi-threaded support (`zstd` feature)
+#[cfg(feature = "zstd")]
 pub struct ZstdWriter {
     encoder: ZstdEncoder<'static, BufWriter<File>>,
 }
 
+#[cfg(feature = "zstd")]
 impl ZstdWriter {
     /// Create a new Zstd writer with multi-threaded compression
     ///
@@ -89,30 +220,36 @@ impl ZstdWriter {
 
         // Create Zstd encoder with specified compression level
         let mut encoder = ZstdEncoder::new(buffered, compression_level)
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            .map_err(io::Error::other)?;
 
         // Enable multi-threaded compression
         if num_threads > 0 {
             encoder.multithread(num_threads)
-                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                .map_err(io::Error::other)?;
         }
 
         // Set long-distance matching for better compression on large files
         encoder.long_distance_matching(true)
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            .map_err(io::Error::other)?;
 
         Ok(ZstdWriter { encoder })
     }
 
     /// Create with automatic settings (level 3, auto threads)
     pub fn new_auto(file: File) -> io::Result<Self> {
-        let num_threads = num_cpus::get() as u32;
         // Use level 3 which is roughly equivalent to gzip default in speed
+        Self::new_auto_with_level(file, 3)
+    }
+
+    /// Create with auto-detected threads and an explicit compression level
+    pub fn new_auto_with_level(file: File, compression_level: i32) -> io::Result<Self> {
+        let num_threads = crate::cores::available_cores() as u32;
         // Buffer size: 1MB for efficient multi-threaded compression
-        Self::new(file, 1024 * 1024, 3, num_threads)
+        Self::new(file, 1024 * 1024, compression_level, num_threads)
     }
 }
 
+#[cfg(feature = "zstd")]
 impl Write for ZstdWriter {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         self.encoder.write(buf)
@@ -123,15 +260,84 @@ impl Write for ZstdWriter {
     }
 }
 
+#[cfg(feature = "zstd")]
 impl CompressedWriter for ZstdWriter {
     fn finish_compression(&mut self) -> io::Result<()> {
         self.encoder.flush()?;
         self.encoder.do_finish()
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            .map_err(io::Error::other)?;
         Ok(())
     }
 }
 
+/// Bzip2 compression writer (`bzip2` feature)
+#[cfg(feature = "bzip2")]
+pub struct Bzip2Writer {
+    encoder: bzip2::write::BzEncoder<BufWriter<File>>,
+}
+
+#[cfg(feature = "bzip2")]
+impl Bzip2Writer {
+    pub fn new(file: File, buffer_size: usize, level: u32) -> io::Result<Self> {
+        let buffered = BufWriter::with_capacity(buffer_size, file);
+        let encoder = bzip2::write::BzEncoder::new(buffered, bzip2::Compression::new(level));
+        Ok(Bzip2Writer { encoder })
+    }
+}
+
+#[cfg(feature = "bzip2")]
+impl Write for Bzip2Writer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.encoder.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.encoder.flush()
+    }
+}
+
+#[cfg(feature = "bzip2")]
+impl CompressedWriter for Bzip2Writer {
+    fn finish_compression(&mut self) -> io::Result<()> {
+        self.encoder.try_finish()?;
+        self.encoder.get_mut().flush()
+    }
+}
+
+/// Xz (LZMA2) compression writer (`xz` feature)
+#[cfg(feature = "xz")]
+pub struct XzWriter {
+    encoder: xz2::write::XzEncoder<BufWriter<File>>,
+}
+
+#[cfg(feature = "xz")]
+impl XzWriter {
+    pub fn new(file: File, buffer_size: usize, level: u32) -> io::Result<Self> {
+        let buffered = BufWriter::with_capacity(buffer_size, file);
+        let encoder = xz2::write::XzEncoder::new(buffered, level);
+        Ok(XzWriter { encoder })
+    }
+}
+
+#[cfg(feature = "xz")]
+impl Write for XzWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.encoder.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.encoder.flush()
+    }
+}
+
+#[cfg(feature = "xz")]
+impl CompressedWriter for XzWriter {
+    fn finish_compression(&mut self) -> io::Result<()> {
+        self.encoder.try_finish()?;
+        self.encoder.get_mut().flush()
+    }
+}
+
 /// Uncompressed writer (pass-through)
 pub struct UncompressedWriter {
     writer: BufWriter<File>,
@@ -161,23 +367,231 @@ impl CompressedWriter for UncompressedWriter {
     }
 }
 
+/// Failure injection: passes writes through until `limit` bytes, then fails like a full disk
+pub struct FailingWriter {
+    inner: Box<dyn CompressedWriter>,
+    remaining: u64,
+}
+
+impl FailingWriter {
+    pub fn new(inner: Box<dyn CompressedWriter>, limit: u64) -> Self {
+        FailingWriter { inner, remaining: limit }
+    }
+}
+
+impl Write for FailingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.len() as u64 > self.remaining {
+            self.remaining = 0;
+            return Err(io::Error::new(io::ErrorKind::StorageFull, "No space left on device (injected)"));
+        }
+        let written = self.inner.write(buf)?;
+        self.remaining -= written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl CompressedWriter for FailingWriter {
+    fn finish_compression(&mut self) -> io::Result<()> {
+        self.inner.finish_compression()
+    }
+}
+
+/// Error for a codec whose cargo feature is off
+#[cfg(not(all(feature = "zstd", feature = "bzip2", feature = "xz")))]
+fn unsupported(feature: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, format!("{} compression needs the `{}` cargo feature", feature, feature))
+}
+
 /// Factory function to create the appropriate compressed writer
+/// `compression_level` of None selects the codec default; `gzip_member_flush_bytes` of 0 writes single-member gzip
 pub fn create_compressed_writer(
     file: File,
     compression_type: CompressionType,
+    compression_level: Option<i32>,
+    gzip_member_flush_bytes: u64,
 ) -> io::Result<Box<dyn CompressedWriter>> {
+    let level = compression_type.effective_level(compression_level);
     match compression_type {
         CompressionType::Gzip => {
-            let writer = GzipWriter::new(file, 256 * 1024)?;
+            let writer = GzipWriter::with_member_flush(file, 256 * 1024, level as u32, gzip_member_flush_bytes)?;
             Ok(Box::new(writer))
         }
+        #[cfg(feature = "zstd")]
         CompressionType::Zstd => {
-            let writer = ZstdWriter::new_auto(file)?;
+            let writer = ZstdWriter::new_auto_with_level(file, level)?;
+            Ok(Box::new(writer))
+        }
+        #[cfg(feature = "bzip2")]
+        CompressionType::Bzip2 => {
+            let writer = Bzip2Writer::new(file, 256 * 1024, level as u32)?;
+            Ok(Box::new(writer))
+        }
+        #[cfg(feature = "xz")]
+        CompressionType::Xz => {
+            let writer = XzWriter::new(file, 256 * 1024, level as u32)?;
             Ok(Box::new(writer))
         }
+        #[cfg(not(feature = "zstd"))]
+        CompressionType::Zstd => Err(unsupported("zstd")),
+        #[cfg(not(feature = "bzip2"))]
+        CompressionType::Bzip2 => Err(unsupported("bzip2")),
+        #[cfg(not(feature = "xz"))]
+        CompressionType::Xz => Err(unsupported("xz")),
         CompressionType::None => {
             let writer = UncompressedWriter::new(file, 256 * 1024)?;
             Ok(Box::new(writer))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(CompressionType::from_str("gzip"), Some(CompressionType::Gzip));
+        assert_eq!(CompressionType::from_str("ZSTD"), Some(CompressionType::Zstd));
+        assert_eq!(CompressionType::from_str("none"), Some(CompressionType::None));
+        assert_eq!(CompressionType::from_str("bz2"), Some(CompressionType::Bzip2));
+        assert_eq!(CompressionType::from_str("XZ"), Some(CompressionType::Xz));
+        assert_eq!(CompressionType::from_str("lz4"), None);
+    }
+
+    #[test]
+    fn test_effective_level() {
+        assert_eq!(CompressionType::Gzip.effective_level(None), 6);
+        assert_eq!(CompressionType::Gzip.effective_level(Some(42)), 9);
+        assert_eq!(CompressionType::Zstd.effective_level(None), 3);
+        assert_eq!(CompressionType::Zstd.effective_level(Some(0)), 1);
+        assert_eq!(CompressionType::Zstd.effective_level(Some(19)), 19);
+        assert_eq!(CompressionType::Bzip2.effective_level(Some(0)), 1);
+        assert_eq!(CompressionType::Xz.effective_level(None), 6);
+    }
+
+    #[test]
+    fn test_feature_gated_availability() {
+        assert_eq!(CompressionType::Zstd.is_available(), cfg!(feature = "zstd"));
+        assert_eq!(CompressionType::Bzip2.is_available(), cfg!(feature = "bzip2"));
+        assert_eq!(CompressionType::Xz.is_available(), cfg!(feature = "xz"));
+        assert!(CompressionType::Gzip.ensure_available().is_ok());
+        #[cfg(not(feature = "xz"))]
+        {
+            let dir = tempdir().unwrap();
+            let file = File::create(dir.path().join("x.xz")).unwrap();
+            let err = create_compressed_writer(file, CompressionType::Xz, Some(6), 0).err().unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+            assert!(CompressionType::Xz.ensure_available().is_err());
+        }
+    }
+
+    #[cfg(feature = "bzip2")]
+    #[test]
+    fn test_bzip2_roundtrip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.csv.bz2");
+        let mut writer = create_compressed_writer(File::create(&path).unwrap(), CompressionType::Bzip2, Some(9), 0).unwrap();
+        writer.write_all(b"a;b\n1;2\n").unwrap();
+        writer.finish_compression().unwrap();
+        drop(writer);
+
+        let mut out = String::new();
+        bzip2::read::BzDecoder::new(File::open(&path).unwrap()).read_to_string(&mut out).unwrap();
+        assert_eq!(out, "a;b\n1;2\n");
+    }
+
+    #[cfg(feature = "xz")]
+    #[test]
+    fn test_xz_roundtrip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.csv.xz");
+        let mut writer = create_compressed_writer(File::create(&path).unwrap(), CompressionType::Xz, Some(6), 0).unwrap();
+        writer.write_all(b"a;b\n1;2\n").unwrap();
+        writer.finish_compression().unwrap();
+        drop(writer);
+
+        let mut out = String::new();
+        xz2::read::XzDecoder::new(File::open(&path).unwrap()).read_to_string(&mut out).unwrap();
+        assert_eq!(out, "a;b\n1;2\n");
+    }
+
+    #[test]
+    fn test_gzip_with_level_roundtrip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("out.csv.gz");
+
+        let mut writer =
+            create_compressed_writer(File::create(&path).unwrap(), CompressionType::Gzip, Some(1), 0)
+                .unwrap();
+        writer.write_all(b"a;b\n1;2\n").unwrap();
+        writer.finish_compression().unwrap();
+        drop(writer);
+
+        let mut decoded = String::new();
+        flate2::read::GzDecoder::new(File::open(&path).unwrap())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, "a;b\n1;2\n");
+    }
+
+    /// Count gzip members by decoding them one at a time
+    fn count_members(data: &[u8]) -> usize {
+        let mut rest = data;
+        let mut members = 0;
+        while !rest.is_empty() {
+            let mut decoder = flate2::bufread::GzDecoder::new(rest);
+            io::copy(&mut decoder, &mut io::sink()).unwrap();
+            rest = decoder.into_inner();
+            members += 1;
+        }
+        members
+    }
+
+    #[test]
+    fn test_gzip_multi_member_roundtrip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("out.csv.gz");
+
+        let mut content = Vec::new();
+        for i in 0..20_000 {
+            content.extend_from_slice(format!("{};31600{:06};DATA\n", i, i).as_bytes());
+        }
+        let flush_bytes = 64 * 1024;
+
+        let mut writer = GzipWriter::with_member_flush(File::create(&path).unwrap(), 256 * 1024, 6, flush_bytes).unwrap();
+        for chunk in content.chunks(1000) {
+            writer.write_all(chunk).unwrap();
+        }
+        writer.finish_compression().unwrap();
+        let expected_members = content.len().div_ceil(flush_bytes as usize);
+        assert_eq!(writer.members(), expected_members);
+        drop(writer);
+
+        let data = std::fs::read(&path).unwrap();
+        assert_eq!(count_members(&data), expected_members);
+
+        let mut decoded = Vec::new();
+        flate2::read::MultiGzDecoder::new(&data[..]).read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, content);
+    }
+
+    #[test]
+    fn test_gzip_single_member_by_default() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("out.csv.gz");
+
+        let mut writer = create_compressed_writer(File::create(&path).unwrap(), CompressionType::Gzip, None, 0).unwrap();
+        writer.write_all(&vec![b'x'; 200_000]).unwrap();
+        writer.finish_compression().unwrap();
+        drop(writer);
+
+        assert_eq!(count_members(&std::fs::read(&path).unwrap()), 1);
+    }
+}
@@ -1,10 +1,232 @@
 // Configuration management for CDR generator
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use crate::cells::{default_rat_mix, RatMix};
+use crate::cores::MAX_CORES_ENV;
+use crate::event_pool::min_event_pool_size;
+use crate::identity::Mccmnc;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
 use std::path::{Path, PathBuf};
 
 pub const DEFAULT_TZ_NAME: &str = "Europe/Amsterdam";
 
+/// Clock that event placement follows: which midnights bound the day and which hour
+/// of the diurnal curve an instant falls in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventTimeBasis {
+    /// Local time of `tz_name`
+    Local,
+    /// UTC, whatever `tz_name` is
+    Utc,
+}
+
+impl EventTimeBasis {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "local" => Some(EventTimeBasis::Local),
+            "utc" => Some(EventTimeBasis::Utc),
+            _ => None,
+        }
+    }
+}
+
+/// Calendar day directories are named by, and whose midnights bound the generated day
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DayFolderBasis {
+    /// Local date of `tz_name`
+    #[default]
+    Local,
+    /// UTC date: the day runs 00:00-24:00 UTC, for lakes partitioned by UTC date
+    Utc,
+}
+
+impl DayFolderBasis {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "local" => Some(DayFolderBasis::Local),
+            "utc" => Some(DayFolderBasis::Utc),
+            _ => None,
+        }
+    }
+}
+
+/// How `msisdn_src` / `msisdn_dst` are written to part files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MsisdnFormat {
+    /// `+31612345678`
+    E164Plus,
+    /// `31612345678`
+    #[default]
+    E164,
+    /// `0612345678`: `country_code` replaced by the trunk prefix 0; numbers of other countries as `00` + E.164
+    National,
+}
+
+impl MsisdnFormat {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "e164_plus" => Some(MsisdnFormat::E164Plus),
+            "e164" => Some(MsisdnFormat::E164),
+            "national" => Some(MsisdnFormat::National),
+            _ => None,
+        }
+    }
+}
+
+/// Directory layout of a day's output under the output directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PartitionLayout {
+    /// `out/2025-01-01/`
+    #[default]
+    Flat,
+    /// `out/date=2025-01-01/`
+    HiveDate,
+    /// `out/date=2025-01-01/type=CALL/` for part files; manifests, stats and summary stay in the date directory
+    HiveDateType,
+}
+
+impl PartitionLayout {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "flat" => Some(PartitionLayout::Flat),
+            "hive_date" => Some(PartitionLayout::HiveDate),
+            "hive_date_type" => Some(PartitionLayout::HiveDateType),
+            _ => None,
+        }
+    }
+
+    /// Directory of a day (or day window, `2025-01-01_1300-1400`) under `out_dir`
+    pub fn day_dir(self, out_dir: &Path, day_label: &str) -> PathBuf {
+        match self {
+            PartitionLayout::Flat => out_dir.join(day_label),
+            PartitionLayout::HiveDate | PartitionLayout::HiveDateType => out_dir.join(format!("date={}", day_label)),
+        }
+    }
+
+    /// Directory of the part files of `event_type` (relative to the day directory), empty when they sit in it
+    pub fn part_subdir(self, event_type: Option<&str>) -> PathBuf {
+        match (self, event_type) {
+            (PartitionLayout::HiveDateType, Some(event_type)) => PathBuf::from(format!("type={}", event_type)),
+            _ => PathBuf::new(),
+        }
+    }
+
+    /// Part files are written per event type
+    pub fn splits_by_event_type(self) -> bool {
+        self == PartitionLayout::HiveDateType
+    }
+}
+
+/// File format of the part files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    /// `;`-separated CSV in the configured codec
+    #[default]
+    Csv,
+    /// Arrow IPC (feather v2) files with typed columns, for pandas/polars (`arrow` feature)
+    Arrow,
+}
+
+impl OutputFormat {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "csv" => Some(OutputFormat::Csv),
+            "arrow" => Some(OutputFormat::Arrow),
+            _ => None,
+        }
+    }
+}
+
+/// What a run does with a day directory that already holds part files or ground truth logs of an earlier run, with
+/// the late files it staged for the day, or, when bundling, with the day bundle it left in the output directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverwritePolicy {
+    /// Refuse to generate the day
+    #[default]
+    Fail,
+    /// Delete the earlier part files, manifests, shard stats, ground truth logs, summary, staged late files and day bundle first
+    Clean,
+    /// Keep the part files next to the new ones and bundle them all, naming in a warning those the new run writes
+    /// again under the same name; a bundle without parts is replaced and staged late files are kept, with warnings
+    Merge,
+}
+
+impl OverwritePolicy {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "fail" => Some(OverwritePolicy::Fail),
+            "clean" => Some(OverwritePolicy::Clean),
+            "merge" => Some(OverwritePolicy::Merge),
+            _ => None,
+        }
+    }
+}
+
+/// What the inline validator does with the rows it samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelfCheckMode {
+    /// No checks
+    #[default]
+    Off,
+    /// Log and count violations, keep generating
+    Warn,
+    /// Abort the run on the first violation
+    Strict,
+}
+
+impl SelfCheckMode {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "off" => Some(SelfCheckMode::Off),
+            "warn" => Some(SelfCheckMode::Warn),
+            "strict" => Some(SelfCheckMode::Strict),
+            _ => None,
+        }
+    }
+}
+
+/// Vendor dialect of the `record_type` values
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordTypeProfile {
+    /// mscVoiceRecord, sgsnSMORecord / sgsnSMTRecord, sgsnPDPRecord / pgwRecord
+    #[default]
+    Default,
+    /// moCallRecord / mtCallRecord, moSMSRecord / mtSMSRecord, sgsnPDPRecord / pGWRecord
+    Ericsson,
+    /// MOC / MTC, SMSMO / SMSMT, sgsnPDPRecord / ggsnPDPRecord
+    Huawei,
+    /// Names from `record_type_map`, one for every record kind
+    Custom,
+}
+
+impl RecordTypeProfile {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "default" => Some(RecordTypeProfile::Default),
+            "ericsson" => Some(RecordTypeProfile::Ericsson),
+            "huawei" => Some(RecordTypeProfile::Huawei),
+            "custom" => Some(RecordTypeProfile::Custom),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     // Population
@@ -12,26 +234,52 @@ pub struct Config {
     pub cells: usize,
     pub prefixes: Vec<String>,
     pub mccmnc_pool: Vec<String>,
+    pub prefix_operator_map: BTreeMap<String, String>, // MSISDN prefix -> MCCMNC; unmapped prefixes draw from mccmnc_pool
 
     // Geography
     pub center_lat: f64,
     pub center_lon: f64,
     pub radius_km: f64,
+    pub regen_cells: bool,  // Rebuild cells.csv when it was generated with other parameters
+    pub rat_mix: BTreeMap<String, f64>,  // RAT -> weight, shared by cells.csv and DATA sessions
+    pub apn_mix: BTreeMap<String, f64>,  // APN -> weight for DATA sessions
+    pub apn_profiles: BTreeMap<String, ApnProfile>, // APN -> traffic profile; APNs without one follow the RAT defaults
+    pub data_close_causes: BTreeMap<String, f64>, // DATA cause_for_record_closing -> weight
+    pub data_handover_prob: f64, // Chance a subscriber's next DATA session of the day moves to a newly drawn RAT and cell
+    pub qci_by_apn: BTreeMap<String, BTreeMap<u32, f64>>, // APN -> QCI (1..=9) -> weight for non-NR sessions; unlisted APNs get QCI 9
+    pub nr_5qi_by_apn: BTreeMap<String, BTreeMap<u32, f64>>, // APN -> 5QI (1..=255) -> weight for NR sessions; unlisted APNs get 5QI 9
+    pub user_segments: BTreeMap<String, SegmentProfile>, // Customer segment -> share, activity and plans; shared with export-crm
 
     // Event rates (per user per day)
     pub avg_calls_per_user: f64,
     pub avg_sms_per_user: f64,
     pub avg_data_sessions_per_user: f64,
+    pub daily_targets: DailyTargets, // Day totals replacing the per-user means of the types they set
+    pub sparse_threshold: f64,       // Per-user means all below this: draw each range's totals and spread them (0 = off)
+
+    // Contact network
+    pub contacts: ContactsConfig,   // Size of each subscriber's contact list
+    // Share of CALL/SMS counterparts drawn from the subscriber's contacts, the rest are external numbers. Unset keeps the
+    // built-in behaviour: random subscribers always call a contact, database subscribers DB_CONTACT_CALL_SHARE of the time
+    pub contact_call_share: Option<f64>,
 
     // MO/MT shares
     pub mo_share_call: f64,
     pub mo_share_sms: f64,
+    pub allow_concurrent_calls: bool, // Allow a subscriber to have overlapping CALL records
+    pub callback_prob: f64,           // Chance the other party calls back after a missed MO call
+    pub sms_unreachable_rate: f64,    // Share of MT SMS to a powered-off handset, retried by the SMSC
+    pub sms_retry_schedule_min: Vec<u32>, // Minutes after submission of each delivery attempt; the last one expires the SMS
 
     // Device behavior
     pub imei_daily_change_prob: f64,
 
+    // Subscriber lifecycle: share of valid subscribers with no traffic on a given day
+    pub daily_inactive_rate: f64,
+
     // Call dispositions
     pub call_dispositions: HashMap<String, f64>,
+    pub call_duration_rules: BTreeMap<String, DurationRule>, // Disposition -> duration of unanswered calls; missing ones last 0 s
 
     // Call duration (seconds)
     pub call_duration_quantiles: CallDurationQuantiles,
@@ -42,6 +290,8 @@ pub struct Config {
     // Temporal patterns - hourly multipliers (24 values)
     pub diurnal_weekday: Vec<f64>,
     pub diurnal_weekend: Vec<f64>,
+    pub data_nighttime_floor: f64,      // Lower bound of the DATA placement weight in every hour (0 = follow the curve)
+    pub data_night_volume_factor: f64,  // Volume multiplier of DATA sessions in hours the floor lifts (keep-alive traffic)
 
     // Seasonality (monthly multipliers, 1-12)
     pub seasonality: HashMap<usize, f64>,
@@ -51,7 +301,43 @@ pub struct Config {
 
     // File rotation and compression
     pub rotate_bytes: u64,
-    pub compression_type: String,  // "gzip", "zstd", or "none"
+    pub output_format: OutputFormat, // csv, or arrow: Arrow IPC files per writer task
+    pub arrow_rotate_rows: u64,      // Rows per Arrow file with output_format: arrow (0 = one file per writer task)
+    pub rotation: BTreeMap<String, RotationPolicy>, // Event type -> part file limits; any entry splits part files by event type
+    pub partition_layout: PartitionLayout, // Day directories: flat, or hive-style by date (and event type)
+    pub overwrite_policy: OverwritePolicy, // Day directory with part files, or a day bundle, of an earlier run: fail, clean or merge
+    pub compression_type: String,  // "gzip", "zstd", "bzip2", "xz" or "none"
+    pub compression_level: Option<i32>,  // None = codec default
+    pub compression_by_target: CompressionByTarget, // Codec of the part files and of the day bundle, over compression_type/level
+    pub gzip_member_flush_bytes: u64,    // Start a new gzip member every N uncompressed bytes (0 = off)
+    pub compression_ratio_alert_factor: f64, // Warn on a part file whose compression ratio is this factor off the day's median (0 = off)
+    pub emit_record_seq: bool,           // Add a per-file record_seq column (dedup testing)
+    pub emit_prev_event_ts: bool,        // Add prev_event_ts_ms: start of the subscriber's previous event (gap analysis)
+    pub emit_imeisv: bool,               // Add an imeisv column after imei, set on DATA rows (voice MSC records keep plain IMEI)
+    pub emit_bearer_fields: bool,        // Add qci and bearer_id columns after rat, set on DATA rows
+    pub emit_node_id: bool,              // Add a node_id column after record_type: the MSC/SGSN/PGW element (clock_skew.<pool>.nodes)
+    pub duplicate_injection_rate: f64,   // Share of rows re-emitted verbatim as duplicates
+    pub late_file_rate: f64,             // Share of part files staged in late/ instead of the day directory
+    pub late_file_delay_hours: u64,      // Delay after the end of the day before staged files are due
+    pub fail_writes_after_bytes: u64,    // Failure injection: part files fail like a full disk after N bytes (0 = off)
+    pub serialize_in_workers: bool,      // Workers encode CSV rows; false = writer tasks serialize (comparison path)
+    pub realtime: bool,                  // Live replay: writers sort their rows by start time and pace the writes
+    pub realtime_speedup: f64,           // Replay N times faster than wall-clock (1 = real time)
+    pub strict_ascii: bool,              // Writers reject rows with quoted fields or bytes outside printable ASCII
+    pub emit_timezone_columns: bool,     // false = drop tz_name/tz_offset_min from part files (UTC-only consumers)
+    pub event_time_basis: EventTimeBasis, // Clock the diurnal curve and day boundaries follow
+    pub day_folder_basis: DayFolderBasis, // Date day directories are named by and day boundaries follow (utc: 00-24 UTC, local diurnal hours)
+    pub dirty_data: DirtyDataConfig,     // Per-row rates of deliberately broken fields (all 0 = clean output)
+    pub clock_skew: ClockSkewConfig,     // Clock offsets and drift of the MSC/SGSN/PGW elements (all 0 = true times)
+    pub time_window: Option<TimeWindow>, // Generate only this part of each day ("13:00-14:00"); None = whole day
+    pub msisdn_format: MsisdnFormat,     // Rendering of msisdn_src/msisdn_dst in part files
+    pub record_type_profile: RecordTypeProfile, // Vendor dialect of record_type
+    pub record_type_map: BTreeMap<String, String>, // Record kind (call_mo, ..., data_pgw) -> record_type with the custom profile
+    pub country_code: String,            // Home country calling code, stripped by msisdn_format: national
+    pub metrics: bool,                   // Time writer tasks and add a throughput report to summary.json
+    pub self_check: SelfCheckMode,       // Workers validate their own rows before they are written
+    pub self_check_sample: u64,          // Validate 1 of every N rows (1 = all)
+    pub day_over_day: DayOverDayConfig,  // Overlap of active MSISDNs with the previous day in summary.json
 
     // Timezone
     pub tz_name: String,
@@ -59,24 +345,57 @@ pub struct Config {
     // Multiprocessing
     pub workers: usize,
 
+    // Random seed for deterministic generation
+    pub seed: u64,
+
+    // Generate only every Nth subscriber by index (1 = full run)
+    pub sample_stride: usize,
+
     // Performance optimization settings
-    pub event_pool_size: usize,      // EventRow object pool size per worker
+    pub event_pool_size: usize,      // EventRow object pool size per worker (at least a batch: batch_size_bytes / 230)
     pub batch_size_bytes: usize,     // Batch size for async writing (bytes)
-    pub writer_tasks: usize,         // Number of async writer tasks (0 = auto)
+    pub batch_max_age_ms: u64,       // Also send a batch once its first row is this old, checked on each push (0 = off)
+    pub flush_batch_per_chunk: bool, // Send the partial batch at the end of each redb chunk
+    pub writer_tasks: usize,         // Number of async writer tasks (0 = auto, at most workers)
     pub chunk_size: usize,           // Number of subscribers to process per chunk (for memory efficiency)
+    pub verbose: bool,               // Report per-chunk timing in the redb path
+    pub write_shard_stats: bool,     // Also write per-shard stats_shardNNN.json files (debugging)
+
+    // Subscriber lookup miss rate: warn above, abort the run above the optional fail limit
+    pub miss_rate_warn_threshold: f64,
+    pub fail_on_miss_rate: Option<f64>,
+
+    // Startup check of the redb database: look up `db_check_samples` index-derived MSISDNs per worker range
+    // and warn with the nearest keys of the misses when fewer than `db_check_min_hit_rate` are found
+    pub check_db: bool,
+    pub db_check_samples: usize,
+    pub db_check_min_hit_rate: f64,
 
     // Subscriber database
-    pub subscriber_db_path: Option<PathBuf>,
+    pub subscriber_db_path: Option<PathBuf>,  // CSV event history, or an `.arrow` export read per worker range
     pub subscriber_db_redb_path: Option<PathBuf>,  // Path to redb database (for chunked processing)
+    pub msisdn_file: Option<PathBuf>,  // Targeted run: only these MSISDNs (one per line) of the redb database
+    pub closed_user_group: bool,       // Targeted run: every CALL/SMS counterpart is another listed MSISDN
     pub generate_subscriber_db: Option<PathBuf>,
     pub db_size: usize,
     pub db_history_days: usize,
     pub db_device_change_rate: f64,
     pub db_number_release_rate: f64,
+    pub db_device_change_seasonality: HashMap<usize, f64>,   // Monthly multipliers (1-12)
+    pub db_device_change_special_days: HashMap<String, f64>, // YYYY-MM-DD -> multiplier
+    pub db_number_release_seasonality: HashMap<usize, f64>,
+    pub db_number_release_special_days: HashMap<String, f64>,
     pub db_cooldown_days: usize,
     pub validate_db_only: bool,
 }
 
+/// Duration range `[min_sec, max_sec]` of a call disposition; 0 gives a setup-only record with `end_ts_ms == start_ts_ms`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DurationRule {
+    pub min_sec: u32,
+    pub max_sec: u32,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CallDurationQuantiles {
     pub p50: u32,
@@ -84,6 +403,248 @@ pub struct CallDurationQuantiles {
     pub p99: u32,
 }
 
+/// Part of a day in wall-clock minutes `[start_min, end_min)` of the placement clock; `end_min` 1440 is midnight
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeWindow {
+    pub start_min: u32,
+    pub end_min: u32,
+}
+
+impl TimeWindow {
+    pub const WHOLE_DAY_MIN: u32 = 24 * 60;
+
+    /// Window from `HH:MM` bounds; `24:00` ends at midnight
+    pub fn new(start: &str, end: &str) -> anyhow::Result<Self> {
+        let minutes = |value: &str| -> anyhow::Result<u32> {
+            let (h, m) = value
+                .trim()
+                .split_once(':')
+                .ok_or_else(|| anyhow::anyhow!("Invalid time {:?}: expected HH:MM", value))?;
+            let (h, m): (u32, u32) = (h.parse()?, m.parse()?);
+            if m >= 60 || h * 60 + m > Self::WHOLE_DAY_MIN {
+                anyhow::bail!("Invalid time {:?}: expected 00:00 to 24:00", value);
+            }
+            Ok(h * 60 + m)
+        };
+        let window = TimeWindow { start_min: minutes(start)?, end_min: minutes(end)? };
+        if window.start_min >= window.end_min {
+            anyhow::bail!("Time window {}-{} is empty: the end must be after the start", start, end);
+        }
+        Ok(window)
+    }
+
+    /// Parse `HH:MM-HH:MM`
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        let (start, end) = value
+            .split_once('-')
+            .ok_or_else(|| anyhow::anyhow!("Invalid time window {:?}: expected HH:MM-HH:MM", value))?;
+        Self::new(start, end)
+    }
+
+    /// Suffix for output names: `1300-1400`
+    pub fn label(&self) -> String {
+        format!(
+            "{:02}{:02}-{:02}{:02}",
+            self.start_min / 60,
+            self.start_min % 60,
+            self.end_min / 60,
+            self.end_min % 60
+        )
+    }
+}
+
+/// Part file limits of one output stream; a part is finished at the first limit reached, 0 leaves a limit off.
+/// `bytes` of 0 in `rotation` falls back to `rotate_bytes`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RotationPolicy {
+    pub bytes: u64,
+    pub rows: u64,
+    // Wall-clock seconds a part stays open
+    pub interval_sec: u64,
+}
+
+/// Share of rows with each kind of broken field, drawn independently per row
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct DirtyDataConfig {
+    pub missing_imei_rate: f64,
+    pub missing_cell_rate: f64,
+    pub truncated_msisdn_rate: f64,
+    pub invalid_duration_rate: f64,
+    // Write dirty_shardNNN.jsonl listing every damaged row
+    pub ground_truth: bool,
+}
+
+impl DirtyDataConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.missing_imei_rate > 0.0
+            || self.missing_cell_rate > 0.0
+            || self.truncated_msisdn_rate > 0.0
+            || self.invalid_duration_rate > 0.0
+    }
+}
+
+/// Clock of one pool of network elements. Each of `nodes` elements is off by an offset drawn per day
+/// from N(`mean_ms`, `sigma_ms`) and gains `drift_ms_per_hour` more through the day; subscribers are homed on the elements by IMSI
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NodeClockSkew {
+    pub mean_ms: f64,
+    pub sigma_ms: f64,
+    pub drift_ms_per_hour: f64,
+    pub nodes: u32,
+}
+
+impl Default for NodeClockSkew {
+    fn default() -> Self {
+        NodeClockSkew {
+            mean_ms: 0.0,
+            sigma_ms: 0.0,
+            drift_ms_per_hour: 0.0,
+            nodes: 4,
+        }
+    }
+}
+
+impl NodeClockSkew {
+    pub fn is_enabled(&self) -> bool {
+        self.mean_ms != 0.0 || self.sigma_ms > 0.0 || self.drift_ms_per_hour != 0.0
+    }
+}
+
+/// Clock skew of the elements writing each kind of record: `msc` voice and SMS, `sgsn` SGSN-closed DATA,
+/// `pgw` PGW-closed DATA. Written timestamps are skewed; correlation between records uses the true time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClockSkewConfig {
+    pub msc: NodeClockSkew,
+    pub sgsn: NodeClockSkew,
+    pub pgw: NodeClockSkew,
+    // Write clock_skew_shardNNN.jsonl with the true times of every skewed row
+    pub ground_truth: bool,
+}
+
+impl ClockSkewConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.msc.is_enabled() || self.sgsn.is_enabled() || self.pgw.is_enabled()
+    }
+}
+
+/// Codec name (as `compression_type`) and level of one output target
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompressionTarget {
+    #[serde(rename = "type")]
+    pub compression_type: String,
+    pub level: Option<i32>,
+}
+
+/// Per-target codecs: `parts` for the shard part files, `bundle` for the day bundle.
+/// A bundle codec other than the parts' makes `bundle_day` decode and re-encode instead of concatenating
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CompressionByTarget {
+    pub parts: Option<CompressionTarget>,
+    pub bundle: Option<CompressionTarget>,
+}
+
+impl Config {
+    /// Codec and level of the part files: `compression_by_target.parts`, else `compression_type`/`compression_level`
+    pub fn part_compression(&self) -> (&str, Option<i32>) {
+        match self.compression_by_target.parts {
+            Some(ref target) => (&target.compression_type, target.level),
+            None => (&self.compression_type, self.compression_level),
+        }
+    }
+
+    /// Codec and level of the day bundle: `compression_by_target.bundle`, else the part files'
+    pub fn bundle_compression(&self) -> (&str, Option<i32>) {
+        match self.compression_by_target.bundle {
+            Some(ref target) => (&target.compression_type, target.level),
+            None => self.part_compression(),
+        }
+    }
+}
+
+/// Contacts per subscriber: a normal draw around `avg` with `sd_ratio * avg` spread, clamped to `min..=max`
+/// (`max` unset = no cap) and to the other subscribers of the shard
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ContactsConfig {
+    pub avg: f64,
+    pub sd_ratio: f64,
+    pub min: usize,
+    pub max: Option<usize>,
+}
+
+impl Default for ContactsConfig {
+    fn default() -> Self {
+        ContactsConfig {
+            avg: 30.0,
+            sd_ratio: 0.3,
+            min: 0,
+            max: None,
+        }
+    }
+}
+
+/// Day total of one event type: a number, or 24 numbers, the totals of each local hour (their sum is the day total,
+/// and they replace the diurnal curve as the shape events are placed by)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum VolumeTarget {
+    Daily(f64),
+    Hourly(Vec<f64>),
+}
+
+impl VolumeTarget {
+    pub fn total(&self) -> f64 {
+        match self {
+            VolumeTarget::Daily(total) => *total,
+            VolumeTarget::Hourly(hours) => hours.iter().sum(),
+        }
+    }
+
+    /// Per-hour totals, None for a whole-day target
+    pub fn hourly(&self) -> Option<&[f64]> {
+        match self {
+            VolumeTarget::Daily(_) => None,
+            VolumeTarget::Hourly(hours) => Some(hours),
+        }
+    }
+}
+
+/// Volume-driven generation: a type with a target draws its per-user mean from the target over the run's expected
+/// active subscribers instead of `avg_*_per_user`. Totals count rows as summary.json does (MT legs and callbacks
+/// are CALL rows; SMS retry rows are not SMS)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DailyTargets {
+    pub calls: Option<VolumeTarget>,
+    pub sms: Option<VolumeTarget>,
+    pub data_sessions: Option<VolumeTarget>,
+}
+
+impl DailyTargets {
+    pub fn is_enabled(&self) -> bool {
+        self.calls.is_some() || self.sms.is_some() || self.data_sessions.is_some()
+    }
+}
+
+/// Day-over-day overlap of the active MSISDNs in summary.json: exact up to `exact_max_subscribers`,
+/// a Bloom filter with false-positive rate `bloom_fpr` above, so memory stays bounded on large runs
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DayOverDayConfig {
+    pub enabled: bool,
+    pub exact_max_subscribers: usize,
+    pub bloom_fpr: f64,
+}
+
+impl Default for DayOverDayConfig {
+    fn default() -> Self {
+        DayOverDayConfig {
+            enabled: true,
+            exact_max_subscribers: 1_000_000,
+            bloom_fpr: 0.01,
+        }
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         let mut call_dispositions = HashMap::new();
@@ -93,6 +654,13 @@ impl Default for Config {
         call_dispositions.insert("FAILED".to_string(), 0.015);
         call_dispositions.insert("CONGESTION".to_string(), 0.005);
 
+        // ANSWERED calls take ring time plus `call_duration_quantiles`; network failures never connect
+        let mut call_duration_rules = BTreeMap::new();
+        call_duration_rules.insert("NO ANSWER".to_string(), DurationRule { min_sec: 5, max_sec: 30 });
+        call_duration_rules.insert("BUSY".to_string(), DurationRule { min_sec: 2, max_sec: 10 });
+        call_duration_rules.insert("FAILED".to_string(), DurationRule { min_sec: 0, max_sec: 0 });
+        call_duration_rules.insert("CONGESTION".to_string(), DurationRule { min_sec: 0, max_sec: 0 });
+
         let mut seasonality = HashMap::new();
         seasonality.insert(1, 0.95);
         seasonality.insert(2, 0.9);
@@ -121,16 +689,36 @@ impl Default for Config {
                 "20416".to_string(),
                 "20420".to_string(),
             ],
+            prefix_operator_map: BTreeMap::new(),
             center_lat: 52.37,
             center_lon: 4.895,
             radius_km: 50.0,
+            regen_cells: false,
+            rat_mix: default_rat_mix(),
+            apn_mix: default_apn_mix(),
+            apn_profiles: default_apn_profiles(),
+            data_close_causes: default_data_close_causes(),
+            data_handover_prob: 0.2,
+            qci_by_apn: default_qci_by_apn(),
+            nr_5qi_by_apn: default_nr_5qi_by_apn(),
+            user_segments: default_user_segments(),
             avg_calls_per_user: 3.5,
             avg_sms_per_user: 5.2,
             avg_data_sessions_per_user: 12.0,
+            daily_targets: DailyTargets::default(),
+            sparse_threshold: 0.1,
+            contacts: ContactsConfig::default(),
+            contact_call_share: None,
             mo_share_call: 0.5,
             mo_share_sms: 0.5,
+            allow_concurrent_calls: false,
+            callback_prob: 0.0,
+            sms_unreachable_rate: 0.0,
+            sms_retry_schedule_min: vec![0, 5, 30, 120],
             imei_daily_change_prob: 0.02,
+            daily_inactive_rate: 0.0,
             call_dispositions,
+            call_duration_rules,
             call_duration_quantiles: CallDurationQuantiles {
                 p50: 75,
                 p90: 240,
@@ -149,23 +737,78 @@ impl Default for Config {
                 1.3, 1.2, 1.1, 1.0, 1.1, 1.3,     // 12-17
                 1.4, 1.3, 1.2, 1.0, 0.6, 0.4,     // 18-23
             ],
+            data_nighttime_floor: 0.0,
+            data_night_volume_factor: 0.1,
             seasonality,
             special_days: HashMap::new(),
             rotate_bytes: 100_000_000,
+            output_format: OutputFormat::Csv,
+            arrow_rotate_rows: 1_000_000,
+            rotation: BTreeMap::new(),
+            partition_layout: PartitionLayout::Flat,
+            overwrite_policy: OverwritePolicy::Fail,
             compression_type: "gzip".to_string(),  // Default to gzip for backward compatibility
+            compression_level: None,
+            compression_by_target: CompressionByTarget::default(),
+            gzip_member_flush_bytes: 0,
+            compression_ratio_alert_factor: 3.0,
+            emit_record_seq: false,
+            emit_prev_event_ts: false,
+            emit_imeisv: false,
+            emit_bearer_fields: false,
+            emit_node_id: false,
+            duplicate_injection_rate: 0.0,
+            late_file_rate: 0.0,
+            late_file_delay_hours: 24,
+            fail_writes_after_bytes: 0,
+            serialize_in_workers: true,
+            realtime: false,
+            realtime_speedup: 1.0,
+            strict_ascii: false,
+            emit_timezone_columns: true,
+            event_time_basis: EventTimeBasis::Local,
+            day_folder_basis: DayFolderBasis::Local,
+            dirty_data: DirtyDataConfig::default(),
+            clock_skew: ClockSkewConfig::default(),
+            time_window: None,
+            msisdn_format: MsisdnFormat::E164,
+            record_type_profile: RecordTypeProfile::Default,
+            record_type_map: BTreeMap::new(),
+            country_code: "31".to_string(),
+            metrics: true,
+            self_check: SelfCheckMode::Off,
+            self_check_sample: 1,
+            day_over_day: DayOverDayConfig::default(),
             tz_name: DEFAULT_TZ_NAME.to_string(),
             workers: 0,
-            event_pool_size: 10_000,           // 10K EventRow objects per worker
+            seed: 42,
+            sample_stride: 1,
+            event_pool_size: 50_000,           // 50K EventRow objects per worker, a default batch and then some
             batch_size_bytes: 10_485_760,      // 10MB batch size
+            batch_max_age_ms: 0,
+            flush_batch_per_chunk: true,
             writer_tasks: 0,                   // Auto-detect (workers / 2)
-            chunk_size: 25_000,                // Process 25K subscribers per chunk (for memory efficiency)
+            chunk_size: 10_000,                // Process 10K subscribers per chunk (for memory efficiency)
+            verbose: false,
+            write_shard_stats: false,
+            miss_rate_warn_threshold: 0.01,
+            fail_on_miss_rate: None,
+            check_db: true,
+            db_check_samples: 64,
+            db_check_min_hit_rate: 0.9,
             subscriber_db_path: None,
             subscriber_db_redb_path: None,
+            msisdn_file: None,
+            closed_user_group: false,
             generate_subscriber_db: None,
             db_size: 10_000,
             db_history_days: 365,
             db_device_change_rate: 0.15,
             db_number_release_rate: 0.05,
+            db_device_change_seasonality: HashMap::new(),
+            db_device_change_special_days: HashMap::new(),
+            db_number_release_seasonality: HashMap::new(),
+            db_number_release_special_days: HashMap::new(),
             db_cooldown_days: 90,
             validate_db_only: false,
         }
@@ -199,30 +842,310 @@ pub fn parse_prefixes(prefixes_str: &str) -> anyhow::Result<Vec<String>> {
     }
 }
 
-/// Load configuration from YAML file and merge with defaults
+/// Default APN weights for DATA sessions
+pub fn default_apn_mix() -> BTreeMap<String, f64> {
+    BTreeMap::from([
+        ("internet".to_string(), 0.8),
+        ("ims".to_string(), 0.1),
+        ("mms".to_string(), 0.1),
+    ])
+}
+
+/// Default weights of the causes closing DATA records. `abnormalRelease` cuts a session short,
+/// `sgsnChange` and `timeLimit` close long ones, `volumeLimit` heavy ones (see `DataGenerator::generate`)
+pub fn default_data_close_causes() -> BTreeMap<String, f64> {
+    BTreeMap::from([
+        ("normalRelease".to_string(), 0.85),
+        ("abnormalRelease".to_string(), 0.04),
+        ("volumeLimit".to_string(), 0.03),
+        ("timeLimit".to_string(), 0.04),
+        ("sgsnChange".to_string(), 0.03),
+        ("managementIntervention".to_string(), 0.01),
+    ])
+}
+
+/// Default QCIs of the default bearer per APN: IMS signalling on QCI 5, MMS and browsing on the
+/// non-GBR QCIs 8 and 9
+pub fn default_qci_by_apn() -> BTreeMap<String, BTreeMap<u32, f64>> {
+    BTreeMap::from([
+        ("ims".to_string(), BTreeMap::from([(5, 1.0)])),
+        ("internet".to_string(), BTreeMap::from([(8, 0.1), (9, 0.9)])),
+        ("mms".to_string(), BTreeMap::from([(8, 1.0)])),
+    ])
+}
+
+/// Default 5QIs of NR sessions per APN: the standardized 5QIs repeat the QCI values for these services
+pub fn default_nr_5qi_by_apn() -> BTreeMap<String, BTreeMap<u32, f64>> {
+    default_qci_by_apn()
+}
+
+/// Highest QCI (LTE and older RATs) and 5QI (NR)
+pub const MAX_QCI: u32 = 9;
+pub const MAX_5QI: u32 = 255;
+
+/// Reject `qci_by_apn` / `nr_5qi_by_apn` entries out of 1..=9 / 1..=255, or with a negative
+/// or non-finite weight, or whose weights are all 0
+pub fn validate_qci_tables(config: &Config) -> anyhow::Result<()> {
+    let tables = [
+        ("qci_by_apn", "QCI", &config.qci_by_apn, MAX_QCI),
+        ("nr_5qi_by_apn", "5QI", &config.nr_5qi_by_apn, MAX_5QI),
+    ];
+    for (key, name, table, max) in tables {
+        for (apn, weights) in table {
+            if let Some(value) = weights.keys().find(|value| !(1..=max).contains(*value)) {
+                anyhow::bail!("Config key {}: APN {} has {} {}, expected 1..={}", key, apn, name, value, max);
+            }
+            if weights.values().any(|w| !w.is_finite() || *w < 0.0) || !weights.values().any(|w| *w > 0.0) {
+                anyhow::bail!("Config key {}: APN {} needs non-negative weights, at least one positive: {:?}", key, apn, weights);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reject an `event_pool_size` below `min_event_pool_size`: a pool slot must not come round again
+/// while the batch its row went into is still being filled
+pub fn validate_event_pool_size(config: &Config) -> anyhow::Result<()> {
+    let min = min_event_pool_size(config.batch_size_bytes);
+    if config.event_pool_size < min {
+        anyhow::bail!(
+            "Config key event_pool_size: {} is below the minimum of {} rows, a batch of batch_size_bytes {}",
+            config.event_pool_size,
+            min,
+            config.batch_size_bytes
+        );
+    }
+    Ok(())
+}
+
+/// Reject a `rat_mix` that cannot be sampled, and a cell catalog with fewer cells than RATs weighted above
+/// zero: DATA sessions of every weighted RAT need a cell of it
+pub fn validate_rat_mix(config: &Config) -> anyhow::Result<()> {
+    let rats = RatMix::new(&config.rat_mix).map_err(|e| anyhow::anyhow!("Config key rat_mix: {}", e))?.rats().count();
+    if config.cells > 0 && config.cells < rats {
+        anyhow::bail!(
+            "Config key cells: {} cells cannot give each of the {} RATs weighted in rat_mix a cell; raise cells or drop RATs",
+            config.cells,
+            rats
+        );
+    }
+    Ok(())
+}
+
+/// Traffic of an APN's DATA sessions in place of the RAT defaults: downlink bytes and duration in seconds,
+/// each a normal distribution. The RAT scales the volume by its speed relative to LTE and sets the uplink share;
+/// `max_bytes` caps the scaled downlink volume
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ApnProfile {
+    pub bytes_mean: f64,
+    pub bytes_sd: f64,
+    pub max_bytes: u64,
+    pub dur_mean: f64,
+    pub dur_sd: f64,
+}
+
+/// Default APN profiles: IMS signalling moves kilobytes over hour-long registrations, MMS a few hundred KB
+/// in seconds. `internet` has none and follows the RAT defaults
+pub fn default_apn_profiles() -> BTreeMap<String, ApnProfile> {
+    BTreeMap::from([
+        (
+            "ims".to_string(),
+            ApnProfile { bytes_mean: 20_000.0, bytes_sd: 10_000.0, max_bytes: 100_000, dur_mean: 3_600.0, dur_sd: 1_800.0 },
+        ),
+        (
+            "mms".to_string(),
+            ApnProfile { bytes_mean: 300_000.0, bytes_sd: 150_000.0, max_bytes: 1_000_000, dur_mean: 20.0, dur_sd: 10.0 },
+        ),
+    ])
+}
+
+/// Customer segment of a subscriber: its share of the population, a multiplier on the per-user event means
+/// and the tariff plans its customers are spread over
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SegmentProfile {
+    pub share: f64,
+    pub activity: f64,
+    pub plans: Vec<String>,
+}
+
+/// Default customer segments. Every activity is 1.0, so segments only label subscribers for the CRM export
+/// until a config makes one busier or quieter
+pub fn default_user_segments() -> BTreeMap<String, SegmentProfile> {
+    let segment = |share: f64, plans: &[&str]| SegmentProfile {
+        share,
+        activity: 1.0,
+        plans: plans.iter().map(|plan| plan.to_string()).collect(),
+    };
+    BTreeMap::from([
+        ("business".to_string(), segment(0.15, &["business_pro", "business_unlimited"])),
+        ("consumer".to_string(), segment(0.7, &["prepaid_basic", "postpaid_standard", "postpaid_plus"])),
+        ("youth".to_string(), segment(0.15, &["youth_unlimited"])),
+    ])
+}
+
+/// Reject configured strings that are written into CSV fields and would have to be quoted:
+/// the delimiter, double quotes and control characters. The error names the offending key
+pub fn validate_output_strings(config: &Config) -> anyhow::Result<()> {
+    let delimiter = crate::writer::CSV_DELIMITER as char;
+    let check = |key: &str, value: &str| -> anyhow::Result<()> {
+        if let Some(c) = value.chars().find(|&c| c == delimiter || c == '"' || c.is_control()) {
+            anyhow::bail!("Config key {}: value {:?} contains {:?}, which is not allowed in CSV output", key, value, c);
+        }
+        Ok(())
+    };
+
+    check("tz_name", &config.tz_name)?;
+    config.apn_mix.keys().try_for_each(|apn| check("apn_mix", apn))?;
+    config.rat_mix.keys().try_for_each(|rat| check("rat_mix", rat))?;
+    config.data_close_causes.keys().try_for_each(|cause| check("data_close_causes", cause))?;
+    config.prefixes.iter().try_for_each(|prefix| check("prefixes", prefix))?;
+    config.mccmnc_pool.iter().try_for_each(|mccmnc| check("mccmnc_pool", mccmnc))?;
+    config.record_type_map.values().try_for_each(|name| check("record_type_map", name))?;
+    if config.country_code.is_empty() || config.country_code.len() > 3 || !config.country_code.bytes().all(|b| b.is_ascii_digit()) {
+        anyhow::bail!("Config key country_code: {:?} is not a 1-3 digit calling code", config.country_code);
+    }
+    Ok(())
+}
+
+/// Reject `mccmnc_pool` entries that are not an MCC with a 2- or 3-digit MNC: 5 or 6 digits,
+/// leaving a 10- or 9-digit MSIN in a 15-digit IMSI
+pub fn validate_mccmnc_pool(config: &Config) -> anyhow::Result<()> {
+    for mccmnc in &config.mccmnc_pool {
+        if let Err(e) = mccmnc.parse::<Mccmnc>() {
+            anyhow::bail!("Config key mccmnc_pool: {:?} is not a 5- or 6-digit MCCMNC ({})", mccmnc, e);
+        }
+    }
+    Ok(())
+}
+
+/// Reject `prefix_operator_map` entries bound to an MCCMNC that is not in `mccmnc_pool`
+pub fn validate_prefix_operator_map(config: &Config) -> anyhow::Result<()> {
+    let unknown: Vec<String> = config
+        .prefix_operator_map
+        .iter()
+        .filter(|(_, mccmnc)| !config.mccmnc_pool.contains(mccmnc))
+        .map(|(prefix, mccmnc)| format!("{} -> {}", prefix, mccmnc))
+        .collect();
+    if !unknown.is_empty() {
+        anyhow::bail!(
+            "Config key prefix_operator_map: MCCMNC not in mccmnc_pool {:?}: {}",
+            config.mccmnc_pool,
+            unknown.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// Prefix of environment variables that override scalar config keys (`CDRGEN_WORKERS=8`);
+/// `CDRGEN_MAX_CORES` is no config key but caps the auto-detected core count (`cores::MAX_CORES_ENV`)
+pub const ENV_PREFIX: &str = "CDRGEN_";
+
+/// Load configuration and merge with defaults.
+/// Precedence: defaults < YAML (`-` reads stdin) < `CDRGEN_*` environment < CLI flags (applied by the caller)
 pub fn load_config(config_path: Option<&Path>) -> anyhow::Result<Config> {
     let mut config = Config::default();
 
     if let Some(path) = config_path {
-        if path.exists() {
+        if path == Path::new("-") {
+            let mut contents = String::new();
+            std::io::stdin().read_to_string(&mut contents)?;
+            merge_yaml(&mut config, &contents)?;
+        } else if path.exists() {
             let contents = std::fs::read_to_string(path)?;
-            let user_config: serde_yaml::Value = serde_yaml::from_str(&contents)?;
+            merge_yaml(&mut config, &contents)?;
+        }
+    }
 
-            // Merge user config with defaults
-            if let serde_yaml::Value::Mapping(map) = user_config {
-                for (key, value) in map {
-                    if let serde_yaml::Value::String(key_str) = key {
-                        merge_config_value(&mut config, &key_str, value);
-                    }
+    apply_env_overrides(&mut config, std::env::vars())?;
+    validate_output_strings(&config)?;
+    validate_mccmnc_pool(&config)?;
+    validate_prefix_operator_map(&config)?;
+    validate_qci_tables(&config)?;
+    validate_event_pool_size(&config)?;
+    validate_rat_mix(&config)?;
+
+    Ok(config)
+}
+
+fn merge_yaml(config: &mut Config, contents: &str) -> anyhow::Result<()> {
+    let user_config: serde_yaml::Value = serde_yaml::from_str(contents)?;
+
+    // Merge user config with defaults
+    if let serde_yaml::Value::Mapping(map) = user_config {
+        for (key, value) in map {
+            if let serde_yaml::Value::String(key_str) = key {
+                merge_config_value(config, &key_str, value)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Apply `CDRGEN_<KEY>` variables to scalar config keys, typed after the current value.
+/// All bad variables are reported together, one line each
+pub fn apply_env_overrides<I>(config: &mut Config, vars: I) -> anyhow::Result<()>
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    use serde_yaml::Value;
+
+    let mut overrides: Vec<(String, String)> = vars
+        .into_iter()
+        .filter(|(name, _)| name.starts_with(ENV_PREFIX) && name != MAX_CORES_ENV)
+        .collect();
+    if overrides.is_empty() {
+        return Ok(());
+    }
+    overrides.sort();
+
+    let current = serde_yaml::to_value(&*config)?;
+    let mut errors = Vec::new();
+
+    for (name, raw) in overrides {
+        let key = name[ENV_PREFIX.len()..].to_lowercase();
+        let parsed: Value = serde_yaml::from_str(&raw).unwrap_or(Value::String(raw.clone()));
+
+        let value = match current.get(key.as_str()) {
+            None => Err("unknown config key".to_string()),
+            Some(Value::Bool(_)) => match parsed {
+                Value::Bool(_) => Ok(parsed),
+                _ => Err("expected true or false".to_string()),
+            },
+            Some(Value::Number(n)) if n.is_u64() => match parsed.as_u64() {
+                Some(_) => Ok(parsed),
+                None => Err("expected an unsigned integer".to_string()),
+            },
+            Some(Value::Number(_)) => match parsed.as_f64() {
+                Some(_) => Ok(parsed),
+                None => Err("expected a number".to_string()),
+            },
+            Some(Value::String(_)) => Ok(Value::String(raw.clone())),
+            // Unset optional keys take any scalar; merge_config_value checks the type
+            Some(Value::Null) => match parsed {
+                Value::Sequence(_) | Value::Mapping(_) => Err("expected a scalar value".to_string()),
+                _ => Ok(parsed),
+            },
+            Some(_) => Err("only scalar keys can be set from the environment".to_string()),
+        };
+
+        match value {
+            Ok(value) => {
+                if let Err(e) = merge_config_value(config, &key, value) {
+                    errors.push(format!("{}={}: {}", name, raw, e));
                 }
             }
+            Err(reason) => errors.push(format!("{}={}: {}", name, raw, reason)),
         }
     }
 
-    Ok(config)
+    if !errors.is_empty() {
+        anyhow::bail!("Invalid environment overrides:\n  {}", errors.join("\n  "));
+    }
+    Ok(())
 }
 
-fn merge_config_value(config: &mut Config, key: &str, value: serde_yaml::Value) {
+fn merge_config_value(config: &mut Config, key: &str, value: serde_yaml::Value) -> anyhow::Result<()> {
     match key {
         "subscribers" => {
             if let Some(v) = value.as_u64() {
@@ -250,6 +1173,13 @@ fn merge_config_value(config: &mut Config, key: &str, value: serde_yaml::Value)
                     .collect();
             }
         }
+        "prefix_operator_map" => {
+            if let Some(map) = value.as_mapping() {
+                // Unquoted prefixes and MCCMNCs parse as YAML numbers
+                let text = |v: &serde_yaml::Value| v.as_str().map(String::from).or_else(|| v.as_u64().map(|n| n.to_string()));
+                config.prefix_operator_map = map.iter().filter_map(|(k, v)| Some((text(k)?, text(v)?))).collect();
+            }
+        }
         "center_lat" => {
             if let Some(v) = value.as_f64() {
                 config.center_lat = v;
@@ -280,6 +1210,34 @@ fn merge_config_value(config: &mut Config, key: &str, value: serde_yaml::Value)
                 config.avg_data_sessions_per_user = v;
             }
         }
+        "sparse_threshold" => {
+            if let Some(v) = value.as_f64() {
+                config.sparse_threshold = v.max(0.0);
+            }
+        }
+        "daily_targets" => {
+            // A non-negative number, or 24 of them; other values leave the type's target unchanged
+            if let Some(map) = value.as_mapping() {
+                let targets = &mut config.daily_targets;
+                for (k, v) in map {
+                    let target = match v.as_sequence() {
+                        Some(seq) => seq
+                            .iter()
+                            .map(|v| v.as_f64().filter(|v| *v >= 0.0))
+                            .collect::<Option<Vec<f64>>>()
+                            .filter(|hours| hours.len() == 24)
+                            .map(VolumeTarget::Hourly),
+                        None => v.as_f64().filter(|v| *v >= 0.0).map(VolumeTarget::Daily),
+                    };
+                    match (k.as_str(), target) {
+                        (Some("calls"), Some(t)) => targets.calls = Some(t),
+                        (Some("sms"), Some(t)) => targets.sms = Some(t),
+                        (Some("data_sessions"), Some(t)) => targets.data_sessions = Some(t),
+                        _ => {}
+                    }
+                }
+            }
+        }
         "mo_share_call" => {
             if let Some(v) = value.as_f64() {
                 config.mo_share_call = v;
@@ -290,54 +1248,555 @@ fn merge_config_value(config: &mut Config, key: &str, value: serde_yaml::Value)
                 config.mo_share_sms = v;
             }
         }
-        "tz_name" => {
-            if let Some(v) = value.as_str() {
-                config.tz_name = v.to_string();
+        "allow_concurrent_calls" => {
+            if let Some(v) = value.as_bool() {
+                config.allow_concurrent_calls = v;
             }
         }
-        "workers" => {
-            if let Some(v) = value.as_u64() {
-                config.workers = v as usize;
+        "callback_prob" => {
+            if let Some(v) = value.as_f64() {
+                config.callback_prob = v.clamp(0.0, 1.0);
             }
         }
-        "event_pool_size" => {
-            if let Some(v) = value.as_u64() {
-                config.event_pool_size = v as usize;
+        "sms_unreachable_rate" => {
+            if let Some(v) = value.as_f64() {
+                config.sms_unreachable_rate = v.clamp(0.0, 1.0);
             }
         }
-        "batch_size_bytes" => {
-            if let Some(v) = value.as_u64() {
-                config.batch_size_bytes = v as usize;
+        "sms_retry_schedule_min" => {
+            // At least a first attempt and a retry, in increasing order
+            if let Some(seq) = value.as_sequence() {
+                let schedule: Option<Vec<u32>> = seq.iter().map(|v| v.as_u64().and_then(|v| u32::try_from(v).ok())).collect();
+                if let Some(schedule) = schedule.filter(|s| s.len() >= 2 && s.windows(2).all(|w| w[0] < w[1])) {
+                    config.sms_retry_schedule_min = schedule;
+                }
             }
         }
-        "writer_tasks" => {
-            if let Some(v) = value.as_u64() {
-                config.writer_tasks = v as usize;
+        "daily_inactive_rate" => {
+            if let Some(v) = value.as_f64() {
+                config.daily_inactive_rate = v.clamp(0.0, 1.0);
             }
         }
-        "chunk_size" => {
-            if let Some(v) = value.as_u64() {
-                config.chunk_size = v as usize;
+        "imei_daily_change_prob" => {
+            if let Some(v) = value.as_f64() {
+                config.imei_daily_change_prob = v.clamp(0.0, 1.0);
             }
         }
-        "rotate_bytes" => {
-            if let Some(v) = value.as_u64() {
-                config.rotate_bytes = v;
+        "interconnect_share" => {
+            if let Some(v) = value.as_f64() {
+                config.interconnect_share = v.clamp(0.0, 1.0);
             }
         }
-        "compression_type" => {
-            if let Some(v) = value.as_str() {
-                config.compression_type = v.to_string();
+        "rat_mix" => {
+            if let Some(map) = value.as_mapping() {
+                config.rat_mix = map
+                    .iter()
+                    .filter_map(|(k, v)| Some((k.as_str()?.to_uppercase(), v.as_f64()?)))
+                    .collect();
             }
         }
-        "db_size" => {
-            if let Some(v) = value.as_u64() {
-                config.db_size = v as usize;
+        "apn_mix" => {
+            if let Some(map) = value.as_mapping() {
+                config.apn_mix = map
+                    .iter()
+                    .filter_map(|(k, v)| Some((k.as_str()?.to_string(), v.as_f64()?)))
+                    .collect();
             }
         }
-        "db_history_days" => {
-            if let Some(v) = value.as_u64() {
-                config.db_history_days = v as usize;
+        "data_close_causes" => {
+            if let Some(map) = value.as_mapping() {
+                config.data_close_causes = map
+                    .iter()
+                    .filter_map(|(k, v)| Some((k.as_str()?.to_string(), v.as_f64()?)))
+                    .collect();
+            }
+        }
+        "qci_by_apn" | "nr_5qi_by_apn" => {
+            // APN -> {value: weight}; entries replace the defaults of their APN
+            if let Some(map) = value.as_mapping() {
+                let table = if key == "qci_by_apn" { &mut config.qci_by_apn } else { &mut config.nr_5qi_by_apn };
+                for (apn, weights) in map {
+                    let (Some(apn), Some(weights)) = (apn.as_str(), weights.as_mapping()) else {
+                        continue;
+                    };
+                    let weights = weights
+                        .iter()
+                        .filter_map(|(k, v)| Some((u32::try_from(k.as_u64()?).ok()?, v.as_f64()?)))
+                        .collect();
+                    table.insert(apn.to_string(), weights);
+                }
+            }
+        }
+        "data_handover_prob" => {
+            if let Some(v) = value.as_f64() {
+                config.data_handover_prob = v.clamp(0.0, 1.0);
+            }
+        }
+        "apn_profiles" => {
+            // Entries replace the defaults of their APN; every field is required
+            if let Some(map) = value.as_mapping() {
+                for (apn, fields) in map {
+                    let (Some(apn), Some(fields)) = (apn.as_str(), fields.as_mapping()) else {
+                        continue;
+                    };
+                    let num = |key: &str| fields.get(key).and_then(|v| v.as_f64()).filter(|v| *v >= 0.0);
+                    let profile = (|| {
+                        Some(ApnProfile {
+                            bytes_mean: num("bytes_mean")?,
+                            bytes_sd: num("bytes_sd")?,
+                            max_bytes: fields.get("max_bytes")?.as_u64()?,
+                            dur_mean: num("dur_mean")?,
+                            dur_sd: num("dur_sd")?,
+                        })
+                    })();
+                    if let Some(profile) = profile {
+                        config.apn_profiles.insert(apn.to_string(), profile);
+                    }
+                }
+            }
+        }
+        "user_segments" => {
+            // Replaces the default segments; `activity` defaults to 1.0, `share` and a non-empty `plans` are required
+            if let Some(map) = value.as_mapping() {
+                config.user_segments = map
+                    .iter()
+                    .filter_map(|(name, fields)| {
+                        let fields = fields.as_mapping()?;
+                        let plans: Vec<String> = fields
+                            .get("plans")?
+                            .as_sequence()?
+                            .iter()
+                            .filter_map(|plan| Some(plan.as_str()?.to_string()))
+                            .collect();
+                        let profile = SegmentProfile {
+                            share: fields.get("share")?.as_f64().filter(|v| *v >= 0.0)?,
+                            activity: fields.get("activity").map_or(Some(1.0), |v| v.as_f64().filter(|v| *v >= 0.0))?,
+                            plans,
+                        };
+                        Some((name.as_str()?.to_string(), profile))
+                    })
+                    .collect();
+            }
+        }
+        "data_nighttime_floor" => {
+            if let Some(v) = value.as_f64() {
+                config.data_nighttime_floor = v.max(0.0);
+            }
+        }
+        "data_night_volume_factor" => {
+            if let Some(v) = value.as_f64() {
+                config.data_night_volume_factor = v.clamp(0.0, 1.0);
+            }
+        }
+        "regen_cells" => {
+            if let Some(v) = value.as_bool() {
+                config.regen_cells = v;
+            }
+        }
+        "tz_name" => {
+            if let Some(v) = value.as_str() {
+                config.tz_name = v.to_string();
+            }
+        }
+        "workers" => {
+            if let Some(v) = value.as_u64() {
+                config.workers = v as usize;
+            }
+        }
+        "seed" => {
+            if let Some(v) = value.as_u64() {
+                config.seed = v;
+            }
+        }
+        "event_pool_size" => {
+            if let Some(v) = value.as_u64() {
+                config.event_pool_size = v as usize;
+            }
+        }
+        "batch_size_bytes" => {
+            if let Some(v) = value.as_u64() {
+                config.batch_size_bytes = v as usize;
+            }
+        }
+        "batch_max_age_ms" => {
+            if let Some(v) = value.as_u64() {
+                config.batch_max_age_ms = v;
+            }
+        }
+        "flush_batch_per_chunk" => {
+            if let Some(v) = value.as_bool() {
+                config.flush_batch_per_chunk = v;
+            }
+        }
+        "writer_tasks" => {
+            if let Some(v) = value.as_u64() {
+                config.writer_tasks = v as usize;
+            }
+        }
+        "chunk_size" => {
+            if let Some(v) = value.as_u64() {
+                config.chunk_size = (v as usize).max(1);
+            }
+        }
+        "verbose" => {
+            if let Some(v) = value.as_bool() {
+                config.verbose = v;
+            }
+        }
+        "sample_stride" => {
+            if let Some(v) = value.as_u64() {
+                config.sample_stride = (v as usize).max(1);
+            }
+        }
+        "write_shard_stats" => {
+            if let Some(v) = value.as_bool() {
+                config.write_shard_stats = v;
+            }
+        }
+        "gzip_member_flush_bytes" => {
+            if let Some(v) = value.as_u64() {
+                config.gzip_member_flush_bytes = v;
+            }
+        }
+        "compression_ratio_alert_factor" => {
+            if let Some(v) = value.as_f64().filter(|v| v.is_finite() && *v >= 0.0) {
+                config.compression_ratio_alert_factor = v;
+            }
+        }
+        "msisdn_format" => {
+            if let Some(v) = value.as_str().and_then(MsisdnFormat::from_str) {
+                config.msisdn_format = v;
+            }
+        }
+        "record_type_profile" => {
+            if let Some(v) = value.as_str().and_then(RecordTypeProfile::from_str) {
+                config.record_type_profile = v;
+            }
+        }
+        "record_type_map" => {
+            // Checked against the record kinds when the generators are built
+            if let Some(map) = value.as_mapping() {
+                for (kind, name) in map {
+                    if let (Some(kind), Some(name)) = (kind.as_str(), name.as_str()) {
+                        config.record_type_map.insert(kind.to_string(), name.to_string());
+                    }
+                }
+            }
+        }
+        "country_code" => {
+            if let Some(v) = value.as_str() {
+                config.country_code = v.to_string();
+            } else if let Some(v) = value.as_u64() {
+                config.country_code = v.to_string();
+            }
+        }
+        "event_time_basis" => {
+            if let Some(v) = value.as_str().and_then(EventTimeBasis::from_str) {
+                config.event_time_basis = v;
+            }
+        }
+        "day_folder_basis" => {
+            if let Some(v) = value.as_str().and_then(DayFolderBasis::from_str) {
+                config.day_folder_basis = v;
+            }
+        }
+        "dirty_data" => {
+            if let Some(map) = value.as_mapping() {
+                let dirty = &mut config.dirty_data;
+                for (k, v) in map {
+                    let rate = v.as_f64().map(|r| r.clamp(0.0, 1.0));
+                    match (k.as_str(), rate) {
+                        (Some("missing_imei_rate"), Some(r)) => dirty.missing_imei_rate = r,
+                        (Some("missing_cell_rate"), Some(r)) => dirty.missing_cell_rate = r,
+                        (Some("truncated_msisdn_rate"), Some(r)) => dirty.truncated_msisdn_rate = r,
+                        (Some("invalid_duration_rate"), Some(r)) => dirty.invalid_duration_rate = r,
+                        (Some("ground_truth"), _) => dirty.ground_truth = v.as_bool().unwrap_or(dirty.ground_truth),
+                        _ => {}
+                    }
+                }
+            }
+        }
+        "clock_skew" => {
+            if let Some(map) = value.as_mapping() {
+                let skew = &mut config.clock_skew;
+                for (k, v) in map {
+                    let pool = match k.as_str() {
+                        Some("msc") => &mut skew.msc,
+                        Some("sgsn") => &mut skew.sgsn,
+                        Some("pgw") => &mut skew.pgw,
+                        Some("ground_truth") => {
+                            skew.ground_truth = v.as_bool().unwrap_or(skew.ground_truth);
+                            continue;
+                        }
+                        _ => continue,
+                    };
+                    for (k, v) in v.as_mapping().into_iter().flatten() {
+                        match (k.as_str(), v.as_f64()) {
+                            (Some("mean_ms"), Some(ms)) => pool.mean_ms = ms,
+                            (Some("sigma_ms"), Some(ms)) => pool.sigma_ms = ms.max(0.0),
+                            (Some("drift_ms_per_hour"), Some(ms)) => pool.drift_ms_per_hour = ms,
+                            (Some("nodes"), _) => pool.nodes = v.as_u64().map_or(pool.nodes, |n| n.clamp(1, u32::MAX as u64) as u32),
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+        "contacts" => {
+            if let Some(map) = value.as_mapping() {
+                let contacts = &mut config.contacts;
+                for (k, v) in map {
+                    match (k.as_str(), v.as_f64().filter(|v| *v >= 0.0)) {
+                        (Some("avg"), Some(avg)) => contacts.avg = avg,
+                        (Some("sd_ratio"), Some(ratio)) => contacts.sd_ratio = ratio,
+                        (Some("min"), _) => contacts.min = v.as_u64().map_or(contacts.min, |n| n as usize),
+                        (Some("max"), _) => contacts.max = v.as_u64().map(|n| n as usize),
+                        _ => {}
+                    }
+                }
+            }
+        }
+        "contact_call_share" => {
+            if let Some(v) = value.as_f64() {
+                config.contact_call_share = Some(v.clamp(0.0, 1.0));
+            }
+        }
+        "call_duration_rules" => {
+            if let Some(map) = value.as_mapping() {
+                for (dispo, limits) in map {
+                    let (Some(dispo), Some(limits)) = (dispo.as_str(), limits.as_mapping()) else {
+                        continue;
+                    };
+                    let bound = |key: &str| limits.get(key).and_then(|v| v.as_u64()).and_then(|v| u32::try_from(v).ok());
+                    if let (Some(min_sec), Some(max_sec)) = (bound("min_sec"), bound("max_sec")) {
+                        if min_sec <= max_sec {
+                            config.call_duration_rules.insert(dispo.to_uppercase(), DurationRule { min_sec, max_sec });
+                        }
+                    }
+                }
+            }
+        }
+        "time_window" => {
+            if let Some(v) = value.as_str() {
+                let window = TimeWindow::parse(v).map_err(|e| anyhow::anyhow!("Config key time_window: {}", e))?;
+                config.time_window = Some(window);
+            }
+        }
+        "emit_timezone_columns" => {
+            if let Some(v) = value.as_bool() {
+                config.emit_timezone_columns = v;
+            }
+        }
+        "strict_ascii" => {
+            if let Some(v) = value.as_bool() {
+                config.strict_ascii = v;
+            }
+        }
+        "metrics" => {
+            if let Some(v) = value.as_bool() {
+                config.metrics = v;
+            }
+        }
+        "self_check" => {
+            // `self_check: true` is warn mode
+            let mode = match value.as_bool() {
+                Some(on) => Some(if on { SelfCheckMode::Warn } else { SelfCheckMode::Off }),
+                None => value.as_str().and_then(SelfCheckMode::from_str),
+            };
+            if let Some(v) = mode {
+                config.self_check = v;
+            }
+        }
+        "day_over_day" => {
+            if let Some(map) = value.as_mapping() {
+                let day_over_day = &mut config.day_over_day;
+                for (k, v) in map {
+                    match k.as_str() {
+                        Some("enabled") => day_over_day.enabled = v.as_bool().unwrap_or(day_over_day.enabled),
+                        Some("exact_max_subscribers") => {
+                            day_over_day.exact_max_subscribers = v.as_u64().map_or(day_over_day.exact_max_subscribers, |n| n as usize)
+                        }
+                        Some("bloom_fpr") => {
+                            if let Some(fpr) = v.as_f64().filter(|fpr| *fpr > 0.0 && *fpr < 1.0) {
+                                day_over_day.bloom_fpr = fpr;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+        "self_check_sample" => {
+            if let Some(v) = value.as_u64() {
+                config.self_check_sample = v.max(1);
+            }
+        }
+        "serialize_in_workers" => {
+            if let Some(v) = value.as_bool() {
+                config.serialize_in_workers = v;
+            }
+        }
+        "realtime" => {
+            if let Some(v) = value.as_bool() {
+                config.realtime = v;
+            }
+        }
+        "realtime_speedup" => {
+            if let Some(v) = value.as_f64().filter(|v| v.is_finite() && *v > 0.0) {
+                config.realtime_speedup = v;
+            }
+        }
+        "fail_writes_after_bytes" => {
+            if let Some(v) = value.as_u64() {
+                config.fail_writes_after_bytes = v;
+            }
+        }
+        "emit_prev_event_ts" => {
+            if let Some(v) = value.as_bool() {
+                config.emit_prev_event_ts = v;
+            }
+        }
+        "emit_imeisv" => {
+            if let Some(v) = value.as_bool() {
+                config.emit_imeisv = v;
+            }
+        }
+        "emit_bearer_fields" => {
+            if let Some(v) = value.as_bool() {
+                config.emit_bearer_fields = v;
+            }
+        }
+        "emit_node_id" => {
+            if let Some(v) = value.as_bool() {
+                config.emit_node_id = v;
+            }
+        }
+        "emit_record_seq" => {
+            if let Some(v) = value.as_bool() {
+                config.emit_record_seq = v;
+            }
+        }
+        "duplicate_injection_rate" => {
+            if let Some(v) = value.as_f64() {
+                config.duplicate_injection_rate = v.clamp(0.0, 1.0);
+            }
+        }
+        "late_file_rate" => {
+            if let Some(v) = value.as_f64() {
+                config.late_file_rate = v.clamp(0.0, 1.0);
+            }
+        }
+        "late_file_delay_hours" => {
+            if let Some(v) = value.as_u64() {
+                config.late_file_delay_hours = v;
+            }
+        }
+        "output_format" => {
+            if let Some(v) = value.as_str().and_then(OutputFormat::from_str) {
+                config.output_format = v;
+            }
+        }
+        "arrow_rotate_rows" => {
+            if let Some(v) = value.as_u64() {
+                config.arrow_rotate_rows = v;
+            }
+        }
+        "rotate_bytes" => {
+            if let Some(v) = value.as_u64() {
+                config.rotate_bytes = v;
+            }
+        }
+        "rotation" => {
+            if let Some(map) = value.as_mapping() {
+                for (event_type, limits) in map {
+                    let (Some(event_type), Some(limits)) = (event_type.as_str(), limits.as_mapping()) else {
+                        continue;
+                    };
+                    let mut policy = RotationPolicy::default();
+                    for (k, v) in limits {
+                        match (k.as_str(), v.as_u64()) {
+                            (Some("bytes"), Some(v)) => policy.bytes = v,
+                            (Some("rows"), Some(v)) => policy.rows = v,
+                            (Some("interval_sec"), Some(v)) => policy.interval_sec = v,
+                            _ => {}
+                        }
+                    }
+                    config.rotation.insert(event_type.to_uppercase(), policy);
+                }
+            }
+        }
+        "partition_layout" => {
+            if let Some(v) = value.as_str().and_then(PartitionLayout::from_str) {
+                config.partition_layout = v;
+            }
+        }
+        "overwrite_policy" => {
+            if let Some(v) = value.as_str().and_then(OverwritePolicy::from_str) {
+                config.overwrite_policy = v;
+            }
+        }
+        "compression_type" => {
+            if let Some(v) = value.as_str() {
+                config.compression_type = v.to_string();
+            }
+        }
+        "compression_level" => {
+            if let Some(v) = value.as_i64() {
+                config.compression_level = Some(v as i32);
+            }
+        }
+        "compression_by_target" => {
+            // `parts` / `bundle`: { type, level }; an entry without `type` is ignored
+            if let Some(map) = value.as_mapping() {
+                for (target, fields) in map {
+                    let Some(fields) = fields.as_mapping() else {
+                        continue;
+                    };
+                    let parsed = fields.get("type").and_then(|v| v.as_str()).map(|compression_type| CompressionTarget {
+                        compression_type: compression_type.to_string(),
+                        level: fields.get("level").and_then(|v| v.as_i64()).map(|v| v as i32),
+                    });
+                    match target.as_str() {
+                        Some("parts") if parsed.is_some() => config.compression_by_target.parts = parsed,
+                        Some("bundle") if parsed.is_some() => config.compression_by_target.bundle = parsed,
+                        _ => {}
+                    }
+                }
+            }
+        }
+        "miss_rate_warn_threshold" => {
+            if let Some(v) = value.as_f64() {
+                config.miss_rate_warn_threshold = v.clamp(0.0, 1.0);
+            }
+        }
+        "fail_on_miss_rate" => {
+            if let Some(v) = value.as_f64() {
+                config.fail_on_miss_rate = Some(v.clamp(0.0, 1.0));
+            }
+        }
+        "check_db" => {
+            if let Some(v) = value.as_bool() {
+                config.check_db = v;
+            }
+        }
+        "db_check_samples" => {
+            if let Some(v) = value.as_u64() {
+                config.db_check_samples = v as usize;
+            }
+        }
+        "db_check_min_hit_rate" => {
+            if let Some(v) = value.as_f64() {
+                config.db_check_min_hit_rate = v.clamp(0.0, 1.0);
+            }
+        }
+        "db_size" => {
+            if let Some(v) = value.as_u64() {
+                config.db_size = v as usize;
+            }
+        }
+        "db_history_days" => {
+            if let Some(v) = value.as_u64() {
+                config.db_history_days = v as usize;
             }
         }
         "db_device_change_rate" => {
@@ -360,6 +1819,541 @@ fn merge_config_value(config: &mut Config, key: &str, value: serde_yaml::Value)
                 config.subscriber_db_redb_path = Some(PathBuf::from(v));
             }
         }
+        "msisdn_file" => {
+            if let Some(v) = value.as_str() {
+                config.msisdn_file = Some(PathBuf::from(v));
+            }
+        }
+        "closed_user_group" => {
+            if let Some(v) = value.as_bool() {
+                config.closed_user_group = v;
+            }
+        }
+        "subscriber_db_path" => {
+            if let Some(v) = value.as_str() {
+                config.subscriber_db_path = Some(PathBuf::from(v));
+            }
+        }
+        "generate_subscriber_db" => {
+            if let Some(v) = value.as_str() {
+                config.generate_subscriber_db = Some(PathBuf::from(v));
+            }
+        }
+        "validate_db_only" => {
+            if let Some(v) = value.as_bool() {
+                config.validate_db_only = v;
+            }
+        }
+        "db_device_change_seasonality" => {
+            if let Some(v) = parse_monthly_multipliers(&value) {
+                config.db_device_change_seasonality = v;
+            }
+        }
+        "db_device_change_special_days" => {
+            if let Some(v) = parse_day_multipliers(&value) {
+                config.db_device_change_special_days = v;
+            }
+        }
+        "db_number_release_seasonality" => {
+            if let Some(v) = parse_monthly_multipliers(&value) {
+                config.db_number_release_seasonality = v;
+            }
+        }
+        "db_number_release_special_days" => {
+            if let Some(v) = parse_day_multipliers(&value) {
+                config.db_number_release_special_days = v;
+            }
+        }
         _ => {}
     }
+    Ok(())
+}
+
+/// Monthly multipliers: a list of 12 values (Jan..Dec) or a map of month (1-12) -> multiplier
+fn parse_monthly_multipliers(value: &serde_yaml::Value) -> Option<HashMap<usize, f64>> {
+    if let Some(seq) = value.as_sequence() {
+        return Some(
+            seq.iter()
+                .take(12)
+                .enumerate()
+                .filter_map(|(i, v)| v.as_f64().map(|m| (i + 1, m.max(0.0))))
+                .collect(),
+        );
+    }
+    value.as_mapping().map(|map| {
+        map.iter()
+            .filter_map(|(k, v)| Some((k.as_u64()? as usize, v.as_f64()?.max(0.0))))
+            .filter(|(month, _)| (1..=12).contains(month))
+            .collect()
+    })
+}
+
+/// Day multipliers: a map of YYYY-MM-DD -> multiplier
+fn parse_day_multipliers(value: &serde_yaml::Value) -> Option<HashMap<String, f64>> {
+    value.as_mapping().map(|map| {
+        map.iter()
+            .filter_map(|(k, v)| Some((k.as_str()?.to_string(), v.as_f64()?.max(0.0))))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_load_config_compression_keys() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "compression_type: zstd").unwrap();
+        writeln!(file, "compression_level: 9").unwrap();
+
+        let cfg = load_config(Some(file.path())).unwrap();
+        assert_eq!(cfg.compression_type, "zstd");
+        assert_eq!(cfg.compression_level, Some(9));
+    }
+
+    #[test]
+    fn test_load_config_clock_skew_block() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "clock_skew:").unwrap();
+        writeln!(file, "  msc: {{mean_ms: 1500, sigma_ms: -3, nodes: 8}}").unwrap();
+        writeln!(file, "  pgw: {{drift_ms_per_hour: 40.5}}").unwrap();
+        writeln!(file, "  ground_truth: true").unwrap();
+        let cfg = load_config(Some(file.path())).unwrap();
+        let skew = cfg.clock_skew;
+        assert_eq!((skew.msc.mean_ms, skew.msc.sigma_ms, skew.msc.nodes), (1500.0, 0.0, 8));
+        assert_eq!((skew.pgw.drift_ms_per_hour, skew.pgw.nodes), (40.5, 4));
+        assert!(!skew.sgsn.is_enabled());
+        assert!(skew.ground_truth && skew.is_enabled());
+        assert!(!Config::default().clock_skew.is_enabled());
+    }
+
+    #[test]
+    fn test_load_config_dirty_data_block() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "dirty_data:").unwrap();
+        writeln!(file, "  missing_imei_rate: 0.01").unwrap();
+        writeln!(file, "  invalid_duration_rate: 2").unwrap();
+        writeln!(file, "  ground_truth: true").unwrap();
+
+        let cfg = load_config(Some(file.path())).unwrap();
+        assert_eq!(cfg.dirty_data.missing_imei_rate, 0.01);
+        assert_eq!(cfg.dirty_data.invalid_duration_rate, 1.0);
+        assert_eq!(cfg.dirty_data.missing_cell_rate, 0.0);
+        assert!(cfg.dirty_data.ground_truth && cfg.dirty_data.is_enabled());
+        assert!(!Config::default().dirty_data.is_enabled());
+    }
+
+    #[test]
+    fn test_load_config_msisdn_format() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "msisdn_format: national").unwrap();
+        writeln!(file, "country_code: 44").unwrap();
+
+        let cfg = load_config(Some(file.path())).unwrap();
+        assert_eq!(cfg.msisdn_format, MsisdnFormat::National);
+        assert_eq!(cfg.country_code, "44");
+        assert_eq!(MsisdnFormat::from_str("E164_PLUS"), Some(MsisdnFormat::E164Plus));
+
+        let cfg = Config { country_code: "+31".to_string(), ..Config::default() };
+        assert!(validate_output_strings(&cfg).unwrap_err().to_string().contains("country_code"));
+    }
+
+    #[test]
+    fn test_load_config_record_type_profile() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "record_type_profile: custom").unwrap();
+        writeln!(file, "record_type_map:").unwrap();
+        writeln!(file, "  call_mo: MOC").unwrap();
+        writeln!(file, "  data_pgw: PGW").unwrap();
+
+        let cfg = load_config(Some(file.path())).unwrap();
+        assert_eq!(cfg.record_type_profile, RecordTypeProfile::Custom);
+        assert_eq!(cfg.record_type_map.get("call_mo").map(String::as_str), Some("MOC"));
+        assert_eq!(cfg.record_type_map.len(), 2);
+        assert_eq!(RecordTypeProfile::from_str("Ericsson"), Some(RecordTypeProfile::Ericsson));
+
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "record_type_map: {{ sms_mo: 'a;b' }}").unwrap();
+        assert!(load_config(Some(file.path())).unwrap_err().to_string().contains("record_type_map"));
+    }
+
+    #[test]
+    fn test_load_config_day_over_day() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "day_over_day:").unwrap();
+        writeln!(file, "  exact_max_subscribers: 1000").unwrap();
+        writeln!(file, "  bloom_fpr: 1.5").unwrap();
+
+        let cfg = load_config(Some(file.path())).unwrap();
+        assert!(cfg.day_over_day.enabled);
+        assert_eq!(cfg.day_over_day.exact_max_subscribers, 1000);
+        // Out of (0, 1): keeps the default
+        assert_eq!(cfg.day_over_day.bloom_fpr, 0.01);
+    }
+
+    #[test]
+    fn test_load_config_self_check() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "self_check: strict").unwrap();
+        writeln!(file, "self_check_sample: 0").unwrap();
+
+        let cfg = load_config(Some(file.path())).unwrap();
+        assert_eq!(cfg.self_check, SelfCheckMode::Strict);
+        assert_eq!(cfg.self_check_sample, 1);
+        assert_eq!(Config::default().self_check, SelfCheckMode::Off);
+
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "self_check: true").unwrap();
+        assert_eq!(load_config(Some(file.path())).unwrap().self_check, SelfCheckMode::Warn);
+    }
+
+    #[test]
+    fn test_load_config_partition_layout() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "partition_layout: hive_date_type").unwrap();
+
+        let cfg = load_config(Some(file.path())).unwrap();
+        assert_eq!(cfg.partition_layout, PartitionLayout::HiveDateType);
+        assert_eq!(Config::default().partition_layout, PartitionLayout::Flat);
+
+        let out = Path::new("out");
+        assert_eq!(PartitionLayout::Flat.day_dir(out, "2025-01-01"), out.join("2025-01-01"));
+        assert_eq!(PartitionLayout::HiveDate.day_dir(out, "2025-01-01"), out.join("date=2025-01-01"));
+        assert_eq!(PartitionLayout::HiveDateType.part_subdir(Some("CALL")), PathBuf::from("type=CALL"));
+        assert_eq!(PartitionLayout::HiveDate.part_subdir(Some("CALL")), PathBuf::new());
+    }
+
+    #[test]
+    fn test_load_config_prefix_operator_map() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "prefix_operator_map:").unwrap();
+        writeln!(file, "  31612: 20408").unwrap();
+        writeln!(file, "  \"31613\": \"20416\"").unwrap();
+
+        let cfg = load_config(Some(file.path())).unwrap();
+        assert_eq!(cfg.prefix_operator_map.get("31612").map(String::as_str), Some("20408"));
+        assert_eq!(cfg.prefix_operator_map.get("31613").map(String::as_str), Some("20416"));
+
+        // MCCMNCs outside the pool are rejected on load
+        writeln!(file, "  31614: 20499").unwrap();
+        let err = load_config(Some(file.path())).unwrap_err().to_string();
+        assert!(err.contains("prefix_operator_map") && err.contains("31614 -> 20499"), "{}", err);
+    }
+
+    #[test]
+    fn test_time_window_parsing() {
+        let window = TimeWindow::parse("13:00-14:00").unwrap();
+        assert_eq!((window.start_min, window.end_min), (780, 840));
+        assert_eq!(window.label(), "1300-1400");
+        assert_eq!(TimeWindow::parse("22:30-24:00").unwrap().label(), "2230-2400");
+        for bad in ["14:00-13:00", "13:00-13:00", "13-14", "25:00-26:00", "12:60-13:00", "13:00"] {
+            assert!(TimeWindow::parse(bad).is_err(), "{}", bad);
+        }
+
+        // A bad window in the config file or environment fails the load instead of generating the whole day
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "time_window: \"14:00-13:00\"").unwrap();
+        let err = load_config(Some(file.path())).unwrap_err().to_string();
+        assert!(err.starts_with("Config key time_window: "), "{}", err);
+        let mut cfg = Config::default();
+        let vars = [("CDRGEN_TIME_WINDOW".to_string(), "13-14".to_string())];
+        let err = apply_env_overrides(&mut cfg, vars).unwrap_err().to_string();
+        assert!(err.contains("CDRGEN_TIME_WINDOW=13-14: Config key time_window: "), "{}", err);
+        assert!(cfg.time_window.is_none());
+    }
+
+    #[test]
+    fn test_unsafe_apn_is_rejected() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "apn_mix:").unwrap();
+        writeln!(file, "  internet: 0.9").unwrap();
+        writeln!(file, "  \"evil;apn\\n\": 0.1").unwrap();
+
+        let err = load_config(Some(file.path())).unwrap_err().to_string();
+        assert!(err.starts_with("Config key apn_mix: value \"evil;apn\\n\""), "{}", err);
+
+        let cfg = Config { tz_name: "Europe/\"Amsterdam\"".to_string(), ..Config::default() };
+        assert!(validate_output_strings(&cfg).unwrap_err().to_string().contains("tz_name"));
+        assert!(validate_output_strings(&Config::default()).is_ok());
+    }
+
+    #[test]
+    fn test_load_config_db_seasonality() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "db_device_change_seasonality: [1, 1, 1, 1, 1, 1, 1, 1, 2.5, 1, 1, 3]").unwrap();
+        writeln!(file, "db_device_change_special_days:").unwrap();
+        writeln!(file, "  \"2024-09-20\": 4.0").unwrap();
+        writeln!(file, "db_number_release_seasonality:").unwrap();
+        writeln!(file, "  1: 1.5").unwrap();
+
+        let cfg = load_config(Some(file.path())).unwrap();
+        assert_eq!(cfg.db_device_change_seasonality.len(), 12);
+        assert_eq!(cfg.db_device_change_seasonality[&9], 2.5);
+        assert_eq!(cfg.db_device_change_seasonality[&12], 3.0);
+        assert_eq!(cfg.db_device_change_special_days["2024-09-20"], 4.0);
+        assert_eq!(cfg.db_number_release_seasonality[&1], 1.5);
+        assert!(cfg.db_number_release_special_days.is_empty());
+    }
+
+    #[test]
+    fn test_rotation_from_yaml() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "rotate_bytes: 1000").unwrap();
+        writeln!(file, "rotation:").unwrap();
+        writeln!(file, "  call: {{ bytes: 50, rows: 10 }}").unwrap();
+        writeln!(file, "  DATA: {{ interval_sec: 60 }}").unwrap();
+
+        let cfg = load_config(Some(file.path())).unwrap();
+        assert_eq!(cfg.rotation.len(), 2);
+        assert_eq!(cfg.rotation["CALL"], RotationPolicy { bytes: 50, rows: 10, interval_sec: 0 });
+        assert_eq!(cfg.rotation["DATA"], RotationPolicy { bytes: 0, rows: 0, interval_sec: 60 });
+    }
+
+    #[test]
+    fn test_call_duration_rules_from_yaml() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "call_duration_rules:").unwrap();
+        writeln!(file, "  busy: {{ min_sec: 0, max_sec: 3 }}").unwrap();
+        writeln!(file, "  FAILED: {{ min_sec: 1, max_sec: 2 }}").unwrap();
+        writeln!(file, "  CONGESTION: {{ min_sec: 9, max_sec: 1 }}").unwrap();
+
+        let cfg = load_config(Some(file.path())).unwrap();
+        assert_eq!(cfg.call_duration_rules["BUSY"], DurationRule { min_sec: 0, max_sec: 3 });
+        assert_eq!(cfg.call_duration_rules["FAILED"], DurationRule { min_sec: 1, max_sec: 2 });
+        // An empty range is ignored and the default stays
+        assert_eq!(cfg.call_duration_rules["CONGESTION"], DurationRule::default());
+        assert_eq!(cfg.call_duration_rules["NO ANSWER"], DurationRule { min_sec: 5, max_sec: 30 });
+    }
+
+    #[test]
+    fn test_sms_retry_schedule_from_yaml() {
+        let load = |yaml: &str| {
+            let mut file = NamedTempFile::new().unwrap();
+            writeln!(file, "{}", yaml).unwrap();
+            load_config(Some(file.path())).unwrap()
+        };
+        let cfg = load("sms_unreachable_rate: 1.5\nsms_retry_schedule_min: [0, 10, 60]");
+        assert_eq!(cfg.sms_unreachable_rate, 1.0);
+        assert_eq!(cfg.sms_retry_schedule_min, [0, 10, 60]);
+        // A schedule without a retry or out of order is ignored
+        assert_eq!(load("sms_retry_schedule_min: [0]").sms_retry_schedule_min, [0, 5, 30, 120]);
+        assert_eq!(load("sms_retry_schedule_min: [0, 30, 5]").sms_retry_schedule_min, [0, 5, 30, 120]);
+    }
+
+    #[test]
+    fn test_apn_profiles_from_yaml() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "apn_profiles:").unwrap();
+        writeln!(file, "  ims: {{ bytes_mean: 5000, bytes_sd: 1000, max_bytes: 20000, dur_mean: 600, dur_sd: 60 }}").unwrap();
+        writeln!(file, "  iot: {{ bytes_mean: 500, bytes_sd: 100 }}").unwrap();
+
+        let cfg = load_config(Some(file.path())).unwrap();
+        assert_eq!(
+            cfg.apn_profiles["ims"],
+            ApnProfile { bytes_mean: 5000.0, bytes_sd: 1000.0, max_bytes: 20_000, dur_mean: 600.0, dur_sd: 60.0 }
+        );
+        // Incomplete entries are ignored, other defaults stay
+        assert!(!cfg.apn_profiles.contains_key("iot"));
+        assert_eq!(cfg.apn_profiles["mms"], default_apn_profiles()["mms"]);
+    }
+
+    #[test]
+    fn test_qci_tables_from_yaml_are_validated() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "qci_by_apn:").unwrap();
+        writeln!(file, "  ims: {{ 1: 0.5, 5: 0.5 }}").unwrap();
+        writeln!(file, "nr_5qi_by_apn:").unwrap();
+        writeln!(file, "  internet: {{ 80: 1.0 }}").unwrap();
+        let cfg = load_config(Some(file.path())).unwrap();
+        assert_eq!(cfg.qci_by_apn["ims"], BTreeMap::from([(1, 0.5), (5, 0.5)]));
+        assert_eq!(cfg.qci_by_apn["internet"], default_qci_by_apn()["internet"]);
+        assert_eq!(cfg.nr_5qi_by_apn["internet"], BTreeMap::from([(80, 1.0)]));
+
+        // 5QIs above 9 are NR only
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "qci_by_apn:").unwrap();
+        writeln!(file, "  internet: {{ 80: 1.0 }}").unwrap();
+        let err = load_config(Some(file.path())).unwrap_err().to_string();
+        assert_eq!(err, "Config key qci_by_apn: APN internet has QCI 80, expected 1..=9");
+
+        let cfg = Config { nr_5qi_by_apn: BTreeMap::from([("ims".to_string(), BTreeMap::from([(5, 0.0)]))]), ..Config::default() };
+        assert!(validate_qci_tables(&cfg).unwrap_err().to_string().starts_with("Config key nr_5qi_by_apn: APN ims needs"));
+        let cfg = Config { nr_5qi_by_apn: BTreeMap::from([("ims".to_string(), BTreeMap::from([(256, 1.0)]))]), ..Config::default() };
+        assert!(validate_qci_tables(&cfg).is_err());
+        assert!(validate_qci_tables(&Config::default()).is_ok());
+    }
+
+    #[test]
+    fn test_event_pool_size_has_a_minimum() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "event_pool_size: 4000\nbatch_size_bytes: 1048576").unwrap();
+        let err = load_config(Some(file.path())).unwrap_err().to_string();
+        assert_eq!(err, "Config key event_pool_size: 4000 is below the minimum of 4559 rows, a batch of batch_size_bytes 1048576");
+        // The minimum follows the batch size, and the defaults hold a full batch
+        assert!(validate_event_pool_size(&Config { event_pool_size: 4559, batch_size_bytes: 1_048_576, ..Config::default() }).is_ok());
+        assert!(validate_event_pool_size(&Config { event_pool_size: 1, batch_size_bytes: 100, ..Config::default() }).is_ok());
+        assert!(validate_event_pool_size(&Config::default()).is_ok());
+    }
+
+    #[test]
+    fn test_cells_must_cover_weighted_rats() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "cells: 2\nrat_mix: {{LTE: 0.8, NR: 0.1, WCDMA: 0.1, GSM: 0.0}}").unwrap();
+        let err = load_config(Some(file.path())).unwrap_err().to_string();
+        assert!(err.contains("Config key cells: 2 cells cannot give each of the 3 RATs"), "{}", err);
+        // RATs weighted zero need no cell, and no catalog at all is fine
+        let mix = BTreeMap::from([("LTE".to_string(), 1.0), ("NR".to_string(), 0.0)]);
+        assert!(validate_rat_mix(&Config { cells: 1, rat_mix: mix.clone(), ..Config::default() }).is_ok());
+        assert!(validate_rat_mix(&Config { cells: 0, ..Config::default() }).is_ok());
+        let err = validate_rat_mix(&Config { rat_mix: BTreeMap::from([("6G".to_string(), 1.0)]), ..Config::default() }).unwrap_err();
+        assert!(err.to_string().starts_with("Config key rat_mix: Unknown RAT"), "{}", err);
+    }
+
+    #[test]
+    fn test_mccmnc_pool_entries_are_five_or_six_digits() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "mccmnc_pool: [\"20408\", \"310012\"]").unwrap();
+        assert_eq!(load_config(Some(file.path())).unwrap().mccmnc_pool, ["20408", "310012"]);
+
+        for bad in ["2040", "3100120", "02040"] {
+            let cfg = Config { mccmnc_pool: vec!["20408".to_string(), bad.to_string()], ..Config::default() };
+            let err = validate_mccmnc_pool(&cfg).unwrap_err().to_string();
+            assert!(err.starts_with(&format!("Config key mccmnc_pool: {:?} is not a 5- or 6-digit MCCMNC", bad)), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_compression_by_target_from_yaml() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "compression_type: zstd").unwrap();
+        writeln!(file, "compression_by_target:").unwrap();
+        writeln!(file, "  bundle: {{ type: zstd, level: 19 }}").unwrap();
+        writeln!(file, "  parts: {{ type: gzip }}").unwrap();
+
+        let cfg = load_config(Some(file.path())).unwrap();
+        assert_eq!(cfg.part_compression(), ("gzip", None));
+        assert_eq!(cfg.bundle_compression(), ("zstd", Some(19)));
+
+        // Without targets both follow compression_type / compression_level
+        let cfg = Config { compression_level: Some(4), ..Config::default() };
+        assert_eq!(cfg.part_compression(), ("gzip", Some(4)));
+        assert_eq!(cfg.bundle_compression(), ("gzip", Some(4)));
+    }
+
+    #[test]
+    fn test_contacts_from_yaml() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "contacts: {{ avg: 12, sd_ratio: 0.5, min: 2, max: 40 }}").unwrap();
+        writeln!(file, "contact_call_share: 1.5").unwrap();
+
+        let cfg = load_config(Some(file.path())).unwrap();
+        assert_eq!(cfg.contacts, ContactsConfig { avg: 12.0, sd_ratio: 0.5, min: 2, max: Some(40) });
+        assert_eq!(cfg.contact_call_share, Some(1.0));
+        assert_eq!(Config::default().contacts, ContactsConfig { avg: 30.0, sd_ratio: 0.3, min: 0, max: None });
+    }
+
+    #[test]
+    fn test_daily_targets_from_yaml() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "daily_targets:").unwrap();
+        writeln!(file, "  calls: 50000").unwrap();
+        writeln!(file, "  sms: [{}]", vec!["100"; 24].join(", ")).unwrap();
+        writeln!(file, "  data_sessions: [1, 2, 3]").unwrap();
+
+        let cfg = load_config(Some(file.path())).unwrap();
+        assert_eq!(cfg.daily_targets.calls, Some(VolumeTarget::Daily(50000.0)));
+        assert_eq!(cfg.daily_targets.sms.as_ref().map(VolumeTarget::total), Some(2400.0));
+        // Not one value per hour
+        assert_eq!(cfg.daily_targets.data_sessions, None);
+        assert!(cfg.daily_targets.is_enabled() && !Config::default().daily_targets.is_enabled());
+    }
+
+    #[test]
+    fn test_user_segments_from_yaml() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "user_segments:").unwrap();
+        writeln!(file, "  heavy: {{ share: 0.2, activity: 3.0, plans: [unlimited] }}").unwrap();
+        writeln!(file, "  light: {{ share: 0.8, plans: [basic, basic_plus] }}").unwrap();
+        writeln!(file, "  broken: {{ activity: 2.0, plans: [basic] }}").unwrap();
+
+        let cfg = load_config(Some(file.path())).unwrap();
+        assert_eq!(cfg.user_segments.keys().collect::<Vec<_>>(), ["heavy", "light"]);
+        assert_eq!(
+            cfg.user_segments["heavy"],
+            SegmentProfile { share: 0.2, activity: 3.0, plans: vec!["unlimited".to_string()] }
+        );
+        assert_eq!(cfg.user_segments["light"].activity, 1.0);
+    }
+
+    fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_env_overrides_after_yaml() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "workers: 2").unwrap();
+        writeln!(file, "rotate_bytes: 1000").unwrap();
+        writeln!(file, "compression_type: zstd").unwrap();
+
+        let mut cfg = load_config(Some(file.path())).unwrap();
+        apply_env_overrides(
+            &mut cfg,
+            env(&[
+                ("CDRGEN_WORKERS", "8"),
+                ("CDRGEN_ROTATE_BYTES", "5000"),
+                ("CDRGEN_COMPRESSION_TYPE", "none"),
+                ("CDRGEN_COMPRESSION_LEVEL", "4"),
+                ("CDRGEN_TZ_NAME", "UTC"),
+                ("CDRGEN_MO_SHARE_CALL", "1"),
+                ("PATH", "/usr/bin"),
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(cfg.workers, 8);
+        assert_eq!(cfg.rotate_bytes, 5000);
+        assert_eq!(cfg.compression_type, "none");
+        assert_eq!(cfg.compression_level, Some(4));
+        assert_eq!(cfg.tz_name, "UTC");
+        assert_eq!(cfg.mo_share_call, 1.0);
+    }
+
+    #[test]
+    fn test_env_override_errors_per_variable() {
+        let mut cfg = Config::default();
+        let err = apply_env_overrides(
+            &mut cfg,
+            env(&[
+                ("CDRGEN_WORKERS", "many"),
+                ("CDRGEN_ROTATE_BYTES", "-1"),
+                ("CDRGEN_VERBOSE", "yes please"),
+                ("CDRGEN_NO_SUCH_KEY", "1"),
+                ("CDRGEN_PREFIXES", "31612"),
+                ("CDRGEN_COMPRESSION_TYPE", "zstd"),
+                ("CDRGEN_MAX_CORES", "4"),
+            ]),
+        )
+        .unwrap_err()
+        .to_string();
+
+        assert!(err.contains("CDRGEN_WORKERS=many: expected an unsigned integer"), "{}", err);
+        assert!(err.contains("CDRGEN_ROTATE_BYTES=-1: expected an unsigned integer"), "{}", err);
+        assert!(err.contains("CDRGEN_VERBOSE=yes please: expected true or false"), "{}", err);
+        assert!(err.contains("CDRGEN_NO_SUCH_KEY=1: unknown config key"), "{}", err);
+        assert!(err.contains("CDRGEN_PREFIXES=31612: only scalar keys"), "{}", err);
+        assert!(!err.contains("CDRGEN_COMPRESSION_TYPE"), "{}", err);
+        // Read by core detection, not a config key
+        assert!(!err.contains("CDRGEN_MAX_CORES"), "{}", err);
+    }
+
+    #[test]
+    fn test_load_config_defaults() {
+        let cfg = load_config(None).unwrap();
+        assert_eq!(cfg.compression_type, "gzip");
+        assert_eq!(cfg.compression_level, None);
+    }
 }
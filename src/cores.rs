@@ -0,0 +1,85 @@
+// Core count behind auto-detected workers and compression threads: the cores the process may actually use
+// (cgroup CPU quota and affinity mask, as in a Kubernetes pod), capped by CDRGEN_MAX_CORES
+use tracing::warn;
+
+/// Caps the detected core count (`CDRGEN_MAX_CORES=4`), for limits the quota does not show
+pub const MAX_CORES_ENV: &str = "CDRGEN_MAX_CORES";
+
+/// Cores available to this process, at least 1
+pub fn available_cores() -> usize {
+    let detected = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let max_cores = std::env::var(MAX_CORES_ENV).ok();
+    capped_cores(detected, max_cores.as_deref())
+}
+
+/// `detected` cores capped by a `CDRGEN_MAX_CORES` value; an unparsable or zero cap is ignored
+fn capped_cores(detected: usize, max_cores: Option<&str>) -> usize {
+    let detected = detected.max(1);
+    match max_cores.map(|raw| (raw, raw.trim().parse::<usize>())) {
+        None => detected,
+        Some((_, Ok(max))) if max > 0 => detected.min(max),
+        Some((raw, _)) => {
+            warn!("Ignoring {}={:?}: expected a positive integer", MAX_CORES_ENV, raw);
+            detected
+        }
+    }
+}
+
+/// Worker count of a `--workers` value: a number (0 = all available cores) or a share of the available
+/// cores such as `50%`, rounded down but at least 1
+pub fn parse_workers(spec: &str) -> anyhow::Result<usize> {
+    workers_of(spec, available_cores())
+}
+
+fn workers_of(spec: &str, cores: usize) -> anyhow::Result<usize> {
+    let spec = spec.trim();
+    match spec.strip_suffix('%') {
+        Some(percent) => {
+            let percent: f64 = percent
+                .trim()
+                .parse()
+                .ok()
+                .filter(|p: &f64| p.is_finite() && *p > 0.0)
+                .ok_or_else(|| anyhow::anyhow!("--workers percentage must be a positive number, got {:?}", spec))?;
+            Ok(((cores as f64 * percent / 100.0).floor() as usize).max(1))
+        }
+        None => {
+            let workers: usize = spec
+                .parse()
+                .map_err(|_| anyhow::anyhow!("--workers must be a number or a percentage like 50%, got {:?}", spec))?;
+            Ok(if workers == 0 { cores } else { workers })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workers_numbers_and_percentages() {
+        assert_eq!(workers_of("6", 16).unwrap(), 6);
+        assert_eq!(workers_of("0", 16).unwrap(), 16);
+        assert_eq!(workers_of("50%", 16).unwrap(), 8);
+        assert_eq!(workers_of(" 25 %", 10).unwrap(), 2);
+        assert_eq!(workers_of("100%", 3).unwrap(), 3);
+        assert_eq!(workers_of("150%", 4).unwrap(), 6);
+        // Never below one worker
+        assert_eq!(workers_of("10%", 2).unwrap(), 1);
+
+        for bad in ["", "%", "-50%", "0%", "half", "1.5", "nan%"] {
+            assert!(workers_of(bad, 8).is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn test_max_cores_caps_detected_cores() {
+        assert_eq!(capped_cores(64, None), 64);
+        assert_eq!(capped_cores(64, Some("4")), 4);
+        assert_eq!(capped_cores(2, Some("8")), 2);
+        assert_eq!(capped_cores(0, None), 1);
+        // Unusable caps are ignored
+        assert_eq!(capped_cores(64, Some("0")), 64);
+        assert_eq!(capped_cores(64, Some("lots")), 64);
+    }
+}
@@ -0,0 +1,93 @@
+// Volume-driven generation: per-user means from the `daily_targets` day totals, and the comparison of each
+// generated day's totals with its targets in summary.json
+use crate::config::{Config, VolumeTarget};
+use crate::estimate::{call_rows_per_call, mean_segment_activity};
+use crate::generators::{placement_shapes, placement_window_shaped};
+use crate::utils::DailySummary;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// `daily_targets` entries as (summary.json key, target) in CALL, SMS, DATA order
+fn targets(cfg: &Config) -> [(&'static str, Option<&VolumeTarget>); 3] {
+    let targets = &cfg.daily_targets;
+    [
+        ("calls", targets.calls.as_ref()),
+        ("sms", targets.sms.as_ref()),
+        ("data_sessions", targets.data_sessions.as_ref()),
+    ]
+}
+
+/// Per-user CALL, SMS and DATA means of a whole day, before segment activity: `avg_*_per_user`, or a type's
+/// target spread over the expected active subscribers of a run over `population` subscribers. Inactive
+/// subscribers, the mean segment activity and the MT legs and callbacks a call writes are taken out,
+/// so the day totals about the target
+pub fn per_user_means(cfg: &Config, population: usize) -> [f64; 3] {
+    let active = population as f64 * (1.0 - cfg.daily_inactive_rate.clamp(0.0, 1.0)) * mean_segment_activity(cfg);
+    let defaults = [cfg.avg_calls_per_user, cfg.avg_sms_per_user, cfg.avg_data_sessions_per_user];
+    let rows_per_event = [call_rows_per_call(cfg), 1.0, 1.0];
+    let mut means = defaults;
+    for (i, (_, target)) in targets(cfg).into_iter().enumerate() {
+        if let Some(target) = target {
+            means[i] = if active > 0.0 { target.total() / (active * rows_per_event[i]) } else { 0.0 };
+        }
+    }
+    means
+}
+
+/// A day's total of one event type against its target
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TargetDeviation {
+    /// Target of the generated part of the day: the `time_window` share, and 1 in `sample_stride` subscribers
+    pub target: f64,
+    pub achieved: usize,
+    /// (achieved - target) / target in percent; 0 for a zero target
+    pub deviation_pct: f64,
+}
+
+/// Totals of `summary` against the targets of `date` in the placement zone `tz`, by `daily_targets` key
+pub fn target_deviations(
+    cfg: &Config,
+    tz: chrono_tz::Tz,
+    date: NaiveDate,
+    summary: &DailySummary,
+) -> anyhow::Result<BTreeMap<String, TargetDeviation>> {
+    let achieved = [summary.total_calls, summary.total_sms, summary.total_data];
+    let mut deviations = BTreeMap::new();
+    for (i, ((key, target), shape)) in targets(cfg).into_iter().zip(placement_shapes(cfg)).enumerate() {
+        let Some(target) = target else {
+            continue;
+        };
+        let (_, _, share) = placement_window_shaped(cfg, tz, date, shape)?;
+        let target = target.total() * share / cfg.sample_stride.max(1) as f64;
+        let deviation_pct = if target > 0.0 { (achieved[i] as f64 - target) / target * 100.0 } else { 0.0 };
+        deviations.insert(key.to_string(), TargetDeviation { target, achieved: achieved[i], deviation_pct });
+    }
+    Ok(deviations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DailyTargets;
+
+    #[test]
+    fn test_means_spread_targets_over_active_subscribers() {
+        let cfg = Config {
+            daily_inactive_rate: 0.2,
+            contact_call_share: Some(0.5),
+            daily_targets: DailyTargets {
+                calls: Some(VolumeTarget::Daily(12_000.0)),
+                sms: Some(VolumeTarget::Hourly(vec![100.0; 24])),
+                data_sessions: None,
+            },
+            ..Config::default()
+        };
+        let [calls, sms, data] = per_user_means(&cfg, 1000);
+        // 800 active subscribers; half of the calls go to a contact and write an MT leg
+        assert!((calls - 12_000.0 / (800.0 * 1.5)).abs() < 1e-9, "{}", calls);
+        assert!((sms - 2400.0 / 800.0).abs() < 1e-9, "{}", sms);
+        assert_eq!(data, cfg.avg_data_sessions_per_user);
+        assert_eq!(per_user_means(&cfg, 0)[1], 0.0);
+    }
+}
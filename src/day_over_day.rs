@@ -0,0 +1,294 @@
+// Day-over-day overlap of the MSISDNs that originate rows (MO CALL/SMS and DATA): how many were active the
+// day before too, how many are new and how many went quiet. Exact sets for small runs, a Bloom filter above
+use crate::config::DayOverDayConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::f64::consts::LN_2;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// `day_over_day` of summary.json: the day's active MSISDNs against the day generated before it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DayOverDay {
+    /// Active on both days
+    pub repeat: usize,
+    /// Active on this day only
+    pub new: usize,
+    /// Active on the previous day only
+    pub gone: usize,
+    /// Counted with Bloom filters: false positives make `repeat` slightly high and `new` slightly low
+    #[serde(default)]
+    pub approximate: bool,
+}
+
+/// SplitMix64 finalizer
+fn mix(key: u64) -> u64 {
+    let mut z = key.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Bloom filter of MSISDNs that the workers of a day insert into concurrently
+#[derive(Debug)]
+pub struct BloomFilter {
+    bits: Vec<AtomicU64>,
+    hashes: u64,
+}
+
+impl BloomFilter {
+    /// Sized for `items` keys at false-positive rate `fpr`
+    pub fn new(items: usize, fpr: f64) -> Self {
+        let n = items.max(1) as f64;
+        let bits = (-n * fpr.clamp(1e-9, 0.5).ln() / (LN_2 * LN_2)).ceil().max(64.0) as usize;
+        let hashes = (bits as f64 / n * LN_2).round().clamp(1.0, 32.0) as u64;
+        BloomFilter {
+            bits: (0..bits.div_ceil(64)).map(|_| AtomicU64::new(0)).collect(),
+            hashes,
+        }
+    }
+
+    /// Bit positions of `key`, by double hashing
+    fn positions(&self, key: u64) -> impl Iterator<Item = usize> {
+        let (h1, h2) = (mix(key), mix(!key) | 1);
+        let m = self.bits.len() as u64 * 64;
+        (0..self.hashes).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % m) as usize)
+    }
+
+    /// Add `key`; true when it was not in the filter yet
+    pub fn insert(&self, key: u64) -> bool {
+        let mut added = false;
+        for pos in self.positions(key) {
+            let mask = 1u64 << (pos % 64);
+            added |= (self.bits[pos / 64].fetch_or(mask, Ordering::Relaxed) & mask) == 0;
+        }
+        added
+    }
+
+    pub fn contains(&self, key: u64) -> bool {
+        self.positions(key)
+            .all(|pos| (self.bits[pos / 64].load(Ordering::Relaxed) & (1u64 << (pos % 64))) != 0)
+    }
+
+    /// Memory held by the bit array
+    pub fn size_bytes(&self) -> usize {
+        self.bits.len() * 8
+    }
+}
+
+/// MSISDNs active on one day
+#[derive(Debug)]
+pub enum DaySet {
+    Exact(HashSet<u64>),
+    Bloom(Arc<BloomFilter>),
+}
+
+impl DaySet {
+    pub fn contains(&self, msisdn: u64) -> bool {
+        match self {
+            DaySet::Exact(set) => set.contains(&msisdn),
+            DaySet::Bloom(bloom) => bloom.contains(msisdn),
+        }
+    }
+}
+
+/// What the workers of one day record into, with the previous day's set to compare against
+#[derive(Debug, Clone, Default)]
+pub struct DayActivity {
+    enabled: bool,
+    previous: Option<Arc<DaySet>>,
+    // Bloom mode: the day's filter, shared by all workers
+    today: Option<Arc<BloomFilter>>,
+}
+
+impl DayActivity {
+    /// Records nothing
+    pub fn off() -> Self {
+        DayActivity::default()
+    }
+
+    /// Recorder for one worker
+    pub fn shard(&self) -> ShardActivity {
+        ShardActivity {
+            day: self.clone(),
+            ..ShardActivity::default()
+        }
+    }
+}
+
+/// One worker's recorder. A subscriber is served by one worker only, so the Bloom counts of the workers add up
+#[derive(Debug, Clone, Default)]
+pub struct ShardActivity {
+    day: DayActivity,
+    // Exact mode
+    msisdns: HashSet<u64>,
+    // Bloom mode
+    active: usize,
+    repeat: usize,
+}
+
+impl ShardActivity {
+    /// `msisdn` originated a row
+    pub fn record(&mut self, msisdn: u64) {
+        if !self.day.enabled {
+            return;
+        }
+        match &self.day.today {
+            Some(bloom) => {
+                if bloom.insert(msisdn) {
+                    self.active += 1;
+                    if self.day.previous.as_ref().is_some_and(|previous| previous.contains(msisdn)) {
+                        self.repeat += 1;
+                    }
+                }
+            }
+            None => {
+                self.msisdns.insert(msisdn);
+            }
+        }
+    }
+}
+
+/// Run-long state: the set of the last generated day and how many MSISDNs it holds
+#[derive(Debug)]
+pub struct DayOverDayTracker {
+    cfg: DayOverDayConfig,
+    population: usize,
+    previous: Option<(Arc<DaySet>, usize)>,
+}
+
+impl DayOverDayTracker {
+    /// `population`: subscribers split across the workers, which sizes the Bloom filter
+    pub fn new(cfg: DayOverDayConfig, population: usize) -> Self {
+        DayOverDayTracker {
+            cfg,
+            population,
+            previous: None,
+        }
+    }
+
+    /// Exact sets up to `exact_max_subscribers`, Bloom filters above
+    pub fn is_exact(&self) -> bool {
+        self.population <= self.cfg.exact_max_subscribers
+    }
+
+    pub fn begin_day(&self) -> DayActivity {
+        if !self.cfg.enabled {
+            return DayActivity::off();
+        }
+        DayActivity {
+            enabled: true,
+            previous: self.previous.as_ref().map(|(set, _)| set.clone()),
+            today: (!self.is_exact()).then(|| Arc::new(BloomFilter::new(self.population, self.cfg.bloom_fpr))),
+        }
+    }
+
+    /// Combine the workers' recorders into the day's set, which becomes the previous day of the next call.
+    /// Returns the overlap with the previous day; None on the first day or when disabled
+    pub fn end_day(&mut self, day: DayActivity, shards: impl IntoIterator<Item = ShardActivity>) -> Option<DayOverDay> {
+        if !day.enabled {
+            return None;
+        }
+        let (today, active, repeat) = match day.today {
+            Some(bloom) => {
+                let (active, repeat) = shards.into_iter().fold((0, 0), |(a, r), shard| (a + shard.active, r + shard.repeat));
+                (DaySet::Bloom(bloom), active, repeat)
+            }
+            None => {
+                let mut msisdns = HashSet::new();
+                for shard in shards {
+                    msisdns.extend(shard.msisdns);
+                }
+                let repeat = match day.previous.as_deref() {
+                    Some(previous) => msisdns.iter().filter(|&&msisdn| previous.contains(msisdn)).count(),
+                    None => 0,
+                };
+                let active = msisdns.len();
+                (DaySet::Exact(msisdns), active, repeat)
+            }
+        };
+        let approximate = matches!(today, DaySet::Bloom(_));
+        let overlap = self.previous.as_ref().map(|&(_, previous_active)| DayOverDay {
+            repeat,
+            new: active - repeat,
+            gone: previous_active.saturating_sub(repeat),
+            approximate,
+        });
+        self.previous = Some((Arc::new(today), active));
+        overlap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_day(tracker: &mut DayOverDayTracker, shards: &[&[u64]]) -> Option<DayOverDay> {
+        let day = tracker.begin_day();
+        let recorders: Vec<ShardActivity> = shards
+            .iter()
+            .map(|msisdns| {
+                let mut shard = day.shard();
+                // Repeats within a day count once
+                for &msisdn in msisdns.iter().chain(msisdns.iter()) {
+                    shard.record(msisdn);
+                }
+                shard
+            })
+            .collect();
+        tracker.end_day(day, recorders)
+    }
+
+    #[test]
+    fn test_exact_overlap() {
+        let mut tracker = DayOverDayTracker::new(DayOverDayConfig::default(), 10);
+        assert!(tracker.is_exact());
+        assert_eq!(run_day(&mut tracker, &[&[1, 2, 3], &[4, 5]]), None);
+        let overlap = run_day(&mut tracker, &[&[1, 2], &[5, 6, 7]]).unwrap();
+        assert_eq!(overlap, DayOverDay { repeat: 3, new: 2, gone: 2, approximate: false });
+        // Compared with the day just before only
+        let overlap = run_day(&mut tracker, &[&[], &[3]]).unwrap();
+        assert_eq!(overlap, DayOverDay { repeat: 0, new: 1, gone: 5, approximate: false });
+    }
+
+    #[test]
+    fn test_bloom_overlap_is_close() {
+        let cfg = DayOverDayConfig {
+            exact_max_subscribers: 0,
+            bloom_fpr: 0.001,
+            ..DayOverDayConfig::default()
+        };
+        let mut tracker = DayOverDayTracker::new(cfg, 20_000);
+        assert!(!tracker.is_exact());
+        let day1: Vec<u64> = (0..10_000).collect();
+        let day2: Vec<u64> = (5_000..15_000).collect();
+        run_day(&mut tracker, &[&day1[..5_000], &day1[5_000..]]);
+        let overlap = run_day(&mut tracker, &[&day2[..5_000], &day2[5_000..]]).unwrap();
+        assert!(overlap.approximate);
+        assert!(overlap.repeat.abs_diff(5_000) <= 50, "{:?}", overlap);
+        assert!(overlap.new.abs_diff(5_000) <= 50, "{:?}", overlap);
+        assert!(overlap.gone.abs_diff(5_000) <= 50, "{:?}", overlap);
+    }
+
+    #[test]
+    fn test_bloom_size_follows_fpr() {
+        // ~9.6 bits per key at 1%
+        let bloom = BloomFilter::new(1_000_000, 0.01);
+        assert!((1_150_000..1_250_000).contains(&bloom.size_bytes()), "{}", bloom.size_bytes());
+        assert!(bloom.insert(42) && !bloom.insert(42));
+        assert!(bloom.contains(42));
+        let false_positives = (1_000..101_000u64).filter(|&key| bloom.contains(key)).count();
+        assert_eq!(false_positives, 0);
+    }
+
+    #[test]
+    fn test_disabled_records_nothing() {
+        let cfg = DayOverDayConfig {
+            enabled: false,
+            ..DayOverDayConfig::default()
+        };
+        let mut tracker = DayOverDayTracker::new(cfg, 10);
+        assert_eq!(run_day(&mut tracker, &[&[1]]), None);
+        assert_eq!(run_day(&mut tracker, &[&[1]]), None);
+    }
+}
@@ -0,0 +1,94 @@
+// Startup consistency check of a redb subscriber database (`check_db`): workers look subscribers up by
+// index-derived MSISDNs, so a database written with other prefixes or number widths misses most of them.
+// A sample of those MSISDNs is looked up before generating, and the misses are shown with the nearest keys
+#[cfg(feature = "redb")]
+use crate::generators::WorkerContext;
+#[cfg(feature = "redb")]
+use crate::subscriber_db_redb::SubscriberDbRedb;
+use std::fmt;
+
+/// Misses listed in the report
+pub const REPORTED_MISSES: usize = 5;
+
+/// Lookups of sampled index-derived MSISDNs in the database
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DbCheckReport {
+    pub sampled: usize,
+    pub hits: usize,
+    /// The first `REPORTED_MISSES` misses as (looked-up MSISDN, nearest database key)
+    pub misses: Vec<(u64, Option<u64>)>,
+}
+
+impl DbCheckReport {
+    /// Share of sampled MSISDNs found; 1 when nothing was sampled
+    pub fn hit_rate(&self) -> f64 {
+        if self.sampled == 0 {
+            1.0
+        } else {
+            self.hits as f64 / self.sampled as f64
+        }
+    }
+}
+
+impl fmt::Display for DbCheckReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} of {} sampled MSISDNs found ({:.1}%)", self.hits, self.sampled, self.hit_rate() * 100.0)?;
+        for (msisdn, nearest) in &self.misses {
+            match nearest {
+                Some(key) => write!(f, "; {} missing, nearest key {}", msisdn, key)?,
+                None => write!(f, "; {} missing, database is empty", msisdn)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Indices of up to `samples` subscribers of `range`, spread evenly over it
+#[cfg(any(feature = "redb", test))]
+fn sample_indices((start, end): (usize, usize), samples: usize) -> impl Iterator<Item = usize> {
+    let len = end.saturating_sub(start);
+    let samples = samples.min(len);
+    (0..samples).map(move |i| start + i * len / samples)
+}
+
+/// Look up `samples` index-derived MSISDNs of every worker range in `db`
+#[cfg(feature = "redb")]
+pub fn check_db_consistency(db: &SubscriberDbRedb, workers: &[WorkerContext], samples: usize) -> anyhow::Result<DbCheckReport> {
+    let mut report = DbCheckReport::default();
+    for ctx in workers {
+        for idx in sample_indices(ctx.users_range(), samples) {
+            let msisdn = ctx.index_msisdn(idx);
+            report.sampled += 1;
+            if db.contains_msisdn(msisdn)? {
+                report.hits += 1;
+            } else if report.misses.len() < REPORTED_MISSES {
+                report.misses.push((msisdn, db.get_nearest_key(msisdn)?));
+            }
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_samples_spread_over_the_range() {
+        assert_eq!(sample_indices((10, 20), 5).collect::<Vec<_>>(), vec![10, 12, 14, 16, 18]);
+        assert_eq!(sample_indices((10, 13), 5).collect::<Vec<_>>(), vec![10, 11, 12]);
+        assert_eq!(sample_indices((4, 4), 5).count(), 0);
+        assert_eq!(sample_indices((0, 100), 0).count(), 0);
+    }
+
+    #[test]
+    fn test_report_lists_misses() {
+        let report = DbCheckReport { sampled: 4, hits: 1, misses: vec![(31613000001, Some(31612000001)), (31613000002, None)] };
+        assert_eq!(report.hit_rate(), 0.25);
+        assert_eq!(
+            report.to_string(),
+            "1 of 4 sampled MSISDNs found (25.0%); 31613000001 missing, nearest key 31612000001; 31613000002 missing, database is empty"
+        );
+        assert_eq!(DbCheckReport::default().hit_rate(), 1.0);
+    }
+}
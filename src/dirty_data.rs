@@ -0,0 +1,268 @@
+// Dirty-data injection: deliberately broken fields for testing ingestion data-quality rules
+use crate::async_writer::EventBatch;
+use crate::clock_skew::ClockSkew;
+use crate::config::DirtyDataConfig;
+use crate::day_over_day::ShardActivity;
+use crate::self_check::SelfCheck;
+use crate::writer::EventRow;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Kind of damage done to a row
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DirtyField {
+    /// IMEI left empty
+    MissingImei,
+    /// `cell_id` set to 0
+    MissingCell,
+    /// Trailing digits chopped off the B-number (`msisdn_dst`)
+    TruncatedMsisdn,
+    /// `end_ts_ms` moved before `start_ts_ms`
+    InvalidDuration,
+}
+
+/// Ground-truth line for one damaged row, keyed by the fields the damage leaves intact
+#[derive(Debug, Serialize)]
+struct DirtyRecord<'a> {
+    event_type: &'a str,
+    direction: &'a str,
+    msisdn_src: u64,
+    start_ts_ms: i64,
+    faults: &'a [DirtyField],
+}
+
+/// Damages a configured share of a worker's rows on their way into the batch
+pub struct DirtyDataInjector {
+    cfg: DirtyDataConfig,
+    rng: StdRng,
+    log_path: Option<PathBuf>,
+    log: Option<BufWriter<File>>,
+    dirty_rows: usize,
+    // See every row before it is damaged
+    self_check: SelfCheck,
+    // Applied after the check, before any damage
+    clock_skew: ClockSkew,
+    activity: ShardActivity,
+}
+
+impl DirtyDataInjector {
+    /// `day_dir` is where `dirty_shardNNN.jsonl` goes when `ground_truth` is on.
+    /// Has its own RNG stream, so clean rows are the same as in a run without dirty data
+    pub fn new(cfg: &DirtyDataConfig, seed: u64, shard_id: usize, day_dir: &Path) -> Self {
+        DirtyDataInjector {
+            cfg: *cfg,
+            rng: StdRng::seed_from_u64(seed ^ 0xD1E7_DA7A ^ (shard_id as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)),
+            log_path: cfg.ground_truth.then(|| day_dir.join(format!("dirty_shard{:03}.jsonl", shard_id))),
+            log: None,
+            dirty_rows: 0,
+            self_check: SelfCheck::off(),
+            clock_skew: ClockSkew::off(),
+            activity: ShardActivity::default(),
+        }
+    }
+
+    /// Validate rows with `self_check` before any damage
+    pub fn with_self_check(mut self, self_check: SelfCheck) -> Self {
+        self.self_check = self_check;
+        self
+    }
+
+    /// Stamp rows with the clocks of the network elements writing them
+    pub fn with_clock_skew(mut self, clock_skew: ClockSkew) -> Self {
+        self.clock_skew = clock_skew;
+        self
+    }
+
+    pub fn self_check(&self) -> &SelfCheck {
+        &self.self_check
+    }
+
+    /// Record the MSISDNs originating rows (MO and DATA) in `activity`
+    pub fn with_activity(mut self, activity: ShardActivity) -> Self {
+        self.activity = activity;
+        self
+    }
+
+    /// Recorder of the MSISDNs that originated rows, taken out at the end of the worker
+    pub fn take_activity(&mut self) -> ShardActivity {
+        std::mem::take(&mut self.activity)
+    }
+
+    /// Rows damaged so far
+    pub fn dirty_rows(&self) -> usize {
+        self.dirty_rows
+    }
+
+    /// Push `row` into the batch, clock-skewed and damaged for the drawn share of rows.
+    /// `row` itself stays intact: callers copy its fields into correlated records afterwards
+    pub fn push(&mut self, batch: &mut EventBatch, row: &EventRow) -> anyhow::Result<()> {
+        self.self_check.check(row)?;
+        if row.direction == "MO" {
+            self.activity.record(row.msisdn_src);
+        }
+        let skewed = self.clock_skew.apply(row)?;
+        let row = skewed.as_ref().unwrap_or(row);
+        if !self.cfg.is_enabled() {
+            batch.push(row);
+            return Ok(());
+        }
+
+        let mut faults = Vec::new();
+        let mut draw = |rate: f64, field: DirtyField, faults: &mut Vec<DirtyField>| {
+            if rate > 0.0 && self.rng.gen::<f64>() < rate {
+                faults.push(field);
+            }
+        };
+        draw(self.cfg.missing_imei_rate, DirtyField::MissingImei, &mut faults);
+        draw(self.cfg.missing_cell_rate, DirtyField::MissingCell, &mut faults);
+        // Only rows with a B-number (no DATA) can have it truncated
+        if row.msisdn_dst >= 10 {
+            draw(self.cfg.truncated_msisdn_rate, DirtyField::TruncatedMsisdn, &mut faults);
+        }
+        draw(self.cfg.invalid_duration_rate, DirtyField::InvalidDuration, &mut faults);
+        if faults.is_empty() {
+            batch.push(row);
+            return Ok(());
+        }
+
+        let mut dirty = row.clone();
+        for fault in &faults {
+            match fault {
+                DirtyField::MissingImei => dirty.imei = 0,
+                DirtyField::MissingCell => dirty.cell_id = 0,
+                DirtyField::TruncatedMsisdn => {
+                    let digits = dirty.msisdn_dst.ilog10();
+                    dirty.msisdn_dst /= 10u64.pow(self.rng.gen_range(1..=digits.min(4)));
+                }
+                DirtyField::InvalidDuration => dirty.end_ts_ms = dirty.start_ts_ms - self.rng.gen_range(1_000..=3_600_000),
+            }
+        }
+        batch.push(&dirty);
+        self.dirty_rows += 1;
+        self.log_fault(&dirty, &faults)
+    }
+
+    fn log_fault(&mut self, row: &EventRow, faults: &[DirtyField]) -> anyhow::Result<()> {
+        let Some(path) = self.log_path.as_ref() else {
+            return Ok(());
+        };
+        if self.log.is_none() {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            self.log = Some(BufWriter::new(File::create(path)?));
+        }
+        let record = DirtyRecord {
+            event_type: row.event_type,
+            direction: row.direction,
+            msisdn_src: row.msisdn_src,
+            start_ts_ms: row.start_ts_ms,
+            faults,
+        };
+        if let Some(log) = self.log.as_mut() {
+            serde_json::to_writer(&mut *log, &record)?;
+            log.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    /// Flush the ground-truth logs
+    pub fn finish(&mut self) -> anyhow::Result<()> {
+        self.clock_skew.finish()?;
+        if let Some(mut log) = self.log.take() {
+            log.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn call_row(i: u64) -> EventRow {
+        EventRow {
+            event_type: "CALL",
+            msisdn_src: 31_612_000_000 + i,
+            msisdn_dst: 31_613_000_000 + i,
+            direction: "MO",
+            start_ts_ms: 1_735_689_600_000 + i as i64 * 1000,
+            end_ts_ms: 1_735_689_660_000 + i as i64 * 1000,
+            imei: 123_456_789_012_345,
+            cell_id: 12_345,
+            ..EventRow::default()
+        }
+    }
+
+    #[test]
+    fn test_disabled_injector_passes_rows_through() {
+        let dir = tempdir().unwrap();
+        let cfg = DirtyDataConfig { ground_truth: true, ..DirtyDataConfig::default() };
+        let mut injector = DirtyDataInjector::new(&cfg, 1, 0, dir.path());
+        let mut batch = EventBatch::new(16);
+        for i in 0..100 {
+            injector.push(&mut batch, &call_row(i)).unwrap();
+        }
+        injector.finish().unwrap();
+        assert_eq!(injector.dirty_rows(), 0);
+        assert!(batch.events.iter().enumerate().all(|(i, row)| row.imei != 0 && row.msisdn_dst == call_row(i as u64).msisdn_dst));
+        assert!(!dir.path().join("dirty_shard000.jsonl").exists());
+    }
+
+    #[test]
+    fn test_faults_match_ground_truth() {
+        let dir = tempdir().unwrap();
+        let cfg = DirtyDataConfig {
+            missing_imei_rate: 0.1,
+            missing_cell_rate: 0.1,
+            truncated_msisdn_rate: 0.1,
+            invalid_duration_rate: 0.1,
+            ground_truth: true,
+        };
+        let mut injector = DirtyDataInjector::new(&cfg, 1, 3, dir.path());
+        let mut batch = EventBatch::new(4096);
+        let rows: Vec<EventRow> = (0..4000).map(call_row).collect();
+        for row in &rows {
+            injector.push(&mut batch, row).unwrap();
+        }
+        injector.finish().unwrap();
+
+        let damaged: Vec<(&EventRow, &EventRow)> = rows
+            .iter()
+            .zip(&batch.events)
+            .filter(|(clean, written)| {
+                clean.imei != written.imei
+                    || clean.cell_id != written.cell_id
+                    || clean.msisdn_dst != written.msisdn_dst
+                    || clean.end_ts_ms != written.end_ts_ms
+            })
+            .collect();
+        // 1 - 0.9^4 of the rows
+        assert_eq!(damaged.len(), injector.dirty_rows());
+        assert!((1200..1600).contains(&damaged.len()), "{} dirty rows", damaged.len());
+        for (clean, written) in &damaged {
+            assert!(written.imei == 0 || written.imei == clean.imei);
+            assert!(written.cell_id == 0 || written.cell_id == clean.cell_id);
+            assert!(written.end_ts_ms < written.start_ts_ms || written.end_ts_ms == clean.end_ts_ms);
+            let (dst, clean_dst) = (written.msisdn_dst.to_string(), clean.msisdn_dst.to_string());
+            assert!(clean_dst.starts_with(&dst) && clean_dst.len() - dst.len() <= 4);
+        }
+
+        let log = std::fs::read_to_string(dir.path().join("dirty_shard003.jsonl")).unwrap();
+        let logged: Vec<serde_json::Value> = log.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(logged.len(), damaged.len());
+        for (entry, (_, written)) in logged.iter().zip(&damaged) {
+            assert_eq!(entry["msisdn_src"], written.msisdn_src);
+            assert_eq!(entry["start_ts_ms"], written.start_ts_ms);
+            let faults = entry["faults"].as_array().unwrap();
+            assert_eq!(faults.contains(&"missing_imei".into()), written.imei == 0);
+            assert_eq!(faults.contains(&"invalid_duration".into()), written.end_ts_ms < written.start_ts_ms);
+        }
+    }
+}
@@ -0,0 +1,388 @@
+// Storage and memory estimate of a run from its config alone, for sizing disks and hosts before generating
+// anything (`generate-cdr --dry-run`, or orchestration services embedding the crate)
+use crate::compression::CompressionType;
+use crate::config::Config;
+use crate::cores::available_cores;
+use crate::daily_targets::per_user_means;
+use crate::generators::{placement_shapes, placement_window_shaped, PlacementShape};
+use crate::timezone_utils::tz_from_name;
+use crate::writer::{header_columns, row_serializer, serialize_row, EventRow, Rotation, WriterOptions, CORRELATION_ID_NONE};
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Size of the default-config CSV over its size with each codec at the default level, measured on a
+/// generated day (~170 bytes per row). Real ratios move with the row mix and the compression level
+pub fn compression_ratio(codec: CompressionType) -> f64 {
+    match codec {
+        CompressionType::Gzip => 6.0,
+        CompressionType::Zstd => 5.7,
+        CompressionType::Bzip2 => 7.2,
+        CompressionType::Xz => 8.6,
+        CompressionType::None => 1.0,
+    }
+}
+
+/// Expected output of a run; event counts follow the summary.json counters
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunEstimate {
+    pub days: usize,
+    pub subscribers: usize,
+    /// Subscribers with events on a day, after `sample_stride` and `daily_inactive_rate`
+    pub active_subscribers_per_day: f64,
+    /// CALL rows of the whole run: MO calls, MT legs of calls between subscribers and callbacks
+    pub calls: f64,
+    /// SMS messages of the whole run, and the extra rows of their retry chains
+    pub sms: f64,
+    pub sms_retry_rows: f64,
+    pub data: f64,
+    /// CSV bytes of the part files before compression, headers included
+    pub uncompressed_bytes: u64,
+    /// Part file bytes with each codec, by `compression_ratio`
+    pub compressed_bytes: BTreeMap<String, u64>,
+    /// Codec of the part files
+    pub compression: String,
+    /// Part files at the configured rotation limits, with the part files' codec
+    pub part_files: usize,
+    pub workers: usize,
+    pub writer_tasks: usize,
+    /// Event batches held at once: one filling in each worker, one queued and one being written per writer task
+    pub peak_ram_bytes: u64,
+}
+
+impl RunEstimate {
+    /// Rows of the whole run
+    pub fn rows(&self) -> f64 {
+        self.calls + self.sms + self.sms_retry_rows + self.data
+    }
+
+    /// Part file bytes with the configured codec
+    pub fn output_bytes(&self) -> u64 {
+        self.compressed_bytes.get(&self.compression).copied().unwrap_or(self.uncompressed_bytes)
+    }
+}
+
+/// Expected rows of each type per active subscriber and day
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct RowsPerSubscriber {
+    calls: f64,
+    sms: f64,
+    sms_retry_rows: f64,
+    data: f64,
+}
+
+/// Mean `activity` of `user_segments` over the subscribers, with the shares cut at 1 as `Segments::assign` draws them
+pub(crate) fn mean_segment_activity(cfg: &Config) -> f64 {
+    let mut cumulative = 0.0f64;
+    let mut mean = 0.0;
+    let last = cfg.user_segments.len().saturating_sub(1);
+    for (i, profile) in cfg.user_segments.values().enumerate() {
+        let from = cumulative.min(1.0);
+        cumulative += profile.share;
+        let to = if i == last { 1.0 } else { cumulative.min(1.0) };
+        mean += (to - from) * profile.activity;
+    }
+    if cfg.user_segments.is_empty() {
+        1.0
+    } else {
+        mean
+    }
+}
+
+/// Share of the day's mass under `shape` in `time_window`, averaged over a reference week so weekdays
+/// and weekends both count; 1 without a window
+fn mean_window_share(cfg: &Config, shape: PlacementShape) -> f64 {
+    if cfg.time_window.is_none() {
+        return 1.0;
+    }
+    let tz = tz_from_name(&cfg.tz_name);
+    let monday = NaiveDate::from_ymd_opt(2025, 1, 6).unwrap();
+    (0..7)
+        .map(|d| placement_window_shaped(cfg, tz, monday + Duration::days(d), shape).map_or(1.0, |(_, _, share)| share))
+        .sum::<f64>()
+        / 7.0
+}
+
+/// CALL rows written per drawn call: calls to a contact get the contact's MT leg; missed calls are called
+/// back with `callback_prob`
+pub(crate) fn call_rows_per_call(cfg: &Config) -> f64 {
+    // Without contact_call_share random subscribers call a contact every time
+    let contact_share = if cfg.contacts.max == Some(0) { 0.0 } else { cfg.contact_call_share.unwrap_or(1.0) };
+    let total_weight: f64 = cfg.call_dispositions.values().sum();
+    let missed_weight: f64 =
+        ["NO ANSWER", "BUSY"].iter().filter_map(|name| cfg.call_dispositions.get(*name)).sum();
+    let missed_share = if total_weight > 0.0 { missed_weight / total_weight } else { 0.0 };
+    1.0 + contact_share + missed_share * cfg.callback_prob
+}
+
+/// Rows per active subscriber of a run over `subscribers` subscribers, which `daily_targets` are spread over
+fn rows_per_subscriber(cfg: &Config, subscribers: usize) -> RowsPerSubscriber {
+    let activity = mean_segment_activity(cfg);
+    let [avg_calls, avg_sms, avg_data] = per_user_means(cfg, subscribers);
+    let [call_shape, sms_shape, data_shape] = placement_shapes(cfg);
+    let calls = avg_calls * activity * mean_window_share(cfg, call_shape);
+    let sms = avg_sms * activity * mean_window_share(cfg, sms_shape);
+    let data = avg_data * activity * mean_window_share(cfg, data_shape);
+
+    // An unreachable MT SMS is delivered at attempt d (uniform in 1..=A, A past the schedule = expired)
+    // and has min(d, A - 1) rows after the first
+    let attempts = cfg.sms_retry_schedule_min.len();
+    let retries_per_unreachable = if attempts > 1 {
+        ((1..attempts).sum::<usize>() + attempts - 1) as f64 / attempts as f64
+    } else {
+        0.0
+    };
+
+    RowsPerSubscriber {
+        calls: calls * call_rows_per_call(cfg),
+        sms,
+        sms_retry_rows: sms * (1.0 - cfg.mo_share_sms) * cfg.sms_unreachable_rate * retries_per_unreachable,
+        data,
+    }
+}
+
+/// Bytes of one CSV line of `row`
+fn row_bytes(row: &EventRow, options: &WriterOptions) -> u64 {
+    let mut serializer = row_serializer(Vec::new());
+    let serialized = serialize_row(&mut serializer, row, options);
+    match (serialized, serializer.into_inner()) {
+        (Ok(()), Ok(buf)) => buf.len() as u64,
+        _ => 0,
+    }
+}
+
+/// Line bytes of a typical CALL, SMS and DATA row with the configured columns and MSISDN format
+fn typical_row_bytes(cfg: &Config, options: &WriterOptions) -> [u64; 3] {
+    let call = EventRow {
+        event_type: "CALL",
+        msisdn_src: 316121722701,
+        msisdn_dst: 316135128446,
+        direction: "MO",
+        start_ts_ms: 1735740790000,
+        end_ts_ms: 1735740873000,
+        tz_name: "Europe/Amsterdam",
+        tz_offset_min: 60,
+        duration_sec: 83,
+        mccmnc: 20416,
+        imsi: 204163181354007,
+        imei: 902432926813213,
+        cell_id: 88095,
+        record_type: "mscVoiceRecord",
+        node_id: if options.emit_node_id { "msc01" } else { "" },
+        cause_for_record_closing: "normalRelease",
+        prev_event_ts_ms: if cfg.emit_prev_event_ts { 1735740000000 } else { 0 },
+        record_seq: if cfg.emit_record_seq { 100_000 } else { 0 },
+        ..EventRow::default()
+    };
+    let sms = EventRow {
+        event_type: "SMS",
+        end_ts_ms: call.start_ts_ms + 2000,
+        duration_sec: 2,
+        record_type: "sgsnSMORecord",
+        cause_for_record_closing: "deliverySuccess",
+        sms_segments: 1,
+        sms_status: "DELIVERED",
+        correlation_id: if options.emit_correlation_id { 4_611_686_018_427_387_904 } else { 0 },
+        ..call.clone()
+    };
+    let data = EventRow {
+        event_type: "DATA",
+        msisdn_dst: 0,
+        end_ts_ms: call.start_ts_ms + 353_000,
+        duration_sec: 353,
+        imeisv: if cfg.emit_imeisv { 9024329268132104 } else { 0 },
+        cell_id: 1525,
+        record_type: "sgsnPDPRecord",
+        node_id: if options.emit_node_id { "sgsn01" } else { "" },
+        data_bytes_in: Some(10_443_870),
+        data_bytes_out: Some(5_921_433),
+        data_duration_sec: Some(353),
+        apn: "internet",
+        rat: "LTE",
+        qci: 9,
+        bearer_id: 1,
+        ..call.clone()
+    };
+    let call = EventRow { correlation_id: if options.emit_correlation_id { CORRELATION_ID_NONE } else { 0 }, ..call };
+
+    // The rows carry a placeholder zone; the configured name replaces it in every line
+    let tz_delta = if options.omit_timezone_columns { 0 } else { cfg.tz_name.len() as i64 - call.tz_name.len() as i64 };
+    [call, sms, data].map(|row| (row_bytes(&row, options) as i64 + tz_delta).max(0) as u64)
+}
+
+/// Files one writer task leaves in a stream of `rows` rows and `bytes` bytes on disk
+fn stream_files(rows: f64, bytes: f64, max_bytes: u64, max_rows: u64) -> usize {
+    if rows < 0.5 {
+        return 0;
+    }
+    let by_bytes = if max_bytes > 0 { (bytes / max_bytes as f64).ceil() as usize } else { 1 };
+    let by_rows = if max_rows > 0 { (rows / max_rows as f64).ceil() as usize } else { 1 };
+    by_bytes.max(by_rows).max(1)
+}
+
+/// Writer tasks the CSV path runs for `ranges` non-empty worker ranges
+fn writer_tasks(cfg: &Config, ranges: usize) -> usize {
+    let tasks = if cfg.writer_tasks > 0 { cfg.writer_tasks } else { (ranges / 2).max(1) };
+    tasks.min(ranges.max(1))
+}
+
+/// Expected events, bytes, part files and batch memory of `days` days for `subscribers` subscribers
+/// with `cfg`. Counts are means of the Poisson draws; bytes come from a typical row per event type
+/// and `compression_ratio`
+pub fn estimate_run(cfg: &Config, days: usize, subscribers: usize) -> RunEstimate {
+    let population = subscribers.div_ceil(cfg.sample_stride.max(1));
+    let active = population as f64 * (1.0 - cfg.daily_inactive_rate.clamp(0.0, 1.0));
+    let per_subscriber = rows_per_subscriber(cfg, subscribers);
+    let per_day = |rate: f64| rate * active;
+    let (calls, sms, sms_retry_rows, data) = (
+        per_day(per_subscriber.calls),
+        per_day(per_subscriber.sms),
+        per_day(per_subscriber.sms_retry_rows),
+        per_day(per_subscriber.data),
+    );
+
+    let options = WriterOptions::from_config(cfg);
+    let [call_bytes, sms_bytes, data_bytes] = typical_row_bytes(cfg, &options);
+    let header_bytes = header_columns(&options).join(";").len() as u64 + 1;
+
+    let workers = if cfg.workers == 0 { available_cores() } else { cfg.workers };
+    let ranges = workers.min(subscribers).max(1);
+    let writer_tasks = writer_tasks(cfg, ranges);
+
+    let (codec_name, _) = cfg.part_compression();
+    let codec = CompressionType::from_str(codec_name).unwrap_or(CompressionType::None);
+    let ratio = compression_ratio(codec);
+
+    // (rows, uncompressed bytes) of each stream of a day
+    let streams: Vec<(Option<&str>, f64, f64)> = vec![
+        (Some("CALL"), calls, calls * call_bytes as f64),
+        (Some("SMS"), sms + sms_retry_rows, (sms + sms_retry_rows) * sms_bytes as f64),
+        (Some("DATA"), data, data * data_bytes as f64),
+    ];
+    let rotation = Rotation::from_config(cfg);
+    let day_streams = if rotation.splits_by_event_type() || cfg.partition_layout.splits_by_event_type() {
+        streams
+    } else {
+        let (rows, bytes) = streams.iter().fold((0.0, 0.0), |(r, b), &(_, rows, bytes)| (r + rows, b + bytes));
+        vec![(None, rows, bytes)]
+    };
+    let mut files_per_day = 0;
+    let mut bytes_per_day = 0.0;
+    for (event_type, rows, bytes) in day_streams {
+        // Worker ranges are spread evenly over the writer tasks
+        let policy = rotation.policy(event_type);
+        let (rows, bytes) = (rows / writer_tasks as f64, bytes / writer_tasks as f64);
+        let files = stream_files(rows, bytes / ratio, policy.bytes, policy.rows);
+        files_per_day += files * writer_tasks;
+        bytes_per_day += (bytes + (files * header_bytes as usize) as f64) * writer_tasks as f64;
+    }
+
+    let days_f = days as f64;
+    let uncompressed_bytes = (bytes_per_day * days_f).round() as u64;
+    let compressed_bytes = CompressionType::ALL
+        .iter()
+        .map(|&codec| (codec.name().to_string(), (uncompressed_bytes as f64 / compression_ratio(codec)).round() as u64))
+        .collect();
+
+    RunEstimate {
+        days,
+        subscribers,
+        active_subscribers_per_day: active,
+        calls: calls * days_f,
+        sms: sms * days_f,
+        sms_retry_rows: sms_retry_rows * days_f,
+        data: data * days_f,
+        uncompressed_bytes,
+        compressed_bytes,
+        compression: codec.name().to_string(),
+        part_files: files_per_day * days,
+        workers,
+        writer_tasks,
+        peak_ram_bytes: ((workers + 2 * writer_tasks) * cfg.batch_size_bytes) as u64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{SegmentProfile, TimeWindow};
+
+    fn config() -> Config {
+        Config {
+            workers: 4,
+            daily_inactive_rate: 0.0,
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn test_event_means_follow_config() {
+        let cfg = Config {
+            contact_call_share: Some(0.5),
+            callback_prob: 0.5,
+            sms_unreachable_rate: 0.2,
+            ..config()
+        };
+        let estimate = estimate_run(&cfg, 2, 1000);
+        // 3.5 calls with half of them getting an MT leg, and 16% missed calls called back half the time
+        assert!((estimate.calls - 2.0 * 1000.0 * 3.5 * (1.5 + 0.16 * 0.5)).abs() < 1e-6, "{}", estimate.calls);
+        assert!((estimate.sms - 2.0 * 1000.0 * 5.2).abs() < 1e-6);
+        // Schedule [0, 5, 30, 120]: (1 + 2 + 3 + 3) / 4 rows after the first
+        assert!((estimate.sms_retry_rows - 2.0 * 1000.0 * 5.2 * 0.5 * 0.2 * 2.25).abs() < 1e-6);
+        assert!((estimate.data - 2.0 * 1000.0 * 12.0).abs() < 1e-6);
+
+        let quiet = Config {
+            daily_inactive_rate: 0.25,
+            sample_stride: 10,
+            ..cfg
+        };
+        assert!((estimate_run(&quiet, 1, 1000).active_subscribers_per_day - 75.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_segment_activity_and_window_scale_means() {
+        let segment = |share: f64, activity: f64| SegmentProfile { share, activity, plans: vec!["basic".to_string()] };
+        let cfg = Config {
+            user_segments: BTreeMap::from([("a".to_string(), segment(0.5, 2.0)), ("b".to_string(), segment(0.5, 0.0))]),
+            ..config()
+        };
+        assert!((estimate_run(&cfg, 1, 100).data - 100.0 * 12.0).abs() < 1e-6);
+
+        let windowed = Config {
+            time_window: Some(TimeWindow::parse("13:00-14:00").unwrap()),
+            ..config()
+        };
+        let share = estimate_run(&windowed, 1, 100).sms / (100.0 * 5.2);
+        assert!(share > 0.02 && share < 0.1, "{}", share);
+    }
+
+    #[test]
+    fn test_bytes_files_and_memory() {
+        let cfg = Config {
+            compression_type: "gzip".to_string(),
+            rotate_bytes: 1_000_000,
+            writer_tasks: 2,
+            ..config()
+        };
+        let estimate = estimate_run(&cfg, 3, 10_000);
+        let bytes_per_row = estimate.uncompressed_bytes as f64 / estimate.rows();
+        assert!((150.0..200.0).contains(&bytes_per_row), "{}", bytes_per_row);
+        assert_eq!(estimate.compression, "gzip");
+        assert_eq!(estimate.compressed_bytes["none"], estimate.uncompressed_bytes);
+        assert!(estimate.output_bytes() < estimate.compressed_bytes["zstd"]);
+        assert!(estimate.compressed_bytes["xz"] < estimate.output_bytes());
+        // Every writer task rotates its own stream: ~1 MB on disk per part
+        let per_part = estimate.output_bytes() as f64 / estimate.part_files as f64;
+        assert!((500_000.0..=1_000_000.0).contains(&per_part), "{} parts", estimate.part_files);
+        assert_eq!(estimate.part_files % (2 * 3), 0);
+        assert_eq!(estimate.peak_ram_bytes, (4 + 2 * 2) * cfg.batch_size_bytes as u64);
+
+        // One stream per event type and writer task, each with at least one part
+        let by_type = Config {
+            rotation: BTreeMap::from([("DATA".to_string(), crate::config::RotationPolicy { rows: 1000, ..Default::default() })]),
+            ..cfg
+        };
+        let estimate = estimate_run(&by_type, 1, 100);
+        assert_eq!(estimate.part_files, 2 + 2 + 2);
+    }
+}
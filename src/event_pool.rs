@@ -1,12 +1,31 @@
 // Object pool for EventRow to eliminate allocations in hot paths
+use crate::async_writer::batch_capacity;
+use crate::record::CdrRecord;
 use crate::writer::EventRow;
 
 /// Simple ring buffer pool for EventRow objects
-/// Each worker thread has its own pool to avoid synchronization overhead
+/// Each worker thread has its own pool to avoid synchronization overhead.
+/// A slot is handed out again `capacity` rows later, so its row must have been copied out (pushed to a
+/// batch or held) by then; callers say so with `consume`. Debug builds stamp every slot with the generation
+/// of its row and panic when a slot whose row was never consumed comes round again
 pub struct EventPool {
     pool: Vec<EventRow>,
     next: usize,
     capacity: usize,
+    wraparounds: u64,
+    // Debug builds: generation of the row each slot holds, rows handed out, and rows consumed
+    #[cfg(debug_assertions)]
+    slot_generations: Vec<u64>,
+    #[cfg(debug_assertions)]
+    generation: u64,
+    #[cfg(debug_assertions)]
+    consumed: u64,
+}
+
+/// Fewest rows a worker pool may hold (`event_pool_size`): a full batch of `batch_size_bytes`, so no slot comes round
+/// again while the batch it went into is still being filled, even if rows stay in the pool until their batch is sent
+pub fn min_event_pool_size(batch_size_bytes: usize) -> usize {
+    batch_capacity(batch_size_bytes).max(1)
 }
 
 impl EventPool {
@@ -21,19 +40,67 @@ impl EventPool {
             pool,
             next: 0,
             capacity,
+            wraparounds: 0,
+            #[cfg(debug_assertions)]
+            slot_generations: vec![0; capacity],
+            #[cfg(debug_assertions)]
+            generation: 0,
+            #[cfg(debug_assertions)]
+            consumed: 0,
+        }
+    }
+
+    /// Index of the next slot to hand out; checks in debug builds that its previous row was consumed
+    fn next_slot(&mut self) -> usize {
+        let index = self.next;
+        #[cfg(debug_assertions)]
+        {
+            let previous = self.slot_generations[index];
+            assert!(
+                previous <= self.consumed,
+                "EventPool slot {} reused while its row {} is still in flight ({} rows handed out, {} consumed, capacity {})",
+                index,
+                previous,
+                self.generation,
+                self.consumed,
+                self.capacity
+            );
+            self.generation += 1;
+            self.slot_generations[index] = self.generation;
+        }
+        self.next = (self.next + 1) % self.capacity;
+        if self.next == 0 {
+            self.wraparounds += 1;
         }
+        index
     }
 
     /// Acquire an EventRow from the pool
     /// Returns a mutable reference to a reset EventRow
     /// Uses ring buffer approach - wraps around when reaching capacity
     pub fn acquire(&mut self) -> &mut EventRow {
-        let event = &mut self.pool[self.next];
-        self.next = (self.next + 1) % self.capacity;
+        let index = self.next_slot();
+        let event = &mut self.pool[index];
         event.reset();
         event
     }
 
+    /// Serialize a generated record into the next EventRow of the pool
+    pub fn put(&mut self, record: impl Into<CdrRecord>) -> &mut EventRow {
+        let index = self.next_slot();
+        let event = &mut self.pool[index];
+        record.into().write_row(event);
+        event
+    }
+
+    /// Every row handed out so far has been copied out of the pool, so its slot may be reused
+    pub fn consume(&mut self) {
+        #[cfg(debug_assertions)]
+        {
+            self.consumed = self.generation;
+        }
+    }
+
     /// Get the current capacity of the pool
     pub fn capacity(&self) -> usize {
         self.capacity
@@ -44,6 +111,7 @@ impl EventPool {
         PoolStats {
             capacity: self.capacity,
             current_index: self.next,
+            wraparounds: self.wraparounds,
         }
     }
 }
@@ -53,6 +121,8 @@ impl EventPool {
 pub struct PoolStats {
     pub capacity: usize,
     pub current_index: usize,
+    // Times the ring went past its last slot and started reusing slots
+    pub wraparounds: u64,
 }
 
 #[cfg(test)]
@@ -89,10 +159,12 @@ mod tests {
         for i in 0..4 {
             let event = pool.acquire();
             event.msisdn_src = i as u64;
+            pool.consume();
         }
 
         // After 4 acquisitions with capacity 3, index should be 1 (4 % 3)
         assert_eq!(pool.stats().current_index, 1);
+        assert_eq!(pool.stats().wraparounds, 1);
     }
 
     #[test]
@@ -112,4 +184,42 @@ mod tests {
         // Values should be default (0 for numbers, "" for strings)
         assert_eq!(event3.msisdn_dst, 0);
     }
+
+    #[test]
+    fn test_event_pool_put_record() {
+        use crate::record::{CallRecord, DataRecord, RecordHeader};
+
+        let mut pool = EventPool::new(1);
+        let data = DataRecord { header: RecordHeader { msisdn_src: 999999, ..RecordHeader::default() }, apn: "internet", ..DataRecord::default() };
+        assert_eq!(pool.put(data).apn, "internet");
+        pool.consume();
+
+        // The same slot again: no DATA column survives into the CALL row
+        let event = pool.put(CallRecord { header: RecordHeader { msisdn_src: 111111, ..RecordHeader::default() } });
+        assert_eq!((event.event_type, event.msisdn_src), ("CALL", 111111));
+        assert_eq!((event.apn, event.data_bytes_in), ("", None));
+    }
+
+    #[test]
+    fn test_consumed_rows_free_their_slots() {
+        let mut pool = EventPool::new(2);
+        for round in 0..10u64 {
+            pool.acquire().msisdn_src = round;
+            pool.acquire().msisdn_src = round;
+            // A batch copies both rows out
+            pool.consume();
+        }
+        assert_eq!(pool.stats().wraparounds, 10);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "EventPool slot 0 reused while its row 1 is still in flight")]
+    fn test_reusing_an_unconsumed_slot_panics() {
+        let mut pool = EventPool::new(3);
+        // More rows in flight than the pool holds
+        for _ in 0..4 {
+            pool.acquire();
+        }
+    }
 }
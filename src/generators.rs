@@ -1,28 +1,48 @@
 // Event generation logic for CALL, SMS, and DATA events
-use crate::async_writer::{EventBatch, WriterMessage};
-use crate::config::Config;
+use crate::async_writer::{batch_capacity, BatchSender, EventBatch, WriterMessage};
+use crate::cells::{CellsCatalog, RatMix};
+use crate::clock_skew::ClockSkew;
+use crate::config::{ApnProfile, Config, DayFolderBasis, DurationRule, EventTimeBasis, RecordTypeProfile, SelfCheckMode, VolumeTarget};
+use crate::daily_targets::per_user_means;
+use crate::day_over_day::{DayActivity, ShardActivity};
+use crate::dirty_data::DirtyDataInjector;
 use crate::event_pool::EventPool;
-use crate::identity::{build_contacts, build_subscribers, gen_imei, Subscriber};
+use crate::identity::{
+    build_contacts, indexed_subscriber, parse_numeric, parse_prefix_operator_map, Contacts, Mccmnc, Msisdn, SubscriberIdentity,
+};
+use crate::record::{CallRecord, DataRecord, RecordHeader, SmsRecord};
+use crate::segments::Segments;
+use crate::self_check::{SelfCheck, ShardMsisdns};
+#[cfg(feature = "arrow")]
+use crate::subscriber_db::MsisdnIndexRange;
 use crate::subscriber_db::SubscriberDatabase;
+#[cfg(feature = "arrow")]
+use crate::subscriber_db_arrow::load_database_from_arrow_in_range;
+#[cfg(feature = "redb")]
 use crate::subscriber_db_redb::SubscriberDbRedb;
-use crate::timezone_utils::{to_epoch_ms, tz_from_name, tz_offset_minutes};
-use crate::writer::EventRow;
-use chrono::{DateTime, Datelike, Duration, TimeZone, Timelike, Weekday};
-use crossbeam_channel::Sender;
+use crate::timezone_utils::{add_seconds, local_day_bounds, local_window_bounds, seconds_between, to_epoch_ms, tz_from_name, tz_offset_minutes};
+use crate::writer::{EventRow, Rotation, WriterOptions, CORRELATION_ID_NONE, PREV_EVENT_TS_NONE};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Timelike, Weekday};
 use rand::distributions::WeightedIndex;
 use rand::prelude::*;
 use rand::rngs::StdRng;
 use rand::SeedableRng;
 use rand_distr::{Distribution, LogNormal, Normal};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tracing::debug;
+
+/// Stand-in for the redb database without the `redb` feature: it has no values, so the redb path is never taken
+#[cfg(not(feature = "redb"))]
+pub enum SubscriberDbRedb {}
 
 /// Calculate lognormal mu and sigma from quantiles
 pub fn lognorm_params_from_quantiles(p50: f64, p90: f64) -> (f64, f64) {
     let mu = p50.max(1.0).ln();
     let sigma = (p90.max(1.0) / p50.max(1.0)).ln() / 1.2815515655446004;
-    let sigma = sigma.max(0.2).min(2.0);
+    let sigma = sigma.clamp(0.2, 2.0);
     (mu, sigma)
 }
 
@@ -95,6 +115,325 @@ pub fn sample_poisson(mean: f64, rng: &mut StdRng) -> usize {
     }
 }
 
+/// SplitMix64 finalizer: spreads a combined key over all bits before it seeds an RNG
+fn splitmix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Decide whether a subscriber is inactive (phone off, abroad, dormant SIM) on a given day
+/// Deterministic in (seed, msisdn, date) so reruns and different shardings agree
+pub fn is_inactive_on_day(seed: u64, msisdn: u64, date: NaiveDate, rate: f64) -> bool {
+    if rate <= 0.0 {
+        return false;
+    }
+    if rate >= 1.0 {
+        return true;
+    }
+
+    let z = splitmix(
+        seed ^ msisdn.wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ (date.num_days_from_ce() as u64).wrapping_mul(0xD1B5_4A32_D192_ED03),
+    );
+
+    // Top 53 bits -> uniform [0, 1)
+    let u = (z >> 11) as f64 / (1u64 << 53) as f64;
+    u < rate
+}
+
+/// Share of a database subscriber's CALL/SMS counterparts taken from the worker's range when `contact_call_share`
+/// is unset
+pub const DB_CONTACT_CALL_SHARE: f64 = 0.7;
+
+/// Contact distribution to draw a CALL/SMS counterpart from, for `contact_call_share` of the events (all of them
+/// when unset, without a draw); None for an external number (also when the subscriber has no contacts)
+fn pick_contact<'a>(
+    contact_dist: Option<&'a WeightedIndex<f64>>,
+    cfg: &Config,
+    rng: &mut StdRng,
+) -> Option<&'a WeightedIndex<f64>> {
+    match cfg.contact_call_share {
+        Some(share) => contact_dist.filter(|_| rng.gen::<f64>() < share),
+        None => contact_dist,
+    }
+}
+
+/// Call, SMS and DATA count samplers of each segment, in `Segments::iter` order:
+/// the per-user means scaled by the segment's activity
+fn segment_samplers(segments: &Segments, avg_calls: f64, avg_sms: f64, avg_data: f64) -> Vec<[EventCountSampler; 3]> {
+    segments
+        .iter()
+        .map(|segment| {
+            [
+                EventCountSampler::new(avg_calls * segment.activity),
+                EventCountSampler::new(avg_sms * segment.activity),
+                EventCountSampler::new(avg_data * segment.activity),
+            ]
+        })
+        .collect()
+}
+
+/// Event counts of a worker range in sparse mode (`sparse_threshold`): each type's total for the range is one
+/// Poisson draw spread over uniformly random subscribers, instead of a mostly-zero draw per subscriber.
+/// Totals are drawn at the highest segment activity and a subscriber keeps each candidate event with its
+/// segment's share of that, so every subscriber's counts are Poisson with the same means as the dense draws
+pub struct SparseCounts {
+    /// Candidate CALL, SMS and DATA counts of the subscribers with any, by subscriber index
+    by_index: BTreeMap<usize, [usize; 3]>,
+    max_activity: f64,
+}
+
+impl SparseCounts {
+    /// Counts of subscribers `range` when every per-user mean at the highest segment activity is below
+    /// `sparse_threshold`; None for the per-subscriber draws
+    fn draw(cfg: &Config, segments: &Segments, means: [f64; 3], (start, end): (usize, usize), rng: &mut StdRng) -> Option<Self> {
+        let max_activity = segments.iter().map(|segment| segment.activity).fold(0.0, f64::max);
+        if cfg.sparse_threshold <= 0.0 || means.iter().any(|&mean| mean * max_activity >= cfg.sparse_threshold) {
+            return None;
+        }
+
+        // Sampled runs keep every Nth subscriber of the full population
+        let stride = cfg.sample_stride.max(1);
+        let first = start.div_ceil(stride) * stride;
+        let eligible = end.saturating_sub(first).div_ceil(stride);
+        let mut by_index = BTreeMap::new();
+        if eligible > 0 {
+            for (kind, mean) in means.iter().enumerate() {
+                for _ in 0..sample_poisson(eligible as f64 * mean * max_activity, rng) {
+                    let idx = first + rng.gen_range(0..eligible) * stride;
+                    by_index.entry(idx).or_insert([0; 3])[kind] += 1;
+                }
+            }
+        }
+        Some(SparseCounts { by_index, max_activity })
+    }
+
+    /// Subscriber indices in `range` with candidate events, in order
+    fn indices(&self, range: std::ops::Range<usize>) -> impl Iterator<Item = usize> + '_ {
+        self.by_index.range(range).map(|(&idx, _)| idx)
+    }
+
+    /// CALL, SMS and DATA counts of subscriber `idx` in a segment of `activity`
+    fn counts(&self, idx: usize, activity: f64, rng: &mut StdRng) -> [usize; 3] {
+        let keep = activity / self.max_activity;
+        let candidates = self.by_index.get(&idx).copied().unwrap_or_default();
+        candidates.map(|n| (0..n).filter(|_| rng.gen::<f64>() < keep).count())
+    }
+}
+
+/// CALL, SMS and DATA counts of subscriber `idx` with IMSI `imsi`: per-subscriber draws scaled by its segment,
+/// or its share of the range's sparse totals
+fn event_counts(
+    cfg: &Config,
+    segments: &Segments,
+    samplers: &[[EventCountSampler; 3]],
+    sparse: Option<&SparseCounts>,
+    idx: usize,
+    imsi: u64,
+    rng: &mut StdRng,
+) -> [usize; 3] {
+    let (segment, profile) = segments.assign(cfg.seed, imsi);
+    match sparse {
+        Some(sparse) => sparse.counts(idx, profile.activity, rng),
+        None => samplers[segment].each_ref().map(|sampler| sampler.sample(rng)),
+    }
+}
+
+/// Zone whose hours the diurnal curve is read in
+pub fn placement_tz(cfg: &Config, tz: chrono_tz::Tz) -> chrono_tz::Tz {
+    match cfg.event_time_basis {
+        EventTimeBasis::Local => tz,
+        EventTimeBasis::Utc => chrono_tz::UTC,
+    }
+}
+
+/// Zone whose midnights bound the generated day and its `time_window`, and whose date names the day:
+/// UTC with either `event_time_basis` or `day_folder_basis` set to utc
+pub fn day_tz(cfg: &Config, tz: chrono_tz::Tz) -> chrono_tz::Tz {
+    match cfg.day_folder_basis {
+        DayFolderBasis::Local => placement_tz(cfg, tz),
+        DayFolderBasis::Utc => chrono_tz::UTC,
+    }
+}
+
+/// Name of a day's output folder and part files: the date, plus the time window if one is set (`2025-01-01_1300-1400`)
+pub fn output_label(cfg: &Config, date: NaiveDate) -> String {
+    match cfg.time_window {
+        Some(window) => format!("{}_{}", date.format("%Y-%m-%d"), window.label()),
+        None => date.format("%Y-%m-%d").to_string(),
+    }
+}
+
+/// Interval events are placed in on `date` (the whole placement day, or `time_window`)
+/// and its share of the day's diurnal mass, by which the per-user means are scaled
+pub fn placement_window(
+    cfg: &Config,
+    tz: chrono_tz::Tz,
+    date: NaiveDate,
+) -> anyhow::Result<(DateTime<chrono_tz::Tz>, DateTime<chrono_tz::Tz>, f64)> {
+    placement_window_floored(cfg, tz, date, 0.0)
+}
+
+/// `placement_window` with the diurnal curve lower-bounded by `floor`, as DATA sessions are placed
+/// with `data_nighttime_floor`
+pub fn placement_window_floored(
+    cfg: &Config,
+    tz: chrono_tz::Tz,
+    date: NaiveDate,
+    floor: f64,
+) -> anyhow::Result<(DateTime<chrono_tz::Tz>, DateTime<chrono_tz::Tz>, f64)> {
+    placement_window_shaped(cfg, tz, date, PlacementShape::Diurnal(floor))
+}
+
+/// `placement_window` with the share of the day taken under `shape`
+pub fn placement_window_shaped(
+    cfg: &Config,
+    tz: chrono_tz::Tz,
+    date: NaiveDate,
+    shape: PlacementShape,
+) -> anyhow::Result<(DateTime<chrono_tz::Tz>, DateTime<chrono_tz::Tz>, f64)> {
+    // Bounds from the day's zone, read on the curve's clock
+    let curve_tz = placement_tz(cfg, tz);
+    let tz = day_tz(cfg, tz);
+    let (day_start, day_end) = local_day_bounds(tz, date)?;
+    let (day_start, day_end) = (day_start.with_timezone(&curve_tz), day_end.with_timezone(&curve_tz));
+    let Some(window) = cfg.time_window else {
+        return Ok((day_start, day_end, 1.0));
+    };
+    let (start, end) = local_window_bounds(tz, date, window.start_min, window.end_min)?;
+    let (start, end) = (start.with_timezone(&curve_tz), end.with_timezone(&curve_tz));
+
+    // Minute-by-minute acceptance probability of `sample_time`, which caps the multiplier at 1
+    let day_str = date.format("%Y-%m-%d").to_string();
+    let mass = |from: DateTime<chrono_tz::Tz>, to: DateTime<chrono_tz::Tz>| -> f64 {
+        (0..(to - from).num_minutes())
+            .map(|minute| shape.weight(&(from + Duration::minutes(minute)), cfg, &day_str))
+            .sum()
+    };
+    let day_mass = mass(day_start, day_end);
+    let share = if day_mass > 0.0 { mass(start, end) / day_mass } else { 0.0 };
+    Ok((start, end, share))
+}
+
+/// Shapes of CALL, SMS and DATA: an hourly `daily_targets` entry, else the diurnal curve (DATA with `data_nighttime_floor`)
+pub fn placement_shapes(cfg: &Config) -> [PlacementShape<'_>; 3] {
+    let targets = &cfg.daily_targets;
+    [
+        PlacementShape::of(targets.calls.as_ref(), 0.0),
+        PlacementShape::of(targets.sms.as_ref(), 0.0),
+        PlacementShape::of(targets.data_sessions.as_ref(), cfg.data_nighttime_floor),
+    ]
+}
+
+/// Per-user CALL, SMS and DATA means over the placement window of `date`: the whole-day means of a run over
+/// `population` subscribers scaled by the window's share of each type's shape
+fn window_means(cfg: &Config, tz: chrono_tz::Tz, date: NaiveDate, population: usize) -> anyhow::Result<[f64; 3]> {
+    let mut means = per_user_means(cfg, population);
+    for (mean, shape) in means.iter_mut().zip(placement_shapes(cfg)) {
+        let (_, _, share) = placement_window_shaped(cfg, tz, date, shape)?;
+        *mean *= share;
+    }
+    Ok(means)
+}
+
+/// What the events of a type are spread over the day by
+#[derive(Debug, Clone, Copy)]
+pub enum PlacementShape<'a> {
+    /// The diurnal curve lower-bounded by a floor, capped at 1
+    Diurnal(f64),
+    /// The 24 local-hour totals of an hourly `daily_targets` entry
+    Hourly(&'a [f64]),
+}
+
+impl<'a> PlacementShape<'a> {
+    /// Shape of a type with `target` (hourly targets only) and diurnal `floor`
+    pub fn of(target: Option<&'a VolumeTarget>, floor: f64) -> Self {
+        match target.and_then(VolumeTarget::hourly) {
+            Some(hours) => PlacementShape::Hourly(hours),
+            None => PlacementShape::Diurnal(floor),
+        }
+    }
+
+    /// Relative event density at `t`
+    fn weight(&self, t: &DateTime<chrono_tz::Tz>, cfg: &Config, day_str: &str) -> f64 {
+        match *self {
+            PlacementShape::Diurnal(floor) => diurnal_multiplier(t, cfg, day_str).max(floor).min(1.0),
+            PlacementShape::Hourly(hours) => hours[t.hour() as usize],
+        }
+    }
+}
+
+/// Start of an event in the `secs` seconds after `start`, following the diurnal curve lower-bounded by `floor`
+/// (uniform after 10 rejected draws). Offsets are elapsed seconds, so a 23 or 25 hour day is covered
+/// exactly and keeps the same event mass as any other day. Workers draw from the same curve through
+/// `PlacementCurve`, which does not evaluate it per draw
+pub fn sample_placement_time(
+    rng: &mut StdRng,
+    cfg: &Config,
+    day_str: &str,
+    start: &DateTime<chrono_tz::Tz>,
+    secs: i64,
+    floor: f64,
+) -> DateTime<chrono_tz::Tz> {
+    for _ in 0..10 {
+        let t = add_seconds(start, rng.gen_range(0..secs));
+        if rng.gen::<f64>() < diurnal_multiplier(&t, cfg, day_str).max(floor) {
+            return t;
+        }
+    }
+    add_seconds(start, rng.gen_range(0..secs))
+}
+
+/// Acceptance curve of `sample_placement_time` tabulated once per worker and day: the placement interval cut at
+/// local hour boundaries, each piece weighted by its capped multiplier times its length. A draw is one
+/// weighted pick of a piece plus a uniform offset in it, instead of up to 10 `diurnal_multiplier` calls
+pub struct PlacementCurve {
+    start: DateTime<chrono_tz::Tz>,
+    secs: i64,
+    // Elapsed-second bounds [from, to) of each piece
+    pieces: Vec<(i64, i64)>,
+    // None when the curve is zero everywhere: uniform, as the sampler falls back to
+    dist: Option<WeightedIndex<f64>>,
+}
+
+impl PlacementCurve {
+    /// Curve of the `secs` seconds after `start` on `day_str`, lower-bounded by `floor` and capped at 1
+    pub fn new(cfg: &Config, day_str: &str, start: &DateTime<chrono_tz::Tz>, secs: i64, floor: f64) -> Self {
+        PlacementCurve::shaped(cfg, day_str, start, secs, PlacementShape::Diurnal(floor))
+    }
+
+    /// Curve of the `secs` seconds after `start` on `day_str` following `shape`
+    pub fn shaped(cfg: &Config, day_str: &str, start: &DateTime<chrono_tz::Tz>, secs: i64, shape: PlacementShape) -> Self {
+        let mut pieces = Vec::with_capacity(26);
+        let mut weights = Vec::with_capacity(26);
+        let mut from = 0;
+        while from < secs {
+            let t = add_seconds(start, from);
+            // The multiplier only changes with the local hour
+            let to = (from + 3600 - (t.minute() * 60 + t.second()) as i64).min(secs);
+            let acceptance = shape.weight(&t, cfg, day_str);
+            pieces.push((from, to));
+            weights.push(acceptance * (to - from) as f64);
+            from = to;
+        }
+        PlacementCurve {
+            start: *start,
+            secs,
+            pieces,
+            dist: WeightedIndex::new(&weights).ok(),
+        }
+    }
+
+    /// Start of an event, following the curve
+    pub fn sample(&self, rng: &mut StdRng) -> DateTime<chrono_tz::Tz> {
+        let Some(dist) = self.dist.as_ref() else {
+            return add_seconds(&self.start, rng.gen_range(0..self.secs));
+        };
+        let (from, to) = self.pieces[dist.sample(rng)];
+        add_seconds(&self.start, rng.gen_range(from..to))
+    }
+}
+
 /// Calculate activity multiplier based on time of day, season, and special days
 pub fn diurnal_multiplier(dt: &DateTime<chrono_tz::Tz>, cfg: &Config, day_str: &str) -> f64 {
     let arr = if dt.weekday() == Weekday::Sat || dt.weekday() == Weekday::Sun {
@@ -110,27 +449,125 @@ pub fn diurnal_multiplier(dt: &DateTime<chrono_tz::Tz>, cfg: &Config, day_str: &
     base * seas * special
 }
 
+/// Shrink a DATA session placed (`placed_at`, in the placement zone) in an hour that `data_nighttime_floor`
+/// lifts to keep-alive size: its volumes are scaled by `data_night_volume_factor`
+fn shrink_background_session(event: &mut DataRecord, placed_at: &DateTime<chrono_tz::Tz>, cfg: &Config, day_str: &str) {
+    if diurnal_multiplier(placed_at, cfg, day_str) >= cfg.data_nighttime_floor {
+        return;
+    }
+    let shrink = |bytes: u64| ((bytes as f64 * cfg.data_night_volume_factor) as u64).max(1);
+    event.bytes_in = shrink(event.bytes_in);
+    event.bytes_out = shrink(event.bytes_out);
+}
+
+/// Logical record kinds, the keys of `record_type_map`
+pub const RECORD_KINDS: [&str; 6] = ["call_mo", "call_mt", "sms_mo", "sms_mt", "data_sgsn", "data_pgw"];
+
+/// `record_type` of each logical record kind, in the dialect of `record_type_profile`.
+/// DATA sessions are closed by the SGSN or the PGW at random
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordTypes {
+    pub call_mo: &'static str,
+    pub call_mt: &'static str,
+    pub sms_mo: &'static str,
+    pub sms_mt: &'static str,
+    pub data_sgsn: &'static str,
+    pub data_pgw: &'static str,
+}
+
+impl Default for RecordTypes {
+    fn default() -> Self {
+        RecordTypes::profile(RecordTypeProfile::Default)
+    }
+}
+
+/// Leak each distinct configured name once, however many generators are built
+pub(crate) fn intern(name: &str) -> &'static str {
+    static INTERNED: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+    let mut interned = INTERNED.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(&name) = interned.get(name) {
+        return name;
+    }
+    let name: &'static str = Box::leak(name.to_string().into_boxed_str());
+    interned.insert(name);
+    name
+}
+
+impl RecordTypes {
+    /// Built-in dialect; `Custom` has no names of its own and reads as `Default`
+    pub fn profile(profile: RecordTypeProfile) -> Self {
+        let [call_mo, call_mt, sms_mo, sms_mt, data_sgsn, data_pgw] = match profile {
+            RecordTypeProfile::Default | RecordTypeProfile::Custom => {
+                ["mscVoiceRecord", "mscVoiceRecord", "sgsnSMORecord", "sgsnSMTRecord", "sgsnPDPRecord", "pgwRecord"]
+            }
+            RecordTypeProfile::Ericsson => ["moCallRecord", "mtCallRecord", "moSMSRecord", "mtSMSRecord", "sgsnPDPRecord", "pGWRecord"],
+            RecordTypeProfile::Huawei => ["MOC", "MTC", "SMSMO", "SMSMT", "sgsnPDPRecord", "ggsnPDPRecord"],
+        };
+        RecordTypes { call_mo, call_mt, sms_mo, sms_mt, data_sgsn, data_pgw }
+    }
+
+    /// Names of `record_type_profile`. The custom profile needs a non-empty name for every kind of `RECORD_KINDS`
+    /// in `record_type_map`; the map is rejected with any other profile
+    pub fn from_config(cfg: &Config) -> anyhow::Result<Self> {
+        let map = &cfg.record_type_map;
+        if cfg.record_type_profile != RecordTypeProfile::Custom {
+            if !map.is_empty() {
+                anyhow::bail!("Config key record_type_map only applies to record_type_profile: custom");
+            }
+            return Ok(RecordTypes::profile(cfg.record_type_profile));
+        }
+        if let Some(kind) = map.keys().find(|kind| !RECORD_KINDS.contains(&kind.as_str())) {
+            anyhow::bail!("Config key record_type_map: unknown record kind {:?}, expected one of {}", kind, RECORD_KINDS.join(", "));
+        }
+        let name = |kind: &str| match map.get(kind).filter(|name| !name.is_empty()) {
+            Some(name) => Ok(intern(name)),
+            None => Err(anyhow::anyhow!("Config key record_type_map: no record_type for {} with record_type_profile: custom", kind)),
+        };
+        Ok(RecordTypes {
+            call_mo: name("call_mo")?,
+            call_mt: name("call_mt")?,
+            sms_mo: name("sms_mo")?,
+            sms_mt: name("sms_mt")?,
+            data_sgsn: name("data_sgsn")?,
+            data_pgw: name("data_pgw")?,
+        })
+    }
+
+    /// CALL or SMS record type of `direction` ("MO" or "MT")
+    fn of(&self, event_type: &str, direction: &str) -> &'static str {
+        match (event_type, direction) {
+            ("CALL", "MO") => self.call_mo,
+            ("CALL", _) => self.call_mt,
+            (_, "MO") => self.sms_mo,
+            _ => self.sms_mt,
+        }
+    }
+}
+
 /// Generate CALL events
 pub struct CallGenerator {
     p_mo: f64,
     dispo_pop: Vec<String>,
     dispo_dist: WeightedIndex<f64>,
-    mu: f64,
-    sigma: f64,
     duration_dist: LogNormal<f64>,  // Pre-computed distribution (OPTIMIZATION #4)
+    // Duration rule of each entry in `dispo_pop`
+    dispo_rules: Vec<DurationRule>,
+    record_types: RecordTypes,
 }
 
 impl CallGenerator {
-    pub fn new(cfg: &Config) -> Self {
+    pub fn new(cfg: &Config) -> anyhow::Result<Self> {
         let p_mo = cfg.mo_share_call;
 
-        let dispo_pop: Vec<String> = cfg.call_dispositions.keys().cloned().collect();
-        let dispo_wts: Vec<f64> = dispo_pop
-            .iter()
-            .map(|k| *cfg.call_dispositions.get(k).unwrap())
-            .collect();
+        // Sorted by name, so a seed draws the same dispositions in every process
+        let mut dispositions: Vec<(String, f64)> =
+            cfg.call_dispositions.iter().map(|(name, &weight)| (name.clone(), weight)).collect();
+        dispositions.sort_by(|a, b| a.0.cmp(&b.0));
+        let (dispo_pop, dispo_wts): (Vec<String>, Vec<f64>) = dispositions.into_iter().unzip();
 
-        let dispo_dist = WeightedIndex::new(&dispo_wts).unwrap();
+        let dispo_dist = WeightedIndex::new(&dispo_wts)
+            .map_err(|e| anyhow::anyhow!("Invalid call_dispositions {:?}: {}", cfg.call_dispositions, e))?;
+        let dispo_rules = dispo_pop.iter().map(|name| cfg.call_duration_rules.get(name).copied().unwrap_or_default()).collect();
 
         let (mu, sigma) = lognorm_params_from_quantiles(
             cfg.call_duration_quantiles.p50 as f64,
@@ -138,147 +575,128 @@ impl CallGenerator {
         );
 
         // Pre-compute LogNormal distribution (OPTIMIZATION #4)
-        let duration_dist = LogNormal::new(mu, sigma).unwrap();
+        let duration_dist = LogNormal::new(mu, sigma).map_err(|e| {
+            anyhow::anyhow!("Invalid call_duration_quantiles {:?}: {}", cfg.call_duration_quantiles, e)
+        })?;
 
-        CallGenerator {
+        Ok(CallGenerator {
             p_mo,
             dispo_pop,
             dispo_dist,
-            mu,
-            sigma,
             duration_dist,
-        }
+            dispo_rules,
+            record_types: RecordTypes::default(),
+        })
+    }
+
+    /// Name records in another dialect
+    pub fn with_record_types(mut self, record_types: RecordTypes) -> Self {
+        self.record_types = record_types;
+        self
+    }
+
+    /// `record_type` of a CALL record of `direction`
+    pub fn record_type(&self, direction: &str) -> &'static str {
+        self.record_types.of("CALL", direction)
+    }
+
+    /// Draw a disposition: (duration in seconds, cause for record closing)
+    fn draw_outcome(&self, rng: &mut StdRng) -> (i64, &'static str) {
+        let idx = self.dispo_dist.sample(rng);
+        let cause = match self.dispo_pop[idx].as_str() {
+            "ANSWERED" => {
+                let ring = rng.gen_range(2..=25);
+                // Use pre-computed distribution (OPTIMIZATION #4)
+                let dur = self.duration_dist.sample(rng).max(1.0) as i64;
+                return (ring + dur, "normalRelease");
+            }
+            "NO ANSWER" => "noAnswer",
+            "BUSY" => "busy",
+            // FAILED, CONGESTION or a custom disposition
+            _ => "failure",
+        };
+        let rule = self.dispo_rules[idx];
+        let dur = if rule.min_sec == rule.max_sec { rule.min_sec } else { rng.gen_range(rule.min_sec..=rule.max_sec) };
+        (dur as i64, cause)
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn generate(
         &self,
-        event: &mut EventRow,
-        sub: &Subscriber,
+        sub: &SubscriberIdentity,
         start_local: DateTime<chrono_tz::Tz>,
         other_msisdn: u64,
         tz_name: &'static str,
         cell_id: u32,
         rng: &mut StdRng,
-    ) {
+    ) -> CallRecord {
         let direction = if rng.gen::<f64>() < self.p_mo {
             "MO"
         } else {
             "MT"
         };
-
-        let (msisdn_src, msisdn_dst) = if direction == "MO" {
-            (sub.msisdn, other_msisdn)
-        } else {
-            (other_msisdn, sub.msisdn)
-        };
-
-        let dispo = &self.dispo_pop[self.dispo_dist.sample(rng)];
-
-        let (dur_sec, cause) = match dispo.as_str() {
-            "ANSWERED" => {
-                let ring = rng.gen_range(2..=25);
-                // Use pre-computed distribution (OPTIMIZATION #4)
-                let dur = self.duration_dist.sample(rng).max(1.0) as i64;
-                (ring + dur, "normalRelease")
-            }
-            "NO ANSWER" => {
-                let dur = rng.gen_range(5..=30);
-                (dur, "noAnswer")
-            }
-            "BUSY" => {
-                let dur = rng.gen_range(2..=10);
-                (dur, "busy")
-            }
-            _ => {
-                // FAILED or CONGESTION
-                let dur = rng.gen_range(1..=5);
-                (dur, "failure")
-            }
-        };
-
-        let end_local = start_local + Duration::seconds(dur_sec);
-
-        event.event_type = "CALL";
-        event.msisdn_src = msisdn_src;
-        event.msisdn_dst = msisdn_dst;
-        event.direction = direction;
-        event.start_ts_ms = to_epoch_ms(&start_local.with_timezone(&chrono::Utc));
-        event.end_ts_ms = to_epoch_ms(&end_local.with_timezone(&chrono::Utc));
-        event.tz_name = tz_name;
-        event.tz_offset_min = tz_offset_minutes(&start_local);
-        event.duration_sec = dur_sec;
-        event.mccmnc = sub.mccmnc;
-        event.imsi = sub.imsi;
-        event.imei = sub.imei;
-        event.cell_id = cell_id;
-        event.record_type = "mscVoiceRecord";
-        event.cause_for_record_closing = cause;
-        // Leave other fields at default (reset by pool)
+        self.generate_forced_direction(sub, start_local, other_msisdn, tz_name, cell_id, rng, direction)
     }
 
     /// Generate call event with forced direction (for MO↔MT correlation)
     /// This allows explicit MO or MT record generation
+    #[allow(clippy::too_many_arguments)]
     pub fn generate_forced_direction(
         &self,
-        event: &mut EventRow,
-        sub: &Subscriber,
+        sub: &SubscriberIdentity,
         start_local: DateTime<chrono_tz::Tz>,
         other_msisdn: u64,
         tz_name: &'static str,
         cell_id: u32,
         rng: &mut StdRng,
         forced_direction: &'static str,  // "MO" or "MT"
-    ) {
+    ) -> CallRecord {
         let direction = forced_direction;
 
         let (msisdn_src, msisdn_dst) = if direction == "MO" {
-            (sub.msisdn, other_msisdn)
+            (sub.msisdn.get(), other_msisdn)
         } else {
-            (other_msisdn, sub.msisdn)
+            (other_msisdn, sub.msisdn.get())
         };
 
-        let dispo = &self.dispo_pop[self.dispo_dist.sample(rng)];
-
-        let (dur_sec, cause) = match dispo.as_str() {
-            "ANSWERED" => {
-                let ring = rng.gen_range(2..=25);
-                // Use pre-computed distribution (OPTIMIZATION #4)
-                let dur = self.duration_dist.sample(rng).max(1.0) as i64;
-                (ring + dur, "normalRelease")
-            }
-            "NO ANSWER" => {
-                let dur = rng.gen_range(5..=30);
-                (dur, "noAnswer")
-            }
-            "BUSY" => {
-                let dur = rng.gen_range(2..=10);
-                (dur, "busy")
-            }
-            _ => {
-                // FAILED or CONGESTION
-                let dur = rng.gen_range(1..=5);
-                (dur, "failure")
-            }
-        };
+        let (dur_sec, cause) = self.draw_outcome(rng);
+
+        let end_local = add_seconds(&start_local, dur_sec);
+
+        CallRecord {
+            header: RecordHeader {
+                msisdn_src,
+                msisdn_dst,
+                direction,
+                start_ts_ms: to_epoch_ms(&start_local.with_timezone(&chrono::Utc)),
+                end_ts_ms: to_epoch_ms(&end_local.with_timezone(&chrono::Utc)),
+                tz_name,
+                tz_offset_min: tz_offset_minutes(&start_local),
+                duration_sec: dur_sec,
+                mccmnc: sub.mccmnc.get(),
+                imsi: sub.imsi.get(),
+                imei: sub.imei.get(),
+                cell_id,
+                record_type: self.record_type(direction),
+                cause_for_record_closing: cause,
+            },
+        }
+    }
 
-        let end_local = start_local + Duration::seconds(dur_sec);
-
-        event.event_type = "CALL";
-        event.msisdn_src = msisdn_src;
-        event.msisdn_dst = msisdn_dst;
-        event.direction = direction;
-        event.start_ts_ms = to_epoch_ms(&start_local.with_timezone(&chrono::Utc));
-        event.end_ts_ms = to_epoch_ms(&end_local.with_timezone(&chrono::Utc));
-        event.tz_name = tz_name;
-        event.tz_offset_min = tz_offset_minutes(&start_local);
-        event.duration_sec = dur_sec;
-        event.mccmnc = sub.mccmnc;
-        event.imsi = sub.imsi;
-        event.imei = sub.imei;
-        event.cell_id = cell_id;
-        event.record_type = "mscVoiceRecord";
-        event.cause_for_record_closing = cause;
-        // Leave other fields at default (reset by pool)
+    /// MT leg of the MO call `mo`, written for the called subscriber `callee`: same times and disposition
+    pub fn mt_leg(&self, mo: &CallRecord, callee: &SubscriberIdentity) -> CallRecord {
+        CallRecord {
+            header: RecordHeader {
+                msisdn_src: mo.header.msisdn_dst,
+                msisdn_dst: mo.header.msisdn_src,
+                direction: "MT",
+                mccmnc: callee.mccmnc.get(),
+                imsi: callee.imsi.get(),
+                imei: callee.imei.get(),
+                record_type: self.record_type("MT"),
+                ..mo.header
+            },
+        }
     }
 }
 
@@ -287,47 +705,111 @@ pub struct SmsGenerator {
     p_mo: f64,
     status_dist: WeightedIndex<f64>,
     segments_dist: WeightedIndex<f64>,
+    unreachable_rate: f64,
+    // Attempt offsets from submission, in minutes
+    retry_schedule: Vec<i64>,
+    record_types: RecordTypes,
 }
 
 impl SmsGenerator {
-    pub fn new(cfg: &Config) -> Self {
+    pub fn new(cfg: &Config) -> anyhow::Result<Self> {
         let status_weights = [0.1, 0.88, 0.02];
-        let status_dist = WeightedIndex::new(&status_weights).unwrap();
+        let status_dist = WeightedIndex::new(status_weights)
+            .map_err(|e| anyhow::anyhow!("Invalid SMS status weights {:?}: {}", status_weights, e))?;
 
         let segments_weights = [0.85, 0.13, 0.02];
-        let segments_dist = WeightedIndex::new(&segments_weights).unwrap();
+        let segments_dist = WeightedIndex::new(segments_weights)
+            .map_err(|e| anyhow::anyhow!("Invalid SMS segment weights {:?}: {}", segments_weights, e))?;
 
-        SmsGenerator {
+        Ok(SmsGenerator {
             p_mo: cfg.mo_share_sms,
             status_dist,
             segments_dist,
+            unreachable_rate: cfg.sms_unreachable_rate,
+            retry_schedule: cfg.sms_retry_schedule_min.iter().map(|&m| m as i64).collect(),
+            record_types: RecordTypes::default(),
+        })
+    }
+
+    /// Name records in another dialect
+    pub fn with_record_types(mut self, record_types: RecordTypes) -> Self {
+        self.record_types = record_types;
+        self
+    }
+
+    /// With `sms_unreachable_rate`, give `event` a correlation id and decide whether an MT SMS finds the
+    /// handset powered off. Returns the number of records of its retry chain (`event` being the first)
+    /// and whether it ends EXPIRED rather than DELIVERED; None for an SMS delivered as drawn
+    pub fn draw_unreachable(&self, event: &mut SmsRecord, rng: &mut StdRng) -> Option<(usize, bool)> {
+        if self.unreachable_rate <= 0.0 {
+            return None;
+        }
+        event.correlation_id = rng.gen_range(1..CORRELATION_ID_NONE);
+        if event.header.direction != "MT" || rng.gen::<f64>() >= self.unreachable_rate {
+            return None;
         }
+        // The attempt that gets through; one past the schedule means none does
+        let attempts = self.retry_schedule.len();
+        let delivered_at = rng.gen_range(1..=attempts);
+        Some((delivered_at.min(attempts - 1) + 1, delivered_at == attempts))
     }
 
+    /// Record `idx` of a retry chain of `records` whose first record `first` was submitted at `start_local`.
+    /// Earlier records are failed ATTEMPTs; the last one is DELIVERED or EXPIRED. Attempts past the end
+    /// of the day stay with the day the SMS was submitted
+    pub fn retry_record(
+        &self,
+        first: &SmsRecord,
+        start_local: DateTime<chrono_tz::Tz>,
+        idx: usize,
+        records: usize,
+        expired: bool,
+    ) -> SmsRecord {
+        let attempt_local = add_seconds(&start_local, self.retry_schedule[idx] * 60);
+        let start_ts_ms = to_epoch_ms(&attempt_local.with_timezone(&chrono::Utc));
+        let (sms_status, cause) = match (idx + 1 == records, expired) {
+            (false, _) => ("ATTEMPT", "deliveryFailure"),
+            (true, false) => ("DELIVERED", "deliverySuccess"),
+            (true, true) => ("EXPIRED", "deliveryFailure"),
+        };
+        SmsRecord {
+            header: RecordHeader {
+                start_ts_ms,
+                end_ts_ms: start_ts_ms + (first.header.end_ts_ms - first.header.start_ts_ms),
+                tz_offset_min: tz_offset_minutes(&attempt_local),
+                cause_for_record_closing: cause,
+                ..first.header
+            },
+            sms_status,
+            ..*first
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn generate(
         &self,
-        event: &mut EventRow,
-        sub: &Subscriber,
+        sub: &SubscriberIdentity,
         start_local: DateTime<chrono_tz::Tz>,
         other_msisdn: u64,
         tz_name: &'static str,
         cell_id: u32,
         rng: &mut StdRng,
-    ) {
+    ) -> SmsRecord {
         let direction = if rng.gen::<f64>() < self.p_mo {
             "MO"
         } else {
             "MT"
         };
 
-        let (msisdn_src, msisdn_dst, record_type) = if direction == "MO" {
-            (sub.msisdn, other_msisdn, "sgsnSMORecord")
+        let (msisdn_src, msisdn_dst) = if direction == "MO" {
+            (sub.msisdn.get(), other_msisdn)
         } else {
-            (other_msisdn, sub.msisdn, "sgsnSMTRecord")
+            (other_msisdn, sub.msisdn.get())
         };
+        let record_type = self.record_types.of("SMS", direction);
 
         let dur = rng.gen_range(1..=5);
-        let end_local = start_local + Duration::seconds(dur);
+        let end_local = add_seconds(&start_local, dur);
 
         let sms_status = match self.status_dist.sample(rng) {
             0 => "SENT",
@@ -347,309 +829,900 @@ impl SmsGenerator {
             _ => 3,
         };
 
-        event.event_type = "SMS";
-        event.msisdn_src = msisdn_src;
-        event.msisdn_dst = msisdn_dst;
-        event.direction = direction;
-        event.start_ts_ms = to_epoch_ms(&start_local.with_timezone(&chrono::Utc));
-        event.end_ts_ms = to_epoch_ms(&end_local.with_timezone(&chrono::Utc));
-        event.tz_name = tz_name;
-        event.tz_offset_min = tz_offset_minutes(&start_local);
-        event.duration_sec = dur;
-        event.mccmnc = sub.mccmnc;
-        event.imsi = sub.imsi;
-        event.imei = sub.imei;
-        event.cell_id = cell_id;
-        event.record_type = record_type;
-        event.cause_for_record_closing = cause;
-        event.sms_segments = sms_segments;
-        event.sms_status = sms_status;
-        // Leave data fields at default (reset by pool)
+        SmsRecord {
+            header: RecordHeader {
+                msisdn_src,
+                msisdn_dst,
+                direction,
+                start_ts_ms: to_epoch_ms(&start_local.with_timezone(&chrono::Utc)),
+                end_ts_ms: to_epoch_ms(&end_local.with_timezone(&chrono::Utc)),
+                tz_name,
+                tz_offset_min: tz_offset_minutes(&start_local),
+                duration_sec: dur,
+                mccmnc: sub.mccmnc.get(),
+                imsi: sub.imsi.get(),
+                imei: sub.imei.get(),
+                cell_id,
+                record_type,
+                cause_for_record_closing: cause,
+            },
+            sms_segments,
+            sms_status,
+            correlation_id: 0,
+        }
     }
 }
 
+/// Cell ids of CALL/SMS rows, and of DATA rows when there is no catalog
+pub const RANDOM_CELL_IDS: std::ops::Range<u32> = 10_000..100_000;
+
+/// Per-RAT cell lists for DATA sessions; an empty catalog (`cells: 0`) means no catalog
+fn data_cells(cells: &CellsCatalog) -> Option<HashMap<String, Vec<u32>>> {
+    let (cells_all, cells_by_rat) = cells;
+    (!cells_all.is_empty()).then(|| cells_by_rat.clone())
+}
+
+/// Downlink bytes and duration distributions of an APN profile, and its volume cap
+type ApnTraffic = (Normal<f64>, Normal<f64>, u64);
+
+/// RAT and cell of a subscriber's last DATA session of the day, which the next session stays on unless it hands over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServingCell {
+    pub rat: &'static str,
+    pub cell_id: u32,
+}
+
+/// A subscriber's DATA sessions of the day so far: where the last one was served and how many bearers were opened
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DataSessions {
+    pub serving: Option<ServingCell>,
+    pub bearers: u32,
+}
+
+/// QCI / 5QI of sessions on an APN without an entry in `qci_by_apn` / `nr_5qi_by_apn`: best-effort internet
+const DEFAULT_QCI: u32 = 9;
+
+// QCI values of one APN and their weights
+type QciTable = (Vec<u32>, WeightedIndex<f64>);
+
 /// Generate DATA session events
 pub struct DataGenerator {
-    cells_by_rat: HashMap<String, Vec<u32>>,
-    cells_all: Vec<u32>,
-    rat_dist: WeightedIndex<f64>,
+    // RAT -> cells of that RAT; None = no catalog, cell ids are drawn at random
+    cells_by_rat: Option<HashMap<String, Vec<u32>>>,
+    rat_mix: RatMix,
+    apns: Vec<&'static str>,
     apn_dist: WeightedIndex<f64>,
+    // Per entry of `apns`; None follows the RAT defaults
+    apn_profiles: Vec<Option<ApnTraffic>>,
+    close_causes: Vec<&'static str>,
+    close_cause_dist: WeightedIndex<f64>,
+    record_types: RecordTypes,
+    // Chance a session leaves the serving cell of the previous one
+    handover_prob: f64,
+    // Per entry of `apns`: QCIs of non-NR sessions and 5QIs of NR sessions (None = `DEFAULT_QCI`).
+    // Empty without bearer fields: no draw and QCI 0
+    qcis: Vec<[Option<QciTable>; 2]>,
 }
 
 impl DataGenerator {
-    pub fn new(cells_by_rat: HashMap<String, Vec<u32>>, cells_all: Vec<u32>) -> Self {
-        let rat_weights = [0.3, 0.5, 0.2];
-        let rat_dist = WeightedIndex::new(&rat_weights).unwrap();
-
-        let apn_weights = [0.8, 0.1, 0.1];
-        let apn_dist = WeightedIndex::new(&apn_weights).unwrap();
+    /// `rat_mix` should be the mix the cells catalog was generated with; `apn_mix` maps APN -> weight,
+    /// `apn_profiles` APN -> traffic profile. With a catalog every RAT of the mix must have cells,
+    /// so each session uses a cell of its own RAT
+    pub fn new(
+        cells_by_rat: Option<HashMap<String, Vec<u32>>>,
+        rat_mix: RatMix,
+        apn_mix: &BTreeMap<String, f64>,
+        apn_profiles: &BTreeMap<String, ApnProfile>,
+    ) -> anyhow::Result<Self> {
+        if let Some(ref cells_by_rat) = cells_by_rat {
+            if let Some(rat) = rat_mix.rats().find(|rat| cells_by_rat.get(*rat).is_none_or(Vec::is_empty)) {
+                anyhow::bail!("Cells catalog has no {} cells for the configured rat_mix; regenerate cells.csv", rat);
+            }
+        }
 
-        DataGenerator {
+        // 'static str for zero-copy EventRow usage, leaked once per distinct APN
+        let apns = apn_mix.keys().map(|apn| intern(apn)).collect();
+        let apn_dist = WeightedIndex::new(apn_mix.values())
+            .map_err(|e| anyhow::anyhow!("Invalid apn_mix {:?}: {}", apn_mix, e))?;
+        let apn_profiles = apn_mix
+            .keys()
+            .map(|apn| {
+                let Some(p) = apn_profiles.get(apn) else {
+                    return Ok(None);
+                };
+                let invalid = |e| anyhow::anyhow!("Invalid apn_profiles entry {}: {}", apn, e);
+                let bytes = Normal::new(p.bytes_mean, p.bytes_sd).map_err(invalid)?;
+                let dur = Normal::new(p.dur_mean, p.dur_sd).map_err(invalid)?;
+                Ok(Some((bytes, dur, p.max_bytes)))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(DataGenerator {
             cells_by_rat,
-            cells_all,
-            rat_dist,
+            rat_mix,
+            apns,
             apn_dist,
-        }
+            apn_profiles,
+            close_causes: vec!["normalRelease"],
+            close_cause_dist: WeightedIndex::new([1.0]).expect("one positive weight"),
+            record_types: RecordTypes::default(),
+            handover_prob: 1.0,
+            qcis: Vec::new(),
+        })
+    }
+
+    /// Draw each session's QCI from its APN's entry of `qci_by_apn` (5QI from `nr_5qi_by_apn` on NR),
+    /// APN -> QCI -> weight; APNs without an entry get QCI 9
+    pub fn with_qcis(
+        mut self,
+        qci_by_apn: &BTreeMap<String, BTreeMap<u32, f64>>,
+        nr_5qi_by_apn: &BTreeMap<String, BTreeMap<u32, f64>>,
+    ) -> anyhow::Result<Self> {
+        let table = |key: &str, tables: &BTreeMap<String, BTreeMap<u32, f64>>, apn: &str| {
+            let Some(weights) = tables.get(apn) else {
+                return Ok::<Option<QciTable>, anyhow::Error>(None);
+            };
+            let dist = WeightedIndex::new(weights.values())
+                .map_err(|e| anyhow::anyhow!("Invalid {} entry {} {:?}: {}", key, apn, weights, e))?;
+            Ok(Some((weights.keys().copied().collect(), dist)))
+        };
+        self.qcis = self
+            .apns
+            .iter()
+            .map(|apn| Ok([table("qci_by_apn", qci_by_apn, apn)?, table("nr_5qi_by_apn", nr_5qi_by_apn, apn)?]))
+            .collect::<anyhow::Result<_>>()?;
+        Ok(self)
+    }
+
+    /// Keep a subscriber's sessions on the serving cell of the previous one, except for a handover with
+    /// probability `handover_prob` (1 = a new RAT and cell every session)
+    pub fn with_handover_prob(mut self, handover_prob: f64) -> Self {
+        self.handover_prob = handover_prob.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Close sessions with the causes of `causes` (cause -> weight) instead of always normalRelease
+    pub fn with_close_causes(mut self, causes: &BTreeMap<String, f64>) -> anyhow::Result<Self> {
+        self.close_cause_dist = WeightedIndex::new(causes.values())
+            .map_err(|e| anyhow::anyhow!("Invalid data_close_causes {:?}: {}", causes, e))?;
+        self.close_causes = causes.keys().map(|cause| intern(cause)).collect();
+        Ok(self)
     }
 
+    /// Name records in another dialect
+    pub fn with_record_types(mut self, record_types: RecordTypes) -> Self {
+        self.record_types = record_types;
+        self
+    }
+
+    /// A session on a newly drawn RAT and cell
     pub fn generate(
         &self,
-        event: &mut EventRow,
-        sub: &Subscriber,
+        sub: &SubscriberIdentity,
         start_local: DateTime<chrono_tz::Tz>,
         tz_name: &'static str,
         rng: &mut StdRng,
-    ) {
-        let rat = match self.rat_dist.sample(rng) {
-            0 => "WCDMA",
-            1 => "LTE",
-            _ => "NR",
-        };
+    ) -> DataRecord {
+        self.generate_from(&mut DataSessions::default(), sub, start_local, tz_name, rng)
+    }
 
-        let (down_mean, down_sd, up_ratio_min, up_ratio_max, dur_mean, dur_sd) = match rat {
+    /// The next session of a subscriber after `sessions`, which this session is added to
+    pub fn generate_from(
+        &self,
+        sessions: &mut DataSessions,
+        sub: &SubscriberIdentity,
+        start_local: DateTime<chrono_tz::Tz>,
+        tz_name: &'static str,
+        rng: &mut StdRng,
+    ) -> DataRecord {
+        // Stay on the serving cell unless the session hands over
+        let stay = sessions.serving.filter(|_| rng.gen::<f64>() >= self.handover_prob);
+        let rat = stay.map_or_else(|| self.rat_mix.sample(rng), |cell| cell.rat);
+        let apn_idx = self.apn_dist.sample(rng);
+        let apn = self.apns[apn_idx];
+
+        let (down_mean, down_sd, up_ratio_min, up_ratio_max, dur_mean, dur_sd): (f64, f64, f64, f64, f64, f64) = match rat {
             "LTE" => (4_000_000.0, 2_000_000.0, 0.1, 0.3, 300.0, 180.0),
             "NR" => (12_000_000.0, 8_000_000.0, 0.1, 0.35, 240.0, 180.0),
             _ => (1_000_000.0, 600_000.0, 0.08, 0.25, 420.0, 240.0),
         };
 
-        let dur_normal = Normal::new(dur_mean, dur_sd).unwrap();
-        let dur = (dur_normal.sample(rng) as f64).abs().max(5.0) as i64;
-        let end_local = start_local + Duration::seconds(dur);
-
-        let down_normal = Normal::new(down_mean, down_sd).unwrap();
-        let down = (down_normal.sample(rng) as f64).abs().max(2_000.0) as u64;
-        let up = (down as f64 * rng.gen_range(up_ratio_min..=up_ratio_max))
-            .max(1_000.0) as u64;
-
-        let apn = match self.apn_dist.sample(rng) {
-            0 => "internet",
-            1 => "ims",
-            _ => "mms",
+        let profile = self.apn_profiles[apn_idx];
+        // Duration and downlink bytes of a session of this RAT and APN
+        let draw = |rng: &mut StdRng| match profile {
+            Some((bytes_dist, dur_dist, max_bytes)) => {
+                let dur = dur_dist.sample(rng).abs().max(1.0) as i64;
+                // Faster RATs move more of the same content, up to the APN's cap
+                let speed = down_mean / 4_000_000.0;
+                let down = (bytes_dist.sample(rng).abs() * speed).clamp(200.0, max_bytes.max(200) as f64) as u64;
+                (dur, down)
+            }
+            None => {
+                let dur_normal = Normal::new(dur_mean, dur_sd).unwrap();
+                let dur = dur_normal.sample(rng).abs().max(5.0) as i64;
+                let down_normal = Normal::new(down_mean, down_sd).unwrap();
+                let down = down_normal.sample(rng).abs().max(2_000.0) as u64;
+                (dur, down)
+            }
         };
+        let min_up = if profile.is_some() { 100.0 } else { 1_000.0 };
+        let (mut dur, mut down) = draw(rng);
+
+        // The cause shapes the session it closes
+        let cause = self.close_causes[self.close_cause_dist.sample(rng)];
+        match cause {
+            // Dropped part way: a share of the duration and volume
+            "abnormalRelease" => {
+                let kept = rng.gen_range(0.05..0.6);
+                dur = ((dur as f64 * kept) as i64).max(1);
+                down = ((down as f64 * kept) as u64).max(1);
+            }
+            // A handover to another SGSN or the partial-record timer ends long sessions: the longer of two draws
+            "sgsnChange" | "timeLimit" => {
+                let other = draw(rng);
+                if other.0 > dur {
+                    (dur, down) = other;
+                }
+            }
+            // The volume trigger ends heavy sessions: the heavier of two draws
+            "volumeLimit" => {
+                let other = draw(rng);
+                if other.1 > down {
+                    (dur, down) = other;
+                }
+            }
+            _ => {}
+        }
+        let end_local = add_seconds(&start_local, dur);
+        let up = (down as f64 * rng.gen_range(up_ratio_min..=up_ratio_max))
+            .max(min_up) as u64;
 
-        let candidates = self.cells_by_rat.get(rat).unwrap_or(&self.cells_all);
-        let cell_id = if !candidates.is_empty() {
-            candidates[rng.gen_range(0..candidates.len())]
-        } else {
-            rng.gen_range(10_000..100_000)
+        let cell_id = match (stay, &self.cells_by_rat) {
+            (Some(cell), _) => cell.cell_id,
+            (None, Some(cells_by_rat)) => {
+                let candidates = &cells_by_rat[rat];
+                candidates[rng.gen_range(0..candidates.len())]
+            }
+            (None, None) => rng.gen_range(RANDOM_CELL_IDS),
         };
+        sessions.serving = Some(ServingCell { rat, cell_id });
+        sessions.bearers += 1;
 
-        let record_types = ["sgsnPDPRecord", "pgwRecord"];
+        let record_types = [self.record_types.data_sgsn, self.record_types.data_pgw];
         let record_type = record_types[rng.gen_range(0..record_types.len())];
 
-        event.event_type = "DATA";
-        event.msisdn_src = sub.msisdn;
-        event.msisdn_dst = 0;
-        event.direction = "MO";
-        event.start_ts_ms = to_epoch_ms(&start_local.with_timezone(&chrono::Utc));
-        event.end_ts_ms = to_epoch_ms(&end_local.with_timezone(&chrono::Utc));
-        event.tz_name = tz_name;
-        event.tz_offset_min = tz_offset_minutes(&start_local);
-        event.duration_sec = dur;
-        event.mccmnc = sub.mccmnc;
-        event.imsi = sub.imsi;
-        event.imei = sub.imei;
-        event.cell_id = cell_id;
-        event.record_type = record_type;
-        event.cause_for_record_closing = "normalRelease";
-        event.data_bytes_in = up;
-        event.data_bytes_out = down;
-        event.data_duration_sec = dur;
-        event.apn = apn;
-        event.rat = rat;
-        // Leave SMS fields at default (reset by pool)
+        let qci = match self.qcis.get(apn_idx).map(|tables| &tables[(rat == "NR") as usize]) {
+            Some(Some((values, dist))) => values[dist.sample(rng)],
+            Some(None) => DEFAULT_QCI,
+            None => 0,
+        };
+
+        DataRecord {
+            header: RecordHeader {
+                msisdn_src: sub.msisdn.get(),
+                msisdn_dst: 0,
+                direction: "MO",
+                start_ts_ms: to_epoch_ms(&start_local.with_timezone(&chrono::Utc)),
+                end_ts_ms: to_epoch_ms(&end_local.with_timezone(&chrono::Utc)),
+                tz_name,
+                tz_offset_min: tz_offset_minutes(&start_local),
+                duration_sec: dur,
+                mccmnc: sub.mccmnc.get(),
+                imsi: sub.imsi.get(),
+                imei: sub.imei.get(),
+                cell_id,
+                record_type,
+                cause_for_record_closing: cause,
+            },
+            // Packet core records carry the IMEISV; MSC voice records only the IMEI
+            imeisv: sub.imeisv(),
+            bytes_in: up,
+            bytes_out: down,
+            data_duration_sec: dur,
+            apn,
+            rat,
+            qci,
+            bearer_id: sessions.bearers,
+        }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ShardStats {
-    pub shard: usize,
-    pub calls: usize,
-    pub sms: usize,
-    pub data: usize,
+/// Give up placing a call after this many shifts past overlapping calls
+const MAX_CALL_SHIFTS: usize = 8;
+
+/// Per-shard calendar of CALL intervals by served IMSI, used to keep calls from overlapping, also across the
+/// lines of a dual-line IMSI. Only calls placed by the same shard are seen: a callee served by another shard,
+/// or a second line whose number falls in another worker's range, can still overlap.
+/// Disabled when concurrent calls are allowed: nothing is stored and every slot is free
+pub struct CallSchedule {
+    enabled: bool,
+    busy: HashMap<u64, Vec<(i64, i64)>>,
 }
 
-/// Worker process that generates events for a shard of users
-pub fn worker_generate(
-    day: DateTime<chrono_tz::Tz>,
-    shard_id: usize,
-    users_range: (usize, usize),
-    cfg: &Config,
-    out_dir: &Path,
-    subscriber_db_path: Option<&Path>,
-    redb: Option<&std::sync::Arc<SubscriberDbRedb>>,
-    writer_tx: Sender<WriterMessage>,
-) -> anyhow::Result<()> {
-    // If redb database is provided, use chunked processing for memory efficiency
-    if let Some(redb_arc) = redb {
-        return worker_generate_redb_chunked(
-            day,
-            shard_id,
-            users_range,
-            cfg,
-            out_dir,
-            redb_arc.clone(),
-            writer_tx,
-        );
+impl CallSchedule {
+    pub fn new(allow_concurrent_calls: bool) -> Self {
+        CallSchedule {
+            enabled: !allow_concurrent_calls,
+            busy: HashMap::new(),
+        }
     }
 
-    use chrono::Duration;
+    /// End of the latest booked call of `imsi` intersecting [start_ms, end_ms], if any
+    pub fn conflict_end(&self, imsi: u64, start_ms: i64, end_ms: i64) -> Option<i64> {
+        self.busy
+            .get(&imsi)?
+            .iter()
+            .filter(|&&(s, e)| s <= end_ms && start_ms <= e)
+            .map(|&(_, e)| e)
+            .max()
+    }
 
-    let seed = (cfg.workers as u64).wrapping_mul(1000) + shard_id as u64;
-    let mut rng = StdRng::seed_from_u64(seed);
+    /// Book [start_ms, end_ms] for `imsi` if it is free
+    pub fn try_book(&mut self, imsi: u64, start_ms: i64, end_ms: i64) -> bool {
+        if !self.enabled {
+            return true;
+        }
+        if self.conflict_end(imsi, start_ms, end_ms).is_some() {
+            return false;
+        }
+        self.busy.entry(imsi).or_default().push((start_ms, end_ms));
+        true
+    }
+}
 
-    // Load and filter subscriber database for this worker's subscriber range (CSV format only)
-    let subscriber_db = if let Some(db_path) = subscriber_db_path {
-        let (start_u, end_u) = users_range;
+/// One subscriber's own events of the day, held back with `emit_prev_event_ts` so they can be
+/// stamped with the previous event's start. Bounded by the subscriber's sampled event counts
+pub struct Timeline {
+    enabled: bool,
+    rows: Vec<EventRow>,
+}
 
-        // CSV loading: load all then filter
-        let full_db = SubscriberDatabase::load_from_csv(db_path)?;
-        let mut filtered_db = full_db.filter_by_msisdn_range(start_u, end_u, &cfg.prefixes);
+impl Timeline {
+    pub fn new(emit_prev_event_ts: bool) -> Self {
+        Timeline {
+            enabled: emit_prev_event_ts,
+            rows: Vec::new(),
+        }
+    }
 
-        // Build snapshots for fast lookup
-        filtered_db.build_snapshots();
+    /// Keep a copy of `row` for the end of the subscriber; false when it should be pushed right away
+    pub fn hold(&mut self, row: &EventRow) -> bool {
+        if self.enabled {
+            self.rows.push(row.clone());
+        }
+        self.enabled
+    }
 
-        Some(filtered_db)
-    } else {
-        None
-    };
+    /// MT leg written for a contact outside its own timeline: the previous event is not known
+    pub fn stamp_alone(&self, row: &mut EventRow) {
+        if self.enabled {
+            row.prev_event_ts_ms = PREV_EVENT_TS_NONE;
+        }
+    }
 
-    let tz = tz_from_name(&cfg.tz_name);
-    // Convert to 'static str for zero-copy EventRow usage
-    let tz_name: &'static str = Box::leak(cfg.tz_name.clone().into_boxed_str());
+    /// Held rows in time order, each stamped with the start of the one before it
+    pub fn drain_stamped(&mut self) -> std::vec::Drain<'_, EventRow> {
+        self.rows.sort_by_key(|row| row.start_ts_ms);
+        let mut prev = PREV_EVENT_TS_NONE;
+        for row in &mut self.rows {
+            row.prev_event_ts_ms = prev;
+            prev = row.start_ts_ms;
+        }
+        self.rows.drain(..)
+    }
+}
 
-    // Build contacts & subscribers for this shard
+/// Generate an MO call for `sub` at `start_local`, shifting it past the subscriber's booked calls.
+/// Returns the call and its final start time, or None when no free slot is left in the day
+#[allow(clippy::too_many_arguments)]
+fn place_call(
+    call_gen: &CallGenerator,
+    schedule: &mut CallSchedule,
+    sub: &SubscriberIdentity,
+    mut start_local: DateTime<chrono_tz::Tz>,
+    day_end_local: DateTime<chrono_tz::Tz>,
+    other_msisdn: u64,
+    tz_name: &'static str,
+    cell_id: u32,
+    rng: &mut StdRng,
+) -> Option<(CallRecord, DateTime<chrono_tz::Tz>)> {
+    for _ in 0..=MAX_CALL_SHIFTS {
+        let call = call_gen.generate_forced_direction(sub, start_local, other_msisdn, tz_name, cell_id, rng, "MO");
+        let (start_ms, end_ms) = (call.header.start_ts_ms, call.header.end_ts_ms);
+        if schedule.try_book(sub.imsi.get(), start_ms, end_ms) {
+            return Some((call, start_local));
+        }
+
+        // Start again a little after the call it collided with
+        let conflict_end = schedule.conflict_end(sub.imsi.get(), start_ms, end_ms)?;
+        let gap_ms = rng.gen_range(5..=120) * 1000;
+        start_local += Duration::milliseconds(conflict_end - start_ms + gap_ms);
+        if start_local >= day_end_local {
+            return None;
+        }
+    }
+    None
+}
+
+/// The other party calls back shortly after a missed MO call: MT record for `sub`.
+/// Returns the callback and its start time, or None if it does not fit the day or the schedule
+#[allow(clippy::too_many_arguments)]
+fn place_callback(
+    call_gen: &CallGenerator,
+    schedule: &mut CallSchedule,
+    sub: &SubscriberIdentity,
+    missed_end_ms: i64,
+    day_end_local: DateTime<chrono_tz::Tz>,
+    other_msisdn: u64,
+    tz_name: &'static str,
+    cell_id: u32,
+    rng: &mut StdRng,
+) -> Option<(CallRecord, DateTime<chrono_tz::Tz>)> {
+    let delay_ms = rng.gen_range(30..=600) * 1000;
+    let start_local = DateTime::from_timestamp_millis(missed_end_ms + delay_ms)?.with_timezone(&day_end_local.timezone());
+    if start_local >= day_end_local {
+        return None;
+    }
+
+    let call = call_gen.generate_forced_direction(sub, start_local, other_msisdn, tz_name, cell_id, rng, "MT");
+    schedule
+        .try_book(sub.imsi.get(), call.header.start_ts_ms, call.header.end_ts_ms)
+        .then_some((call, start_local))
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShardStats {
+    pub shard: usize,
+    pub calls: usize,
+    pub sms: usize,
+    pub data: usize,
+    pub active_subscribers: usize,
+    pub inactive_subscribers: usize,
+    pub skipped_subscribers: usize,
+    // Events by local hour of start time
+    #[serde(default)]
+    pub calls_by_hour: [usize; 24],
+    #[serde(default)]
+    pub sms_by_hour: [usize; 24],
+    #[serde(default)]
+    pub data_by_hour: [usize; 24],
+    // Rows written with dirty_data faults
+    #[serde(default)]
+    pub dirty_rows: usize,
+    // MT SMS that found the handset powered off, and the rows of their retry chains after the first
+    // (`sms` counts each message once)
+    #[serde(default)]
+    pub sms_unreachable: usize,
+    #[serde(default)]
+    pub sms_retry_rows: usize,
+    // Rows the self-check validated, and those that broke an invariant
+    #[serde(default)]
+    pub self_check_rows: usize,
+    #[serde(default)]
+    pub self_check_violations: usize,
+    // MSISDNs that originated rows, for the day-over-day overlap
+    #[serde(skip)]
+    pub activity: ShardActivity,
+}
+
+impl ShardStats {
+    fn record_call(&mut self, start_local: &DateTime<chrono_tz::Tz>) {
+        self.calls += 1;
+        self.calls_by_hour[start_local.hour() as usize] += 1;
+    }
+
+    fn record_sms(&mut self, start_local: &DateTime<chrono_tz::Tz>) {
+        self.sms += 1;
+        self.sms_by_hour[start_local.hour() as usize] += 1;
+    }
+
+    fn record_sms_retries(&mut self, records: usize) {
+        self.sms_unreachable += 1;
+        self.sms_retry_rows += records - 1;
+    }
+
+    fn record_data(&mut self, start_local: &DateTime<chrono_tz::Tz>) {
+        self.data += 1;
+        self.data_by_hour[start_local.hour() as usize] += 1;
+    }
+}
+
+/// Write a shard's stats as `stats_shardNNN.json` into the day directory (debugging aid)
+pub fn write_shard_stats(stats: &ShardStats, day_dir: &Path) -> anyhow::Result<()> {
+    let stat_path = day_dir.join(format!("stats_shard{:03}.json", stats.shard));
+    let stats_json = serde_json::to_string_pretty(stats)?;
+    std::fs::write(stat_path, stats_json)?;
+    Ok(())
+}
+
+/// What a worker needs every day of a run and builds only once: generators, parsed config, the contacts
+/// of its subscribers and its part of a CSV subscriber database. `DayGenerator` keeps one per worker range
+pub struct WorkerContext {
+    shard_id: usize,
+    users_range: (usize, usize),
+    // Subscribers of all workers, which `daily_targets` are spread over
+    population: usize,
+    tz: chrono_tz::Tz,
+    // Leaked once per run for zero-copy EventRow usage
+    tz_name: &'static str,
+    call_gen: CallGenerator,
+    sms_gen: SmsGenerator,
+    data_gen: DataGenerator,
+    record_types: RecordTypes,
+    segments: Segments,
+    numeric_prefixes: Vec<u64>,
+    mccmnc_pool: Vec<Mccmnc>,
+    operators: HashMap<u64, Mccmnc>,
+    // Random and CSV subscribers only; the redb path draws counterparts from the index range
+    contacts: Vec<Contacts>,
+    // Filtered to the worker's range, with snapshots built
+    subscriber_db: Option<SubscriberDatabase>,
+    // Seed of this worker's RNG streams, from the run seed, the worker count and the shard
+    seed: u64,
+    // Targeted run (`msisdn_file`): the listed MSISDNs of all workers, subscriber index -> MSISDN
+    targets: Option<Arc<[u64]>>,
+}
+
+impl WorkerContext {
+    /// Context of worker `shard_id` serving subscribers `users_range` of a run over `population` subscribers;
+    /// `redb`: subscribers come from a redb database
+    pub fn new(
+        shard_id: usize,
+        users_range: (usize, usize),
+        population: usize,
+        cfg: &Config,
+        cells: &CellsCatalog,
+        subscriber_db_path: Option<&Path>,
+        redb: bool,
+    ) -> anyhow::Result<Self> {
+        let shard_key = (cfg.workers as u64).wrapping_mul(1000) + shard_id as u64;
+        let seed = splitmix(cfg.seed ^ shard_key.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        let mut rng = StdRng::seed_from_u64(seed);
+        let (start_u, end_u) = users_range;
+
+        // Load and filter subscriber database for this worker's subscriber range (CSV or Arrow event history)
+        let subscriber_db = match subscriber_db_path {
+            // Arrow: rows of other ranges are skipped while reading
+            #[cfg(feature = "arrow")]
+            Some(db_path) if !redb && db_path.extension().is_some_and(|ext| ext == "arrow") => {
+                let range = MsisdnIndexRange::new(start_u, end_u, &cfg.prefixes)?;
+                let mut filtered_db = load_database_from_arrow_in_range(db_path, &range)?;
+                filtered_db.build_snapshots();
+                Some(filtered_db)
+            }
+            #[cfg(not(feature = "arrow"))]
+            Some(db_path) if !redb && db_path.extension().is_some_and(|ext| ext == "arrow") => {
+                anyhow::bail!("An Arrow subscriber database requires building with `--features arrow`")
+            }
+            Some(db_path) if !redb => {
+                // CSV loading: load all then filter
+                let full_db = SubscriberDatabase::load_from_csv(db_path)?;
+                let mut filtered_db = full_db.filter_by_msisdn_range(start_u, end_u, &cfg.prefixes)?;
+
+                // Build snapshots for fast lookup
+                filtered_db.build_snapshots();
+
+                Some(filtered_db)
+            }
+            _ => None,
+        };
+
+        let record_types = RecordTypes::from_config(cfg)?;
+
+        // Pre-allocate with exact capacity to avoid reallocations
+        let contacts = if redb { Vec::new() } else { build_contacts(end_u - start_u, &cfg.contacts, &mut rng) };
+
+        let data_gen = DataGenerator::new(data_cells(cells), RatMix::new(&cfg.rat_mix)?, &cfg.apn_mix, &cfg.apn_profiles)?
+            .with_close_causes(&cfg.data_close_causes)?
+            .with_record_types(record_types)
+            .with_handover_prob(cfg.data_handover_prob);
+        // QCIs are only drawn when written, so runs without the columns keep their sessions
+        let data_gen = if cfg.emit_bearer_fields {
+            data_gen.with_qcis(&cfg.qci_by_apn, &cfg.nr_5qi_by_apn)?
+        } else {
+            data_gen
+        };
+
+        Ok(WorkerContext {
+            shard_id,
+            users_range,
+            population,
+            tz: tz_from_name(&cfg.tz_name),
+            tz_name: intern(&cfg.tz_name),
+            call_gen: CallGenerator::new(cfg)?.with_record_types(record_types),
+            sms_gen: SmsGenerator::new(cfg)?.with_record_types(record_types),
+            data_gen,
+            record_types,
+            segments: Segments::new(&cfg.user_segments)?,
+            numeric_prefixes: parse_numeric(&cfg.prefixes, "prefixes")?,
+            mccmnc_pool: parse_numeric(&cfg.mccmnc_pool, "mccmnc_pool")?,
+            operators: parse_prefix_operator_map(&cfg.prefix_operator_map)?,
+            contacts,
+            subscriber_db,
+            seed,
+            targets: None,
+        })
+    }
+
+    /// Serve listed MSISDNs instead of index-derived ones: subscriber index `idx` is `targets[idx]`,
+    /// and the worker's range indexes into the list
+    pub fn with_targets(mut self, targets: Arc<[u64]>) -> Self {
+        self.targets = Some(targets);
+        self
+    }
+
+    /// Whether this is a targeted run over listed MSISDNs
+    pub fn is_targeted(&self) -> bool {
+        self.targets.is_some()
+    }
+
+    /// Event RNG of the worker on `date`: the same for a rerun of the day, different across seeds and days
+    fn day_rng(&self, date: NaiveDate) -> StdRng {
+        StdRng::seed_from_u64(splitmix(self.seed ^ (date.num_days_from_ce() as u64).wrapping_mul(0xD1B5_4A32_D192_ED03)))
+    }
+
+    /// Whether database subscriber `msisdn` is in this worker's range, by the index-derived MSISDNs
+    /// the workers look subscribers up with. Inverts `index_msisdn` instead of scanning the range: the prefix is
+    /// the number above the 7-digit subscriber number, which every index mapping to `msisdn` is congruent to
+    pub fn serves_msisdn(&self, msisdn: u64) -> bool {
+        let (start_u, end_u) = self.users_range;
+        if let Some(targets) = self.targets.as_deref() {
+            return targets[start_u..end_u].contains(&msisdn);
+        }
+        let (prefix, number) = (msisdn / 10_000_000, (msisdn % 10_000_000) as usize);
+        let prefixes = &self.numeric_prefixes;
+        (number..end_u).step_by(10_000_000).any(|idx| idx >= start_u && prefixes[idx % prefixes.len()] == prefix)
+    }
+
+    /// MSISDN the workers look database subscriber `idx` up with: a prefix by index, then the index as the
+    /// 7-digit subscriber number; the listed MSISDN `idx` in a targeted run
+    pub fn index_msisdn(&self, idx: usize) -> u64 {
+        if let Some(targets) = self.targets.as_deref() {
+            return targets[idx];
+        }
+        let prefixes = &self.numeric_prefixes;
+        prefixes[idx % prefixes.len()] * 10_000_000 + (idx % 10_000_000) as u64
+    }
+
+    /// Counterpart of a database subscriber's CALL or SMS: with `contact_call_share` (default `DB_CONTACT_CALL_SHARE`)
+    /// a subscriber of the worker's range, otherwise an external number. A targeted run draws subscribers from the whole list, and with
+    /// `closed_user_group` always does, so the dataset only talks to itself
+    #[cfg(feature = "redb")]
+    fn counterpart_msisdn(&self, cfg: &Config, rng: &mut StdRng) -> u64 {
+        let share = cfg.contact_call_share.unwrap_or(DB_CONTACT_CALL_SHARE);
+        if let Some(targets) = self.targets.as_deref() {
+            if cfg.closed_user_group || rng.gen::<f64>() < share {
+                return targets[rng.gen_range(0..targets.len())];
+            }
+        } else if rng.gen::<f64>() < share {
+            let (start_u, end_u) = self.users_range;
+            return self.index_msisdn(rng.gen_range(start_u..end_u));
+        }
+        let prefixes = &self.numeric_prefixes;
+        prefixes[rng.gen_range(0..prefixes.len())] * 10_000_000 + rng.gen_range(0..10_000_000u64)
+    }
+
+    /// Subscriber indices [start, end) of this worker
+    pub fn users_range(&self) -> (usize, usize) {
+        self.users_range
+    }
+
+    /// Subscribers come from a CSV subscriber database
+    pub fn has_csv_subscribers(&self) -> bool {
+        self.subscriber_db.is_some()
+    }
+
+    /// Contexts of every worker range, built in parallel
+    pub fn for_ranges(
+        ranges: &[(usize, usize)],
+        cfg: &Config,
+        cells: &CellsCatalog,
+        subscriber_db_path: Option<&Path>,
+        redb: bool,
+    ) -> anyhow::Result<Vec<Self>> {
+        use rayon::prelude::*;
+        let population = ranges.last().map_or(0, |&(_, end)| end);
+        ranges
+            .par_iter()
+            .enumerate()
+            .map(|(i, &range)| WorkerContext::new(i, range, population, cfg, cells, subscriber_db_path, redb))
+            .collect()
+    }
+
+    pub fn shard_id(&self) -> usize {
+        self.shard_id
+    }
+}
+
+/// Worker process that generates events for a shard of users
+/// Returns the shard's event and subscriber counts
+#[allow(clippy::too_many_arguments)]
+pub fn worker_generate(
+    day: DateTime<chrono_tz::Tz>,
+    ctx: &WorkerContext,
+    cfg: &Config,
+    cells: &CellsCatalog,
+    out_dir: &Path,
+    redb: Option<&std::sync::Arc<SubscriberDbRedb>>,
+    activity: &DayActivity,
+    writer_tx: BatchSender,
+) -> anyhow::Result<ShardStats> {
+    // If redb database is provided, use chunked processing for memory efficiency
+    if let Some(redb_arc) = redb {
+        #[cfg(feature = "redb")]
+        return worker_generate_redb_chunked(day, ctx, cfg, cells, out_dir, redb_arc.clone(), activity, writer_tx);
+        #[cfg(not(feature = "redb"))]
+        match **redb_arc {}
+    }
+
+    let (shard_id, users_range) = (ctx.shard_id, ctx.users_range);
+    let mut rng = ctx.day_rng(day.date_naive());
+    let subscriber_db = ctx.subscriber_db.as_ref();
+    let (tz, tz_name) = (ctx.tz, ctx.tz_name);
+
+    // Day boundaries follow `day_tz`, diurnal hours `event_time_basis`; events still carry local time
+    let (day_start_local, day_end_local) = local_day_bounds(day_tz(cfg, tz), day.date_naive())?;
+    // Subscribers are looked up as of the first instant of the generated day, and again at its last
+    let (day_start_ts, day_end_ts) = (day_start_local.timestamp_millis(), day_end_local.timestamp_millis());
+    // Events go into the time window, if set, with means scaled to its share of the day
+    let (day_start_local, day_end_local, _) = placement_window(cfg, tz, day.date_naive())?;
+    // 23 or 25 hours on DST transition days
+    let day_secs = seconds_between(&day_start_local, &day_end_local);
+
+    // Subscribers of this shard
     let (start_u, end_u) = users_range;
     let shard_pop = end_u - start_u;
+    let contacts = &ctx.contacts;
+    let numeric_prefixes = &ctx.numeric_prefixes;
 
-    // Pre-allocate with exact capacity to avoid reallocations
-    let contacts = build_contacts(shard_pop, 30, &mut rng);
+    // Subscribers activated during the day have no events before this instant
+    let mut active_from = vec![i64::MIN; shard_pop];
 
-    // Use subscriber database if provided, otherwise generate random subscribers
-    let subs = if let Some(ref db) = subscriber_db {
-        // Pre-allocate subscribers array
-        let mut subscribers = vec![Subscriber {
-            msisdn: 0,
-            imsi: 0,
-            mccmnc: 0,
-            imei: 0,
-        }; shard_pop];
+    // Use subscriber database if provided, otherwise generate random subscribers;
+    // None marks a database subscriber without a valid snapshot on this day
+    let subs: Vec<Option<SubscriberIdentity>> = if let Some(db) = subscriber_db {
+        let mut subscribers = vec![None; shard_pop];
 
         // Fill from database snapshots
-        let day_start_ts = day.timestamp_millis();
-
-        for uidx in 0..shard_pop {
+        for (uidx, slot) in subscribers.iter_mut().enumerate() {
             let sub_idx = start_u + uidx;
 
             // Generate MSISDN for this subscriber
-            let prefix = &cfg.prefixes[sub_idx % cfg.prefixes.len()];
-            let number = sub_idx % 10_000_000;
-            let msisdn_str = format!("{}{:07}", prefix, number);
-
-            // Get snapshot from database
-            if let Some(snapshot) = db.get_snapshot_by_msisdn(&msisdn_str, day_start_ts) {
-                subscribers[uidx] = Subscriber {
-                    msisdn: snapshot.msisdn.parse::<u64>().unwrap_or(0),
-                    imsi: snapshot.imsi.parse::<u64>().unwrap_or(0),
-                    imei: snapshot.imei.parse::<u64>().unwrap_or(0),
-                    mccmnc: snapshot.mccmnc.parse::<u32>().unwrap_or(0),
-                };
+            let prefix = numeric_prefixes[sub_idx % numeric_prefixes.len()];
+            let msisdn = Msisdn::new(prefix * 10_000_000 + (sub_idx % 10_000_000) as u64)?;
+
+            // Get snapshot from database; a miss at day start may still be activated later in the day
+            let snapshot = db
+                .get_snapshot_by_msisdn(msisdn, day_start_ts)
+                .or_else(|| db.get_snapshot_by_msisdn(msisdn, day_end_ts - 1));
+            if let Some(snapshot) = snapshot {
+                active_from[uidx] = snapshot.valid_from;
+                *slot = Some(snapshot.identity);
             }
         }
 
         subscribers
     } else {
-        build_subscribers(shard_pop, &cfg.prefixes, &cfg.mccmnc_pool, &mut rng)
+        // Keyed by (seed, subscriber index) so identities and IMEIs carry over from day to day
+        (start_u..end_u)
+            .map(|idx| {
+                indexed_subscriber(
+                    cfg.seed,
+                    idx,
+                    numeric_prefixes,
+                    &ctx.mccmnc_pool,
+                    &ctx.operators,
+                    day.date_naive(),
+                    cfg.imei_daily_change_prob,
+                )
+                .map(Some)
+            })
+            .collect::<anyhow::Result<_>>()?
     };
 
-    // Event counts per user
-    let avg_calls = cfg.avg_calls_per_user;
-    let avg_sms = cfg.avg_sms_per_user;
-    let avg_data = cfg.avg_data_sessions_per_user;
+    // Event counts per user, from `avg_*_per_user` or the `daily_targets` spread over the run's subscribers
+    let [avg_calls, avg_sms, avg_data] = window_means(cfg, tz, day.date_naive(), ctx.population)?;
 
-    // Pre-compute event count samplers (OPTIMIZATION #4)
-    let calls_sampler = EventCountSampler::new(avg_calls);
-    let sms_sampler = EventCountSampler::new(avg_sms);
-    let data_sampler = EventCountSampler::new(avg_data);
+    // Pre-compute event count samplers per customer segment (OPTIMIZATION #4)
+    let segments = &ctx.segments;
+    let samplers = segment_samplers(segments, avg_calls, avg_sms, avg_data);
+    // Sparse traffic: only the subscribers drawn for the range's totals are visited
+    let sparse = SparseCounts::draw(cfg, segments, [avg_calls, avg_sms, avg_data], users_range, &mut rng);
 
-    // Initialize generators
-    let call_gen = CallGenerator::new(cfg);
-    let sms_gen = SmsGenerator::new(cfg);
-    let data_gen = DataGenerator::new(HashMap::new(), vec![]);
+    let (call_gen, sms_gen, data_gen) = (&ctx.call_gen, &ctx.sms_gen, &ctx.data_gen);
 
     let day_str = day.format("%Y-%m-%d").to_string();
+    let day_date = day.date_naive();
+    let out_label = output_label(cfg, day_date);
 
     // Initialize event pool for zero-allocation event generation
     let mut event_pool = EventPool::new(cfg.event_pool_size);
 
     // Initialize batch for async writing
-    let batch_capacity = cfg.batch_size_bytes / 230; // ~230 bytes per event
-    let mut batch = EventBatch::new(batch_capacity);
-
-    let day_start_local = tz
-        .with_ymd_and_hms(day.year(), day.month(), day.day(), 0, 0, 0)
-        .unwrap();
+    let new_batch = || EventBatch::for_config(cfg, batch_capacity(cfg.batch_size_bytes));
+    let mut batch = new_batch();
+    // The shard's MSISDN set is only built for a check that uses it
+    let self_check = if cfg.self_check == SelfCheckMode::Off {
+        SelfCheck::off()
+    } else {
+        let served = ShardMsisdns::Set(subs.iter().flatten().map(|sub| sub.msisdn.get()).collect());
+        SelfCheck::new(cfg, shard_id, cells, served, subscriber_db.is_some())?
+    };
+    let day_dir = cfg.partition_layout.day_dir(out_dir, &out_label);
+    let clock_skew =
+        ClockSkew::new(&cfg.clock_skew, cfg.emit_node_id, cfg.seed, day_start_ts, ctx.record_types, shard_id, &day_dir)?;
+    let mut dirty = DirtyDataInjector::new(&cfg.dirty_data, cfg.seed, shard_id, &day_dir)
+        .with_self_check(self_check)
+        .with_clock_skew(clock_skew)
+        .with_activity(activity.shard());
 
     let mut stats = ShardStats {
         shard: shard_id,
-        calls: 0,
-        sms: 0,
-        data: 0,
+        ..ShardStats::default()
     };
 
-    // Helper: sample time during the day with diurnal pattern
-    let sample_time = |rng: &mut StdRng| -> DateTime<chrono_tz::Tz> {
-        for _ in 0..10 {
-            let offset_secs = rng.gen_range(0..86400);
-            let t = day_start_local + Duration::seconds(offset_secs);
-            if rng.gen::<f64>() < diurnal_multiplier(&t, cfg, &day_str) {
-                return t;
-            }
-        }
-        let offset_secs = rng.gen_range(0..86400);
-        day_start_local + Duration::seconds(offset_secs)
+    let mut schedule = CallSchedule::new(cfg.allow_concurrent_calls);
+    let mut timeline = Timeline::new(cfg.emit_prev_event_ts);
+
+    // Helper: sample time during the day with diurnal pattern (or hourly targets), from curves tabulated once for the day.
+    // DATA keeps a background level overnight
+    let [call_curve, sms_curve, data_curve] =
+        placement_shapes(cfg).map(|shape| PlacementCurve::shaped(cfg, &day_str, &day_start_local, day_secs, shape));
+    let sample_time = |rng: &mut StdRng| call_curve.sample(rng).with_timezone(&tz);
+    let sample_sms_time = |rng: &mut StdRng| sms_curve.sample(rng).with_timezone(&tz);
+    let sample_data_time = |rng: &mut StdRng| data_curve.sample(rng).with_timezone(&tz);
+
+    let sample_stride = cfg.sample_stride.max(1);
+    let uidxs: Box<dyn Iterator<Item = usize> + '_> = match sparse {
+        Some(ref sparse) => Box::new(sparse.indices(start_u..end_u).map(|idx| idx - start_u)),
+        None => Box::new(0..shard_pop),
     };
-
-    // Parse prefixes to u64 for numeric operations
-    let numeric_prefixes: Vec<u64> = cfg.prefixes
-        .iter()
-        .map(|s| s.parse().unwrap_or(31612))
-        .collect();
-
-    for uidx in 0..shard_pop {
-        // Get subscriber info from pre-loaded array
-        let mut sub = subs[uidx];
-
-        // Skip if subscriber has no data (msisdn == 0)
-        if sub.msisdn == 0 {
+    for uidx in uidxs {
+        // Sampled runs keep every Nth subscriber of the full population
+        if (start_u + uidx) % sample_stride != 0 {
             continue;
         }
 
-        // Occasional IMEI change (new device) - only for non-DB mode
-        if subscriber_db.is_none() && rng.gen::<f64>() < cfg.imei_daily_change_prob {
-            sub.imei = gen_imei(&mut rng);
+        // Get subscriber info from pre-loaded array, skipping subscribers without data
+        let Some(sub) = subs[uidx] else {
+            stats.skipped_subscribers += 1;
+            continue;
+        };
+
+        // Valid but silent today (phone off, abroad, dormant SIM)
+        if is_inactive_on_day(cfg.seed, sub.msisdn.get(), day_date, cfg.daily_inactive_rate) {
+            stats.inactive_subscribers += 1;
+            continue;
         }
+        stats.active_subscribers += 1;
 
+        let sub_active_from = active_from[uidx];
         let c = &contacts[uidx % contacts.len()];
         let c_pool = &c.pool;
 
         // Use pre-computed contact distribution (OPTIMIZATION #2)
         let contact_dist = c.dist.as_ref();
 
-        // Sample event counts for this user (OPTIMIZATION #4)
-        let n_calls = calls_sampler.sample(&mut rng);
-        let n_sms = sms_sampler.sample(&mut rng);
-        let n_data = data_sampler.sample(&mut rng);
+        // Sample event counts for this user, scaled by its segment (OPTIMIZATION #4)
+        let [n_calls, n_sms, n_data] =
+            event_counts(cfg, segments, &samplers, sparse.as_ref(), start_u + uidx, sub.imsi.get(), &mut rng);
 
-        // Generate CALL events
-        for _ in 0..n_calls {
-            let start_local = sample_time(&mut rng);
+        // Generate CALL events in time order so they can be kept from overlapping
+        let mut call_starts: Vec<_> = (0..n_calls).map(|_| sample_time(&mut rng)).collect();
+        call_starts.sort();
+        for start_local in call_starts {
+            // Not yet activated at this time of day
+            if start_local.timestamp_millis() < sub_active_from {
+                continue;
+            }
 
             // Pick counterpart MSISDN (u64) and track if they're in our database
-            let (other_msisdn, other_sub_opt): (u64, Option<&Subscriber>) = if let Some(dist) = contact_dist {
+            let (other_msisdn, other_sub_opt): (u64, Option<SubscriberIdentity>) = if let Some(dist) = pick_contact(contact_dist, cfg, &mut rng) {
                 let other_idx = c_pool[dist.sample(&mut rng)] % subs.len();
-                let other_sub = &subs[other_idx];
-                (other_sub.msisdn, Some(other_sub))
+                let other_sub = subs[other_idx];
+                // A contact activated later in the day has no MT leg yet
+                let reachable = other_sub.filter(|_| start_local.timestamp_millis() >= active_from[other_idx]);
+                (other_sub.map_or(0, |s| s.msisdn.get()), reachable)
             } else {
                 // Generate random MSISDN (not in our database)
                 let prefix_idx = rng.gen_range(0..numeric_prefixes.len());
@@ -658,71 +1731,97 @@ pub fn worker_generate(
                 (prefix * 10_000_000 + subscriber_number, None)
             };
 
-            let cell_id = rng.gen_range(10_000..100_000);
+            let cell_id = rng.gen_range(RANDOM_CELL_IDS);
 
             // Generate MO (Mobile Originated) record for current subscriber
-            let mo_event = event_pool.acquire();
-            call_gen.generate_forced_direction(mo_event, &sub, start_local, other_msisdn, tz_name, cell_id, &mut rng, "MO");
+            let Some((mo, start_local)) = place_call(
+                call_gen,
+                &mut schedule,
+                &sub,
+                start_local,
+                day_end_local,
+                other_msisdn,
+                tz_name,
+                cell_id,
+                &mut rng,
+            ) else {
+                // No free slot left today
+                continue;
+            };
 
             // Add MO record to batch
-            batch.push(mo_event.clone());
-            stats.calls += 1;
+            let mo_event = event_pool.put(mo);
+            if !timeline.hold(mo_event) {
+                dirty.push(&mut batch, mo_event)?;
+            }
+            event_pool.consume();
+            stats.record_call(&start_local);
 
             // Send batch if full
-            if batch.is_full(cfg.batch_size_bytes) {
-                writer_tx.send(WriterMessage::Batch(batch))?;
-                batch = EventBatch::new(batch_capacity);
+            if batch.is_due(cfg.batch_size_bytes) {
+                writer_tx.send(batch.into_message())?;
+                batch = new_batch();
+            }
+
+            // Call parameters of the MO leg, for the callback and the MT leg
+            let (start_ts, end_ts) = (mo.header.start_ts_ms, mo.header.end_ts_ms);
+            let cause = mo.header.cause_for_record_closing;
+
+            // Missed call: the other party sometimes calls back shortly afterwards
+            if cfg.callback_prob > 0.0 && matches!(cause, "noAnswer" | "busy") && rng.gen::<f64>() < cfg.callback_prob {
+                let callback = place_callback(
+                    call_gen,
+                    &mut schedule,
+                    &sub,
+                    end_ts,
+                    day_end_local,
+                    other_msisdn,
+                    tz_name,
+                    cell_id,
+                    &mut rng,
+                );
+                if let Some((cb, cb_start)) = callback {
+                    let cb_event = event_pool.put(cb);
+                    if !timeline.hold(cb_event) {
+                        dirty.push(&mut batch, cb_event)?;
+                    }
+                    event_pool.consume();
+                    stats.record_call(&cb_start);
+
+                    if batch.is_due(cfg.batch_size_bytes) {
+                        writer_tx.send(batch.into_message())?;
+                        batch = new_batch();
+                    }
+                }
             }
 
             // If other party is in our database, generate correlated MT (Mobile Terminated) record
             if let Some(other_sub) = other_sub_opt {
-                // Skip if other subscriber has no data
-                if other_sub.msisdn == 0 {
+                // Callee is already on another call
+                if !schedule.try_book(other_sub.imsi.get(), start_ts, end_ts) {
                     continue;
                 }
 
-                // Save call parameters from MO event for MT correlation (before borrowing event_pool again)
-                let start_ts = mo_event.start_ts_ms;
-                let end_ts = mo_event.end_ts_ms;
-                let tz_offset = mo_event.tz_offset_min;
-                let duration = mo_event.duration_sec;
-                let cause = mo_event.cause_for_record_closing;
-
                 // Generate MT record with same call parameters (time, duration, disposition)
-                let mt_event = event_pool.acquire();
-
-                // Copy call parameters from MO event for correlation
-                mt_event.event_type = "CALL";
-                mt_event.msisdn_src = other_msisdn;
-                mt_event.msisdn_dst = sub.msisdn;
-                mt_event.direction = "MT";
-                mt_event.start_ts_ms = start_ts;
-                mt_event.end_ts_ms = end_ts;
-                mt_event.tz_name = tz_name;
-                mt_event.tz_offset_min = tz_offset;
-                mt_event.duration_sec = duration;
-                mt_event.mccmnc = other_sub.mccmnc;
-                mt_event.imsi = other_sub.imsi;
-                mt_event.imei = other_sub.imei;
-                mt_event.cell_id = cell_id;
-                mt_event.record_type = "mscVoiceRecord";
-                mt_event.cause_for_record_closing = cause;
+                let mt_event = event_pool.put(call_gen.mt_leg(&mo, &other_sub));
 
                 // Add MT record to batch
-                batch.push(mt_event.clone());
-                stats.calls += 1;
+                timeline.stamp_alone(mt_event);
+                dirty.push(&mut batch, mt_event)?;
+                event_pool.consume();
+                stats.record_call(&start_local);
 
                 // Send batch if full
-                if batch.is_full(cfg.batch_size_bytes) {
-                    writer_tx.send(WriterMessage::Batch(batch))?;
-                    batch = EventBatch::new(batch_capacity);
+                if batch.is_due(cfg.batch_size_bytes) {
+                    writer_tx.send(batch.into_message())?;
+                    batch = new_batch();
                 }
             }
         }
 
         // Generate SMS events
         for _ in 0..n_sms {
-            let start_local = sample_time(&mut rng);
+            let start_local = sample_sms_time(&mut rng);
 
             // TODO: Support subscriber database updates for SMS
             if subscriber_db.is_some() {
@@ -731,9 +1830,9 @@ pub fn worker_generate(
             }
 
             // Pick counterpart MSISDN (u64)
-            let other_msisdn: u64 = if let Some(dist) = contact_dist {
+            let other_msisdn: u64 = if let Some(dist) = pick_contact(contact_dist, cfg, &mut rng) {
                 let other_idx = c_pool[dist.sample(&mut rng)] % subs.len();
-                subs[other_idx].msisdn
+                subs[other_idx].map_or(0, |s| s.msisdn.get())
             } else {
                 // Generate random MSISDN
                 let prefix_idx = rng.gen_range(0..numeric_prefixes.len());
@@ -742,27 +1841,52 @@ pub fn worker_generate(
                 prefix * 10_000_000 + subscriber_number
             };
 
-            let cell_id = rng.gen_range(10_000..100_000);
+            let cell_id = rng.gen_range(RANDOM_CELL_IDS);
 
             // Acquire event from pool and populate it
-            let event = event_pool.acquire();
-            sms_gen.generate(event, &sub, start_local, other_msisdn, tz_name, cell_id, &mut rng);
+            let mut sms = sms_gen.generate(&sub, start_local, other_msisdn, tz_name, cell_id, &mut rng);
+            // Powered-off handset: this is the first of the SMSC's delivery attempts
+            let retries = sms_gen.draw_unreachable(&mut sms, &mut rng).map(|(records, expired)| (sms, records, expired));
+            if let Some((first, records, expired)) = &retries {
+                sms = sms_gen.retry_record(first, start_local, 0, *records, *expired);
+            }
+            let event = event_pool.put(sms);
 
             // Add to batch (clone because batch needs ownership)
-            batch.push(event.clone());
-            stats.sms += 1;
+            if !timeline.hold(event) {
+                dirty.push(&mut batch, event)?;
+            }
+            event_pool.consume();
+            stats.record_sms(&start_local);
 
             // Send batch if full
-            if batch.is_full(cfg.batch_size_bytes) {
-                writer_tx.send(WriterMessage::Batch(batch))?;
-                batch = EventBatch::new(batch_capacity);
+            if batch.is_due(cfg.batch_size_bytes) {
+                writer_tx.send(batch.into_message())?;
+                batch = new_batch();
             }
-        }
 
-        // Generate DATA sessions
-        for _ in 0..n_data {
-            let start_local = sample_time(&mut rng);
+            if let Some((first, records, expired)) = retries {
+                for idx in 1..records {
+                    let retry = event_pool.put(sms_gen.retry_record(&first, start_local, idx, records, expired));
+                    if !timeline.hold(retry) {
+                        dirty.push(&mut batch, retry)?;
+                    }
+                    event_pool.consume();
+
+                    if batch.is_due(cfg.batch_size_bytes) {
+                        writer_tx.send(batch.into_message())?;
+                        batch = new_batch();
+                    }
+                }
+                stats.record_sms_retries(records);
+            }
+        }
 
+        // Generate DATA sessions in time order, so each starts on the serving cell of the one before
+        let mut data_starts: Vec<_> = (0..n_data).map(|_| sample_data_time(&mut rng)).collect();
+        data_starts.sort();
+        let mut sessions = DataSessions::default();
+        for start_local in data_starts {
             // TODO: Support subscriber database updates for DATA
             if subscriber_db.is_some() {
                 // Skip for now when using subscriber database
@@ -770,112 +1894,153 @@ pub fn worker_generate(
             }
 
             // Acquire event from pool and populate it
-            let event = event_pool.acquire();
-            data_gen.generate(event, &sub, start_local, tz_name, &mut rng);
+            let mut session = data_gen.generate_from(&mut sessions, &sub, start_local, tz_name, &mut rng);
+            shrink_background_session(&mut session, &start_local.with_timezone(&day_start_local.timezone()), cfg, &day_str);
+            let event = event_pool.put(session);
 
             // Add to batch (clone because batch needs ownership)
-            batch.push(event.clone());
-            stats.data += 1;
+            if !timeline.hold(event) {
+                dirty.push(&mut batch, event)?;
+            }
+            event_pool.consume();
+            stats.record_data(&start_local);
 
             // Send batch if full
-            if batch.is_full(cfg.batch_size_bytes) {
-                writer_tx.send(WriterMessage::Batch(batch))?;
-                batch = EventBatch::new(batch_capacity);
+            if batch.is_due(cfg.batch_size_bytes) {
+                writer_tx.send(batch.into_message())?;
+                batch = new_batch();
+            }
+        }
+
+        // Held own events go out in time order once the subscriber is done
+        for row in timeline.drain_stamped() {
+            dirty.push(&mut batch, &row)?;
+            if batch.is_due(cfg.batch_size_bytes) {
+                writer_tx.send(batch.into_message())?;
+                batch = new_batch();
             }
         }
     }
 
     // Send remaining events in batch
     if !batch.is_empty() {
-        writer_tx.send(WriterMessage::Batch(batch))?;
+        writer_tx.send(batch.into_message())?;
     }
+    dirty.finish()?;
+    stats.dirty_rows = dirty.dirty_rows();
+    stats.self_check_rows = dirty.self_check().checked_rows();
+    stats.self_check_violations = dirty.self_check().violations();
+    stats.activity = dirty.take_activity();
 
     // No need to send Close here - main.rs will handle that after all workers complete
 
-    // Write stats
-    let stat_path = out_dir
-        .join(&day_str)
-        .join(format!("stats_shard{:03}.json", shard_id));
-    let stats_json = serde_json::to_string_pretty(&stats)?;
-    std::fs::write(stat_path, stats_json)?;
+    debug!(
+        shard = shard_id,
+        calls = stats.calls,
+        sms = stats.sms,
+        data = stats.data,
+        "Shard generation finished"
+    );
+
+    // Per-shard stats JSON is only kept for debugging
+    if cfg.write_shard_stats {
+        write_shard_stats(&stats, &cfg.partition_layout.day_dir(out_dir, &out_label))?;
+    }
 
-    Ok(())
+    Ok(stats)
 }
 
 /// Worker process with redb-based chunked processing for memory efficiency
 /// This version loads subscribers in small chunks to minimize memory usage
+#[cfg(feature = "redb")]
+#[allow(clippy::too_many_arguments)]
 fn worker_generate_redb_chunked(
     day: DateTime<chrono_tz::Tz>,
-    shard_id: usize,
-    users_range: (usize, usize),
+    ctx: &WorkerContext,
     cfg: &Config,
+    cells: &CellsCatalog,
     out_dir: &Path,
     redb: std::sync::Arc<SubscriberDbRedb>,
-    writer_tx: Sender<WriterMessage>,
-) -> anyhow::Result<()> {
-    use chrono::Duration;
-
-    let seed = (cfg.workers as u64).wrapping_mul(1000) + shard_id as u64;
-    let mut rng = StdRng::seed_from_u64(seed);
+    activity: &DayActivity,
+    writer_tx: BatchSender,
+) -> anyhow::Result<ShardStats> {
+    use std::time::Instant;
+    use tracing::info;
 
-    let tz = tz_from_name(&cfg.tz_name);
-    let tz_name: &'static str = Box::leak(cfg.tz_name.clone().into_boxed_str());
-
-    // Initialize generators
-    let call_gen = CallGenerator::new(cfg);
-    let sms_gen = SmsGenerator::new(cfg);
-    let data_gen = DataGenerator::new(HashMap::new(), vec![]);
+    let (shard_id, users_range) = (ctx.shard_id, ctx.users_range);
+    let mut rng = ctx.day_rng(day.date_naive());
+    let (tz, tz_name) = (ctx.tz, ctx.tz_name);
+    let (call_gen, sms_gen, data_gen) = (&ctx.call_gen, &ctx.sms_gen, &ctx.data_gen);
 
     let day_str = day.format("%Y-%m-%d").to_string();
+    let day_date = day.date_naive();
+    let out_label = output_label(cfg, day_date);
 
     // Initialize event pool
     let mut event_pool = EventPool::new(cfg.event_pool_size);
 
     // Initialize batch
-    let batch_capacity = cfg.batch_size_bytes / 230;
-    let mut batch = EventBatch::new(batch_capacity);
-
-    let day_start_local = tz
-        .with_ymd_and_hms(day.year(), day.month(), day.day(), 0, 0, 0)
-        .unwrap();
-
-    let day_start_ts = day.timestamp_millis();
+    let new_batch = || EventBatch::for_config(cfg, batch_capacity(cfg.batch_size_bytes));
+    let mut batch = new_batch();
+    // Day boundaries follow `day_tz`, diurnal hours `event_time_basis`; events still carry local time
+    let (day_start_local, day_end_local) = local_day_bounds(day_tz(cfg, tz), day.date_naive())?;
+    // Subscribers are looked up as of the first instant of the generated day, or from their activation during it
+    let (day_start_ts, day_end_ts) = (day_start_local.timestamp_millis(), day_end_local.timestamp_millis());
+    // Events go into the time window, if set, with means scaled to its share of the day
+    let (day_start_local, day_end_local, _) = placement_window(cfg, tz, day.date_naive())?;
+    // 23 or 25 hours on DST transition days
+    let day_secs = seconds_between(&day_start_local, &day_end_local);
+
+    // Served MSISDNs are derived from the subscriber index, like the chunks below build them;
+    // a targeted run's set is only built for a check that uses it
+    let self_check = match ctx.targets {
+        _ if cfg.self_check == SelfCheckMode::Off => SelfCheck::off(),
+        Some(_) => {
+            let served = ShardMsisdns::Set((users_range.0..users_range.1).map(|idx| ctx.index_msisdn(idx)).collect());
+            SelfCheck::new(cfg, shard_id, cells, served, true)?
+        }
+        None => {
+            let served = ShardMsisdns::Indexed {
+                prefixes: ctx.numeric_prefixes.clone(),
+                range: users_range,
+            };
+            SelfCheck::new(cfg, shard_id, cells, served, true)?
+        }
+    };
+    let day_dir = cfg.partition_layout.day_dir(out_dir, &out_label);
+    let clock_skew =
+        ClockSkew::new(&cfg.clock_skew, cfg.emit_node_id, cfg.seed, day_start_ts, ctx.record_types, shard_id, &day_dir)?;
+    let mut dirty = DirtyDataInjector::new(&cfg.dirty_data, cfg.seed, shard_id, &day_dir)
+        .with_self_check(self_check)
+        .with_clock_skew(clock_skew)
+        .with_activity(activity.shard());
 
     let mut stats = ShardStats {
         shard: shard_id,
-        calls: 0,
-        sms: 0,
-        data: 0,
+        ..ShardStats::default()
     };
 
-    // Event counts per user
-    let avg_calls = cfg.avg_calls_per_user;
-    let avg_sms = cfg.avg_sms_per_user;
-    let avg_data = cfg.avg_data_sessions_per_user;
-
-    // Pre-compute event count samplers (OPTIMIZATION #4)
-    let calls_sampler = EventCountSampler::new(avg_calls);
-    let sms_sampler = EventCountSampler::new(avg_sms);
-    let data_sampler = EventCountSampler::new(avg_data);
-
-    // Helper: sample time during the day with diurnal pattern
-    let sample_time = |rng: &mut StdRng| -> DateTime<chrono_tz::Tz> {
-        for _ in 0..10 {
-            let offset_secs = rng.gen_range(0..86400);
-            let t = day_start_local + Duration::seconds(offset_secs);
-            if rng.gen::<f64>() < diurnal_multiplier(&t, cfg, &day_str) {
-                return t;
-            }
-        }
-        let offset_secs = rng.gen_range(0..86400);
-        day_start_local + Duration::seconds(offset_secs)
-    };
+    let mut schedule = CallSchedule::new(cfg.allow_concurrent_calls);
+    let mut timeline = Timeline::new(cfg.emit_prev_event_ts);
 
-    // Parse prefixes to u64 for numeric operations
-    let numeric_prefixes: Vec<u64> = cfg.prefixes
-        .iter()
-        .map(|s| s.parse().unwrap_or(31612))
-        .collect();
+    // Event counts per user, from `avg_*_per_user` or the `daily_targets` spread over the run's subscribers
+    let [avg_calls, avg_sms, avg_data] = window_means(cfg, tz, day.date_naive(), ctx.population)?;
+
+    // Pre-compute event count samplers per customer segment (OPTIMIZATION #4)
+    let segments = &ctx.segments;
+    let samplers = segment_samplers(segments, avg_calls, avg_sms, avg_data);
+    // Sparse traffic: only the subscribers drawn for the range's totals are fetched from the database
+    let sparse = SparseCounts::draw(cfg, segments, [avg_calls, avg_sms, avg_data], users_range, &mut rng);
+
+    // Helper: sample time during the day with diurnal pattern (or hourly targets), from curves tabulated once for the day.
+    // DATA keeps a background level overnight
+    let [call_curve, sms_curve, data_curve] =
+        placement_shapes(cfg).map(|shape| PlacementCurve::shaped(cfg, &day_str, &day_start_local, day_secs, shape));
+    let sample_time = |rng: &mut StdRng| call_curve.sample(rng).with_timezone(&tz);
+    let sample_sms_time = |rng: &mut StdRng| sms_curve.sample(rng).with_timezone(&tz);
+    let sample_data_time = |rng: &mut StdRng| data_curve.sample(rng).with_timezone(&tz);
+
+    let numeric_prefixes = &ctx.numeric_prefixes;
 
     // Calculate total subscriber range for this worker
     let (start_u, end_u) = users_range;
@@ -883,226 +2048,1162 @@ fn worker_generate_redb_chunked(
 
     // Calculate MSISDN range for this worker
     let start_msisdn_idx = start_u;
-    let end_msisdn_idx = end_u;
 
     // Process subscribers in chunks
-    let chunk_size = cfg.chunk_size;
-    for chunk_start_idx in (0..total_subs).step_by(chunk_size) {
+    let sample_stride = cfg.sample_stride.max(1);
+    let chunk_size = cfg.chunk_size.max(1);
+    let num_chunks = total_subs.div_ceil(chunk_size);
+    for (chunk_num, chunk_start_idx) in (0..total_subs).step_by(chunk_size).enumerate() {
         let chunk_end_idx = (chunk_start_idx + chunk_size).min(total_subs);
+        let chunk_timer = Instant::now();
+        let chunk_events_before = stats.calls + stats.sms + stats.data;
 
         // Calculate MSISDN range for this chunk
         let chunk_start_sub = start_msisdn_idx + chunk_start_idx;
         let chunk_end_sub = start_msisdn_idx + chunk_end_idx;
 
-        // Calculate min and max MSISDN for efficient range query
-        let mut min_msisdn = u64::MAX;
-        let mut max_msisdn = 0u64;
+        // Sparse ranges fetch only the subscribers with events
+        let sparse_subs: Option<Vec<usize>> = sparse.as_ref().map(|sparse| sparse.indices(chunk_start_sub..chunk_end_sub).collect());
+        let chunk_data = match sparse_subs {
+            Some(ref subs) => {
+                let msisdns: Vec<u64> = subs.iter().map(|&sub_idx| ctx.index_msisdn(sub_idx)).collect();
+                redb.load_msisdns(&msisdns)?
+            }
+            // Listed MSISDNs are scattered over the database
+            None if ctx.is_targeted() => {
+                let msisdns: Vec<u64> = (chunk_start_sub..chunk_end_sub).map(|sub_idx| ctx.index_msisdn(sub_idx)).collect();
+                redb.load_msisdns(&msisdns)?
+            }
+            None => {
+                // Calculate min and max MSISDN for efficient range query
+                let mut min_msisdn = u64::MAX;
+                let mut max_msisdn = 0u64;
 
-        for sub_idx in chunk_start_sub..chunk_end_sub {
-            let prefix_idx = sub_idx % cfg.prefixes.len();
-            let prefix = numeric_prefixes[prefix_idx];
-            let number = (sub_idx % 10_000_000) as u64;
-            let msisdn = prefix * 10_000_000 + number;
-            min_msisdn = min_msisdn.min(msisdn);
-            max_msisdn = max_msisdn.max(msisdn);
-        }
+                for sub_idx in chunk_start_sub..chunk_end_sub {
+                    let prefix_idx = sub_idx % cfg.prefixes.len();
+                    let prefix = numeric_prefixes[prefix_idx];
+                    let number = (sub_idx % 10_000_000) as u64;
+                    let msisdn = prefix * 10_000_000 + number;
+                    min_msisdn = min_msisdn.min(msisdn);
+                    max_msisdn = max_msisdn.max(msisdn);
+                }
 
-        // Load chunk from redb in one transaction (OPTIMIZATION #1)
-        let chunk_data = redb.load_chunk(min_msisdn, max_msisdn + 1)?;
+                // Load chunk from redb in one transaction (OPTIMIZATION #1)
+                redb.load_chunk(min_msisdn, max_msisdn + 1)?
+            }
+        };
 
         // Build HashMap for O(1) lookup (OPTIMIZATION #1)
-        let snapshot_cache: HashMap<u64, Vec<crate::subscriber_db_redb::SubscriberSnapshotNumeric>> =
+        let snapshot_cache: HashMap<u64, Vec<crate::subscriber_db::SubscriberSnapshot>> =
             chunk_data.into_iter().collect();
 
         // Build subscriber list for this chunk using cache
-        let mut chunk_subs = Vec::with_capacity((chunk_end_idx - chunk_start_idx) as usize);
+        let mut chunk_subs = Vec::with_capacity(chunk_end_idx - chunk_start_idx);
+
+        let sub_idxs: Box<dyn Iterator<Item = usize>> = match sparse_subs {
+            Some(subs) => Box::new(subs.into_iter()),
+            None => Box::new(chunk_start_sub..chunk_end_sub),
+        };
+        for sub_idx in sub_idxs {
+            // Sampled runs keep every Nth subscriber of the full population
+            if sub_idx % sample_stride != 0 {
+                continue;
+            }
 
-        for sub_idx in chunk_start_sub..chunk_end_sub {
             // Generate MSISDN using arithmetic (OPTIMIZATION #3 - partial)
-            let prefix_idx = sub_idx % cfg.prefixes.len();
-            let prefix = numeric_prefixes[prefix_idx];
-            let number = (sub_idx % 10_000_000) as u64;
-            let msisdn = prefix * 10_000_000 + number;
+            let msisdn = ctx.index_msisdn(sub_idx);
 
             // Look up subscriber in cache (OPTIMIZATION #1)
-            if let Some(snapshots) = snapshot_cache.get(&msisdn) {
-                if let Some(snapshot) = crate::subscriber_db_redb::SubscriberDbRedb::find_snapshot_at(snapshots, day_start_ts) {
-                    chunk_subs.push(Subscriber {
-                        msisdn: snapshot.msisdn,
-                        imsi: snapshot.imsi,
-                        imei: snapshot.imei,
-                        mccmnc: snapshot.mccmnc,
-                    });
-                }
+            let snapshot = snapshot_cache.get(&msisdn).and_then(|snapshots| {
+                crate::subscriber_db_redb::SubscriberDbRedb::find_snapshot_in_day(snapshots, day_start_ts, day_end_ts)
+            });
+            if let Some(snapshot) = snapshot {
+                // Activated during the day: no events before `valid_from`
+                chunk_subs.push((snapshot.identity, snapshot.valid_from, sub_idx));
+            } else {
+                // No valid snapshot on this day (prefix mismatch, released or not yet active)
+                stats.skipped_subscribers += 1;
             }
         }
 
         // Generate events for this chunk
-        for sub in &chunk_subs {
-            if sub.msisdn == 0 {
+        for (sub, active_from, sub_idx) in &chunk_subs {
+            // Valid but silent today (phone off, abroad, dormant SIM)
+            if is_inactive_on_day(cfg.seed, sub.msisdn.get(), day_date, cfg.daily_inactive_rate) {
+                stats.inactive_subscribers += 1;
                 continue;
             }
+            stats.active_subscribers += 1;
+
+            // Sample event counts for this user, scaled by its segment (OPTIMIZATION #4)
+            let [n_calls, n_sms, n_data] =
+                event_counts(cfg, segments, &samplers, sparse.as_ref(), *sub_idx, sub.imsi.get(), &mut rng);
+
+            // Generate CALL events in time order so they can be kept from overlapping
+            let mut call_starts: Vec<_> = (0..n_calls).map(|_| sample_time(&mut rng)).collect();
+            call_starts.sort();
+            for start_local in call_starts {
+                // Not yet activated at this time of day
+                if start_local.timestamp_millis() < *active_from {
+                    continue;
+                }
 
-            // Sample event counts for this user (OPTIMIZATION #4)
-            let n_calls = calls_sampler.sample(&mut rng);
-            let n_sms = sms_sampler.sample(&mut rng);
-            let n_data = data_sampler.sample(&mut rng);
-
-            // Generate CALL events
-            for _ in 0..n_calls {
-                let start_local = sample_time(&mut rng);
-
-                // Generate random contact MSISDN using arithmetic (OPTIMIZATION #3)
-                let other_msisdn: u64 = if rng.gen::<f64>() < 0.7 {
-                    // Generate from our subscriber range (may or may not be in DB)
-                    let random_idx = rng.gen_range(start_msisdn_idx..end_msisdn_idx);
-                    let prefix_idx = random_idx % cfg.prefixes.len();
-                    let prefix = numeric_prefixes[prefix_idx];
-                    let number = (random_idx % 10_000_000) as u64;
-                    prefix * 10_000_000 + number
-                } else {
-                    // Generate external number
-                    let prefix_idx = rng.gen_range(0..numeric_prefixes.len());
-                    let prefix = numeric_prefixes[prefix_idx];
-                    let subscriber_number = rng.gen_range(0..10_000_000u64);
-                    prefix * 10_000_000 + subscriber_number
-                };
+                // Counterpart from the subscriber range (`contact_call_share`, may or may not be in DB) or external,
+                // using arithmetic (OPTIMIZATION #3)
+                let other_msisdn = ctx.counterpart_msisdn(cfg, &mut rng);
 
-                let cell_id = rng.gen_range(10_000..100_000);
+                let cell_id = rng.gen_range(RANDOM_CELL_IDS);
 
                 // Generate MO record
-                let mo_event = event_pool.acquire();
-                call_gen.generate_forced_direction(
-                    mo_event,
+                let Some((mo, start_local)) = place_call(
+                    call_gen,
+                    &mut schedule,
                     sub,
                     start_local,
+                    day_end_local,
                     other_msisdn,
                     tz_name,
                     cell_id,
                     &mut rng,
-                    "MO",
-                );
+                ) else {
+                    // No free slot left today
+                    continue;
+                };
 
-                batch.push(mo_event.clone());
-                stats.calls += 1;
+                let mo_event = event_pool.put(mo);
+                if !timeline.hold(mo_event) {
+                    dirty.push(&mut batch, mo_event)?;
+                }
+                event_pool.consume();
+                stats.record_call(&start_local);
 
-                if batch.is_full(cfg.batch_size_bytes) {
-                    writer_tx.send(WriterMessage::Batch(batch))?;
-                    batch = EventBatch::new(batch_capacity);
+                if batch.is_due(cfg.batch_size_bytes) {
+                    writer_tx.send(batch.into_message())?;
+                    batch = new_batch();
                 }
 
-                // Check if other party is in database for MT generation
+                // Parameters for MT correlation
+                let (start_ts, end_ts) = (mo.header.start_ts_ms, mo.header.end_ts_ms);
+                let cause = mo.header.cause_for_record_closing;
+
+                // Missed call: the other party sometimes calls back shortly afterwards
+                if cfg.callback_prob > 0.0 && matches!(cause, "noAnswer" | "busy") && rng.gen::<f64>() < cfg.callback_prob {
+                    let callback = place_callback(
+                        call_gen,
+                        &mut schedule,
+                        sub,
+                        end_ts,
+                        day_end_local,
+                        other_msisdn,
+                        tz_name,
+                        cell_id,
+                        &mut rng,
+                    );
+                    if let Some((cb, cb_start)) = callback {
+                        let cb_event = event_pool.put(cb);
+                        if !timeline.hold(cb_event) {
+                            dirty.push(&mut batch, cb_event)?;
+                        }
+                        event_pool.consume();
+                        stats.record_call(&cb_start);
+
+                        if batch.is_due(cfg.batch_size_bytes) {
+                            writer_tx.send(batch.into_message())?;
+                            batch = new_batch();
+                        }
+                    }
+                }
+
+                // Check if other party is in database for MT generation, as of the call start
                 // First check cache, fallback to DB for out-of-chunk MSISDNs (OPTIMIZATION #1)
                 let other_snapshot_opt = if let Some(snapshots) = snapshot_cache.get(&other_msisdn) {
-                    crate::subscriber_db_redb::SubscriberDbRedb::find_snapshot_at(snapshots, day_start_ts).cloned()
+                    crate::subscriber_db_redb::SubscriberDbRedb::find_snapshot_at(snapshots, start_ts).cloned()
                 } else {
                     // Fallback: MSISDN is outside current chunk, use DB lookup
-                    redb.get_subscriber_at(other_msisdn, day_start_ts)?
+                    redb.get_subscriber_at(other_msisdn, start_ts)?
                 };
 
                 if let Some(ref other_snapshot) = other_snapshot_opt {
-                    if other_snapshot.msisdn == 0 {
+                    // Callee is already on another call
+                    if !schedule.try_book(other_snapshot.identity.imsi.get(), start_ts, end_ts) {
                         continue;
                     }
 
-                    // Save parameters for MT correlation
-                    let start_ts = mo_event.start_ts_ms;
-                    let end_ts = mo_event.end_ts_ms;
-                    let tz_offset = mo_event.tz_offset_min;
-                    let duration = mo_event.duration_sec;
-                    let cause = mo_event.cause_for_record_closing;
-
                     // Generate correlated MT record
-                    let mt_event = event_pool.acquire();
-                    mt_event.event_type = "CALL";
-                    mt_event.msisdn_src = other_msisdn;
-                    mt_event.msisdn_dst = sub.msisdn;
-                    mt_event.direction = "MT";
-                    mt_event.start_ts_ms = start_ts;
-                    mt_event.end_ts_ms = end_ts;
-                    mt_event.tz_name = tz_name;
-                    mt_event.tz_offset_min = tz_offset;
-                    mt_event.duration_sec = duration;
-                    mt_event.mccmnc = other_snapshot.mccmnc;
-                    mt_event.imsi = other_snapshot.imsi;
-                    mt_event.imei = other_snapshot.imei;
-                    mt_event.cell_id = cell_id;
-                    mt_event.record_type = "mscVoiceRecord";
-                    mt_event.cause_for_record_closing = cause;
-
-                    batch.push(mt_event.clone());
-                    stats.calls += 1;
-
-                    if batch.is_full(cfg.batch_size_bytes) {
-                        writer_tx.send(WriterMessage::Batch(batch))?;
-                        batch = EventBatch::new(batch_capacity);
+                    let mt_event = event_pool.put(call_gen.mt_leg(&mo, &other_snapshot.identity));
+
+                    timeline.stamp_alone(mt_event);
+                    dirty.push(&mut batch, mt_event)?;
+                    event_pool.consume();
+                    stats.record_call(&start_local);
+
+                    if batch.is_due(cfg.batch_size_bytes) {
+                        writer_tx.send(batch.into_message())?;
+                        batch = new_batch();
                     }
                 }
             }
 
             // Generate SMS events
             for _ in 0..n_sms {
-                let start_local = sample_time(&mut rng);
+                let start_local = sample_sms_time(&mut rng);
+                if start_local.timestamp_millis() < *active_from {
+                    continue;
+                }
 
-                // Generate random contact MSISDN using arithmetic (OPTIMIZATION #3)
-                let other_msisdn: u64 = if rng.gen::<f64>() < 0.7 {
-                    let random_idx = rng.gen_range(start_msisdn_idx..end_msisdn_idx);
-                    let prefix_idx = random_idx % cfg.prefixes.len();
-                    let prefix = numeric_prefixes[prefix_idx];
-                    let number = (random_idx % 10_000_000) as u64;
-                    prefix * 10_000_000 + number
-                } else {
-                    let prefix_idx = rng.gen_range(0..numeric_prefixes.len());
-                    let prefix = numeric_prefixes[prefix_idx];
-                    let subscriber_number = rng.gen_range(0..10_000_000u64);
-                    prefix * 10_000_000 + subscriber_number
-                };
+                // Counterpart from the subscriber range (`contact_call_share`) or external, using arithmetic (OPTIMIZATION #3)
+                let other_msisdn = ctx.counterpart_msisdn(cfg, &mut rng);
 
-                let cell_id = rng.gen_range(10_000..100_000);
+                let cell_id = rng.gen_range(RANDOM_CELL_IDS);
 
-                let event = event_pool.acquire();
-                sms_gen.generate(event, sub, start_local, other_msisdn, tz_name, cell_id, &mut rng);
+                let mut sms = sms_gen.generate(sub, start_local, other_msisdn, tz_name, cell_id, &mut rng);
+                // Powered-off handset: this is the first of the SMSC's delivery attempts
+                let retries = sms_gen.draw_unreachable(&mut sms, &mut rng).map(|(records, expired)| (sms, records, expired));
+                if let Some((first, records, expired)) = &retries {
+                    sms = sms_gen.retry_record(first, start_local, 0, *records, *expired);
+                }
+                let event = event_pool.put(sms);
 
-                batch.push(event.clone());
-                stats.sms += 1;
+                if !timeline.hold(event) {
+                    dirty.push(&mut batch, event)?;
+                }
+                event_pool.consume();
+                stats.record_sms(&start_local);
 
-                if batch.is_full(cfg.batch_size_bytes) {
-                    writer_tx.send(WriterMessage::Batch(batch))?;
-                    batch = EventBatch::new(batch_capacity);
+                if batch.is_due(cfg.batch_size_bytes) {
+                    writer_tx.send(batch.into_message())?;
+                    batch = new_batch();
+                }
+
+                if let Some((first, records, expired)) = retries {
+                    for idx in 1..records {
+                        let retry = event_pool.put(sms_gen.retry_record(&first, start_local, idx, records, expired));
+                        if !timeline.hold(retry) {
+                            dirty.push(&mut batch, retry)?;
+                        }
+                        event_pool.consume();
+
+                        if batch.is_due(cfg.batch_size_bytes) {
+                            writer_tx.send(batch.into_message())?;
+                            batch = new_batch();
+                        }
+                    }
+                    stats.record_sms_retries(records);
                 }
             }
 
-            // Generate DATA events
-            for _ in 0..n_data {
-                let start_local = sample_time(&mut rng);
+            // Generate DATA events in time order, so each starts on the serving cell of the one before
+            let mut data_starts: Vec<_> = (0..n_data).map(|_| sample_data_time(&mut rng)).collect();
+            data_starts.sort();
+            let mut sessions = DataSessions::default();
+            for start_local in data_starts {
+                if start_local.timestamp_millis() < *active_from {
+                    continue;
+                }
 
-                let event = event_pool.acquire();
-                data_gen.generate(event, sub, start_local, tz_name, &mut rng);
+                let mut session = data_gen.generate_from(&mut sessions, sub, start_local, tz_name, &mut rng);
+                shrink_background_session(&mut session, &start_local.with_timezone(&day_start_local.timezone()), cfg, &day_str);
+                let event = event_pool.put(session);
 
-                batch.push(event.clone());
-                stats.data += 1;
+                if !timeline.hold(event) {
+                    dirty.push(&mut batch, event)?;
+                }
+                event_pool.consume();
+                stats.record_data(&start_local);
 
-                if batch.is_full(cfg.batch_size_bytes) {
-                    writer_tx.send(WriterMessage::Batch(batch))?;
-                    batch = EventBatch::new(batch_capacity);
+                if batch.is_due(cfg.batch_size_bytes) {
+                    writer_tx.send(batch.into_message())?;
+                    batch = new_batch();
+                }
+            }
+
+            for row in timeline.drain_stamped() {
+                dirty.push(&mut batch, &row)?;
+                if batch.is_due(cfg.batch_size_bytes) {
+                    writer_tx.send(batch.into_message())?;
+                    batch = new_batch();
                 }
             }
         }
 
+        let chunk_events = stats.calls + stats.sms + stats.data - chunk_events_before;
+        let chunk_subs_count = chunk_end_idx - chunk_start_idx;
+        if cfg.verbose {
+            info!(
+                "Shard {} chunk {}/{}: {} subscribers, {} events in {:.2?}",
+                shard_id, chunk_num + 1, num_chunks, chunk_subs_count, chunk_events, chunk_timer.elapsed()
+            );
+        } else {
+            debug!(
+                "Shard {} chunk {}/{}: {} subscribers, {} events in {:.2?}",
+                shard_id, chunk_num + 1, num_chunks, chunk_subs_count, chunk_events, chunk_timer.elapsed()
+            );
+        }
+
+        // Rows of this chunk do not wait for the next one
+        if cfg.flush_batch_per_chunk && !batch.is_empty() {
+            writer_tx.send(batch.into_message())?;
+            batch = new_batch();
+        }
+
         // Chunk is dropped here, memory released
     }
 
     // Send remaining batch
     if !batch.is_empty() {
-        writer_tx.send(WriterMessage::Batch(batch))?;
+        writer_tx.send(batch.into_message())?;
+    }
+    dirty.finish()?;
+    stats.dirty_rows = dirty.dirty_rows();
+    stats.self_check_rows = dirty.self_check().checked_rows();
+    stats.self_check_violations = dirty.self_check().violations();
+    stats.activity = dirty.take_activity();
+
+    debug!(
+        shard = shard_id,
+        skipped = stats.skipped_subscribers,
+        calls = stats.calls,
+        sms = stats.sms,
+        data = stats.data,
+        "Shard generation finished"
+    );
+
+    // Per-shard stats JSON is only kept for debugging
+    if cfg.write_shard_stats {
+        write_shard_stats(&stats, &cfg.partition_layout.day_dir(out_dir, &out_label))?;
     }
 
-    // Write stats
-    let stat_path = out_dir
-        .join(&day_str)
-        .join(format!("stats_shard{:03}.json", shard_id));
-    let stats_json = serde_json::to_string_pretty(&stats)?;
-    std::fs::write(stat_path, stats_json)?;
+    Ok(stats)
+}
 
-    Ok(())
+/// Generate one day of CDRs for the given shard ranges into `out_dir/<day>/`
+///
+/// Synchronous convenience wrapper for tests and simple library users: spawns one
+/// writer task per range (so file `writerNNN` holds exactly range NNN), runs the
+/// workers in parallel, waits for all files to be finished and returns the shard stats.
+/// Uses the redb database from `cfg.subscriber_db_redb_path` or the CSV database from
+/// `cfg.subscriber_db_path` when set, otherwise generates random subscribers.
+pub fn generate_day_to_dir(
+    cfg: &Config,
+    day: DateTime<chrono_tz::Tz>,
+    ranges: &[(usize, usize)],
+    out_dir: &Path,
+) -> anyhow::Result<Vec<ShardStats>> {
+    use crate::async_writer::WriterRuntime;
+    use crate::compression::CompressionType;
+    use rayon::prelude::*;
+
+    let day_str = output_label(cfg, day.date_naive());
+    let day_dir = cfg.partition_layout.day_dir(out_dir, &day_str);
+    std::fs::create_dir_all(&day_dir)?;
+
+    let (part_codec, part_level) = cfg.part_compression();
+    let compression_type =
+        CompressionType::from_str(part_codec).ok_or_else(|| anyhow::anyhow!("Invalid compression type: {}", part_codec))?;
+    compression_type.ensure_available()?;
+
+    let redb = match cfg.subscriber_db_redb_path {
+        #[cfg(feature = "redb")]
+        Some(ref path) => Some(Arc::new(SubscriberDbRedb::open(path)?)),
+        #[cfg(not(feature = "redb"))]
+        Some(_) => anyhow::bail!("A redb subscriber database requires building with `--features redb`"),
+        None => None,
+    };
+    let csv_db_path = cfg.subscriber_db_path.as_deref();
+
+    // Use the catalog already in out_dir (see ensure_cells_catalog), otherwise build one in memory
+    let cells_path = out_dir.join("cells.csv");
+    let cells = if cells_path.exists() {
+        crate::cells::load_cells_catalog(&cells_path)?
+    } else {
+        crate::cells::cells_catalog(&crate::cells::generate_cells(
+            cfg.cells,
+            cfg.center_lat,
+            cfg.center_lon,
+            cfg.radius_km,
+            cfg.seed,
+            &RatMix::new(&cfg.rat_mix)?,
+        ))
+    };
+
+    let writers = WriterRuntime::new()?;
+
+    // Set by a failing writer so every worker stops generating
+    let stop = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let mut writer_channels = Vec::with_capacity(ranges.len());
+    let mut writer_handles = Vec::with_capacity(ranges.len());
+    for shard_id in 0..ranges.len() {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        writer_channels.push(tx);
+        writer_handles.push(writers.spawn(
+            rx,
+            out_dir.to_path_buf(),
+            day_str.clone(),
+            shard_id,
+            vec![shard_id],
+            Rotation::from_config(cfg),
+            compression_type,
+            part_level,
+            WriterOptions::from_config(cfg),
+            stop.clone(),
+            false,
+            None,
+        ));
+    }
+
+    let worker_result: anyhow::Result<Vec<ShardStats>> = WorkerContext::for_ranges(ranges, cfg, &cells, csv_db_path, redb.is_some())
+        .and_then(|contexts| {
+            contexts
+                .par_iter()
+                .map(|ctx| {
+                    let writer_tx = BatchSender::new(writer_channels[ctx.shard_id()].clone(), stop.clone());
+                    worker_generate(day, ctx, cfg, &cells, out_dir, redb.as_ref(), &DayActivity::off(), writer_tx)
+                })
+                .collect()
+        });
+
+    // Close writers even if a worker failed so their files are finished
+    for tx in writer_channels {
+        let _ = tx.send(WriterMessage::Close);
+    }
+    // A writer failure is the root cause of workers stopping, so it is reported first
+    let mut writer_result = Ok(());
+    for handle in writer_handles {
+        let result = writers.join(handle);
+        if writer_result.is_ok() {
+            writer_result = result.map(|_| ());
+        }
+    }
+    writer_result?;
+
+    worker_result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "redb")]
+    use crate::subscriber_db::SubscriberSnapshot;
+    use crate::timezone_utils::local_day_start;
+    use chrono::TimeZone;
+    use std::collections::HashSet;
+    #[cfg(feature = "redb")]
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    #[cfg(feature = "redb")]
+    /// Build a redb database whose MSISDNs follow the worker's index scheme
+    fn build_test_redb(path: &Path, prefix: u64, n: usize) -> Arc<SubscriberDbRedb> {
+        let db = SubscriberDbRedb::new(path).unwrap();
+        let batch: Vec<(u64, Vec<SubscriberSnapshot>)> = (0..n as u64)
+            .map(|idx| {
+                let msisdn = prefix * 10_000_000 + idx;
+                let snapshot = SubscriberSnapshot {
+                    identity: SubscriberIdentity::from_numbers(msisdn, 204080000000000 + idx, 350000000000000 + idx, 20408).unwrap(),
+                    valid_from: 0,
+                    valid_to: None,
+                };
+                (msisdn, vec![snapshot])
+            })
+            .collect();
+        db.insert_snapshots_batch(&batch).unwrap();
+        Arc::new(db)
+    }
+
+    #[cfg(feature = "redb")]
+    /// Run the redb worker for one shard and return its stats and the rows it produced, in order
+    fn run_redb_worker(cfg: &Config, redb: &Arc<SubscriberDbRedb>, out_dir: &Path, n: usize) -> (ShardStats, Vec<String>) {
+        // Keep the rows in the batches so they can be compared
+        let cfg = &Config { serialize_in_workers: false, ..cfg.clone() };
+        let tz = tz_from_name(&cfg.tz_name);
+        let day = tz.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        std::fs::create_dir_all(out_dir.join("2025-01-01")).unwrap();
+
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let cells = CellsCatalog::default();
+        let ctx = WorkerContext::new(0, (0, n), n, cfg, &cells, None, true).unwrap();
+        let stats = worker_generate(day, &ctx, cfg, &cells, out_dir, Some(redb), &DayActivity::off(), tx.into()).unwrap();
+
+        let mut rows = Vec::new();
+        for msg in rx.try_iter() {
+            if let WriterMessage::Batch(batch) = msg {
+                rows.extend(batch.events.iter().map(|e| format!("{:?}", e)));
+            }
+        }
+        (stats, rows)
+    }
+
+    #[cfg(feature = "redb")]
+    #[test]
+    fn test_redb_chunk_size_does_not_change_output() {
+        let dir = tempdir().unwrap();
+        let n = 500;
+        let redb = build_test_redb(&dir.path().join("subs.redb"), 31612, n);
+
+        let mut cfg = Config {
+            prefixes: vec!["31612".to_string()],
+            workers: 1,
+            chunk_size: 100,
+            ..Config::default()
+        };
+
+        let (_, small) = run_redb_worker(&cfg, &redb, &dir.path().join("small"), n);
+
+        cfg.chunk_size = 10_000;
+        let (_, large) = run_redb_worker(&cfg, &redb, &dir.path().join("large"), n);
+
+        assert!(!small.is_empty());
+        assert_eq!(small, large);
+    }
+
+    #[cfg(feature = "redb")]
+    #[test]
+    fn test_shard_stats_json_only_written_on_request() {
+        let dir = tempdir().unwrap();
+        let n = 100;
+        let redb = build_test_redb(&dir.path().join("subs.redb"), 31612, n);
+
+        let mut cfg = Config {
+            prefixes: vec!["31612".to_string()],
+            workers: 1,
+            ..Config::default()
+        };
+        let plain_dir = dir.path().join("plain");
+        let (stats, _) = run_redb_worker(&cfg, &redb, &plain_dir, n);
+        assert_eq!(stats.active_subscribers + stats.inactive_subscribers, n);
+        assert!(!plain_dir.join("2025-01-01/stats_shard000.json").exists());
+
+        cfg.write_shard_stats = true;
+        let debug_dir = dir.path().join("debug");
+        let (stats, _) = run_redb_worker(&cfg, &redb, &debug_dir, n);
+        let contents = std::fs::read_to_string(debug_dir.join("2025-01-01/stats_shard000.json")).unwrap();
+        let written: ShardStats = serde_json::from_str(&contents).unwrap();
+        assert_eq!(written.calls, stats.calls);
+        assert_eq!(written.active_subscribers, stats.active_subscribers);
+    }
+
+    #[test]
+    fn test_call_dispositions_reproducible_for_seed() {
+        let cfg = Config::default();
+        let draw = |generator: &CallGenerator| -> Vec<String> {
+            let mut rng = StdRng::seed_from_u64(11);
+            (0..200).map(|_| generator.dispo_pop[generator.dispo_dist.sample(&mut rng)].clone()).collect()
+        };
+        let first = CallGenerator::new(&cfg).unwrap();
+        // A new map has its own hasher, so it iterates the same entries in another order
+        let call_dispositions = cfg.call_dispositions.clone().into_iter().collect();
+        let second = CallGenerator::new(&Config { call_dispositions, ..Config::default() }).unwrap();
+        assert_eq!(draw(&first), draw(&second));
+        assert!(first.dispo_pop.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", first.dispo_pop);
+    }
+
+    #[test]
+    fn test_bad_weights_fail_setup_with_the_config_key() {
+        let weights = |a: f64, b: f64| BTreeMap::from([("a".to_string(), a), ("b".to_string(), b)]);
+        let cases = [("negative", weights(0.5, -0.1)), ("zero-sum", weights(0.0, 0.0)), ("NaN", weights(0.5, f64::NAN))];
+        let cells = CellsCatalog::default();
+        for (case, bad) in cases {
+            let cfg = Config { call_dispositions: bad.clone().into_iter().collect(), ..Config::default() };
+            let err = CallGenerator::new(&cfg).err().unwrap_or_else(|| panic!("{} call_dispositions accepted", case));
+            assert!(err.to_string().starts_with("Invalid call_dispositions"), "{}: {}", case, err);
+
+            let err = DataGenerator::new(None, RatMix::new(&cfg.rat_mix).unwrap(), &bad, &BTreeMap::new()).err().unwrap();
+            assert!(err.to_string().starts_with("Invalid apn_mix"), "{}: {}", case, err);
+
+            // Worker setup reports it instead of panicking inside rayon
+            let cfg = Config { data_close_causes: bad, ..Config::default() };
+            let err = WorkerContext::for_ranges(&[(0, 50), (50, 100)], &cfg, &cells, None, false).err().unwrap();
+            assert!(err.to_string().starts_with("Invalid data_close_causes"), "{}: {}", case, err);
+        }
+    }
+
+    #[test]
+    fn test_failed_calls_have_zero_duration() {
+        let cfg = Config {
+            call_dispositions: HashMap::from([("FAILED".to_string(), 0.5), ("CONGESTION".to_string(), 0.5)]),
+            ..Config::default()
+        };
+        let generator = CallGenerator::new(&cfg).unwrap();
+        let sub = SubscriberIdentity::from_numbers(31612000000, 204080000000000, 350000000000000, 20408).unwrap();
+        let start = chrono_tz::Europe::Amsterdam.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+        let mut rng = StdRng::seed_from_u64(5);
+        for _ in 0..50 {
+            let event = generator.generate(&sub, start, 31612000001, "Europe/Amsterdam", 1, &mut rng).header;
+            assert_eq!(event.duration_sec, 0);
+            assert_eq!(event.end_ts_ms, event.start_ts_ms);
+            assert_eq!(event.cause_for_record_closing, "failure");
+        }
+
+        // A rule can still give failures a short setup time
+        let cfg = Config {
+            call_duration_rules: BTreeMap::from([("FAILED".to_string(), DurationRule { min_sec: 1, max_sec: 5 })]),
+            ..cfg
+        };
+        let generator = CallGenerator::new(&cfg).unwrap();
+        let durations: Vec<i64> = (0..200)
+            .map(|_| {
+                generator.generate(&sub, start, 31612000001, "Europe/Amsterdam", 1, &mut rng).header.duration_sec
+            })
+            .collect();
+        assert!(durations.iter().all(|&d| d <= 5));
+        assert!(durations.iter().any(|&d| d >= 1), "FAILED rule not applied");
+        // CONGESTION lost its rule with the override and falls back to 0
+        assert!(durations.contains(&0));
+    }
+
+    #[test]
+    fn test_sparse_counts_are_poisson_per_subscriber() {
+        let cfg = Config { sample_stride: 2, ..Config::default() };
+        let segments = Segments::new(&cfg.user_segments).unwrap();
+        let max_activity = segments.iter().map(|segment| segment.activity).fold(0.0, f64::max);
+        let mut rng = StdRng::seed_from_u64(7);
+
+        // Typical traffic keeps the per-subscriber draws
+        assert!(SparseCounts::draw(&cfg, &segments, [3.5, 0.01, 0.01], (0, 1000), &mut rng).is_none());
+        let off = Config { sparse_threshold: 0.0, ..cfg.clone() };
+        assert!(SparseCounts::draw(&off, &segments, [0.01; 3], (0, 1000), &mut rng).is_none());
+
+        // A subscriber at the highest activity draws Poisson(mean) calls, one at half of it Poisson(mean / 2)
+        let mean = 0.05 / max_activity;
+        let (range, rounds) = ((101, 1101), 400);
+        let (mut full, mut half) = (vec![0usize; 4], vec![0usize; 4]);
+        for _ in 0..rounds {
+            let sparse = SparseCounts::draw(&cfg, &segments, [mean, 0.0, 0.0], range, &mut rng).unwrap();
+            assert!(sparse.indices(0..usize::MAX).all(|idx| idx % 2 == 0 && (101..1101).contains(&idx)));
+            for idx in (102..1101).step_by(2) {
+                let [calls, sms, data] = sparse.counts(idx, max_activity, &mut rng);
+                assert_eq!((sms, data), (0, 0));
+                full[calls.min(3)] += 1;
+                half[sparse.counts(idx, max_activity / 2.0, &mut rng)[0].min(3)] += 1;
+            }
+        }
+        let draws = (rounds * 500) as f64;
+        for (histogram, lambda) in [(&full, 0.05f64), (&half, 0.025)] {
+            let zero = histogram[0] as f64 / draws;
+            let one = histogram[1] as f64 / draws;
+            assert!((zero - (-lambda).exp()).abs() < 0.003, "P(0) {} for {}", zero, lambda);
+            assert!((one - lambda * (-lambda).exp()).abs() < 0.003, "P(1) {} for {}", one, lambda);
+        }
+    }
+
+    #[test]
+    fn test_is_inactive_on_day_deterministic() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        for msisdn in 31612000000u64..31612000100 {
+            assert_eq!(
+                is_inactive_on_day(42, msisdn, date, 0.3),
+                is_inactive_on_day(42, msisdn, date, 0.3)
+            );
+        }
+    }
+
+    #[test]
+    fn test_is_inactive_on_day_rate() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let n = 20_000u64;
+
+        assert!(!(0..n).any(|i| is_inactive_on_day(42, 31612000000 + i, date, 0.0)));
+        assert!((0..n).all(|i| is_inactive_on_day(42, 31612000000 + i, date, 1.0)));
+
+        let inactive = (0..n)
+            .filter(|i| is_inactive_on_day(42, 31612000000 + i, date, 0.1))
+            .count();
+        let share = inactive as f64 / n as f64;
+        assert!((share - 0.1).abs() < 0.01, "inactive share {}", share);
+
+        // A different day selects a different set of subscribers
+        let next = date.succ_opt().unwrap();
+        let same = (0..n)
+            .filter(|i| {
+                is_inactive_on_day(42, 31612000000 + i, date, 0.1)
+                    == is_inactive_on_day(42, 31612000000 + i, next, 0.1)
+            })
+            .count();
+        assert!(same < n as usize);
+    }
+
+    #[cfg(feature = "redb")]
+    #[test]
+    fn test_mismatched_prefixes_trigger_miss_rate_failure() {
+        use crate::utils::{check_miss_rate, create_daily_summary};
+
+        let dir = tempdir().unwrap();
+        let n = 200;
+        let redb = build_test_redb(&dir.path().join("subs.redb"), 31612, n);
+
+        // Matching prefixes: every lookup hits
+        let mut cfg = Config {
+            prefixes: vec!["31612".to_string()],
+            workers: 1,
+            ..Config::default()
+        };
+        let ok_dir = dir.path().join("ok");
+        let (stats, _) = run_redb_worker(&cfg, &redb, &ok_dir, n);
+        let summary = create_daily_summary(&[stats], &ok_dir.join("2025-01-01"), 1).unwrap();
+        assert_eq!(summary.skipped_subscribers, 0);
+        assert!(check_miss_rate(&summary, "2025-01-01", 0.01, Some(0.05)).is_ok());
+
+        // Prefix not present in the database: every lookup misses
+        cfg.prefixes = vec!["31699".to_string()];
+        let bad_dir = dir.path().join("bad");
+        let (stats, _) = run_redb_worker(&cfg, &redb, &bad_dir, n);
+        let summary = create_daily_summary(&[stats], &bad_dir.join("2025-01-01"), 1).unwrap();
+        assert_eq!(summary.skipped_subscribers, n);
+        assert!(check_miss_rate(&summary, "2025-01-01", 0.01, Some(0.05)).is_err());
+    }
+
+    /// Run the non-DB worker for one shard and return the rows it produced
+    fn run_random_worker(cfg: &Config, out_dir: &Path, n: usize) -> Vec<EventRow> {
+        run_random_worker_on(cfg, out_dir, n, NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(), &CellsCatalog::default())
+    }
+
+    fn run_random_worker_on(cfg: &Config, out_dir: &Path, n: usize, date: NaiveDate, cells: &CellsCatalog) -> Vec<EventRow> {
+        let ctx = WorkerContext::new(0, (0, n), n, cfg, cells, None, false).unwrap();
+        run_worker_with(&ctx, cfg, out_dir, date, cells)
+    }
+
+    fn run_worker_with(ctx: &WorkerContext, cfg: &Config, out_dir: &Path, date: NaiveDate, cells: &CellsCatalog) -> Vec<EventRow> {
+        // Keep the rows in the batches so they can be inspected
+        let cfg = &Config { serialize_in_workers: false, ..cfg.clone() };
+        let day = local_day_start(tz_from_name(&cfg.tz_name), date).unwrap();
+        std::fs::create_dir_all(out_dir.join(date.to_string())).unwrap();
+
+        let (tx, rx) = crossbeam_channel::unbounded();
+        worker_generate(day, ctx, cfg, cells, out_dir, None, &DayActivity::off(), tx.into()).unwrap();
+
+        let mut rows = Vec::new();
+        for msg in rx.try_iter() {
+            if let WriterMessage::Batch(batch) = msg {
+                rows.extend(batch.events);
+            }
+        }
+        rows
+    }
+
+    #[test]
+    fn test_record_type_profiles() {
+        let dir = tempdir().unwrap();
+        let custom: BTreeMap<String, String> = RECORD_KINDS.iter().map(|kind| (kind.to_string(), format!("x_{}", kind))).collect();
+        let profiles = [
+            (RecordTypeProfile::Default, BTreeMap::new(), ["mscVoiceRecord", "mscVoiceRecord", "sgsnSMORecord", "sgsnSMTRecord", "sgsnPDPRecord", "pgwRecord"]),
+            (RecordTypeProfile::Ericsson, BTreeMap::new(), ["moCallRecord", "mtCallRecord", "moSMSRecord", "mtSMSRecord", "sgsnPDPRecord", "pGWRecord"]),
+            (RecordTypeProfile::Huawei, BTreeMap::new(), ["MOC", "MTC", "SMSMO", "SMSMT", "sgsnPDPRecord", "ggsnPDPRecord"]),
+            (RecordTypeProfile::Custom, custom, ["x_call_mo", "x_call_mt", "x_sms_mo", "x_sms_mt", "x_data_sgsn", "x_data_pgw"]),
+        ];
+        for (profile, record_type_map, [call_mo, call_mt, sms_mo, sms_mt, data_sgsn, data_pgw]) in profiles {
+            let cfg = Config { record_type_profile: profile, record_type_map, callback_prob: 0.5, ..Config::default() };
+            let rows = run_random_worker_on(&cfg, dir.path(), 200, NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(), &CellsCatalog::default());
+            let names = |event_type: &str, direction: &str| -> BTreeSet<&str> {
+                rows.iter()
+                    .filter(|row| row.event_type == event_type && row.direction == direction)
+                    .map(|row| row.record_type)
+                    .collect()
+            };
+            assert_eq!(names("CALL", "MO"), BTreeSet::from([call_mo]), "{:?}", profile);
+            assert_eq!(names("CALL", "MT"), BTreeSet::from([call_mt]), "{:?}", profile);
+            assert_eq!(names("SMS", "MO"), BTreeSet::from([sms_mo]), "{:?}", profile);
+            assert_eq!(names("SMS", "MT"), BTreeSet::from([sms_mt]), "{:?}", profile);
+            assert_eq!(names("DATA", "MO"), BTreeSet::from([data_sgsn, data_pgw]), "{:?}", profile);
+        }
+    }
+
+    #[test]
+    fn test_custom_record_types_are_validated() {
+        let custom = |pairs: &[(&str, &str)]| Config {
+            record_type_profile: RecordTypeProfile::Custom,
+            record_type_map: pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            ..Config::default()
+        };
+        let full: Vec<(&str, &str)> = RECORD_KINDS.iter().map(|kind| (*kind, "rec")).collect();
+        assert!(RecordTypes::from_config(&custom(&full)).is_ok());
+
+        let err = RecordTypes::from_config(&custom(&full[1..])).unwrap_err();
+        assert!(err.to_string().contains("call_mo"), "{}", err);
+        let mut unknown = full.clone();
+        unknown.push(("data_ggsn", "rec"));
+        let err = RecordTypes::from_config(&custom(&unknown)).unwrap_err();
+        assert!(err.to_string().contains("data_ggsn"), "{}", err);
+        let mut empty = full.clone();
+        empty[2] = ("sms_mo", "");
+        assert!(RecordTypes::from_config(&custom(&empty)).is_err());
+        // A map only goes with the custom profile
+        let cfg = Config { record_type_profile: RecordTypeProfile::Huawei, ..custom(&full) };
+        assert!(RecordTypes::from_config(&cfg).is_err());
+        // Interned once
+        let a = RecordTypes::from_config(&custom(&full)).unwrap();
+        let b = RecordTypes::from_config(&custom(&full)).unwrap();
+        assert!(std::ptr::eq(a.call_mo, b.sms_mt));
+    }
+
+    #[test]
+    fn test_reused_context_matches_a_fresh_one() {
+        let dir = tempdir().unwrap();
+        let cfg = Config::default();
+        let cells = CellsCatalog::default();
+        let ctx = WorkerContext::new(0, (0, 200), 200, &cfg, &cells, None, false).unwrap();
+        let day1 = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let day2 = NaiveDate::from_ymd_opt(2025, 1, 2).unwrap();
+        let rows = |ctx: &WorkerContext, date| format!("{:?}", run_worker_with(ctx, &cfg, dir.path(), date, &cells));
+        // Generating a day leaves the context as it was
+        let first = rows(&ctx, day1);
+        let fresh = WorkerContext::new(0, (0, 200), 200, &cfg, &cells, None, false).unwrap();
+        assert_eq!(rows(&ctx, day2), rows(&fresh, day2));
+        assert_eq!(rows(&ctx, day1), first);
+    }
+
+    #[test]
+    fn test_serves_msisdn_inverts_index_msisdn() {
+        let cfg = Config { prefixes: vec!["31612".into(), "31613".into(), "31612".into()], ..Config::default() };
+        let cells = CellsCatalog::default();
+        let ctx = WorkerContext::new(1, (100, 300), 400, &cfg, &cells, None, false).unwrap();
+        for idx in 0..400 {
+            assert_eq!(ctx.serves_msisdn(ctx.index_msisdn(idx)), (100..300).contains(&idx), "index {}", idx);
+        }
+        // Index 100 takes the second prefix, so the same subscriber number under the first is nobody's
+        assert!(!ctx.serves_msisdn(316_120_000_100));
+        assert!(!ctx.serves_msisdn(316_140_000_150));
+    }
+
+    #[test]
+    fn test_data_cells_come_from_the_catalog() {
+        let dir = tempdir().unwrap();
+        let cfg = Config::default();
+        let cells = crate::cells::generate_cells(500, 52.37, 4.895, 20.0, 1, &RatMix::new(&cfg.rat_mix).unwrap());
+        let rat_of: HashMap<u32, &str> = cells.iter().map(|cell| (cell.cell_id, cell.rat.as_str())).collect();
+
+        let date = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let rows = run_random_worker_on(&cfg, dir.path(), 200, date, &crate::cells::cells_catalog(&cells));
+        let data: Vec<_> = rows.iter().filter(|row| row.event_type == "DATA").collect();
+        assert!(data.len() > 1_000);
+        for row in data {
+            assert_eq!(rat_of.get(&row.cell_id), Some(&row.rat), "{:?}", row);
+        }
+    }
+
+    #[test]
+    fn test_apn_profiles_order_volumes() {
+        let cfg = Config::default();
+        let generator = DataGenerator::new(None, RatMix::new(&cfg.rat_mix).unwrap(), &cfg.apn_mix, &cfg.apn_profiles).unwrap();
+        let sub = SubscriberIdentity::from_numbers(31612000000, 204080000000000, 350000000000000, 20408).unwrap();
+        let start = chrono_tz::Europe::Amsterdam.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+        let mut rng = StdRng::seed_from_u64(3);
+        // APN -> (sessions, bytes, seconds)
+        let mut totals: HashMap<&str, (f64, f64, f64)> = HashMap::new();
+        for _ in 0..20_000 {
+            let event = generator.generate(&sub, start, "Europe/Amsterdam", &mut rng);
+            let total = totals.entry(event.apn).or_default();
+            total.0 += 1.0;
+            total.1 += (event.bytes_in + event.bytes_out) as f64;
+            total.2 += event.header.duration_sec as f64;
+            if event.apn == "ims" {
+                assert!(event.bytes_out <= 100_000, "{:?}", event);
+            }
+        }
+        let mean_bytes = |apn: &str| totals[apn].1 / totals[apn].0;
+        let mean_secs = |apn: &str| totals[apn].2 / totals[apn].0;
+        assert!(mean_bytes("internet") > mean_bytes("mms"), "{:?}", totals);
+        assert!(mean_bytes("mms") > mean_bytes("ims"), "{:?}", totals);
+        assert!(mean_bytes("ims") < 100_000.0, "{:?}", totals);
+        // Signalling sessions stay up far longer than an MMS transfer
+        assert!(mean_secs("ims") > 10.0 * mean_secs("mms"), "{:?}", totals);
+    }
+
+    #[test]
+    fn test_data_close_causes_follow_weights_and_shape_sessions() {
+        let cfg = Config::default();
+        let generator = DataGenerator::new(None, RatMix::new(&cfg.rat_mix).unwrap(), &cfg.apn_mix, &BTreeMap::new())
+            .unwrap()
+            .with_close_causes(&cfg.data_close_causes)
+            .unwrap();
+        let sub = SubscriberIdentity::from_numbers(31612000000, 204080000000000, 350000000000000, 20408).unwrap();
+        let start = chrono_tz::Europe::Amsterdam.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+        let mut rng = StdRng::seed_from_u64(5);
+        // Cause -> (sessions, bytes, seconds)
+        let mut totals: HashMap<&str, (f64, f64, f64)> = HashMap::new();
+        let sessions = 50_000;
+        for _ in 0..sessions {
+            let event = generator.generate(&sub, start, "Europe/Amsterdam", &mut rng);
+            let total = totals.entry(event.header.cause_for_record_closing).or_default();
+            total.0 += 1.0;
+            total.1 += (event.bytes_in + event.bytes_out) as f64;
+            total.2 += event.header.duration_sec as f64;
+        }
+        for (cause, weight) in &cfg.data_close_causes {
+            let share = totals[cause.as_str()].0 / sessions as f64;
+            assert!((share - weight).abs() < 0.01, "{}: {} vs {}", cause, share, weight);
+        }
+
+        let mean = |cause: &str, idx: usize| {
+            let total = totals[cause];
+            [total.1, total.2][idx] / total.0
+        };
+        let all_bytes = totals.values().map(|total| total.1).sum::<f64>() / sessions as f64;
+        assert!(mean("abnormalRelease", 0) < 0.6 * all_bytes, "{:?}", totals);
+        assert!(mean("abnormalRelease", 1) < mean("normalRelease", 1), "{:?}", totals);
+        assert!(mean("sgsnChange", 1) > mean("normalRelease", 1), "{:?}", totals);
+        assert!(mean("volumeLimit", 0) > mean("normalRelease", 0), "{:?}", totals);
+
+        // Without configured causes every session is released normally
+        let plain = DataGenerator::new(None, RatMix::new(&cfg.rat_mix).unwrap(), &cfg.apn_mix, &BTreeMap::new()).unwrap();
+        let event = plain.generate(&sub, start, "Europe/Amsterdam", &mut rng);
+        assert_eq!(event.header.cause_for_record_closing, "normalRelease");
+        let zero = BTreeMap::from([("normalRelease".to_string(), 0.0)]);
+        assert!(plain.with_close_causes(&zero).is_err());
+
+        // APN names are interned, not leaked again by every generator
+        let again = DataGenerator::new(None, RatMix::new(&cfg.rat_mix).unwrap(), &cfg.apn_mix, &BTreeMap::new()).unwrap();
+        assert!(again.apns.iter().zip(&generator.apns).all(|(a, b)| std::ptr::eq(*a, *b)));
+    }
+
+    #[test]
+    fn test_qcis_follow_apn_and_rat_and_bearers_count_up() {
+        let cfg = Config::default();
+        let apn_mix = BTreeMap::from([("ims".to_string(), 0.5), ("internet".to_string(), 0.5), ("iot".to_string(), 0.1)]);
+        let rat_mix = BTreeMap::from([("LTE".to_string(), 0.5), ("NR".to_string(), 0.5)]);
+        let nr_5qi = BTreeMap::from([("internet".to_string(), BTreeMap::from([(80, 1.0)]))]);
+        let generator = DataGenerator::new(None, RatMix::new(&rat_mix).unwrap(), &apn_mix, &BTreeMap::new())
+            .unwrap()
+            .with_qcis(&cfg.qci_by_apn, &nr_5qi)
+            .unwrap();
+        let sub = SubscriberIdentity::from_numbers(31612000000, 204080000000000, 350000000000000, 20408).unwrap();
+        let start = chrono_tz::Europe::Amsterdam.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+        let mut rng = StdRng::seed_from_u64(9);
+        let mut sessions = DataSessions::default();
+        let mut seen = HashSet::new();
+        for n in 1..=2_000 {
+            let event = generator.generate_from(&mut sessions, &sub, start, "Europe/Amsterdam", &mut rng);
+            assert_eq!(event.bearer_id, n);
+            let expected: &[u32] = match (event.apn, event.rat) {
+                ("ims", "LTE") => &[5],
+                ("internet", "LTE") => &[8, 9],
+                ("internet", "NR") => &[80],
+                // No 5QI table for ims, no table at all for iot
+                _ => &[9],
+            };
+            assert!(expected.contains(&event.qci), "{} on {}: QCI {}", event.apn, event.rat, event.qci);
+            seen.insert((event.apn, event.qci));
+        }
+        assert!(seen.contains(&("internet", 8)) && seen.contains(&("internet", 9)), "{:?}", seen);
+
+        // Without QCI tables no QCI is drawn
+        let plain = DataGenerator::new(None, RatMix::new(&rat_mix).unwrap(), &apn_mix, &BTreeMap::new()).unwrap();
+        assert_eq!(plain.generate(&sub, start, "Europe/Amsterdam", &mut rng).qci, 0);
+    }
+
+    #[test]
+    fn test_catalog_missing_a_rat_is_an_error() {
+        let lte_only = HashMap::from([("LTE".to_string(), vec![1, 2, 3])]);
+        let mix = RatMix::new(&BTreeMap::from([("LTE".to_string(), 0.5), ("NR".to_string(), 0.5)])).unwrap();
+        let err = DataGenerator::new(Some(lte_only.clone()), mix, &crate::config::default_apn_mix(), &BTreeMap::new()).err().unwrap();
+        assert!(err.to_string().contains("no NR cells"), "{}", err);
+
+        // A RAT weighted zero needs no cells
+        let mix = RatMix::new(&BTreeMap::from([("LTE".to_string(), 1.0), ("NR".to_string(), 0.0)])).unwrap();
+        assert!(DataGenerator::new(Some(lte_only), mix, &crate::config::default_apn_mix(), &BTreeMap::new()).is_ok());
+    }
+
+    /// Rows of a DST transition day: all within the local day, offsets taken at each row's own start
+    fn check_dst_day(tz_name: &str, date: NaiveDate) -> Vec<EventRow> {
+        let dir = tempdir().unwrap();
+        let cfg = Config {
+            tz_name: tz_name.to_string(),
+            // Flat curves so every hour of the day gets events
+            diurnal_weekday: vec![1.0; 24],
+            diurnal_weekend: vec![1.0; 24],
+            ..Config::default()
+        };
+        let rows = run_random_worker_on(&cfg, dir.path(), 300, date, &CellsCatalog::default());
+        assert!(rows.len() > 1_000);
+
+        let tz = tz_from_name(tz_name);
+        let (start, end) = local_day_bounds(tz, date).unwrap();
+        for row in &rows {
+            assert!((start.timestamp_millis()..end.timestamp_millis()).contains(&row.start_ts_ms), "{:?}", row);
+            let local = DateTime::from_timestamp_millis(row.start_ts_ms).unwrap().with_timezone(&tz);
+            assert_eq!(row.tz_offset_min, tz_offset_minutes(&local), "{:?}", row);
+        }
+        rows
+    }
+
+    #[test]
+    fn test_day_with_midnight_in_dst_gap() {
+        // Santiago springs forward at midnight: 00:00-01:00 does not exist on 2024-09-08
+        let date = NaiveDate::from_ymd_opt(2024, 9, 8).unwrap();
+        let rows = check_dst_day("America/Santiago", date);
+        let (start, _) = local_day_bounds(chrono_tz::America::Santiago, date).unwrap();
+        assert_eq!(start.naive_local(), date.and_hms_opt(1, 0, 0).unwrap());
+        assert!(rows.iter().all(|row| row.tz_offset_min == -180));
+    }
+
+    #[test]
+    fn test_25_hour_day_is_fully_covered() {
+        let rows = check_dst_day("Europe/Amsterdam", NaiveDate::from_ymd_opt(2025, 10, 26).unwrap());
+        // Both offsets of the day show up, and the 25th hour gets events too
+        assert!(rows.iter().any(|row| row.tz_offset_min == 120));
+        assert!(rows.iter().any(|row| row.tz_offset_min == 60));
+        let last_hour = chrono_tz::Europe::Amsterdam.with_ymd_and_hms(2025, 10, 26, 23, 0, 0).unwrap().timestamp_millis();
+        assert!(rows.iter().any(|row| row.start_ts_ms >= last_hour));
+    }
+
+    #[test]
+    fn test_placement_spreads_over_true_day_length() {
+        let tz = chrono_tz::Europe::Amsterdam;
+        let cfg = Config {
+            diurnal_weekday: vec![1.0; 24],
+            diurnal_weekend: vec![1.0; 24],
+            ..Config::default()
+        };
+        let draws = 48_000;
+        // Normal, spring-forward (no 02:00 hour) and fall-back (02:00 twice) days
+        for (month, day, repeated_hour, skipped_hour) in [(1, 15, None, None), (3, 30, None, Some(2)), (10, 26, Some(2), None)] {
+            let date = NaiveDate::from_ymd_opt(2025, month, day).unwrap();
+            let (start, _) = local_day_bounds(tz, date).unwrap();
+            let secs = crate::timezone_utils::seconds_in_local_day(tz, date).unwrap();
+            let hours = secs / 3600;
+
+            // The rejection sampler and the tabulated curve
+            let curve = PlacementCurve::new(&cfg, &date.to_string(), &start, secs, 0.0);
+            for tabulated in [false, true] {
+                let mut rng = StdRng::seed_from_u64(7);
+                let mut by_hour = [0usize; 24];
+                for _ in 0..draws {
+                    let t = if tabulated {
+                        curve.sample(&mut rng)
+                    } else {
+                        sample_placement_time(&mut rng, &cfg, &date.to_string(), &start, secs, 0.0)
+                    };
+                    assert_eq!(t.date_naive(), date);
+                    by_hour[t.hour() as usize] += 1;
+                }
+
+                // Every elapsed hour gets the same share of the day's events
+                let per_hour = draws as f64 / hours as f64;
+                for (hour, &count) in by_hour.iter().enumerate() {
+                    let expected = match (Some(hour as u32) == repeated_hour, Some(hour as u32) == skipped_hour) {
+                        (true, _) => 2.0 * per_hour,
+                        (_, true) => 0.0,
+                        _ => per_hour,
+                    };
+                    assert!((count as f64 - expected).abs() <= 5.0 * expected.sqrt() + 1.0, "{} hour {}: {} vs {:.0}", date, hour, count, expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_placement_curve_matches_rejection_sampler() {
+        let cfg = Config {
+            seasonality: HashMap::from([(1, 1.3)]),
+            ..Config::default()
+        };
+        let tz = chrono_tz::Europe::Amsterdam;
+        let date = NaiveDate::from_ymd_opt(2025, 1, 15).unwrap();
+        let (start, _) = local_day_bounds(tz, date).unwrap();
+        let secs = crate::timezone_utils::seconds_in_local_day(tz, date).unwrap();
+        let draws = 200_000;
+        for floor in [0.0, cfg.data_nighttime_floor] {
+            let curve = PlacementCurve::new(&cfg, &date.to_string(), &start, secs, floor);
+            let mut rng = StdRng::seed_from_u64(11);
+            let (mut rejection, mut tabulated) = ([0usize; 24], [0usize; 24]);
+            for _ in 0..draws {
+                rejection[sample_placement_time(&mut rng, &cfg, &date.to_string(), &start, secs, floor).hour() as usize] += 1;
+                tabulated[curve.sample(&mut rng).hour() as usize] += 1;
+            }
+            // Same hourly shares, within sampling noise plus the rejection sampler's rare uniform fallback
+            for hour in 0..24 {
+                let (a, b) = (rejection[hour] as f64, tabulated[hour] as f64);
+                assert!((a - b).abs() <= 6.0 * (a + b).sqrt() + 0.002 * draws as f64, "floor {} hour {}: {} vs {}", floor, hour, a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_dst_days_keep_the_event_mass() {
+        let dir = tempdir().unwrap();
+        let cfg = Config {
+            tz_name: "Europe/Amsterdam".to_string(),
+            daily_inactive_rate: 0.0,
+            ..Config::default()
+        };
+        // A 23 or 25 hour day has the same per-user means as any other
+        for (month, day) in [(1, 15), (3, 30), (10, 26)] {
+            let date = NaiveDate::from_ymd_opt(2025, month, day).unwrap();
+            let rows = run_random_worker_on(&cfg, &dir.path().join(date.to_string()), 500, date, &CellsCatalog::default());
+            for (event_type, mean) in [("SMS", cfg.avg_sms_per_user), ("DATA", cfg.avg_data_sessions_per_user)] {
+                let expected = 500.0 * mean;
+                let count = rows.iter().filter(|r| r.event_type == event_type).count() as f64;
+                assert!((count - expected).abs() <= 5.0 * expected.sqrt(), "{} {}: {} vs {}", date, event_type, count, expected);
+            }
+        }
+    }
+
+    /// Number of pairs of CALL rows of the same served subscriber (by IMSI) whose [start, end] intersect
+    fn overlapping_calls(rows: &[EventRow]) -> usize {
+        let mut by_imsi: HashMap<u64, Vec<(i64, i64)>> = HashMap::new();
+        for row in rows.iter().filter(|r| r.event_type == "CALL") {
+            by_imsi.entry(row.imsi).or_default().push((row.start_ts_ms, row.end_ts_ms));
+        }
+        by_imsi
+            .values_mut()
+            .map(|calls| {
+                calls.sort();
+                calls.windows(2).filter(|w| w[1].0 <= w[0].1).count()
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_calls_do_not_overlap() {
+        let dir = tempdir().unwrap();
+        let cfg = Config {
+            workers: 1,
+            avg_calls_per_user: 40.0,
+            callback_prob: 0.5,
+            ..Config::default()
+        };
+
+        let rows = run_random_worker(&cfg, dir.path(), 300);
+        assert!(rows.iter().any(|r| r.event_type == "CALL" && r.direction == "MT"));
+        assert_eq!(overlapping_calls(&rows), 0);
+
+        // The old behaviour is still available
+        let concurrent = Config {
+            allow_concurrent_calls: true,
+            ..cfg
+        };
+        let rows = run_random_worker(&concurrent, &dir.path().join("concurrent"), 300);
+        assert!(overlapping_calls(&rows) > 0);
+    }
+
+    #[test]
+    fn test_missed_calls_get_callbacks() {
+        let dir = tempdir().unwrap();
+        let cfg = Config {
+            workers: 1,
+            callback_prob: 1.0,
+            ..Config::default()
+        };
+
+        let rows = run_random_worker(&cfg, dir.path(), 300);
+        let calls: Vec<&EventRow> = rows.iter().filter(|r| r.event_type == "CALL").collect();
+        let missed: Vec<&&EventRow> = calls
+            .iter()
+            .filter(|r| r.direction == "MO" && matches!(r.cause_for_record_closing, "noAnswer" | "busy"))
+            .collect();
+        assert!(!missed.is_empty());
+
+        // Callback: MT for the caller from the same party, 30 s to 10 min after the missed call
+        let callbacks = missed
+            .iter()
+            .filter(|mo| {
+                calls.iter().any(|r| {
+                    r.direction == "MT"
+                        && r.imsi == mo.imsi
+                        && r.msisdn_src == mo.msisdn_dst
+                        && (30_000..=600_000).contains(&(r.start_ts_ms - mo.end_ts_ms))
+                })
+            })
+            .count();
+        assert!(callbacks * 2 > missed.len(), "{} callbacks for {} missed calls", callbacks, missed.len());
+    }
 }
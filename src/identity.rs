@@ -1,14 +1,227 @@
 // Subscriber identity management: MSISDN, IMSI, IMEI, MCCMNC
-use rand::Rng;
+use crate::config::ContactsConfig;
+use anyhow::{anyhow, bail, Context};
+use chrono::NaiveDate;
+use rand::{Rng, SeedableRng};
 use rand::rngs::StdRng;
 use rand::distributions::WeightedIndex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
 
-#[derive(Debug, Clone, Copy)]
-pub struct Subscriber {
-    pub msisdn: u64,  // Numeric MSISDN (e.g., 31612345678)
-    pub imsi: u64,    // Numeric IMSI
-    pub mccmnc: u32,  // Numeric MCCMNC (e.g., 20408)
-    pub imei: u64,    // Numeric IMEI (15 digits fits in u64)
+/// Number of decimal digits in `value`
+fn digit_count(mut value: u64) -> usize {
+    let mut digits = 1;
+    while value >= 10 {
+        value /= 10;
+        digits += 1;
+    }
+    digits
+}
+
+/// Numeric identifier of `MIN..=MAX` decimal digits.
+/// Fixed-width identifiers (MIN == MAX) keep leading zeros by padding on display;
+/// variable-width ones reject leading zeros, which a number cannot keep
+macro_rules! identifier {
+    ($(#[$meta:meta])* $name:ident($inner:ty), $repr:literal, $label:literal, $min:literal..=$max:literal) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+        #[serde(try_from = $repr, into = $repr)]
+        pub struct $name($inner);
+
+        impl $name {
+            pub const MIN_DIGITS: usize = $min;
+            pub const MAX_DIGITS: usize = $max;
+
+            /// Fails when `value` has more digits than the identifier allows (or fewer, for variable width)
+            pub fn new(value: $inner) -> anyhow::Result<Self> {
+                let digits = digit_count(value as u64);
+                let fixed_width = Self::MIN_DIGITS == Self::MAX_DIGITS;
+                if digits > Self::MAX_DIGITS || (!fixed_width && digits < Self::MIN_DIGITS) {
+                    bail!("Invalid {} {}: expected {}-{} digits", $label, value, Self::MIN_DIGITS, Self::MAX_DIGITS);
+                }
+                Ok($name(value))
+            }
+
+            pub fn get(self) -> $inner {
+                self.0
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = anyhow::Error;
+
+            fn from_str(s: &str) -> anyhow::Result<Self> {
+                if !(Self::MIN_DIGITS..=Self::MAX_DIGITS).contains(&s.len()) || !s.bytes().all(|b| b.is_ascii_digit()) {
+                    bail!("Invalid {} {:?}: expected {}-{} digits", $label, s, Self::MIN_DIGITS, Self::MAX_DIGITS);
+                }
+                if Self::MIN_DIGITS != Self::MAX_DIGITS && s.starts_with('0') {
+                    bail!("Invalid {} {:?}: leading zero", $label, s);
+                }
+                Ok($name(s.parse().with_context(|| format!("Invalid {} {:?}", $label, s))?))
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                if Self::MIN_DIGITS == Self::MAX_DIGITS {
+                    write!(f, "{:0width$}", self.0, width = Self::MAX_DIGITS)
+                } else {
+                    write!(f, "{}", self.0)
+                }
+            }
+        }
+
+        impl TryFrom<$inner> for $name {
+            type Error = anyhow::Error;
+
+            fn try_from(value: $inner) -> anyhow::Result<Self> {
+                Self::new(value)
+            }
+        }
+
+        impl From<$name> for $inner {
+            fn from(value: $name) -> $inner {
+                value.0
+            }
+        }
+    };
+}
+
+identifier!(
+    /// Phone number in international format without `+` (e.g., 31612345678)
+    Msisdn(u64), "u64", "MSISDN", 8..=15
+);
+identifier!(
+    /// IMSI: MCCMNC followed by the MSIN
+    Imsi(u64), "u64", "IMSI", 14..=15
+);
+identifier!(
+    /// IMEI: TAC + serial + Luhn check digit, always 15 digits (the TAC may start with 0)
+    Imei(u64), "u64", "IMEI", 15..=15
+);
+identifier!(
+    /// MCC + 2- or 3-digit MNC (e.g., 20408)
+    Mccmnc(u32), "u32", "MCCMNC", 5..=6
+);
+
+impl Mccmnc {
+    /// MSIN digits after this MCCMNC in a 15-digit IMSI: 10 after a 2-digit MNC, 9 after a 3-digit one
+    pub fn msin_digits(self) -> usize {
+        Imsi::MAX_DIGITS - digit_count(self.0 as u64)
+    }
+
+    /// Number of MSINs under this MCCMNC, `10^msin_digits`
+    pub fn msin_count(self) -> u64 {
+        10u64.pow(self.msin_digits() as u32)
+    }
+}
+
+impl Imsi {
+    /// 15-digit IMSI of `mccmnc` followed by `msin`, which must fit the MSIN width the MCCMNC leaves
+    pub fn from_parts(mccmnc: Mccmnc, msin: u64) -> anyhow::Result<Self> {
+        if msin >= mccmnc.msin_count() {
+            bail!("Invalid MSIN {} under MCCMNC {}: expected at most {} digits", msin, mccmnc, mccmnc.msin_digits());
+        }
+        Imsi::new(mccmnc.get() as u64 * mccmnc.msin_count() + msin)
+    }
+
+    /// Whether this IMSI is a 15-digit IMSI of `mccmnc`
+    pub fn has_mccmnc(self, mccmnc: Mccmnc) -> bool {
+        self.0 / mccmnc.msin_count() == mccmnc.get() as u64
+    }
+}
+
+/// Identity of a subscriber at a point in time: MSISDN ↔ IMSI ↔ IMEI ↔ MCCMNC
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SubscriberIdentity {
+    // Field order is part of the redb snapshot encoding
+    pub imsi: Imsi,
+    pub msisdn: Msisdn,
+    pub imei: Imei,
+    pub mccmnc: Mccmnc,
+    /// Software version of the device (the last two IMEISV digits), 00-98
+    pub sv: u8,
+}
+
+impl SubscriberIdentity {
+    /// Build from raw numbers, failing on any out-of-range identifier; the software version is 00
+    pub fn from_numbers(msisdn: u64, imsi: u64, imei: u64, mccmnc: u32) -> anyhow::Result<Self> {
+        Ok(SubscriberIdentity {
+            imsi: Imsi::new(imsi)?,
+            msisdn: Msisdn::new(msisdn)?,
+            imei: Imei::new(imei)?,
+            mccmnc: Mccmnc::new(mccmnc)?,
+            sv: 0,
+        })
+    }
+
+    /// IMEISV of the device as a number (16 digits, see `Imei::imeisv`)
+    pub fn imeisv(&self) -> u64 {
+        self.imei.imeisv(self.sv)
+    }
+}
+
+impl Imei {
+    /// IMEISV: the 14 TAC + serial digits followed by the 2-digit software version `sv`
+    /// (the Luhn check digit is dropped). The TAC may start with 0, so render it 16 digits wide
+    pub fn imeisv(self, sv: u8) -> u64 {
+        self.0 / 10 * 100 + sv as u64
+    }
+}
+
+/// Highest device software version; SV 99 is reserved
+pub const MAX_SV: u8 = 98;
+
+/// Parse a 2-digit software version ("00"-"98")
+pub fn parse_sv(s: &str) -> anyhow::Result<u8> {
+    match s.parse::<u8>() {
+        Ok(sv) if s.len() == 2 && s.bytes().all(|b| b.is_ascii_digit()) && sv <= MAX_SV => Ok(sv),
+        _ => bail!("Invalid software version {:?}: expected 2 digits, 00-{}", s, MAX_SV),
+    }
+}
+
+/// Software version a device starts with when nothing else decides it: 00-09, fixed per IMEI
+/// so every day of a run (and every run) agrees on it without drawing from an RNG
+pub fn initial_sv(imei: Imei) -> u8 {
+    let mut key = imei.0.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    key = (key ^ (key >> 31)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    ((key ^ (key >> 29)) % 10) as u8
+}
+
+/// Parse configured identifier strings (prefixes, MCCMNC pool), naming the config key on failure
+pub fn parse_numeric<T: FromStr>(values: &[String], key: &str) -> anyhow::Result<Vec<T>>
+where
+    T::Err: fmt::Display,
+{
+    values
+        .iter()
+        .map(|value| value.parse().map_err(|e| anyhow!("Config key {}: {:?} is not valid: {}", key, value, e)))
+        .collect()
+}
+
+/// Parse `prefix_operator_map` (MSISDN prefix → MCCMNC) into numbers
+pub fn parse_prefix_operator_map(map: &BTreeMap<String, String>) -> anyhow::Result<HashMap<u64, Mccmnc>> {
+    map.iter()
+        .map(|(prefix, mccmnc)| {
+            let prefix = prefix
+                .parse()
+                .map_err(|_| anyhow!("Config key prefix_operator_map: prefix {:?} is not a number", prefix))?;
+            let mccmnc = mccmnc
+                .parse()
+                .map_err(|_| anyhow!("Config key prefix_operator_map: {:?} is not a number", mccmnc))?;
+            Ok((prefix, mccmnc))
+        })
+        .collect()
+}
+
+/// MCCMNC bound to the prefix of `msisdn` (prefix + 7-digit subscriber number); None for unmapped prefixes
+pub fn bound_operator(operators: &HashMap<u64, Mccmnc>, msisdn: Msisdn) -> Option<Mccmnc> {
+    if operators.is_empty() {
+        return None;
+    }
+    operators.get(&(msisdn.get() / 10_000_000)).copied()
 }
 
 #[derive(Debug, Clone)]
@@ -20,8 +233,7 @@ pub struct Contacts {
 
 /// Generate a valid 15-digit IMEI with Luhn checksum
 /// Format: TAC (8 digits) + SNR (6 digits) + check digit
-/// Returns numeric IMEI as u64
-pub fn gen_imei(rng: &mut StdRng) -> u64 {
+pub fn gen_imei(rng: &mut StdRng) -> Imei {
     // Generate first 14 digits
     let tac = rng.gen_range(10_000_000u64..100_000_000u64); // 8 digits
     let snr = rng.gen_range(100_000u64..1_000_000u64);      // 6 digits
@@ -43,63 +255,134 @@ pub fn gen_imei(rng: &mut StdRng) -> u64 {
     }
 
     let check = (10 - (total % 10)) % 10;
-    base * 10 + check as u64
+    Imei(base * 10 + check as u64)
+}
+
+/// Identity with MSISDN, IMSI and MCCMNC drawn from `rng`; the MCCMNC comes from `operators` when the prefix is bound
+fn draw_identity(
+    rng: &mut StdRng,
+    prefixes: &[u64],
+    mccmnc_pool: &[Mccmnc],
+    operators: &HashMap<u64, Mccmnc>,
+    imei: Imei,
+) -> anyhow::Result<SubscriberIdentity> {
+    // Prefix followed by a 7-digit subscriber number
+    let prefix = prefixes[rng.gen_range(0..prefixes.len())];
+    let subscriber_number = rng.gen_range(0..10_000_000u64);
+    let msisdn = Msisdn::new(prefix * 10_000_000 + subscriber_number)?;
+
+    // MCCMNC followed by a 10- or 9-digit MSIN
+    let mccmnc = match operators.get(&prefix) {
+        Some(&mccmnc) => mccmnc,
+        None => mccmnc_pool[rng.gen_range(0..mccmnc_pool.len())],
+    };
+    let msin = rng.gen_range(0..mccmnc.msin_count());
+    let imsi = Imsi::from_parts(mccmnc, msin)?;
+
+    Ok(SubscriberIdentity {
+        imsi,
+        msisdn,
+        imei,
+        mccmnc,
+        sv: initial_sv(imei),
+    })
 }
 
 /// Build stable subscriber identities
 /// Each subscriber gets consistent MSISDN ↔ IMSI ↔ MCCMNC ↔ IMEI
-/// Note: prefixes and mccmnc_pool are now expected to be numeric strings
+/// Note: prefixes, mccmnc_pool and prefix_operator_map are expected to be numeric strings
 pub fn build_subscribers(
     n_users: usize,
     prefixes: &[String],
     mccmnc_pool: &[String],
+    prefix_operator_map: &BTreeMap<String, String>,
     rng: &mut StdRng,
-) -> Vec<Subscriber> {
+) -> anyhow::Result<Vec<SubscriberIdentity>> {
+    let prefixes: Vec<u64> = parse_numeric(prefixes, "prefixes")?;
+    let mccmnc_pool: Vec<Mccmnc> = parse_numeric(mccmnc_pool, "mccmnc_pool")?;
+    let operators = parse_prefix_operator_map(prefix_operator_map)?;
     let mut subs = Vec::with_capacity(n_users);
 
     for _ in 0..n_users {
-        // Parse prefix to u64 and append subscriber number
-        let prefix_str = &prefixes[rng.gen_range(0..prefixes.len())];
-        let prefix_num: u64 = prefix_str.parse().unwrap_or(31612);
-        let subscriber_number = rng.gen_range(0..10_000_000u64);
-        let msisdn = prefix_num * 10_000_000 + subscriber_number;
+        let mut identity = draw_identity(rng, &prefixes, &mccmnc_pool, &operators, Imei(0))?;
+        identity.imei = gen_imei(rng);
+        identity.sv = initial_sv(identity.imei);
+        subs.push(identity);
+    }
 
-        // Parse MCCMNC to u32 and append MSIN
-        let mccmnc_str = &mccmnc_pool[rng.gen_range(0..mccmnc_pool.len())];
-        let mccmnc: u32 = mccmnc_str.parse().unwrap_or(20408);
-        let msin = rng.gen_range(0..10_000_000_000u64);  // 10 digits
-        let imsi = (mccmnc as u64) * 10_000_000_000 + msin;
+    Ok(subs)
+}
 
-        let imei = gen_imei(rng);
+// RNG streams of one subscriber: identity, device-change days, then one per device
+const IDENTITY_STREAM: u64 = 0;
+const DEVICE_CHANGE_STREAM: u64 = 1;
+const DEVICE_STREAM: u64 = 2;
 
-        subs.push(Subscriber {
-            msisdn,
-            imsi,
-            mccmnc,
-            imei,
-        });
+/// RNG of one stream of subscriber `index`, independent of shards and days
+fn subscriber_rng(seed: u64, index: usize, stream: u64) -> StdRng {
+    let mut key = seed ^ (index as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    key = (key ^ (key >> 31)).wrapping_mul(0xBF58_476D_1CE4_E5B9) ^ stream.wrapping_mul(0x94D0_49BB_1331_11EB);
+    StdRng::seed_from_u64(key)
+}
+
+/// Device changes of subscriber `index` on or before `date`.
+/// Change days are drawn as geometric gaps from 2020-01-01 with `daily_change_prob` per day,
+/// so every day of a run agrees on them whatever days are generated
+pub fn device_changes_until(seed: u64, index: usize, date: NaiveDate, daily_change_prob: f64) -> u32 {
+    let epoch = NaiveDate::from_ymd_opt(2020, 1, 1).expect("valid date");
+    let days = (date - epoch).num_days();
+    if daily_change_prob <= 0.0 || days < 0 {
+        return 0;
     }
 
-    subs
+    let mut rng = subscriber_rng(seed, index, DEVICE_CHANGE_STREAM);
+    let ln_keep = (1.0 - daily_change_prob.min(1.0)).ln();
+    let (mut day, mut changes) = (0i64, 0u32);
+    loop {
+        // Days until the next change, at least 1
+        let u: f64 = rng.gen_range(f64::MIN_POSITIVE..1.0);
+        day += (u.ln() / ln_keep).floor() as i64 + 1;
+        if day > days {
+            return changes;
+        }
+        changes += 1;
+    }
+}
+
+/// Non-DB subscriber `index` on `date`: the same MSISDN/IMSI/MCCMNC on every day of a run with
+/// the same seed, and an IMEI that only changes on the subscriber's device-change days
+pub fn indexed_subscriber(
+    seed: u64,
+    index: usize,
+    prefixes: &[u64],
+    mccmnc_pool: &[Mccmnc],
+    operators: &HashMap<u64, Mccmnc>,
+    date: NaiveDate,
+    daily_change_prob: f64,
+) -> anyhow::Result<SubscriberIdentity> {
+    let changes = device_changes_until(seed, index, date, daily_change_prob);
+    let imei = gen_imei(&mut subscriber_rng(seed, index, DEVICE_STREAM + changes as u64));
+    draw_identity(&mut subscriber_rng(seed, index, IDENTITY_STREAM), prefixes, mccmnc_pool, operators, imei)
 }
 
 /// Build contact networks with Zipf-like distribution
 /// Users call their close contacts more frequently
 pub fn build_contacts(
     n_users: usize,
-    avg_contacts: usize,
+    config: &ContactsConfig,
     rng: &mut StdRng,
 ) -> Vec<Contacts> {
     use rand_distr::{Normal, Distribution};
     use rand::seq::index::sample;
 
     let mut contacts_list = Vec::with_capacity(n_users);
-    let normal = Normal::new(avg_contacts as f64, avg_contacts as f64 * 0.3).unwrap();
+    let normal = Normal::new(config.avg, config.avg * config.sd_ratio).unwrap();
+    let max = config.max.unwrap_or(usize::MAX).max(config.min);
 
     for _ in 0..n_users {
         // Sample number of contacts
         let n_contacts_f = normal.sample(rng);
-        let mut n_contacts = n_contacts_f.max(0.0) as usize;
+        let mut n_contacts = (n_contacts_f.max(0.0) as usize).clamp(config.min, max);
         n_contacts = n_contacts.min(n_users.saturating_sub(1));
 
         if n_contacts == 0 {
@@ -135,12 +418,40 @@ pub fn build_contacts(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use rand::SeedableRng;
+
+    #[test]
+    fn test_indexed_subscriber_is_stable_across_days() {
+        let prefixes = [31612u64, 31613];
+        let pool = [Mccmnc::new(20408).unwrap()];
+        let day = |d| NaiveDate::from_ymd_opt(2025, 1, d).unwrap();
+
+        let mut changed = 0;
+        for index in 0..500 {
+            let first = indexed_subscriber(7, index, &prefixes, &pool, &HashMap::new(), day(1), 0.05).unwrap();
+            let mut previous = first;
+            for d in 2..=20 {
+                let today = indexed_subscriber(7, index, &prefixes, &pool, &HashMap::new(), day(d), 0.05).unwrap();
+                assert_eq!((today.msisdn, today.imsi, today.mccmnc), (first.msisdn, first.imsi, first.mccmnc));
+                let changes = device_changes_until(7, index, day(d), 0.05) - device_changes_until(7, index, day(d - 1), 0.05);
+                assert_eq!(today.imei != previous.imei, changes > 0, "subscriber {} day {}", index, d);
+                changed += (changes > 0) as usize;
+                previous = today;
+            }
+        }
+        // 500 subscribers x 19 days at 5%
+        assert!((350..600).contains(&changed), "{} changes", changed);
+
+        assert_eq!(device_changes_until(7, 0, day(10), 0.0), 0);
+        assert_ne!(
+            indexed_subscriber(7, 0, &prefixes, &pool, &HashMap::new(), day(1), 0.0).unwrap(),
+            indexed_subscriber(8, 0, &prefixes, &pool, &HashMap::new(), day(1), 0.0).unwrap()
+        );
+    }
 
     #[test]
     fn test_gen_imei() {
         let mut rng = StdRng::seed_from_u64(42);
-        let imei = gen_imei(&mut rng);
+        let imei = gen_imei(&mut rng).get();
         // IMEI should be 15 digits (fits in u64)
         assert!(imei >= 100_000_000_000_000);
         assert!(imei < 1_000_000_000_000_000);
@@ -152,24 +463,131 @@ mod tests {
         let prefixes = vec!["31612".to_string(), "31613".to_string()];
         let mccmnc_pool = vec!["20408".to_string(), "20416".to_string()];
 
-        let subs = build_subscribers(10, &prefixes, &mccmnc_pool, &mut rng);
+        let subs = build_subscribers(10, &prefixes, &mccmnc_pool, &BTreeMap::new(), &mut rng).unwrap();
         assert_eq!(subs.len(), 10);
 
         for sub in &subs {
             // Check IMEI is 15 digits
-            assert!(sub.imei >= 100_000_000_000_000);
-            assert!(sub.imei < 1_000_000_000_000_000);
+            assert!(sub.imei.get() >= 100_000_000_000_000);
+            assert!(sub.imei.get() < 1_000_000_000_000_000);
             // Check MSISDN starts with 316
-            assert!(sub.msisdn >= 31612_0000000 && sub.msisdn < 31614_0000000);
+            assert!(sub.msisdn.get() >= 31612_0000000 && sub.msisdn.get() < 31614_0000000);
             // Check MCCMNC is valid
-            assert!(sub.mccmnc == 20408 || sub.mccmnc == 20416);
+            assert!(sub.mccmnc.get() == 20408 || sub.mccmnc.get() == 20416);
+            // IMSI starts with the MCCMNC
+            assert!(sub.imsi.has_mccmnc(sub.mccmnc));
+        }
+    }
+
+    #[test]
+    fn test_six_digit_mccmnc_leaves_a_nine_digit_msin() {
+        let (two_digit_mnc, three_digit_mnc) = (Mccmnc::new(20408).unwrap(), Mccmnc::new(310_012).unwrap());
+        assert_eq!((two_digit_mnc.msin_digits(), three_digit_mnc.msin_digits()), (10, 9));
+        assert_eq!(Imsi::from_parts(three_digit_mnc, 999_999_999).unwrap().to_string(), "310012999999999");
+        assert!(Imsi::from_parts(three_digit_mnc, 1_000_000_000).is_err());
+        assert!(Imsi::from_parts(two_digit_mnc, 1_000_000_000).is_ok());
+
+        let mut rng = StdRng::seed_from_u64(5);
+        let pool = vec!["310012".to_string(), "20408".to_string()];
+        let subs = build_subscribers(200, &["31612".to_string()], &pool, &BTreeMap::new(), &mut rng).unwrap();
+        for sub in &subs {
+            assert_eq!(sub.imsi.to_string().len(), 15, "{:?}", sub);
+            assert!(sub.imsi.has_mccmnc(sub.mccmnc), "{:?}", sub);
+        }
+        assert!(subs.iter().any(|sub| sub.mccmnc == three_digit_mnc));
+        assert!(subs.iter().any(|sub| sub.mccmnc == two_digit_mnc));
+    }
+
+    #[test]
+    fn test_prefix_operator_map_binds_mccmnc() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let prefixes = vec!["31612".to_string(), "31613".to_string(), "31614".to_string()];
+        let mccmnc_pool = vec!["20408".to_string(), "20416".to_string()];
+        let map = BTreeMap::from([
+            ("31612".to_string(), "20408".to_string()),
+            ("31613".to_string(), "20416".to_string()),
+        ]);
+
+        let subs = build_subscribers(500, &prefixes, &mccmnc_pool, &map, &mut rng).unwrap();
+        let mut unmapped = HashMap::new();
+        for sub in &subs {
+            let expected = match sub.msisdn.get() / 10_000_000 {
+                31612 => Some(20408),
+                31613 => Some(20416),
+                _ => None,
+            };
+            match expected {
+                Some(mccmnc) => assert_eq!(sub.mccmnc.get(), mccmnc, "{}", sub.msisdn),
+                None => *unmapped.entry(sub.mccmnc.get()).or_insert(0) += 1,
+            }
+            assert!(sub.imsi.has_mccmnc(sub.mccmnc));
+        }
+        // The unmapped prefix still draws from the whole pool
+        assert_eq!(unmapped.len(), 2, "{:?}", unmapped);
+
+        let operators = parse_prefix_operator_map(&map).unwrap();
+        let pool = [Mccmnc::new(20404).unwrap()];
+        let day = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        for index in 0..100 {
+            let sub = indexed_subscriber(7, index, &[31612, 31613], &pool, &operators, day, 0.0).unwrap();
+            assert_eq!(Some(sub.mccmnc), bound_operator(&operators, sub.msisdn));
         }
+
+        let bad = BTreeMap::from([("3161x".to_string(), "20408".to_string())]);
+        assert!(parse_prefix_operator_map(&bad).unwrap_err().to_string().contains("prefix_operator_map"));
+    }
+
+    #[test]
+    fn test_build_subscribers_rejects_non_numeric_prefix() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let err = build_subscribers(1, &["316x2".to_string()], &["20408".to_string()], &BTreeMap::new(), &mut rng).unwrap_err();
+        assert!(err.to_string().contains("prefixes"), "{}", err);
+    }
+
+    #[test]
+    fn test_identifier_parsing_and_padding() {
+        // Fixed-width IMEI keeps its leading zero
+        let imei: Imei = "012345678901237".parse().unwrap();
+        assert_eq!(imei.get(), 12_345_678_901_237);
+        assert_eq!(imei.to_string(), "012345678901237");
+        assert!("12345678901237".parse::<Imei>().is_err());
+
+        // Variable-width identifiers cannot keep a leading zero, so they refuse one
+        assert!("0612345678".parse::<Msisdn>().is_err());
+        assert_eq!("31612345678".parse::<Msisdn>().unwrap().to_string(), "31612345678");
+        assert!("".parse::<Imsi>().is_err());
+        assert!("20408abc0000000".parse::<Imsi>().is_err());
+        assert!("2040".parse::<Mccmnc>().is_err());
+
+        // Numeric constructors check the digit count
+        assert!(Msisdn::new(0).is_err());
+        assert!(Imei::new(1_000_000_000_000_000).is_err());
+        assert!(Mccmnc::new(1_000_000).is_err());
+        assert!(SubscriberIdentity::from_numbers(31612345678, 204080000000001, 350000000000000, 20408).is_ok());
+    }
+
+    #[test]
+    fn test_imeisv() {
+        // The check digit gives way to the software version
+        let imei: Imei = "012345678901237".parse().unwrap();
+        assert_eq!(imei.imeisv(7), 123_456_789_012_307);
+        assert_eq!(imei.imeisv(98), 123_456_789_012_398);
+
+        assert_eq!(parse_sv("07").unwrap(), 7);
+        assert_eq!(parse_sv("98").unwrap(), MAX_SV);
+        assert!(parse_sv("99").is_err());
+        assert!(parse_sv("7").is_err());
+        assert!(parse_sv("+7").is_err());
+
+        let mut rng = StdRng::seed_from_u64(42);
+        let svs: std::collections::HashSet<u8> = (0..200).map(|_| initial_sv(gen_imei(&mut rng))).collect();
+        assert!(svs.len() > 5 && svs.iter().all(|&sv| sv < 10), "{:?}", svs);
     }
 
     #[test]
     fn test_build_contacts() {
         let mut rng = StdRng::seed_from_u64(42);
-        let contacts = build_contacts(100, 30, &mut rng);
+        let contacts = build_contacts(100, &ContactsConfig::default(), &mut rng);
         assert_eq!(contacts.len(), 100);
 
         for c in &contacts {
@@ -180,4 +598,17 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_build_contacts_bounds() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let config = ContactsConfig { avg: 10.0, sd_ratio: 1.0, min: 5, max: Some(12) };
+        let sizes: Vec<usize> = build_contacts(500, &config, &mut rng).iter().map(|c| c.pool.len()).collect();
+        assert!(sizes.iter().all(|&n| (5..=12).contains(&n)), "{:?}", sizes);
+        assert!(sizes.contains(&5) && sizes.contains(&12));
+
+        // Never more contacts than other subscribers in the shard
+        let sizes: Vec<usize> = build_contacts(8, &config, &mut rng).iter().map(|c| c.pool.len()).collect();
+        assert!(sizes.iter().all(|&n| (5..=7).contains(&n)), "{:?}", sizes);
+    }
 }
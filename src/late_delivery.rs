@@ -0,0 +1,360 @@
+// Late-arriving file simulation: stage part files outside the day directory and deliver them later
+use crate::config::PartitionLayout;
+use crate::writer::{ManifestEntry, ShardManifest};
+use anyhow::Context;
+use chrono::{DateTime, NaiveDateTime};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+
+/// Staging directory for late files, relative to the output directory
+pub const LATE_DIR: &str = "late";
+
+/// Sidecar annotation written next to each staged file (`<file>.late.json`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LateFile {
+    pub day: String,
+    /// Writer task whose manifest lists the file
+    #[serde(alias = "shard")]
+    pub writer: usize,
+    pub entry: ManifestEntry,
+    pub delay_hours: u64,
+    /// Delivery becomes due at this instant (UTC ms)
+    pub due_at_ms: i64,
+    /// Layout of the day directory the file is delivered into
+    #[serde(default)]
+    pub partition_layout: PartitionLayout,
+}
+
+/// Name of a staged file in `late/<day>/`: the part file name, without its `type=*` directory
+fn staged_name(entry: &ManifestEntry) -> String {
+    Path::new(&entry.file).file_name().unwrap_or_default().to_string_lossy().into_owned()
+}
+
+fn manifest_path(day_dir: &Path, writer: usize) -> PathBuf {
+    day_dir.join(format!("manifest_writer{:03}.json", writer))
+}
+
+fn read_manifest(path: &Path) -> anyhow::Result<ShardManifest> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read manifest {:?}", path))?;
+    serde_json::from_str(&text).with_context(|| format!("Failed to parse manifest {:?}", path))
+}
+
+fn write_manifest(path: &Path, manifest: &ShardManifest) -> anyhow::Result<()> {
+    std::fs::write(path, serde_json::to_string_pretty(manifest)?)?;
+    Ok(())
+}
+
+/// Manifest files in a day directory, sorted by name
+fn manifest_files(day_dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(day_dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            name.starts_with("manifest_writer") && name.ends_with(".json")
+        })
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+/// Files an earlier run staged for a day in `late/<day>/` (part files and their `.late.json` sidecars), sorted
+pub fn staged_files(out_dir: &Path, day_str: &str) -> anyhow::Result<Vec<PathBuf>> {
+    let late_dir = out_dir.join(LATE_DIR).join(day_str);
+    if !late_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut paths: Vec<PathBuf> =
+        std::fs::read_dir(&late_dir)?.filter_map(|entry| entry.ok()).map(|entry| entry.path()).filter(|path| path.is_file()).collect();
+    paths.sort();
+    Ok(paths)
+}
+
+/// Remove what an earlier run staged for a day, then `late/<day>/` if that leaves it empty.
+/// Returns the number of files removed
+pub fn remove_staged_files(out_dir: &Path, day_str: &str) -> anyhow::Result<usize> {
+    let staged = staged_files(out_dir, day_str)?;
+    for path in &staged {
+        std::fs::remove_file(path).with_context(|| format!("Failed to remove {:?}", path))?;
+    }
+    let late_dir = out_dir.join(LATE_DIR).join(day_str);
+    if late_dir.is_dir() && std::fs::read_dir(&late_dir)?.next().is_none() {
+        std::fs::remove_dir(&late_dir)?;
+    }
+    Ok(staged.len())
+}
+
+/// Move a share of the day's part files into `late/<day>/` and drop them from the manifests.
+/// `ready_at_ms` is when the on-time files are considered delivered (usually the end of the day).
+pub fn stage_late_files(
+    out_dir: &Path,
+    day_str: &str,
+    layout: PartitionLayout,
+    late_file_rate: f64,
+    delay_hours: u64,
+    ready_at_ms: i64,
+    seed: u64,
+) -> anyhow::Result<Vec<LateFile>> {
+    let mut staged = Vec::new();
+    if late_file_rate <= 0.0 {
+        return Ok(staged);
+    }
+
+    let day_dir = layout.day_dir(out_dir, day_str);
+    let late_dir = out_dir.join(LATE_DIR).join(day_str);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let due_at_ms = ready_at_ms + delay_hours as i64 * 3_600_000;
+
+    for path in manifest_files(&day_dir)? {
+        let mut manifest = read_manifest(&path)?;
+        let mut on_time = Vec::with_capacity(manifest.files.len());
+
+        for entry in std::mem::take(&mut manifest.files) {
+            if rng.gen::<f64>() >= late_file_rate {
+                on_time.push(entry);
+                continue;
+            }
+
+            std::fs::create_dir_all(&late_dir)?;
+            std::fs::rename(day_dir.join(&entry.file), late_dir.join(staged_name(&entry)))
+                .with_context(|| format!("Failed to stage {:?}", entry.file))?;
+            let late = LateFile {
+                day: day_str.to_string(),
+                writer: manifest.writer,
+                entry,
+                delay_hours,
+                due_at_ms,
+                partition_layout: layout,
+            };
+            let sidecar = late_dir.join(format!("{}.late.json", staged_name(&late.entry)));
+            std::fs::write(sidecar, serde_json::to_string_pretty(&late)?)?;
+            debug!(file = %late.entry.file, due_at_ms, "Staged late file");
+            staged.push(late);
+        }
+
+        manifest.files = on_time;
+        write_manifest(&path, &manifest)?;
+    }
+
+    if !staged.is_empty() {
+        info!("Day {}: staged {} late files (+{}h)", day_str, staged.len(), delay_hours);
+    }
+    Ok(staged)
+}
+
+/// Move staged files that are due at `as_of_ms` into their day folders and add them back to the manifests
+pub fn deliver_late_files(out_dir: &Path, as_of_ms: i64) -> anyhow::Result<Vec<LateFile>> {
+    let mut delivered = Vec::new();
+    let late_root = out_dir.join(LATE_DIR);
+    if !late_root.exists() {
+        return Ok(delivered);
+    }
+
+    let mut day_dirs: Vec<PathBuf> = std::fs::read_dir(&late_root)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+    day_dirs.sort();
+
+    for late_dir in day_dirs {
+        let mut sidecars: Vec<PathBuf> = std::fs::read_dir(&late_dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.file_name().unwrap_or_default().to_string_lossy().ends_with(".late.json"))
+            .collect();
+        sidecars.sort();
+
+        for sidecar in sidecars {
+            let late: LateFile = serde_json::from_str(&std::fs::read_to_string(&sidecar)?)
+                .with_context(|| format!("Failed to parse {:?}", sidecar))?;
+            if late.due_at_ms > as_of_ms {
+                continue;
+            }
+
+            let day_dir = late.partition_layout.day_dir(out_dir, &late.day);
+            let target = day_dir.join(&late.entry.file);
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::rename(late_dir.join(staged_name(&late.entry)), target)
+                .with_context(|| format!("Failed to deliver {:?}", late.entry.file))?;
+            std::fs::remove_file(&sidecar)?;
+
+            let path = manifest_path(&day_dir, late.writer);
+            let mut manifest = if path.exists() {
+                read_manifest(&path)?
+            } else {
+                ShardManifest {
+                    day: late.day.clone(),
+                    writer: late.writer,
+                    worker_shards: late.entry.worker_shards.clone(),
+                    columns: Vec::new(),
+                    schema: Vec::new(),
+                    rotation: BTreeMap::new(),
+                    files: Vec::new(),
+                }
+            };
+            manifest.files.retain(|entry| entry.file != late.entry.file);
+            manifest.files.push(late.entry.clone());
+            manifest.files.sort_by_key(|entry| entry.file_seq);
+            write_manifest(&path, &manifest)?;
+
+            debug!(day = %late.day, file = %late.entry.file, "Delivered late file");
+            delivered.push(late);
+        }
+
+        // Drop the staging folder once everything in it has been delivered
+        if std::fs::read_dir(&late_dir)?.next().is_none() {
+            std::fs::remove_dir(&late_dir)?;
+        }
+    }
+
+    Ok(delivered)
+}
+
+/// Parse `--as-of` (and other CLI instants): RFC 3339, or `YYYY-MM-DD HH:MM:SS` / `YYYY-MM-DDTHH:MM:SS` taken as UTC
+pub fn parse_as_of(value: &str) -> anyhow::Result<i64> {
+    if let Ok(ts) = DateTime::parse_from_rfc3339(value) {
+        return Ok(ts.timestamp_millis());
+    }
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S"] {
+        if let Ok(ts) = NaiveDateTime::parse_from_str(value, format) {
+            return Ok(ts.and_utc().timestamp_millis());
+        }
+    }
+    anyhow::bail!("Invalid timestamp: {} (expected RFC 3339 or YYYY-MM-DD HH:MM:SS)", value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compression::CompressionType;
+    use crate::writer::{EventRow, EventWriter, WriterOptions};
+    use tempfile::tempdir;
+
+    const DAY: &str = "2025-01-01";
+    const READY_AT_MS: i64 = 1_735_776_000_000; // 2025-01-02T00:00:00Z
+
+    /// Write `shards` shards of several part files each
+    fn write_day(out_dir: &Path, shards: usize) {
+        for shard in 0..shards {
+            let mut writer = EventWriter::new(out_dir, DAY, 1_000, shard, CompressionType::None, None, WriterOptions::default()).unwrap();
+            for i in 0..30 {
+                let row = EventRow {
+                    event_type: "DATA",
+                    msisdn_src: 31_600_000_000 + i,
+                    ..EventRow::default()
+                };
+                writer.write_row(&row).unwrap();
+            }
+            writer.close().unwrap();
+        }
+    }
+
+    fn manifest_names(out_dir: &Path) -> Vec<String> {
+        let day_dir = out_dir.join(DAY);
+        let mut names = Vec::new();
+        for path in manifest_files(&day_dir).unwrap() {
+            names.extend(read_manifest(&path).unwrap().files.into_iter().map(|entry| entry.file));
+        }
+        names.sort();
+        names
+    }
+
+    fn cdr_files(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .filter(|name| name.starts_with("cdr_") && name.ends_with(".csv"))
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_stage_late_files() {
+        let dir = tempdir().unwrap();
+        write_day(dir.path(), 2);
+        let before = cdr_files(&dir.path().join(DAY));
+
+        let staged = stage_late_files(dir.path(), DAY, PartitionLayout::Flat, 0.5, 6, READY_AT_MS, 1).unwrap();
+        assert!(!staged.is_empty() && staged.len() < before.len());
+
+        let late_dir = dir.path().join(LATE_DIR).join(DAY);
+        let on_time = cdr_files(&dir.path().join(DAY));
+        assert_eq!(on_time.len() + staged.len(), before.len());
+        assert_eq!(manifest_names(dir.path()), on_time);
+        for late in &staged {
+            assert!(late_dir.join(&late.entry.file).exists());
+            assert!(late_dir.join(format!("{}.late.json", late.entry.file)).exists());
+            assert_eq!(late.due_at_ms, READY_AT_MS + 6 * 3_600_000);
+        }
+    }
+
+    #[test]
+    fn test_deliver_late_files() {
+        let dir = tempdir().unwrap();
+        write_day(dir.path(), 2);
+        let before = manifest_names(dir.path());
+        let staged = stage_late_files(dir.path(), DAY, PartitionLayout::Flat, 0.5, 6, READY_AT_MS, 1).unwrap();
+        assert!(!staged.is_empty());
+
+        // Not yet due
+        let delivered = deliver_late_files(dir.path(), READY_AT_MS + 3_600_000).unwrap();
+        assert!(delivered.is_empty());
+        assert_eq!(manifest_names(dir.path()).len() + staged.len(), before.len());
+
+        let delivered = deliver_late_files(dir.path(), READY_AT_MS + 6 * 3_600_000).unwrap();
+        assert_eq!(delivered.len(), staged.len());
+        assert_eq!(manifest_names(dir.path()), before);
+        assert_eq!(cdr_files(&dir.path().join(DAY)), before);
+        assert!(!dir.path().join(LATE_DIR).join(DAY).exists());
+
+        // Manifests stay ordered by file_seq
+        for path in manifest_files(&dir.path().join(DAY)).unwrap() {
+            let seqs: Vec<u32> = read_manifest(&path).unwrap().files.iter().map(|entry| entry.file_seq).collect();
+            assert!(seqs.windows(2).all(|w| w[0] < w[1]));
+        }
+    }
+
+    #[test]
+    fn test_late_files_keep_type_partitions() {
+        let dir = tempdir().unwrap();
+        let layout = PartitionLayout::HiveDateType;
+        let options = WriterOptions { partition_layout: layout, ..WriterOptions::default() };
+        let mut writer = EventWriter::new(dir.path(), DAY, 1_000, 0, CompressionType::None, None, options).unwrap();
+        for (i, event_type) in ["CALL", "SMS", "DATA"].iter().cycle().take(90).enumerate() {
+            writer.write_row(&EventRow { event_type, msisdn_src: 31_600_000_000 + i as u64, ..EventRow::default() }).unwrap();
+        }
+        writer.close().unwrap();
+        let day_dir = layout.day_dir(dir.path(), DAY);
+        let before = read_manifest(&manifest_path(&day_dir, 0)).unwrap().files;
+
+        let staged = stage_late_files(dir.path(), DAY, layout, 0.5, 6, READY_AT_MS, 1).unwrap();
+        assert!(!staged.is_empty());
+        for late in &staged {
+            assert!(late.entry.file.starts_with("type="));
+            assert!(!day_dir.join(&late.entry.file).exists());
+        }
+
+        let delivered = deliver_late_files(dir.path(), READY_AT_MS + 6 * 3_600_000).unwrap();
+        assert_eq!(delivered.len(), staged.len());
+        let after = read_manifest(&manifest_path(&day_dir, 0)).unwrap().files;
+        assert_eq!(after.len(), before.len());
+        assert!(after.iter().all(|entry| day_dir.join(&entry.file).exists()));
+    }
+
+    #[test]
+    fn test_parse_as_of() {
+        assert_eq!(parse_as_of("2025-01-02T00:00:00Z").unwrap(), READY_AT_MS);
+        assert_eq!(parse_as_of("2025-01-02T01:00:00+01:00").unwrap(), READY_AT_MS);
+        assert_eq!(parse_as_of("2025-01-02 00:00:00").unwrap(), READY_AT_MS);
+        assert!(parse_as_of("tomorrow").is_err());
+    }
+}
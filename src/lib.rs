@@ -1,13 +1,35 @@
 // CDR Generator Library
+pub mod api;
+#[cfg(feature = "arrow")]
+pub mod arrow_writer;
 pub mod async_writer;
 pub mod cells;
+pub mod clock_skew;
 pub mod compression;
 pub mod config;
+pub mod cores;
+pub mod daily_targets;
+pub mod day_over_day;
+pub mod db_check;
+pub mod dirty_data;
+pub mod estimate;
 pub mod event_pool;
 pub mod generators;
 pub mod identity;
+pub mod late_delivery;
+pub mod provenance;
+pub mod provisioning;
+pub mod reader;
+pub mod realtime;
+pub mod record;
+pub mod schema;
+pub mod segments;
+pub mod self_check;
 pub mod subscriber_db;
+#[cfg(feature = "arrow")]
+pub mod subscriber_db_arrow;
 pub mod subscriber_db_generator;
+#[cfg(feature = "redb")]
 pub mod subscriber_db_redb;
 pub mod timezone_utils;
 pub mod utils;
@@ -12,20 +12,29 @@
 // - Stable subscriber identity: MSISDN ↔ IMSI ↔ MCCMNC
 // - redb-based subscriber database for efficient chunked processing
 
-use chrono::{Datelike, Duration, TimeZone};
+use anyhow::Context;
+use chrono::Duration;
 use clap::{Parser, Subcommand};
-use crossbeam_channel::unbounded;
-use rayon::prelude::*;
-use rs_cdr_generator::async_writer::{writer_task, WriterMessage};
-use rs_cdr_generator::cells::{ensure_cells_catalog, load_cells_catalog};
-use rs_cdr_generator::config::{load_config, parse_prefixes, Config};
-use rs_cdr_generator::generators::worker_generate;
-use rs_cdr_generator::subscriber_db_generator::{generate_database_redb, GeneratorConfig};
-use rs_cdr_generator::subscriber_db_redb::SubscriberDbRedb;
-use rs_cdr_generator::timezone_utils::tz_from_name;
-use rs_cdr_generator::utils::{bundle_day, create_daily_summary};
-use std::path::PathBuf;
-use std::sync::Arc;
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::cells::{extend_cells_catalog, CellsMeta};
+use rs_cdr_generator::config::{load_config, parse_prefixes, OutputFormat, OverwritePolicy, SelfCheckMode, TimeWindow};
+use rs_cdr_generator::cores::{available_cores, parse_workers};
+use rs_cdr_generator::estimate::estimate_run;
+use rs_cdr_generator::late_delivery::{deliver_late_files, parse_as_of};
+use rs_cdr_generator::provisioning::export_provisioning;
+use rs_cdr_generator::schema::{OutputSchema, SchemaFormat};
+use rs_cdr_generator::segments::export_crm;
+use rs_cdr_generator::subscriber_db::{load_msisdn_file, SubscriberDatabase};
+use rs_cdr_generator::subscriber_db_generator::{
+    generate_database, write_database_redb, write_snapshots_redb, EventExportFormat, GeneratorConfig,
+};
+use rs_cdr_generator::subscriber_db_redb::{compact_database, diff_databases, DbDurability, DbWriteOptions, SubscriberDbRedb};
+use rs_cdr_generator::utils::read_day_summaries;
+use rs_cdr_generator::writer::{header_columns, row_serializer, serialize_row, WriterOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::{error, info, warn};
+use tracing_subscriber::EnvFilter;
 
 #[derive(Parser, Debug)]
 #[command(name = "rs_cdr_generator")]
@@ -34,9 +43,18 @@ use std::sync::Arc;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Уровень логирования: error | warn | info | debug | trace (по умолчанию RUST_LOG или info)
+    #[arg(long, global = true)]
+    log_level: Option<String>,
+
+    /// Логи в формате JSON (по одному объекту на строку)
+    #[arg(long, global = true, default_value = "false")]
+    log_json: bool,
 }
 
 #[derive(Subcommand, Debug)]
+#[allow(clippy::large_enum_variant)]
 enum Commands {
     /// Generate subscriber database in redb format
     GenerateSubscribers {
@@ -60,23 +78,211 @@ enum Commands {
         #[arg(long, default_value = "0.05")]
         number_release_rate: f64,
 
+        /// Доля смен устройства на общее устройство (dual-SIM, передача телефона) [0..1]
+        #[arg(long, default_value = "0.0")]
+        shared_device_rate: f64,
+
+        /// Вероятность обновления ПО устройства (SV в IMEISV) в год [0..1]
+        #[arg(long, default_value = "0.5")]
+        software_update_rate: f64,
+
         /// Дни "остывания" номера перед переназначением
         #[arg(long, default_value = "90")]
         cooldown_days: usize,
 
+        /// Вероятность переназначения номера в день после "остывания" [0..1]; 1.0 = сразу
+        #[arg(long, default_value = "1.0")]
+        reassignment_rate: f64,
+
+        /// Доля переназначенных номеров, которые получает уже активный абонент как вторую линию на своём IMSI [0..1]
+        #[arg(long, default_value = "0.0")]
+        reassign_to_existing_rate: f64,
+
+        /// Через сколько дней после освобождения непереназначенный номер выводится навсегда
+        #[arg(long)]
+        max_dormancy_days: Option<usize>,
+
         /// Префиксы без кода страны, через запятую
         #[arg(long)]
         prefixes: Option<String>,
 
+        /// Дата начала истории базы YYYY-MM-DD (UTC); CDR раньше неё не найдут абонентов
+        #[arg(long, default_value = "2024-01-01")]
+        start_date: String,
+
         /// Seed для детерминизма
         #[arg(long, default_value = "42")]
         seed: u64,
 
-        /// YAML конфиг (для prefixes и mccmnc_pool)
+        /// YAML конфиг (prefixes, mccmnc_pool и сезонность событий db_*_seasonality / db_*_special_days); "-" = stdin
+        /// Переменные окружения CDRGEN_<KEY> перекрывают YAML
+        #[arg(long)]
+        config: Option<PathBuf>,
+
+        /// Дополнительно выгрузить историю событий абонентов (.arrow — Arrow IPC, .csv — CSV)
+        #[arg(long)]
+        also_export_events: Option<PathBuf>,
+
+        /// Сколько MSISDN записывать в redb за одну транзакцию
+        #[arg(long, default_value = "10000")]
+        db_batch_size: usize,
+
+        /// Надёжность записи redb: safe (fsync на каждую транзакцию) или fast (fsync только в конце)
+        #[arg(long, default_value = "safe")]
+        db_durability: String,
+
+        /// Сжать файл redb после записи и проверить выборку MSISDN
+        #[arg(long, default_value = "false")]
+        compact: bool,
+    },
+
+    /// Validate a subscriber database (redb), or repair a CSV event history into one
+    ValidateSubscribers {
+        /// Путь к базе данных абонентов (.redb; с --repair — CSV история событий)
+        #[arg(long)]
+        input: PathBuf,
+
+        /// Максимум IMSI, одновременно использующих один IMEI
+        #[arg(long, default_value = "2")]
+        max_imsis_per_imei: usize,
+
+        /// Также проверить окна MSISDN: положительная длина, без перекрытий
+        #[arg(long, default_value = "false")]
+        strict: bool,
+
+        /// Исправить CSV историю (сортировка, недостающие RELEASE_NUMBER, перекрытия и пустые окна) и записать в --output
+        #[arg(long, default_value = "false")]
+        repair: bool,
+
+        /// Выходная база (.redb) для --repair
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Compact a subscriber database (redb) in place
+    CompactSubscribers {
+        /// Путь к базе данных абонентов (.redb)
+        #[arg(long)]
+        input: PathBuf,
+
+        /// Сколько случайных MSISDN сверить до и после сжатия
+        #[arg(long, default_value = "1000")]
+        spot_checks: usize,
+
+        /// Seed для выборки MSISDN
+        #[arg(long, default_value = "42")]
+        seed: u64,
+    },
+
+    /// Print statistics about a subscriber database (redb)
+    SubscriberStats {
+        /// Путь к базе данных абонентов (.redb)
+        #[arg(long)]
+        input: PathBuf,
+
+        /// Вывод в формате JSON
+        #[arg(long, default_value = "false")]
+        json: bool,
+    },
+
+    /// Print totals and provenance (version, config hash, seed, subscriber database) of generated days
+    Stats {
+        /// Выходной каталог генерации или каталог одного дня
+        #[arg(long)]
+        input: PathBuf,
+
+        /// Вывод в формате JSON
+        #[arg(long, default_value = "false")]
+        json: bool,
+    },
+
+    /// Compare two subscriber databases (redb) MSISDN by MSISDN
+    DiffSubscribers {
+        /// Исходная база абонентов (.redb)
+        #[arg(long)]
+        a: PathBuf,
+
+        /// Новая база абонентов (.redb)
+        #[arg(long)]
+        b: PathBuf,
+
+        /// Момент сравнения (RFC 3339 или YYYY-MM-DD HH:MM:SS в UTC); по умолчанию последний снимок каждого MSISDN
+        #[arg(long)]
+        at: Option<String>,
+
+        /// CSV с различиями по каждому MSISDN
+        #[arg(long)]
+        output: Option<PathBuf>,
+
+        /// Вывод в формате JSON
+        #[arg(long, default_value = "false")]
+        json: bool,
+    },
+
+    /// Export a CRM extract (msisdn, customer_id, activation_date, plan, segment) of the subscribers active on a day
+    ExportCrm {
+        /// Путь к базе данных абонентов (.redb)
+        #[arg(long)]
+        subscriber_db: PathBuf,
+
+        /// Дата выгрузки YYYY-MM-DD; одна строка на каждый MSISDN, активный в этот день
+        #[arg(long)]
+        at: String,
+
+        /// Выходной CSV
+        #[arg(long, default_value = "crm.csv")]
+        output: PathBuf,
+
+        /// Seed генерации CDR (сегменты и customer_id совпадают только при том же seed); по умолчанию из YAML / CDRGEN_SEED, иначе 42
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// YAML конфиг генерации CDR (user_segments, tz_name); "-" = stdin
         #[arg(long)]
         config: Option<PathBuf>,
     },
 
+    /// Export the provisioning delta (activations, releases, device changes) of a day from the subscriber database
+    ExportProvisioning {
+        /// Путь к базе данных абонентов (.redb)
+        #[arg(long)]
+        subscriber_db: PathBuf,
+
+        /// Дата выгрузки YYYY-MM-DD; одна строка на каждое событие абонента в этот день
+        #[arg(long)]
+        date: String,
+
+        /// Выходной CSV
+        #[arg(long, default_value = "provisioning.csv")]
+        output: PathBuf,
+
+        /// YAML конфиг генерации CDR (tz_name, event_time_basis); "-" = stdin
+        #[arg(long)]
+        config: Option<PathBuf>,
+    },
+
+    /// Print the column schema of the part files (as in schema.json) without generating data
+    Schema {
+        /// Формат вывода: json | markdown
+        #[arg(long, default_value = "json")]
+        format: String,
+
+        /// YAML конфиг генерации CDR (emit_* колонки, msisdn_format); "-" = stdin
+        #[arg(long)]
+        config: Option<PathBuf>,
+    },
+
+    /// Deliver staged late files whose delay has elapsed
+    DeliverLate {
+        /// Каталог вывода generate-cdr
+        #[arg(long, default_value = "out")]
+        out: PathBuf,
+
+        /// Момент доставки (RFC 3339 или YYYY-MM-DD HH:MM:SS в UTC)
+        #[arg(long)]
+        as_of: String,
+    },
+
     /// Generate CDR data from subscriber database
     GenerateCdr {
         /// Путь к базе данных подписчиков (redb)
@@ -95,9 +301,9 @@ enum Commands {
         #[arg(long, default_value = "out")]
         out: PathBuf,
 
-        /// Seed для детерминизма
-        #[arg(long, default_value = "42")]
-        seed: u64,
+        /// Seed для детерминизма (по умолчанию seed из YAML / CDRGEN_SEED, иначе 42)
+        #[arg(long)]
+        seed: Option<u64>,
 
         /// Префиксы без кода страны, через запятую
         #[arg(long)]
@@ -107,11 +313,20 @@ enum Commands {
         #[arg(long)]
         rotate_bytes: Option<u64>,
 
-        /// Число процессов (0 = auto-detect)
+        /// Сжатие выходных файлов: gzip | zstd | bzip2 | xz | none (bzip2/xz — с cargo-фичами)
         #[arg(long)]
-        workers: Option<usize>,
+        compression: Option<String>,
 
-        /// YAML конфиг поверх дефолтов
+        /// Уровень сжатия (gzip 0-9, zstd 1-22, bzip2 1-9, xz 0-9)
+        #[arg(long)]
+        compression_level: Option<i32>,
+
+        /// Число процессов: N, доля доступных ядер (50%) или 0 = все доступные ядра (с учётом лимитов cgroup и CDRGEN_MAX_CORES)
+        #[arg(long)]
+        workers: Option<String>,
+
+        /// YAML конфиг поверх дефолтов ("-" = stdin)
+        /// Приоритет: дефолты < YAML < переменные окружения CDRGEN_<KEY> (CDRGEN_WORKERS=8) < флаги CLI
         #[arg(long)]
         config: Option<PathBuf>,
 
@@ -131,6 +346,14 @@ enum Commands {
         #[arg(long)]
         cell_radius_km: Option<f64>,
 
+        /// Пересоздать cells.csv, если он сгенерирован с другими параметрами
+        #[arg(long, default_value = "false")]
+        regen_cells: bool,
+
+        /// Дописать N новых вышек в существующий cells.csv, не трогая старые
+        #[arg(long, conflicts_with_all = ["cells", "regen_cells"])]
+        extend_cells: Option<usize>,
+
         /// Вероятность MO для CALL [0..1]
         #[arg(long)]
         mo_share_call: Option<f64>,
@@ -143,26 +366,182 @@ enum Commands {
         #[arg(long)]
         imei_change_prob: Option<f64>,
 
+        /// Доля абонентов без активности в день [0..1]
+        #[arg(long)]
+        daily_inactive_rate: Option<f64>,
+
+        /// Сколько абонентов обрабатывать за один чанк
+        #[arg(long)]
+        chunk_size: Option<usize>,
+
+        /// Прервать генерацию, если доля ненайденных в БД абонентов выше порога [0..1]
+        #[arg(long)]
+        fail_on_miss_rate: Option<f64>,
+
+        /// Перед генерацией проверить выборку MSISDN воркеров по redb БД и показать промахи с ближайшими ключами (включено по умолчанию; --check-db=false — выключить)
+        #[arg(long, num_args = 0..=1, default_missing_value = "true", require_equals = true)]
+        check_db: Option<bool>,
+
+        /// Подробный вывод (время обработки чанков)
+        #[arg(long, default_value = "false")]
+        verbose: bool,
+
+        /// Дополнительно сохранять статистику по шардам (stats_shardNNN.json) для отладки
+        #[arg(long, default_value = "false")]
+        write_shard_stats: bool,
+
         /// Удалять исходные файлы после архивации
         #[arg(long, default_value = "false")]
         cleanup_after_archive: bool,
+
+        /// Если в каталоге дня уже есть cdr_ файлы или логи ground truth (dirty_shard, clock_skew_shard) прошлого запуска,
+        /// его опоздавшие файлы в late/ (или его склеенный файл дня при склейке): fail (по умолчанию) — ошибка,
+        /// clean — удалить их вместе с манифестами, статистикой, summary и склеенным файлом,
+        /// merge — оставить и склеить вместе с новыми (файлы с теми же именами заменяются новыми, с предупреждением)
+        #[arg(long)]
+        overwrite_policy: Option<String>,
+
+        /// Формат файлов частей: csv (по умолчанию) или arrow — Arrow IPC (feather) с типизированными колонками для анализа в Python; файлы arrow не склеиваются в архив дня
+        #[arg(long)]
+        output_format: Option<String>,
+
+        /// Пробный прогон на доле подписчиков (например, 0.01 = каждый 100-й; доля округляется до 1/N),
+        /// вывод в <out>/sample и оценка полного объёма по cdr_ файлам
+        #[arg(long)]
+        sample: Option<f64>,
+
+        /// Генерировать только часть суток по локальному времени, HH:MM-HH:MM (например, 13:00-14:00)
+        #[arg(long, conflicts_with_all = ["start_time", "end_time"])]
+        window: Option<String>,
+
+        /// Начало окна HH:MM (по умолчанию 00:00)
+        #[arg(long)]
+        start_time: Option<String>,
+
+        /// Конец окна HH:MM (по умолчанию 24:00)
+        #[arg(long)]
+        end_time: Option<String>,
+
+        /// Склеивать файлы окна в один архив (без флага окно остаётся отдельными part-файлами)
+        #[arg(long, default_value = "false")]
+        bundle_window: bool,
+
+        /// Не собирать метрики производительности (время записи, глубина очереди) и не писать их в summary.json
+        #[arg(long, default_value = "false")]
+        no_metrics: bool,
+
+        /// Проверять собственные строки на лету: warn (по умолчанию) — логировать и считать нарушения, strict — прервать генерацию на первом
+        #[arg(long, num_args = 0..=1, default_missing_value = "warn", require_equals = true)]
+        self_check: Option<String>,
+
+        /// Дополнительно выгружать за каждый день provisioning.csv (активации, освобождения номеров, смены устройств) в каталог дня
+        #[arg(long, default_value = "false")]
+        with_provisioning: bool,
+
+        /// Генерировать только для MSISDN из файла (по одному в строке; пустые строки и # — комментарии) вместо диапазонов индексов; абоненты ищутся в redb БД как обычно, вторые стороны по возможности берутся из списка
+        #[arg(long)]
+        msisdn_file: Option<PathBuf>,
+
+        /// С msisdn_file (флаг или конфиг): все вторые стороны CALL/SMS — только номера из списка (замкнутая группа)
+        #[arg(long, default_value = "false")]
+        closed_user_group: bool,
+
+        /// Живое воспроизведение: каждый writer сортирует строки дня по времени начала и пишет событие с start_ts T примерно через (T - начало дня) / speedup после старта воспроизведения; строки дня держатся в памяти
+        #[arg(long, default_value = "false")]
+        realtime: bool,
+
+        /// Ускорение времени для --realtime (по умолчанию 1 — реальное время; 60 — час событий за минуту)
+        #[arg(long, requires = "realtime")]
+        speedup: Option<f64>,
+
+        /// Ничего не генерировать: вывести в stdout оценку (JSON) числа событий, объёма, числа файлов и памяти и выйти
+        #[arg(long, default_value = "false")]
+        dry_run: bool,
+    },
+
+    /// Regenerate the events of one subscriber on one day, as generate-cdr wrote them, and print them as CSV to stdout
+    ReplaySubscriber {
+        /// Путь к базе данных подписчиков (redb или .csv), та же, что у generate-cdr
+        #[arg(long)]
+        subscriber_db: PathBuf,
+
+        /// Дата YYYY-MM-DD
+        #[arg(long)]
+        date: String,
+
+        /// MSISDN абонента без кода страны, как в строках CDR
+        #[arg(long)]
+        msisdn: u64,
+
+        /// Каталог вывода исходного прогона (берётся его cells.csv)
+        #[arg(long, default_value = "out")]
+        out: PathBuf,
+
+        /// Seed исходного прогона (по умолчанию из YAML / CDRGEN_SEED, иначе 42)
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// Префиксы исходного прогона, через запятую
+        #[arg(long)]
+        prefixes: Option<String>,
+
+        /// Число процессов исходного прогона (N, 50% или 0 = auto-detect); от него зависят диапазоны и seed воркеров
+        #[arg(long)]
+        workers: Option<String>,
+
+        /// YAML конфиг исходного прогона; "-" = stdin
+        #[arg(long)]
+        config: Option<PathBuf>,
     },
 }
 
+/// Install the global tracing subscriber
+/// Precedence: --log-level > RUST_LOG > info. Logs go to stderr so stdout stays clean.
+fn init_logging(log_level: Option<&str>, log_json: bool) -> anyhow::Result<()> {
+    let filter = match log_level {
+        Some(level) => EnvFilter::try_new(level)?,
+        None => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+    };
+
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_target(false);
+
+    if log_json {
+        builder.json().init();
+    } else {
+        builder.init();
+    }
+
+    Ok(())
+}
+
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
+    init_logging(cli.log_level.as_deref(), cli.log_json)?;
 
-    match cli.command {
+    let result = match cli.command {
         Commands::GenerateSubscribers {
             output,
             size,
             history_days,
             device_change_rate,
             number_release_rate,
+            shared_device_rate,
+            software_update_rate,
             cooldown_days,
+            reassignment_rate,
+            reassign_to_existing_rate,
+            max_dormancy_days,
             prefixes,
             seed,
+            start_date,
             config,
+            also_export_events,
+            db_batch_size,
+            db_durability,
+            compact,
         } => {
             handle_generate_subscribers(
                 output,
@@ -170,12 +549,48 @@ fn main() -> anyhow::Result<()> {
                 history_days,
                 device_change_rate,
                 number_release_rate,
+                shared_device_rate,
+                software_update_rate,
                 cooldown_days,
+                reassignment_rate,
+                reassign_to_existing_rate,
+                max_dormancy_days,
                 prefixes,
                 seed,
+                start_date,
                 config,
+                also_export_events,
+                db_batch_size,
+                db_durability,
+                compact,
             )
         }
+        Commands::ValidateSubscribers {
+            input,
+            max_imsis_per_imei,
+            strict,
+            repair,
+            output,
+        } => handle_validate_subscribers(input, max_imsis_per_imei, strict, repair, output),
+        Commands::CompactSubscribers { input, spot_checks, seed } => handle_compact_subscribers(input, spot_checks, seed),
+        Commands::SubscriberStats { input, json } => handle_subscriber_stats(input, json),
+        Commands::Stats { input, json } => handle_stats(input, json),
+        Commands::DiffSubscribers { a, b, at, output, json } => handle_diff_subscribers(a, b, at, output, json),
+        Commands::ExportCrm {
+            subscriber_db,
+            at,
+            output,
+            seed,
+            config,
+        } => handle_export_crm(subscriber_db, at, output, seed, config),
+        Commands::ExportProvisioning {
+            subscriber_db,
+            date,
+            output,
+            config,
+        } => handle_export_provisioning(subscriber_db, date, output, config),
+        Commands::Schema { format, config } => handle_schema(format, config),
+        Commands::DeliverLate { out, as_of } => handle_deliver_late(out, as_of),
         Commands::GenerateCdr {
             subscriber_db,
             start,
@@ -184,16 +599,41 @@ fn main() -> anyhow::Result<()> {
             seed,
             prefixes,
             rotate_bytes,
+            compression,
+            compression_level,
             workers,
             config,
             tz,
             cells,
             cell_center,
             cell_radius_km,
+            regen_cells,
+            extend_cells,
             mo_share_call,
             mo_share_sms,
             imei_change_prob,
+            daily_inactive_rate,
+            chunk_size,
+            fail_on_miss_rate,
+            check_db,
+            verbose,
+            write_shard_stats,
             cleanup_after_archive,
+            overwrite_policy,
+            output_format,
+            sample,
+            window,
+            start_time,
+            end_time,
+            bundle_window,
+            no_metrics,
+            self_check,
+            with_provisioning,
+            msisdn_file,
+            closed_user_group,
+            realtime,
+            speedup,
+            dry_run,
         } => {
             handle_generate_cdr(
                 subscriber_db,
@@ -203,41 +643,100 @@ fn main() -> anyhow::Result<()> {
                 seed,
                 prefixes,
                 rotate_bytes,
+                compression,
+                compression_level,
                 workers,
                 config,
                 tz,
                 cells,
                 cell_center,
                 cell_radius_km,
+                regen_cells,
+                extend_cells,
                 mo_share_call,
                 mo_share_sms,
                 imei_change_prob,
+                daily_inactive_rate,
+                chunk_size,
+                fail_on_miss_rate,
+                check_db,
+                verbose,
+                write_shard_stats,
                 cleanup_after_archive,
+                overwrite_policy,
+                output_format,
+                sample,
+                window,
+                start_time,
+                end_time,
+                bundle_window,
+                no_metrics,
+                self_check,
+                with_provisioning,
+                msisdn_file,
+                closed_user_group,
+                realtime,
+                speedup,
+                dry_run,
             )
         }
+        Commands::ReplaySubscriber {
+            subscriber_db,
+            date,
+            msisdn,
+            out,
+            seed,
+            prefixes,
+            workers,
+            config,
+        } => handle_replay_subscriber(subscriber_db, date, msisdn, out, seed, prefixes, workers, config),
+    };
+
+    if let Err(ref e) = result {
+        error!("{:#}", e);
     }
+    result
 }
 
+#[allow(clippy::too_many_arguments)]
 fn handle_generate_subscribers(
     output: PathBuf,
     size: usize,
     history_days: usize,
     device_change_rate: f64,
     number_release_rate: f64,
+    shared_device_rate: f64,
+    software_update_rate: f64,
     cooldown_days: usize,
+    reassignment_rate: f64,
+    reassign_to_existing_rate: f64,
+    max_dormancy_days: Option<usize>,
     prefixes: Option<String>,
     seed: u64,
+    start_date: String,
     config_path: Option<PathBuf>,
+    also_export_events: Option<PathBuf>,
+    db_batch_size: usize,
+    db_durability: String,
+    compact: bool,
 ) -> anyhow::Result<()> {
-    println!("=== Generating Subscriber Database ===\n");
+    info!("=== Generating Subscriber Database ===");
 
-    // Load config for prefixes and mccmnc_pool
-    let cfg = if let Some(ref path) = config_path {
-        load_config(Some(path))?
-    } else {
-        Config::default()
+    let write_options = DbWriteOptions {
+        batch_size: db_batch_size.max(1),
+        durability: DbDurability::from_str(&db_durability)
+            .with_context(|| format!("Unknown --db-durability {:?}, expected fast or safe", db_durability))?,
+        compact,
     };
 
+    // Reject an unknown export format before spending time on generation
+    let export = also_export_events
+        .map(|path| EventExportFormat::from_path(&path).map(|format| (path, format)))
+        .transpose()?;
+
+    // Load config for prefixes and mccmnc_pool (YAML, then CDRGEN_* environment)
+    let cfg = load_config(config_path.as_deref())?;
+
     // Parse prefixes from CLI or use config
     let prefixes_list = if let Some(prefixes_str) = prefixes {
         parse_prefixes(&prefixes_str)?
@@ -245,64 +744,296 @@ fn handle_generate_subscribers(
         cfg.prefixes.clone()
     };
 
+    // History starts at UTC midnight of --start-date
+    let start_timestamp_ms = chrono::NaiveDate::parse_from_str(&start_date, "%Y-%m-%d")
+        .with_context(|| format!("Invalid --start-date {:?}, expected YYYY-MM-DD", start_date))?
+        .and_time(chrono::NaiveTime::MIN)
+        .and_utc()
+        .timestamp_millis();
+
     let gen_config = GeneratorConfig {
         initial_subscribers: size,
         history_days,
-        device_change_rate: device_change_rate.max(0.0).min(1.0),
-        number_release_rate: number_release_rate.max(0.0).min(1.0),
+        device_change_rate: device_change_rate.clamp(0.0, 1.0),
+        number_release_rate: number_release_rate.clamp(0.0, 1.0),
+        software_update_rate: software_update_rate.clamp(0.0, 1.0),
+        device_change_seasonality: cfg.db_device_change_seasonality.clone(),
+        device_change_special_days: cfg.db_device_change_special_days.clone(),
+        number_release_seasonality: cfg.db_number_release_seasonality.clone(),
+        number_release_special_days: cfg.db_number_release_special_days.clone(),
+        shared_device_rate: shared_device_rate.clamp(0.0, 1.0),
         cooldown_days,
+        reassignment_rate: reassignment_rate.clamp(0.0, 1.0),
+        reassign_to_existing_rate: reassign_to_existing_rate.clamp(0.0, 1.0),
+        max_dormancy_days,
         prefixes: prefixes_list,
         mccmnc_pool: cfg.mccmnc_pool.clone(),
+        prefix_operator_map: cfg.prefix_operator_map.clone(),
         seed,
-        start_timestamp_ms: 1704067200000, // 2024-01-01
+        start_timestamp_ms,
     };
 
-    generate_database_redb(&gen_config, &output)?;
+    // One generation pass feeds both the export and the redb database
+    info!(subscribers = size, history_days, "Generating subscriber events");
+    let events = generate_database(&gen_config)?;
+    let event_count = events.len();
+    if let Some((ref path, format)) = export {
+        format.write(&events, path)?;
+    }
+    let stats = write_database_redb(events, &output, &write_options)?;
+
+    info!("=== Subscriber Database Generation Complete ===");
+    info!("Database file: {:?}", output);
+    info!(
+        "Events: {}, MSISDNs: {}, snapshots: {}",
+        event_count, stats.total_msisdns, stats.total_snapshots
+    );
+    if let Some((path, _)) = export {
+        info!("Events exported to: {:?}", path);
+    }
+
+    Ok(())
+}
+
+fn handle_validate_subscribers(
+    input: PathBuf,
+    max_imsis_per_imei: usize,
+    strict: bool,
+    repair: bool,
+    output: Option<PathBuf>,
+) -> anyhow::Result<()> {
+    if !repair {
+        if output.is_some() {
+            anyhow::bail!("--output only applies with --repair");
+        }
+        let db = SubscriberDbRedb::open(&input)?;
+        db.validate(max_imsis_per_imei)?;
+        if strict {
+            db.validate_windows()?;
+        }
+        info!("Subscriber database {:?} is valid", input);
+        return Ok(());
+    }
+
+    let output = output.ok_or_else(|| anyhow::anyhow!("--repair needs --output for the repaired database"))?;
+    let mut history = SubscriberDatabase::load_from_csv(&input)?;
+    let report = history.repair();
+    for adjustment in &report.adjustments {
+        warn!("Repair: {}", adjustment);
+    }
+    info!("Repaired {:?}: {}", input, report.summary());
+    write_snapshots_redb(history.get_snapshots(), &output, &DbWriteOptions::default())?;
+
+    // The repaired database always gets the strict checks
+    let db = SubscriberDbRedb::open(&output)?;
+    db.validate(max_imsis_per_imei)?;
+    db.validate_windows()?;
+    info!("Repaired subscriber database {:?} is valid", output);
+    Ok(())
+}
+
+fn handle_compact_subscribers(input: PathBuf, spot_checks: usize, seed: u64) -> anyhow::Result<()> {
+    let report = compact_database(&input, spot_checks, seed)?;
+    println!("Subscriber database: {:?}", input);
+    println!("Compacted: {}", report.summary());
+    Ok(())
+}
+
+fn handle_subscriber_stats(input: PathBuf, json: bool) -> anyhow::Result<()> {
+    let db = SubscriberDbRedb::open(&input)?;
+    let stats = db.stats()?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+    } else {
+        println!("Subscriber database: {:?}", input);
+        print!("{}", stats.to_table());
+    }
+
+    Ok(())
+}
+
+fn handle_stats(input: PathBuf, json: bool) -> anyhow::Result<()> {
+    let days = read_day_summaries(&input)?;
+
+    if json {
+        let days: Vec<serde_json::Value> =
+            days.iter().map(|(day, summary)| serde_json::json!({ "day": day, "summary": summary })).collect();
+        println!("{}", serde_json::to_string_pretty(&days)?);
+        return Ok(());
+    }
+
+    let mut config_hashes = std::collections::BTreeSet::new();
+    for (day, summary) in &days {
+        println!(
+            "{}: {} calls, {} SMS, {} data, {} active subscribers",
+            day, summary.total_calls, summary.total_sms, summary.total_data, summary.active_subscribers
+        );
+        match summary.provenance {
+            Some(ref provenance) => {
+                println!("  {}", provenance.describe());
+                config_hashes.insert(provenance.config_sha256.clone());
+            }
+            None => println!("  no provenance recorded"),
+        }
+    }
+    if config_hashes.len() > 1 {
+        println!("{} days from {} different configs", days.len(), config_hashes.len());
+    }
+    Ok(())
+}
+
+fn handle_diff_subscribers(a: PathBuf, b: PathBuf, at: Option<String>, output: Option<PathBuf>, json: bool) -> anyhow::Result<()> {
+    let at_ms = at.as_deref().map(parse_as_of).transpose()?;
+    let db_a = SubscriberDbRedb::open(&a)?;
+    let db_b = SubscriberDbRedb::open(&b)?;
+
+    let mut csv = output.as_deref().map(csv::Writer::from_path).transpose()?;
+    let diff = diff_databases(&db_a, &db_b, at_ms, |msisdn_diff| {
+        if let Some(ref mut csv) = csv {
+            csv.serialize(msisdn_diff)?;
+        }
+        Ok(())
+    })?;
+    if let Some(mut csv) = csv {
+        csv.flush()?;
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+    } else {
+        println!("A: {:?}", a);
+        println!("B: {:?}", b);
+        print!("{}", diff.to_table());
+    }
+    if let Some(output) = output {
+        info!("Per-MSISDN differences written to {:?}", output);
+    }
+    Ok(())
+}
+
+fn handle_export_crm(
+    subscriber_db: PathBuf,
+    at: String,
+    output: PathBuf,
+    seed: Option<u64>,
+    config_path: Option<PathBuf>,
+) -> anyhow::Result<()> {
+    let date = chrono::NaiveDate::parse_from_str(&at, "%Y-%m-%d").with_context(|| format!("Invalid --at date {:?}", at))?;
+    let mut cfg = load_config(config_path.as_deref())?;
+    if let Some(seed) = seed {
+        cfg.seed = seed;
+    }
 
-    println!("\n=== Subscriber Database Generation Complete ===");
-    println!("Database file: {:?}", output);
+    let db = SubscriberDbRedb::open(&subscriber_db)?;
+    let file = std::fs::File::create(&output).with_context(|| format!("Failed to create {:?}", output))?;
+    let rows = export_crm(&db, &cfg, date, std::io::BufWriter::new(file))?;
+    info!("Exported {} CRM rows for {} to {:?}", rows, date, output);
+    Ok(())
+}
+
+fn handle_export_provisioning(subscriber_db: PathBuf, date: String, output: PathBuf, config_path: Option<PathBuf>) -> anyhow::Result<()> {
+    let date = chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d").with_context(|| format!("Invalid --date {:?}", date))?;
+    let cfg = load_config(config_path.as_deref())?;
+
+    let db = SubscriberDbRedb::open(&subscriber_db)?;
+    let file = std::fs::File::create(&output).with_context(|| format!("Failed to create {:?}", output))?;
+    let rows = export_provisioning(&db, &cfg, date, std::io::BufWriter::new(file))?;
+    info!("Exported {} provisioning rows for {} to {:?}", rows, date, output);
+    Ok(())
+}
 
+fn handle_schema(format: String, config_path: Option<PathBuf>) -> anyhow::Result<()> {
+    let format = SchemaFormat::from_str(&format)
+        .ok_or_else(|| anyhow::anyhow!("Invalid schema format: {}. Expected json or markdown.", format))?;
+    let cfg = load_config(config_path.as_deref())?;
+    print!("{}", format.render(&OutputSchema::new(&WriterOptions::from_config(&cfg))?)?);
     Ok(())
 }
 
+fn handle_deliver_late(out: PathBuf, as_of: String) -> anyhow::Result<()> {
+    let as_of_ms = parse_as_of(&as_of)?;
+    let delivered = deliver_late_files(&out, as_of_ms)?;
+    for late in &delivered {
+        info!("Delivered {} → {}/", late.entry.file, late.day);
+    }
+    info!("Delivered {} late files as of {}", delivered.len(), as_of);
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
 fn handle_generate_cdr(
     subscriber_db: PathBuf,
     start: String,
     days: usize,
     out: PathBuf,
-    seed: u64,
+    seed: Option<u64>,
     prefixes: Option<String>,
     rotate_bytes: Option<u64>,
-    workers: Option<usize>,
+    compression: Option<String>,
+    compression_level: Option<i32>,
+    workers: Option<String>,
     config_path: Option<PathBuf>,
     tz: Option<String>,
     cells: Option<usize>,
     cell_center: Option<String>,
     cell_radius_km: Option<f64>,
+    regen_cells: bool,
+    extend_cells: Option<usize>,
     mo_share_call: Option<f64>,
     mo_share_sms: Option<f64>,
     imei_change_prob: Option<f64>,
+    daily_inactive_rate: Option<f64>,
+    chunk_size: Option<usize>,
+    fail_on_miss_rate: Option<f64>,
+    check_db: Option<bool>,
+    verbose: bool,
+    write_shard_stats: bool,
     cleanup_after_archive: bool,
+    overwrite_policy: Option<String>,
+    output_format: Option<String>,
+    sample: Option<f64>,
+    window: Option<String>,
+    start_time: Option<String>,
+    end_time: Option<String>,
+    bundle_window: bool,
+    no_metrics: bool,
+    self_check: Option<String>,
+    with_provisioning: bool,
+    msisdn_file: Option<PathBuf>,
+    closed_user_group: bool,
+    realtime: bool,
+    speedup: Option<f64>,
+    dry_run: bool,
 ) -> anyhow::Result<()> {
-    println!("=== Generating CDR Data ===\n");
+    info!("=== Generating CDR Data ===");
+
+    // Sampled runs keep every Nth subscriber and write apart from real output
+    let out = match sample {
+        Some(fraction) => {
+            if !(fraction > 0.0 && fraction <= 1.0) {
+                anyhow::bail!("--sample must be in (0, 1], got {}", fraction);
+            }
+            out.join("sample")
+        }
+        None => out,
+    };
+    let sample_stride = sample.map_or(1, |fraction| ((1.0 / fraction).round() as usize).max(1));
+    // Every Nth subscriber only samples 1/N exactly
+    let effective_fraction = 1.0 / sample_stride as f64;
+    if let Some(fraction) = sample.filter(|&fraction| (fraction - effective_fraction).abs() > 1e-9) {
+        warn!("--sample {} keeps 1 in {} subscribers, an effective fraction of {:.4}", fraction, sample_stride, effective_fraction);
+    }
 
-    // Verify subscriber database exists
-    if !subscriber_db.exists() {
-        eprintln!("Error: Subscriber database not found: {:?}", subscriber_db);
-        eprintln!("\nPlease generate a subscriber database first:");
-        eprintln!("  rs_cdr_generator generate-subscribers --output subscriber_db.redb");
+    // Verify subscriber database exists (a dry run falls back to `subscribers` without one)
+    if !dry_run && !subscriber_db.exists() {
+        error!("Subscriber database not found: {:?}", subscriber_db);
+        error!("Please generate a subscriber database first: rs_cdr_generator generate-subscribers --output subscriber_db.redb");
         std::process::exit(1);
     }
 
-    // Load and merge configuration with CLI priority
-    let mut cfg = if let Some(ref path) = config_path {
-        load_config(Some(path))?
-    } else {
-        Config::default()
-    };
-
-    // Set subscriber database path
-    cfg.subscriber_db_redb_path = Some(subscriber_db.clone());
+    // Load and merge configuration: defaults < YAML < CDRGEN_* environment < CLI flags
+    let mut cfg = load_config(config_path.as_deref())?;
 
     // CLI overrides YAML (only if explicitly provided)
     if let Some(prefixes_str) = prefixes {
@@ -313,14 +1044,18 @@ fn handle_generate_cdr(
         cfg.rotate_bytes = rb;
     }
 
+    if let Some(c) = compression {
+        cfg.compression_type = c;
+    }
+
+    if let Some(level) = compression_level {
+        cfg.compression_level = Some(level);
+    }
+
     if let Some(w) = workers {
-        cfg.workers = if w == 0 {
-            num_cpus::get()
-        } else {
-            w.max(1)
-        };
+        cfg.workers = parse_workers(&w)?;
     } else if cfg.workers == 0 {
-        cfg.workers = num_cpus::get();
+        cfg.workers = available_cores();
     }
 
     if let Some(tz_name) = tz {
@@ -328,161 +1063,242 @@ fn handle_generate_cdr(
     }
 
     if let Some(mo) = mo_share_call {
-        cfg.mo_share_call = mo.max(0.0).min(1.0);
+        cfg.mo_share_call = mo.clamp(0.0, 1.0);
     }
 
     if let Some(mo) = mo_share_sms {
-        cfg.mo_share_sms = mo.max(0.0).min(1.0);
+        cfg.mo_share_sms = mo.clamp(0.0, 1.0);
     }
 
     if let Some(prob) = imei_change_prob {
-        cfg.imei_daily_change_prob = prob.max(0.0).min(1.0);
+        cfg.imei_daily_change_prob = prob.clamp(0.0, 1.0);
+    }
+
+    if let Some(rate) = daily_inactive_rate {
+        cfg.daily_inactive_rate = rate.clamp(0.0, 1.0);
+    }
+
+    if let Some(cs) = chunk_size {
+        cfg.chunk_size = cs.max(1);
+    }
+
+    if let Some(rate) = fail_on_miss_rate {
+        cfg.fail_on_miss_rate = Some(rate.clamp(0.0, 1.0));
+    }
+
+    if let Some(check) = check_db {
+        cfg.check_db = check;
+    }
+
+    if verbose {
+        cfg.verbose = true;
+    }
+
+    if write_shard_stats {
+        cfg.write_shard_stats = true;
+    }
+
+    if no_metrics {
+        cfg.metrics = false;
+    }
+
+    if let Some(path) = msisdn_file {
+        cfg.msisdn_file = Some(path);
+    }
+    if closed_user_group {
+        cfg.closed_user_group = true;
     }
 
+    if realtime {
+        cfg.realtime = true;
+    }
+    if let Some(speedup) = speedup {
+        if !(speedup.is_finite() && speedup > 0.0) {
+            anyhow::bail!("--speedup must be a positive number, got {}", speedup);
+        }
+        cfg.realtime_speedup = speedup;
+    }
+
+    if let Some(policy) = overwrite_policy {
+        cfg.overwrite_policy = OverwritePolicy::from_str(&policy)
+            .ok_or_else(|| anyhow::anyhow!("--overwrite-policy must be fail, clean or merge, got {:?}", policy))?;
+    }
+
+    if let Some(format) = output_format {
+        cfg.output_format = OutputFormat::from_str(&format)
+            .ok_or_else(|| anyhow::anyhow!("--output-format must be csv or arrow, got {:?}", format))?;
+    }
+
+    if let Some(mode) = self_check {
+        cfg.self_check = SelfCheckMode::from_str(&mode)
+            .ok_or_else(|| anyhow::anyhow!("--self-check must be warn, strict or off, got {:?}", mode))?;
+    }
+
+    if let Some(seed) = seed {
+        cfg.seed = seed;
+    }
+    cfg.sample_stride = sample_stride;
+
     // Parse cell center from CLI or use config values
-    let (center_lat, center_lon) = if let Some(cell_center_str) = cell_center {
+    if let Some(cell_center_str) = cell_center {
         let parts: Vec<&str> = cell_center_str.split(',').collect();
         if parts.len() == 2 {
-            let lat = parts[0].trim().parse::<f64>().unwrap_or(cfg.center_lat);
-            let lon = parts[1].trim().parse::<f64>().unwrap_or(cfg.center_lon);
-            (lat, lon)
-        } else {
-            (cfg.center_lat, cfg.center_lon)
+            cfg.center_lat = parts[0].trim().parse::<f64>().unwrap_or(cfg.center_lat);
+            cfg.center_lon = parts[1].trim().parse::<f64>().unwrap_or(cfg.center_lon);
         }
-    } else {
-        (cfg.center_lat, cfg.center_lon)
-    };
+    }
 
-    let cell_radius = cell_radius_km.unwrap_or(cfg.radius_km);
-    let num_cells = cells.unwrap_or(cfg.cells);
+    if let Some(radius) = cell_radius_km {
+        cfg.radius_km = radius;
+    }
 
-    // Ensure cells catalog
-    let cells_path = ensure_cells_catalog(
-        &out,
-        num_cells,
-        center_lat,
-        center_lon,
-        cell_radius,
-        seed,
-    )?;
+    if let Some(n) = cells {
+        cfg.cells = n;
+    }
+
+    if regen_cells {
+        cfg.regen_cells = true;
+    }
 
-    let (_cells_all, _cells_by_rat) = load_cells_catalog(&cells_path)?;
+    // Grow the existing catalog first, so the run below finds it matching
+    if let Some(extra) = extend_cells {
+        cfg.cells = extend_cells_catalog(&out, &CellsMeta::from_config(&cfg), extra)?.cells;
+    }
 
-    let tz = tz_from_name(&cfg.tz_name);
+    if let Some(w) = window {
+        cfg.time_window = Some(TimeWindow::parse(&w)?);
+    } else if start_time.is_some() || end_time.is_some() {
+        cfg.time_window = Some(TimeWindow::new(
+            start_time.as_deref().unwrap_or("00:00"),
+            end_time.as_deref().unwrap_or("24:00"),
+        )?);
+    }
+    // Windows of an hourly feed stay as part files unless asked to bundle; Arrow part files are never bundled
+    let bundle = (cfg.time_window.is_none() || bundle_window) && cfg.output_format == OutputFormat::Csv;
 
     // Parse start date
     let start_date = chrono::NaiveDate::parse_from_str(&start, "%Y-%m-%d")?;
 
-    // Open redb database (will be shared across all workers)
-    println!("Loading subscriber database: {:?}", subscriber_db);
-    let redb = SubscriberDbRedb::open(&subscriber_db)?;
-    let subs = redb.count_msisdns()?;
-    println!("Loaded {} subscribers from database\n", subs);
+    if dry_run {
+        let subscribers = if let Some(ref path) = cfg.msisdn_file {
+            load_msisdn_file(path)?.len()
+        } else if subscriber_db.exists() {
+            SubscriberDbRedb::open(&subscriber_db)?.count_msisdns()?
+        } else {
+            cfg.subscribers
+        };
+        let estimate = estimate_run(&cfg, days, subscribers);
+        info!(
+            "Dry run: {:.0} rows, {} bytes ({}) in {} part files, ~{} bytes of batches in memory",
+            estimate.rows(),
+            estimate.output_bytes(),
+            estimate.compression,
+            estimate.part_files,
+            estimate.peak_ram_bytes
+        );
+        println!("{}", serde_json::to_string_pretty(&estimate)?);
+        return Ok(());
+    }
 
-    let redb_arc = Arc::new(redb);
+    // The redb database is opened once and shared across all days and workers
+    let mut builder = DayGenerator::builder().config(cfg).subscriber_db(subscriber_db).out_dir(&out);
+    if bundle {
+        builder = builder.bundle(cleanup_after_archive);
+    }
+    if with_provisioning {
+        builder = builder.provisioning();
+    }
+    let mut generator = builder.build()?;
+    if generator.config().output_format == OutputFormat::Csv {
+        generator.write_schema()?;
+    }
 
     // Generate data for each day
+    let mut total_events = 0;
     for d in 0..days {
-        let day_naive = start_date + Duration::days(d as i64);
-        let day = tz
-            .with_ymd_and_hms(
-                day_naive.year(),
-                day_naive.month(),
-                day_naive.day(),
-                0,
-                0,
-                0,
-            )
-            .unwrap();
-
-        let day_str = day.format("%Y-%m-%d").to_string();
-        let day_dir = out.join(&day_str);
-        std::fs::create_dir_all(&day_dir)?;
-
-        // Split users uniformly across workers
-        let w = cfg.workers;
-        let shard_size = subs / w;
-
-        let mut ranges = Vec::new();
-        let mut s = 0;
-        for i in 0..w {
-            let e = if i < w - 1 { s + shard_size } else { subs };
-            ranges.push((s, e));
-            s = e;
-        }
-
-        // Create Tokio runtime for async writers
-        let rt = tokio::runtime::Runtime::new()?;
+        let summary = generator.generate(start_date + Duration::days(d as i64))?;
+        total_events += summary.total_calls + summary.total_sms + summary.sms_retry_rows + summary.total_data;
+    }
 
-        // Determine number of writer tasks (default: workers / 2)
-        let writer_tasks = if cfg.writer_tasks > 0 {
-            cfg.writer_tasks
-        } else {
-            (w / 2).max(1)
-        };
+    info!("=== CDR Generation Complete ===");
+    if let Some(throughput) = generator.run_throughput() {
+        info!("Run throughput: {}", throughput.report());
+    }
 
-        // Create channels and spawn async writer tasks
-        let mut writer_channels = Vec::new();
-        let mut writer_handles = Vec::new();
-
-        for shard_id in 0..writer_tasks {
-            let (tx, rx) = unbounded();
-            writer_channels.push(tx);
-
-            let out_dir = out.clone();
-            let day_str_clone = day_str.clone();
-            let rotate_bytes = cfg.rotate_bytes;
-            let compression_type = rs_cdr_generator::compression::CompressionType::from_str(&cfg.compression_type)
-                .unwrap_or(rs_cdr_generator::compression::CompressionType::Gzip);
-
-            let handle = rt.spawn(async move {
-                writer_task(
-                    rx,
-                    out_dir,
-                    day_str_clone,
-                    shard_id,
-                    rotate_bytes,
-                    compression_type,
-                )
-                .await
-            });
-
-            writer_handles.push(handle);
-        }
+    if sample.is_some() {
+        // Only the CDR files grow with the subscriber count; per-day summaries, manifests, stats and the cells
+        // catalog do not
+        let sample_bytes = cdr_files_size(&out)?;
+        info!("Sample 1/{}: {} events, {} bytes of CDR files in {:?}", sample_stride, total_events, sample_bytes, out);
+        info!(
+            "Estimated full run from an effective fraction of {:.4}: {} events, {} bytes of CDR files",
+            effective_fraction,
+            total_events * sample_stride,
+            sample_bytes * sample_stride as u64
+        );
+    }
 
-        // Run workers in parallel with writer channels
-        ranges
-            .par_iter()
-            .enumerate()
-            .try_for_each(|(i, &(lo, hi))| {
-                // Map worker to writer shard (round-robin)
-                let writer_idx = i % writer_tasks;
-                let writer_tx = writer_channels[writer_idx].clone();
-
-                worker_generate(day, i, (lo, hi), &cfg, &out, None, Some(&redb_arc), writer_tx)
-            })?;
-
-        // Send Close messages to all writers
-        for tx in writer_channels {
-            tx.send(WriterMessage::Close)?;
-        }
+    Ok(())
+}
 
-        // Wait for all writer tasks to complete
-        for handle in writer_handles {
-            rt.block_on(handle)??;
+/// Total size of the CDR part files and day bundles (`cdr_*`) under `path`
+fn cdr_files_size(path: &Path) -> anyhow::Result<u64> {
+    let mut total = 0;
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        let meta = entry.metadata()?;
+        if meta.is_dir() {
+            total += cdr_files_size(&entry.path())?;
+        } else if entry.file_name().to_string_lossy().starts_with("cdr_") {
+            total += meta.len();
         }
+    }
+    Ok(total)
+}
 
-        // Create summary and bundle
-        create_daily_summary(&out, &day)?;
-        let compression_ext = rs_cdr_generator::compression::CompressionType::from_str(&cfg.compression_type)
-            .unwrap_or(rs_cdr_generator::compression::CompressionType::Gzip)
-            .extension();
-        let tarfile_path = bundle_day(&out, &day, cleanup_after_archive, compression_ext)?;
-
-        println!("Day {} done → {:?}", day_str, tarfile_path);
+/// Print the rows of `msisdn` on `date` with the header of the part files; `record_seq` is left out since
+/// the writer numbers rows per part file
+#[allow(clippy::too_many_arguments)]
+fn handle_replay_subscriber(
+    subscriber_db: PathBuf,
+    date: String,
+    msisdn: u64,
+    out: PathBuf,
+    seed: Option<u64>,
+    prefixes: Option<String>,
+    workers: Option<String>,
+    config_path: Option<PathBuf>,
+) -> anyhow::Result<()> {
+    let date = chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d").with_context(|| format!("Invalid --date {:?}", date))?;
+    if !subscriber_db.exists() {
+        anyhow::bail!("Subscriber database not found: {:?}", subscriber_db);
+    }
+    let mut cfg = load_config(config_path.as_deref())?;
+    if let Some(prefixes_str) = prefixes {
+        cfg.prefixes = parse_prefixes(&prefixes_str)?;
+    }
+    if let Some(w) = workers {
+        cfg.workers = parse_workers(&w)?;
+    }
+    if let Some(seed) = seed {
+        cfg.seed = seed;
     }
 
-    println!("\n=== CDR Generation Complete ===");
+    let generator = DayGenerator::builder().config(cfg).subscriber_db(subscriber_db).out_dir(&out).build()?;
+    let rows = generator.replay_subscriber(date, msisdn)?;
+    info!("Replayed {} rows of {} on {}", rows.len(), msisdn, date);
 
+    let options = WriterOptions {
+        emit_record_seq: false,
+        ..WriterOptions::from_config(generator.config())
+    };
+    let mut stdout = std::io::stdout().lock();
+    writeln!(stdout, "{}", header_columns(&options).join(";"))?;
+    for row in &rows {
+        let mut serializer = row_serializer(Vec::new());
+        serialize_row(&mut serializer, row, &options)?;
+        let line = serializer.into_inner().map_err(|e| anyhow::anyhow!("Failed to serialize row: {}", e))?;
+        stdout.write_all(&line)?;
+    }
     Ok(())
 }
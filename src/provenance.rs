@@ -0,0 +1,175 @@
+// Provenance of a run in summary.json and run_config.yaml: generator version, a fingerprint of the effective
+// config, the seed and the subscriber database
+use crate::config::Config;
+#[cfg(feature = "redb")]
+use crate::subscriber_db_redb::SubscriberDbRedb;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Effective config and provenance of a day, written next to summary.json
+pub const RUN_CONFIG_FILE: &str = "run_config.yaml";
+
+/// Commit the generator was built from (build.rs); None for a build outside a git checkout
+pub const GIT_HASH: Option<&str> = option_env!("RS_CDR_GIT_HASH");
+
+/// Which generator, config and subscriber database produced a day
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunProvenance {
+    pub crate_version: String,
+    pub git_hash: Option<String>,
+    /// SHA-256 of the effective config (seed and worker count resolved), see `config_sha256`
+    pub config_sha256: String,
+    pub seed: u64,
+    /// None for random subscribers and for a redb database opened by the caller
+    pub subscriber_db: Option<PathBuf>,
+    /// SHA-256 of the database's metadata: the redb metadata table and MSISDN count, or the size and
+    /// modification time of an event history file
+    pub subscriber_db_fingerprint: Option<String>,
+}
+
+impl RunProvenance {
+    pub fn new(config: &Config, subscriber_db: Option<PathBuf>, subscriber_db_fingerprint: Option<String>) -> anyhow::Result<Self> {
+        Ok(RunProvenance {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            git_hash: GIT_HASH.map(str::to_string),
+            config_sha256: config_sha256(config)?,
+            seed: config.seed,
+            subscriber_db,
+            subscriber_db_fingerprint,
+        })
+    }
+
+    /// One line, e.g. "v5.1.0 (git 1f99818a2b3c), config 3a7bd3e2360a, seed 42, subscribers subs.redb (9f86d081884c)"
+    pub fn describe(&self) -> String {
+        let short = |hash: &str| hash.chars().take(12).collect::<String>();
+        let mut line = format!("v{}", self.crate_version);
+        if let Some(ref git_hash) = self.git_hash {
+            line.push_str(&format!(" (git {})", git_hash));
+        }
+        line.push_str(&format!(", config {}, seed {}", short(&self.config_sha256), self.seed));
+        match (&self.subscriber_db, &self.subscriber_db_fingerprint) {
+            (Some(path), Some(fingerprint)) => line.push_str(&format!(", subscribers {} ({})", path.display(), short(fingerprint))),
+            (Some(path), None) => line.push_str(&format!(", subscribers {}", path.display())),
+            (None, Some(fingerprint)) => line.push_str(&format!(", subscribers ({})", short(fingerprint))),
+            (None, None) => line.push_str(", random subscribers"),
+        }
+        line
+    }
+}
+
+/// `run_config.yaml`: the provenance and the config its hash was taken of
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunConfig {
+    pub provenance: RunProvenance,
+    pub config: Config,
+}
+
+/// Write `day_dir/run_config.yaml`
+pub fn write_run_config(day_dir: &Path, provenance: &RunProvenance, config: &Config) -> anyhow::Result<()> {
+    let run_config = RunConfig { provenance: provenance.clone(), config: config.clone() };
+    let path = day_dir.join(RUN_CONFIG_FILE);
+    std::fs::write(&path, serde_yaml::to_string(&run_config)?).with_context(|| format!("Failed to write {:?}", path))
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// SHA-256 of the config as JSON. Objects are key-sorted, so the order of map entries does not matter
+pub fn config_sha256(config: &Config) -> anyhow::Result<String> {
+    let value = serde_json::to_value(config)?;
+    Ok(sha256_hex(&serde_json::to_vec(&value)?))
+}
+
+/// Fingerprint of an event history file (CSV or Arrow) from its size and modification time
+pub fn file_fingerprint(path: &Path) -> anyhow::Result<String> {
+    let metadata = std::fs::metadata(path).with_context(|| format!("Failed to read metadata of {:?}", path))?;
+    let modified = metadata.modified()?.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+    Ok(sha256_hex(format!("size={}\nmodified_ns={}\n", metadata.len(), modified).as_bytes()))
+}
+
+/// Fingerprint of a redb database from its metadata table and MSISDN count, without reading the snapshots
+#[cfg(feature = "redb")]
+pub fn redb_fingerprint(db: &SubscriberDbRedb) -> anyhow::Result<String> {
+    let mut text: String = db.metadata()?.iter().map(|(key, value)| format!("{}={}\n", key, value)).collect();
+    text.push_str(&format!("msisdns={}\n", db.count_msisdns()?));
+    Ok(sha256_hex(text.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// The value changed in some way that keeps it of the same type, None when there is no obvious one
+    fn mutate(value: &serde_json::Value) -> Option<serde_json::Value> {
+        use serde_json::Value;
+        match value {
+            Value::Bool(b) => Some(Value::Bool(!b)),
+            Value::Number(n) => match (n.as_u64(), n.as_f64()) {
+                (Some(u), _) => Some(Value::from(u + 1)),
+                (None, Some(f)) => Some(Value::from(f + 0.5)),
+                _ => None,
+            },
+            Value::String(s) => Some(Value::String(format!("{}x", s))),
+            Value::Array(items) if !items.is_empty() => Some(Value::Array(items[1..].to_vec())),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_config_hash_changes_with_any_value() {
+        let config = Config::default();
+        let hash = config_sha256(&config).unwrap();
+        assert_eq!(hash.len(), 64);
+        assert_eq!(config_sha256(&config.clone()).unwrap(), hash);
+
+        // Every top-level key that can be changed and still load moves the hash
+        let serde_json::Value::Object(keys) = serde_json::to_value(&config).unwrap() else { panic!("config is no map") };
+        let mut changed = 0;
+        for (key, value) in &keys {
+            let Some(mutated) = mutate(value) else { continue };
+            let mut json = serde_json::Value::Object(keys.clone());
+            json[key] = mutated;
+            let Ok(other) = serde_json::from_value::<Config>(json) else { continue };
+            assert_ne!(config_sha256(&other).unwrap(), hash, "changing {} kept the hash", key);
+            changed += 1;
+        }
+        // The rest are enums, unset options and empty maps
+        assert!(changed > keys.len() / 2, "only {} of {} keys changed", changed, keys.len());
+
+        // Nested values count too
+        let other = Config { seasonality: HashMap::from([(7, 1.5)]), ..config.clone() };
+        assert_ne!(config_sha256(&other).unwrap(), hash);
+    }
+
+    #[test]
+    fn test_config_hash_ignores_map_order() {
+        // HashMaps of the same entries inserted in opposite orders
+        let days: Vec<(String, f64)> = (1..=28).map(|day| (format!("2025-02-{:02}", day), 1.0 + day as f64 / 10.0)).collect();
+        let a = Config { special_days: days.iter().cloned().collect(), ..Config::default() };
+        let b = Config { special_days: days.iter().rev().cloned().collect(), ..Config::default() };
+        assert_eq!(config_sha256(&a).unwrap(), config_sha256(&b).unwrap());
+    }
+
+    #[test]
+    fn test_describe() {
+        let provenance = RunProvenance {
+            crate_version: "5.1.0".to_string(),
+            git_hash: Some("1f99818abcde".to_string()),
+            config_sha256: "3a7bd3e2360a3d29eea436fcfb7e44c735d117c42d1c1835420b6b9942dd4f1b".to_string(),
+            seed: 42,
+            subscriber_db: Some(PathBuf::from("subs.redb")),
+            subscriber_db_fingerprint: Some("9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08".to_string()),
+        };
+        assert_eq!(
+            provenance.describe(),
+            "v5.1.0 (git 1f99818abcde), config 3a7bd3e2360a, seed 42, subscribers subs.redb (9f86d081884c)"
+        );
+        let random = RunProvenance { git_hash: None, subscriber_db: None, subscriber_db_fingerprint: None, ..provenance };
+        assert_eq!(random.describe(), "v5.1.0, config 3a7bd3e2360a, seed 42, random subscribers");
+    }
+}
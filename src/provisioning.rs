@@ -0,0 +1,155 @@
+// Provisioning delta feed (HLR/HSS): the subscriber events of a day - activations, releases, device and
+// software changes - rebuilt from the snapshot boundaries of the subscriber database
+use crate::subscriber_db::{SubscriberEventType, SubscriberSnapshot};
+use serde::Serialize;
+
+#[cfg(feature = "redb")]
+use crate::config::Config;
+#[cfg(feature = "redb")]
+use crate::generators::placement_window;
+#[cfg(feature = "redb")]
+use crate::subscriber_db_redb::SubscriberDbRedb;
+#[cfg(feature = "redb")]
+use crate::timezone_utils::tz_from_name;
+#[cfg(feature = "redb")]
+use chrono::NaiveDate;
+
+/// File name of the provisioning delta in a day directory (`generate-cdr --with-provisioning`)
+pub const PROVISIONING_FILE: &str = "provisioning.csv";
+
+/// One provisioning row. `old_imei` is empty on activations, `new_imei` on releases
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProvisioningRecord {
+    pub event_type: &'static str,
+    pub timestamp_ms: i64,
+    pub msisdn: u64,
+    pub imsi: u64,
+    pub old_imei: Option<u64>,
+    pub new_imei: Option<u64>,
+}
+
+impl ProvisioningRecord {
+    fn new(event_type: SubscriberEventType, timestamp_ms: i64, snapshot: &SubscriberSnapshot) -> Self {
+        ProvisioningRecord {
+            event_type: event_type.to_str(),
+            timestamp_ms,
+            msisdn: snapshot.identity.msisdn.get(),
+            imsi: snapshot.identity.imsi.get(),
+            old_imei: None,
+            new_imei: None,
+        }
+    }
+}
+
+/// Events of one MSISDN whose timestamp is in `[from_ms, to_ms)`, in time order.
+/// The number's first snapshot is a NEW_SUBSCRIBER; a snapshot starting where the one before ended with the
+/// same IMSI is a CHANGE_DEVICE (new IMEI) or SOFTWARE_UPDATE (new SV); any other start is an ASSIGN_NUMBER,
+/// and an end not followed by the same IMSI is a RELEASE_NUMBER
+pub fn snapshot_events(snapshots: &[SubscriberSnapshot], from_ms: i64, to_ms: i64) -> Vec<ProvisioningRecord> {
+    let mut sorted: Vec<&SubscriberSnapshot> = snapshots.iter().collect();
+    sorted.sort_by_key(|s| s.valid_from);
+
+    let mut events = Vec::new();
+    for (i, snapshot) in sorted.iter().enumerate() {
+        let identity = &snapshot.identity;
+        let previous = i.checked_sub(1).map(|p| sorted[p]);
+        let continued = previous.filter(|p| p.valid_to == Some(snapshot.valid_from) && p.identity.imsi == identity.imsi);
+        let event = match continued {
+            Some(p) if p.identity.imei != identity.imei => Some((SubscriberEventType::ChangeDevice, Some(p.identity.imei.get()))),
+            Some(p) if p.identity.sv != identity.sv => Some((SubscriberEventType::SoftwareUpdate, Some(p.identity.imei.get()))),
+            // Nothing a provisioning feed sees changed
+            Some(_) => None,
+            None if i == 0 => Some((SubscriberEventType::NewSubscriber, None)),
+            None => Some((SubscriberEventType::AssignNumber, None)),
+        };
+        if let Some((event_type, old_imei)) = event {
+            events.push(ProvisioningRecord {
+                old_imei,
+                new_imei: Some(identity.imei.get()),
+                ..ProvisioningRecord::new(event_type, snapshot.valid_from, snapshot)
+            });
+        }
+
+        if let Some(valid_to) = snapshot.valid_to {
+            let next = sorted.get(i + 1);
+            if !next.is_some_and(|n| n.valid_from == valid_to && n.identity.imsi == identity.imsi) {
+                events.push(ProvisioningRecord {
+                    old_imei: Some(identity.imei.get()),
+                    ..ProvisioningRecord::new(SubscriberEventType::ReleaseNumber, valid_to, snapshot)
+                });
+            }
+        }
+    }
+    // A release sorts before the assignment of the number at the same instant
+    events.sort_by_key(|e| e.timestamp_ms);
+    events.retain(|e| e.timestamp_ms >= from_ms && e.timestamp_ms < to_ms);
+    events
+}
+
+/// Provisioning feed of `date` in the CDR day's timezone (and time window, if one is set): every subscriber
+/// event of the day, in MSISDN and time order. Returns the number of rows
+#[cfg(feature = "redb")]
+pub fn export_provisioning<W: std::io::Write>(db: &SubscriberDbRedb, cfg: &Config, date: NaiveDate, out: W) -> anyhow::Result<usize> {
+    let (start, end, _) = placement_window(cfg, tz_from_name(&cfg.tz_name), date)?;
+    let (start_ms, end_ms) = (start.timestamp_millis(), end.timestamp_millis());
+
+    let mut csv = csv::Writer::from_writer(out);
+    let mut rows = 0;
+    db.iter_all(|_, snapshots| {
+        for record in snapshot_events(&snapshots, start_ms, end_ms) {
+            csv.serialize(record)?;
+            rows += 1;
+        }
+        Ok(())
+    })?;
+    csv.flush()?;
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subscriber_db::SubscriberDatabase;
+    use crate::subscriber_db_generator::{generate_database, GeneratorConfig};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_events_match_the_generated_history() {
+        let config = GeneratorConfig {
+            initial_subscribers: 2_000,
+            history_days: 120,
+            device_change_rate: 0.5,
+            number_release_rate: 0.3,
+            cooldown_days: 10,
+            ..GeneratorConfig::default()
+        };
+        let history = generate_database(&config).unwrap();
+        let mut db = SubscriberDatabase::new();
+        db.events = history.clone();
+        db.build_snapshots();
+        let mut by_msisdn: HashMap<u64, Vec<SubscriberSnapshot>> = HashMap::new();
+        for snapshot in db.get_snapshots() {
+            by_msisdn.entry(snapshot.identity.msisdn.get()).or_default().push(snapshot.clone());
+        }
+
+        // Day 30 to 60 of the history
+        let day_ms = 86_400_000;
+        let (from, to) = (config.start_timestamp_ms + 30 * day_ms, config.start_timestamp_ms + 60 * day_ms);
+        let key = |event_type: &str, ts: i64, msisdn: u64, imsi: u64| (event_type.to_string(), ts, msisdn, imsi);
+        let mut expected: Vec<_> = history
+            .iter()
+            .filter(|e| e.timestamp_ms >= from && e.timestamp_ms < to)
+            .map(|e| key(e.event_type.to_str(), e.timestamp_ms, e.msisdn.unwrap().get(), e.imsi.get()))
+            .collect();
+        let mut rebuilt: Vec<_> = by_msisdn
+            .values()
+            .flat_map(|snapshots| snapshot_events(snapshots, from, to))
+            .map(|e| key(e.event_type, e.timestamp_ms, e.msisdn, e.imsi))
+            .collect();
+        expected.sort();
+        rebuilt.sort();
+        assert!(expected.iter().any(|e| e.0 == "ASSIGN_NUMBER"));
+        assert!(expected.iter().any(|e| e.0 == "SOFTWARE_UPDATE"));
+        assert_eq!(rebuilt, expected);
+    }
+}
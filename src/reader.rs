@@ -0,0 +1,345 @@
+// Reading generated CDR part files back into typed rows
+use crate::compression::CompressionType;
+use crate::writer::{EventRow, BEARER_FIELD_NONE, CORRELATION_ID_NONE, CSV_DELIMITER, IMEISV_NONE};
+use anyhow::Context;
+use serde::{Deserialize, Deserializer};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+const BZIP2_MAGIC: &[u8] = b"BZh";
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+
+/// One CDR row as read from a part file: `EventRow` with owned strings
+/// Empty numeric fields read as 0, the value the writer leaves empty.
+/// MSISDNs read back as E.164 from `e164` and `e164_plus` files only
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct ParsedEventRow {
+    pub event_type: String,
+    #[serde(deserialize_with = "zero_if_empty")]
+    pub msisdn_src: u64,
+    #[serde(deserialize_with = "zero_if_empty")]
+    pub msisdn_dst: u64,
+    pub direction: String,
+    pub start_ts_ms: i64,
+    pub end_ts_ms: i64,
+    /// Empty when the file was written without timezone columns
+    #[serde(default)]
+    pub tz_name: String,
+    #[serde(default)]
+    pub tz_offset_min: i32,
+    pub duration_sec: i64,
+    #[serde(deserialize_with = "zero_if_empty")]
+    pub mccmnc: u32,
+    #[serde(deserialize_with = "zero_if_empty")]
+    pub imsi: u64,
+    #[serde(deserialize_with = "zero_if_empty")]
+    pub imei: u64,
+    /// 0 on non-DATA rows and when the file has no `imeisv` column
+    #[serde(default, deserialize_with = "zero_if_empty")]
+    pub imeisv: u64,
+    pub cell_id: u32,
+    pub record_type: String,
+    /// Empty when the file has no `node_id` column
+    #[serde(default)]
+    pub node_id: String,
+    pub cause_for_record_closing: String,
+    #[serde(deserialize_with = "zero_if_empty")]
+    pub sms_segments: u32,
+    pub sms_status: String,
+    /// `None` for an empty column, unlike a 0-byte or 0-second data session
+    pub data_bytes_in: Option<u64>,
+    pub data_bytes_out: Option<u64>,
+    pub data_duration_sec: Option<i64>,
+    pub apn: String,
+    pub rat: String,
+    /// 0 on non-DATA rows and when the file has no `qci` / `bearer_id` columns
+    #[serde(default, deserialize_with = "zero_if_empty")]
+    pub qci: u32,
+    #[serde(default, deserialize_with = "zero_if_empty")]
+    pub bearer_id: u32,
+    /// 0 on non-SMS rows and when the file has no `correlation_id` column
+    #[serde(default, deserialize_with = "zero_if_empty")]
+    pub correlation_id: u64,
+    /// 0 for a subscriber's first event and when the file has no `prev_event_ts_ms` column
+    #[serde(default, deserialize_with = "zero_if_empty")]
+    pub prev_event_ts_ms: i64,
+    /// 0 when the file has no `record_seq` column
+    #[serde(default)]
+    pub record_seq: u64,
+}
+
+fn zero_if_empty<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    Option::<T>::deserialize(deserializer).map(Option::unwrap_or_default)
+}
+
+impl From<&EventRow> for ParsedEventRow {
+    fn from(row: &EventRow) -> Self {
+        ParsedEventRow {
+            event_type: row.event_type.to_string(),
+            msisdn_src: row.msisdn_src,
+            msisdn_dst: row.msisdn_dst,
+            direction: row.direction.to_string(),
+            start_ts_ms: row.start_ts_ms,
+            end_ts_ms: row.end_ts_ms,
+            tz_name: row.tz_name.to_string(),
+            tz_offset_min: row.tz_offset_min,
+            duration_sec: row.duration_sec,
+            mccmnc: row.mccmnc,
+            imsi: row.imsi,
+            imei: row.imei,
+            imeisv: if row.imeisv == IMEISV_NONE { 0 } else { row.imeisv },
+            cell_id: row.cell_id,
+            record_type: row.record_type.to_string(),
+            node_id: row.node_id.to_string(),
+            cause_for_record_closing: row.cause_for_record_closing.to_string(),
+            sms_segments: row.sms_segments,
+            sms_status: row.sms_status.to_string(),
+            data_bytes_in: row.data_bytes_in,
+            data_bytes_out: row.data_bytes_out,
+            data_duration_sec: row.data_duration_sec,
+            apn: row.apn.to_string(),
+            rat: row.rat.to_string(),
+            qci: if row.qci == BEARER_FIELD_NONE { 0 } else { row.qci },
+            bearer_id: if row.bearer_id == BEARER_FIELD_NONE { 0 } else { row.bearer_id },
+            correlation_id: if row.correlation_id == CORRELATION_ID_NONE { 0 } else { row.correlation_id },
+            prev_event_ts_ms: row.prev_event_ts_ms.max(0),
+            record_seq: row.record_seq,
+        }
+    }
+}
+
+/// Codec of a file from its magic bytes, falling back to the extension for short files
+/// A compressed extension on a file without the matching magic is an error
+pub fn detect_compression(path: &Path) -> anyhow::Result<CompressionType> {
+    let mut magic = Vec::with_capacity(XZ_MAGIC.len());
+    File::open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?
+        .take(XZ_MAGIC.len() as u64)
+        .read_to_end(&mut magic)?;
+    let from_extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(CompressionType::from_str)
+        .unwrap_or(CompressionType::None);
+    let from_magic = [
+        (CompressionType::Gzip, GZIP_MAGIC),
+        (CompressionType::Zstd, ZSTD_MAGIC),
+        (CompressionType::Bzip2, BZIP2_MAGIC),
+        (CompressionType::Xz, XZ_MAGIC),
+    ]
+    .into_iter()
+    .find(|(_, prefix)| magic.starts_with(prefix))
+    .map(|(codec, _)| codec);
+
+    match from_magic {
+        Some(codec) => Ok(codec),
+        None if magic.is_empty() || from_extension == CompressionType::None => Ok(from_extension),
+        None => anyhow::bail!("{} has a {:?} extension but no {:?} header", path.display(), from_extension, from_extension),
+    }
+}
+
+/// Decompressed contents of a part file; gzip files may hold several members
+pub fn open_decompressed(path: &Path) -> anyhow::Result<Box<dyn Read + Send>> {
+    decode(detect_compression(path)?, File::open(path)?)
+}
+
+/// Decompressed contents of `file`, written with `codec`
+pub fn decode(codec: CompressionType, file: File) -> anyhow::Result<Box<dyn Read + Send>> {
+    codec.ensure_available()?;
+    let file = BufReader::with_capacity(256 * 1024, file);
+    Ok(match codec {
+        CompressionType::Gzip => Box::new(flate2::read::MultiGzDecoder::new(file)),
+        #[cfg(feature = "zstd")]
+        CompressionType::Zstd => Box::new(zstd::stream::read::Decoder::with_buffer(file)?),
+        #[cfg(feature = "bzip2")]
+        CompressionType::Bzip2 => Box::new(bzip2::read::MultiBzDecoder::new(file)),
+        #[cfg(feature = "xz")]
+        CompressionType::Xz => Box::new(xz2::read::XzDecoder::new_multi_decoder(file)),
+        #[allow(unreachable_patterns)]
+        CompressionType::Zstd | CompressionType::Bzip2 | CompressionType::Xz => unreachable!("checked by ensure_available"),
+        CompressionType::None => Box::new(file),
+    })
+}
+
+/// Rows of a CDR part file (`cdr_*.csv[.gz|.zst]`) in file order
+/// Columns are matched by header name, so files with or without timezone / `imeisv` / `qci` / `bearer_id` / `prev_event_ts_ms` /
+/// `record_seq` columns all read
+pub fn open_cdr_reader(path: &Path) -> anyhow::Result<impl Iterator<Item = anyhow::Result<ParsedEventRow>>> {
+    let reader = csv::ReaderBuilder::new()
+        .delimiter(CSV_DELIMITER)
+        .has_headers(true)
+        .from_reader(open_decompressed(path)?);
+    let display = path.display().to_string();
+    Ok(reader
+        .into_deserialize::<ParsedEventRow>()
+        .map(move |row| row.with_context(|| format!("Failed to parse a row of {}", display))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::writer::{EventWriter, WriterOptions, PREV_EVENT_TS_NONE};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use tempfile::tempdir;
+
+    fn pick(rng: &mut StdRng, values: &[&'static str]) -> &'static str {
+        values[rng.gen_range(0..values.len())]
+    }
+
+    /// Random row over the full range of every field, zeros (empty columns) included
+    fn random_row(rng: &mut StdRng) -> EventRow {
+        let mut number = |max: u64| if rng.gen_bool(0.2) { 0 } else { rng.gen_range(1..=max) };
+        let (msisdn_src, msisdn_dst, imsi) = (number(u64::MAX), number(999_999_999_999_999), number(u64::MAX));
+        let (imei, mccmnc, sms_segments) = (number(999_999_999_999_999), number(999_999) as u32, number(u32::MAX as u64) as u32);
+        let mut present = |max: u64| match rng.gen_range(0..5) {
+            0 => None,
+            1 => Some(0),
+            _ => Some(rng.gen_range(1..=max)),
+        };
+        let (data_bytes_in, data_bytes_out, data_duration_sec) =
+            (present(u64::MAX), present(u64::MAX), present(i64::MAX as u64).map(|v| v as i64));
+        EventRow {
+            event_type: pick(rng, &["CALL", "SMS", "DATA"]),
+            msisdn_src,
+            msisdn_dst,
+            direction: pick(rng, &["MO", "MT", ""]),
+            start_ts_ms: rng.gen_range(i64::MIN / 2..i64::MAX / 2),
+            end_ts_ms: rng.gen(),
+            tz_name: pick(rng, &["Europe/Amsterdam", "America/St_Johns", "UTC", ""]),
+            tz_offset_min: rng.gen_range(-720..=840),
+            duration_sec: rng.gen(),
+            mccmnc,
+            imsi,
+            imei,
+            imeisv: 0,
+            cell_id: rng.gen(),
+            record_type: pick(rng, &["mocCall", "mtcCall", "sgsnPDPRecord", ""]),
+            node_id: "",
+            cause_for_record_closing: pick(rng, &["normalRelease", "partialRecord", ""]),
+            sms_segments,
+            sms_status: pick(rng, &["DELIVERED", "FAILED", ""]),
+            data_bytes_in,
+            data_bytes_out,
+            data_duration_sec,
+            apn: pick(rng, &["internet", "ims;quoted \"apn\"", ""]),
+            rat: pick(rng, &["LTE", "NR", "UMTS", ""]),
+            qci: 0,
+            bearer_id: 0,
+            correlation_id: 0,
+            prev_event_ts_ms: 0,
+            record_seq: 0,
+        }
+    }
+
+    #[test]
+    fn test_write_read_roundtrip_every_field() {
+        let compressions = [CompressionType::None, CompressionType::Gzip, CompressionType::Zstd];
+        for compression in compressions.into_iter().filter(CompressionType::is_available) {
+            for seed in 0..8u64 {
+                let mut rng = StdRng::seed_from_u64(seed);
+                let dir = tempdir().unwrap();
+                let options = WriterOptions {
+                    emit_record_seq: seed % 2 == 0,
+                    emit_prev_event_ts: seed % 3 == 0,
+                    emit_correlation_id: seed % 5 < 2,
+                    emit_imeisv: seed % 3 == 2,
+                    emit_bearer_fields: seed % 4 == 3,
+                    emit_node_id: seed % 2 == 1,
+                    omit_timezone_columns: seed % 4 == 1,
+                    // Many small members
+                    gzip_member_flush_bytes: 4 * 1024,
+                    ..WriterOptions::default()
+                };
+                let mut writer = EventWriter::new(dir.path(), "2025-01-01", u64::MAX, 0, compression, None, options).unwrap();
+                let mut rows: Vec<EventRow> = (0..500).map(|_| random_row(&mut rng)).collect();
+                if options.emit_prev_event_ts {
+                    // Workers stamp every row, the first event of a subscriber with an empty value
+                    for row in &mut rows {
+                        row.prev_event_ts_ms = if rng.gen_bool(0.2) { PREV_EVENT_TS_NONE } else { rng.gen_range(1..i64::MAX) };
+                    }
+                }
+                if options.emit_correlation_id {
+                    // Only some rows carry one; the others get an empty cell from the writer
+                    for row in &mut rows {
+                        if rng.gen_bool(0.5) {
+                            row.correlation_id = rng.gen_range(1..CORRELATION_ID_NONE);
+                        }
+                    }
+                }
+                if options.emit_imeisv {
+                    // DATA rows carry one, 16 digits with leading zeros kept; the others get an empty cell
+                    for row in &mut rows {
+                        if rng.gen_bool(0.5) {
+                            row.imeisv = rng.gen_range(1..10_000_000_000_000_000);
+                        }
+                    }
+                }
+                if options.emit_node_id {
+                    // Every row carries the element that wrote it
+                    for row in &mut rows {
+                        row.node_id = pick(&mut rng, &["msc01", "sgsn12", "pgw03"]);
+                    }
+                }
+                if options.emit_bearer_fields {
+                    // DATA rows carry both; the others get empty cells
+                    for row in &mut rows {
+                        if rng.gen_bool(0.5) {
+                            row.qci = rng.gen_range(1..=255);
+                            row.bearer_id = rng.gen_range(1..BEARER_FIELD_NONE);
+                        }
+                    }
+                }
+                for row in &rows {
+                    writer.write_row(row).unwrap();
+                }
+                writer.close().unwrap();
+                assert_eq!(writer.files_written(), 1);
+
+                let name = format!("cdr_2025-01-01_writer000_part001.csv{}", compression.extension());
+                let path = dir.path().join("2025-01-01").join(name);
+                assert_eq!(detect_compression(&path).unwrap(), compression);
+                let parsed: Vec<ParsedEventRow> = open_cdr_reader(&path).unwrap().collect::<anyhow::Result<_>>().unwrap();
+                assert_eq!(parsed.len(), rows.len(), "{:?} seed {}", compression, seed);
+                for (i, (row, parsed)) in rows.iter().zip(&parsed).enumerate() {
+                    let mut expected = ParsedEventRow::from(row);
+                    if options.emit_record_seq {
+                        expected.record_seq = i as u64 + 1;
+                    }
+                    if options.omit_timezone_columns {
+                        expected.tz_name.clear();
+                        expected.tz_offset_min = 0;
+                    }
+                    assert_eq!(parsed, &expected, "{:?} seed {} row {}", compression, seed, i);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_detects_codec_by_magic_bytes() {
+        let dir = tempdir().unwrap();
+        let plain = dir.path().join("cdr.csv");
+        std::fs::write(&plain, "event_type\n").unwrap();
+        assert_eq!(detect_compression(&plain).unwrap(), CompressionType::None);
+
+        // Renamed gzip file still reads as gzip
+        let renamed = dir.path().join("cdr.csv.zst");
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut encoder, b"event_type\n").unwrap();
+        std::fs::write(&renamed, encoder.finish().unwrap()).unwrap();
+        assert_eq!(detect_compression(&renamed).unwrap(), CompressionType::Gzip);
+
+        // A .gz file that is not gzip
+        let truncated = dir.path().join("cdr.csv.gz");
+        std::fs::write(&truncated, "event_type\n").unwrap();
+        assert!(detect_compression(&truncated).is_err());
+    }
+}
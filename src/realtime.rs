@@ -0,0 +1,207 @@
+// Live replay: hold a writer's rows for the day, then write them in event time order at wall-clock pace
+use crate::async_writer::EventBatch;
+use crate::writer::EventRow;
+use anyhow::Result;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Most rows written at once when several are already due
+pub const REPLAY_BATCH_ROWS: usize = 1024;
+
+/// Time a replay is paced against
+#[derive(Debug, Clone, Default)]
+pub enum ReplayTime {
+    /// Waits sleep the writer thread
+    #[default]
+    Wall,
+    /// Waits move a virtual offset forward at once, so a replay at any speedup finishes immediately.
+    /// Starts at zero and is shared by every replay of the run; clones read the same offset
+    Virtual(Arc<Mutex<Duration>>),
+}
+
+impl ReplayTime {
+    pub fn virtual_time() -> Self {
+        ReplayTime::Virtual(Arc::default())
+    }
+
+    /// Offset the virtual time has reached; zero on the wall clock
+    pub fn virtual_elapsed(&self) -> Duration {
+        match self {
+            ReplayTime::Wall => Duration::ZERO,
+            ReplayTime::Virtual(now) => *now.lock().unwrap(),
+        }
+    }
+
+    /// Offset of now in a replay that started at `started`
+    fn elapsed(&self, started: Instant) -> Duration {
+        match self {
+            ReplayTime::Wall => started.elapsed(),
+            ReplayTime::Virtual(_) => self.virtual_elapsed(),
+        }
+    }
+
+    /// Block until offset `due` of a replay that started at `started`
+    fn wait_until(&self, started: Instant, due: Duration) {
+        match self {
+            ReplayTime::Wall => {
+                if let Some(wait) = due.checked_sub(started.elapsed()) {
+                    std::thread::sleep(wait);
+                }
+            }
+            ReplayTime::Virtual(now) => {
+                let mut now = now.lock().unwrap();
+                *now = (*now).max(due);
+            }
+        }
+    }
+}
+
+/// Maps event times to offsets from the start of the replay
+#[derive(Debug, Clone)]
+pub struct ReplayClock {
+    /// Event time replayed at offset 0: start of the day, or of its time window (UTC ms)
+    pub origin_ms: i64,
+    /// Event time runs this many times faster than wall-clock
+    pub speedup: f64,
+    pub time: ReplayTime,
+}
+
+impl ReplayClock {
+    pub fn new(origin_ms: i64, speedup: f64) -> Self {
+        ReplayClock { origin_ms, speedup, time: ReplayTime::Wall }
+    }
+
+    /// Offset from the start of the replay at which an event starting at `start_ts_ms` is written;
+    /// events before the origin are due at once
+    pub fn due(&self, start_ts_ms: i64) -> Duration {
+        let event_ms = start_ts_ms.saturating_sub(self.origin_ms).max(0) as f64;
+        Duration::from_secs_f64(event_ms / 1000.0 / self.speedup)
+    }
+}
+
+/// Rows of one writer collected during generation and replayed once the workers are done.
+/// The whole day (or time window) is held in memory, since workers produce it subscriber by subscriber
+pub struct RealtimeReplay {
+    clock: ReplayClock,
+    rows: Vec<EventRow>,
+}
+
+impl RealtimeReplay {
+    pub fn new(clock: ReplayClock) -> Self {
+        RealtimeReplay { clock, rows: Vec::new() }
+    }
+
+    pub fn push(&mut self, batch: EventBatch) {
+        self.rows.extend(batch.events);
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Sort the rows by start time (stable, so equal times keep generation order) and hand them to `write`
+    /// in batches, each once its first row is due; rows already due by then go along in the same batch
+    pub fn replay(mut self, mut write: impl FnMut(EventBatch) -> Result<()>) -> Result<()> {
+        self.rows.sort_by_key(|row| row.start_ts_ms);
+        let started = Instant::now();
+        let mut rows = self.rows.into_iter().peekable();
+        while let Some(first) = rows.next() {
+            self.clock.time.wait_until(started, self.clock.due(first.start_ts_ms));
+            let now = self.clock.time.elapsed(started);
+            let mut events = vec![first];
+            while events.len() < REPLAY_BATCH_ROWS {
+                match rows.next_if(|row| self.clock.due(row.start_ts_ms) <= now) {
+                    Some(row) => events.push(row),
+                    None => break,
+                }
+            }
+            write(EventBatch::from_rows(events))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(start_ts_ms: i64) -> EventRow {
+        EventRow {
+            event_type: "CALL",
+            start_ts_ms,
+            end_ts_ms: start_ts_ms + 1_000,
+            ..EventRow::default()
+        }
+    }
+
+    #[test]
+    fn test_due_scales_by_speedup_from_origin() {
+        let clock = ReplayClock::new(1_000_000, 100.0);
+        assert_eq!(clock.due(1_000_000), Duration::ZERO);
+        assert_eq!(clock.due(1_100_000), Duration::from_secs(1));
+        assert_eq!(clock.due(0), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_replay_writes_in_start_order_at_pace() {
+        let mut replay = RealtimeReplay::new(ReplayClock::new(0, 1_000.0));
+        let mut batch = EventBatch::new(4);
+        for ts in [200_000, 0, 100_000, 100_000] {
+            batch.push(&row(ts));
+        }
+        replay.push(batch);
+        assert_eq!(replay.len(), 4);
+
+        let started = Instant::now();
+        let mut written = Vec::new();
+        replay
+            .replay(|batch| {
+                let at = started.elapsed();
+                written.extend(batch.events.iter().map(|row| (row.start_ts_ms, at)));
+                Ok(())
+            })
+            .unwrap();
+
+        let times: Vec<i64> = written.iter().map(|(ts, _)| *ts).collect();
+        assert_eq!(times, vec![0, 100_000, 100_000, 200_000]);
+        // 100 s of event time at 1000x is 100 ms of wall-clock
+        assert!(written[1].1 >= Duration::from_millis(100));
+        assert!(written[3].1 >= Duration::from_millis(200));
+        assert!(written[3].1 < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_virtual_time_writes_each_row_when_due() {
+        let time = ReplayTime::virtual_time();
+        let clock = ReplayClock { time: time.clone(), ..ReplayClock::new(0, 1.0) };
+        let mut replay = RealtimeReplay::new(clock.clone());
+        let mut batch = EventBatch::new(4);
+        for ts in [3_600_000, 0, 60_000, 60_000] {
+            batch.push(&row(ts));
+        }
+        replay.push(batch);
+
+        // An hour of event time at 1x, without waiting for it
+        let started = Instant::now();
+        let mut written = Vec::new();
+        replay
+            .replay(|batch| {
+                written.push((batch.events.iter().map(|row| row.start_ts_ms).collect::<Vec<_>>(), time.virtual_elapsed()));
+                Ok(())
+            })
+            .unwrap();
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(
+            written,
+            vec![
+                (vec![0], Duration::ZERO),
+                (vec![60_000, 60_000], Duration::from_secs(60)),
+                (vec![3_600_000], Duration::from_secs(3_600)),
+            ]
+        );
+    }
+}
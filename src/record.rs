@@ -0,0 +1,205 @@
+// Typed CDR records: what the generators produce, before serialization into the unified EventRow columns
+use crate::writer::EventRow;
+
+/// Fields every record carries: the parties, the times, the served subscriber's identity and the cell
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RecordHeader {
+    pub msisdn_src: u64,
+    pub msisdn_dst: u64,
+    pub direction: &'static str,
+    pub start_ts_ms: i64,
+    pub end_ts_ms: i64,
+    pub tz_name: &'static str,
+    pub tz_offset_min: i32,
+    pub duration_sec: i64,
+    pub mccmnc: u32,
+    pub imsi: u64,
+    pub imei: u64,
+    pub cell_id: u32,
+    pub record_type: &'static str,
+    pub cause_for_record_closing: &'static str,
+}
+
+/// One leg of a voice call
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CallRecord {
+    pub header: RecordHeader,
+}
+
+/// One SMS record; a powered-off handset gives a chain of them sharing `correlation_id`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SmsRecord {
+    pub header: RecordHeader,
+    pub sms_segments: u32,
+    pub sms_status: &'static str,
+    /// SMSC message reference, 0 without `sms_unreachable_rate`
+    pub correlation_id: u64,
+}
+
+/// One packet data session, always MO with no other party
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DataRecord {
+    pub header: RecordHeader,
+    /// IMEISV of the device: packet core records carry it, MSC voice records only the IMEI
+    pub imeisv: u64,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub data_duration_sec: i64,
+    pub apn: &'static str,
+    pub rat: &'static str,
+    /// QCI (5QI on NR) of the bearer, 0 without `emit_bearer_fields`
+    pub qci: u32,
+    /// The session's number among the subscriber's DATA sessions of the day, from 1
+    pub bearer_id: u32,
+}
+
+/// A generated record of any event type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CdrRecord {
+    Call(CallRecord),
+    Sms(SmsRecord),
+    Data(DataRecord),
+}
+
+impl From<CallRecord> for CdrRecord {
+    fn from(record: CallRecord) -> Self {
+        CdrRecord::Call(record)
+    }
+}
+
+impl From<SmsRecord> for CdrRecord {
+    fn from(record: SmsRecord) -> Self {
+        CdrRecord::Sms(record)
+    }
+}
+
+impl From<DataRecord> for CdrRecord {
+    fn from(record: DataRecord) -> Self {
+        CdrRecord::Data(record)
+    }
+}
+
+impl CdrRecord {
+    /// `event_type` column value
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            CdrRecord::Call(_) => "CALL",
+            CdrRecord::Sms(_) => "SMS",
+            CdrRecord::Data(_) => "DATA",
+        }
+    }
+
+    pub fn header(&self) -> &RecordHeader {
+        match self {
+            CdrRecord::Call(record) => &record.header,
+            CdrRecord::Sms(record) => &record.header,
+            CdrRecord::Data(record) => &record.header,
+        }
+    }
+
+    pub fn header_mut(&mut self) -> &mut RecordHeader {
+        match self {
+            CdrRecord::Call(record) => &mut record.header,
+            CdrRecord::Sms(record) => &mut record.header,
+            CdrRecord::Data(record) => &mut record.header,
+        }
+    }
+
+    /// Overwrite `row` with this record in the unified columns. Columns of other event types are reset,
+    /// so nothing of the row's previous use leaks in; `prev_event_ts_ms` and `record_seq` are left to the
+    /// timeline and the writer
+    pub fn write_row(&self, row: &mut EventRow) {
+        row.reset();
+        let header = self.header();
+        row.event_type = self.event_type();
+        row.msisdn_src = header.msisdn_src;
+        row.msisdn_dst = header.msisdn_dst;
+        row.direction = header.direction;
+        row.start_ts_ms = header.start_ts_ms;
+        row.end_ts_ms = header.end_ts_ms;
+        row.tz_name = header.tz_name;
+        row.tz_offset_min = header.tz_offset_min;
+        row.duration_sec = header.duration_sec;
+        row.mccmnc = header.mccmnc;
+        row.imsi = header.imsi;
+        row.imei = header.imei;
+        row.cell_id = header.cell_id;
+        row.record_type = header.record_type;
+        row.cause_for_record_closing = header.cause_for_record_closing;
+        match self {
+            CdrRecord::Call(_) => {}
+            CdrRecord::Sms(sms) => {
+                row.sms_segments = sms.sms_segments;
+                row.sms_status = sms.sms_status;
+                row.correlation_id = sms.correlation_id;
+            }
+            CdrRecord::Data(data) => {
+                row.imeisv = data.imeisv;
+                row.data_bytes_in = Some(data.bytes_in);
+                row.data_bytes_out = Some(data.bytes_out);
+                row.data_duration_sec = Some(data.data_duration_sec);
+                row.apn = data.apn;
+                row.rat = data.rat;
+                row.qci = data.qci;
+                row.bearer_id = data.bearer_id;
+            }
+        }
+    }
+
+    /// The record as a new row
+    pub fn to_row(&self) -> EventRow {
+        let mut row = EventRow::default();
+        self.write_row(&mut row);
+        row
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header() -> RecordHeader {
+        RecordHeader {
+            msisdn_src: 31_612_000_001,
+            direction: "MO",
+            start_ts_ms: 1_735_689_600_000,
+            end_ts_ms: 1_735_689_660_000,
+            duration_sec: 60,
+            cell_id: 12_345,
+            ..RecordHeader::default()
+        }
+    }
+
+    #[test]
+    fn test_rows_keep_other_types_columns_empty() {
+        // A pooled row last used for an SMS
+        let mut row = CdrRecord::from(SmsRecord {
+            header: header(),
+            sms_segments: 2,
+            sms_status: "DELIVERED",
+            correlation_id: 7,
+        })
+        .to_row();
+        assert_eq!((row.event_type, row.sms_segments, row.correlation_id), ("SMS", 2, 7));
+
+        CdrRecord::from(DataRecord {
+            header: header(),
+            imeisv: 350_000_000_000_001,
+            bytes_in: 0,
+            bytes_out: 2_000,
+            data_duration_sec: 60,
+            apn: "internet",
+            rat: "LTE",
+            qci: 9,
+            bearer_id: 1,
+        })
+        .write_row(&mut row);
+        assert_eq!(row.event_type, "DATA");
+        assert_eq!((row.sms_segments, row.sms_status, row.correlation_id), (0, "", 0));
+        assert_eq!((row.data_bytes_in, row.data_bytes_out), (Some(0), Some(2_000)));
+
+        CdrRecord::from(CallRecord { header: header() }).write_row(&mut row);
+        assert_eq!((row.event_type, row.cell_id, row.duration_sec), ("CALL", 12_345, 60));
+        assert_eq!((row.imeisv, row.data_bytes_in, row.apn, row.qci, row.bearer_id), (0, None, "", 0, 0));
+    }
+}
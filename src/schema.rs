@@ -0,0 +1,157 @@
+// Column schema of the part files: `schema.json` in the output root, the manifests and the `schema` subcommand
+use crate::writer::{emitted_columns, WriterOptions, CSV_DELIMITER};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// File name of the schema in the output root
+pub const SCHEMA_FILE: &str = "schema.json";
+
+/// One column of the part files
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnSchema {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub data_type: String,
+    pub nullable: bool,
+    pub example: String,
+    /// Event types whose rows fill the column; the others leave it empty
+    pub event_types: Vec<String>,
+    pub description: String,
+}
+
+/// Columns of the part files, in header order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutputSchema {
+    pub delimiter: String,
+    pub columns: Vec<ColumnSchema>,
+}
+
+impl OutputSchema {
+    /// Schema of the part files `options` writes: the writer's columns in header order,
+    /// with the MSISDN examples in the configured format
+    pub fn new(options: &WriterOptions) -> anyhow::Result<Self> {
+        let columns = emitted_columns(options)
+            .map(|column| {
+                let example = match column.name {
+                    "msisdn_src" | "msisdn_dst" => {
+                        let mut buf = [0u8; 24];
+                        options.msisdn.render(column.example.parse()?, &mut buf).to_string()
+                    }
+                    _ => column.example.to_string(),
+                };
+                Ok(ColumnSchema {
+                    name: column.name.to_string(),
+                    data_type: column.data_type.to_string(),
+                    nullable: column.nullable,
+                    example,
+                    event_types: column.event_types.iter().map(|t| t.to_string()).collect(),
+                    description: column.description.to_string(),
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(OutputSchema {
+            delimiter: (CSV_DELIMITER as char).to_string(),
+            columns,
+        })
+    }
+
+    /// Markdown table, one row per column
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("Delimiter: `{}`\n\n", self.delimiter);
+        out.push_str("| # | Column | Type | Nullable | Event types | Example | Description |\n");
+        out.push_str("|---|--------|------|----------|-------------|---------|-------------|\n");
+        for (i, column) in self.columns.iter().enumerate() {
+            out.push_str(&format!(
+                "| {} | `{}` | {} | {} | {} | `{}` | {} |\n",
+                i + 1,
+                column.name,
+                column.data_type,
+                if column.nullable { "yes" } else { "no" },
+                column.event_types.join(", "),
+                column.example,
+                column.description
+            ));
+        }
+        out
+    }
+}
+
+/// How `schema` prints the schema
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaFormat {
+    Json,
+    Markdown,
+}
+
+impl SchemaFormat {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "json" => Some(SchemaFormat::Json),
+            "markdown" | "md" => Some(SchemaFormat::Markdown),
+            _ => None,
+        }
+    }
+
+    pub fn render(&self, schema: &OutputSchema) -> anyhow::Result<String> {
+        match self {
+            SchemaFormat::Json => Ok(serde_json::to_string_pretty(schema)? + "\n"),
+            SchemaFormat::Markdown => Ok(schema.to_markdown()),
+        }
+    }
+}
+
+/// Write `schema.json` into the output root
+pub fn write_schema(out_dir: &Path, options: &WriterOptions) -> anyhow::Result<PathBuf> {
+    std::fs::create_dir_all(out_dir)?;
+    let path = out_dir.join(SCHEMA_FILE);
+    std::fs::write(&path, SchemaFormat::Json.render(&OutputSchema::new(options)?)?)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, MsisdnFormat};
+    use crate::writer::{header_columns, COLUMNS};
+
+    #[test]
+    fn test_every_column_can_be_emitted() {
+        let options = WriterOptions {
+            emit_record_seq: true,
+            emit_prev_event_ts: true,
+            emit_correlation_id: true,
+            emit_imeisv: true,
+            emit_bearer_fields: true,
+            emit_node_id: true,
+            ..WriterOptions::default()
+        };
+        let schema = OutputSchema::new(&options).unwrap();
+        let names: Vec<&str> = schema.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, COLUMNS.iter().map(|column| column.name).collect::<Vec<_>>());
+        // Numeric examples parse as their type
+        for column in schema.columns.iter().filter(|c| c.data_type != "string") {
+            assert!(column.example.parse::<i128>().is_ok(), "{}: {:?}", column.name, column.example);
+        }
+    }
+
+    #[test]
+    fn test_schema_follows_writer_options() {
+        let cfg = Config {
+            emit_timezone_columns: false,
+            msisdn_format: MsisdnFormat::E164Plus,
+            ..Config::default()
+        };
+        let options = WriterOptions::from_config(&cfg);
+        let schema = OutputSchema::new(&options).unwrap();
+        let names: Vec<&str> = schema.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, header_columns(&options));
+        assert!(!names.contains(&"tz_name") && !names.contains(&"record_seq"));
+        assert_eq!(schema.columns[1].example, "+31612345678");
+
+        let markdown = schema.to_markdown();
+        assert_eq!(markdown.lines().filter(|line| line.starts_with("| ")).count(), schema.columns.len() + 1);
+        let json: OutputSchema = serde_json::from_str(&SchemaFormat::Json.render(&schema).unwrap()).unwrap();
+        assert_eq!(json, schema);
+    }
+}
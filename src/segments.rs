@@ -0,0 +1,201 @@
+// Customer segments: deterministic per-subscriber segment, plan and customer id,
+// shared by CDR generation (event means) and the CRM export so both agree on every subscriber
+use crate::config::SegmentProfile;
+use std::collections::BTreeMap;
+
+#[cfg(feature = "redb")]
+use crate::config::Config;
+#[cfg(feature = "redb")]
+use crate::generators::day_tz;
+#[cfg(feature = "redb")]
+use crate::subscriber_db::SubscriberSnapshot;
+#[cfg(feature = "redb")]
+use crate::subscriber_db_redb::SubscriberDbRedb;
+#[cfg(feature = "redb")]
+use crate::timezone_utils::{local_day_bounds, tz_from_name};
+#[cfg(feature = "redb")]
+use chrono::{DateTime, NaiveDate};
+#[cfg(feature = "redb")]
+use serde::Serialize;
+
+// Salts keeping the segment, plan and customer id hashes of one IMSI independent
+const SEGMENT_SALT: u64 = 0x5E6D_E417_0000_0001;
+const PLAN_SALT: u64 = 0x5E6D_E417_0000_0002;
+const CUSTOMER_SALT: u64 = 0x5E6D_E417_0000_0003;
+
+/// SplitMix64 finalizer over the seed, the IMSI and a salt
+fn mix(seed: u64, imsi: u64, salt: u64) -> u64 {
+    let mut z = seed ^ imsi.wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ salt;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Top 53 bits of a hash -> uniform [0, 1)
+fn unit(z: u64) -> f64 {
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Customer id of the subscriber behind `imsi`: a new IMSI on a reassigned number is a new customer
+pub fn customer_id(seed: u64, imsi: u64) -> String {
+    format!("C{:016X}", mix(seed, imsi, CUSTOMER_SALT))
+}
+
+/// One configured segment
+#[derive(Debug, Clone)]
+pub struct Segment {
+    pub name: String,
+    pub activity: f64,
+    pub plans: Vec<String>,
+}
+
+impl Segment {
+    /// Tariff plan of `imsi` within this segment, uniform over its plans
+    pub fn plan(&self, seed: u64, imsi: u64) -> &str {
+        let idx = (unit(mix(seed, imsi, PLAN_SALT)) * self.plans.len() as f64) as usize;
+        &self.plans[idx.min(self.plans.len() - 1)]
+    }
+}
+
+/// Segments of `user_segments` in name order, with their cumulative shares
+#[derive(Debug, Clone)]
+pub struct Segments {
+    segments: Vec<Segment>,
+    cumulative: Vec<f64>,
+}
+
+impl Segments {
+    pub fn new(profiles: &BTreeMap<String, SegmentProfile>) -> anyhow::Result<Self> {
+        let mut segments = Vec::with_capacity(profiles.len());
+        let mut cumulative = Vec::with_capacity(profiles.len());
+        let mut total = 0.0;
+        for (name, profile) in profiles {
+            if profile.plans.is_empty() {
+                anyhow::bail!("Config key user_segments: segment {:?} has no plans", name);
+            }
+            total += profile.share;
+            cumulative.push(total);
+            segments.push(Segment {
+                name: name.clone(),
+                activity: profile.activity,
+                plans: profile.plans.clone(),
+            });
+        }
+        if total <= 0.0 {
+            anyhow::bail!("Config key user_segments: needs at least one segment with a positive share");
+        }
+        cumulative.iter_mut().for_each(|c| *c /= total);
+        Ok(Self { segments, cumulative })
+    }
+
+    /// Segments in the order `assign` indexes them
+    pub fn iter(&self) -> impl Iterator<Item = &Segment> {
+        self.segments.iter()
+    }
+
+    /// Index and segment of the subscriber behind `imsi`. A pure hash of seed and IMSI,
+    /// so it draws nothing from the shard RNGs and any tool can recompute it
+    pub fn assign(&self, seed: u64, imsi: u64) -> (usize, &Segment) {
+        let u = unit(mix(seed, imsi, SEGMENT_SALT));
+        let idx = self.cumulative.iter().position(|&c| u < c).unwrap_or(self.segments.len() - 1);
+        (idx, &self.segments[idx])
+    }
+}
+
+/// One CRM extract row
+#[cfg(feature = "redb")]
+#[derive(Debug, Clone, Serialize)]
+pub struct CrmRecord {
+    pub msisdn: u64,
+    pub customer_id: String,
+    pub activation_date: String,
+    pub plan: String,
+    pub segment: String,
+}
+
+/// CRM extract of every MSISDN with a subscriber on `date`: the same snapshot CDR generation picks for the day
+/// (valid at its first instant, or activated during it), in MSISDN order.
+/// `activation_date` is the UTC day the number's current IMSI took it over. Returns the number of rows
+#[cfg(feature = "redb")]
+pub fn export_crm<W: std::io::Write>(db: &SubscriberDbRedb, cfg: &Config, date: NaiveDate, out: W) -> anyhow::Result<usize> {
+    let segments = Segments::new(&cfg.user_segments)?;
+    let (day_start, day_end) = local_day_bounds(day_tz(cfg, tz_from_name(&cfg.tz_name)), date)?;
+    let (day_start_ts, day_end_ts) = (day_start.timestamp_millis(), day_end.timestamp_millis());
+
+    let mut csv = csv::Writer::from_writer(out);
+    let mut rows = 0;
+    db.iter_all(|msisdn, snapshots| {
+        let Some(snapshot) = SubscriberDbRedb::find_snapshot_in_day(&snapshots, day_start_ts, day_end_ts) else {
+            return Ok(());
+        };
+        let imsi = snapshot.identity.imsi.get();
+        let (_, segment) = segments.assign(cfg.seed, imsi);
+        csv.serialize(CrmRecord {
+            msisdn,
+            customer_id: customer_id(cfg.seed, imsi),
+            activation_date: activation_date(&snapshots, snapshot),
+            plan: segment.plan(cfg.seed, imsi).to_string(),
+            segment: segment.name.clone(),
+        })?;
+        rows += 1;
+        Ok(())
+    })?;
+    csv.flush()?;
+    Ok(rows)
+}
+
+/// Start of the run of snapshots with `current`'s IMSI that ends at `current` (device changes keep the customer)
+#[cfg(feature = "redb")]
+fn activation_date(snapshots: &[SubscriberSnapshot], current: &SubscriberSnapshot) -> String {
+    let imsi = current.identity.imsi;
+    let end = snapshots.iter().position(|s| s.valid_from == current.valid_from).unwrap_or(0);
+    let start = snapshots[..=end]
+        .iter()
+        .rposition(|s| s.identity.imsi != imsi)
+        .map_or(0, |idx| idx + 1);
+    DateTime::from_timestamp_millis(snapshots[start].valid_from)
+        .map(|t| t.format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::default_user_segments;
+
+    #[test]
+    fn test_assignment_follows_shares() {
+        let segments = Segments::new(&default_user_segments()).unwrap();
+        let n = 20_000u64;
+        let mut counts = BTreeMap::new();
+        for imsi in 204_080_000_000_000..204_080_000_000_000 + n {
+            let (idx, segment) = segments.assign(42, imsi);
+            // Stable for the same seed and IMSI
+            assert_eq!(segments.assign(42, imsi).0, idx);
+            assert!(segment.plans.iter().any(|plan| plan == segment.plan(42, imsi)));
+            *counts.entry(segment.name.clone()).or_insert(0u64) += 1;
+        }
+        let share = |name: &str| counts[name] as f64 / n as f64;
+        assert!((share("consumer") - 0.7).abs() < 0.02, "{:?}", counts);
+        assert!((share("business") - 0.15).abs() < 0.02, "{:?}", counts);
+        assert!((share("youth") - 0.15).abs() < 0.02, "{:?}", counts);
+    }
+
+    #[test]
+    fn test_customer_id_is_stable_per_imsi() {
+        assert_eq!(customer_id(42, 204_080_000_000_001), customer_id(42, 204_080_000_000_001));
+        assert_ne!(customer_id(42, 204_080_000_000_001), customer_id(42, 204_080_000_000_002));
+        assert_ne!(customer_id(42, 204_080_000_000_001), customer_id(7, 204_080_000_000_001));
+        assert_eq!(customer_id(42, 1).len(), 17);
+    }
+
+    #[test]
+    fn test_rejects_unusable_segments() {
+        let profile = |share: f64, plans: Vec<String>| SegmentProfile { share, activity: 1.0, plans };
+        let no_plans = BTreeMap::from([("a".to_string(), profile(1.0, vec![]))]);
+        assert!(Segments::new(&no_plans).is_err());
+        let no_share = BTreeMap::from([("a".to_string(), profile(0.0, vec!["p".to_string()]))]);
+        assert!(Segments::new(&no_share).is_err());
+        assert!(Segments::new(&BTreeMap::new()).is_err());
+    }
+}
@@ -0,0 +1,288 @@
+// Inline sanity validator (`self_check`): workers check a sample of their own rows against the invariants
+// every generated row holds, before dirty_data damages any and before they reach a writer
+use crate::cells::CellsCatalog;
+use crate::config::{Config, SelfCheckMode};
+use crate::generators::RANDOM_CELL_IDS;
+use crate::identity::{parse_numeric, parse_prefix_operator_map};
+use crate::writer::EventRow;
+use std::collections::HashSet;
+use std::fmt;
+use tracing::warn;
+
+/// Invariant a row broke
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Violation {
+    /// `end_ts_ms` before `start_ts_ms`
+    EndBeforeStart,
+    /// `duration_sec` is not the whole seconds between start and end
+    DurationMismatch,
+    /// MO row whose `msisdn_src` is not a subscriber of the shard
+    SourceOutsideShard,
+    /// `sms_segments` / `sms_status` set on a CALL or DATA row
+    SmsFieldsOnNonSms,
+    /// `cell_id` neither in the catalog (DATA) nor in `RANDOM_CELL_IDS`
+    UnknownCell,
+    /// `mccmnc` not in `mccmnc_pool` / `prefix_operator_map`
+    UnknownMccmnc,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Violation::EndBeforeStart => "end before start",
+            Violation::DurationMismatch => "duration_sec does not match the timestamps",
+            Violation::SourceOutsideShard => "MO msisdn_src outside the shard",
+            Violation::SmsFieldsOnNonSms => "SMS fields on a non-SMS row",
+            Violation::UnknownCell => "cell_id not in the catalog",
+            Violation::UnknownMccmnc => "mccmnc not in the pool",
+        })
+    }
+}
+
+/// MSISDNs of the subscribers a shard serves
+#[derive(Debug, Clone)]
+pub enum ShardMsisdns {
+    /// Identities the worker holds in memory
+    Set(HashSet<u64>),
+    /// Index-derived numbers `prefix * 10^7 + idx % 10^7` of subscribers `start..end`, as the redb path builds them
+    Indexed { prefixes: Vec<u64>, range: (usize, usize) },
+}
+
+impl ShardMsisdns {
+    pub fn contains(&self, msisdn: u64) -> bool {
+        match self {
+            ShardMsisdns::Set(msisdns) => msisdns.contains(&msisdn),
+            ShardMsisdns::Indexed { prefixes, range: (start, end) } => {
+                if prefixes.is_empty() {
+                    return false;
+                }
+                let (prefix, number) = (msisdn / 10_000_000, (msisdn % 10_000_000) as usize);
+                // Subscriber indexes with this number: number, number + 10^7, ...
+                (start - start % 10_000_000 + number..*end)
+                    .step_by(10_000_000)
+                    .any(|idx| idx >= *start && prefixes[idx % prefixes.len()] == prefix)
+            }
+        }
+    }
+}
+
+/// Per-worker validator; sees every row on its way into the batch and checks 1 in `self_check_sample`
+#[derive(Debug, Clone)]
+pub struct SelfCheck {
+    mode: SelfCheckMode,
+    sample: u64,
+    shard: usize,
+    served: ShardMsisdns,
+    // Catalog cells DATA rows use; empty = no catalog, DATA cells are drawn from RANDOM_CELL_IDS too
+    data_cells: HashSet<u32>,
+    // None when identities come from a subscriber database, which carries its own MCCMNCs
+    mccmnc_pool: Option<HashSet<u32>>,
+    seen: u64,
+    checked: usize,
+    violations: usize,
+}
+
+impl SelfCheck {
+    /// `from_db`: identities come from a subscriber database rather than `mccmnc_pool`
+    pub fn new(cfg: &Config, shard: usize, cells: &CellsCatalog, served: ShardMsisdns, from_db: bool) -> anyhow::Result<Self> {
+        let mccmnc_pool = if from_db || cfg.self_check == SelfCheckMode::Off {
+            None
+        } else {
+            let mut pool: HashSet<u32> = parse_numeric::<u32>(&cfg.mccmnc_pool, "mccmnc_pool")?.into_iter().collect();
+            pool.extend(parse_prefix_operator_map(&cfg.prefix_operator_map)?.values().map(|mccmnc| mccmnc.get()));
+            Some(pool)
+        };
+        Ok(SelfCheck {
+            mode: cfg.self_check,
+            sample: cfg.self_check_sample.max(1),
+            shard,
+            served,
+            data_cells: cells.0.iter().copied().collect(),
+            mccmnc_pool,
+            seen: 0,
+            checked: 0,
+            violations: 0,
+        })
+    }
+
+    /// Validator that checks nothing
+    pub fn off() -> Self {
+        SelfCheck {
+            mode: SelfCheckMode::Off,
+            sample: 1,
+            shard: 0,
+            served: ShardMsisdns::Set(HashSet::new()),
+            data_cells: HashSet::new(),
+            mccmnc_pool: None,
+            seen: 0,
+            checked: 0,
+            violations: 0,
+        }
+    }
+
+    /// Rows checked so far
+    pub fn checked_rows(&self) -> usize {
+        self.checked
+    }
+
+    /// Rows that broke an invariant so far
+    pub fn violations(&self) -> usize {
+        self.violations
+    }
+
+    /// Check `row` if it falls in the sample. A violation is logged with the row and counted;
+    /// in strict mode it is returned as an error, which stops the worker and the run
+    pub fn check(&mut self, row: &EventRow) -> anyhow::Result<()> {
+        if self.mode == SelfCheckMode::Off {
+            return Ok(());
+        }
+        let sampled = self.seen.is_multiple_of(self.sample);
+        self.seen += 1;
+        if !sampled {
+            return Ok(());
+        }
+        self.checked += 1;
+        let Some(violation) = self.violation(row) else {
+            return Ok(());
+        };
+        self.violations += 1;
+        if self.mode == SelfCheckMode::Strict {
+            anyhow::bail!("Self-check failed on shard {}: {}: {:?}", self.shard, violation, row);
+        }
+        warn!(shard = self.shard, "Self-check: {}: {:?}", violation, row);
+        Ok(())
+    }
+
+    /// First invariant `row` breaks, if any
+    pub fn violation(&self, row: &EventRow) -> Option<Violation> {
+        if row.end_ts_ms < row.start_ts_ms {
+            return Some(Violation::EndBeforeStart);
+        }
+        if row.duration_sec != (row.end_ts_ms - row.start_ts_ms) / 1000 {
+            return Some(Violation::DurationMismatch);
+        }
+        if row.direction == "MO" && !self.served.contains(row.msisdn_src) {
+            return Some(Violation::SourceOutsideShard);
+        }
+        if row.event_type != "SMS" && (row.sms_segments != 0 || !row.sms_status.is_empty()) {
+            return Some(Violation::SmsFieldsOnNonSms);
+        }
+        let known_cell = if row.event_type == "DATA" && !self.data_cells.is_empty() {
+            self.data_cells.contains(&row.cell_id)
+        } else {
+            RANDOM_CELL_IDS.contains(&row.cell_id)
+        };
+        if !known_cell {
+            return Some(Violation::UnknownCell);
+        }
+        if self.mccmnc_pool.as_ref().is_some_and(|pool| !pool.contains(&row.mccmnc)) {
+            return Some(Violation::UnknownMccmnc);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cells::cells_catalog;
+    use crate::cells::Cell;
+
+    fn self_check(mode: SelfCheckMode, sample: u64) -> SelfCheck {
+        let cfg = Config {
+            self_check: mode,
+            self_check_sample: sample,
+            mccmnc_pool: vec!["20408".to_string()],
+            ..Config::default()
+        };
+        let cells: Vec<Cell> = (1..=3)
+            .map(|cell_id| Cell { cell_id, lat: 0.0, lon: 0.0, rat: "LTE".to_string() })
+            .collect();
+        let served = ShardMsisdns::Set(HashSet::from([31612000001]));
+        SelfCheck::new(&cfg, 0, &cells_catalog(&cells), served, false).unwrap()
+    }
+
+    fn call() -> EventRow {
+        EventRow {
+            event_type: "CALL",
+            msisdn_src: 31612000001,
+            msisdn_dst: 31687654321,
+            direction: "MO",
+            start_ts_ms: 1_735_710_480_000,
+            end_ts_ms: 1_735_710_561_500,
+            duration_sec: 81,
+            mccmnc: 20408,
+            cell_id: 66009,
+            ..EventRow::default()
+        }
+    }
+
+    #[test]
+    fn test_detects_corrupted_rows() {
+        let check = self_check(SelfCheckMode::Warn, 1);
+        assert_eq!(check.violation(&call()), None);
+        let data = EventRow { event_type: "DATA", msisdn_dst: 0, cell_id: 2, ..call() };
+        assert_eq!(check.violation(&data), None);
+        // The counterpart of an MT row is anyone
+        assert_eq!(check.violation(&EventRow { direction: "MT", msisdn_src: 1, ..call() }), None);
+
+        let corrupted = [
+            (EventRow { end_ts_ms: 1_735_710_000_000, ..call() }, Violation::EndBeforeStart),
+            (EventRow { duration_sec: 80, ..call() }, Violation::DurationMismatch),
+            (EventRow { msisdn_src: 31612000002, ..call() }, Violation::SourceOutsideShard),
+            (EventRow { sms_status: "DELIVERED", ..call() }, Violation::SmsFieldsOnNonSms),
+            (EventRow { sms_segments: 1, ..data.clone() }, Violation::SmsFieldsOnNonSms),
+            (EventRow { cell_id: 0, ..call() }, Violation::UnknownCell),
+            (EventRow { cell_id: 66009, ..data }, Violation::UnknownCell),
+            (EventRow { mccmnc: 20416, ..call() }, Violation::UnknownMccmnc),
+        ];
+        for (row, expected) in corrupted {
+            assert_eq!(check.violation(&row), Some(expected), "{:?}", row);
+        }
+    }
+
+    #[test]
+    fn test_warn_counts_and_strict_aborts() {
+        let bad = EventRow { duration_sec: 1, ..call() };
+
+        let mut warn = self_check(SelfCheckMode::Warn, 1);
+        warn.check(&call()).unwrap();
+        warn.check(&bad).unwrap();
+        warn.check(&bad).unwrap();
+        assert_eq!((warn.checked_rows(), warn.violations()), (3, 2));
+
+        let mut strict = self_check(SelfCheckMode::Strict, 1);
+        strict.check(&call()).unwrap();
+        let err = strict.check(&bad).unwrap_err().to_string();
+        assert!(err.contains("duration_sec"), "{}", err);
+
+        let mut off = self_check(SelfCheckMode::Off, 1);
+        off.check(&bad).unwrap();
+        assert_eq!(off.checked_rows(), 0);
+    }
+
+    #[test]
+    fn test_samples_one_in_n() {
+        let mut check = self_check(SelfCheckMode::Strict, 3);
+        // Rows 1, 4 and 7 are checked; the bad ones in between pass unseen
+        for i in 0..7 {
+            let row = if i % 3 == 0 { call() } else { EventRow { duration_sec: 1, ..call() } };
+            check.check(&row).unwrap();
+        }
+        assert_eq!(check.checked_rows(), 3);
+        assert!(check.check(&EventRow { duration_sec: 1, ..call() }).is_ok());
+        assert!(check.check(&EventRow { duration_sec: 1, ..call() }).is_ok());
+        assert!(check.check(&EventRow { duration_sec: 1, ..call() }).is_err());
+    }
+
+    #[test]
+    fn test_indexed_shard_msisdns() {
+        let served = ShardMsisdns::Indexed { prefixes: vec![31612, 31613], range: (100, 200) };
+        // Index 150 uses prefix 31612 (150 % 2 == 0), index 151 prefix 31613
+        assert!(served.contains(316120000150));
+        assert!(served.contains(316130000151));
+        assert!(!served.contains(316130000150));
+        assert!(!served.contains(316120000200));
+        assert!(!served.contains(316120000098));
+    }
+}
@@ -1,10 +1,11 @@
 // Subscriber database management with historical changes
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::path::Path;
 use anyhow::{anyhow, Context, Result};
 use serde::{Deserialize, Serialize};
+use crate::identity::{parse_numeric, parse_sv, Imei, Imsi, Mccmnc, Msisdn, SubscriberIdentity};
 
 /// Types of subscriber events
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -19,9 +20,12 @@ pub enum SubscriberEventType {
     ReleaseNumber,
     /// Phone number assigned to different subscriber (MSISDN assigned to new IMSI)
     AssignNumber,
+    /// Device software update (SV changes, IMSI, MSISDN and IMEI stay)
+    SoftwareUpdate,
 }
 
 impl SubscriberEventType {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Result<Self> {
         match s {
             "NEW_SUBSCRIBER" => Ok(SubscriberEventType::NewSubscriber),
@@ -29,6 +33,7 @@ impl SubscriberEventType {
             "CHANGE_SIM" => Ok(SubscriberEventType::ChangeSim),
             "RELEASE_NUMBER" => Ok(SubscriberEventType::ReleaseNumber),
             "ASSIGN_NUMBER" => Ok(SubscriberEventType::AssignNumber),
+            "SOFTWARE_UPDATE" => Ok(SubscriberEventType::SoftwareUpdate),
             _ => Err(anyhow!("Unknown event type: {}", s)),
         }
     }
@@ -40,55 +45,189 @@ impl SubscriberEventType {
             SubscriberEventType::ChangeSim => "CHANGE_SIM",
             SubscriberEventType::ReleaseNumber => "RELEASE_NUMBER",
             SubscriberEventType::AssignNumber => "ASSIGN_NUMBER",
+            SubscriberEventType::SoftwareUpdate => "SOFTWARE_UPDATE",
         }
     }
 }
 
 /// A single event in subscriber history
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SubscriberEvent {
     pub timestamp_ms: i64,
     pub event_type: SubscriberEventType,
-    pub imsi: String,
-    pub msisdn: Option<String>,
-    pub imei: Option<String>,
-    pub mccmnc: String,
+    pub imsi: Imsi,
+    pub msisdn: Option<Msisdn>,
+    pub imei: Option<Imei>,
+    pub mccmnc: Mccmnc,
+    /// Software version of `imei`, 00-98; 0 on events without a device
+    pub sv: u8,
 }
 
-/// Snapshot of subscriber state at a point in time
+/// Default limit of IMSIs using one IMEI at the same time (dual-SIM phones)
+pub const DEFAULT_MAX_IMSIS_PER_IMEI: usize = 2;
+
+/// One IMSI using one IMEI over [from, to)
 #[derive(Debug, Clone)]
+pub struct ImeiUsage {
+    pub imei: Imei,
+    pub imsi: Imsi,
+    pub from: i64,
+    pub to: Option<i64>,
+}
+
+/// Fail when an IMEI is used by more than `max_imsis` IMSIs at the same time.
+/// Sequential reuse (one user stops before the next starts) is allowed
+pub fn check_imei_sharing(mut usages: Vec<ImeiUsage>, max_imsis: usize) -> Result<()> {
+    usages.sort_by(|a, b| a.imei.cmp(&b.imei).then(a.from.cmp(&b.from)));
+
+    let mut active: Vec<&ImeiUsage> = Vec::new();
+    for (i, usage) in usages.iter().enumerate() {
+        if i == 0 || usages[i - 1].imei != usage.imei {
+            active.clear();
+        }
+        active.retain(|u| u.to.is_none_or(|to| to > usage.from));
+        active.push(usage);
+
+        let imsis: HashSet<Imsi> = active.iter().map(|u| u.imsi).collect();
+        if imsis.len() > max_imsis {
+            let mut imsis: Vec<_> = imsis.into_iter().collect();
+            imsis.sort_unstable();
+            let imsis: Vec<String> = imsis.iter().map(Imsi::to_string).collect();
+            return Err(anyhow!(
+                "IMEI {} used by {} IMSIs at the same time at {} (max {}): {}",
+                usage.imei,
+                imsis.len(),
+                usage.from,
+                max_imsis,
+                imsis.join(", ")
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// MSISDNs of a targeted run, one per line; blank lines and `#` comments are skipped.
+/// Returned sorted without duplicates, so the same file always splits the same way over the workers
+pub fn load_msisdn_file(path: &Path) -> Result<Vec<u64>> {
+    let file = File::open(path).with_context(|| format!("Failed to open MSISDN list {:?}", path))?;
+    let mut msisdns = Vec::new();
+    for (line_no, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let msisdn: Msisdn = line.parse().with_context(|| format!("{:?} line {}", path, line_no + 1))?;
+        msisdns.push(msisdn.get());
+    }
+    msisdns.sort_unstable();
+    msisdns.dedup();
+    if msisdns.is_empty() {
+        return Err(anyhow!("MSISDN list {:?} is empty", path));
+    }
+    Ok(msisdns)
+}
+
+/// Subscriber numbers per prefix: index-derived MSISDNs are `prefix * 10^7 + idx % 10^7`
+const SUBSCRIBER_NUMBERS: u64 = 10_000_000;
+
+/// MSISDNs of a worker's subscriber indices [start_u, end_u): index `idx` has prefix `idx % prefixes.len()`
+/// and subscriber number `idx % 10^7`. Membership is computed from the number, without listing the range's MSISDNs
+#[derive(Debug, Clone)]
+pub struct MsisdnIndexRange {
+    start_u: u64,
+    end_u: u64,
+    prefixes: Vec<u64>,
+}
+
+impl MsisdnIndexRange {
+    pub fn new(start_u: usize, end_u: usize, prefixes: &[String]) -> Result<Self> {
+        let prefixes: Vec<u64> = parse_numeric(prefixes, "prefixes")?;
+        if prefixes.is_empty() {
+            return Err(anyhow!("Config key prefixes: at least one prefix is needed to derive MSISDNs"));
+        }
+        Ok(MsisdnIndexRange { start_u: start_u as u64, end_u: end_u as u64, prefixes })
+    }
+
+    /// Whether some index of the range derives `msisdn`
+    pub fn contains(&self, msisdn: u64) -> bool {
+        let (prefix, number) = (msisdn / SUBSCRIBER_NUMBERS, msisdn % SUBSCRIBER_NUMBERS);
+        // Indices with this subscriber number are number + k * 10^7; below 10^7 subscribers there is one at most
+        let mut idx = match self.start_u.checked_sub(number) {
+            Some(gap) if gap > 0 => number + gap.div_ceil(SUBSCRIBER_NUMBERS) * SUBSCRIBER_NUMBERS,
+            _ => number,
+        };
+        while idx < self.end_u {
+            if self.prefixes[(idx % self.prefixes.len() as u64) as usize] == prefix {
+                return true;
+            }
+            idx += SUBSCRIBER_NUMBERS;
+        }
+        false
+    }
+}
+
+/// Snapshot of subscriber state over [valid_from, valid_to); also the redb value type
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SubscriberSnapshot {
-    pub imsi: String,
-    pub msisdn: String,
-    pub imei: String,
-    pub mccmnc: String,
+    pub identity: SubscriberIdentity,
     pub valid_from: i64,
     pub valid_to: Option<i64>,
 }
 
+/// What `SubscriberDatabase::repair` changed in a hand-crafted history, one line per adjustment
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepairReport {
+    /// Events that were out of chronological order
+    pub reordered_events: usize,
+    /// ReleaseNumber events added before a number was assigned to another IMSI
+    pub synthesized_releases: usize,
+    /// ReleaseNumber events of a number the IMSI no longer held
+    pub dropped_releases: usize,
+    /// Windows cut short at the start of the next window of the same MSISDN
+    pub clamped_windows: usize,
+    /// Windows without a positive length
+    pub dropped_windows: usize,
+    pub adjustments: Vec<String>,
+}
+
+impl RepairReport {
+    pub fn is_clean(&self) -> bool {
+        self.adjustments.is_empty()
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "{} events reordered, {} releases synthesized, {} stale releases dropped, {} windows clamped, {} zero-length windows dropped",
+            self.reordered_events,
+            self.synthesized_releases,
+            self.dropped_releases,
+            self.clamped_windows,
+            self.dropped_windows
+        )
+    }
+}
+
 /// Main subscriber database with history
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct SubscriberDatabase {
     pub events: Vec<SubscriberEvent>,
     // Indices for fast lookup
-    by_imsi: HashMap<String, Vec<usize>>,      // IMSI -> event indices
-    by_msisdn: HashMap<String, Vec<usize>>,    // MSISDN -> event indices
+    by_imsi: HashMap<Imsi, Vec<usize>>,        // IMSI -> event indices
+    by_msisdn: HashMap<Msisdn, Vec<usize>>,    // MSISDN -> event indices
     snapshots: Vec<SubscriberSnapshot>,        // Pre-computed snapshots
 }
 
 impl SubscriberDatabase {
     /// Create empty database
     pub fn new() -> Self {
-        SubscriberDatabase {
-            events: Vec::new(),
-            by_imsi: HashMap::new(),
-            by_msisdn: HashMap::new(),
-            snapshots: Vec::new(),
-        }
+        Self::default()
     }
 
     /// Load subscriber database from CSV file
-    /// CSV format: timestamp_ms,event_type,imsi,msisdn,imei,mccmnc
+    /// CSV format: timestamp_ms,event_type,imsi,msisdn,imei,mccmnc,sv
+    /// Files without the `sv` column (older exports) read with software version 00
     pub fn load_from_csv<P: AsRef<Path>>(path: P) -> Result<Self> {
         let file = File::open(&path)
             .with_context(|| format!("Failed to open subscriber DB: {:?}", path.as_ref()))?;
@@ -109,9 +248,9 @@ impl SubscriberDatabase {
             }
 
             let parts: Vec<&str> = line.split(',').collect();
-            if parts.len() != 6 {
+            if parts.len() != 6 && parts.len() != 7 {
                 return Err(anyhow!(
-                    "Line {}: Expected 6 fields, got {}",
+                    "Line {}: Expected 6 or 7 fields, got {}",
                     line_num + 1,
                     parts.len()
                 ));
@@ -124,18 +263,23 @@ impl SubscriberDatabase {
             let event_type = SubscriberEventType::from_str(parts[1])
                 .with_context(|| format!("Line {}: Invalid event type", line_num + 1))?;
 
-            let imsi = parts[2].to_string();
+            let line_context = || format!("Line {}", line_num + 1);
+            let imsi = parts[2].parse().with_context(line_context)?;
             let msisdn = if parts[3].is_empty() {
                 None
             } else {
-                Some(parts[3].to_string())
+                Some(parts[3].parse().with_context(line_context)?)
             };
             let imei = if parts[4].is_empty() {
                 None
             } else {
-                Some(parts[4].to_string())
+                Some(parts[4].parse().with_context(line_context)?)
+            };
+            let mccmnc = parts[5].parse().with_context(line_context)?;
+            let sv = match parts.get(6) {
+                Some(sv) if !sv.is_empty() => parse_sv(sv).with_context(line_context)?,
+                _ => 0,
             };
-            let mccmnc = parts[5].to_string();
 
             events.push(SubscriberEvent {
                 timestamp_ms,
@@ -144,6 +288,7 @@ impl SubscriberDatabase {
                 msisdn,
                 imei,
                 mccmnc,
+                sv,
             });
         }
 
@@ -161,21 +306,26 @@ impl SubscriberDatabase {
 
         for (idx, event) in self.events.iter().enumerate() {
             self.by_imsi
-                .entry(event.imsi.clone())
-                .or_insert_with(Vec::new)
+                .entry(event.imsi)
+                .or_default()
                 .push(idx);
 
-            if let Some(ref msisdn) = event.msisdn {
+            if let Some(msisdn) = event.msisdn {
                 self.by_msisdn
-                    .entry(msisdn.clone())
-                    .or_insert_with(Vec::new)
+                    .entry(msisdn)
+                    .or_default()
                     .push(idx);
             }
         }
     }
 
-    /// Validate database integrity
+    /// Validate database integrity with the default IMEI sharing limit
     pub fn validate(&self) -> Result<()> {
+        self.validate_with(DEFAULT_MAX_IMSIS_PER_IMEI)
+    }
+
+    /// Validate database integrity, allowing up to `max_imsis_per_imei` simultaneous users of one IMEI
+    pub fn validate_with(&self, max_imsis_per_imei: usize) -> Result<()> {
         // 1. Check chronological order
         for i in 1..self.events.len() {
             if self.events[i].timestamp_ms < self.events[i - 1].timestamp_ms {
@@ -187,36 +337,33 @@ impl SubscriberDatabase {
         }
 
         // 2. Check that MSISDN is not used by multiple IMSI at the same time
-        let mut msisdn_ownership: HashMap<String, (String, i64, Option<i64>)> = HashMap::new(); // msisdn -> (imsi, from, to)
+        let mut msisdn_ownership: HashMap<Msisdn, (Imsi, i64, Option<i64>)> = HashMap::new(); // msisdn -> (imsi, from, to)
 
         for event in &self.events {
-            if let Some(ref msisdn) = event.msisdn {
+            if let Some(msisdn) = event.msisdn {
                 match event.event_type {
                     SubscriberEventType::NewSubscriber | SubscriberEventType::AssignNumber => {
                         // Check if this MSISDN is already owned by someone else
-                        if let Some((owner_imsi, from, to)) = msisdn_ownership.get(msisdn) {
-                            if to.is_none() || to.unwrap() > event.timestamp_ms {
-                                if owner_imsi != &event.imsi {
-                                    return Err(anyhow!(
-                                        "MSISDN {} conflict: owned by {} from {} to {:?}, but assigned to {} at {}",
-                                        msisdn,
-                                        owner_imsi,
-                                        from,
-                                        to,
-                                        event.imsi,
-                                        event.timestamp_ms
-                                    ));
-                                }
+                        if let Some((owner_imsi, from, to)) = msisdn_ownership.get(&msisdn) {
+                            if (to.is_none() || to.unwrap() > event.timestamp_ms)
+                                && owner_imsi != &event.imsi
+                            {
+                                return Err(anyhow!(
+                                    "MSISDN {} conflict: owned by {} from {} to {:?}, but assigned to {} at {}",
+                                    msisdn,
+                                    owner_imsi,
+                                    from,
+                                    to,
+                                    event.imsi,
+                                    event.timestamp_ms
+                                ));
                             }
                         }
-                        msisdn_ownership.insert(
-                            msisdn.clone(),
-                            (event.imsi.clone(), event.timestamp_ms, None),
-                        );
+                        msisdn_ownership.insert(msisdn, (event.imsi, event.timestamp_ms, None));
                     }
                     SubscriberEventType::ReleaseNumber => {
                         // Mark MSISDN as released
-                        if let Some(ownership) = msisdn_ownership.get_mut(msisdn) {
+                        if let Some(ownership) = msisdn_ownership.get_mut(&msisdn) {
                             if ownership.0 == event.imsi {
                                 ownership.2 = Some(event.timestamp_ms);
                             }
@@ -227,144 +374,217 @@ impl SubscriberDatabase {
             }
         }
 
-        // 3. Validate IMSI format (should be 14-15 digits)
-        for event in &self.events {
-            if event.imsi.len() < 14 || event.imsi.len() > 15 {
-                return Err(anyhow!("Invalid IMSI length: {}", event.imsi));
-            }
-            if !event.imsi.chars().all(|c| c.is_ascii_digit()) {
-                return Err(anyhow!("Invalid IMSI (not all digits): {}", event.imsi));
-            }
-        }
+        // IMSI/MSISDN/IMEI formats are enforced when events are parsed
+
+        // 3. Check that no IMEI is used by too many IMSIs at the same time
+        check_imei_sharing(self.imei_usages(), max_imsis_per_imei)?;
 
-        // 4. Validate MSISDN format
+        // 4. Check that a software update keeps the device the IMSI is using
+        let mut devices: HashMap<Imsi, Imei> = HashMap::new();
+        let mut held = HeldNumbers::default();
         for event in &self.events {
-            if let Some(ref msisdn) = event.msisdn {
-                if msisdn.len() < 8 || msisdn.len() > 15 {
-                    return Err(anyhow!("Invalid MSISDN length: {}", msisdn));
+            let holds_number = held.apply(event);
+            match event.event_type {
+                SubscriberEventType::NewSubscriber
+                | SubscriberEventType::ChangeDevice
+                | SubscriberEventType::AssignNumber => {
+                    if let Some(imei) = event.imei {
+                        devices.insert(event.imsi, imei);
+                    }
                 }
-                if !msisdn.chars().all(|c| c.is_ascii_digit()) {
-                    return Err(anyhow!("Invalid MSISDN (not all digits): {}", msisdn));
+                // Another line of the IMSI keeps the device
+                SubscriberEventType::ReleaseNumber if holds_number => {}
+                SubscriberEventType::ReleaseNumber | SubscriberEventType::ChangeSim => {
+                    devices.remove(&event.imsi);
+                }
+                SubscriberEventType::SoftwareUpdate => {
+                    let current = devices.get(&event.imsi).copied();
+                    if event.imei.is_none() || event.imei != current {
+                        return Err(anyhow!(
+                            "Software update of {} at {} names IMEI {:?}, but the IMSI uses {:?}",
+                            event.imsi,
+                            event.timestamp_ms,
+                            event.imei.map(|imei| imei.to_string()),
+                            current.map(|imei| imei.to_string())
+                        ));
+                    }
                 }
             }
         }
 
-        // 5. Validate IMEI format (should be 15 digits)
+        Ok(())
+    }
+
+    /// IMEI usage intervals per IMSI derived from the event history
+    fn imei_usages(&self) -> Vec<ImeiUsage> {
+        let mut usages = Vec::new();
+        let mut current: HashMap<Imsi, (Imei, i64)> = HashMap::new(); // imsi -> (imei, since)
+        let mut held = HeldNumbers::default();
+
         for event in &self.events {
-            if let Some(ref imei) = event.imei {
-                if imei.len() != 15 {
-                    return Err(anyhow!("Invalid IMEI length: {}", imei));
-                }
-                if !imei.chars().all(|c| c.is_ascii_digit()) {
-                    return Err(anyhow!("Invalid IMEI (not all digits): {}", imei));
+            // Releasing one of several lines leaves the device in use
+            if held.apply(event) && event.event_type == SubscriberEventType::ReleaseNumber {
+                continue;
+            }
+            // Every event closes the IMSI's current device usage
+            if let Some((imei, from)) = current.remove(&event.imsi) {
+                usages.push(ImeiUsage {
+                    imei,
+                    imsi: event.imsi,
+                    from,
+                    to: Some(event.timestamp_ms),
+                });
+            }
+
+            let starts_usage = matches!(
+                event.event_type,
+                SubscriberEventType::NewSubscriber
+                    | SubscriberEventType::ChangeDevice
+                    | SubscriberEventType::AssignNumber
+                    | SubscriberEventType::SoftwareUpdate
+            );
+            if starts_usage {
+                if let Some(imei) = event.imei {
+                    current.insert(event.imsi, (imei, event.timestamp_ms));
                 }
             }
         }
 
-        Ok(())
+        for (imsi, (imei, from)) in current {
+            usages.push(ImeiUsage {
+                imei,
+                imsi,
+                from,
+                to: None,
+            });
+        }
+
+        usages
     }
 
     /// Build snapshots for efficient querying
     pub fn build_snapshots(&mut self) {
-        // Group events by IMSI and build snapshots
+        // Group events by IMSI and build snapshots, one window per number the IMSI holds
         let mut snapshots = Vec::new();
-        let mut imsi_states: HashMap<String, SubscriberState> = HashMap::new();
+        let mut imsi_states: HashMap<Imsi, SubscriberState> = HashMap::new();
 
         for event in &self.events {
-            let imsi = &event.imsi;
-            let state = imsi_states.entry(imsi.clone()).or_insert_with(|| SubscriberState {
-                imsi: imsi.clone(),
-                msisdn: None,
-                imei: None,
-                mccmnc: event.mccmnc.clone(),
-                valid_from: event.timestamp_ms,
-            });
+            imsi_states
+                .entry(event.imsi)
+                .or_insert_with(|| SubscriberState::empty(event))
+                .apply(event, &mut snapshots);
+        }
 
-            // Create snapshot for previous state if it changed
-            match event.event_type {
-                SubscriberEventType::NewSubscriber => {
-                    state.msisdn = event.msisdn.clone();
-                    state.imei = event.imei.clone();
-                    state.mccmnc = event.mccmnc.clone();
-                    state.valid_from = event.timestamp_ms;
-                }
-                SubscriberEventType::ChangeDevice => {
-                    // Close previous snapshot
-                    if let (Some(msisdn), Some(imei)) = (&state.msisdn, &state.imei) {
-                        snapshots.push(SubscriberSnapshot {
-                            imsi: state.imsi.clone(),
-                            msisdn: msisdn.clone(),
-                            imei: imei.clone(),
-                            mccmnc: state.mccmnc.clone(),
-                            valid_from: state.valid_from,
-                            valid_to: Some(event.timestamp_ms),
+        // Add final snapshots for all active subscribers
+        for state in imsi_states.values() {
+            snapshots.extend(state.snapshots(None));
+        }
+
+        self.snapshots = snapshots;
+    }
+
+    /// Normalize a messy event history and build its snapshots: sort the events, release a number before
+    /// it is assigned to another IMSI, drop releases of numbers the IMSI no longer holds, then clamp
+    /// overlapping windows of one MSISDN and drop windows without a positive length
+    pub fn repair(&mut self) -> RepairReport {
+        let mut report = RepairReport {
+            reordered_events: self.events.windows(2).filter(|pair| pair[1].timestamp_ms < pair[0].timestamp_ms).count(),
+            ..RepairReport::default()
+        };
+        if report.reordered_events > 0 {
+            self.events.sort_by_key(|event| event.timestamp_ms);
+            report.adjustments.push(format!("sorted {} out-of-order events", report.reordered_events));
+        }
+
+        // MSISDN -> IMSI holding it, and the last MCCMNC of every IMSI for synthesized releases
+        let mut holders: HashMap<Msisdn, Imsi> = HashMap::new();
+        let mut mccmncs: HashMap<Imsi, Mccmnc> = HashMap::new();
+        let mut events = Vec::with_capacity(self.events.len());
+        for event in std::mem::take(&mut self.events) {
+            match (event.event_type, event.msisdn) {
+                (SubscriberEventType::NewSubscriber | SubscriberEventType::AssignNumber, Some(msisdn)) => {
+                    if let Some(&holder) = holders.get(&msisdn).filter(|&&holder| holder != event.imsi) {
+                        report.synthesized_releases += 1;
+                        report.adjustments.push(format!(
+                            "released {} from {} at {} before its assignment to {}",
+                            msisdn, holder, event.timestamp_ms, event.imsi
+                        ));
+                        events.push(SubscriberEvent {
+                            timestamp_ms: event.timestamp_ms,
+                            event_type: SubscriberEventType::ReleaseNumber,
+                            imsi: holder,
+                            msisdn: Some(msisdn),
+                            imei: None,
+                            mccmnc: mccmncs.get(&holder).copied().unwrap_or(event.mccmnc),
+                            sv: 0,
                         });
                     }
-                    // Update state
-                    state.imei = event.imei.clone();
-                    state.valid_from = event.timestamp_ms;
+                    holders.insert(msisdn, event.imsi);
                 }
-                SubscriberEventType::ChangeSim => {
-                    // Close previous snapshot
-                    if let (Some(msisdn), Some(imei)) = (&state.msisdn, &state.imei) {
-                        snapshots.push(SubscriberSnapshot {
-                            imsi: state.imsi.clone(),
-                            msisdn: msisdn.clone(),
-                            imei: imei.clone(),
-                            mccmnc: state.mccmnc.clone(),
-                            valid_from: state.valid_from,
-                            valid_to: Some(event.timestamp_ms),
-                        });
+                (SubscriberEventType::ReleaseNumber, Some(msisdn)) => {
+                    if holders.get(&msisdn) != Some(&event.imsi) {
+                        report.dropped_releases += 1;
+                        report.adjustments.push(format!(
+                            "dropped release of {} by {} at {}: the IMSI does not hold the number",
+                            msisdn, event.imsi, event.timestamp_ms
+                        ));
+                        continue;
                     }
-                    // Update state (new IMSI means we need to track new state)
-                    // This is handled by the new event for the new IMSI
-                }
-                SubscriberEventType::ReleaseNumber => {
-                    // Close snapshot
-                    if let (Some(msisdn), Some(imei)) = (&state.msisdn, &state.imei) {
-                        snapshots.push(SubscriberSnapshot {
-                            imsi: state.imsi.clone(),
-                            msisdn: msisdn.clone(),
-                            imei: imei.clone(),
-                            mccmnc: state.mccmnc.clone(),
-                            valid_from: state.valid_from,
-                            valid_to: Some(event.timestamp_ms),
-                        });
-                    }
-                    state.msisdn = None;
-                    state.imei = None;
-                }
-                SubscriberEventType::AssignNumber => {
-                    state.msisdn = event.msisdn.clone();
-                    state.imei = event.imei.clone();
-                    state.valid_from = event.timestamp_ms;
+                    holders.remove(&msisdn);
                 }
+                _ => {}
             }
+            mccmncs.insert(event.imsi, event.mccmnc);
+            events.push(event);
         }
+        self.events = events;
+        self.build_indices();
+        self.build_snapshots();
 
-        // Add final snapshots for all active subscribers
-        for (_, state) in imsi_states {
-            if let (Some(msisdn), Some(imei)) = (state.msisdn, state.imei) {
-                snapshots.push(SubscriberSnapshot {
-                    imsi: state.imsi,
-                    msisdn,
-                    imei,
-                    mccmnc: state.mccmnc,
-                    valid_from: state.valid_from,
-                    valid_to: None,
-                });
+        // Windows of each MSISDN in start order; a window ends at the latest where the next one starts
+        let mut by_msisdn: HashMap<Msisdn, Vec<SubscriberSnapshot>> = HashMap::new();
+        for snapshot in std::mem::take(&mut self.snapshots) {
+            by_msisdn.entry(snapshot.identity.msisdn).or_default().push(snapshot);
+        }
+        let mut msisdns: Vec<Msisdn> = by_msisdn.keys().copied().collect();
+        msisdns.sort_unstable();
+        for msisdn in msisdns {
+            let mut windows = by_msisdn.remove(&msisdn).unwrap_or_default();
+            windows.sort_by_key(|snapshot| snapshot.valid_from);
+            for i in 0..windows.len() {
+                let next_from = windows.get(i + 1).map(|next| next.valid_from);
+                let window = &mut windows[i];
+                if let Some(next_from) = next_from.filter(|&from| window.valid_to.is_none_or(|to| to > from)) {
+                    report.clamped_windows += 1;
+                    report.adjustments.push(format!(
+                        "clamped window of {} ({}) from {} to end at {} instead of {:?}",
+                        msisdn, window.identity.imsi, window.valid_from, next_from, window.valid_to
+                    ));
+                    window.valid_to = Some(next_from);
+                }
+            }
+            for window in windows {
+                if window.valid_to.is_some_and(|to| to <= window.valid_from) {
+                    report.dropped_windows += 1;
+                    report.adjustments.push(format!(
+                        "dropped zero-length window of {} ({}) at {}",
+                        msisdn, window.identity.imsi, window.valid_from
+                    ));
+                    continue;
+                }
+                self.snapshots.push(window);
             }
         }
 
-        self.snapshots = snapshots;
+        report
     }
 
     /// Get subscriber snapshot at specific timestamp by IMSI
-    pub fn get_snapshot_at(&self, imsi: &str, timestamp_ms: i64) -> Option<SubscriberSnapshot> {
+    pub fn get_snapshot_at(&self, imsi: Imsi, timestamp_ms: i64) -> Option<SubscriberSnapshot> {
         // Use pre-computed snapshots if available
         if !self.snapshots.is_empty() {
             for snapshot in &self.snapshots {
-                if snapshot.imsi == imsi
+                if snapshot.identity.imsi == imsi
                     && snapshot.valid_from <= timestamp_ms
                     && (snapshot.valid_to.is_none() || snapshot.valid_to.unwrap() > timestamp_ms)
                 {
@@ -375,72 +595,34 @@ impl SubscriberDatabase {
         }
 
         // Fallback: compute from events
-        let indices = self.by_imsi.get(imsi)?;
-        let mut current_state: Option<SubscriberState> = None;
+        self.state_at(imsi, timestamp_ms)?.snapshots(None).next()
+    }
 
+    /// State of an IMSI after its events up to `timestamp_ms`, replayed from the event history
+    fn state_at(&self, imsi: Imsi, timestamp_ms: i64) -> Option<SubscriberState> {
+        let indices = self.by_imsi.get(&imsi)?;
+        let mut state = SubscriberState::empty(&self.events[*indices.first()?]);
+        let mut closed = Vec::new();
         for &idx in indices {
             let event = &self.events[idx];
             if event.timestamp_ms > timestamp_ms {
                 break;
             }
-
-            match event.event_type {
-                SubscriberEventType::NewSubscriber => {
-                    current_state = Some(SubscriberState {
-                        imsi: event.imsi.clone(),
-                        msisdn: event.msisdn.clone(),
-                        imei: event.imei.clone(),
-                        mccmnc: event.mccmnc.clone(),
-                        valid_from: event.timestamp_ms,
-                    });
-                }
-                SubscriberEventType::ChangeDevice => {
-                    if let Some(ref mut state) = current_state {
-                        state.imei = event.imei.clone();
-                    }
-                }
-                SubscriberEventType::ReleaseNumber => {
-                    current_state = None;
-                }
-                SubscriberEventType::AssignNumber => {
-                    current_state = Some(SubscriberState {
-                        imsi: event.imsi.clone(),
-                        msisdn: event.msisdn.clone(),
-                        imei: event.imei.clone(),
-                        mccmnc: event.mccmnc.clone(),
-                        valid_from: event.timestamp_ms,
-                    });
-                }
-                _ => {}
-            }
+            state.apply(event, &mut closed);
         }
-
-        current_state.and_then(|state| {
-            if let (Some(msisdn), Some(imei)) = (state.msisdn, state.imei) {
-                Some(SubscriberSnapshot {
-                    imsi: state.imsi,
-                    msisdn,
-                    imei,
-                    mccmnc: state.mccmnc,
-                    valid_from: state.valid_from,
-                    valid_to: None,
-                })
-            } else {
-                None
-            }
-        })
+        Some(state)
     }
 
     /// Get subscriber snapshot by MSISDN at specific timestamp
     pub fn get_snapshot_by_msisdn(
         &self,
-        msisdn: &str,
+        msisdn: Msisdn,
         timestamp_ms: i64,
     ) -> Option<SubscriberSnapshot> {
         // Use pre-computed snapshots if available
         if !self.snapshots.is_empty() {
             for snapshot in &self.snapshots {
-                if snapshot.msisdn == msisdn
+                if snapshot.identity.msisdn == msisdn
                     && snapshot.valid_from <= timestamp_ms
                     && (snapshot.valid_to.is_none() || snapshot.valid_to.unwrap() > timestamp_ms)
                 {
@@ -451,17 +633,14 @@ impl SubscriberDatabase {
         }
 
         // Fallback: find IMSI that owns this MSISDN at this time
-        let indices = self.by_msisdn.get(msisdn)?;
+        let indices = self.by_msisdn.get(&msisdn)?;
 
         for &idx in indices.iter().rev() {
             let event = &self.events[idx];
-            if event.timestamp_ms <= timestamp_ms {
-                if let Some(ref event_msisdn) = event.msisdn {
-                    if event_msisdn == msisdn {
-                        // Found the IMSI, now get its snapshot
-                        return self.get_snapshot_at(&event.imsi, timestamp_ms);
-                    }
-                }
+            if event.timestamp_ms <= timestamp_ms && event.msisdn == Some(msisdn) {
+                // Found the IMSI, now get the window of this number among its lines
+                let state = self.state_at(event.imsi, timestamp_ms)?;
+                return state.snapshots(None).find(|snapshot| snapshot.identity.msisdn == msisdn);
             }
         }
 
@@ -484,8 +663,8 @@ impl SubscriberDatabase {
     }
 
     /// Get all unique IMSIs in the database
-    pub fn get_all_unique_imsi(&self) -> Vec<String> {
-        self.by_imsi.keys().cloned().collect()
+    pub fn get_all_unique_imsi(&self) -> Vec<Imsi> {
+        self.by_imsi.keys().copied().collect()
     }
 
     /// Get all snapshots (requires build_snapshots() to be called first)
@@ -495,22 +674,13 @@ impl SubscriberDatabase {
 
     /// Filter database by MSISDN range (for worker partitioning)
     /// Creates a new database containing only events for subscribers in [start_u..end_u) range
-    pub fn filter_by_msisdn_range(&self, start_u: usize, end_u: usize, prefixes: &[String]) -> Self {
-        use std::collections::HashSet;
-
-        // Generate expected MSISDNs for this worker's subscriber range
-        let msisdn_set: HashSet<String> = (start_u..end_u)
-            .map(|idx| {
-                let prefix = &prefixes[idx % prefixes.len()];
-                let number = idx % 10_000_000;
-                format!("{}{:07}", prefix, number)
-            })
-            .collect();
+    pub fn filter_by_msisdn_range(&self, start_u: usize, end_u: usize, prefixes: &[String]) -> Result<Self> {
+        let range = MsisdnIndexRange::new(start_u, end_u, prefixes)?;
 
         // Filter events to only include those for our MSISDNs
         let filtered_events: Vec<SubscriberEvent> = self.events
             .iter()
-            .filter(|e| e.msisdn.as_ref().map_or(false, |m| msisdn_set.contains(m)))
+            .filter(|e| e.msisdn.is_some_and(|m| range.contains(m.get())))
             .cloned()
             .collect();
 
@@ -518,17 +688,140 @@ impl SubscriberDatabase {
         let mut db = SubscriberDatabase::new();
         db.events = filtered_events;
         db.build_indices();
-        db
+        Ok(db)
+    }
+}
+
+/// Numbers each IMSI holds, followed event by event
+#[derive(Default)]
+struct HeldNumbers(HashMap<Imsi, HashSet<Msisdn>>);
+
+impl HeldNumbers {
+    /// Apply `event`; true while its IMSI still holds a number afterwards
+    fn apply(&mut self, event: &SubscriberEvent) -> bool {
+        let held = self.0.entry(event.imsi).or_default();
+        match event.event_type {
+            SubscriberEventType::NewSubscriber => {
+                held.clear();
+                held.extend(event.msisdn);
+            }
+            SubscriberEventType::AssignNumber => held.extend(event.msisdn),
+            // A release naming no held number ends them all, as in the snapshots
+            SubscriberEventType::ReleaseNumber => match event.msisdn {
+                Some(msisdn) if held.contains(&msisdn) => {
+                    held.remove(&msisdn);
+                }
+                _ => held.clear(),
+            },
+            _ => {}
+        }
+        !held.is_empty()
     }
 }
 
-/// Internal state tracker for building snapshots
+/// Internal state tracker for building snapshots: an IMSI, its device and the numbers it holds
 struct SubscriberState {
-    imsi: String,
-    msisdn: Option<String>,
-    imei: Option<String>,
-    mccmnc: String,
-    valid_from: i64,
+    imsi: Imsi,
+    /// Numbers held and the start of their current window; a customer who added a line holds several
+    lines: Vec<(Msisdn, i64)>,
+    imei: Option<Imei>,
+    mccmnc: Mccmnc,
+    sv: u8,
+}
+
+impl SubscriberState {
+    /// State of the IMSI of `event` before it holds a number
+    fn empty(event: &SubscriberEvent) -> Self {
+        SubscriberState {
+            imsi: event.imsi,
+            lines: Vec::new(),
+            imei: None,
+            mccmnc: event.mccmnc,
+            sv: 0,
+        }
+    }
+
+    /// Apply an event of this IMSI, pushing the windows it closes to `closed`
+    fn apply(&mut self, event: &SubscriberEvent, closed: &mut Vec<SubscriberSnapshot>) {
+        let ts = event.timestamp_ms;
+        match event.event_type {
+            SubscriberEventType::NewSubscriber => {
+                *self = SubscriberState {
+                    imsi: event.imsi,
+                    lines: event.msisdn.map(|msisdn| (msisdn, ts)).into_iter().collect(),
+                    imei: event.imei,
+                    mccmnc: event.mccmnc,
+                    sv: event.sv,
+                };
+            }
+            SubscriberEventType::ChangeDevice => {
+                // Every line moves to the new device
+                self.restart_lines(ts, closed);
+                self.imei = event.imei;
+                self.sv = event.sv;
+            }
+            SubscriberEventType::SoftwareUpdate => {
+                // Same device, new software version
+                self.restart_lines(ts, closed);
+                self.sv = event.sv;
+            }
+            SubscriberEventType::ChangeSim => {
+                // Close the windows; the new IMSI is tracked from its own events
+                closed.extend(self.snapshots(Some(ts)));
+            }
+            SubscriberEventType::ReleaseNumber => {
+                // Only the released line ends; a release naming no held number ends them all
+                let held = self.lines.iter().any(|&(msisdn, _)| event.msisdn == Some(msisdn));
+                let released = |&(msisdn, _): &(Msisdn, i64)| !held || event.msisdn == Some(msisdn);
+                closed.extend(self.lines.iter().filter(|line| released(line)).filter_map(|&line| self.line_snapshot(line, Some(ts))));
+                self.lines.retain(|line| !released(line));
+                if self.lines.is_empty() {
+                    self.imei = None;
+                }
+            }
+            SubscriberEventType::AssignNumber => {
+                // A number assigned to an IMSI that already holds one is an extra line. A number it
+                // already holds starts over, and so do the other lines if the device comes with it
+                if let Some(idx) = self.lines.iter().position(|&(msisdn, _)| event.msisdn == Some(msisdn)) {
+                    let line = self.lines.remove(idx);
+                    closed.extend(self.line_snapshot(line, Some(ts)));
+                }
+                if (self.imei, self.sv) != (event.imei, event.sv) {
+                    self.restart_lines(ts, closed);
+                }
+                self.imei = event.imei;
+                self.sv = event.sv;
+                self.lines.extend(event.msisdn.map(|msisdn| (msisdn, ts)));
+            }
+        }
+    }
+
+    /// Close the window of every line at `ts` and open the next one there
+    fn restart_lines(&mut self, ts: i64, closed: &mut Vec<SubscriberSnapshot>) {
+        closed.extend(self.snapshots(Some(ts)));
+        for line in &mut self.lines {
+            line.1 = ts;
+        }
+    }
+
+    /// Snapshots of the current windows of all lines up to `valid_to`; none while the IMSI has no device
+    fn snapshots(&self, valid_to: Option<i64>) -> impl Iterator<Item = SubscriberSnapshot> + '_ {
+        self.lines.iter().filter_map(move |&line| self.line_snapshot(line, valid_to))
+    }
+
+    fn line_snapshot(&self, (msisdn, valid_from): (Msisdn, i64), valid_to: Option<i64>) -> Option<SubscriberSnapshot> {
+        Some(SubscriberSnapshot {
+            identity: SubscriberIdentity {
+                imsi: self.imsi,
+                msisdn,
+                imei: self.imei?,
+                mccmnc: self.mccmnc,
+                sv: self.sv,
+            },
+            valid_from,
+            valid_to,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -547,6 +840,10 @@ mod tests {
             SubscriberEventType::NewSubscriber.to_str(),
             "NEW_SUBSCRIBER"
         );
+        assert_eq!(
+            SubscriberEventType::from_str("SOFTWARE_UPDATE").unwrap(),
+            SubscriberEventType::SoftwareUpdate
+        );
     }
 
     #[test]
@@ -614,15 +911,232 @@ mod tests {
         db.build_snapshots();
 
         // Before first event
-        let snapshot = db.get_snapshot_at("204081234567890", 1704000000000);
+        let imsi: Imsi = "204081234567890".parse().unwrap();
+        let snapshot = db.get_snapshot_at(imsi, 1704000000000);
         assert!(snapshot.is_none());
 
         // After first event, before device change
-        let snapshot = db.get_snapshot_at("204081234567890", 1704100000000).unwrap();
-        assert_eq!(snapshot.imei, "123456789012345");
+        let snapshot = db.get_snapshot_at(imsi, 1704100000000).unwrap();
+        assert_eq!(snapshot.identity.imei.to_string(), "123456789012345");
 
         // After device change
-        let snapshot = db.get_snapshot_at("204081234567890", 1704200000000).unwrap();
-        assert_eq!(snapshot.imei, "987654321098765");
+        let snapshot = db.get_snapshot_at(imsi, 1704200000000).unwrap();
+        assert_eq!(snapshot.identity.imei.to_string(), "987654321098765");
+    }
+
+    #[test]
+    fn test_validate_imei_sharing() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "timestamp_ms,event_type,imsi,msisdn,imei,mccmnc").unwrap();
+        writeln!(file, "1704067200000,NEW_SUBSCRIBER,204080000000001,31612000001,111111111111111,20408").unwrap();
+        writeln!(file, "1704067200000,NEW_SUBSCRIBER,204080000000002,31612000002,222222222222222,20408").unwrap();
+        // Hand-me-down: subscriber 1 stops using the device before subscriber 2 picks it up
+        writeln!(file, "1704153600000,CHANGE_DEVICE,204080000000001,31612000001,333333333333333,20408").unwrap();
+        writeln!(file, "1704240000000,CHANGE_DEVICE,204080000000002,31612000002,111111111111111,20408").unwrap();
+
+        let db = SubscriberDatabase::load_from_csv(file.path()).unwrap();
+        assert!(db.validate_with(1).is_ok());
+
+        // Dual-SIM: subscriber 3 uses the same device while subscriber 2 still has it
+        writeln!(file, "1704326400000,NEW_SUBSCRIBER,204080000000003,31612000003,111111111111111,20408").unwrap();
+        let db = SubscriberDatabase::load_from_csv(file.path()).unwrap();
+        assert!(db.validate_with(1).is_err());
+        assert!(db.validate_with(2).is_ok());
+    }
+
+    #[test]
+    fn test_check_imei_sharing() {
+        let usage = |imsi: u64, from: i64, to: Option<i64>| ImeiUsage {
+            imei: "111111111111111".parse().unwrap(),
+            imsi: Imsi::new(204080000000000 + imsi).unwrap(),
+            from,
+            to,
+        };
+
+        // Back-to-back intervals do not overlap
+        let sequential = vec![usage(1, 0, Some(10)), usage(2, 10, Some(20)), usage(3, 20, None)];
+        assert!(check_imei_sharing(sequential, 1).is_ok());
+
+        let simultaneous = vec![usage(1, 0, None), usage(2, 5, None), usage(3, 8, Some(9))];
+        assert!(check_imei_sharing(simultaneous.clone(), 3).is_ok());
+        let err = check_imei_sharing(simultaneous, 2).unwrap_err();
+        assert!(err.to_string().contains("3 IMSIs"), "{}", err);
+    }
+
+    #[test]
+    fn test_load_csv_rejects_malformed_identifiers() {
+        for (row, expected) in [
+            ("1704067200000,NEW_SUBSCRIBER,2040812345678x0,31612345678,123456789012345,20408", "IMSI"),
+            ("1704067200000,NEW_SUBSCRIBER,204081234567890,0612345678,123456789012345,20408", "leading zero"),
+            ("1704067200000,NEW_SUBSCRIBER,204081234567890,31612345678,12345678901234,20408", "IMEI"),
+        ] {
+            let mut file = NamedTempFile::new().unwrap();
+            writeln!(file, "timestamp_ms,event_type,imsi,msisdn,imei,mccmnc").unwrap();
+            writeln!(file, "{}", row).unwrap();
+            let err = SubscriberDatabase::load_from_csv(file.path()).unwrap_err();
+            assert!(format!("{:#}", err).contains(expected), "{:#}", err);
+            assert!(format!("{:#}", err).starts_with("Line 2"), "{:#}", err);
+        }
+
+        // A leading-zero IMEI is fine and keeps its digits
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "timestamp_ms,event_type,imsi,msisdn,imei,mccmnc").unwrap();
+        writeln!(file, "1704067200000,NEW_SUBSCRIBER,204081234567890,31612345678,012345678901237,20408").unwrap();
+        let db = SubscriberDatabase::load_from_csv(file.path()).unwrap();
+        assert_eq!(db.events[0].imei.unwrap().to_string(), "012345678901237");
+    }
+
+    #[test]
+    fn test_load_msisdn_file() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "# QA numbers").unwrap();
+        writeln!(file, "31612000042").unwrap();
+        writeln!(file).unwrap();
+        writeln!(file, " 31612000007 ").unwrap();
+        writeln!(file, "31612000042").unwrap();
+        assert_eq!(load_msisdn_file(file.path()).unwrap(), vec![31612000007, 31612000042]);
+
+        writeln!(file, "+31612000001").unwrap();
+        let err = load_msisdn_file(file.path()).unwrap_err();
+        assert!(format!("{:#}", err).contains("line 6: Invalid MSISDN"), "{:#}", err);
+
+        let empty = NamedTempFile::new().unwrap();
+        assert!(load_msisdn_file(empty.path()).unwrap_err().to_string().contains("is empty"));
+    }
+
+    #[test]
+    fn test_msisdn_index_range_matches_derived_msisdns() {
+        let prefixes = ["31612".to_string(), "31613".to_string(), "31612".to_string()];
+        // A range within the first block of subscriber numbers, and one crossing into the second
+        for (start_u, end_u) in [(100, 400), (9_999_900, 10_000_200)] {
+            let range = MsisdnIndexRange::new(start_u, end_u, &prefixes).unwrap();
+            let derived: HashSet<u64> =
+                (start_u..end_u).map(|idx| [31612u64, 31613, 31612][idx % 3] * 10_000_000 + (idx % 10_000_000) as u64).collect();
+            let candidates = (0..500).chain(9_999_800..10_000_000).flat_map(|n| [316_120_000_000 + n, 316_130_000_000 + n]);
+            for msisdn in candidates {
+                assert_eq!(range.contains(msisdn), derived.contains(&msisdn), "{} in {}..{}", msisdn, start_u, end_u);
+            }
+        }
+        assert!(!MsisdnIndexRange::new(0, 100, &prefixes).unwrap().contains(316_140_000_001));
+        assert!(MsisdnIndexRange::new(0, 100, &[]).is_err());
+    }
+
+    #[test]
+    fn test_software_update() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "timestamp_ms,event_type,imsi,msisdn,imei,mccmnc,sv").unwrap();
+        writeln!(file, "1704067200000,NEW_SUBSCRIBER,204081234567890,31612345678,123456789012345,20408,03").unwrap();
+        writeln!(file, "1704153600000,SOFTWARE_UPDATE,204081234567890,31612345678,123456789012345,20408,04").unwrap();
+
+        let mut db = SubscriberDatabase::load_from_csv(file.path()).unwrap();
+        assert!(db.validate().is_ok());
+        let imsi: Imsi = "204081234567890".parse().unwrap();
+        assert_eq!(db.get_snapshot_at(imsi, 1704100000000).unwrap().identity.sv, 3);
+        assert_eq!(db.get_snapshot_at(imsi, 1704200000000).unwrap().identity.sv, 4);
+
+        // Precomputed snapshots split at the update and keep the device
+        db.build_snapshots();
+        assert_eq!(db.snapshot_count(), 2);
+        let before = db.get_snapshot_at(imsi, 1704100000000).unwrap();
+        let after = db.get_snapshot_at(imsi, 1704200000000).unwrap();
+        assert_eq!((before.valid_to, after.valid_from), (Some(1704153600000), 1704153600000));
+        assert_eq!(before.identity.imei, after.identity.imei);
+        assert_eq!(after.identity.imeisv(), 1_234_567_890_123_404);
+
+        // An update must name the device the IMSI is using
+        writeln!(file, "1704240000000,SOFTWARE_UPDATE,204081234567890,31612345678,987654321098765,20408,05").unwrap();
+        let db = SubscriberDatabase::load_from_csv(file.path()).unwrap();
+        let err = db.validate().unwrap_err();
+        assert!(err.to_string().contains("Software update"), "{}", err);
+
+        // SV 99 is reserved
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "timestamp_ms,event_type,imsi,msisdn,imei,mccmnc,sv").unwrap();
+        writeln!(file, "1704067200000,NEW_SUBSCRIBER,204081234567890,31612345678,123456789012345,20408,99").unwrap();
+        let err = SubscriberDatabase::load_from_csv(file.path()).unwrap_err();
+        assert!(format!("{:#}", err).contains("software version"), "{:#}", err);
+    }
+
+    #[test]
+    fn test_second_line_on_one_imsi() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "timestamp_ms,event_type,imsi,msisdn,imei,mccmnc,sv").unwrap();
+        writeln!(file, "1000,NEW_SUBSCRIBER,204080000000001,31612000001,111111111111111,20408,03").unwrap();
+        writeln!(file, "1000,NEW_SUBSCRIBER,204080000000002,31612000002,222222222222222,20408,01").unwrap();
+        // Subscriber 1 adds a line on the same SIM and device, moves both to a new phone, then drops the first
+        writeln!(file, "2000,ASSIGN_NUMBER,204080000000001,31612000003,111111111111111,20408,03").unwrap();
+        writeln!(file, "3000,CHANGE_DEVICE,204080000000001,31612000001,333333333333333,20408,05").unwrap();
+        writeln!(file, "4000,RELEASE_NUMBER,204080000000001,31612000001,,20408,").unwrap();
+        writeln!(file, "5000,SOFTWARE_UPDATE,204080000000001,31612000003,333333333333333,20408,06").unwrap();
+
+        let mut db = SubscriberDatabase::load_from_csv(file.path()).unwrap();
+        // One IMSI on two numbers is neither an MSISDN conflict nor a shared device
+        assert!(db.validate_with(1).is_ok());
+        let msisdn = |n: u64| Msisdn::new(31_612_000_000 + n).unwrap();
+        let imsi: Imsi = "204080000000001".parse().unwrap();
+        let check = |db: &SubscriberDatabase| {
+            let second = db.get_snapshot_by_msisdn(msisdn(3), 2500).unwrap().identity;
+            assert_eq!((second.imsi, second.imei.to_string(), second.sv), (imsi, "111111111111111".to_string(), 3));
+            let first = db.get_snapshot_by_msisdn(msisdn(1), 3500).unwrap().identity;
+            assert_eq!((first.imsi, first.imei.to_string()), (imsi, "333333333333333".to_string()));
+            assert!(db.get_snapshot_by_msisdn(msisdn(1), 4500).is_none());
+            assert_eq!(db.get_snapshot_by_msisdn(msisdn(3), 5500).unwrap().identity.sv, 6);
+        };
+        // Replayed from the events, then from the precomputed snapshots
+        check(&db);
+        db.build_snapshots();
+        check(&db);
+
+        let mut windows: Vec<(u64, i64, Option<i64>)> =
+            db.get_snapshots().iter().map(|s| (s.identity.msisdn.get() % 1000, s.valid_from, s.valid_to)).collect();
+        windows.sort();
+        assert_eq!(
+            windows,
+            vec![(1, 1000, Some(3000)), (1, 3000, Some(4000)), (2, 1000, None), (3, 2000, Some(3000)), (3, 3000, Some(5000)), (3, 5000, None)]
+        );
+
+        // The second line is still one MSISDN: another IMSI cannot take it while it is held
+        writeln!(file, "6000,ASSIGN_NUMBER,204080000000002,31612000003,222222222222222,20408,01").unwrap();
+        let db = SubscriberDatabase::load_from_csv(file.path()).unwrap();
+        assert!(db.validate().unwrap_err().to_string().contains("MSISDN 31612000003 conflict"));
+    }
+
+    #[test]
+    fn test_repair_messy_history() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "timestamp_ms,event_type,imsi,msisdn,imei,mccmnc").unwrap();
+        writeln!(file, "1000,NEW_SUBSCRIBER,204080000000001,31612000001,111111111111111,20408").unwrap();
+        // Reassigned without a release; the late release of the old holder is stale
+        writeln!(file, "3000,RELEASE_NUMBER,204080000000001,31612000001,,20408").unwrap();
+        writeln!(file, "2000,ASSIGN_NUMBER,204080000000002,31612000001,222222222222222,20408").unwrap();
+        // Device changed at the very moment the subscriber joined
+        writeln!(file, "1500,NEW_SUBSCRIBER,204080000000003,31612000003,333333333333333,20408").unwrap();
+        writeln!(file, "1500,CHANGE_DEVICE,204080000000003,31612000003,444444444444444,20408").unwrap();
+
+        let mut db = SubscriberDatabase::load_from_csv(file.path()).unwrap();
+        assert!(db.validate().is_err());
+        let report = db.repair();
+        assert_eq!(report.reordered_events, 2);
+        assert_eq!((report.synthesized_releases, report.dropped_releases), (1, 1));
+        assert_eq!(report.dropped_windows, 1);
+        assert!(!report.is_clean());
+        assert!(db.validate().is_ok());
+
+        let msisdn: Msisdn = "31612000001".parse().unwrap();
+        let owner = |ts| db.get_snapshot_by_msisdn(msisdn, ts).map(|s| s.identity.imsi.to_string());
+        assert_eq!(owner(1500).as_deref(), Some("204080000000001"));
+        assert_eq!(owner(2500).as_deref(), Some("204080000000002"));
+        assert_eq!(owner(3500).as_deref(), Some("204080000000002"));
+        let device = db.get_snapshot_by_msisdn("31612000003".parse().unwrap(), 1500).unwrap();
+        assert_eq!(device.identity.imei.to_string(), "444444444444444");
+
+        // A clean history needs no repair
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "timestamp_ms,event_type,imsi,msisdn,imei,mccmnc").unwrap();
+        writeln!(file, "1000,NEW_SUBSCRIBER,204080000000001,31612000001,111111111111111,20408").unwrap();
+        writeln!(file, "2000,CHANGE_DEVICE,204080000000001,31612000001,222222222222222,20408").unwrap();
+        let mut db = SubscriberDatabase::load_from_csv(file.path()).unwrap();
+        assert!(db.repair().is_clean());
+        assert_eq!(db.snapshot_count(), 2);
     }
 }
@@ -0,0 +1,171 @@
+// Arrow IPC export of the subscriber event history, and its import per worker range (`arrow` feature)
+use crate::identity::{parse_sv, Msisdn};
+use crate::subscriber_db::{MsisdnIndexRange, SubscriberDatabase, SubscriberEvent, SubscriberEventType};
+use anyhow::{Context, Result};
+use arrow_array::{Array, ArrayRef, Int64Array, RecordBatch, StringArray};
+use arrow_ipc::reader::FileReader;
+use arrow_ipc::writer::FileWriter;
+use arrow_schema::{DataType, Field, Schema};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::sync::Arc;
+use tracing::info;
+
+/// Rows per record batch in Arrow exports
+const ARROW_BATCH_ROWS: usize = 65_536;
+
+/// Arrow schema of exported events: the `export_to_csv` columns, with nullable MSISDN/IMEI/SV
+pub fn events_arrow_schema() -> Schema {
+    Schema::new(vec![
+        Field::new("timestamp_ms", DataType::Int64, false),
+        Field::new("event_type", DataType::Utf8, false),
+        Field::new("imsi", DataType::Utf8, false),
+        Field::new("msisdn", DataType::Utf8, true),
+        Field::new("imei", DataType::Utf8, true),
+        Field::new("mccmnc", DataType::Utf8, false),
+        Field::new("sv", DataType::Utf8, true),
+    ])
+}
+
+/// Export events to an Arrow IPC file, `ARROW_BATCH_ROWS` rows per record batch
+pub fn write_events_to_arrow<P: AsRef<Path>>(events: &[SubscriberEvent], path: P) -> Result<()> {
+    let schema = Arc::new(events_arrow_schema());
+    let file = BufWriter::new(File::create(&path)?);
+    let mut writer = FileWriter::try_new(file, &schema)?;
+
+    for chunk in events.chunks(ARROW_BATCH_ROWS) {
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(Int64Array::from_iter_values(chunk.iter().map(|e| e.timestamp_ms))),
+            Arc::new(StringArray::from_iter_values(chunk.iter().map(|e| e.event_type.to_str()))),
+            Arc::new(StringArray::from_iter_values(chunk.iter().map(|e| e.imsi.to_string()))),
+            Arc::new(chunk.iter().map(|e| e.msisdn.map(|m| m.to_string())).collect::<StringArray>()),
+            Arc::new(chunk.iter().map(|e| e.imei.map(|i| i.to_string())).collect::<StringArray>()),
+            Arc::new(StringArray::from_iter_values(chunk.iter().map(|e| e.mccmnc.to_string()))),
+            Arc::new(chunk.iter().map(|e| e.imei.map(|_| format!("{:02}", e.sv))).collect::<StringArray>()),
+        ];
+        writer.write(&RecordBatch::try_new(schema.clone(), columns)?)?;
+    }
+    writer.finish()?;
+
+    info!("Exported {} events to {:?}", events.len(), path.as_ref());
+    Ok(())
+}
+
+/// Read events written by `write_events_to_arrow`; malformed identifiers are an error.
+/// Files without the `sv` column (older exports) read with software version 00
+pub fn read_events_from_arrow<P: AsRef<Path>>(path: P) -> Result<Vec<SubscriberEvent>> {
+    read_events(path.as_ref(), None)
+}
+
+/// Read the events of a worker's subscriber range, as `SubscriberDatabase::filter_by_msisdn_range` keeps them.
+/// The MSISDN column is tested per row before anything else is parsed, so rows of other workers cost
+/// one integer parse and no allocation; events without an MSISDN are skipped
+pub fn read_events_from_arrow_in_range<P: AsRef<Path>>(path: P, range: &MsisdnIndexRange) -> Result<Vec<SubscriberEvent>> {
+    read_events(path.as_ref(), Some(range))
+}
+
+/// Load a worker's share of an Arrow event export as a database
+pub fn load_database_from_arrow_in_range(path: &Path, range: &MsisdnIndexRange) -> Result<SubscriberDatabase> {
+    let mut db = SubscriberDatabase::new();
+    db.events = read_events_from_arrow_in_range(path, range)
+        .with_context(|| format!("Failed to read subscriber DB: {:?}", path))?;
+    db.build_indices();
+    Ok(db)
+}
+
+fn read_events(path: &Path, range: Option<&MsisdnIndexRange>) -> Result<Vec<SubscriberEvent>> {
+    let reader = FileReader::try_new(File::open(path)?, None)?;
+    let mut events = Vec::new();
+    let mut rows_read = 0;
+
+    for batch in reader {
+        let batch = batch?;
+        let column = |name: &str| -> Result<&StringArray> {
+            batch
+                .column_by_name(name)
+                .and_then(|c| c.as_any().downcast_ref::<StringArray>())
+                .with_context(|| format!("Arrow events: missing string column {}", name))
+        };
+        let timestamps = batch
+            .column_by_name("timestamp_ms")
+            .and_then(|c| c.as_any().downcast_ref::<Int64Array>())
+            .context("Arrow events: missing Int64 column timestamp_ms")?;
+        let (event_types, imsis, msisdns, imeis, mccmncs) =
+            (column("event_type")?, column("imsi")?, column("msisdn")?, column("imei")?, column("mccmnc")?);
+        let svs = batch.column_by_name("sv").map(|_| column("sv")).transpose()?;
+
+        for row in 0..batch.num_rows() {
+            let row_context = || format!("Arrow events row {}", rows_read + row + 1);
+            let msisdn: Option<Msisdn> =
+                (!msisdns.is_null(row)).then(|| msisdns.value(row).parse()).transpose().with_context(row_context)?;
+            if let Some(range) = range {
+                if !msisdn.is_some_and(|m| range.contains(m.get())) {
+                    continue;
+                }
+            }
+            events.push(SubscriberEvent {
+                timestamp_ms: timestamps.value(row),
+                event_type: SubscriberEventType::from_str(event_types.value(row)).with_context(row_context)?,
+                imsi: imsis.value(row).parse().with_context(row_context)?,
+                msisdn,
+                imei: (!imeis.is_null(row)).then(|| imeis.value(row).parse()).transpose().with_context(row_context)?,
+                mccmnc: mccmncs.value(row).parse().with_context(row_context)?,
+                sv: match svs {
+                    Some(svs) if !svs.is_null(row) => parse_sv(svs.value(row)).with_context(row_context)?,
+                    _ => 0,
+                },
+            });
+        }
+        rows_read += batch.num_rows();
+    }
+
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subscriber_db_generator::{generate_database, EventExportFormat, GeneratorConfig};
+
+    #[test]
+    fn test_arrow_export_roundtrip() {
+        let config = GeneratorConfig {
+            initial_subscribers: 300,
+            history_days: 60,
+            seed: 7,
+            software_update_rate: 0.5,
+            ..GeneratorConfig::default()
+        };
+        let events = generate_database(&config).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.arrow");
+        let format = EventExportFormat::from_path(&path).unwrap();
+        format.write(&events, &path).unwrap();
+
+        let reader = FileReader::try_new(File::open(&path).unwrap(), None).unwrap();
+        assert_eq!(*reader.schema(), events_arrow_schema());
+        assert!(events.iter().any(|e| e.event_type == SubscriberEventType::SoftwareUpdate));
+        assert_eq!(format.read(&path).unwrap(), events);
+        assert!(EventExportFormat::from_path(Path::new("events.parquet")).is_err());
+    }
+
+    #[test]
+    fn test_range_read_keeps_the_worker_events() {
+        let config = GeneratorConfig { initial_subscribers: 500, history_days: 30, seed: 11, ..GeneratorConfig::default() };
+        let events = generate_database(&config).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.arrow");
+        write_events_to_arrow(&events, &path).unwrap();
+
+        let mut full = SubscriberDatabase::new();
+        full.events = events;
+        for (start_u, end_u) in [(0, 5_000_000), (2_500_000, 7_500_000)] {
+            let range = MsisdnIndexRange::new(start_u, end_u, &config.prefixes).unwrap();
+            let expected = full.filter_by_msisdn_range(start_u, end_u, &config.prefixes).unwrap().events;
+            let read = load_database_from_arrow_in_range(&path, &range).unwrap().events;
+            assert!(!read.is_empty() && read.len() < full.events.len(), "{} of {}", read.len(), full.events.len());
+            assert_eq!(read, expected);
+        }
+    }
+}
@@ -1,15 +1,21 @@
 // Generator for synthetic subscriber database with realistic history
-use crate::identity::gen_imei;
-use crate::subscriber_db::{SubscriberEvent, SubscriberEventType};
+use crate::identity::{
+    bound_operator, gen_imei, initial_sv, parse_numeric, parse_prefix_operator_map, Imei, Imsi, Mccmnc, Msisdn, MAX_SV,
+};
+use crate::subscriber_db::{SubscriberDatabase, SubscriberEvent, SubscriberEventType};
+#[cfg(feature = "redb")]
+use crate::subscriber_db_redb::{compact_database, DbStats, DbWriteOptions, DEFAULT_SPOT_CHECKS};
 use anyhow::Result;
+use chrono::{DateTime, Datelike, NaiveDate};
 use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
 use rand::Rng;
 use rand::SeedableRng;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::fs::File;
-use std::io::Write;
+use std::io::{BufWriter, Write};
 use std::path::Path;
+use tracing::{debug, info};
 
 /// Configuration for subscriber database generation
 #[derive(Debug, Clone)]
@@ -22,12 +28,35 @@ pub struct GeneratorConfig {
     pub device_change_rate: f64,
     /// Annual number release rate (0.0 - 1.0)
     pub number_release_rate: f64,
+    /// Annual device software update rate (0.0 - 1.0): the SV of the subscriber's device goes up by one
+    pub software_update_rate: f64,
+    /// Monthly multipliers (1-12) for the daily device change probability; missing months = 1.0
+    pub device_change_seasonality: HashMap<usize, f64>,
+    /// Day multipliers (YYYY-MM-DD) for device changes, e.g. product launches
+    pub device_change_special_days: HashMap<String, f64>,
+    /// Monthly multipliers (1-12) for the daily number release probability
+    pub number_release_seasonality: HashMap<usize, f64>,
+    /// Day multipliers (YYYY-MM-DD) for number releases
+    pub number_release_special_days: HashMap<String, f64>,
+    /// Share of device changes that pick up a shared device (0.0 - 1.0):
+    /// a recently released IMEI (hand-me-down) or another subscriber's current IMEI (dual-SIM)
+    pub shared_device_rate: f64,
     /// Cooldown period in days before reassigning released numbers
     pub cooldown_days: usize,
+    /// Daily probability that a released number past its cooldown is reassigned (1.0 = on the day cooldown ends)
+    pub reassignment_rate: f64,
+    /// Share of reassigned numbers that go to an existing active customer adding a line (0.0 - 1.0):
+    /// the customer's IMSI then holds both numbers, the rest go to a new IMSI. CDR generation keeps the calls of
+    /// both lines from overlapping when one worker serves both numbers
+    pub reassign_to_existing_rate: f64,
+    /// Days after release when a number still not reassigned is retired for good (None = never)
+    pub max_dormancy_days: Option<usize>,
     /// Phone number prefixes
     pub prefixes: Vec<String>,
     /// MCC+MNC pool
     pub mccmnc_pool: Vec<String>,
+    /// MSISDN prefix → MCCMNC; subscribers on unmapped prefixes draw from `mccmnc_pool`
+    pub prefix_operator_map: BTreeMap<String, String>,
     /// Random seed
     pub seed: u64,
     /// Start timestamp (milliseconds)
@@ -41,9 +70,19 @@ impl Default for GeneratorConfig {
             history_days: 365,
             device_change_rate: 0.15,
             number_release_rate: 0.05,
+            software_update_rate: 0.5,
+            device_change_seasonality: HashMap::new(),
+            device_change_special_days: HashMap::new(),
+            number_release_seasonality: HashMap::new(),
+            number_release_special_days: HashMap::new(),
+            shared_device_rate: 0.0,
             cooldown_days: 90,
+            reassignment_rate: 1.0,
+            reassign_to_existing_rate: 0.0,
+            max_dormancy_days: None,
             prefixes: vec!["31612".to_string(), "31613".to_string()],
             mccmnc_pool: vec!["20408".to_string(), "20416".to_string()],
+            prefix_operator_map: BTreeMap::new(),
             seed: 42,
             start_timestamp_ms: 1704067200000, // 2024-01-01
         }
@@ -53,131 +92,310 @@ impl Default for GeneratorConfig {
 /// Subscriber state during generation
 #[derive(Debug, Clone)]
 struct ActiveSubscriber {
-    imsi: String,
-    msisdn: String,
-    imei: u64,
-    mccmnc: String,
+    imsi: Imsi,
+    msisdn: Msisdn,
+    imei: Imei,
+    mccmnc: Mccmnc,
+    sv: u8,
+    /// Lines added later on the same IMSI and device, newest last
+    extra_msisdns: Vec<Msisdn>,
     #[allow(dead_code)]
     activation_time: i64,
 }
 
+/// How long a released IMEI stays available for hand-me-down reuse
+const SHARED_DEVICE_WINDOW_DAYS: i64 = 90;
+
+/// Mixed into the seed of the software update RNG, so updates leave the other events as they were
+const SOFTWARE_UPDATE_SEED_SALT: u64 = 0x5E7F_0A2E_D0C5_1A7E;
+
 /// Released phone number in cooldown
 #[derive(Debug, Clone)]
 struct ReleasedNumber {
-    msisdn: String,
+    msisdn: Msisdn,
     release_time: i64,
 }
 
+/// Multiplier for a base-event probability on the given day (month seasonality x special day)
+fn event_multiplier(seasonality: &HashMap<usize, f64>, special_days: &HashMap<String, f64>, date: NaiveDate) -> f64 {
+    let seas = seasonality.get(&(date.month() as usize)).unwrap_or(&1.0);
+    let special = special_days.get(&date.format("%Y-%m-%d").to_string()).unwrap_or(&1.0);
+    seas * special
+}
+
+/// Per-month counts of subscriber-base events
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MonthlyEventCounts {
+    pub new_subscribers: usize,
+    pub device_changes: usize,
+    pub sim_changes: usize,
+    pub number_releases: usize,
+    pub number_assignments: usize,
+    pub software_updates: usize,
+}
+
+/// Count events per UTC month (YYYY-MM)
+pub fn monthly_event_counts(events: &[SubscriberEvent]) -> BTreeMap<String, MonthlyEventCounts> {
+    let mut counts: BTreeMap<String, MonthlyEventCounts> = BTreeMap::new();
+    for event in events {
+        let month = DateTime::from_timestamp_millis(event.timestamp_ms)
+            .unwrap_or_default()
+            .format("%Y-%m")
+            .to_string();
+        let entry = counts.entry(month).or_default();
+        match event.event_type {
+            SubscriberEventType::NewSubscriber => entry.new_subscribers += 1,
+            SubscriberEventType::ChangeDevice => entry.device_changes += 1,
+            SubscriberEventType::ChangeSim => entry.sim_changes += 1,
+            SubscriberEventType::ReleaseNumber => entry.number_releases += 1,
+            SubscriberEventType::AssignNumber => entry.number_assignments += 1,
+            SubscriberEventType::SoftwareUpdate => entry.software_updates += 1,
+        }
+    }
+    counts
+}
+
 /// Generate subscriber database with realistic history
 pub fn generate_database(config: &GeneratorConfig) -> Result<Vec<SubscriberEvent>> {
+    if config.max_dormancy_days.is_some_and(|days| days < config.cooldown_days) {
+        anyhow::bail!(
+            "max_dormancy_days ({}) must not be shorter than cooldown_days ({})",
+            config.max_dormancy_days.unwrap_or_default(),
+            config.cooldown_days
+        );
+    }
+
     let mut rng = StdRng::seed_from_u64(config.seed);
+    let mut sv_rng = StdRng::seed_from_u64(config.seed ^ SOFTWARE_UPDATE_SEED_SALT);
     let mut events = Vec::new();
-    let mut active_subscribers: HashMap<String, ActiveSubscriber> = HashMap::new();
+    // Ordered: device changes and releases draw from the RNG once per subscriber in key order
+    let mut active_subscribers: BTreeMap<Imsi, ActiveSubscriber> = BTreeMap::new();
     let mut released_numbers: Vec<ReleasedNumber> = Vec::new();
-    let mut used_msisdns: HashSet<String> = HashSet::new();
+    // Recently released devices (imei, sv, release time) available for hand-me-down reuse
+    let mut released_imeis: VecDeque<(Imei, u8, i64)> = VecDeque::new();
+    // IMEIs already used by two IMSIs at once; never shared further
+    let mut shared_imeis: HashSet<Imei> = HashSet::new();
+    let mut used_msisdns: HashSet<Msisdn> = HashSet::new();
     let mut imsi_counter = 0u64;
+    let prefixes: Vec<u64> = parse_numeric(&config.prefixes, "prefixes")?;
+    let mccmnc_pool: Vec<Mccmnc> = parse_numeric(&config.mccmnc_pool, "mccmnc_pool")?;
+    let operators = parse_prefix_operator_map(&config.prefix_operator_map)?;
 
     let ms_per_day = 86400000i64;
 
     // Helper: generate unique MSISDN
-    let gen_msisdn = |rng: &mut StdRng, used: &HashSet<String>, prefixes: &[String]| -> String {
+    let gen_msisdn = |rng: &mut StdRng, used: &HashSet<Msisdn>| -> Result<Msisdn> {
         loop {
             let prefix = prefixes.choose(rng).unwrap();
-            let number = rng.gen_range(0..10_000_000);
-            let msisdn = format!("{}{:07}", prefix, number);
+            let number = rng.gen_range(0..10_000_000u32);
+            let msisdn = Msisdn::new(prefix * 10_000_000 + number as u64)?;
             if !used.contains(&msisdn) {
-                return msisdn;
+                return Ok(msisdn);
             }
         }
     };
 
-    // Helper: generate unique IMSI
-    let gen_imsi = |counter: &mut u64, mccmnc_pool: &[String]| -> String {
-        let mccmnc = mccmnc_pool[(*counter as usize) % mccmnc_pool.len()].to_string();
-        let msin = *counter % 10_000_000_000u64;
+    // Helper: operator of a number, bound by its prefix or drawn from the pool
+    let pick_mccmnc = |rng: &mut StdRng, msisdn: Msisdn| -> Mccmnc {
+        bound_operator(&operators, msisdn).unwrap_or_else(|| *mccmnc_pool.choose(rng).unwrap())
+    };
+
+    // Helper: generate unique IMSI under the subscriber's MCCMNC
+    let gen_imsi = |counter: &mut u64, mccmnc: Mccmnc| -> Result<Imsi> {
+        let msin = *counter % mccmnc.msin_count();
         *counter += 1;
-        format!("{}{:010}", mccmnc, msin)
+        Imsi::from_parts(mccmnc, msin)
     };
 
     // Step 1: Create initial subscribers
-    println!(
+    info!(
         "Generating {} initial subscribers...",
         config.initial_subscribers
     );
     for _ in 0..config.initial_subscribers {
-        let imsi = gen_imsi(&mut imsi_counter, &config.mccmnc_pool);
-        let msisdn = gen_msisdn(&mut rng, &used_msisdns, &config.prefixes);
+        let msisdn = gen_msisdn(&mut rng, &used_msisdns)?;
         let imei = gen_imei(&mut rng);
-        let mccmnc = config.mccmnc_pool.choose(&mut rng).unwrap().clone();
+        let mccmnc = pick_mccmnc(&mut rng, msisdn);
+        let imsi = gen_imsi(&mut imsi_counter, mccmnc)?;
 
-        used_msisdns.insert(msisdn.clone());
+        used_msisdns.insert(msisdn);
 
         events.push(SubscriberEvent {
             timestamp_ms: config.start_timestamp_ms,
             event_type: SubscriberEventType::NewSubscriber,
-            imsi: imsi.clone(),
-            msisdn: Some(msisdn.clone()),
-            imei: Some(imei.to_string()),
-            mccmnc: mccmnc.clone(),
+            imsi,
+            msisdn: Some(msisdn),
+            imei: Some(imei),
+            mccmnc,
+            sv: initial_sv(imei),
         });
 
         active_subscribers.insert(
-            imsi.clone(),
+            imsi,
             ActiveSubscriber {
                 imsi,
                 msisdn,
                 imei,
                 mccmnc,
+                sv: initial_sv(imei),
+                extra_msisdns: Vec::new(),
                 activation_time: config.start_timestamp_ms,
             },
         );
     }
 
     // Step 2: Generate events over time
-    println!("Generating historical events over {} days...", config.history_days);
+    info!("Generating historical events over {} days...", config.history_days);
 
     // Calculate daily event probabilities
     let device_change_daily_prob = 1.0 - (1.0 - config.device_change_rate).powf(1.0 / 365.0);
     let number_release_daily_prob = 1.0 - (1.0 - config.number_release_rate).powf(1.0 / 365.0);
+    let software_update_daily_prob = 1.0 - (1.0 - config.software_update_rate).powf(1.0 / 365.0);
 
     let cooldown_ms = config.cooldown_days as i64 * ms_per_day;
+    let max_dormancy_ms = config.max_dormancy_days.map(|days| days as i64 * ms_per_day);
+    let mut reassigned_numbers = 0usize;
+    let mut retired_numbers = 0usize;
+    let mut added_lines = 0usize;
 
     for day in 1..config.history_days {
         let current_time = config.start_timestamp_ms + (day as i64 * ms_per_day);
+        let current_date = DateTime::from_timestamp_millis(current_time)
+            .unwrap_or_default()
+            .date_naive();
+        let device_change_prob = (device_change_daily_prob
+            * event_multiplier(&config.device_change_seasonality, &config.device_change_special_days, current_date))
+        .min(1.0);
+        let number_release_prob = (number_release_daily_prob
+            * event_multiplier(&config.number_release_seasonality, &config.number_release_special_days, current_date))
+        .min(1.0);
+
+        let window_ms = SHARED_DEVICE_WINDOW_DAYS * ms_per_day;
+        while released_imeis.front().is_some_and(|&(_, _, t)| current_time - t > window_ms) {
+            released_imeis.pop_front();
+        }
 
         // Process device changes
-        let subscribers: Vec<String> = active_subscribers.keys().cloned().collect();
+        let subscribers: Vec<Imsi> = active_subscribers.keys().copied().collect();
+        let mut changed_devices: HashSet<Imsi> = HashSet::new();
         for imsi in &subscribers {
-            if rng.gen::<f64>() < device_change_daily_prob {
+            if rng.gen::<f64>() < device_change_prob {
+                let shared_device = if rng.gen::<f64>() < config.shared_device_rate {
+                    if !released_imeis.is_empty() && rng.gen_bool(0.5) {
+                        // Hand-me-down: a device another subscriber stopped using
+                        let idx = rng.gen_range(0..released_imeis.len());
+                        released_imeis.remove(idx).map(|(imei, sv, _)| (imei, sv))
+                    } else {
+                        // Dual-SIM: a device another subscriber is still using
+                        let other = subscribers.choose(&mut rng).unwrap();
+                        let dual_sim = active_subscribers
+                            .get(other)
+                            .filter(|o| o.imsi != *imsi && !shared_imeis.contains(&o.imei))
+                            .map(|o| (o.imei, o.sv));
+                        if let Some((imei, _)) = dual_sim {
+                            shared_imeis.insert(imei);
+                        }
+                        dual_sim
+                    }
+                } else {
+                    None
+                };
+
                 if let Some(sub) = active_subscribers.get_mut(imsi) {
-                    let new_imei = gen_imei(&mut rng);
+                    // A shared device keeps its software version
+                    let (new_imei, new_sv) = shared_device.unwrap_or_else(|| {
+                        let imei = gen_imei(&mut rng);
+                        (imei, initial_sv(imei))
+                    });
+                    if !shared_imeis.contains(&sub.imei) {
+                        released_imeis.push_back((sub.imei, sub.sv, current_time));
+                    }
                     events.push(SubscriberEvent {
                         timestamp_ms: current_time,
                         event_type: SubscriberEventType::ChangeDevice,
-                        imsi: sub.imsi.clone(),
-                        msisdn: Some(sub.msisdn.clone()),
-                        imei: Some(new_imei.to_string()),
-                        mccmnc: sub.mccmnc.clone(),
+                        imsi: sub.imsi,
+                        msisdn: Some(sub.msisdn),
+                        imei: Some(new_imei),
+                        mccmnc: sub.mccmnc,
+                        sv: new_sv,
                     });
                     sub.imei = new_imei;
+                    sub.sv = new_sv;
+                    changed_devices.insert(sub.imsi);
+                }
+            }
+        }
+
+        // Process software updates, on their own RNG. Every IMSI on the device (dual-SIM) gets
+        // the new version; a device changed today already runs the version it came with
+        for imsi in &subscribers {
+            if sv_rng.gen::<f64>() >= software_update_daily_prob || changed_devices.contains(imsi) {
+                continue;
+            }
+            let Some(sub) = active_subscribers.get(imsi) else { continue };
+            if sub.sv >= MAX_SV {
+                continue;
+            }
+            let (imei, sv) = (sub.imei, sub.sv + 1);
+            let on_device: Vec<Imsi> = if shared_imeis.contains(&imei) {
+                active_subscribers
+                    .values()
+                    .filter(|o| o.imei == imei && !changed_devices.contains(&o.imsi))
+                    .map(|o| o.imsi)
+                    .collect()
+            } else {
+                vec![*imsi]
+            };
+            for other in on_device {
+                if let Some(sub) = active_subscribers.get_mut(&other) {
+                    events.push(SubscriberEvent {
+                        timestamp_ms: current_time,
+                        event_type: SubscriberEventType::SoftwareUpdate,
+                        imsi: sub.imsi,
+                        msisdn: Some(sub.msisdn),
+                        imei: Some(imei),
+                        mccmnc: sub.mccmnc,
+                        sv,
+                    });
+                    sub.sv = sv;
                 }
             }
         }
 
         // Process number releases
-        let subscribers: Vec<String> = active_subscribers.keys().cloned().collect();
+        let subscribers: Vec<Imsi> = active_subscribers.keys().copied().collect();
         for imsi in &subscribers {
-            if rng.gen::<f64>() < number_release_daily_prob {
-                if let Some(sub) = active_subscribers.remove(imsi) {
+            if rng.gen::<f64>() < number_release_prob {
+                // A customer with several lines gives up the newest one and stays active
+                if let Some(sub) = active_subscribers.get_mut(imsi).filter(|sub| !sub.extra_msisdns.is_empty()) {
+                    let msisdn = sub.extra_msisdns.pop().unwrap();
+                    events.push(SubscriberEvent {
+                        timestamp_ms: current_time,
+                        event_type: SubscriberEventType::ReleaseNumber,
+                        imsi: sub.imsi,
+                        msisdn: Some(msisdn),
+                        imei: None,
+                        mccmnc: sub.mccmnc,
+                        sv: 0,
+                    });
+                    released_numbers.push(ReleasedNumber {
+                        msisdn,
+                        release_time: current_time,
+                    });
+                } else if let Some(sub) = active_subscribers.remove(imsi) {
                     events.push(SubscriberEvent {
                         timestamp_ms: current_time,
                         event_type: SubscriberEventType::ReleaseNumber,
-                        imsi: sub.imsi.clone(),
-                        msisdn: Some(sub.msisdn.clone()),
+                        imsi: sub.imsi,
+                        msisdn: Some(sub.msisdn),
                         imei: None,
-                        mccmnc: sub.mccmnc.clone(),
+                        mccmnc: sub.mccmnc,
+                        sv: 0,
                     });
 
+                    if !shared_imeis.contains(&sub.imei) {
+                        released_imeis.push_back((sub.imei, sub.sv, current_time));
+                    }
                     released_numbers.push(ReleasedNumber {
                         msisdn: sub.msisdn,
                         release_time: current_time,
@@ -186,39 +404,79 @@ pub fn generate_database(config: &GeneratorConfig) -> Result<Vec<SubscriberEvent
             }
         }
 
-        // Process number reassignments (after cooldown)
+        // Process number reassignments: past cooldown each number is picked up with
+        // reassignment_rate per day, and retired once it has been dormant too long
         let mut to_reassign = Vec::new();
         released_numbers.retain(|rel| {
-            if current_time - rel.release_time >= cooldown_ms {
-                to_reassign.push(rel.msisdn.clone());
+            let dormant_ms = current_time - rel.release_time;
+            if dormant_ms < cooldown_ms {
+                true
+            } else if config.reassignment_rate >= 1.0 || rng.gen::<f64>() < config.reassignment_rate {
+                to_reassign.push(rel.msisdn);
+                false
+            } else if max_dormancy_ms.is_some_and(|max| dormant_ms >= max) {
+                retired_numbers += 1;
                 false
             } else {
                 true
             }
         });
+        reassigned_numbers += to_reassign.len();
 
+        // Customers who may add a line; the RNG is only drawn from when reassign_to_existing_rate is set
+        let customers: Vec<Imsi> = if config.reassign_to_existing_rate > 0.0 && !to_reassign.is_empty() {
+            active_subscribers.keys().copied().collect()
+        } else {
+            Vec::new()
+        };
         for msisdn in to_reassign {
+            // An existing customer adds the number as a line on their IMSI and device, if it is not bound
+            // to another operator
+            if !customers.is_empty() && rng.gen::<f64>() < config.reassign_to_existing_rate {
+                let imsi = customers.choose(&mut rng).unwrap();
+                if let Some(sub) = active_subscribers
+                    .get_mut(imsi)
+                    .filter(|sub| bound_operator(&operators, msisdn).is_none_or(|mccmnc| mccmnc == sub.mccmnc))
+                {
+                    events.push(SubscriberEvent {
+                        timestamp_ms: current_time,
+                        event_type: SubscriberEventType::AssignNumber,
+                        imsi: sub.imsi,
+                        msisdn: Some(msisdn),
+                        imei: Some(sub.imei),
+                        mccmnc: sub.mccmnc,
+                        sv: sub.sv,
+                    });
+                    sub.extra_msisdns.push(msisdn);
+                    added_lines += 1;
+                    continue;
+                }
+            }
+
             // Assign to new subscriber
-            let imsi = gen_imsi(&mut imsi_counter, &config.mccmnc_pool);
             let imei = gen_imei(&mut rng);
-            let mccmnc = config.mccmnc_pool.choose(&mut rng).unwrap().clone();
+            let mccmnc = pick_mccmnc(&mut rng, msisdn);
+            let imsi = gen_imsi(&mut imsi_counter, mccmnc)?;
 
             events.push(SubscriberEvent {
                 timestamp_ms: current_time,
                 event_type: SubscriberEventType::AssignNumber,
-                imsi: imsi.clone(),
-                msisdn: Some(msisdn.clone()),
-                imei: Some(imei.to_string()),
-                mccmnc: mccmnc.clone(),
+                imsi,
+                msisdn: Some(msisdn),
+                imei: Some(imei),
+                mccmnc,
+                sv: initial_sv(imei),
             });
 
             active_subscribers.insert(
-                imsi.clone(),
+                imsi,
                 ActiveSubscriber {
                     imsi,
                     msisdn,
                     imei,
                     mccmnc,
+                    sv: initial_sv(imei),
+                    extra_msisdns: Vec::new(),
                     activation_time: current_time,
                 },
             );
@@ -227,29 +485,32 @@ pub fn generate_database(config: &GeneratorConfig) -> Result<Vec<SubscriberEvent
         // Occasionally add completely new subscribers
         if rng.gen::<f64>() < 0.01 {
             // 1% chance per day
-            let imsi = gen_imsi(&mut imsi_counter, &config.mccmnc_pool);
-            let msisdn = gen_msisdn(&mut rng, &used_msisdns, &config.prefixes);
+            let msisdn = gen_msisdn(&mut rng, &used_msisdns)?;
             let imei = gen_imei(&mut rng);
-            let mccmnc = config.mccmnc_pool.choose(&mut rng).unwrap().clone();
+            let mccmnc = pick_mccmnc(&mut rng, msisdn);
+            let imsi = gen_imsi(&mut imsi_counter, mccmnc)?;
 
-            used_msisdns.insert(msisdn.clone());
+            used_msisdns.insert(msisdn);
 
             events.push(SubscriberEvent {
                 timestamp_ms: current_time,
                 event_type: SubscriberEventType::NewSubscriber,
-                imsi: imsi.clone(),
-                msisdn: Some(msisdn.clone()),
-                imei: Some(imei.to_string()),
-                mccmnc: mccmnc.clone(),
+                imsi,
+                msisdn: Some(msisdn),
+                imei: Some(imei),
+                mccmnc,
+                sv: initial_sv(imei),
             });
 
             active_subscribers.insert(
-                imsi.clone(),
+                imsi,
                 ActiveSubscriber {
                     imsi,
                     msisdn,
                     imei,
                     mccmnc,
+                    sv: initial_sv(imei),
+                    extra_msisdns: Vec::new(),
                     activation_time: current_time,
                 },
             );
@@ -259,124 +520,198 @@ pub fn generate_database(config: &GeneratorConfig) -> Result<Vec<SubscriberEvent
     // Sort events by timestamp
     events.sort_by_key(|e| e.timestamp_ms);
 
-    println!("Generated {} events", events.len());
-    println!("Active subscribers: {}", active_subscribers.len());
-    println!("Released numbers in cooldown: {}", released_numbers.len());
+    info!("Generated {} events", events.len());
+    for (month, counts) in monthly_event_counts(&events) {
+        info!(
+            "{}: {} new, {} device changes, {} software updates, {} releases, {} reassignments",
+            month,
+            counts.new_subscribers,
+            counts.device_changes,
+            counts.software_updates,
+            counts.number_releases,
+            counts.number_assignments
+        );
+    }
+    info!(
+        "Released numbers: {} reassigned ({} as an existing customer's extra line), {} retired, {} still dormant",
+        reassigned_numbers,
+        added_lines,
+        retired_numbers,
+        released_numbers.len()
+    );
+    debug!("Active subscribers: {}", active_subscribers.len());
 
     Ok(events)
 }
 
 /// Export events to CSV file
 pub fn export_to_csv<P: AsRef<Path>>(events: &[SubscriberEvent], path: P) -> Result<()> {
-    let mut file = File::create(&path)?;
+    let mut file = BufWriter::new(File::create(&path)?);
 
     // Write header
-    writeln!(file, "timestamp_ms,event_type,imsi,msisdn,imei,mccmnc")?;
+    writeln!(file, "timestamp_ms,event_type,imsi,msisdn,imei,mccmnc,sv")?;
 
-    // Write events
+    // Write events; the software version is empty, like the IMEI, on events without a device
     for event in events {
         writeln!(
             file,
-            "{},{},{},{},{},{}",
+            "{},{},{},{},{},{},{}",
             event.timestamp_ms,
             event.event_type.to_str(),
             event.imsi,
-            event.msisdn.as_deref().unwrap_or(""),
-            event.imei.as_deref().unwrap_or(""),
-            event.mccmnc
+            event.msisdn.map(|m| m.to_string()).unwrap_or_default(),
+            event.imei.map(|i| i.to_string()).unwrap_or_default(),
+            event.mccmnc,
+            event.imei.map(|_| format!("{:02}", event.sv)).unwrap_or_default()
         )?;
     }
 
-    println!("Exported {} events to {:?}", events.len(), path.as_ref());
+    file.flush()?;
+
+    info!("Exported {} events to {:?}", events.len(), path.as_ref());
     Ok(())
 }
 
+#[cfg(feature = "arrow")]
+pub use crate::subscriber_db_arrow::{events_arrow_schema, read_events_from_arrow, write_events_to_arrow};
+
+/// File format of an event export, chosen by the file extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventExportFormat {
+    Arrow,
+    Csv,
+}
+
+impl EventExportFormat {
+    /// `.arrow` -> Arrow IPC file, `.csv` -> CSV
+    pub fn from_path(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("arrow") => Ok(EventExportFormat::Arrow),
+            Some("csv") => Ok(EventExportFormat::Csv),
+            _ => anyhow::bail!("Unsupported event export {:?}: expected a .arrow or .csv path", path),
+        }
+    }
+
+    pub fn write(&self, events: &[SubscriberEvent], path: &Path) -> Result<()> {
+        match self {
+            #[cfg(feature = "arrow")]
+            EventExportFormat::Arrow => crate::subscriber_db_arrow::write_events_to_arrow(events, path),
+            #[cfg(not(feature = "arrow"))]
+            EventExportFormat::Arrow => anyhow::bail!("Arrow event export requires building with `--features arrow`"),
+            EventExportFormat::Csv => export_to_csv(events, path),
+        }
+    }
+
+    pub fn read(&self, path: &Path) -> Result<Vec<SubscriberEvent>> {
+        match self {
+            #[cfg(feature = "arrow")]
+            EventExportFormat::Arrow => crate::subscriber_db_arrow::read_events_from_arrow(path),
+            #[cfg(not(feature = "arrow"))]
+            EventExportFormat::Arrow => anyhow::bail!("Arrow event import requires building with `--features arrow`"),
+            EventExportFormat::Csv => Ok(SubscriberDatabase::load_from_csv(path)?.events),
+        }
+    }
+}
+
 // ============================================================================
 // Direct redb generation without Arrow intermediate format
 // ============================================================================
 
 /// Generate subscriber database directly in redb format
 /// This is more efficient than Arrow->redb conversion
+#[cfg(feature = "redb")]
 pub fn generate_database_redb<P: AsRef<Path>>(
     config: &GeneratorConfig,
     output_path: P,
-) -> Result<()> {
-    use crate::subscriber_db::{SubscriberDatabase, SubscriberSnapshot};
-    use crate::subscriber_db_redb::{SubscriberDbRedb, SubscriberSnapshotNumeric};
-    use std::collections::HashMap;
-
-    println!("Generating subscriber database directly to redb format...");
-    println!("  Subscribers: {}", config.initial_subscribers);
-    println!("  History days: {}", config.history_days);
-    println!();
+    options: &DbWriteOptions,
+) -> Result<DbStats> {
+    info!(
+        subscribers = config.initial_subscribers,
+        history_days = config.history_days,
+        "Generating subscriber database directly to redb format"
+    );
 
     // Generate events using existing logic
     let events = generate_database(config)?;
+    write_database_redb(events, output_path, options)
+}
 
+/// Build snapshots from an event history and store them in a new redb database
+#[cfg(feature = "redb")]
+pub fn write_database_redb<P: AsRef<Path>>(
+    events: Vec<SubscriberEvent>,
+    output_path: P,
+    options: &DbWriteOptions,
+) -> Result<DbStats> {
     // Build in-memory database and compute snapshots
-    println!("Building snapshots from events...");
+    info!("Building snapshots from events...");
     let mut db = SubscriberDatabase::new();
     db.events = events;
     db.build_indices();
     db.build_snapshots();
+    write_snapshots_redb(db.get_snapshots(), output_path, options)
+}
+
+/// Store already built snapshots (e.g. of a repaired history) in a new redb database
+#[cfg(feature = "redb")]
+pub fn write_snapshots_redb<P: AsRef<Path>>(
+    snapshots: &[crate::subscriber_db::SubscriberSnapshot],
+    output_path: P,
+    options: &DbWriteOptions,
+) -> Result<DbStats> {
+    use crate::subscriber_db::SubscriberSnapshot;
+    use crate::subscriber_db_redb::SubscriberDbRedb;
 
-    let snapshots = db.get_snapshots();
-    println!("Total snapshots: {}", snapshots.len());
+    debug!("Total snapshots: {}", snapshots.len());
+    let earliest_snapshot_ms = snapshots.iter().map(|s| s.valid_from).min();
 
     // Group snapshots by MSISDN
-    println!("Grouping snapshots by MSISDN...");
-    let mut msisdn_snapshots: HashMap<u64, Vec<SubscriberSnapshotNumeric>> = HashMap::new();
+    debug!("Grouping snapshots by MSISDN...");
+    let mut msisdn_snapshots: HashMap<u64, Vec<SubscriberSnapshot>> = HashMap::new();
 
     for snapshot in snapshots {
-        let msisdn = snapshot.msisdn.parse::<u64>().unwrap_or(0);
-        if msisdn == 0 {
-            continue; // Skip invalid MSISDNs
-        }
-
-        let numeric_snapshot = SubscriberSnapshotNumeric::from(snapshot);
-        msisdn_snapshots.entry(msisdn)
-            .or_insert_with(Vec::new)
-            .push(numeric_snapshot);
+        msisdn_snapshots.entry(snapshot.identity.msisdn.get())
+            .or_default()
+            .push(snapshot.clone());
     }
 
-    println!("Unique MSISDNs: {}", msisdn_snapshots.len());
+    debug!("Unique MSISDNs: {}", msisdn_snapshots.len());
 
     // Create redb database and insert snapshots
-    println!("Creating redb database at {:?}...", output_path.as_ref());
+    info!("Creating redb database at {:?}...", output_path.as_ref());
     let redb = SubscriberDbRedb::new(output_path.as_ref())?;
 
-    println!("Inserting snapshots into redb (batch mode)...");
-
-    // Convert HashMap to Vec for batching
-    let all_entries: Vec<(u64, Vec<SubscriberSnapshotNumeric>)> = msisdn_snapshots.into_iter().collect();
-    let total = all_entries.len();
-
-    // Insert in batches of 10,000 for optimal performance
-    let batch_size = 10_000;
-    let mut inserted = 0;
-
-    for batch_start in (0..total).step_by(batch_size) {
-        let batch_end = (batch_start + batch_size).min(total);
-        let batch = &all_entries[batch_start..batch_end];
-
-        redb.insert_snapshots_batch(batch)?;
-        inserted += batch.len();
+    debug!(
+        batch_size = options.batch_size,
+        durability = ?options.durability,
+        "Inserting snapshots into redb (batch mode)..."
+    );
+    let mut all_entries: Vec<(u64, Vec<SubscriberSnapshot>)> = msisdn_snapshots.into_iter().collect();
+    // Inserting in key order keeps the B-tree appends sequential
+    all_entries.sort_unstable_by_key(|(msisdn, _)| *msisdn);
+    redb.insert_all(&all_entries, options)?;
 
-        if inserted % 50_000 == 0 || inserted == total {
-            println!("  Inserted {}/{} MSISDNs...", inserted, total);
-        }
+    if let Some(ts_ms) = earliest_snapshot_ms {
+        redb.set_earliest_snapshot_ms(ts_ms)?;
     }
 
-    println!("\nGeneration complete!");
-    println!("Database saved to: {:?}", output_path.as_ref());
+    info!("Database saved to: {:?}", output_path.as_ref());
 
     // Print statistics
     let stats = redb.stats()?;
-    println!("\nDatabase statistics:");
-    println!("  Total MSISDNs: {}", stats.total_msisdns);
-    println!("  Total snapshots: {}", stats.total_snapshots);
+    info!(
+        total_msisdns = stats.total_msisdns,
+        total_snapshots = stats.total_snapshots,
+        "Database statistics"
+    );
 
-    Ok(())
+    if options.compact {
+        drop(redb);
+        let report = compact_database(output_path.as_ref(), DEFAULT_SPOT_CHECKS, 0)?;
+        info!("Compacted redb database: {}", report.summary());
+    }
+
+    Ok(stats)
 }
 
 #[cfg(test)]
@@ -396,6 +731,7 @@ mod tests {
             mccmnc_pool: vec!["20408".to_string()],
             seed: 42,
             start_timestamp_ms: 1704067200000,
+            ..GeneratorConfig::default()
         };
 
         let events = generate_database(&config).unwrap();
@@ -408,24 +744,290 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_device_change_seasonality() {
+        // February device changes are three times as likely as January ones
+        let config = GeneratorConfig {
+            initial_subscribers: 20_000,
+            history_days: 60,
+            device_change_rate: 0.3,
+            number_release_rate: 0.0,
+            device_change_seasonality: HashMap::from([(2, 3.0)]),
+            prefixes: vec!["31612".to_string()],
+            mccmnc_pool: vec!["20408".to_string()],
+            ..GeneratorConfig::default()
+        };
+
+        let counts = monthly_event_counts(&generate_database(&config).unwrap());
+        // Day 0 has no events, so January covers 30 days and February 29
+        let jan = counts["2024-01"].device_changes as f64 / 30.0;
+        let feb = counts["2024-02"].device_changes as f64 / 29.0;
+        let ratio = feb / jan;
+        assert!((2.5..3.5).contains(&ratio), "Feb/Jan device change ratio {}", ratio);
+    }
+
+    #[test]
+    fn test_shared_devices() {
+        let config = GeneratorConfig {
+            initial_subscribers: 2_000,
+            history_days: 180,
+            device_change_rate: 0.5,
+            number_release_rate: 0.1,
+            cooldown_days: 30,
+            shared_device_rate: 0.5,
+            prefixes: vec!["31612".to_string()],
+            mccmnc_pool: vec!["20408".to_string()],
+            ..GeneratorConfig::default()
+        };
+        let events = generate_database(&config).unwrap();
+
+        // Some IMEIs end up with more than one IMSI over the history
+        let mut imsis_per_imei: HashMap<Imei, HashSet<Imsi>> = HashMap::new();
+        for event in &events {
+            if let Some(imei) = event.imei {
+                imsis_per_imei.entry(imei).or_default().insert(event.imsi);
+            }
+        }
+        assert!(imsis_per_imei.values().any(|imsis| imsis.len() > 1));
+
+        let mut db = SubscriberDatabase::new();
+        db.events = events;
+        db.build_indices();
+        // Dual-SIM devices are used by two IMSIs at once, never more
+        assert!(db.validate().is_ok());
+        assert!(db.validate_with(1).is_err());
+    }
+
+    #[test]
+    fn test_software_updates() {
+        let config = GeneratorConfig {
+            initial_subscribers: 1_000,
+            history_days: 180,
+            device_change_rate: 0.3,
+            shared_device_rate: 0.3,
+            software_update_rate: 0.8,
+            cooldown_days: 30,
+            ..GeneratorConfig::default()
+        };
+        let events = generate_database(&config).unwrap();
+
+        // Each update keeps the device and moves its version up by one
+        let mut current: HashMap<Imsi, (Imei, u8)> = HashMap::new();
+        let mut updates = 0;
+        for event in &events {
+            match event.event_type {
+                SubscriberEventType::SoftwareUpdate => {
+                    let (imei, sv) = current[&event.imsi];
+                    assert_eq!((event.imei, event.sv), (Some(imei), sv + 1), "{:?}", event);
+                    updates += 1;
+                }
+                SubscriberEventType::ReleaseNumber => {
+                    current.remove(&event.imsi);
+                    continue;
+                }
+                _ => {}
+            }
+            current.insert(event.imsi, (event.imei.unwrap(), event.sv));
+        }
+        // About 1 - 0.2^(180/365) of 1000 devices, most of them once
+        assert!((450..900).contains(&updates), "{} updates", updates);
+
+        let mut db = SubscriberDatabase::new();
+        db.events = events.clone();
+        db.build_indices();
+        assert!(db.validate().is_ok());
+
+        // Updates draw from their own RNG: without them the other events are the same
+        let without = generate_database(&GeneratorConfig { software_update_rate: 0.0, ..config }).unwrap();
+        let strip = |events: &[SubscriberEvent]| -> Vec<SubscriberEvent> {
+            events
+                .iter()
+                .filter(|e| e.event_type != SubscriberEventType::SoftwareUpdate)
+                .map(|e| SubscriberEvent { sv: 0, ..e.clone() })
+                .collect()
+        };
+        assert_eq!(strip(&events), strip(&without));
+    }
+
+    #[test]
+    fn test_reassignment_rate_spreads_and_retires_numbers() {
+        let config = GeneratorConfig {
+            initial_subscribers: 5_000,
+            history_days: 365,
+            number_release_rate: 0.3,
+            cooldown_days: 30,
+            reassignment_rate: 0.05,
+            max_dormancy_days: Some(60),
+            prefixes: vec!["31612".to_string()],
+            mccmnc_pool: vec!["20408".to_string()],
+            ..GeneratorConfig::default()
+        };
+        let events = generate_database(&config).unwrap();
+        let end = config.start_timestamp_ms + config.history_days as i64 * 86_400_000;
+
+        // Days from each release to the reassignment of the same number
+        let mut released_at: HashMap<Msisdn, i64> = HashMap::new();
+        let mut gaps = Vec::new();
+        for event in &events {
+            match event.event_type {
+                SubscriberEventType::ReleaseNumber => {
+                    released_at.insert(event.msisdn.unwrap(), event.timestamp_ms);
+                }
+                SubscriberEventType::AssignNumber => {
+                    let release = released_at.remove(&event.msisdn.unwrap()).unwrap();
+                    gaps.push((event.timestamp_ms - release) / 86_400_000);
+                }
+                _ => {}
+            }
+        }
+        assert!(gaps.len() > 50, "{} reassignments", gaps.len());
+        assert!(gaps.iter().all(|&gap| (30..=60).contains(&gap)), "{:?}", gaps);
+        let on_expiry = gaps.iter().filter(|&&gap| gap == 30).count();
+        assert!(on_expiry * 4 < gaps.len(), "{} of {} on the expiry day", on_expiry, gaps.len());
+
+        // About 0.95^30 of the numbers past max dormancy were retired instead
+        let retired = released_at.values().filter(|&&release| release + 61 * 86_400_000 <= end).count();
+        assert!(retired > gaps.len() / 8, "{} retired, {} reassigned", retired, gaps.len());
+
+        let mut db = SubscriberDatabase::new();
+        db.events = events;
+        db.build_indices();
+        assert!(db.validate().is_ok());
+    }
+
+    #[test]
+    fn test_reassign_to_existing_adds_second_lines() {
+        let config = GeneratorConfig {
+            initial_subscribers: 2_000,
+            history_days: 180,
+            number_release_rate: 0.4,
+            cooldown_days: 10,
+            reassign_to_existing_rate: 0.5,
+            prefixes: vec!["31612".to_string()],
+            mccmnc_pool: vec!["20408".to_string()],
+            ..GeneratorConfig::default()
+        };
+        // Share of reassignments that go to an IMSI seen before
+        let to_existing = |events: &[SubscriberEvent]| {
+            let mut seen = HashSet::new();
+            let (mut existing, mut assignments) = (0, 0);
+            for event in events {
+                if event.event_type == SubscriberEventType::AssignNumber {
+                    assignments += 1;
+                    existing += usize::from(seen.contains(&event.imsi));
+                }
+                seen.insert(event.imsi);
+            }
+            (existing, assignments)
+        };
+        let events = generate_database(&config).unwrap();
+        let (existing, assignments) = to_existing(&events);
+        assert!(assignments > 100, "{} reassignments", assignments);
+        let share = existing as f64 / assignments as f64;
+        assert!((0.4..0.6).contains(&share), "{} of {} to existing IMSIs", existing, assignments);
+
+        let mut db = SubscriberDatabase::new();
+        db.events = events;
+        db.build_indices();
+        assert!(db.validate_with(1).is_ok());
+        // Some IMSIs end the history holding two numbers on one device
+        db.build_snapshots();
+        let mut open_lines: HashMap<Imsi, Vec<Imei>> = HashMap::new();
+        for snapshot in db.get_snapshots().iter().filter(|s| s.valid_to.is_none()) {
+            open_lines.entry(snapshot.identity.imsi).or_default().push(snapshot.identity.imei);
+        }
+        assert!(open_lines.values().any(|imeis| imeis.len() > 1));
+        assert!(open_lines.values().all(|imeis| imeis.iter().all(|&imei| imei == imeis[0])));
+
+        // Without the rate every number goes to a new IMSI
+        let (existing, _) = to_existing(&generate_database(&GeneratorConfig { reassign_to_existing_rate: 0.0, ..config }).unwrap());
+        assert_eq!(existing, 0);
+    }
+
+    #[test]
+    fn test_max_dormancy_shorter_than_cooldown_is_rejected() {
+        let config = GeneratorConfig { cooldown_days: 90, max_dormancy_days: Some(30), ..GeneratorConfig::default() };
+        assert!(generate_database(&config).is_err());
+    }
+
+    #[test]
+    fn test_special_day_multiplier() {
+        let seasonality = HashMap::from([(9, 2.0)]);
+        let special_days = HashMap::from([("2024-09-20".to_string(), 5.0)]);
+        let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+
+        assert_eq!(event_multiplier(&seasonality, &special_days, date("2024-08-20")), 1.0);
+        assert_eq!(event_multiplier(&seasonality, &special_days, date("2024-09-19")), 2.0);
+        assert_eq!(event_multiplier(&seasonality, &special_days, date("2024-09-20")), 10.0);
+    }
+
     #[test]
     fn test_export_csv() {
         let events = vec![
             SubscriberEvent {
                 timestamp_ms: 1704067200000,
                 event_type: SubscriberEventType::NewSubscriber,
-                imsi: "204081234567890".to_string(),
-                msisdn: Some("31612345678".to_string()),
-                imei: Some("123456789012345".to_string()),
-                mccmnc: "20408".to_string(),
+                imsi: "204081234567890".parse().unwrap(),
+                msisdn: Some("31612345678".parse().unwrap()),
+                imei: Some("012345678901237".parse().unwrap()),
+                mccmnc: "20408".parse().unwrap(),
+                sv: 4,
             },
         ];
 
         let file = NamedTempFile::new().unwrap();
         export_to_csv(&events, file.path()).unwrap();
 
-        // Verify file was created
-        let metadata = std::fs::metadata(file.path()).unwrap();
-        assert!(metadata.len() > 0);
+        // The leading zero of the IMEI survives the export and the reload
+        let csv = std::fs::read_to_string(file.path()).unwrap();
+        assert!(csv.contains(",31612345678,012345678901237,20408,04"), "{}", csv);
+        assert_eq!(EventExportFormat::Csv.read(file.path()).unwrap(), events);
+    }
+
+    #[test]
+    fn test_prefix_operator_map_holds_for_every_subscriber() {
+        let config = GeneratorConfig {
+            initial_subscribers: 500,
+            history_days: 120,
+            number_release_rate: 0.2,
+            cooldown_days: 10,
+            prefixes: vec!["31612".to_string(), "31613".to_string(), "31614".to_string()],
+            mccmnc_pool: vec!["20408".to_string(), "20416".to_string()],
+            prefix_operator_map: BTreeMap::from([
+                ("31612".to_string(), "20408".to_string()),
+                ("31613".to_string(), "20416".to_string()),
+            ]),
+            ..GeneratorConfig::default()
+        };
+        let events = generate_database(&config).unwrap();
+
+        let mut reassigned = 0;
+        let mut unmapped = HashSet::new();
+        for event in &events {
+            assert!(event.imsi.has_mccmnc(event.mccmnc), "{:?}", event);
+            let Some(msisdn) = event.msisdn else { continue };
+            match msisdn.get() / 10_000_000 {
+                31612 => assert_eq!(event.mccmnc.get(), 20408, "{:?}", event),
+                31613 => assert_eq!(event.mccmnc.get(), 20416, "{:?}", event),
+                _ => {
+                    unmapped.insert(event.mccmnc.get());
+                }
+            }
+            reassigned += usize::from(event.event_type == SubscriberEventType::AssignNumber);
+        }
+        assert!(reassigned > 0);
+        assert_eq!(unmapped.len(), 2, "{:?}", unmapped);
+    }
+
+    #[test]
+    fn test_generate_database_reproducible_for_seed() {
+        let config = GeneratorConfig {
+            initial_subscribers: 300,
+            history_days: 90,
+            shared_device_rate: 0.3,
+            cooldown_days: 10,
+            ..GeneratorConfig::default()
+        };
+        assert_eq!(generate_database(&config).unwrap(), generate_database(&config).unwrap());
     }
 }
@@ -1,74 +1,196 @@
 use anyhow::{Context, Result};
 use bincode::{deserialize, serialize};
-use redb::{Database, ReadableTable, ReadableTableMetadata, TableDefinition};
+use redb::{Database, Durability, ReadableTable, ReadableTableMetadata, TableDefinition, TableError};
+use chrono::{DateTime, Datelike, Timelike};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::Path;
+use tracing::debug;
 
-use crate::subscriber_db::SubscriberSnapshot;
+use crate::identity::{Imei, Imsi, Mccmnc, Msisdn, SubscriberIdentity, MAX_SV};
+use crate::subscriber_db::{check_imei_sharing, ImeiUsage, SubscriberSnapshot};
 
-/// Numeric version of SubscriberSnapshot for efficient storage and lookup
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SubscriberSnapshotNumeric {
-    pub imsi: u64,
-    pub msisdn: u64,
-    pub imei: u64,
-    pub mccmnc: u32,
-    pub valid_from: i64,
-    pub valid_to: Option<i64>,
+/// Table: MSISDN -> Vec<SubscriberSnapshot>
+/// Stores all historical snapshots for each MSISDN
+const SNAPSHOTS: TableDefinition<u64, &[u8]> = TableDefinition::new("snapshots");
+
+/// Table: key -> value, facts about the database as a whole
+const METADATA: TableDefinition<&str, i64> = TableDefinition::new("metadata");
+
+/// `valid_from` of the earliest snapshot, i.e. the generator start timestamp
+const EARLIEST_SNAPSHOT_MS: &str = "earliest_snapshot_ms";
+
+/// Layout of the snapshot values; absent in databases written before the software version
+const SNAPSHOT_FORMAT: &str = "snapshot_format";
+
+/// Snapshot values without the device software version, which reads as 00
+pub const SNAPSHOT_FORMAT_LEGACY: i64 = 1;
+
+/// Snapshot values with `SubscriberIdentity::sv`
+pub const SNAPSHOT_FORMAT_CURRENT: i64 = 2;
+
+/// Snapshot value of `SNAPSHOT_FORMAT_LEGACY` databases
+#[derive(Serialize, Deserialize)]
+struct LegacySnapshot {
+    imsi: Imsi,
+    msisdn: Msisdn,
+    imei: Imei,
+    mccmnc: Mccmnc,
+    valid_from: i64,
+    valid_to: Option<i64>,
+}
+
+/// Serialize snapshots in the value layout of `format`
+pub fn encode_snapshots(snapshots: &[SubscriberSnapshot], format: i64) -> Result<Vec<u8>> {
+    let encoded = if format == SNAPSHOT_FORMAT_LEGACY {
+        let legacy: Vec<LegacySnapshot> = snapshots
+            .iter()
+            .map(|s| LegacySnapshot {
+                imsi: s.identity.imsi,
+                msisdn: s.identity.msisdn,
+                imei: s.identity.imei,
+                mccmnc: s.identity.mccmnc,
+                valid_from: s.valid_from,
+                valid_to: s.valid_to,
+            })
+            .collect();
+        serialize(&legacy)
+    } else {
+        serialize(snapshots)
+    };
+    encoded.context("Failed to serialize snapshots")
+}
+
+/// Deserialize snapshots stored in the value layout of `format`
+pub fn decode_snapshots(bytes: &[u8], format: i64) -> Result<Vec<SubscriberSnapshot>> {
+    if format == SNAPSHOT_FORMAT_LEGACY {
+        let legacy: Vec<LegacySnapshot> = deserialize(bytes).context("Failed to deserialize snapshots")?;
+        Ok(legacy
+            .into_iter()
+            .map(|s| SubscriberSnapshot {
+                identity: SubscriberIdentity { imsi: s.imsi, msisdn: s.msisdn, imei: s.imei, mccmnc: s.mccmnc, sv: 0 },
+                valid_from: s.valid_from,
+                valid_to: s.valid_to,
+            })
+            .collect())
+    } else {
+        deserialize(bytes).context("Failed to deserialize snapshots")
+    }
+}
+
+/// `SNAPSHOT_FORMAT` from the metadata table, None when not recorded
+fn stored_format(db: &Database) -> Result<Option<i64>> {
+    let read_txn = db.begin_read()?;
+    let table = match read_txn.open_table(METADATA) {
+        Ok(table) => table,
+        Err(TableError::TableDoesNotExist(_)) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(table.get(SNAPSHOT_FORMAT)?.map(|v| v.value()))
+}
+
+/// Whether the snapshots table holds any MSISDN
+fn has_snapshots(db: &Database) -> Result<bool> {
+    let read_txn = db.begin_read()?;
+    match read_txn.open_table(SNAPSHOTS) {
+        Ok(table) => Ok(!table.is_empty()?),
+        Err(TableError::TableDoesNotExist(_)) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
 }
 
-impl From<&SubscriberSnapshot> for SubscriberSnapshotNumeric {
-    fn from(snapshot: &SubscriberSnapshot) -> Self {
-        SubscriberSnapshotNumeric {
-            imsi: snapshot.imsi.parse::<u64>().unwrap_or(0),
-            msisdn: snapshot.msisdn.parse::<u64>().unwrap_or(0),
-            imei: snapshot.imei.parse::<u64>().unwrap_or(0),
-            mccmnc: snapshot.mccmnc.parse::<u32>().unwrap_or(0),
-            valid_from: snapshot.valid_from,
-            valid_to: snapshot.valid_to,
+/// How bulk-load commits reach the disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DbDurability {
+    /// Batches commit without fsync (redb `Eventual`); one `Immediate` commit at the end
+    Fast,
+    /// Every batch is fsync-ed on commit (redb `Immediate`)
+    #[default]
+    Safe,
+}
+
+impl DbDurability {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "fast" => Some(DbDurability::Fast),
+            "safe" => Some(DbDurability::Safe),
+            _ => None,
         }
     }
 }
 
-/// Table: MSISDN -> Vec<SubscriberSnapshot>
-/// Stores all historical snapshots for each MSISDN
-const SNAPSHOTS: TableDefinition<u64, &[u8]> = TableDefinition::new("snapshots");
+/// Bulk-load settings for `SubscriberDbRedb::insert_all`
+#[derive(Debug, Clone, Copy)]
+pub struct DbWriteOptions {
+    /// MSISDNs per write transaction
+    pub batch_size: usize,
+    pub durability: DbDurability,
+    /// Compact the file once loaded (see `compact_database`)
+    pub compact: bool,
+}
+
+impl Default for DbWriteOptions {
+    fn default() -> Self {
+        DbWriteOptions { batch_size: 10_000, durability: DbDurability::Safe, compact: false }
+    }
+}
+
+/// MSISDNs compared before and after compaction when not configured
+pub const DEFAULT_SPOT_CHECKS: usize = 1_000;
 
 /// Embedded redb-based subscriber database for chunked processing
 ///
 /// Architecture:
 /// - Key = MSISDN (u64)
-/// - Value = Vec<SubscriberSnapshot> serialized with bincode
+/// - Value = Vec<SubscriberSnapshot> serialized with bincode, in the layout of `snapshot_format()`
 /// - Supports efficient range queries for chunk loading
 /// - Supports O(1) lookup by MSISDN for MT generation
 pub struct SubscriberDbRedb {
     db: Database,
+    format: i64,
 }
 
 impl SubscriberDbRedb {
-    /// Create or open a redb database at the given path
+    /// Create or open a redb database at the given path.
+    /// A new database stores `SNAPSHOT_FORMAT_CURRENT`; an existing one keeps its format
     pub fn new(path: &Path) -> Result<Self> {
         let db = Database::create(path).context("Failed to create redb database")?;
-        Ok(Self { db })
+        let format = match stored_format(&db)? {
+            Some(format) => format,
+            None if has_snapshots(&db)? => SNAPSHOT_FORMAT_LEGACY,
+            None => {
+                let write_txn = db.begin_write()?;
+                write_txn.open_table(METADATA)?.insert(SNAPSHOT_FORMAT, SNAPSHOT_FORMAT_CURRENT)?;
+                write_txn.commit()?;
+                SNAPSHOT_FORMAT_CURRENT
+            }
+        };
+        Ok(Self { db, format })
     }
 
     /// Open an existing redb database (read-only)
     pub fn open(path: &Path) -> Result<Self> {
         let db = Database::open(path).context("Failed to open redb database")?;
-        Ok(Self { db })
+        let format = stored_format(&db)?.unwrap_or(SNAPSHOT_FORMAT_LEGACY);
+        Ok(Self { db, format })
+    }
+
+    /// Layout of the snapshot values: `SNAPSHOT_FORMAT_LEGACY` or `SNAPSHOT_FORMAT_CURRENT`
+    pub fn snapshot_format(&self) -> i64 {
+        self.format
     }
 
     /// Insert or update snapshots for a given MSISDN
     /// This appends new snapshots to the existing list
-    pub fn insert_snapshots(&self, msisdn: u64, snapshots: &[SubscriberSnapshotNumeric]) -> Result<()> {
+    pub fn insert_snapshots(&self, msisdn: u64, snapshots: &[SubscriberSnapshot]) -> Result<()> {
         let write_txn = self.db.begin_write()?;
         {
             let mut table = write_txn.open_table(SNAPSHOTS)?;
 
             // Check if MSISDN already has snapshots
             let mut all_snapshots = if let Some(existing) = table.get(msisdn)? {
-                let bytes = existing.value();
-                deserialize::<Vec<SubscriberSnapshotNumeric>>(bytes)
+                decode_snapshots(existing.value(), self.format)
                     .context("Failed to deserialize existing snapshots")?
             } else {
                 Vec::new()
@@ -78,8 +200,7 @@ impl SubscriberDbRedb {
             all_snapshots.extend_from_slice(snapshots);
 
             // Serialize and store
-            let serialized = serialize(&all_snapshots)
-                .context("Failed to serialize snapshots")?;
+            let serialized = encode_snapshots(&all_snapshots, self.format)?;
             table.insert(msisdn, serialized.as_slice())?;
         }
         write_txn.commit()?;
@@ -87,15 +208,43 @@ impl SubscriberDbRedb {
     }
 
     /// Batch insert snapshots for multiple MSISDNs in a single transaction (much faster)
-    pub fn insert_snapshots_batch(&self, batch: &[(u64, Vec<SubscriberSnapshotNumeric>)]) -> Result<()> {
-        let write_txn = self.db.begin_write()?;
+    pub fn insert_snapshots_batch(&self, batch: &[(u64, Vec<SubscriberSnapshot>)]) -> Result<()> {
+        self.insert_batch_with(batch, Durability::Immediate)
+    }
+
+    /// Bulk load in transactions of `options.batch_size` MSISDNs.
+    /// With `DbDurability::Fast` everything is made durable by a final `Immediate` commit
+    pub fn insert_all(&self, entries: &[(u64, Vec<SubscriberSnapshot>)], options: &DbWriteOptions) -> Result<()> {
+        let durability = match options.durability {
+            DbDurability::Fast => Durability::Eventual,
+            DbDurability::Safe => Durability::Immediate,
+        };
+        let total = entries.len();
+        let mut inserted = 0;
+        for batch in entries.chunks(options.batch_size.max(1)) {
+            self.insert_batch_with(batch, durability)?;
+            inserted += batch.len();
+            if inserted % 50_000 < batch.len() || inserted == total {
+                debug!("Inserted {}/{} MSISDNs...", inserted, total);
+            }
+        }
+        if options.durability == DbDurability::Fast {
+            let mut write_txn = self.db.begin_write()?;
+            write_txn.set_durability(Durability::Immediate);
+            write_txn.commit()?;
+        }
+        Ok(())
+    }
+
+    fn insert_batch_with(&self, batch: &[(u64, Vec<SubscriberSnapshot>)], durability: Durability) -> Result<()> {
+        let mut write_txn = self.db.begin_write()?;
+        write_txn.set_durability(durability);
         {
             let mut table = write_txn.open_table(SNAPSHOTS)?;
 
             for (msisdn, snapshots) in batch {
                 // Serialize and store (assuming no existing data for bulk import)
-                let serialized = serialize(snapshots)
-                    .context("Failed to serialize snapshots")?;
+                let serialized = encode_snapshots(snapshots, self.format)?;
                 table.insert(*msisdn, serialized.as_slice())?;
             }
         }
@@ -103,16 +252,86 @@ impl SubscriberDbRedb {
         Ok(())
     }
 
+    /// Record the `valid_from` of the earliest snapshot
+    pub fn set_earliest_snapshot_ms(&self, ts_ms: i64) -> Result<()> {
+        let write_txn = self.db.begin_write()?;
+        {
+            let mut table = write_txn.open_table(METADATA)?;
+            table.insert(EARLIEST_SNAPSHOT_MS, ts_ms)?;
+        }
+        write_txn.commit()?;
+        Ok(())
+    }
+
+    /// `valid_from` of the earliest snapshot; None for databases written before the metadata table
+    pub fn earliest_snapshot_ms(&self) -> Result<Option<i64>> {
+        let read_txn = self.db.begin_read()?;
+        let table = match read_txn.open_table(METADATA) {
+            Ok(table) => table,
+            Err(TableError::TableDoesNotExist(_)) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(table.get(EARLIEST_SNAPSHOT_MS)?.map(|v| v.value()))
+    }
+
+    /// Every entry of the metadata table, empty for databases written before it
+    pub fn metadata(&self) -> Result<BTreeMap<String, i64>> {
+        let read_txn = self.db.begin_read()?;
+        let table = match read_txn.open_table(METADATA) {
+            Ok(table) => table,
+            Err(TableError::TableDoesNotExist(_)) => return Ok(BTreeMap::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut entries = BTreeMap::new();
+        for entry in table.iter()? {
+            let (key, value) = entry?;
+            entries.insert(key.value().to_string(), value.value());
+        }
+        Ok(entries)
+    }
+
+    /// Compact the file in place; false when there was nothing to reclaim
+    pub fn compact(&mut self) -> Result<bool> {
+        self.db.compact().context("Failed to compact redb database")
+    }
+
+    /// Whether `msisdn` is a key of the database
+    pub fn contains_msisdn(&self, msisdn: u64) -> Result<bool> {
+        let read_txn = self.db.begin_read()?;
+        let table = match read_txn.open_table(SNAPSHOTS) {
+            Ok(table) => table,
+            Err(TableError::TableDoesNotExist(_)) => return Ok(false),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(table.get(msisdn)?.is_some())
+    }
+
+    /// The key closest to `msisdn` (itself when present), from one range scan on each side; the lower key
+    /// wins a tie. None for an empty database
+    pub fn get_nearest_key(&self, msisdn: u64) -> Result<Option<u64>> {
+        let read_txn = self.db.begin_read()?;
+        let table = match read_txn.open_table(SNAPSHOTS) {
+            Ok(table) => table,
+            Err(TableError::TableDoesNotExist(_)) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let below = table.range(..=msisdn)?.next_back().transpose()?.map(|(key, _)| key.value());
+        let above = table.range(msisdn..)?.next().transpose()?.map(|(key, _)| key.value());
+        Ok(match (below, above) {
+            (Some(below), Some(above)) if above - msisdn < msisdn - below => Some(above),
+            (Some(below), _) => Some(below),
+            (None, above) => above,
+        })
+    }
+
     /// Get the subscriber snapshot valid at the given timestamp
     /// Returns None if MSISDN not found or no valid snapshot at that time
-    pub fn get_subscriber_at(&self, msisdn: u64, timestamp: i64) -> Result<Option<SubscriberSnapshotNumeric>> {
+    pub fn get_subscriber_at(&self, msisdn: u64, timestamp: i64) -> Result<Option<SubscriberSnapshot>> {
         let read_txn = self.db.begin_read()?;
         let table = read_txn.open_table(SNAPSHOTS)?;
 
         if let Some(value) = table.get(msisdn)? {
-            let bytes = value.value();
-            let snapshots: Vec<SubscriberSnapshotNumeric> = deserialize(bytes)
-                .context("Failed to deserialize snapshots")?;
+            let snapshots = decode_snapshots(value.value(), self.format)?;
 
             // Find snapshot valid at timestamp
             for snapshot in snapshots {
@@ -129,12 +348,12 @@ impl SubscriberDbRedb {
     }
 
     /// Load a chunk of subscribers by MSISDN range [start_msisdn, end_msisdn)
-    /// Returns a list of (MSISDN, Vec<SubscriberSnapshotNumeric>)
+    /// Returns a list of (MSISDN, Vec<SubscriberSnapshot>)
     pub fn load_chunk(
         &self,
         start_msisdn: u64,
         end_msisdn: u64,
-    ) -> Result<Vec<(u64, Vec<SubscriberSnapshotNumeric>)>> {
+    ) -> Result<Vec<(u64, Vec<SubscriberSnapshot>)>> {
         let read_txn = self.db.begin_read()?;
         let table = read_txn.open_table(SNAPSHOTS)?;
 
@@ -143,9 +362,7 @@ impl SubscriberDbRedb {
         // Range scan from start_msisdn to end_msisdn
         for entry in table.range(start_msisdn..end_msisdn)? {
             let (msisdn, value) = entry?;
-            let bytes = value.value();
-            let snapshots: Vec<SubscriberSnapshotNumeric> = deserialize(bytes)
-                .context("Failed to deserialize snapshots")?;
+            let snapshots = decode_snapshots(value.value(), self.format)?;
 
             result.push((msisdn.value(), snapshots));
         }
@@ -153,6 +370,20 @@ impl SubscriberDbRedb {
         Ok(result)
     }
 
+    /// Snapshots of each of `msisdns` present in the database, in one transaction
+    pub fn load_msisdns(&self, msisdns: &[u64]) -> Result<Vec<(u64, Vec<SubscriberSnapshot>)>> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(SNAPSHOTS)?;
+
+        let mut result = Vec::with_capacity(msisdns.len());
+        for &msisdn in msisdns {
+            if let Some(value) = table.get(msisdn)? {
+                result.push((msisdn, decode_snapshots(value.value(), self.format)?));
+            }
+        }
+        Ok(result)
+    }
+
     /// Get the total number of MSISDNs (subscribers) in the database
     /// This is faster than stats() as it doesn't deserialize snapshots
     pub fn count_msisdns(&self) -> Result<usize> {
@@ -166,9 +397,9 @@ impl SubscriberDbRedb {
     /// This is much faster than get_subscriber_at() for repeated lookups
     /// Returns None if no valid snapshot found at that timestamp
     pub fn find_snapshot_at(
-        snapshots: &[SubscriberSnapshotNumeric],
+        snapshots: &[SubscriberSnapshot],
         timestamp: i64,
-    ) -> Option<&SubscriberSnapshotNumeric> {
+    ) -> Option<&SubscriberSnapshot> {
         // Linear search through snapshots (they're sorted by valid_from)
         // For small lists (typically 1-3 snapshots per subscriber), linear is faster than binary
         for snapshot in snapshots {
@@ -182,41 +413,517 @@ impl SubscriberDbRedb {
         None
     }
 
-    /// Get statistics about the database
-    pub fn stats(&self) -> Result<DbStats> {
+    /// Snapshot for a day `[day_start, day_end)`: the one valid at `day_start`, else the first that
+    /// starts during the day (a mid-day activation, from its `valid_from` on)
+    pub fn find_snapshot_in_day(
+        snapshots: &[SubscriberSnapshot],
+        day_start: i64,
+        day_end: i64,
+    ) -> Option<&SubscriberSnapshot> {
+        Self::find_snapshot_at(snapshots, day_start)
+            .or_else(|| snapshots.iter().find(|snapshot| snapshot.valid_from > day_start && snapshot.valid_from < day_end))
+    }
+
+    /// Stream over all MSISDNs in key order without loading the whole table
+    pub fn iter_all<F>(&self, mut f: F) -> Result<()>
+    where
+        F: FnMut(u64, Vec<SubscriberSnapshot>) -> Result<()>,
+    {
         let read_txn = self.db.begin_read()?;
         let table = read_txn.open_table(SNAPSHOTS)?;
 
-        let mut total_msisdns = 0u64;
-        let mut total_snapshots = 0u64;
-
         for entry in table.iter()? {
-            let (_, value) = entry?;
-            let bytes = value.value();
-            let snapshots: Vec<SubscriberSnapshotNumeric> = deserialize(bytes)?;
-
-            total_msisdns += 1;
-            total_snapshots += snapshots.len() as u64;
+            let (msisdn, value) = entry?;
+            let snapshots = decode_snapshots(value.value(), self.format)?;
+            f(msisdn.value(), snapshots)?;
         }
 
-        Ok(DbStats {
-            total_msisdns,
-            total_snapshots,
+        Ok(())
+    }
+
+    /// Check that no IMEI is used by more than `max_imsis_per_imei` IMSIs at the same time
+    /// and that every software version is in range (00-98)
+    pub fn validate(&self, max_imsis_per_imei: usize) -> Result<()> {
+        let mut usages = Vec::new();
+        self.iter_all(|msisdn, snapshots| {
+            if let Some(s) = snapshots.iter().find(|s| s.identity.sv > MAX_SV) {
+                anyhow::bail!("MSISDN {}: software version {} of IMEI {} is out of range", msisdn, s.identity.sv, s.identity.imei);
+            }
+            usages.extend(snapshots.into_iter().map(|s| ImeiUsage {
+                imei: s.identity.imei,
+                imsi: s.identity.imsi,
+                from: s.valid_from,
+                to: s.valid_to,
+            }));
+            Ok(())
+        })?;
+        check_imei_sharing(usages, max_imsis_per_imei)
+    }
+
+    /// Stricter check for imported histories: the windows of each MSISDN have a positive length and
+    /// do not overlap
+    pub fn validate_windows(&self) -> Result<()> {
+        self.iter_all(|msisdn, mut snapshots| {
+            snapshots.sort_by_key(|s| s.valid_from);
+            if let Some(s) = snapshots.iter().find(|s| s.valid_to.is_some_and(|to| to <= s.valid_from)) {
+                anyhow::bail!("MSISDN {}: window of {} from {} has no positive length ({:?})", msisdn, s.identity.imsi, s.valid_from, s.valid_to);
+            }
+            if let Some(pair) = snapshots.windows(2).find(|pair| pair[0].valid_to.is_none_or(|to| to > pair[1].valid_from)) {
+                anyhow::bail!(
+                    "MSISDN {}: window of {} from {} overlaps the window of {} from {}",
+                    msisdn,
+                    pair[0].identity.imsi,
+                    pair[0].valid_from,
+                    pair[1].identity.imsi,
+                    pair[1].valid_from
+                );
+            }
+            Ok(())
         })
     }
+
+    /// Get statistics about the database (single streaming pass)
+    pub fn stats(&self) -> Result<DbStats> {
+        let mut stats = DbStats::default();
+        // Month index -> change in active snapshots at the start of that month
+        let mut active_deltas: BTreeMap<i32, i64> = BTreeMap::new();
+        let mut validity_counts = [0u64; VALIDITY_BUCKETS.len()];
+
+        self.iter_all(|_, mut snapshots| {
+            snapshots.sort_by_key(|s| s.valid_from);
+
+            stats.total_msisdns += 1;
+            stats.total_snapshots += snapshots.len() as u64;
+            *stats.snapshots_per_msisdn.entry(snapshots.len()).or_default() += 1;
+
+            for snapshot in &snapshots {
+                *active_deltas.entry(first_month_at_or_after(snapshot.valid_from)).or_default() += 1;
+                match snapshot.valid_to {
+                    Some(valid_to) => {
+                        *active_deltas.entry(first_month_at_or_after(valid_to)).or_default() -= 1;
+                        let days = (valid_to - snapshot.valid_from) / MS_PER_DAY;
+                        let bucket = VALIDITY_BUCKETS
+                            .iter()
+                            .position(|&(_, max_days)| days < max_days)
+                            .unwrap_or(VALIDITY_BUCKETS.len() - 1);
+                        validity_counts[bucket] += 1;
+                    }
+                    None => stats.open_snapshots += 1,
+                }
+            }
+
+            // Same IMSI with a new IMEI is a device change, with a new SV a software update,
+            // a new IMSI is a reassignment
+            for pair in snapshots.windows(2) {
+                if pair[0].identity.imsi == pair[1].identity.imsi {
+                    if pair[0].identity.imei != pair[1].identity.imei {
+                        stats.device_changes += 1;
+                    } else if pair[0].identity.sv != pair[1].identity.sv {
+                        stats.software_updates += 1;
+                    }
+                } else {
+                    stats.number_reassignments += 1;
+                }
+            }
+
+            Ok(())
+        })?;
+
+        if let (Some(&first), Some(&last)) = (active_deltas.keys().next(), active_deltas.keys().next_back()) {
+            let mut active = 0i64;
+            for month in first..=last {
+                active += active_deltas.get(&month).copied().unwrap_or(0);
+                stats.active_by_month.push((month_label(month), active.max(0) as u64));
+            }
+        }
+
+        stats.validity_days = VALIDITY_BUCKETS
+            .iter()
+            .zip(validity_counts)
+            .map(|(&(label, _), count)| (label.to_string(), count))
+            .collect();
+
+        Ok(stats)
+    }
+}
+
+/// File sizes around a compaction and the spot check that followed it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompactionReport {
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+    pub msisdns: usize,
+    /// MSISDNs whose snapshots were compared before and after
+    pub spot_checked: usize,
+}
+
+impl CompactionReport {
+    /// One-line size report, e.g. "12.5 MB -> 8.1 MB (-35.2%), 1000 of 20000 MSISDNs spot-checked"
+    pub fn summary(&self) -> String {
+        let mb = |bytes: u64| bytes as f64 / 1_000_000.0;
+        let change = if self.bytes_before == 0 {
+            0.0
+        } else {
+            (self.bytes_after as f64 / self.bytes_before as f64 - 1.0) * 100.0
+        };
+        format!(
+            "{:.1} MB -> {:.1} MB ({:+.1}%), {} of {} MSISDNs spot-checked",
+            mb(self.bytes_before),
+            mb(self.bytes_after),
+            change,
+            self.spot_checked,
+            self.msisdns
+        )
+    }
 }
 
-#[derive(Debug)]
+/// Compact the database at `path`, then check that `spot_checks` random MSISDNs
+/// (and the MSISDN count) read back exactly as before
+pub fn compact_database(path: &Path, spot_checks: usize, seed: u64) -> Result<CompactionReport> {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    let bytes_before = std::fs::metadata(path)?.len();
+    let mut db = SubscriberDbRedb::open(path)?;
+    let msisdns = db.count_msisdns()?;
+
+    // Reservoir sample of (MSISDN, snapshots)
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut sample: Vec<(u64, Vec<SubscriberSnapshot>)> = Vec::with_capacity(spot_checks.min(msisdns));
+    let mut seen = 0usize;
+    db.iter_all(|msisdn, snapshots| {
+        seen += 1;
+        if sample.len() < spot_checks {
+            sample.push((msisdn, snapshots));
+        } else {
+            let slot = rng.gen_range(0..seen);
+            if slot < spot_checks {
+                sample[slot] = (msisdn, snapshots);
+            }
+        }
+        Ok(())
+    })?;
+
+    db.compact()?;
+    drop(db);
+    let bytes_after = std::fs::metadata(path)?.len();
+
+    let db = SubscriberDbRedb::open(path)?;
+    if db.count_msisdns()? != msisdns {
+        anyhow::bail!("{} MSISDNs before compaction, {} after", msisdns, db.count_msisdns()?);
+    }
+    for (msisdn, snapshots) in &sample {
+        let after = db.load_chunk(*msisdn, *msisdn + 1)?;
+        if after.len() != 1 || &after[0].1 != snapshots {
+            anyhow::bail!("Snapshots of MSISDN {} differ after compaction", msisdn);
+        }
+    }
+
+    Ok(CompactionReport { bytes_before, bytes_after, msisdns, spot_checked: sample.len() })
+}
+
+/// MSISDNs buffered between the two readers of `diff_databases`
+const DIFF_CHANNEL_CAPACITY: usize = 1_024;
+
+/// How two subscriber databases differ (see `diff_databases`)
+#[derive(Debug, Default, Serialize)]
+pub struct DbDiff {
+    /// Instant the common MSISDNs were compared at (UTC ms); None = each MSISDN's latest snapshot
+    pub at_ms: Option<i64>,
+    pub only_in_a: u64,
+    pub only_in_b: u64,
+    pub common: u64,
+    /// Common MSISDNs with a snapshot at `at_ms` in one database only
+    pub active_only_in_a: u64,
+    pub active_only_in_b: u64,
+    pub imsi_differs: u64,
+    pub imei_differs: u64,
+    pub mccmnc_differs: u64,
+    /// Common MSISDNs with any difference above
+    pub differing: u64,
+    pub snapshots_a: u64,
+    pub snapshots_b: u64,
+    /// Snapshot count of B minus that of A -> number of common MSISDNs, zero left out
+    pub snapshot_count_deltas: BTreeMap<i64, u64>,
+}
+
+/// One MSISDN that differs between the databases, for the per-MSISDN CSV
+#[derive(Debug, Clone, Serialize)]
+pub struct MsisdnDiff {
+    pub msisdn: u64,
+    /// `only_in_a`, `only_in_b` or `changed`
+    pub kind: &'static str,
+    pub snapshots_a: usize,
+    pub snapshots_b: usize,
+    pub imsi_a: Option<String>,
+    pub imsi_b: Option<String>,
+    pub imei_a: Option<String>,
+    pub imei_b: Option<String>,
+    pub mccmnc_a: Option<String>,
+    pub mccmnc_b: Option<String>,
+}
+
+impl MsisdnDiff {
+    fn new(msisdn: u64, kind: &'static str, a: &[SubscriberSnapshot], b: &[SubscriberSnapshot], at_ms: Option<i64>) -> Self {
+        let (sa, sb) = (compared_snapshot(a, at_ms), compared_snapshot(b, at_ms));
+        MsisdnDiff {
+            msisdn,
+            kind,
+            snapshots_a: a.len(),
+            snapshots_b: b.len(),
+            imsi_a: sa.map(|s| s.identity.imsi.to_string()),
+            imsi_b: sb.map(|s| s.identity.imsi.to_string()),
+            imei_a: sa.map(|s| s.identity.imei.to_string()),
+            imei_b: sb.map(|s| s.identity.imei.to_string()),
+            mccmnc_a: sa.map(|s| s.identity.mccmnc.to_string()),
+            mccmnc_b: sb.map(|s| s.identity.mccmnc.to_string()),
+        }
+    }
+}
+
+/// Snapshot an MSISDN is compared on: the one valid at `at_ms`, else the latest
+fn compared_snapshot(snapshots: &[SubscriberSnapshot], at_ms: Option<i64>) -> Option<&SubscriberSnapshot> {
+    match at_ms {
+        Some(ts) => SubscriberDbRedb::find_snapshot_at(snapshots, ts),
+        None => snapshots.iter().max_by_key(|s| s.valid_from),
+    }
+}
+
+impl DbDiff {
+    fn only_in_a(&mut self, msisdn: u64, a: &[SubscriberSnapshot]) -> MsisdnDiff {
+        self.only_in_a += 1;
+        self.snapshots_a += a.len() as u64;
+        MsisdnDiff::new(msisdn, "only_in_a", a, &[], self.at_ms)
+    }
+
+    fn only_in_b(&mut self, msisdn: u64, b: &[SubscriberSnapshot]) -> MsisdnDiff {
+        self.only_in_b += 1;
+        self.snapshots_b += b.len() as u64;
+        MsisdnDiff::new(msisdn, "only_in_b", &[], b, self.at_ms)
+    }
+
+    /// Compare an MSISDN present in both; Some when it differs
+    fn common(&mut self, msisdn: u64, a: &[SubscriberSnapshot], b: &[SubscriberSnapshot]) -> Option<MsisdnDiff> {
+        self.common += 1;
+        self.snapshots_a += a.len() as u64;
+        self.snapshots_b += b.len() as u64;
+        let delta = b.len() as i64 - a.len() as i64;
+        if delta != 0 {
+            *self.snapshot_count_deltas.entry(delta).or_default() += 1;
+        }
+
+        let differs = match (compared_snapshot(a, self.at_ms), compared_snapshot(b, self.at_ms)) {
+            (Some(sa), Some(sb)) => {
+                let (ia, ib) = (&sa.identity, &sb.identity);
+                self.imsi_differs += (ia.imsi != ib.imsi) as u64;
+                self.imei_differs += (ia.imei != ib.imei) as u64;
+                self.mccmnc_differs += (ia.mccmnc != ib.mccmnc) as u64;
+                ia.imsi != ib.imsi || ia.imei != ib.imei || ia.mccmnc != ib.mccmnc
+            }
+            (Some(_), None) => {
+                self.active_only_in_a += 1;
+                true
+            }
+            (None, Some(_)) => {
+                self.active_only_in_b += 1;
+                true
+            }
+            (None, None) => false,
+        };
+        self.differing += differs as u64;
+        differs.then(|| MsisdnDiff::new(msisdn, "changed", a, b, self.at_ms))
+    }
+
+    /// Render the differences as a plain-text table
+    pub fn to_table(&self) -> String {
+        use std::fmt::Write;
+
+        let mut out = String::new();
+        let at = match self.at_ms.and_then(DateTime::from_timestamp_millis) {
+            Some(ts) => ts.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+            None => "latest snapshot".to_string(),
+        };
+        let _ = writeln!(out, "Compared at: {}", at);
+        let rows = [
+            ("MSISDNs only in A", self.only_in_a),
+            ("MSISDNs only in B", self.only_in_b),
+            ("Common MSISDNs", self.common),
+            ("  differing", self.differing),
+            ("  IMSI differs", self.imsi_differs),
+            ("  IMEI differs", self.imei_differs),
+            ("  MCCMNC differs", self.mccmnc_differs),
+            ("  active only in A", self.active_only_in_a),
+            ("  active only in B", self.active_only_in_b),
+            ("Snapshots in A", self.snapshots_a),
+            ("Snapshots in B", self.snapshots_b),
+        ];
+        for (label, value) in rows {
+            let _ = writeln!(out, "  {:<24} {:>12}", label, value);
+        }
+
+        let _ = writeln!(out, "\nSnapshot count delta (B - A), common MSISDNs");
+        for (delta, msisdns) in &self.snapshot_count_deltas {
+            let _ = writeln!(out, "  {:<24} {:>12}", format!("{:+}", delta), msisdns);
+        }
+
+        out
+    }
+}
+
+/// Compare two databases MSISDN by MSISDN. Both are streamed in key order with `iter_all`
+/// (B on a second thread through a bounded channel) and merge-joined, so memory stays bounded.
+/// `on_diff` gets every MSISDN that is in one database only or differs at `at_ms`
+pub fn diff_databases<F>(a: &SubscriberDbRedb, b: &SubscriberDbRedb, at_ms: Option<i64>, mut on_diff: F) -> Result<DbDiff>
+where
+    F: FnMut(&MsisdnDiff) -> Result<()>,
+{
+    let mut diff = DbDiff { at_ms, ..DbDiff::default() };
+
+    std::thread::scope(|scope| {
+        let (tx, rx) = std::sync::mpsc::sync_channel::<(u64, Vec<SubscriberSnapshot>)>(DIFF_CHANNEL_CAPACITY);
+        let reader = scope.spawn(move || {
+            b.iter_all(|msisdn, snapshots| tx.send((msisdn, snapshots)).map_err(|_| anyhow::anyhow!("Diff stopped early")))
+        });
+
+        let mut merge = || -> Result<()> {
+            let mut pending_b = rx.recv().ok();
+            a.iter_all(|msisdn, snapshots_a| {
+                // MSISDNs of B sorting before this one are missing from A
+                while let Some((msisdn_b, snapshots_b)) = pending_b.take() {
+                    if msisdn_b >= msisdn {
+                        pending_b = Some((msisdn_b, snapshots_b));
+                        break;
+                    }
+                    on_diff(&diff.only_in_b(msisdn_b, &snapshots_b))?;
+                    pending_b = rx.recv().ok();
+                }
+                match pending_b.take() {
+                    Some((msisdn_b, snapshots_b)) if msisdn_b == msisdn => {
+                        if let Some(changed) = diff.common(msisdn, &snapshots_a, &snapshots_b) {
+                            on_diff(&changed)?;
+                        }
+                        pending_b = rx.recv().ok();
+                    }
+                    next => {
+                        pending_b = next;
+                        on_diff(&diff.only_in_a(msisdn, &snapshots_a))?;
+                    }
+                }
+                Ok(())
+            })?;
+            while let Some((msisdn_b, snapshots_b)) = pending_b.take() {
+                on_diff(&diff.only_in_b(msisdn_b, &snapshots_b))?;
+                pending_b = rx.recv().ok();
+            }
+            Ok(())
+        };
+        let merged = merge();
+        // Unblock the reader when the merge stopped early
+        drop(rx);
+        let read = reader.join().map_err(|_| anyhow::anyhow!("Database B reader panicked"))?;
+        merged?;
+        read
+    })?;
+
+    Ok(diff)
+}
+
+const MS_PER_DAY: i64 = 86_400_000;
+
+/// Closed snapshot validity buckets: (label, exclusive upper bound in days)
+const VALIDITY_BUCKETS: [(&str, i64); 6] = [
+    ("<7d", 7),
+    ("7-30d", 30),
+    ("30-90d", 90),
+    ("90-180d", 180),
+    ("180-365d", 365),
+    (">=365d", i64::MAX),
+];
+
+/// Month index (year * 12 + month0) of the first UTC month start at or after `ts_ms`
+fn first_month_at_or_after(ts_ms: i64) -> i32 {
+    let dt = DateTime::from_timestamp_millis(ts_ms).unwrap_or_default();
+    let month = dt.year() * 12 + dt.month0() as i32;
+    let at_month_start = dt.day() == 1 && dt.num_seconds_from_midnight() == 0 && dt.nanosecond() == 0;
+    if at_month_start {
+        month
+    } else {
+        month + 1
+    }
+}
+
+fn month_label(month: i32) -> String {
+    format!("{:04}-{:02}", month.div_euclid(12), month.rem_euclid(12) + 1)
+}
+
+#[derive(Debug, Default, Serialize)]
 pub struct DbStats {
     pub total_msisdns: u64,
     pub total_snapshots: u64,
+    /// Snapshot count -> number of MSISDNs with that many snapshots
+    pub snapshots_per_msisdn: BTreeMap<usize, u64>,
+    /// Active MSISDNs at the first day of each month (UTC)
+    pub active_by_month: Vec<(String, u64)>,
+    /// Validity duration histogram of closed snapshots
+    pub validity_days: Vec<(String, u64)>,
+    /// Snapshots still valid (no valid_to)
+    pub open_snapshots: u64,
+    pub device_changes: u64,
+    pub software_updates: u64,
+    pub number_reassignments: u64,
+}
+
+impl DbStats {
+    /// Render the statistics as a plain-text table
+    pub fn to_table(&self) -> String {
+        use std::fmt::Write;
+
+        let mut out = String::new();
+        let rows = [
+            ("MSISDNs", self.total_msisdns),
+            ("Snapshots", self.total_snapshots),
+            ("Device changes", self.device_changes),
+            ("Software updates", self.software_updates),
+            ("Number reassignments", self.number_reassignments),
+            ("Open snapshots", self.open_snapshots),
+        ];
+        for (label, value) in rows {
+            let _ = writeln!(out, "  {:<24} {:>12}", label, value);
+        }
+
+        let _ = writeln!(out, "\nSnapshots per MSISDN");
+        for (snapshots, msisdns) in &self.snapshots_per_msisdn {
+            let _ = writeln!(out, "  {:<24} {:>12}", snapshots, msisdns);
+        }
+
+        let _ = writeln!(out, "\nSnapshot validity (closed)");
+        for (bucket, count) in &self.validity_days {
+            let _ = writeln!(out, "  {:<24} {:>12}", bucket, count);
+        }
+
+        let _ = writeln!(out, "\nActive subscribers (1st of month, UTC)");
+        for (month, active) in &self.active_by_month {
+            let _ = writeln!(out, "  {:<24} {:>12}", month, active);
+        }
+
+        out
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::identity::SubscriberIdentity;
     use tempfile::tempdir;
 
+    fn snapshot(msisdn: u64, imsi: u64, imei: u64, valid_from: i64, valid_to: Option<i64>) -> SubscriberSnapshot {
+        SubscriberSnapshot {
+            identity: SubscriberIdentity::from_numbers(msisdn, 250010000000000 + imsi, imei, 25001).unwrap(),
+            valid_from,
+            valid_to,
+        }
+    }
+
     #[test]
     fn test_insert_and_get() -> Result<()> {
         let dir = tempdir()?;
@@ -224,33 +931,19 @@ mod tests {
         let db = SubscriberDbRedb::new(&db_path)?;
 
         let snapshots = vec![
-            SubscriberSnapshotNumeric {
-                imsi: 123456789,
-                msisdn: 79001234567,
-                imei: 111111111111111,
-                mccmnc: 25001,
-                valid_from: 1000,
-                valid_to: Some(2000),
-            },
-            SubscriberSnapshotNumeric {
-                imsi: 123456789,
-                msisdn: 79001234567,
-                imei: 222222222222222,
-                mccmnc: 25001,
-                valid_from: 2000,
-                valid_to: None,
-            },
+            snapshot(79001234567, 1, 111111111111111, 1000, Some(2000)),
+            snapshot(79001234567, 1, 222222222222222, 2000, None),
         ];
 
         db.insert_snapshots(79001234567, &snapshots)?;
 
         // Get snapshot at timestamp 1500 (first snapshot)
         let sub = db.get_subscriber_at(79001234567, 1500)?.unwrap();
-        assert_eq!(sub.imei, 111111111111111);
+        assert_eq!(sub.identity.imei.get(), 111111111111111);
 
         // Get snapshot at timestamp 2500 (second snapshot)
         let sub = db.get_subscriber_at(79001234567, 2500)?.unwrap();
-        assert_eq!(sub.imei, 222222222222222);
+        assert_eq!(sub.identity.imei.get(), 222222222222222);
 
         // No snapshot at timestamp 500
         assert!(db.get_subscriber_at(79001234567, 500)?.is_none());
@@ -258,6 +951,69 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_nearest_key() -> Result<()> {
+        let dir = tempdir()?;
+        let db = SubscriberDbRedb::new(&dir.path().join("test.redb"))?;
+        let base = 31612000000u64;
+        assert_eq!(db.get_nearest_key(base)?, None);
+        assert!(!db.contains_msisdn(base)?);
+
+        let batch: Vec<(u64, Vec<SubscriberSnapshot>)> = [100u64, 200, 300]
+            .iter()
+            .map(|&i| (base + i, vec![snapshot(base + i, i, 100_000_000_000_000 + i, 0, None)]))
+            .collect();
+        db.insert_snapshots_batch(&batch)?;
+        assert!(db.contains_msisdn(base + 200)?);
+        assert!(!db.contains_msisdn(base + 201)?);
+        assert_eq!(db.get_nearest_key(base + 200)?, Some(base + 200));
+        assert_eq!(db.get_nearest_key(base + 240)?, Some(base + 200));
+        assert_eq!(db.get_nearest_key(base + 260)?, Some(base + 300));
+        // A tie goes to the lower key
+        assert_eq!(db.get_nearest_key(base + 250)?, Some(base + 200));
+        assert_eq!(db.get_nearest_key(0)?, Some(base + 100));
+        assert_eq!(db.get_nearest_key(u64::MAX)?, Some(base + 300));
+        Ok(())
+    }
+
+    #[test]
+    fn test_insert_all_fast_durability_persists() -> Result<()> {
+        let dir = tempdir()?;
+        let db_path = dir.path().join("test.redb");
+        let entries: Vec<(u64, Vec<SubscriberSnapshot>)> = (0..2_500u64)
+            .map(|i| (31612000000 + i, vec![snapshot(31612000000 + i, i, 100_000_000_000_000 + i, 0, None)]))
+            .collect();
+        let options = DbWriteOptions { batch_size: 1_000, durability: DbDurability::Fast, compact: false };
+        SubscriberDbRedb::new(&db_path)?.insert_all(&entries, &options)?;
+
+        let db = SubscriberDbRedb::open(&db_path)?;
+        assert_eq!(db.count_msisdns()?, entries.len());
+        assert_eq!(db.get_subscriber_at(31612002499, 0)?.unwrap().identity.imsi.get(), 250010000000000 + 2499);
+        assert_eq!(DbDurability::from_str("FAST"), Some(DbDurability::Fast));
+        assert_eq!(DbDurability::from_str("eventual"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_compact_database_keeps_snapshots() -> Result<()> {
+        let dir = tempdir()?;
+        let db_path = dir.path().join("test.redb");
+        {
+            let db = SubscriberDbRedb::new(&db_path)?;
+            // Many small commits leave free pages behind
+            for i in 0..500u64 {
+                db.insert_snapshots(31612000000 + i, &[snapshot(31612000000 + i, i, 100_000_000_000_000 + i, 0, None)])?;
+            }
+        }
+
+        let report = compact_database(&db_path, 50, 1)?;
+        assert_eq!(report.msisdns, 500);
+        assert_eq!(report.spot_checked, 50);
+        assert!(report.bytes_after <= report.bytes_before, "{:?}", report);
+        assert_eq!(SubscriberDbRedb::open(&db_path)?.count_msisdns()?, 500);
+        Ok(())
+    }
+
     #[test]
     fn test_load_chunk() -> Result<()> {
         let dir = tempdir()?;
@@ -266,14 +1022,7 @@ mod tests {
 
         // Insert snapshots for multiple MSISDNs
         for msisdn in 79001234560..79001234570 {
-            let snapshot = SubscriberSnapshotNumeric {
-                imsi: msisdn - 79000000000,
-                msisdn,
-                imei: msisdn * 1000,
-                mccmnc: 25001,
-                valid_from: 0,
-                valid_to: None,
-            };
+            let snapshot = snapshot(msisdn, msisdn - 79001234500, msisdn * 1000, 0, None);
             db.insert_snapshots(msisdn, &[snapshot])?;
         }
 
@@ -295,22 +1044,8 @@ mod tests {
         // Insert 100 MSISDNs, each with 2 snapshots
         for msisdn in 79001234500..79001234600 {
             let snapshots = vec![
-                SubscriberSnapshotNumeric {
-                    imsi: msisdn - 79000000000,
-                    msisdn,
-                    imei: msisdn * 1000,
-                    mccmnc: 25001,
-                    valid_from: 0,
-                    valid_to: Some(1000),
-                },
-                SubscriberSnapshotNumeric {
-                    imsi: msisdn - 79000000000,
-                    msisdn,
-                    imei: msisdn * 2000,
-                    mccmnc: 25001,
-                    valid_from: 1000,
-                    valid_to: None,
-                },
+                snapshot(msisdn, msisdn - 79001234500, msisdn * 1000, 0, Some(1000)),
+                snapshot(msisdn, msisdn - 79001234500, msisdn * 2000, 1000, None),
             ];
             db.insert_snapshots(msisdn, &snapshots)?;
         }
@@ -318,7 +1053,156 @@ mod tests {
         let stats = db.stats()?;
         assert_eq!(stats.total_msisdns, 100);
         assert_eq!(stats.total_snapshots, 200);
+        assert_eq!(stats.snapshots_per_msisdn.get(&2), Some(&100));
+        assert_eq!(stats.device_changes, 100);
+        assert_eq!(stats.number_reassignments, 0);
+        assert_eq!(stats.open_snapshots, 100);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stats_history() -> Result<()> {
+        let dir = tempdir()?;
+        let db = SubscriberDbRedb::new(&dir.path().join("test.redb"))?;
+
+        let jan_1 = 1704067200000i64; // 2024-01-01
+        let feb_15 = jan_1 + 45 * MS_PER_DAY;
+        let apr_1 = jan_1 + 91 * MS_PER_DAY;
+
+        // Number released on Feb 15 and reassigned to a new IMSI on Apr 1
+        db.insert_snapshots(
+            31612000001,
+            &[
+                snapshot(31612000001, 1, 10, jan_1, Some(feb_15)),
+                snapshot(31612000001, 2, 20, apr_1, None),
+            ],
+        )?;
+        // Always-active subscriber
+        db.insert_snapshots(
+            31612000002,
+            &[snapshot(31612000002, 3, 30, jan_1, None)],
+        )?;
+
+        let stats = db.stats()?;
+        assert_eq!(stats.number_reassignments, 1);
+        assert_eq!(stats.device_changes, 0);
+        assert_eq!(
+            stats.active_by_month,
+            vec![
+                ("2024-01".to_string(), 2),
+                ("2024-02".to_string(), 2),
+                ("2024-03".to_string(), 1),
+                ("2024-04".to_string(), 2),
+            ]
+        );
+        assert_eq!(stats.validity_days[2], ("30-90d".to_string(), 1));
+        assert!(stats.to_table().contains("Number reassignments"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_snapshot_encoding_unchanged() {
+        // Databases written with the former numeric snapshot type must still decode
+        let legacy = (250010000000001u64, 79001234567u64, 12345678901237u64, 25001u32, 1000i64, Some(2000i64));
+        let bytes = serialize(&vec![legacy]).unwrap();
+        let decoded = decode_snapshots(&bytes, SNAPSHOT_FORMAT_LEGACY).unwrap();
+        assert_eq!(decoded, vec![snapshot(79001234567, 1, 12345678901237, 1000, Some(2000))]);
+        assert_eq!(decoded[0].identity.imei.to_string(), "012345678901237");
+        assert_eq!(decoded[0].identity.sv, 0);
+        assert_eq!(encode_snapshots(&decoded, SNAPSHOT_FORMAT_LEGACY).unwrap(), bytes);
+
+        // The current format appends the software version to the identity
+        let mut current = decoded.clone();
+        current[0].identity.sv = 7;
+        let with_sv = (250010000000001u64, 79001234567u64, 12345678901237u64, 25001u32, 7u8, 1000i64, Some(2000i64));
+        let bytes = encode_snapshots(&current, SNAPSHOT_FORMAT_CURRENT).unwrap();
+        assert_eq!(bytes, serialize(&vec![with_sv]).unwrap());
+        assert_eq!(decode_snapshots(&bytes, SNAPSHOT_FORMAT_CURRENT).unwrap(), current);
+
+        // Out-of-range identifiers fail to decode instead of turning into zeros
+        let corrupt = (250010000000001u64, 79001234567u64, 1_000_000_000_000_000u64, 25001u32, 1000i64, None::<i64>);
+        assert!(decode_snapshots(&serialize(&vec![corrupt]).unwrap(), SNAPSHOT_FORMAT_LEGACY).is_err());
+    }
+
+    #[test]
+    fn test_legacy_database_keeps_its_format() -> Result<()> {
+        let dir = tempdir()?;
+        let db_path = dir.path().join("legacy.redb");
+        {
+            // As written before the snapshot format was recorded
+            let db = Database::create(&db_path)?;
+            let write_txn = db.begin_write()?;
+            let legacy = (250010000000001u64, 79001234567u64, 12345678901237u64, 25001u32, 1000i64, None::<i64>);
+            write_txn.open_table(SNAPSHOTS)?.insert(79001234567, serialize(&vec![legacy])?.as_slice())?;
+            write_txn.commit()?;
+        }
+
+        let db = SubscriberDbRedb::open(&db_path)?;
+        assert_eq!(db.snapshot_format(), SNAPSHOT_FORMAT_LEGACY);
+        assert_eq!(db.get_subscriber_at(79001234567, 1500)?.unwrap().identity.sv, 0);
+        drop(db);
+
+        // Appending to it keeps the legacy layout, so older readers still read it
+        let db = SubscriberDbRedb::new(&db_path)?;
+        assert_eq!(db.snapshot_format(), SNAPSHOT_FORMAT_LEGACY);
+        db.insert_snapshots(79001234568, &[snapshot(79001234568, 2, 222222222222222, 1000, None)])?;
+        assert_eq!(db.count_msisdns()?, 2);
+        assert!(db.validate(2).is_ok());
+
+        // A new database records the current format
+        let fresh = SubscriberDbRedb::new(&dir.path().join("fresh.redb"))?;
+        assert_eq!(fresh.snapshot_format(), SNAPSHOT_FORMAT_CURRENT);
+        let mut updated = snapshot(79001234567, 1, 111111111111111, 2000, None);
+        updated.identity.sv = 12;
+        fresh.insert_snapshots(79001234567, &[snapshot(79001234567, 1, 111111111111111, 1000, Some(2000)), updated])?;
+        assert_eq!(fresh.get_subscriber_at(79001234567, 2500)?.unwrap().identity.imeisv(), 1_111_111_111_111_112);
+        let stats = fresh.stats()?;
+        assert_eq!((stats.device_changes, stats.software_updates), (0, 1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_databases() -> Result<()> {
+        let dir = tempdir()?;
+        let a = SubscriberDbRedb::new(&dir.path().join("a.redb"))?;
+        let b = SubscriberDbRedb::new(&dir.path().join("b.redb"))?;
+        for i in 0..100u64 {
+            let msisdn = 31612000000 + i;
+            if i < 90 {
+                a.insert_snapshots(msisdn, &[snapshot(msisdn, i, 100_000_000_000_000 + i, 0, None)])?;
+            }
+            if i >= 5 {
+                // New devices from t=1000 for every tenth MSISDN
+                let mut snapshots = vec![snapshot(msisdn, i, 100_000_000_000_000 + i, 0, None)];
+                if i % 10 == 0 {
+                    snapshots[0].valid_to = Some(1000);
+                    snapshots.push(snapshot(msisdn, i, 200_000_000_000_000 + i, 1000, None));
+                }
+                b.insert_snapshots(msisdn, &snapshots)?;
+            }
+        }
+
+        let mut changed = Vec::new();
+        let diff = diff_databases(&a, &b, None, |d| {
+            changed.push((d.msisdn, d.kind));
+            Ok(())
+        })?;
+        assert_eq!((diff.only_in_a, diff.only_in_b, diff.common), (5, 10, 85));
+        // 10, 20, ..., 80 carry a new IMEI in B
+        assert_eq!((diff.differing, diff.imei_differs, diff.imsi_differs), (8, 8, 0));
+        assert_eq!(diff.snapshot_count_deltas, BTreeMap::from([(1, 8)]));
+        assert_eq!((diff.snapshots_a, diff.snapshots_b), (90, 95 + 9));
+        assert_eq!(changed.len(), 5 + 10 + 8);
+        assert!(changed.windows(2).all(|w| w[0].0 < w[1].0));
+        assert_eq!(changed[0], (31612000000, "only_in_a"));
+        assert_eq!(changed.last(), Some(&(31612000099, "only_in_b")));
 
+        // Before the device changes the common MSISDNs agree
+        let diff = diff_databases(&a, &b, Some(500), |_| Ok(()))?;
+        assert_eq!(diff.differing, 0);
+        assert!(diff.to_table().contains("MSISDNs only in B"));
         Ok(())
     }
 }
@@ -1,5 +1,5 @@
 // Timezone handling utilities
-use chrono::{DateTime, Offset, TimeZone};
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Offset, TimeZone};
 use chrono_tz::Tz;
 
 /// Get timezone from name
@@ -13,6 +13,54 @@ pub fn to_epoch_ms<T: TimeZone>(dt: &DateTime<T>) -> i64 {
     dt.timestamp_millis()
 }
 
+/// First instant of the local calendar day `date` in `tz`.
+/// Midnight can fall in a DST gap (America/Santiago springs forward at 00:00), then the day
+/// starts where the gap ends; an ambiguous midnight resolves to the earlier instant
+pub fn local_day_start(tz: Tz, date: NaiveDate) -> anyhow::Result<DateTime<Tz>> {
+    let midnight = date.and_time(NaiveTime::MIN);
+    // Offsets and their transitions fall on 15-minute boundaries
+    (0..24 * 4)
+        .find_map(|quarter| tz.from_local_datetime(&(midnight + Duration::minutes(15 * quarter))).earliest())
+        .ok_or_else(|| anyhow::anyhow!("{} has no local time on {}", tz.name(), date))
+}
+
+/// Instants bounding the local day `date`: 23 or 25 hours long on DST transition days
+pub fn local_day_bounds(tz: Tz, date: NaiveDate) -> anyhow::Result<(DateTime<Tz>, DateTime<Tz>)> {
+    let next = date.succ_opt().ok_or_else(|| anyhow::anyhow!("No day after {}", date))?;
+    Ok((local_day_start(tz, date)?, local_day_start(tz, next)?))
+}
+
+/// Length of the local day `date` in seconds: 86400, or 82800 / 90000 on DST transition days
+pub fn seconds_in_local_day(tz: Tz, date: NaiveDate) -> anyhow::Result<i64> {
+    let (start, end) = local_day_bounds(tz, date)?;
+    Ok(seconds_between(&start, &end))
+}
+
+/// Seconds elapsed from `start` to `end`, which differs from the wall-clock reading across a DST transition
+pub fn seconds_between<T: TimeZone>(start: &DateTime<T>, end: &DateTime<T>) -> i64 {
+    end.clone().signed_duration_since(start).num_seconds()
+}
+
+/// `secs` elapsed seconds after `t`, in `t`'s zone: across a DST transition the wall clock also moves by
+/// the offset change (01:30 + 1h reads 03:30 on a day springing forward at 02:00)
+pub fn add_seconds<T: TimeZone>(t: &DateTime<T>, secs: i64) -> DateTime<T> {
+    t.clone() + Duration::seconds(secs)
+}
+
+/// Instants bounding wall-clock minutes `[start_min, end_min)` of the local day `date`.
+/// A bound in a DST gap moves to where the gap ends; 0 and 1440 are the day bounds
+pub fn local_window_bounds(tz: Tz, date: NaiveDate, start_min: u32, end_min: u32) -> anyhow::Result<(DateTime<Tz>, DateTime<Tz>)> {
+    let (day_start, day_end) = local_day_bounds(tz, date)?;
+    let instant = |minute: u32| -> DateTime<Tz> {
+        let wall = date.and_time(NaiveTime::MIN) + Duration::minutes(minute as i64);
+        (0..4 * 4)
+            .find_map(|quarter| tz.from_local_datetime(&(wall + Duration::minutes(15 * quarter))).earliest())
+            .unwrap_or(day_end)
+            .clamp(day_start, day_end)
+    };
+    Ok((instant(start_min), instant(end_min)))
+}
+
 /// Get timezone offset in minutes from datetime
 pub fn tz_offset_minutes<T: TimeZone>(dt: &DateTime<T>) -> i32 {
     dt.offset().fix().local_minus_utc() / 60
@@ -51,4 +99,67 @@ mod tests {
         let offset_summer = tz_offset_minutes(&dt_summer);
         assert_eq!(offset_summer, 120); // CEST is UTC+2
     }
+
+    #[test]
+    fn test_local_day_bounds_on_dst_days() {
+        let hours = |tz: Tz, y, m, d| {
+            let (start, end) = local_day_bounds(tz, NaiveDate::from_ymd_opt(y, m, d).unwrap()).unwrap();
+            (start.format("%H:%M%z").to_string(), (end - start).num_hours())
+        };
+
+        assert_eq!(hours(chrono_tz::Europe::Amsterdam, 2025, 1, 15), ("00:00+0100".to_string(), 24));
+        assert_eq!(hours(chrono_tz::Europe::Amsterdam, 2025, 3, 30), ("00:00+0100".to_string(), 23));
+        assert_eq!(hours(chrono_tz::Europe::Amsterdam, 2025, 10, 26), ("00:00+0200".to_string(), 25));
+
+        // Santiago springs forward at midnight: the day starts at 01:00 and lasts 23 hours
+        assert_eq!(hours(chrono_tz::America::Santiago, 2024, 9, 8), ("01:00-0300".to_string(), 23));
+        // Falling back at midnight repeats 23:00, so the day starts at the single midnight
+        assert_eq!(hours(chrono_tz::America::Santiago, 2025, 4, 6), ("00:00-0400".to_string(), 24));
+        assert_eq!(hours(chrono_tz::America::Santiago, 2025, 4, 5), ("00:00-0300".to_string(), 25));
+
+        // Samoa skipped 2011-12-30 entirely
+        assert!(local_day_start(chrono_tz::Pacific::Apia, NaiveDate::from_ymd_opt(2011, 12, 30).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_seconds_in_local_day() {
+        let amsterdam = |m, d| seconds_in_local_day(chrono_tz::Europe::Amsterdam, NaiveDate::from_ymd_opt(2025, m, d).unwrap()).unwrap();
+        assert_eq!(amsterdam(1, 15), 86_400);
+        assert_eq!(amsterdam(3, 30), 82_800);
+        assert_eq!(amsterdam(10, 26), 90_000);
+        assert_eq!(seconds_in_local_day(chrono_tz::UTC, NaiveDate::from_ymd_opt(2025, 3, 30).unwrap()).unwrap(), 86_400);
+    }
+
+    #[test]
+    fn test_add_seconds_across_dst() {
+        let tz = chrono_tz::Europe::Amsterdam;
+        let wall = |t: DateTime<Tz>| t.format("%H:%M%z").to_string();
+
+        // Spring forward at 02:00: an hour after 01:30 reads 03:30
+        let before = tz.with_ymd_and_hms(2025, 3, 30, 1, 30, 0).unwrap();
+        assert_eq!(wall(add_seconds(&before, 3600)), "03:30+0200");
+        // Fall back at 03:00: an hour after 02:30 CEST is 02:30 CET
+        let before = tz.with_ymd_and_hms(2025, 10, 26, 2, 30, 0).earliest().unwrap();
+        let after = add_seconds(&before, 3600);
+        assert_eq!(wall(after), "02:30+0100");
+        assert_eq!(seconds_between(&before, &after), 3600);
+        assert_eq!(add_seconds(&after, -3600), before);
+    }
+
+    #[test]
+    fn test_local_window_bounds() {
+        let window = |y, m, d, start_min, end_min| {
+            let date = NaiveDate::from_ymd_opt(y, m, d).unwrap();
+            let (start, end) = local_window_bounds(chrono_tz::Europe::Amsterdam, date, start_min, end_min).unwrap();
+            (start.format("%H:%M%z").to_string(), (end - start).num_minutes())
+        };
+
+        assert_eq!(window(2025, 1, 15, 13 * 60, 14 * 60), ("13:00+0100".to_string(), 60));
+        assert_eq!(window(2025, 1, 15, 23 * 60, 24 * 60), ("23:00+0100".to_string(), 60));
+        // 02:00-03:00 does not exist on the spring-forward day
+        assert_eq!(window(2025, 3, 30, 60, 4 * 60), ("01:00+0100".to_string(), 120));
+        assert_eq!(window(2025, 3, 30, 2 * 60, 3 * 60), ("03:00+0200".to_string(), 0));
+        // 02:00-03:00 happens twice on the fall-back day
+        assert_eq!(window(2025, 10, 26, 60, 4 * 60), ("01:00+0200".to_string(), 240));
+    }
 }
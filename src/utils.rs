@@ -1,127 +1,797 @@
 // Utility functions for bundling and aggregation
-use chrono::DateTime;
-use chrono_tz::Tz;
+use crate::async_writer::WriterMetrics;
+use crate::compression::{create_compressed_writer, CompressionType};
+use crate::config::PartitionLayout;
+use crate::daily_targets::TargetDeviation;
+use crate::day_over_day::DayOverDay;
+use crate::generators::ShardStats;
+use crate::provenance::{RunProvenance, RUN_CONFIG_FILE};
+use crate::provisioning::PROVISIONING_FILE;
+use crate::reader::{decode, open_decompressed};
+use crate::writer::{sync_dir, ShardManifest, PART_TMP_SUFFIX};
+use anyhow::Context;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::File;
-use std::io::Write;
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct DailySummary {
     pub total_calls: usize,
     pub total_sms: usize,
     pub total_data: usize,
+    pub active_subscribers: usize,
+    pub inactive_subscribers: usize,
+    pub skipped_subscribers: usize,
     pub shards: usize,
+    #[serde(default)]
+    pub expected_shards: usize,
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    // Events by local hour of start time, summed over shards
+    #[serde(default)]
+    pub calls_by_hour: [usize; 24],
+    #[serde(default)]
+    pub sms_by_hour: [usize; 24],
+    #[serde(default)]
+    pub data_by_hour: [usize; 24],
+    #[serde(default)]
+    pub dirty_rows: usize,
+    // MT SMS retried by the SMSC (`sms_unreachable_rate`) and their extra attempt rows, not in total_sms
+    #[serde(default)]
+    pub sms_unreachable: usize,
+    #[serde(default)]
+    pub sms_retry_rows: usize,
+    // Rows checked by `self_check` and those that broke an invariant
+    #[serde(default)]
+    pub self_check_rows: usize,
+    #[serde(default)]
+    pub self_check_violations: usize,
+    // Active MSISDNs against the previous generated day; absent on the first day of a run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub day_over_day: Option<DayOverDay>,
+    // Absent when the run was made with --no-metrics
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub throughput: Option<ThroughputMetrics>,
+    // Totals against `daily_targets`, by target key; absent without targets
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub daily_targets: BTreeMap<String, TargetDeviation>,
+    // Worker shards feeding each writer task's `writerNNN` files; absent with a custom record writer
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub writers: Vec<WriterShards>,
+    // Generator version, config hash, seed and subscriber database of the run; absent in older summaries
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<RunProvenance>,
 }
 
-/// Aggregate statistics from all shards into a summary.json file
-pub fn create_daily_summary(out_dir: &Path, day: &DateTime<Tz>) -> anyhow::Result<DailySummary> {
-    let day_str = day.format("%Y-%m-%d").to_string();
-    let day_dir = out_dir.join(&day_str);
+/// Worker shards whose events one writer task wrote
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WriterShards {
+    pub writer: usize,
+    pub worker_shards: Vec<usize>,
+}
 
-    let mut summary = DailySummary {
-        total_calls: 0,
-        total_sms: 0,
-        total_data: 0,
-        shards: 0,
-    };
+/// Round-robin assignment of `shards` worker shards to `writer_tasks` writer tasks: worker shard `i` feeds
+/// writer `i % writer_tasks`
+pub fn writer_shard_map(shards: usize, writer_tasks: usize) -> Vec<WriterShards> {
+    (0..writer_tasks)
+        .map(|writer| WriterShards { writer, worker_shards: (writer..shards).step_by(writer_tasks).collect() })
+        .collect()
+}
+
+/// Performance report for a day, or a whole run when days are added together
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ThroughputMetrics {
+    pub wall_clock_sec: f64,
+    pub events: usize,
+    pub events_per_sec: f64,
+    // CSV bytes before compression
+    pub uncompressed_bytes: u64,
+    // Part file sizes on disk
+    pub compressed_bytes: u64,
+    // uncompressed / compressed, 0 when nothing was written
+    pub compression_ratio: f64,
+    pub writers: Vec<WriterMetrics>,
+}
+
+impl ThroughputMetrics {
+    pub fn new(wall_clock_sec: f64, writers: Vec<WriterMetrics>) -> Self {
+        let mut metrics = ThroughputMetrics {
+            wall_clock_sec,
+            writers,
+            ..ThroughputMetrics::default()
+        };
+        metrics.recompute();
+        metrics
+    }
+
+    /// Add another day, summing writers with the same id and keeping the highest queue depth
+    pub fn add(&mut self, other: &ThroughputMetrics) {
+        self.wall_clock_sec += other.wall_clock_sec;
+        for theirs in &other.writers {
+            match self.writers.iter_mut().find(|w| w.writer == theirs.writer) {
+                Some(ours) => {
+                    ours.events += theirs.events;
+                    ours.uncompressed_bytes += theirs.uncompressed_bytes;
+                    ours.compressed_bytes += theirs.compressed_bytes;
+                    ours.busy_sec += theirs.busy_sec;
+                    ours.idle_sec += theirs.idle_sec;
+                    ours.peak_queue_depth = ours.peak_queue_depth.max(theirs.peak_queue_depth);
+                }
+                None => self.writers.push(theirs.clone()),
+            }
+        }
+        self.recompute();
+    }
+
+    /// Rebuild totals and rates from the per-writer figures
+    fn recompute(&mut self) {
+        self.events = self.writers.iter().map(|w| w.events).sum();
+        self.uncompressed_bytes = self.writers.iter().map(|w| w.uncompressed_bytes).sum();
+        self.compressed_bytes = self.writers.iter().map(|w| w.compressed_bytes).sum();
+        self.events_per_sec = if self.wall_clock_sec > 0.0 {
+            self.events as f64 / self.wall_clock_sec
+        } else {
+            0.0
+        };
+        self.compression_ratio = if self.compressed_bytes > 0 {
+            self.uncompressed_bytes as f64 / self.compressed_bytes as f64
+        } else {
+            0.0
+        };
+    }
+
+    /// One-line report for the log
+    pub fn report(&self) -> String {
+        let busy: f64 = self.writers.iter().map(|w| w.busy_sec).sum();
+        let idle: f64 = self.writers.iter().map(|w| w.idle_sec).sum();
+        let peak = self.writers.iter().map(|w| w.peak_queue_depth).max().unwrap_or(0);
+        format!(
+            "{} events in {:.2}s ({:.0} events/s), {:.1} MB uncompressed, {:.1} MB on disk (ratio {:.2}), writers busy {:.2}s / idle {:.2}s, peak queue depth {}",
+            self.events,
+            self.wall_clock_sec,
+            self.events_per_sec,
+            self.uncompressed_bytes as f64 / 1e6,
+            self.compressed_bytes as f64 / 1e6,
+            self.compression_ratio,
+            busy,
+            idle,
+            peak
+        )
+    }
+}
+
+impl DailySummary {
+    /// Share of subscribers in the worker ranges whose DB lookup missed
+    pub fn miss_rate(&self) -> f64 {
+        let total = self.active_subscribers + self.inactive_subscribers + self.skipped_subscribers;
+        if total == 0 {
+            0.0
+        } else {
+            self.skipped_subscribers as f64 / total as f64
+        }
+    }
+}
+
+/// Warn when the subscriber lookup miss rate exceeds `warn_threshold`
+/// and fail when it exceeds `fail_threshold` (clearly misconfigured run, e.g. prefix mismatch)
+pub fn check_miss_rate(summary: &DailySummary, day_str: &str, warn_threshold: f64, fail_threshold: Option<f64>) -> anyhow::Result<f64> {
+    let rate = summary.miss_rate();
+
+    if let Some(limit) = fail_threshold {
+        if rate > limit {
+            anyhow::bail!(
+                "Subscriber lookup miss rate {:.2}% on {} exceeds --fail-on-miss-rate {:.2}% ({} of {} skipped); check prefixes against the subscriber database",
+                rate * 100.0,
+                day_str,
+                limit * 100.0,
+                summary.skipped_subscribers,
+                summary.active_subscribers + summary.inactive_subscribers + summary.skipped_subscribers
+            );
+        }
+    }
+
+    if rate > warn_threshold {
+        warn!(
+            skipped = summary.skipped_subscribers,
+            "Subscriber lookup miss rate {:.2}% on {} exceeds {:.2}%",
+            rate * 100.0,
+            day_str,
+            warn_threshold * 100.0
+        );
+    }
+
+    Ok(rate)
+}
+
+/// Render counts as a one-line ASCII sparkline, scaled to the largest value
+pub fn sparkline(values: &[usize]) -> String {
+    const LEVELS: &[u8] = b" .:-=+*#%@";
+    let max = values.iter().copied().max().unwrap_or(0);
+    values
+        .iter()
+        .map(|&v| match (v * (LEVELS.len() - 1)).checked_div(max) {
+            Some(level) => LEVELS[level] as char,
+            None => ' ',
+        })
+        .collect()
+}
+
+/// Aggregate statistics from all shards and write them to `day_dir/summary.json`
+pub fn create_daily_summary(stats: &[ShardStats], day_dir: &Path, expected_shards: usize) -> anyhow::Result<DailySummary> {
+    let summary = aggregate_shard_stats(stats, expected_shards, Vec::new());
+    write_summary(&summary, day_dir)?;
+    Ok(summary)
+}
 
-    // Find all stats files
-    let stats_files: Vec<_> = std::fs::read_dir(&day_dir)?
+/// Build the summary from `stats_shard*.json` files in `day_dir` (written with `write_shard_stats`).
+/// Unreadable files are skipped and listed in `warnings`; fails only if none could be read
+pub fn create_daily_summary_from_files(day_dir: &Path, expected_shards: usize) -> anyhow::Result<DailySummary> {
+    let mut stats_files: Vec<_> = std::fs::read_dir(day_dir)?
         .filter_map(|entry| entry.ok())
-        .filter(|entry| {
-            entry
-                .file_name()
-                .to_string_lossy()
-                .starts_with("stats_shard")
-                && entry.file_name().to_string_lossy().ends_with(".json")
+        .map(|entry| entry.path())
+        .filter(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            name.starts_with("stats_shard") && name.ends_with(".json")
         })
         .collect();
+    stats_files.sort();
 
-    summary.shards = stats_files.len();
+    let mut stats = Vec::with_capacity(stats_files.len());
+    let mut warnings = Vec::new();
 
-    for entry in stats_files {
-        let contents = std::fs::read_to_string(entry.path())?;
-        let shard_stats: serde_json::Value = serde_json::from_str(&contents)?;
-
-        if let Some(calls) = shard_stats.get("calls").and_then(|v| v.as_u64()) {
-            summary.total_calls += calls as usize;
-        }
-        if let Some(sms) = shard_stats.get("sms").and_then(|v| v.as_u64()) {
-            summary.total_sms += sms as usize;
+    for path in &stats_files {
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let parsed = std::fs::read_to_string(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| Ok(serde_json::from_str::<ShardStats>(&contents)?));
+        match parsed {
+            Ok(shard_stats) => stats.push(shard_stats),
+            Err(e) => {
+                warn!("Skipping unreadable stats file {}: {}", name, e);
+                warnings.push(format!("unreadable stats file: {}", name));
+            }
         }
-        if let Some(data) = shard_stats.get("data").and_then(|v| v.as_u64()) {
-            summary.total_data += data as usize;
+    }
+
+    if stats.is_empty() {
+        anyhow::bail!(
+            "No readable stats files in {:?} ({} found, all unreadable)",
+            day_dir,
+            stats_files.len()
+        );
+    }
+
+    let summary = aggregate_shard_stats(&stats, expected_shards, warnings);
+    write_summary(&summary, day_dir)?;
+    Ok(summary)
+}
+
+/// Sum shard stats into a summary without writing anything
+pub fn aggregate_shard_stats(stats: &[ShardStats], expected_shards: usize, mut warnings: Vec<String>) -> DailySummary {
+    if stats.len() != expected_shards {
+        warn!("Summary covers {} shards, expected {}", stats.len(), expected_shards);
+        warnings.push(format!("shard count {} does not match expected {}", stats.len(), expected_shards));
+    }
+
+    let mut summary = DailySummary {
+        shards: stats.len(),
+        expected_shards,
+        warnings,
+        ..DailySummary::default()
+    };
+
+    for shard_stats in stats {
+        summary.total_calls += shard_stats.calls;
+        summary.total_sms += shard_stats.sms;
+        summary.total_data += shard_stats.data;
+        summary.active_subscribers += shard_stats.active_subscribers;
+        summary.inactive_subscribers += shard_stats.inactive_subscribers;
+        summary.skipped_subscribers += shard_stats.skipped_subscribers;
+        summary.dirty_rows += shard_stats.dirty_rows;
+        summary.sms_unreachable += shard_stats.sms_unreachable;
+        summary.sms_retry_rows += shard_stats.sms_retry_rows;
+        summary.self_check_rows += shard_stats.self_check_rows;
+        summary.self_check_violations += shard_stats.self_check_violations;
+        for hour in 0..24 {
+            summary.calls_by_hour[hour] += shard_stats.calls_by_hour[hour];
+            summary.sms_by_hour[hour] += shard_stats.sms_by_hour[hour];
+            summary.data_by_hour[hour] += shard_stats.data_by_hour[hour];
         }
     }
 
-    // Write summary
+    summary
+}
+
+/// Write `summary` to `day_dir/summary.json`
+pub fn write_summary(summary: &DailySummary, day_dir: &Path) -> anyhow::Result<()> {
     let summary_path = day_dir.join("summary.json");
-    let summary_json = serde_json::to_string_pretty(&summary)?;
+    let summary_json = serde_json::to_string_pretty(summary)?;
     std::fs::write(summary_path, summary_json)?;
+    Ok(())
+}
 
-    Ok(summary)
+/// Summaries of generated days: `dir/summary.json` for a day directory, else the summary.json of every day
+/// directory in `dir`, by directory name
+pub fn read_day_summaries(dir: &Path) -> anyhow::Result<Vec<(String, DailySummary)>> {
+    let read = |path: &Path| -> anyhow::Result<DailySummary> {
+        let contents = std::fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?;
+        serde_json::from_str(&contents).with_context(|| format!("Failed to parse {:?}", path))
+    };
+    let name = |path: &Path| path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+    if dir.join("summary.json").exists() {
+        return Ok(vec![(name(dir), read(&dir.join("summary.json"))?)]);
+    }
+    let mut day_dirs: Vec<PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read {:?}", dir))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.join("summary.json").exists())
+        .collect();
+    day_dirs.sort();
+    if day_dirs.is_empty() {
+        anyhow::bail!("No summary.json in {:?} or its day directories", dir);
+    }
+    day_dirs.iter().map(|day_dir| Ok((name(day_dir), read(&day_dir.join("summary.json"))?))).collect()
 }
 
-/// Combine all CDR shard files for a day into a single compressed file
-pub fn bundle_day(out_dir: &Path, day: &DateTime<Tz>, cleanup: bool, compression_ext: &str) -> anyhow::Result<PathBuf> {
-    use rayon::prelude::*;
+/// Directories holding a day's part files under `layout`: the day directory, plus its `type=*` directories
+fn part_dirs(day_dir: &Path, layout: PartitionLayout) -> anyhow::Result<Vec<PathBuf>> {
+    let mut dirs = vec![day_dir.to_path_buf()];
+    if layout.splits_by_event_type() {
+        let mut type_dirs: Vec<PathBuf> = std::fs::read_dir(day_dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir() && path.file_name().unwrap_or_default().to_string_lossy().starts_with("type="))
+            .collect();
+        type_dirs.sort();
+        dirs.extend(type_dirs);
+    }
+    Ok(dirs)
+}
 
-    let day_str = day.format("%Y-%m-%d").to_string();
-    let day_dir = out_dir.join(&day_str);
+/// `cdr_` files in the part directories of a day whose name passes `keep`, relative to the day directory
+fn cdr_files_in(day_dir: &Path, layout: PartitionLayout, keep: impl Fn(&str) -> bool) -> anyhow::Result<Vec<String>> {
+    let mut files = Vec::new();
+    if !day_dir.exists() {
+        return Ok(files);
+    }
+    for dir in part_dirs(day_dir, layout)? {
+        for entry in std::fs::read_dir(&dir)?.filter_map(|entry| entry.ok()) {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with("cdr_") && keep(&name) {
+                let path = dir.join(&name);
+                let relative = path.strip_prefix(day_dir).unwrap_or(&path);
+                files.push(relative.to_string_lossy().replace('\\', "/"));
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Part files a writer never finished (`*.tmp`, left by a crash), relative to the day directory
+pub fn incomplete_part_files(day_dir: &Path, layout: PartitionLayout) -> anyhow::Result<Vec<String>> {
+    cdr_files_in(day_dir, layout, |name| name.ends_with(PART_TMP_SUFFIX))
+}
+
+/// Part files of any earlier run in a day directory, finished or not, relative to it
+pub fn existing_part_files(day_dir: &Path, layout: PartitionLayout) -> anyhow::Result<Vec<String>> {
+    cdr_files_in(day_dir, layout, |_| true)
+}
+
+/// Part files of `earlier` (an earlier run's, relative to the day directory) that the first `writer_tasks` writer
+/// tasks of this run wrote again under the same name, per their manifests
+pub fn replaced_part_files(day_dir: &Path, earlier: &[String], writer_tasks: usize) -> anyhow::Result<Vec<String>> {
+    let mut written = HashSet::new();
+    for writer in 0..writer_tasks {
+        let path = day_dir.join(format!("manifest_writer{:03}.json", writer));
+        if !path.exists() {
+            continue;
+        }
+        let manifest: ShardManifest = serde_json::from_str(&std::fs::read_to_string(&path)?)
+            .with_context(|| format!("Failed to parse manifest {:?}", path))?;
+        written.extend(manifest.files.into_iter().map(|entry| entry.file));
+    }
+    Ok(earlier.iter().filter(|file| written.contains(*file)).cloned().collect())
+}
+
+/// Day bundles of any earlier run in `out_dir` (`cdr_<day>.csv[.ext]`, whatever the codec)
+pub fn existing_bundles(out_dir: &Path, day_str: &str) -> Vec<PathBuf> {
+    CompressionType::ALL
+        .into_iter()
+        .map(|codec| out_dir.join(format!("cdr_{}.csv{}", day_str, codec.extension())))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// Ground truth logs a worker shard writes lazily, only once it damages or skews a row: a run that writes fewer of them
+/// than an earlier one does not replace them all
+fn is_ground_truth_file(name: &str) -> bool {
+    let per_shard = |prefix: &str| name.starts_with(prefix) && name.ends_with(".jsonl");
+    per_shard("dirty_shard") || per_shard("clock_skew_shard")
+}
 
+/// Per-day files of a run besides the part files: writer manifests and duplicates logs, worker shard stats,
+/// ground truth logs, summary, run config and provisioning delta
+fn is_run_file(name: &str) -> bool {
+    let per_task = |prefix: &str, extension: &str| name.starts_with(prefix) && name.ends_with(extension);
+    per_task("manifest_writer", ".json")
+        || per_task("stats_shard", ".json")
+        || per_task("duplicates_writer", ".jsonl")
+        || is_ground_truth_file(name)
+        || name == "summary.json"
+        || name == RUN_CONFIG_FILE
+        || name == PROVISIONING_FILE
+}
+
+/// Files in a day directory (not its event type directories) whose names match `keep`, sorted
+fn day_files_matching(day_dir: &Path, keep: fn(&str) -> bool) -> anyhow::Result<Vec<String>> {
+    if !day_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut files: Vec<String> = std::fs::read_dir(day_dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_file())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| keep(name))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Ground truth logs of any earlier run in a day directory
+pub fn existing_ground_truth_files(day_dir: &Path) -> anyhow::Result<Vec<String>> {
+    day_files_matching(day_dir, is_ground_truth_file)
+}
+
+/// Remove an earlier run's part files and per-day files from a day directory (`overwrite_policy: clean`),
+/// then the event type directories they leave empty. Returns the number of files removed
+pub fn clean_day_dir(day_dir: &Path, layout: PartitionLayout) -> anyhow::Result<usize> {
+    if !day_dir.exists() {
+        return Ok(0);
+    }
+    let mut removed = 0;
+    for file in existing_part_files(day_dir, layout)? {
+        std::fs::remove_file(day_dir.join(&file)).with_context(|| format!("Failed to remove {:?}", file))?;
+        removed += 1;
+    }
+    for file in day_files_matching(day_dir, is_run_file)? {
+        std::fs::remove_file(day_dir.join(&file)).with_context(|| format!("Failed to remove {:?}", file))?;
+        removed += 1;
+    }
+    for dir in &part_dirs(day_dir, layout)?[1..] {
+        if std::fs::read_dir(dir)?.next().is_none() {
+            std::fs::remove_dir(dir)?;
+        }
+    }
+    debug!("Removed {} files of an earlier run from {:?}", removed, day_dir);
+    Ok(removed)
+}
+
+/// Writer task (`_writerNNN`) a part file name belongs to
+fn part_file_writer(name: &str) -> Option<usize> {
+    let rest = &name[name.find("_writer")? + "_writer".len()..];
+    let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+    rest[..digits].parse().ok()
+}
+
+/// Finished CDR part files of a day, sorted by name, and the directories holding them.
+/// With `writer_tasks`, part files of any other writer task are from another run and refused
+fn day_part_files(
+    day_dir: &Path,
+    layout: PartitionLayout,
+    writer_tasks: Option<usize>,
+) -> anyhow::Result<(Vec<PathBuf>, Vec<PathBuf>)> {
     if !day_dir.exists() {
         anyhow::bail!("Day directory not found: {:?}", day_dir);
     }
 
+    // Part files sit in the day directory, or one directory per event type with `hive_date_type`
+    let part_dirs = part_dirs(day_dir, layout)?;
+
+    // Unfinished `.tmp` parts never match the codec extensions below; they are left out of the bundle
+    let incomplete = incomplete_part_files(day_dir, layout)?;
+    if !incomplete.is_empty() {
+        warn!("Skipping {} incomplete part files in {:?}: {}", incomplete.len(), day_dir, incomplete.join(", "));
+    }
+
     // Collect all compressed CDR shard files (sorted by name for consistent ordering)
-    // Support both .gz and .zst extensions
-    let mut cdr_files: Vec<_> = std::fs::read_dir(&day_dir)?
-        .filter_map(|entry| entry.ok())
-        .filter(|entry| {
-            let name = entry.file_name();
-            let name_str = name.to_string_lossy();
-            name_str.starts_with("cdr_") && (name_str.ends_with(".csv.gz") || name_str.ends_with(".csv.zst") || name_str.ends_with(".csv"))
-        })
-        .collect();
+    // Any codec extension is accepted, so a day can be bundled whatever it was written with
+    let mut cdr_files: Vec<PathBuf> = Vec::new();
+    for dir in &part_dirs {
+        cdr_files.extend(
+            std::fs::read_dir(dir)?
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    let name_str = path.file_name().unwrap_or_default().to_string_lossy();
+                    name_str.starts_with("cdr_")
+                        && CompressionType::ALL.iter().any(|c| name_str.ends_with(&format!(".csv{}", c.extension())))
+                }),
+        );
+    }
 
-    cdr_files.sort_by_key(|entry| entry.file_name());
+    cdr_files.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
 
+    // A run with fewer writer tasks than the one before leaves the old run's higher writers' parts behind
+    if let Some(writer_tasks) = writer_tasks {
+        let foreign: Vec<String> = cdr_files
+            .iter()
+            .map(|path| path.file_name().unwrap_or_default().to_string_lossy().into_owned())
+            .filter(|name| part_file_writer(name).is_none_or(|writer| writer >= writer_tasks))
+            .collect();
+        if !foreign.is_empty() {
+            anyhow::bail!(
+                "{} part files in {:?} are not from a run with {} writer tasks: {}; remove them or use overwrite_policy merge",
+                foreign.len(),
+                day_dir,
+                writer_tasks,
+                foreign.join(", ")
+            );
+        }
+    }
+
+    // Part files are only created for writer tasks with events, so a quiet day bundles into an empty file
     if cdr_files.is_empty() {
-        anyhow::bail!("No CDR files found in directory: {:?}", day_dir);
+        warn!("No CDR files found in directory: {:?}", day_dir);
+    }
+    Ok((part_dirs, cdr_files))
+}
+
+/// Newlines in `reader` to its end: the lines of a decoded part file or bundle (CSV fields hold no line breaks)
+fn count_lines(mut reader: impl Read) -> std::io::Result<u64> {
+    let mut buf = vec![0u8; 256 * 1024];
+    let mut lines = 0;
+    loop {
+        match reader.read(&mut buf)? {
+            0 => return Ok(lines),
+            n => lines += buf[..n].iter().filter(|&&b| b == b'\n').count() as u64,
+        }
+    }
+}
+
+/// Lines the decoded bundle of `cdr_files` must hold: per part its header and the `records` its writer's manifest
+/// lists, or, for parts no manifest lists (merged or delivered late), the lines of the decoded part
+fn expected_bundle_lines(day_dir: &Path, cdr_files: &[PathBuf]) -> anyhow::Result<u64> {
+    let mut records: HashMap<String, u64> = HashMap::new();
+    for entry in std::fs::read_dir(day_dir)?.filter_map(|entry| entry.ok()) {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with("manifest_writer") && name.ends_with(".json") {
+            let text = std::fs::read_to_string(entry.path())?;
+            let manifest: ShardManifest =
+                serde_json::from_str(&text).with_context(|| format!("Failed to parse manifest {:?}", entry.path()))?;
+            records.extend(manifest.files.into_iter().map(|file| (file.file, file.records)));
+        }
     }
 
+    let mut lines = 0;
+    for path in cdr_files {
+        let relative = path.strip_prefix(day_dir).unwrap_or(path).to_string_lossy().replace('\\', "/");
+        lines += match records.get(&relative) {
+            Some(records) => records + 1,
+            None => count_lines(open_decompressed(path)?).with_context(|| format!("Failed to decode {:?}", path))?,
+        };
+    }
+    Ok(lines)
+}
+
+/// Sync the bundle written to `tmp_path`, decode it to the end with `codec` and give it its final name
+/// if it holds `expected_lines` lines (and `expected_bytes` bytes, for concatenated parts).
+/// A bundle failing any check is deleted, so the part files stay the only copy of the day
+fn commit_bundle(
+    tmp_path: &Path,
+    output_path: &Path,
+    codec: CompressionType,
+    expected_lines: u64,
+    expected_bytes: Option<u64>,
+) -> anyhow::Result<()> {
+    let verify = || -> anyhow::Result<()> {
+        let file = File::open(tmp_path)?;
+        file.sync_all()?;
+        let bytes = file.metadata()?.len();
+        if let Some(expected) = expected_bytes.filter(|&expected| expected != bytes) {
+            anyhow::bail!("{} bytes written, {} expected", bytes, expected);
+        }
+        // An empty stream decodes to nothing whatever the codec
+        let lines = if bytes == 0 { 0 } else { count_lines(decode(codec, file)?).context("undecodable")? };
+        if lines != expected_lines {
+            anyhow::bail!("{} lines decoded, {} expected", lines, expected_lines);
+        }
+        Ok(())
+    };
+    if let Err(e) = verify() {
+        // Best effort: the error below is what matters
+        let _ = std::fs::remove_file(tmp_path);
+        anyhow::bail!("Bundle {:?} failed verification ({:#}); part files kept", output_path, e);
+    }
+    std::fs::rename(tmp_path, output_path)?;
+    sync_dir(output_path.parent().unwrap_or(Path::new(".")))?;
+    Ok(())
+}
+
+/// Run `write`, which writes a bundle to `tmp_path`, removing the partial file when it fails (a full disk)
+fn write_bundle(tmp_path: &Path, write: impl FnOnce() -> anyhow::Result<u64>) -> anyhow::Result<u64> {
+    write().inspect_err(|_| {
+        let _ = std::fs::remove_file(tmp_path);
+    })
+}
+
+/// Temporary name of a bundle until `commit_bundle` verified it
+fn bundle_tmp_path(output_path: &Path) -> PathBuf {
+    let mut name = output_path.as_os_str().to_owned();
+    name.push(PART_TMP_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// Remove bundled part files, then the event type directories they leave empty
+fn remove_part_files(part_dirs: &[PathBuf], cdr_files: &[PathBuf]) -> anyhow::Result<()> {
+    for path in cdr_files {
+        std::fs::remove_file(path)?;
+    }
+    for dir in &part_dirs[1..] {
+        if std::fs::read_dir(dir)?.next().is_none() {
+            std::fs::remove_dir(dir)?;
+        }
+    }
+    debug!("Cleaned up {} part files", cdr_files.len());
+    Ok(())
+}
+
+/// Combine all CDR shard files for a day (or day window, `2025-01-01_1300-1400`) into a single compressed file.
+/// Part files are collected from the `layout`'s day directory and its `type=*` directories; unfinished `.tmp` parts are skipped.
+/// The compressed parts are concatenated file by file, so they must share the bundle's codec (`compression_ext`).
+/// `writer_tasks` is the writer task count of the run that wrote the day; parts of other writer tasks fail the bundle,
+/// None takes every part (`overwrite_policy: merge`).
+/// The bundle is written under a `.tmp` name and only renamed (and the parts removed with `cleanup`) once it decodes
+/// to the rows the manifests list; a bundle that does not is deleted and the parts are left untouched
+pub fn bundle_day(
+    out_dir: &Path,
+    day_str: &str,
+    layout: PartitionLayout,
+    cleanup: bool,
+    compression_ext: &str,
+    writer_tasks: Option<usize>,
+) -> anyhow::Result<PathBuf> {
+    bundle_day_through(out_dir, day_str, layout, cleanup, compression_ext, writer_tasks, |file| Box::new(file))
+}
+
+/// `bundle_day` writing the bundle through `wrap`
+fn bundle_day_through(
+    out_dir: &Path,
+    day_str: &str,
+    layout: PartitionLayout,
+    cleanup: bool,
+    compression_ext: &str,
+    writer_tasks: Option<usize>,
+    wrap: impl FnOnce(File) -> Box<dyn Write>,
+) -> anyhow::Result<PathBuf> {
+    let codec = CompressionType::ALL
+        .into_iter()
+        .find(|codec| codec.extension() == compression_ext)
+        .ok_or_else(|| anyhow::anyhow!("No codec has the extension {:?}", compression_ext))?;
+    let day_dir = layout.day_dir(out_dir, day_str);
+    let (part_dirs, cdr_files) = day_part_files(&day_dir, layout, writer_tasks)?;
+    // Parts of another codec (an earlier run's, merged in) would not concatenate into one valid stream
+    let suffix = format!(".csv{}", compression_ext);
+    let mixed: Vec<String> = cdr_files
+        .iter()
+        .map(|path| path.file_name().unwrap_or_default().to_string_lossy().into_owned())
+        .filter(|name| !name.ends_with(&suffix))
+        .collect();
+    if !mixed.is_empty() {
+        anyhow::bail!(
+            "Day {}: {} part files are not {} like the bundle ({}, ...); mixed codecs cannot be concatenated, \
+             set compression_by_target.bundle to re-encode them",
+            day_str,
+            mixed.len(),
+            codec.name(),
+            mixed[0]
+        );
+    }
+    let expected_lines = expected_bundle_lines(&day_dir, &cdr_files)?;
+
     // Create final combined file path with appropriate extension
     let output_path = out_dir.join(format!("cdr_{}.csv{}", day_str, compression_ext));
+    let tmp_path = bundle_tmp_path(&output_path);
 
-    // Phase 1: Parallel read - read all files into memory in parallel
-    let file_contents: Vec<Vec<u8>> = cdr_files
-        .par_iter()
-        .map(|entry| {
-            std::fs::read(entry.path())
-                .map_err(|e| anyhow::anyhow!("Failed to read {:?}: {}", entry.path(), e))
-        })
-        .collect::<anyhow::Result<Vec<_>>>()?;
+    // Stream every part in order; memory stays at the copy buffer whatever the day's size
+    let bytes = write_bundle(&tmp_path, || {
+        let mut output = std::io::BufWriter::new(wrap(File::create(&tmp_path)?));
+        let mut bytes = 0;
+        for path in &cdr_files {
+            let mut part = File::open(path).map_err(|e| anyhow::anyhow!("Failed to read {:?}: {}", path, e))?;
+            bytes += std::io::copy(&mut part, &mut output)?;
+        }
+        output.flush()?;
+        Ok(bytes)
+    })?;
+    commit_bundle(&tmp_path, &output_path, codec, expected_lines, Some(bytes))?;
+
+    info!("Combined {} part files into: {:?}", cdr_files.len(), output_path);
 
-    // Phase 2: Sequential write - write all chunks in order
-    let mut output = File::create(&output_path)?;
-    for chunk in &file_contents {
-        std::io::Write::write_all(&mut output, chunk)?;
+    // Cleanup original shard files if requested, then the event type directories they leave empty
+    if cleanup {
+        remove_part_files(&part_dirs, &cdr_files)?;
     }
 
-    output.flush()?;
+    Ok(output_path)
+}
 
-    println!("Combined {} shard files into: {:?}", cdr_files.len(), output_path);
+/// Day bundle written with its own codec
+#[derive(Debug, Clone, PartialEq)]
+pub struct BundleReport {
+    pub path: PathBuf,
+    pub parts: usize,
+    /// Decompressed bytes of all parts, headers included
+    pub raw_bytes: u64,
+    pub bundle_bytes: u64,
+}
 
-    // Cleanup original shard files if requested
-    if cleanup {
-        for entry in &cdr_files {
-            std::fs::remove_file(entry.path())?;
+impl BundleReport {
+    /// Raw bytes per bundle byte (0 for an empty bundle)
+    pub fn ratio(&self) -> f64 {
+        if self.bundle_bytes == 0 {
+            0.0
+        } else {
+            self.raw_bytes as f64 / self.bundle_bytes as f64
         }
-        println!("Cleaned up {} shard files", cdr_files.len());
     }
+}
 
-    Ok(output_path)
+/// `bundle_day` for a bundle codec other than the parts' (`compression_by_target`): every part is decoded
+/// and streamed into one `compression` encoder, so the bundle decompresses to the parts' contents in order.
+/// The bundle is verified before it replaces the parts, as with `bundle_day`
+pub fn recompress_day(
+    out_dir: &Path,
+    day_str: &str,
+    layout: PartitionLayout,
+    cleanup: bool,
+    compression: CompressionType,
+    compression_level: Option<i32>,
+    writer_tasks: Option<usize>,
+) -> anyhow::Result<BundleReport> {
+    compression.ensure_available()?;
+    let day_dir = layout.day_dir(out_dir, day_str);
+    let (part_dirs, cdr_files) = day_part_files(&day_dir, layout, writer_tasks)?;
+    let expected_lines = expected_bundle_lines(&day_dir, &cdr_files)?;
+
+    let output_path = out_dir.join(format!("cdr_{}.csv{}", day_str, compression.extension()));
+    let tmp_path = bundle_tmp_path(&output_path);
+    let raw_bytes = write_bundle(&tmp_path, || {
+        let mut output = create_compressed_writer(File::create(&tmp_path)?, compression, compression_level, 0)?;
+        let mut raw_bytes = 0;
+        for path in &cdr_files {
+            let mut part = open_decompressed(path).with_context(|| format!("Failed to decode {:?}", path))?;
+            raw_bytes += std::io::copy(&mut part, &mut output)?;
+        }
+        output.finish_compression()?;
+        Ok(raw_bytes)
+    })?;
+    commit_bundle(&tmp_path, &output_path, compression, expected_lines, None)?;
+
+    let report = BundleReport {
+        parts: cdr_files.len(),
+        raw_bytes,
+        bundle_bytes: std::fs::metadata(&output_path)?.len(),
+        path: output_path,
+    };
+    info!(
+        "Recompressed {} part files into: {:?} ({} → {} bytes, ratio {:.2})",
+        report.parts,
+        report.path,
+        report.raw_bytes,
+        report.bundle_bytes,
+        report.ratio()
+    );
+
+    if cleanup {
+        remove_part_files(&part_dirs, &cdr_files)?;
+    }
+
+    Ok(report)
 }
 
 #[cfg(test)]
@@ -141,18 +811,103 @@ mod tests {
         let day_dir = dir.path().join(&day_str);
         fs::create_dir_all(&day_dir).unwrap();
 
-        // Create fake stats files
-        let stats1 = r#"{"shard": 0, "calls": 100, "sms": 200, "data": 300}"#;
-        fs::write(day_dir.join("stats_shard000.json"), stats1).unwrap();
-
-        let stats2 = r#"{"shard": 1, "calls": 150, "sms": 250, "data": 350}"#;
-        fs::write(day_dir.join("stats_shard001.json"), stats2).unwrap();
+        let stats = vec![
+            ShardStats {
+                shard: 0,
+                calls: 100,
+                sms: 200,
+                data: 300,
+                active_subscribers: 40,
+                inactive_subscribers: 2,
+                calls_by_hour: [1; 24],
+                ..ShardStats::default()
+            },
+            ShardStats {
+                shard: 1,
+                calls: 150,
+                sms: 250,
+                data: 350,
+                active_subscribers: 45,
+                inactive_subscribers: 3,
+                calls_by_hour: [2; 24],
+                ..ShardStats::default()
+            },
+        ];
 
-        let summary = create_daily_summary(dir.path(), &day).unwrap();
+        let summary = create_daily_summary(&stats, &day_dir, 2).unwrap();
+        assert!(day_dir.join("summary.json").exists());
         assert_eq!(summary.total_calls, 250);
         assert_eq!(summary.total_sms, 450);
         assert_eq!(summary.total_data, 650);
+        assert_eq!(summary.active_subscribers, 85);
+        assert_eq!(summary.inactive_subscribers, 5);
+        assert_eq!(summary.shards, 2);
+        assert!(summary.warnings.is_empty());
+        assert_eq!(summary.calls_by_hour, [3; 24]);
+    }
+
+    #[test]
+    fn test_sparkline() {
+        assert_eq!(sparkline(&[0, 0, 0]), "   ");
+        assert_eq!(sparkline(&[0, 5, 10]), " =@");
+        assert_eq!(sparkline(&[1; 24]).len(), 24);
+    }
+
+    #[test]
+    fn test_create_daily_summary_from_files_skips_corrupt() {
+        let dir = tempdir().unwrap();
+        let day_dir = dir.path().join("2025-01-01");
+        fs::create_dir_all(&day_dir).unwrap();
+
+        let stats1 = r#"{"shard": 0, "calls": 100, "sms": 200, "data": 300, "active_subscribers": 40, "inactive_subscribers": 2, "skipped_subscribers": 0}"#;
+        fs::write(day_dir.join("stats_shard000.json"), stats1).unwrap();
+        // Truncated by a crashed worker
+        fs::write(day_dir.join("stats_shard001.json"), r#"{"shard": 1, "calls": 15"#).unwrap();
+        let stats3 = r#"{"shard": 2, "calls": 50, "sms": 60, "data": 70, "active_subscribers": 10, "inactive_subscribers": 1, "skipped_subscribers": 0}"#;
+        fs::write(day_dir.join("stats_shard002.json"), stats3).unwrap();
+
+        let summary = create_daily_summary_from_files(&day_dir, 3).unwrap();
+        assert_eq!(summary.total_calls, 150);
+        assert_eq!(summary.total_sms, 260);
         assert_eq!(summary.shards, 2);
+        assert_eq!(summary.expected_shards, 3);
+        assert!(summary.warnings.iter().any(|w| w.contains("stats_shard001.json")));
+        assert!(summary.warnings.iter().any(|w| w.contains("expected 3")));
+
+        let written: DailySummary =
+            serde_json::from_str(&fs::read_to_string(day_dir.join("summary.json")).unwrap()).unwrap();
+        assert_eq!(written.warnings, summary.warnings);
+
+        // No readable stats at all is still an error
+        let empty_dir = dir.path().join("empty");
+        fs::create_dir_all(&empty_dir).unwrap();
+        fs::write(empty_dir.join("stats_shard000.json"), "{").unwrap();
+        assert!(create_daily_summary_from_files(&empty_dir, 1).is_err());
+    }
+
+    #[test]
+    fn test_check_miss_rate() {
+        let summary = DailySummary {
+            active_subscribers: 90,
+            inactive_subscribers: 0,
+            skipped_subscribers: 10,
+            shards: 1,
+            expected_shards: 1,
+            ..DailySummary::default()
+        };
+        assert!((summary.miss_rate() - 0.1).abs() < 1e-9);
+
+        // Above the warn threshold only: warning, no failure
+        assert!(check_miss_rate(&summary, "2025-01-01", 0.01, None).is_ok());
+        assert!(check_miss_rate(&summary, "2025-01-01", 0.01, Some(0.2)).is_ok());
+        // Above the fail threshold
+        assert!(check_miss_rate(&summary, "2025-01-01", 0.01, Some(0.05)).is_err());
+    }
+
+    fn write_gz(path: &Path, content: &str) {
+        let mut writer = create_compressed_writer(File::create(path).unwrap(), CompressionType::Gzip, None, 0).unwrap();
+        writer.write_all(content.as_bytes()).unwrap();
+        writer.finish_compression().unwrap();
     }
 
     #[test]
@@ -165,24 +920,16 @@ mod tests {
         let day_dir = dir.path().join(&day_str);
         fs::create_dir_all(&day_dir).unwrap();
 
-        // Create dummy CDR shard files
-        fs::write(
-            day_dir.join("cdr_2025-01-01_shard000_part001.csv"),
-            "header1;header2\ndata1;data2\n",
-        )
-        .unwrap();
-        fs::write(
-            day_dir.join("cdr_2025-01-01_shard001_part001.csv"),
-            "header1;header2\ndata3;data4\n",
-        )
-        .unwrap();
+        // Create dummy CDR shard files, compressed like the bundle
+        write_gz(&day_dir.join("cdr_2025-01-01_writer000_part001.csv.gz"), "header1;header2\ndata1;data2\n");
+        write_gz(&day_dir.join("cdr_2025-01-01_writer001_part001.csv.gz"), "header1;header2\ndata3;data4\n");
 
-        let gz_path = bundle_day(dir.path(), &day, false, ".gz").unwrap();
+        let gz_path = bundle_day(dir.path(), &day_str, PartitionLayout::Flat, false, ".gz", Some(2)).unwrap();
         assert!(gz_path.exists());
         assert!(gz_path.to_string_lossy().ends_with(".csv.gz"));
         // Original shard files should still exist when cleanup=false
-        assert!(day_dir.join("cdr_2025-01-01_shard000_part001.csv").exists());
-        assert!(day_dir.join("cdr_2025-01-01_shard001_part001.csv").exists());
+        assert!(day_dir.join("cdr_2025-01-01_writer000_part001.csv.gz").exists());
+        assert!(day_dir.join("cdr_2025-01-01_writer001_part001.csv.gz").exists());
     }
 
     #[test]
@@ -195,23 +942,225 @@ mod tests {
         let day_dir = dir.path().join(&day_str);
         fs::create_dir_all(&day_dir).unwrap();
 
-        // Create dummy CDR shard files
-        fs::write(
-            day_dir.join("cdr_2025-01-01_shard000_part001.csv"),
-            "header1;header2\ndata1;data2\n",
-        )
-        .unwrap();
-        fs::write(
-            day_dir.join("cdr_2025-01-01_shard001_part001.csv"),
-            "header1;header2\ndata3;data4\n",
-        )
-        .unwrap();
+        // Create dummy CDR shard files, compressed like the bundle
+        write_gz(&day_dir.join("cdr_2025-01-01_writer000_part001.csv.gz"), "header1;header2\ndata1;data2\n");
+        write_gz(&day_dir.join("cdr_2025-01-01_writer001_part001.csv.gz"), "header1;header2\ndata3;data4\n");
 
-        let gz_path = bundle_day(dir.path(), &day, true, ".gz").unwrap();
+        let gz_path = bundle_day(dir.path(), &day_str, PartitionLayout::Flat, true, ".gz", None).unwrap();
         assert!(gz_path.exists());
         assert!(gz_path.to_string_lossy().ends_with(".csv.gz"));
         // Original shard files should be deleted when cleanup=true
-        assert!(!day_dir.join("cdr_2025-01-01_shard000_part001.csv").exists());
-        assert!(!day_dir.join("cdr_2025-01-01_shard001_part001.csv").exists());
+        assert!(!day_dir.join("cdr_2025-01-01_writer000_part001.csv.gz").exists());
+        assert!(!day_dir.join("cdr_2025-01-01_writer001_part001.csv.gz").exists());
+    }
+
+    #[test]
+    fn test_recompress_day_decodes_parts() {
+        let dir = tempdir().unwrap();
+        let day_dir = dir.path().join("2025-01-01");
+        fs::create_dir_all(&day_dir).unwrap();
+        let contents = ["header1;header2\ndata1;data2\n", "header1;header2\ndata3;data4\n"];
+        for (shard, content) in contents.iter().enumerate() {
+            let path = day_dir.join(format!("cdr_2025-01-01_writer{:03}_part001.csv.gz", shard));
+            let mut writer = create_compressed_writer(File::create(path).unwrap(), CompressionType::Gzip, None, 0).unwrap();
+            writer.write_all(content.repeat(50).as_bytes()).unwrap();
+            writer.finish_compression().unwrap();
+        }
+
+        let report =
+            recompress_day(dir.path(), "2025-01-01", PartitionLayout::Flat, true, CompressionType::None, None, Some(2)).unwrap();
+        assert_eq!(report.path, dir.path().join("cdr_2025-01-01.csv"));
+        assert_eq!(report.parts, 2);
+        let expected = contents.map(|content| content.repeat(50)).concat();
+        assert_eq!(fs::read_to_string(&report.path).unwrap(), expected);
+        assert_eq!((report.raw_bytes, report.bundle_bytes), (expected.len() as u64, expected.len() as u64));
+        assert_eq!(report.ratio(), 1.0);
+        assert!(fs::read_dir(&day_dir).unwrap().next().is_none());
+    }
+
+    #[test]
+    fn test_bundle_day_hive_date_type() {
+        let dir = tempdir().unwrap();
+        let day_dir = dir.path().join("date=2025-01-01");
+        fs::create_dir_all(day_dir.join("type=CALL")).unwrap();
+        fs::create_dir_all(day_dir.join("type=SMS")).unwrap();
+        fs::write(day_dir.join("type=CALL/cdr_2025-01-01_writer000_call_part001.csv"), "h\ncall\n").unwrap();
+        fs::write(day_dir.join("type=SMS/cdr_2025-01-01_writer000_sms_part001.csv"), "h\nsms\n").unwrap();
+        fs::write(day_dir.join("summary.json"), "{}").unwrap();
+
+        let path = bundle_day(dir.path(), "2025-01-01", PartitionLayout::HiveDateType, true, "", Some(1)).unwrap();
+        assert_eq!(path, dir.path().join("cdr_2025-01-01.csv"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "h\ncall\nh\nsms\n");
+        // Emptied event type directories go with their part files, the day directory stays
+        assert!(!day_dir.join("type=CALL").exists());
+        assert!(!day_dir.join("type=SMS").exists());
+        assert!(day_dir.join("summary.json").exists());
+    }
+
+    #[test]
+    fn test_bundle_day_skips_incomplete_parts() {
+        let dir = tempdir().unwrap();
+        let day_dir = dir.path().join("2025-01-01");
+        fs::create_dir_all(&day_dir).unwrap();
+        fs::write(day_dir.join("cdr_2025-01-01_writer000_part001.csv"), "h\ndone\n").unwrap();
+        fs::write(day_dir.join("cdr_2025-01-01_writer000_part002.csv.tmp"), "h\npart").unwrap();
+
+        assert_eq!(
+            incomplete_part_files(&day_dir, PartitionLayout::Flat).unwrap(),
+            vec!["cdr_2025-01-01_writer000_part002.csv.tmp".to_string()]
+        );
+        let path = bundle_day(dir.path(), "2025-01-01", PartitionLayout::Flat, true, "", Some(1)).unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), "h\ndone\n");
+        // Left in place for inspection
+        assert!(day_dir.join("cdr_2025-01-01_writer000_part002.csv.tmp").exists());
+    }
+
+    #[test]
+    fn test_bundle_day_refuses_parts_of_other_writer_tasks() {
+        let dir = tempdir().unwrap();
+        let day_dir = dir.path().join("2025-01-01");
+        fs::create_dir_all(&day_dir).unwrap();
+        fs::write(day_dir.join("cdr_2025-01-01_writer000_part001.csv"), "h\nnew\n").unwrap();
+        // Left by an earlier run with more writer tasks
+        fs::write(day_dir.join("cdr_2025-01-01_writer001_part001.csv"), "h\nold\n").unwrap();
+        assert_eq!(part_file_writer("cdr_2025-01-01_1300-1400_writer012_call_part001.csv"), Some(12));
+
+        let err = bundle_day(dir.path(), "2025-01-01", PartitionLayout::Flat, false, "", Some(1)).unwrap_err();
+        assert!(err.to_string().contains("cdr_2025-01-01_writer001_part001.csv"), "{}", err);
+        let path = bundle_day(dir.path(), "2025-01-01", PartitionLayout::Flat, false, "", None).unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), "h\nnew\nh\nold\n");
+    }
+
+    #[test]
+    fn test_bundle_day_refuses_mixed_codecs() {
+        let dir = tempdir().unwrap();
+        let day_dir = dir.path().join("2025-01-01");
+        fs::create_dir_all(&day_dir).unwrap();
+        write_gz(&day_dir.join("cdr_2025-01-01_writer000_part001.csv.gz"), "h\nnew\n");
+        // Merged in from an earlier uncompressed run
+        fs::write(day_dir.join("cdr_2025-01-01_writer000_part002.csv"), "h\nold\n").unwrap();
+
+        let err = bundle_day(dir.path(), "2025-01-01", PartitionLayout::Flat, false, ".gz", None).unwrap_err();
+        assert!(err.to_string().contains("cdr_2025-01-01_writer000_part002.csv"), "{}", err);
+        assert!(!dir.path().join("cdr_2025-01-01.csv.gz").exists());
+        assert!(!bundle_tmp_path(&dir.path().join("cdr_2025-01-01.csv.gz")).exists());
+        // Decoding each part by its own extension still works
+        let report = recompress_day(dir.path(), "2025-01-01", PartitionLayout::Flat, false, CompressionType::Gzip, None, None).unwrap();
+        assert_eq!(report.parts, 2);
+    }
+
+    /// Accepts every write but keeps only the first `limit` bytes, like a copy cut short without an error
+    struct TruncatingWriter {
+        inner: File,
+        limit: usize,
+    }
+
+    impl Write for TruncatingWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            let kept = buf.len().min(self.limit);
+            self.inner.write_all(&buf[..kept])?;
+            self.limit -= kept;
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.inner.flush()
+        }
+    }
+
+    #[test]
+    fn test_truncated_bundle_keeps_parts() {
+        let dir = tempdir().unwrap();
+        let day_dir = dir.path().join("2025-01-01");
+        fs::create_dir_all(&day_dir).unwrap();
+        let parts = ["cdr_2025-01-01_writer000_part001.csv.gz", "cdr_2025-01-01_writer001_part001.csv.gz"];
+        for part in parts {
+            write_gz(&day_dir.join(part), &"h1;h2\nd1;d2\n".repeat(100));
+        }
+
+        let truncate = |file| -> Box<dyn Write> { Box::new(TruncatingWriter { inner: file, limit: 100 }) };
+        let err =
+            bundle_day_through(dir.path(), "2025-01-01", PartitionLayout::Flat, true, ".gz", Some(2), truncate).unwrap_err();
+        assert!(err.to_string().contains("failed verification"), "{}", err);
+        // Parts untouched, neither the bundle nor its temporary file left behind
+        assert!(parts.iter().all(|part| day_dir.join(part).exists()));
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        // The same day bundles once the copy is complete
+        let path = bundle_day(dir.path(), "2025-01-01", PartitionLayout::Flat, true, ".gz", Some(2)).unwrap();
+        assert_eq!(count_lines(open_decompressed(&path).unwrap()).unwrap(), 400);
+        assert!(!day_dir.join(parts[0]).exists());
+    }
+
+    #[test]
+    fn test_bundle_is_checked_against_manifests() {
+        let dir = tempdir().unwrap();
+        let day_dir = dir.path().join("2025-01-01");
+        fs::create_dir_all(&day_dir).unwrap();
+        fs::write(day_dir.join("cdr_2025-01-01_writer000_part001.csv"), "h\na\nb\n").unwrap();
+        let manifest = |records: u64| {
+            let manifest = serde_json::json!({
+                "day": "2025-01-01",
+                "writer": 0,
+                "files": [
+                    {"file": "cdr_2025-01-01_writer000_part001.csv", "file_seq": 1, "records": records, "duplicates": 0, "bytes": 6}
+                ],
+            });
+            fs::write(day_dir.join("manifest_writer000.json"), manifest.to_string()).unwrap();
+        };
+
+        // The manifest lists a row the part does not hold
+        let recompress = || recompress_day(dir.path(), "2025-01-01", PartitionLayout::Flat, true, CompressionType::Gzip, None, Some(1));
+        manifest(3);
+        let err = recompress().unwrap_err();
+        assert!(err.to_string().contains("3 lines decoded, 4 expected"), "{}", err);
+        assert!(day_dir.join("cdr_2025-01-01_writer000_part001.csv").exists());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        manifest(2);
+        assert_eq!(recompress().unwrap().path, dir.path().join("cdr_2025-01-01.csv.gz"));
+    }
+
+    #[test]
+    fn test_cut_compressed_stream_fails_to_decode() {
+        let dir = tempdir().unwrap();
+        let (tmp, output) = (dir.path().join("cdr.csv.gz.tmp"), dir.path().join("cdr.csv.gz"));
+        let mut writer = create_compressed_writer(File::create(&tmp).unwrap(), CompressionType::Gzip, None, 0).unwrap();
+        writer.write_all("h\nrow\n".repeat(1000).as_bytes()).unwrap();
+        writer.finish_compression().unwrap();
+        drop(writer);
+        let len = fs::metadata(&tmp).unwrap().len();
+        File::options().write(true).open(&tmp).unwrap().set_len(len - 10).unwrap();
+
+        let err = commit_bundle(&tmp, &output, CompressionType::Gzip, 2000, None).unwrap_err();
+        assert!(err.to_string().contains("undecodable"), "{}", err);
+        assert!(!tmp.exists() && !output.exists());
+    }
+
+    #[test]
+    fn test_clean_day_dir() {
+        let dir = tempdir().unwrap();
+        let day_dir = dir.path().join("date=2025-01-01");
+        fs::create_dir_all(day_dir.join("type=CALL")).unwrap();
+        for file in [
+            "type=CALL/cdr_2025-01-01_writer000_call_part001.csv.gz",
+            "type=CALL/cdr_2025-01-01_writer001_call_part002.csv.gz.tmp",
+            "manifest_writer000.json",
+            "stats_shard003.json",
+            "duplicates_writer000.jsonl",
+            "summary.json",
+            "run_config.yaml",
+            "provisioning.csv",
+            "notes.txt",
+        ] {
+            fs::write(day_dir.join(file), "x").unwrap();
+        }
+
+        assert_eq!(existing_part_files(&day_dir, PartitionLayout::HiveDateType).unwrap().len(), 2);
+        assert_eq!(clean_day_dir(&day_dir, PartitionLayout::HiveDateType).unwrap(), 8);
+        // Files the generator does not write are not its to remove
+        let left: Vec<_> = fs::read_dir(&day_dir).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+        assert_eq!(left, vec!["notes.txt"]);
+        assert_eq!(clean_day_dir(&dir.path().join("missing"), PartitionLayout::Flat).unwrap(), 0);
     }
 }
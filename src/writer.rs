@@ -1,150 +1,501 @@
 // CSV event writer with file rotation
 use csv::{Writer, WriterBuilder};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use serde::Serialize;
+use std::collections::BTreeMap;
 use std::fs::File;
+use std::io::{BufWriter, Write};
 use std::path::{Path, PathBuf};
-use crate::compression::{create_compressed_writer, CompressedWriter, CompressionType};
+use std::time::Instant;
+use crate::async_writer::{EventBatch, SerializedBatch};
+use crate::config::{MsisdnFormat, OutputFormat, PartitionLayout, RotationPolicy};
+use crate::compression::{create_compressed_writer, CompressedWriter, CompressionType, FailingWriter};
+use crate::identity::Imei;
+use crate::schema::{ColumnSchema, OutputSchema};
+use tracing::{debug, warn};
 
 // EventRow with primitive types for zero-copy performance
-// Serde will handle conversion to strings during serialization
-#[derive(Debug, Clone, Serialize)]
+// `COLUMNS` converts the fields to CSV cells during serialization
+#[derive(Debug, Clone, Default)]
 pub struct EventRow {
-    #[serde(serialize_with = "serialize_str")]
     pub event_type: &'static str,
-    #[serde(serialize_with = "serialize_u64")]
+    /// Empty when 0; written in `WriterOptions::msisdn`'s format
     pub msisdn_src: u64,
-    #[serde(serialize_with = "serialize_u64")]
     pub msisdn_dst: u64,
-    #[serde(serialize_with = "serialize_str")]
     pub direction: &'static str,
     pub start_ts_ms: i64,
     pub end_ts_ms: i64,
-    #[serde(serialize_with = "serialize_str")]
     pub tz_name: &'static str,
     pub tz_offset_min: i32,
     pub duration_sec: i64,
-    #[serde(serialize_with = "serialize_u32")]
     pub mccmnc: u32,
-    #[serde(serialize_with = "serialize_u64")]
     pub imsi: u64,
-    #[serde(serialize_with = "serialize_u64")]
     pub imei: u64,
+    /// IMEISV of the device on DATA rows (`Imei::imeisv`), with `emit_imeisv`
+    /// (empty when 0 or `IMEISV_NONE`)
+    pub imeisv: u64,
     pub cell_id: u32,
-    #[serde(serialize_with = "serialize_str")]
     pub record_type: &'static str,
-    #[serde(serialize_with = "serialize_str")]
+    /// MSC/SGSN/PGW element that wrote the record ("msc01"), with `emit_node_id`; empty otherwise
+    pub node_id: &'static str,
     pub cause_for_record_closing: &'static str,
-    #[serde(serialize_with = "serialize_u32_or_empty")]
+    /// Empty when 0: an SMS has at least one segment, so 0 only marks other event types
     pub sms_segments: u32,
-    #[serde(serialize_with = "serialize_str")]
     pub sms_status: &'static str,
-    #[serde(serialize_with = "serialize_u64_or_empty")]
-    pub data_bytes_in: u64,
-    #[serde(serialize_with = "serialize_u64_or_empty")]
-    pub data_bytes_out: u64,
-    #[serde(serialize_with = "serialize_i64_or_empty")]
-    pub data_duration_sec: i64,
-    #[serde(serialize_with = "serialize_str")]
+    /// Data session columns: `None` (empty) on CALL/SMS rows, so a session with 0 bytes or 0 s still renders "0"
+    pub data_bytes_in: Option<u64>,
+    pub data_bytes_out: Option<u64>,
+    pub data_duration_sec: Option<i64>,
     pub apn: &'static str,
-    #[serde(serialize_with = "serialize_str")]
     pub rat: &'static str,
+    /// QCI (5QI on NR) of the DATA session's bearer, with `emit_bearer_fields`
+    /// (empty when 0 or `BEARER_FIELD_NONE`)
+    pub qci: u32,
+    /// Bearer of the DATA session, counted from 1 over the subscriber's sessions of the day; like `qci`
+    pub bearer_id: u32,
+    /// SMSC message reference shared by all records of one SMS, with `sms_unreachable_rate`
+    /// (empty when 0 or `CORRELATION_ID_NONE`)
+    pub correlation_id: u64,
+    /// Start of the served subscriber's previous event that day, with `emit_prev_event_ts`
+    /// (empty when 0 or `PREV_EVENT_TS_NONE`)
+    pub prev_event_ts_ms: i64,
+    /// Per-file record sequence number of a row read back from a part file; the writer stamps
+    /// its own when writing (`EventWriter::write_stamped`)
+    pub record_seq: u64,
 }
 
-// Custom serializers for efficient conversion
-fn serialize_str<S>(value: &&str, serializer: S) -> Result<S::Ok, S::Error>
-where
-    S: serde::Serializer,
-{
-    serializer.serialize_str(value)
+/// `qci` / `bearer_id` of a row that has none while the columns are emitted: the cells are empty
+pub const BEARER_FIELD_NONE: u32 = u32::MAX;
+
+/// `prev_event_ts_ms` of a subscriber's first event of the day: the column is there but empty
+pub const PREV_EVENT_TS_NONE: i64 = -1;
+
+/// `correlation_id` of a row that has none while the column is emitted: the cell is empty
+pub const CORRELATION_ID_NONE: u64 = u64::MAX;
+
+/// `imeisv` of a row that has none while the column is emitted: the cell is empty
+pub const IMEISV_NONE: u64 = u64::MAX;
+
+/// 14 TAC + serial digits and the 2-digit software version
+const IMEISV_DIGITS: usize = 16;
+
+const ALL: &[&str] = &["CALL", "SMS", "DATA"];
+
+/// One column of the part files: its schema, whether a part file has it and how a row fills its cell
+pub struct Column {
+    pub name: &'static str,
+    pub data_type: &'static str,
+    /// The cell can be empty (event types it does not apply to, or no value)
+    pub nullable: bool,
+    pub example: &'static str,
+    /// Event types whose rows fill the column
+    pub event_types: &'static [&'static str],
+    pub description: &'static str,
+    /// The part files written with these options have the column
+    present: fn(&WriterOptions) -> bool,
+    /// Append the cell of a row to the buffer, nothing for an empty cell. None for `record_seq`,
+    /// which the writer stamps per part file
+    cell: Option<fn(&EventRow, &WriterOptions, &mut Vec<u8>)>,
 }
 
-fn serialize_u64<S>(value: &u64, serializer: S) -> Result<S::Ok, S::Error>
-where
-    S: serde::Serializer,
-{
-    if *value == 0 {
-        serializer.serialize_str("")
-    } else {
-        let mut buf = itoa::Buffer::new();
-        serializer.serialize_str(buf.format(*value))
-    }
+/// Every column the writer can emit, in header order: the header, each row's cells and `schema.json` follow it
+pub const COLUMNS: &[Column] = &[
+    Column {
+        name: "event_type",
+        data_type: "string",
+        nullable: false,
+        example: "CALL",
+        event_types: ALL,
+        description: "CALL, SMS or DATA",
+        present: always,
+        cell: Some(|row, _, cell| push_str(cell, row.event_type)),
+    },
+    Column {
+        name: "msisdn_src",
+        data_type: "string",
+        nullable: false,
+        example: "31612345678",
+        event_types: ALL,
+        description: "Calling or sending party; the served subscriber on MO and DATA rows. Written in `msisdn_format`",
+        present: always,
+        cell: Some(|row, options, cell| push_msisdn(cell, row.msisdn_src, options)),
+    },
+    Column {
+        name: "msisdn_dst",
+        data_type: "string",
+        nullable: true,
+        example: "31687654321",
+        event_types: &["CALL", "SMS"],
+        description: "Called or receiving party; the served subscriber on MT rows. Written in `msisdn_format`",
+        present: always,
+        cell: Some(|row, options, cell| push_msisdn(cell, row.msisdn_dst, options)),
+    },
+    Column {
+        name: "direction",
+        data_type: "string",
+        nullable: false,
+        example: "MO",
+        event_types: ALL,
+        description: "MO (mobile originated) or MT (mobile terminated); DATA rows are MO",
+        present: always,
+        cell: Some(|row, _, cell| push_str(cell, row.direction)),
+    },
+    Column {
+        name: "start_ts_ms",
+        data_type: "int64",
+        nullable: false,
+        example: "1735710480000",
+        event_types: ALL,
+        description: "Start, milliseconds since the Unix epoch",
+        present: always,
+        cell: Some(|row, _, cell| push_int(cell, row.start_ts_ms)),
+    },
+    Column {
+        name: "end_ts_ms",
+        data_type: "int64",
+        nullable: false,
+        example: "1735710561000",
+        event_types: ALL,
+        description: "End, milliseconds since the Unix epoch",
+        present: always,
+        cell: Some(|row, _, cell| push_int(cell, row.end_ts_ms)),
+    },
+    Column {
+        name: "tz_name",
+        data_type: "string",
+        nullable: false,
+        example: "Europe/Amsterdam",
+        event_types: ALL,
+        description: "IANA timezone of the local time",
+        present: timezone_columns,
+        cell: Some(|row, _, cell| push_str(cell, row.tz_name)),
+    },
+    Column {
+        name: "tz_offset_min",
+        data_type: "int32",
+        nullable: false,
+        example: "60",
+        event_types: ALL,
+        description: "UTC offset of `tz_name` at the start, in minutes",
+        present: timezone_columns,
+        cell: Some(|row, _, cell| push_int(cell, row.tz_offset_min)),
+    },
+    Column {
+        name: "duration_sec",
+        data_type: "int64",
+        nullable: false,
+        example: "81",
+        event_types: ALL,
+        description: "End minus start, in seconds",
+        present: always,
+        cell: Some(|row, _, cell| push_int(cell, row.duration_sec)),
+    },
+    Column {
+        name: "mccmnc",
+        data_type: "string",
+        nullable: false,
+        example: "20416",
+        event_types: ALL,
+        description: "Home network (MCC + MNC) of the served subscriber",
+        present: always,
+        cell: Some(|row, _, cell| push_nonzero(cell, row.mccmnc as u64)),
+    },
+    Column {
+        name: "imsi",
+        data_type: "string",
+        nullable: false,
+        example: "204163181354007",
+        event_types: ALL,
+        description: "IMSI of the served subscriber",
+        present: always,
+        cell: Some(|row, _, cell| push_nonzero(cell, row.imsi)),
+    },
+    // IMEIs are fixed-width: zero-pad so a TAC starting with 0 keeps its digit
+    Column {
+        name: "imei",
+        data_type: "string",
+        nullable: false,
+        example: "902432926813213",
+        event_types: ALL,
+        description: "15-digit IMEI of the served subscriber's device, zero-padded",
+        present: always,
+        cell: Some(|row, _, cell| push_zero_padded(cell, row.imei, Imei::MAX_DIGITS)),
+    },
+    Column {
+        name: "imeisv",
+        data_type: "string",
+        nullable: true,
+        example: "9024329268132104",
+        event_types: &["DATA"],
+        description: "16-digit IMEISV (IMEI without check digit + software version), with `emit_imeisv`",
+        present: |options| options.emit_imeisv,
+        cell: Some(|row, _, cell| push_zero_padded(cell, none_as_zero(row.imeisv, IMEISV_NONE), IMEISV_DIGITS)),
+    },
+    Column {
+        name: "cell_id",
+        data_type: "uint32",
+        nullable: false,
+        example: "66009",
+        event_types: ALL,
+        description: "Serving cell, a `cell_id` of cells.csv",
+        present: always,
+        cell: Some(|row, _, cell| push_int(cell, row.cell_id)),
+    },
+    Column {
+        name: "record_type",
+        data_type: "string",
+        nullable: false,
+        example: "mscVoiceRecord",
+        event_types: ALL,
+        description: "3GPP record type in the dialect of `record_type_profile`; by default mscVoiceRecord, sgsnSMORecord, sgsnSMTRecord, sgsnPDPRecord or pgwRecord",
+        present: always,
+        cell: Some(|row, _, cell| push_str(cell, row.record_type)),
+    },
+    Column {
+        name: "node_id",
+        data_type: "string",
+        nullable: false,
+        example: "msc01",
+        event_types: ALL,
+        description: "Element that wrote the record: an MSC for CALL and SMS, an SGSN or PGW for DATA; with `emit_node_id`",
+        present: |options| options.emit_node_id,
+        cell: Some(|row, _, cell| push_str(cell, row.node_id)),
+    },
+    Column {
+        name: "cause_for_record_closing",
+        data_type: "string",
+        nullable: false,
+        example: "normalRelease",
+        event_types: ALL,
+        description: "3GPP cause for record closing; DATA causes are drawn from `data_close_causes`",
+        present: always,
+        cell: Some(|row, _, cell| push_str(cell, row.cause_for_record_closing)),
+    },
+    Column {
+        name: "sms_segments",
+        data_type: "uint32",
+        nullable: true,
+        example: "1",
+        event_types: &["SMS"],
+        description: "Number of concatenated SMS segments",
+        present: always,
+        cell: Some(|row, _, cell| push_nonzero(cell, row.sms_segments as u64)),
+    },
+    Column {
+        name: "sms_status",
+        data_type: "string",
+        nullable: true,
+        example: "DELIVERED",
+        event_types: &["SMS"],
+        description: "SENT, DELIVERED, FAILED, ATTEMPT or EXPIRED",
+        present: always,
+        cell: Some(|row, _, cell| push_str(cell, row.sms_status)),
+    },
+    Column {
+        name: "data_bytes_in",
+        data_type: "uint64",
+        nullable: true,
+        example: "393652",
+        event_types: &["DATA"],
+        description: "Downlink volume of the session, bytes",
+        present: always,
+        cell: Some(|row, _, cell| push_option(cell, row.data_bytes_in)),
+    },
+    Column {
+        name: "data_bytes_out",
+        data_type: "uint64",
+        nullable: true,
+        example: "75669",
+        event_types: &["DATA"],
+        description: "Uplink volume of the session, bytes",
+        present: always,
+        cell: Some(|row, _, cell| push_option(cell, row.data_bytes_out)),
+    },
+    Column {
+        name: "data_duration_sec",
+        data_type: "int64",
+        nullable: true,
+        example: "375",
+        event_types: &["DATA"],
+        description: "Session duration, seconds",
+        present: always,
+        cell: Some(|row, _, cell| push_option(cell, row.data_duration_sec)),
+    },
+    Column {
+        name: "apn",
+        data_type: "string",
+        nullable: true,
+        example: "internet",
+        event_types: &["DATA"],
+        description: "Access point name of the session",
+        present: always,
+        cell: Some(|row, _, cell| push_str(cell, row.apn)),
+    },
+    Column {
+        name: "rat",
+        data_type: "string",
+        nullable: true,
+        example: "LTE",
+        event_types: &["DATA"],
+        description: "Radio access technology of the session",
+        present: always,
+        cell: Some(|row, _, cell| push_str(cell, row.rat)),
+    },
+    Column {
+        name: "qci",
+        data_type: "uint32",
+        nullable: true,
+        example: "9",
+        event_types: &["DATA"],
+        description: "QCI (1-9) of the session's bearer, 5QI (1-255) on NR; drawn per APN from `qci_by_apn` / `nr_5qi_by_apn`, with `emit_bearer_fields`",
+        present: |options| options.emit_bearer_fields,
+        cell: Some(|row, _, cell| push_nonzero(cell, none_as_zero(row.qci as u64, BEARER_FIELD_NONE as u64))),
+    },
+    Column {
+        name: "bearer_id",
+        data_type: "uint32",
+        nullable: true,
+        example: "1",
+        event_types: &["DATA"],
+        description: "Bearer of the session, counted from 1 over the subscriber's DATA sessions of the day; with `emit_bearer_fields`",
+        present: |options| options.emit_bearer_fields,
+        cell: Some(|row, _, cell| push_nonzero(cell, none_as_zero(row.bearer_id as u64, BEARER_FIELD_NONE as u64))),
+    },
+    Column {
+        name: "correlation_id",
+        data_type: "uint64",
+        nullable: true,
+        example: "17663920960864842712",
+        event_types: &["SMS"],
+        description: "SMSC message reference shared by all records of one SMS, with `sms_unreachable_rate` above 0",
+        present: |options| options.emit_correlation_id,
+        cell: Some(|row, _, cell| push_nonzero(cell, none_as_zero(row.correlation_id, CORRELATION_ID_NONE))),
+    },
+    Column {
+        name: "prev_event_ts_ms",
+        data_type: "int64",
+        nullable: true,
+        example: "1735716389000",
+        event_types: ALL,
+        description: "Start of the served subscriber's previous event that day, empty on the first; with `emit_prev_event_ts`",
+        present: |options| options.emit_prev_event_ts,
+        // PREV_EVENT_TS_NONE is negative
+        cell: Some(|row, _, cell| push_nonzero(cell, row.prev_event_ts_ms.max(0) as u64)),
+    },
+    Column {
+        name: "record_seq",
+        data_type: "uint64",
+        nullable: false,
+        example: "1",
+        event_types: ALL,
+        description: "Record number from 1 within each part file, with `emit_record_seq`",
+        present: |options| options.emit_record_seq,
+        cell: None,
+    },
+];
+
+fn always(_: &WriterOptions) -> bool {
+    true
 }
 
-fn serialize_u32<S>(value: &u32, serializer: S) -> Result<S::Ok, S::Error>
-where
-    S: serde::Serializer,
-{
-    if *value == 0 {
-        serializer.serialize_str("")
-    } else {
-        let mut buf = itoa::Buffer::new();
-        serializer.serialize_str(buf.format(*value))
+fn timezone_columns(options: &WriterOptions) -> bool {
+    !options.omit_timezone_columns
+}
+
+/// 0 for the `none` placeholder of an emitted column, so the cell is empty
+fn none_as_zero(value: u64, none: u64) -> u64 {
+    if value == none { 0 } else { value }
+}
+
+fn push_str(cell: &mut Vec<u8>, value: &str) {
+    cell.extend_from_slice(value.as_bytes());
+}
+
+fn push_int<I: itoa::Integer>(cell: &mut Vec<u8>, value: I) {
+    cell.extend_from_slice(itoa::Buffer::new().format(value).as_bytes());
+}
+
+/// Empty when 0
+fn push_nonzero(cell: &mut Vec<u8>, value: u64) {
+    if value != 0 {
+        push_int(cell, value);
     }
 }
 
-fn serialize_u64_or_empty<S>(value: &u64, serializer: S) -> Result<S::Ok, S::Error>
-where
-    S: serde::Serializer,
-{
-    if *value == 0 {
-        serializer.serialize_str("")
-    } else {
-        let mut buf = itoa::Buffer::new();
-        serializer.serialize_str(buf.format(*value))
+fn push_option<I: itoa::Integer>(cell: &mut Vec<u8>, value: Option<I>) {
+    if let Some(value) = value {
+        push_int(cell, value);
     }
 }
 
-fn serialize_u32_or_empty<S>(value: &u32, serializer: S) -> Result<S::Ok, S::Error>
-where
-    S: serde::Serializer,
-{
-    if *value == 0 {
-        serializer.serialize_str("")
-    } else {
-        let mut buf = itoa::Buffer::new();
-        serializer.serialize_str(buf.format(*value))
+/// `width` digits with leading zeros, empty when 0
+fn push_zero_padded(cell: &mut Vec<u8>, value: u64, width: usize) {
+    if value == 0 {
+        return;
     }
+    let mut buf = itoa::Buffer::new();
+    let digits = buf.format(value);
+    cell.resize(cell.len() + width.saturating_sub(digits.len()), b'0');
+    cell.extend_from_slice(digits.as_bytes());
 }
 
-fn serialize_i64_or_empty<S>(value: &i64, serializer: S) -> Result<S::Ok, S::Error>
-where
-    S: serde::Serializer,
-{
-    if *value == 0 {
-        serializer.serialize_str("")
-    } else {
-        let mut buf = itoa::Buffer::new();
-        serializer.serialize_str(buf.format(*value))
+/// Empty when 0, otherwise in the options' MSISDN format
+fn push_msisdn(cell: &mut Vec<u8>, msisdn: u64, options: &WriterOptions) {
+    if msisdn != 0 {
+        let mut buf = [0u8; 24];
+        push_str(cell, options.msisdn.render(msisdn, &mut buf));
     }
 }
 
-impl Default for EventRow {
-    fn default() -> Self {
-        EventRow {
-            event_type: "",
-            msisdn_src: 0,
-            msisdn_dst: 0,
-            direction: "",
-            start_ts_ms: 0,
-            end_ts_ms: 0,
-            tz_name: "",
-            tz_offset_min: 0,
-            duration_sec: 0,
-            mccmnc: 0,
-            imsi: 0,
-            imei: 0,
-            cell_id: 0,
-            record_type: "",
-            cause_for_record_closing: "",
-            sms_segments: 0,
-            sms_status: "",
-            data_bytes_in: 0,
-            data_bytes_out: 0,
-            data_duration_sec: 0,
-            apn: "",
-            rat: "",
+/// `MsisdnFormat` with the calling code it needs, ready to render numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MsisdnRendering {
+    pub format: MsisdnFormat,
+    country_code: u64,
+    country_digits: u32,
+}
+
+impl MsisdnRendering {
+    /// `country_code` must be 1-3 digits (see `validate_output_strings`); anything else renders as E.164
+    pub fn new(format: MsisdnFormat, country_code: &str) -> Self {
+        match country_code.parse::<u64>() {
+            Ok(code) if (1..=3).contains(&country_code.len()) => MsisdnRendering {
+                format,
+                country_code: code,
+                country_digits: country_code.len() as u32,
+            },
+            _ => MsisdnRendering::default(),
         }
     }
+
+    pub fn from_config(cfg: &crate::config::Config) -> Self {
+        MsisdnRendering::new(cfg.msisdn_format, &cfg.country_code)
+    }
+
+    /// `msisdn` as written to the part file, using `buf` for the digits
+    pub fn render<'a>(&self, msisdn: u64, buf: &'a mut [u8; 24]) -> &'a str {
+        let mut digits = itoa::Buffer::new();
+        let digits = digits.format(msisdn);
+        let (prefix, body) = match self.format {
+            MsisdnFormat::E164 => ("", digits),
+            MsisdnFormat::E164Plus => ("+", digits),
+            MsisdnFormat::National => {
+                let national = digits.len() as u32 > self.country_digits
+                    && msisdn / 10u64.pow(digits.len() as u32 - self.country_digits) == self.country_code;
+                if national {
+                    ("0", &digits[self.country_digits as usize..])
+                } else {
+                    ("00", digits)
+                }
+            }
+        };
+        let len = prefix.len() + body.len();
+        buf[..prefix.len()].copy_from_slice(prefix.as_bytes());
+        buf[prefix.len()..len].copy_from_slice(body.as_bytes());
+        // ASCII digits and prefixes only
+        std::str::from_utf8(&buf[..len]).unwrap_or_default()
+    }
 }
 
 impl EventRow {
@@ -162,123 +513,802 @@ impl EventRow {
         self.mccmnc = 0;
         self.imsi = 0;
         self.imei = 0;
+        self.imeisv = 0;
         self.cell_id = 0;
         self.record_type = "";
+        self.node_id = "";
         self.cause_for_record_closing = "";
         self.sms_segments = 0;
         self.sms_status = "";
-        self.data_bytes_in = 0;
-        self.data_bytes_out = 0;
-        self.data_duration_sec = 0;
+        self.data_bytes_in = None;
+        self.data_bytes_out = None;
+        self.data_duration_sec = None;
         self.apn = "";
         self.rat = "";
+        self.qci = 0;
+        self.bearer_id = 0;
+        self.correlation_id = 0;
+        self.prev_event_ts_ms = 0;
+        self.record_seq = 0;
+    }
+}
+
+/// Optional per-record stamping for dedup testing and output layout tweaks
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WriterOptions {
+    /// Add a `record_seq` column numbered from 1 within each part file
+    pub emit_record_seq: bool,
+    /// Add a `prev_event_ts_ms` column; workers stamp every row with it
+    pub emit_prev_event_ts: bool,
+    /// Add a `correlation_id` column, set on SMS rows and empty on the others
+    pub emit_correlation_id: bool,
+    /// Add an `imeisv` column, set on DATA rows and empty on the others
+    pub emit_imeisv: bool,
+    /// Add `qci` and `bearer_id` columns, set on DATA rows and empty on the others
+    pub emit_bearer_fields: bool,
+    /// Add a `node_id` column: the element that wrote each row
+    pub emit_node_id: bool,
+    /// Share of rows written twice verbatim (same record_seq); logged to `duplicates_writerNNN.jsonl`
+    pub duplicate_injection_rate: f64,
+    /// Seed for choosing the duplicated rows
+    pub seed: u64,
+    /// Start a new gzip member every N uncompressed bytes (0 = one member per file)
+    pub gzip_member_flush_bytes: u64,
+    /// Failure injection: each part file fails as if the disk were full after N bytes (0 = off)
+    pub fail_writes_after_bytes: u64,
+    /// Fail the write of any row with a quoted field or a byte outside printable ASCII
+    pub strict_ascii: bool,
+    /// Leave the `tz_name` and `tz_offset_min` columns out of every part file
+    pub omit_timezone_columns: bool,
+    /// Format of the `msisdn_src` / `msisdn_dst` columns
+    pub msisdn: MsisdnRendering,
+    /// Day directory naming, and `type=<EVENT_TYPE>/` part file directories with `hive_date_type`
+    pub partition_layout: PartitionLayout,
+    /// CSV part files, or Arrow IPC files of typed columns
+    pub output_format: OutputFormat,
+    /// Rows per Arrow file (0 = one file per writer task)
+    pub arrow_rotate_rows: u64,
+    /// Warn when a part file's compression ratio is more than this factor above or below the median
+    /// of the writer's earlier files of the day (0 = off)
+    pub compression_ratio_alert_factor: f64,
+}
+
+impl WriterOptions {
+    pub fn from_config(cfg: &crate::config::Config) -> Self {
+        WriterOptions {
+            emit_record_seq: cfg.emit_record_seq,
+            emit_prev_event_ts: cfg.emit_prev_event_ts,
+            emit_correlation_id: cfg.sms_unreachable_rate > 0.0,
+            emit_imeisv: cfg.emit_imeisv,
+            emit_bearer_fields: cfg.emit_bearer_fields,
+            emit_node_id: cfg.emit_node_id,
+            duplicate_injection_rate: cfg.duplicate_injection_rate,
+            seed: cfg.seed,
+            gzip_member_flush_bytes: cfg.gzip_member_flush_bytes,
+            fail_writes_after_bytes: cfg.fail_writes_after_bytes,
+            strict_ascii: cfg.strict_ascii,
+            omit_timezone_columns: !cfg.emit_timezone_columns,
+            msisdn: MsisdnRendering::from_config(cfg),
+            partition_layout: cfg.partition_layout,
+            output_format: cfg.output_format,
+            arrow_rotate_rows: cfg.arrow_rotate_rows,
+            compression_ratio_alert_factor: cfg.compression_ratio_alert_factor,
+        }
+    }
+}
+
+/// One finished part file, as listed in `manifest_writerNNN.json`
+#[derive(Debug, Clone, Serialize, serde::Deserialize)]
+pub struct ManifestEntry {
+    /// Path relative to the day directory, `type=CALL/<name>` with the `hive_date_type` layout
+    pub file: String,
+    /// Writer task that wrote the file, the `writerNNN` in its name
+    #[serde(default)]
+    pub writer: usize,
+    /// Worker shards whose rows the file may hold (see `ShardManifest::worker_shards`)
+    #[serde(default)]
+    pub worker_shards: Vec<usize>,
+    pub file_seq: u32,
+    /// Event type of the part when rotation splits part files by type
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_type: Option<String>,
+    pub records: u64,
+    pub duplicates: u64,
+    pub bytes: u64,
+    /// CSV bytes before compression divided by `bytes`; 0 in manifests from older versions
+    #[serde(default)]
+    pub compression_ratio: f64,
+}
+
+/// Per-writer-task list of part files written for a day
+#[derive(Debug, Clone, Serialize, serde::Deserialize)]
+pub struct ShardManifest {
+    pub day: String,
+    /// Writer task id: the `writerNNN` in part file and manifest names
+    #[serde(alias = "shard")]
+    pub writer: usize,
+    /// Worker shards (subscriber ranges, `stats_shardNNN.json`, `dirty_shardNNN.jsonl`) that fed this writer task.
+    /// Empty when unknown (manifests from older versions)
+    #[serde(default)]
+    pub worker_shards: Vec<usize>,
+    /// Header of every part file in `files`, in order. `tz_name`/`tz_offset_min` are absent
+    /// with `emit_timezone_columns: false`; `imeisv`, `node_id`, `qci`/`bearer_id`, `correlation_id`, `prev_event_ts_ms` and
+    /// `record_seq` are present with `emit_imeisv`, `emit_node_id`, `emit_bearer_fields`, `sms_unreachable_rate` above 0,
+    /// `emit_prev_event_ts` and `emit_record_seq`.
+    /// Empty when unknown (manifests from older versions, or rebuilt by late delivery)
+    #[serde(default)]
+    pub columns: Vec<String>,
+    /// Type, nullability, example and event types of each of `columns`, as in the output root's `schema.json`.
+    /// Empty when unknown
+    #[serde(default)]
+    pub schema: Vec<ColumnSchema>,
+    /// Rotation policy applied per stream: event type, or `*` for the single mixed stream.
+    /// Empty in manifests from older versions
+    #[serde(default)]
+    pub rotation: BTreeMap<String, RotationPolicy>,
+    pub files: Vec<ManifestEntry>,
+}
+
+/// Field delimiter of the part files
+pub const CSV_DELIMITER: u8 = b';';
+
+/// CSV writer for header-less rows in the part file layout; batches serialize into it at push time
+pub fn row_serializer(buf: Vec<u8>) -> Writer<Vec<u8>> {
+    WriterBuilder::new().delimiter(CSV_DELIMITER).has_headers(false).from_writer(buf)
+}
+
+/// Serialize `row` as one CSV line with a cell for each column of `options` but `record_seq`,
+/// which the writer appends, and the MSISDNs in `options.msisdn`'s format
+pub fn serialize_row(serializer: &mut Writer<Vec<u8>>, row: &EventRow, options: &WriterOptions) -> csv::Result<()> {
+    let mut cell = Vec::with_capacity(32);
+    for column in emitted_columns(options) {
+        if let Some(render) = column.cell {
+            cell.clear();
+            render(row, options, &mut cell);
+            serializer.write_field(&cell)?;
+        }
+    }
+    serializer.write_record(None::<&[u8]>)
+}
+
+/// Header line of a part file
+fn csv_header(options: &WriterOptions) -> Vec<u8> {
+    let mut header = header_columns(options).join(&(CSV_DELIMITER as char).to_string()).into_bytes();
+    header.push(b'\n');
+    header
+}
+
+/// Columns of the part files `options` writes, in header order
+pub fn emitted_columns(options: &WriterOptions) -> impl Iterator<Item = &'static Column> + '_ {
+    COLUMNS.iter().filter(|column| (column.present)(options))
+}
+
+/// Column names of the header `options` puts in every part file
+pub fn header_columns(options: &WriterOptions) -> Vec<&'static str> {
+    emitted_columns(options).map(|column| column.name).collect()
+}
+
+/// Rotation limits of every output stream, resolved from the config
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Rotation {
+    /// The single mixed stream, or event types without a policy of their own
+    pub default: RotationPolicy,
+    /// Event type -> limits; any entry gives every event type its own part files
+    pub by_event_type: BTreeMap<String, RotationPolicy>,
+}
+
+impl Rotation {
+    /// One mixed stream rotated by size only
+    pub fn bytes(rotate_bytes: u64) -> Self {
+        Rotation {
+            default: RotationPolicy {
+                bytes: rotate_bytes,
+                ..RotationPolicy::default()
+            },
+            by_event_type: BTreeMap::new(),
+        }
+    }
+
+    /// `rotate_bytes` with the `rotation` map; entries without `bytes` take `rotate_bytes`
+    pub fn from_config(cfg: &crate::config::Config) -> Self {
+        let mut rotation = Rotation::bytes(cfg.rotate_bytes);
+        for (event_type, policy) in &cfg.rotation {
+            let bytes = if policy.bytes > 0 { policy.bytes } else { cfg.rotate_bytes };
+            rotation.by_event_type.insert(event_type.clone(), RotationPolicy { bytes, ..*policy });
+        }
+        rotation
+    }
+
+    pub(crate) fn splits_by_event_type(&self) -> bool {
+        !self.by_event_type.is_empty()
+    }
+
+    pub(crate) fn policy(&self, event_type: Option<&str>) -> RotationPolicy {
+        event_type.and_then(|t| self.by_event_type.get(t)).copied().unwrap_or(self.default)
+    }
+}
+
+/// Manifest key of a stream's policy: its event type, `*` for the mixed stream
+pub const MIXED_STREAM: &str = "*";
+
+/// Suffix of a part file still being written; it gets its final name once complete and synced
+pub const PART_TMP_SUFFIX: &str = ".tmp";
+
+/// Make a rename in `dir` durable. Directories cannot be opened for syncing on Windows,
+/// where the rename is left to the filesystem
+pub(crate) fn sync_dir(dir: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    File::open(dir)?.sync_all()?;
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+/// Part files of one output stream: every row, or one event type when rotation splits by type
+struct PartStream {
+    event_type: Option<String>,
+    policy: RotationPolicy,
+    part_num: u32,
+    writer: Option<BufWriter<Box<dyn CompressedWriter>>>,
+    // Second handle on the open part file; its size is read through the handle, since
+    // Windows does not update directory metadata of a file while it is being written
+    file: Option<File>,
+    size: u64,
+    opened_at: Option<Instant>,
+    record_seq: u64,
+    records: u64,
+    duplicates: u64,
+    // CSV bytes handed to the compressor for the open part file; `size` is recalibrated to the disk size instead
+    uncompressed: u64,
+}
+
+impl PartStream {
+    fn new(event_type: Option<String>, policy: RotationPolicy) -> Self {
+        PartStream {
+            event_type,
+            policy,
+            part_num: 1,
+            writer: None,
+            file: None,
+            size: 0,
+            opened_at: None,
+            record_seq: 0,
+            records: 0,
+            duplicates: 0,
+            uncompressed: 0,
+        }
+    }
+}
+
+/// Part files smaller than this before compression are not checked: a few rows compress poorly whatever the codec
+pub const RATIO_CHECK_MIN_BYTES: u64 = 16 * 1024;
+
+/// Running median of the compression ratios of a writer's part files for the day. A file far off it
+/// (a codec silently falling back to stored blocks, a truncated stream) is flagged at close
+#[derive(Debug, Default)]
+struct RatioMonitor {
+    factor: f64,
+    ratios: Vec<f64>,
+    alerts: Vec<String>,
+}
+
+impl RatioMonitor {
+    fn new(factor: f64) -> Self {
+        RatioMonitor { factor, ..RatioMonitor::default() }
+    }
+
+    /// Record a finished file's ratio; returns the median it deviates from when that is more than `factor`
+    fn observe(&mut self, file: &str, uncompressed: u64, ratio: f64) -> Option<f64> {
+        if self.factor <= 0.0 || uncompressed < RATIO_CHECK_MIN_BYTES {
+            return None;
+        }
+        let median = self.median();
+        self.ratios.push(ratio);
+        let median = median?;
+        let deviation = if ratio > median { ratio / median } else { median / ratio };
+        if deviation > self.factor {
+            self.alerts.push(file.to_string());
+            return Some(median);
+        }
+        None
+    }
+
+    fn median(&self) -> Option<f64> {
+        if self.ratios.is_empty() {
+            return None;
+        }
+        let mut sorted = self.ratios.clone();
+        sorted.sort_by(f64::total_cmp);
+        let mid = sorted.len() / 2;
+        Some(if sorted.len().is_multiple_of(2) { (sorted[mid - 1] + sorted[mid]) / 2.0 } else { sorted[mid] })
     }
 }
 
 /// Manages rotating CSV files for CDR events
-/// Auto-rotates when file size exceeds threshold
+/// Auto-rotates when a part file reaches a limit of its stream's rotation policy
 /// Each file is compressed on-the-fly with the configured compression algorithm
+/// Part files are written as `<name>.tmp` and renamed once complete, so pollers can take any other file
 pub struct EventWriter {
     #[allow(dead_code)]
     out_dir: PathBuf,
     day_str: String,
-    rotate_bytes: u64,
-    part_num: u32,
-    current_writer: Option<Writer<Box<dyn CompressedWriter>>>,
-    current_size: u64,
+    rotation: Rotation,
+    streams: Vec<PartStream>,
+    // Stream of the row written last, named in errors and the duplicates log
+    current: usize,
     day_dir: PathBuf,
-    shard_id: usize,
+    // Writer task id, not a worker shard; several workers may feed one writer
+    writer_id: usize,
+    worker_shards: Vec<usize>,
     compression_type: CompressionType,
+    compression_level: Option<i32>,
+    files_written: u32,
+    bytes_written: u64,
+    // CSV bytes handed to the compressor, headers and record_seq included
+    uncompressed_bytes: u64,
+    writer_options: WriterOptions,
+    duplicate_rng: StdRng,
+    duplicates_log: Option<File>,
+    manifest: Vec<ManifestEntry>,
+    ratio_monitor: RatioMonitor,
+    closed: bool,
+    header: Vec<u8>,
+    // Scratch buffer for single rows passed to write_row
+    row_buf: Vec<u8>,
 }
 
 impl EventWriter {
-    pub fn new(out_dir: &Path, day_str: &str, rotate_bytes: u64, shard_id: usize, compression_type: CompressionType) -> anyhow::Result<Self> {
-        let day_dir = out_dir.join(day_str);
+    pub fn new(
+        out_dir: &Path,
+        day_str: &str,
+        rotate_bytes: u64,
+        writer_id: usize,
+        compression_type: CompressionType,
+        compression_level: Option<i32>,
+        writer_options: WriterOptions,
+    ) -> anyhow::Result<Self> {
+        let day_dir = writer_options.partition_layout.day_dir(out_dir, day_str);
         std::fs::create_dir_all(&day_dir)?;
 
-        let mut writer = EventWriter {
+        Ok(EventWriter {
             out_dir: out_dir.to_path_buf(),
             day_str: day_str.to_string(),
-            rotate_bytes,
-            part_num: 1,
-            current_writer: None,
-            current_size: 0,
+            rotation: Rotation::bytes(rotate_bytes),
+            streams: Vec::new(),
+            current: 0,
             day_dir,
-            shard_id,
+            writer_id,
+            worker_shards: Vec::new(),
             compression_type,
-        };
+            compression_level,
+            files_written: 0,
+            bytes_written: 0,
+            uncompressed_bytes: 0,
+            writer_options,
+            duplicate_rng: StdRng::seed_from_u64(writer_options.seed ^ (writer_id as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)),
+            duplicates_log: None,
+            manifest: Vec::new(),
+            ratio_monitor: RatioMonitor::new(writer_options.compression_ratio_alert_factor),
+            closed: false,
+            header: csv_header(&writer_options),
+            row_buf: Vec::new(),
+        })
+    }
 
-        writer.open_new_file()?;
-        Ok(writer)
+    /// Record the worker shards feeding this writer in its manifest
+    pub fn set_worker_shards(&mut self, worker_shards: Vec<usize>) {
+        self.worker_shards = worker_shards;
     }
 
-    fn open_new_file(&mut self) -> anyhow::Result<()> {
-        // Close current file if any
-        if let Some(mut writer) = self.current_writer.take() {
-            writer.flush()?;
-            // Finish compression
-            let mut inner = writer.into_inner().map_err(|e| anyhow::anyhow!("Failed to get inner writer: {}", e))?;
+    /// Replace the size-only rotation given to `new`; call before the first row
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        self.rotation = rotation;
+    }
+
+    /// File name of the part file stream `idx` is writing
+    fn current_file_name(&self, idx: usize) -> String {
+        let extension = self.compression_type.extension();
+        let stream = &self.streams[idx];
+        match stream.event_type {
+            Some(ref event_type) => format!(
+                "cdr_{}_writer{:03}_{}_part{:03}.csv{}",
+                self.day_str,
+                self.writer_id,
+                event_type.to_lowercase(),
+                stream.part_num,
+                extension
+            ),
+            None => format!("cdr_{}_writer{:03}_part{:03}.csv{}", self.day_str, self.writer_id, stream.part_num, extension),
+        }
+    }
+
+    /// Part file stream `idx` is writing, relative to the day directory (`/`-separated)
+    fn current_file(&self, idx: usize) -> String {
+        let subdir = self.writer_options.partition_layout.part_subdir(self.streams[idx].event_type.as_deref());
+        match subdir.to_str() {
+            Some("") | None => self.current_file_name(idx),
+            Some(subdir) => format!("{}/{}", subdir, self.current_file_name(idx)),
+        }
+    }
+
+    /// Final path of the part file stream `idx` is writing
+    fn current_path(&self, idx: usize) -> PathBuf {
+        self.day_dir.join(self.current_file(idx))
+    }
+
+    /// Path the part file of stream `idx` is written under until it is complete
+    fn tmp_path(&self, idx: usize) -> PathBuf {
+        self.day_dir.join(format!("{}{}", self.current_file(idx), PART_TMP_SUFFIX))
+    }
+
+    /// Stream of a serialized row: its event type (the first field) when splitting, else the mixed one
+    fn stream_for(&mut self, line: &[u8]) -> usize {
+        let splits = self.rotation.splits_by_event_type() || self.writer_options.partition_layout.splits_by_event_type();
+        let event_type = splits.then(|| {
+            let end = line.iter().position(|&b| b == CSV_DELIMITER).unwrap_or(line.len());
+            String::from_utf8_lossy(&line[..end]).into_owned()
+        });
+        if let Some(idx) = self.streams.iter().position(|s| s.event_type == event_type) {
+            return idx;
+        }
+        let policy = self.rotation.policy(event_type.as_deref());
+        self.streams.push(PartStream::new(event_type, policy));
+        self.streams.len() - 1
+    }
+
+    /// Flush and finish compression of stream `idx`'s file, sync it and give it its final name,
+    /// recording its final size
+    fn finish_current_file(&mut self, idx: usize) -> anyhow::Result<()> {
+        if let Some(writer) = self.streams[idx].writer.take() {
+            // Finish compression and flush all buffers
+            let mut inner = writer.into_inner().map_err(|e| e.into_error())?;
             inner.finish_compression()?;
+            drop(inner);
+
+            // Sync and read the size, then close the last handle so the file can be renamed or deleted on Windows
+            let bytes = match self.streams[idx].file.take() {
+                Some(file) => {
+                    file.sync_all()?;
+                    file.metadata()?.len()
+                }
+                None => std::fs::metadata(self.tmp_path(idx))?.len(),
+            };
+            let path = self.current_path(idx);
+            std::fs::rename(self.tmp_path(idx), &path)?;
+            if let Some(dir) = path.parent() {
+                sync_dir(dir)?;
+            }
+            self.files_written += 1;
+            self.bytes_written += bytes;
+            let file = self.current_file(idx);
+            let stream = &self.streams[idx];
+            let compression_ratio = if bytes > 0 { stream.uncompressed as f64 / bytes as f64 } else { 0.0 };
+            if let Some(median) = self.ratio_monitor.observe(&file, stream.uncompressed, compression_ratio) {
+                warn!(
+                    writer = self.writer_id,
+                    file = %file,
+                    ratio = compression_ratio,
+                    median,
+                    "Part file compression ratio is far off the day's median; the file may be corrupt"
+                );
+            }
+            self.manifest.push(ManifestEntry {
+                file,
+                writer: self.writer_id,
+                worker_shards: self.worker_shards.clone(),
+                file_seq: stream.part_num,
+                event_type: stream.event_type.clone(),
+                records: stream.records,
+                duplicates: stream.duplicates,
+                bytes,
+                compression_ratio,
+            });
         }
+        Ok(())
+    }
 
-        let extension = self.compression_type.extension();
-        let filename = format!("cdr_{}_shard{:03}_part{:03}.csv{}", self.day_str, self.shard_id, self.part_num, extension);
-        let filepath = self.day_dir.join(&filename);
+    fn open_new_file(&mut self, idx: usize) -> anyhow::Result<()> {
+        let filepath = self.tmp_path(idx);
+        if let Some(parent) = filepath.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
 
         let file = File::create(&filepath)?;
+        let file_handle = file.try_clone()?;
         // Create compressed writer using factory function
-        let compressed = create_compressed_writer(file, self.compression_type)?;
+        let mut compressed = create_compressed_writer(
+            file,
+            self.compression_type,
+            self.compression_level,
+            self.writer_options.gzip_member_flush_bytes,
+        )?;
+        if self.writer_options.fail_writes_after_bytes > 0 {
+            compressed = Box::new(FailingWriter::new(compressed, self.writer_options.fail_writes_after_bytes));
+        }
 
-        let wtr = WriterBuilder::new()
-            .delimiter(b';')
-            .has_headers(true)
-            .from_writer(compressed);
-        self.current_size = std::fs::metadata(&filepath)?.len();
-        self.current_writer = Some(wtr);
+        let mut wtr = BufWriter::with_capacity(64 * 1024, compressed);
+        wtr.write_all(&self.header)?;
+        self.uncompressed_bytes += self.header.len() as u64;
+        let stream = &mut self.streams[idx];
+        stream.file = Some(file_handle);
+        stream.size = self.header.len() as u64;
+        stream.uncompressed = self.header.len() as u64;
+        stream.opened_at = Some(Instant::now());
+        stream.writer = Some(wtr);
+        stream.record_seq = 0;
+        stream.records = 0;
+        stream.duplicates = 0;
 
         Ok(())
     }
 
     pub fn write_row(&mut self, row: &EventRow) -> anyhow::Result<()> {
-        if let Some(ref mut writer) = self.current_writer {
-            writer.serialize(row)?;
-
-            // Estimate row size instead of checking file size every time
-            // Average CDR row is ~200-250 bytes
-            self.current_size += 230;
-
-            // Check if rotation needed (with periodic verification every 1000 rows)
-            if self.current_size >= self.rotate_bytes {
-                writer.flush()?;
-
-                // Get actual file size for accuracy
-                let extension = self.compression_type.extension();
-                let filename = format!("cdr_{}_shard{:03}_part{:03}.csv{}", self.day_str, self.shard_id, self.part_num, extension);
-                let filepath = self.day_dir.join(&filename);
-                let actual_size = std::fs::metadata(&filepath)?.len();
-
-                if actual_size >= self.rotate_bytes {
-                    self.part_num += 1;
-                    self.open_new_file()?;
-                } else {
-                    // Calibrate estimate
-                    self.current_size = actual_size;
+        let mut serializer = row_serializer(std::mem::take(&mut self.row_buf));
+        let options = &self.writer_options;
+        serialize_row(&mut serializer, row, options)?;
+        let mut line = serializer.into_inner().map_err(|e| anyhow::anyhow!("Failed to serialize row: {}", e))?;
+        let result = self.check_strict_ascii(&line).and_then(|_| self.write_line(&line).map_err(|e| self.part_error(e)));
+        line.clear();
+        self.row_buf = line;
+        result
+    }
+
+    /// Write the rows of a batch, serializing them here
+    pub fn write_batch(&mut self, batch: &EventBatch) -> anyhow::Result<()> {
+        batch.events.iter().try_for_each(|event| self.write_row(event))
+    }
+
+    /// Write rows already serialized by the worker
+    pub fn write_serialized(&mut self, batch: &SerializedBatch) -> anyhow::Result<()> {
+        for line in batch.lines() {
+            self.check_strict_ascii(line)?;
+            self.write_line(line).map_err(|e| self.part_error(e))?;
+        }
+        Ok(())
+    }
+
+    /// With `strict_ascii`, only printable ASCII without quotes may precede the newline,
+    /// so the csv crate never had to quote a field
+    fn check_strict_ascii(&mut self, line: &[u8]) -> anyhow::Result<()> {
+        if !self.writer_options.strict_ascii {
+            return Ok(());
+        }
+        let body = line.strip_suffix(b"\n").unwrap_or(line);
+        if let Some(&byte) = body.iter().find(|&&b| !(b' '..=b'~').contains(&b) || b == b'"') {
+            let idx = self.stream_for(line);
+            anyhow::bail!(
+                "strict_ascii: byte 0x{:02x} in row for writer {} {}: {:?}",
+                byte,
+                self.writer_id,
+                self.part_label(idx),
+                String::from_utf8_lossy(body)
+            );
+        }
+        Ok(())
+    }
+
+    /// `part N` of stream `idx`, prefixed with its event type when splitting
+    fn part_label(&self, idx: usize) -> String {
+        match self.streams.get(idx) {
+            Some(PartStream { event_type: Some(event_type), part_num, .. }) => {
+                format!("{} part {}", event_type.to_lowercase(), part_num)
+            }
+            Some(stream) => format!("part {}", stream.part_num),
+            None => "part 1".to_string(),
+        }
+    }
+
+    /// Name the writer task and part in a write error; a full disk gets its own message
+    fn part_error(&self, err: anyhow::Error) -> anyhow::Error {
+        let is_out_of_space = |e: &std::io::Error| e.kind() == std::io::ErrorKind::StorageFull || e.raw_os_error() == Some(28);
+        let out_of_space = err.chain().any(|cause| {
+            cause.downcast_ref::<std::io::Error>().is_some_and(is_out_of_space)
+                || cause
+                    .downcast_ref::<csv::Error>()
+                    .is_some_and(|e| matches!(e.kind(), csv::ErrorKind::Io(io) if is_out_of_space(io)))
+        });
+        let what = if out_of_space { "Out of space" } else { "I/O error" };
+        err.context(format!("{} while writing writer {} {}", what, self.writer_id, self.part_label(self.current)))
+    }
+
+    /// Write one serialized CSV line (ending in `\n`) to its stream, stamping `record_seq` when enabled
+    fn write_line(&mut self, line: &[u8]) -> anyhow::Result<()> {
+        if self.closed {
+            return Ok(());
+        }
+        let idx = self.stream_for(line);
+        self.current = idx;
+        // Part files are opened on their first row, so a writer task without rows leaves no files behind
+        if self.streams[idx].writer.is_none() {
+            self.open_new_file(idx)?;
+        }
+        let emit_record_seq = self.writer_options.emit_record_seq;
+        let stream = &mut self.streams[idx];
+        let Some(writer) = stream.writer.as_mut() else {
+            return Ok(());
+        };
+
+        stream.record_seq += 1;
+        stream.records += 1;
+        let written = Self::write_stamped(writer, line, emit_record_seq, stream.record_seq)?;
+
+        // Uncompressed bytes so far: an upper bound on the file size, checked against disk below
+        stream.size += written;
+        stream.uncompressed += written;
+        self.uncompressed_bytes += written;
+
+        // Re-emit the row verbatim so dedup pipelines have something to catch
+        let rate = self.writer_options.duplicate_injection_rate;
+        if rate > 0.0 && self.duplicate_rng.gen::<f64>() < rate {
+            let written = Self::write_stamped(writer, line, emit_record_seq, stream.record_seq)?;
+            stream.size += written;
+            stream.uncompressed += written;
+            self.uncompressed_bytes += written;
+            stream.records += 1;
+            stream.duplicates += 1;
+            self.log_duplicate()?;
+        }
+
+        // Row and interval limits are exact; no size check needed
+        let stream = &self.streams[idx];
+        let policy = stream.policy;
+        let rows_reached = policy.rows > 0 && stream.records >= policy.rows;
+        let interval_reached =
+            policy.interval_sec > 0 && stream.opened_at.is_some_and(|t| t.elapsed().as_secs() >= policy.interval_sec);
+        if rows_reached || interval_reached {
+            self.finish_current_file(idx)?;
+            self.streams[idx].part_num += 1;
+            return Ok(());
+        }
+
+        // Check if rotation needed (with periodic verification every 1000 rows)
+        let path = self.current_path(idx);
+        let stream = &mut self.streams[idx];
+        let Some(writer) = stream.writer.as_mut() else {
+            return Ok(());
+        };
+        if stream.size >= policy.bytes {
+            writer.flush()?;
+
+            // Get actual file size for accuracy, from the open handle rather than the path
+            let actual_size = match stream.file {
+                Some(ref file) => file.metadata()?.len(),
+                None => std::fs::metadata(&path)?.len(),
+            };
+
+            if actual_size >= policy.bytes {
+                // The estimate lagged far behind the real size, so the part overshot the limit
+                if actual_size > policy.bytes + policy.bytes / 10 {
+                    warn!(
+                        writer = self.writer_id,
+                        part = self.streams[idx].part_num,
+                        actual_size,
+                        rotate_bytes = policy.bytes,
+                        "Part file exceeded rotation limit by more than 10%"
+                    );
                 }
+                self.finish_current_file(idx)?;
+                self.streams[idx].part_num += 1;
+            } else {
+                // Calibrate estimate
+                debug!(
+                    writer = self.writer_id,
+                    estimated = stream.size,
+                    actual_size,
+                    "Calibrating rotation size estimate"
+                );
+                stream.size = actual_size;
             }
         }
 
         Ok(())
     }
 
+    /// Write `line`, inserting `;record_seq` before its newline when `emit_record_seq` is set
+    fn write_stamped(writer: &mut impl Write, line: &[u8], emit_record_seq: bool, record_seq: u64) -> std::io::Result<u64> {
+        if !emit_record_seq {
+            writer.write_all(line)?;
+            return Ok(line.len() as u64);
+        }
+        let body = line.strip_suffix(b"\n").unwrap_or(line);
+        writer.write_all(body)?;
+        let suffix = format!(";{}\n", record_seq);
+        writer.write_all(suffix.as_bytes())?;
+        Ok((body.len() + suffix.len()) as u64)
+    }
+
+    /// Append the duplicated record to the ground-truth log
+    fn log_duplicate(&mut self) -> anyhow::Result<()> {
+        if self.duplicates_log.is_none() {
+            let path = self.day_dir.join(format!("duplicates_writer{:03}.jsonl", self.writer_id));
+            self.duplicates_log = Some(File::create(path)?);
+        }
+        let line = serde_json::json!({
+            "file": self.current_file(self.current),
+            "record_seq": self.streams[self.current].record_seq,
+        });
+        if let Some(log) = self.duplicates_log.as_mut() {
+            writeln!(log, "{}", line)?;
+        }
+        Ok(())
+    }
+
+    /// Column names from the header every part file starts with
+    fn columns(&self) -> Vec<String> {
+        header_columns(&self.writer_options).into_iter().map(str::to_string).collect()
+    }
+
+    /// Policies applied to the streams that were written, `*` for the mixed stream
+    fn applied_rotation(&self) -> BTreeMap<String, RotationPolicy> {
+        if self.streams.is_empty() {
+            return BTreeMap::from([(MIXED_STREAM.to_string(), self.rotation.default)]);
+        }
+        self.streams
+            .iter()
+            .map(|s| (s.event_type.clone().unwrap_or_else(|| MIXED_STREAM.to_string()), s.policy))
+            .collect()
+    }
+
+    /// Finish the current files and write `manifest_writerNNN.json`
     pub fn close(&mut self) -> anyhow::Result<()> {
-        if let Some(mut writer) = self.current_writer.take() {
-            writer.flush()?;
-            // Finish compression and flush all buffers
-            let mut inner = writer.into_inner().map_err(|e| anyhow::anyhow!("Failed to get inner writer: {}", e))?;
-            inner.finish_compression()?;
+        if self.closed {
+            return Ok(());
+        }
+        for idx in 0..self.streams.len() {
+            self.current = idx;
+            self.finish_current_file(idx).map_err(|e| self.part_error(e))?;
         }
+        if let Some(mut log) = self.duplicates_log.take() {
+            log.flush()?;
+        }
+
+        let manifest = ShardManifest {
+            day: self.day_str.clone(),
+            writer: self.writer_id,
+            worker_shards: self.worker_shards.clone(),
+            columns: self.columns(),
+            schema: OutputSchema::new(&self.writer_options)?.columns,
+            rotation: self.applied_rotation(),
+            files: std::mem::take(&mut self.manifest),
+        };
+        let path = self.day_dir.join(format!("manifest_writer{:03}.json", self.writer_id));
+        std::fs::write(path, serde_json::to_string_pretty(&manifest)?)?;
+        self.closed = true;
         Ok(())
     }
+
+    /// Give up after a write failure: drop the unfinished part files and record the completed ones
+    pub fn abort(&mut self) {
+        for idx in 0..self.streams.len() {
+            // Both handles must be closed first, Windows refuses to delete an open file
+            let had_writer = self.streams[idx].writer.take().is_some();
+            self.streams[idx].file = None;
+            if had_writer {
+                let _ = std::fs::remove_file(self.tmp_path(idx));
+            }
+        }
+        let _ = self.close();
+    }
+
+    /// Number of part files completed so far
+    pub fn files_written(&self) -> u32 {
+        self.files_written
+    }
+
+    /// Total on-disk size of completed part files (bytes, after compression)
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// Total CSV bytes written before compression, across all part files
+    pub fn uncompressed_bytes(&self) -> u64 {
+        self.uncompressed_bytes
+    }
+
+    /// Part files whose compression ratio was flagged as far off the day's median
+    pub fn compression_ratio_alerts(&self) -> &[String] {
+        &self.ratio_monitor.alerts
+    }
 }
 
 impl Drop for EventWriter {
@@ -286,3 +1316,344 @@ impl Drop for EventWriter {
         let _ = self.close();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn sample_row(i: u64) -> EventRow {
+        EventRow {
+            event_type: "CALL",
+            msisdn_src: 31_600_000_000 + i,
+            msisdn_dst: 31_600_100_000 + i,
+            direction: "MO",
+            tz_name: "Europe/Amsterdam",
+            ..EventRow::default()
+        }
+    }
+
+    fn read_csv(path: &Path) -> (csv::StringRecord, Vec<csv::StringRecord>) {
+        let mut rdr = csv::ReaderBuilder::new().delimiter(b';').from_path(path).unwrap();
+        let headers = rdr.headers().unwrap().clone();
+        let rows = rdr.records().map(|r| r.unwrap()).collect();
+        (headers, rows)
+    }
+
+    fn read_manifest(day_dir: &Path) -> ShardManifest {
+        let text = std::fs::read_to_string(day_dir.join("manifest_writer000.json")).unwrap();
+        serde_json::from_str(&text).unwrap()
+    }
+
+    #[test]
+    fn test_uncompressed_part_among_gzip_parts_is_flagged() {
+        let dir = tempdir().unwrap();
+        let options = WriterOptions { compression_ratio_alert_factor: 3.0, ..WriterOptions::default() };
+        let mut writer = EventWriter::new(dir.path(), "2025-01-01", 1 << 30, 0, CompressionType::Gzip, None, options).unwrap();
+        // Exact row limits, so the codec can be swapped between parts
+        writer.set_rotation(Rotation { default: RotationPolicy { bytes: 1 << 30, rows: 500, interval_sec: 0 }, ..Rotation::default() });
+        for i in 0..1_500 {
+            writer.write_row(&sample_row(i)).unwrap();
+        }
+        // A codec silently storing its input, as if the compressor had been dropped
+        writer.compression_type = CompressionType::None;
+        for i in 1_500..2_000 {
+            writer.write_row(&sample_row(i)).unwrap();
+        }
+        writer.close().unwrap();
+
+        let manifest = read_manifest(&dir.path().join("2025-01-01"));
+        assert_eq!(manifest.files.len(), 4);
+        let (gzip, stored) = manifest.files.split_at(3);
+        assert!(gzip.iter().all(|e| e.file.ends_with(".csv.gz") && e.compression_ratio > 3.0), "{:?}", gzip);
+        assert_eq!(stored[0].compression_ratio, 1.0);
+        assert_eq!(writer.compression_ratio_alerts(), [stored[0].file.clone()]);
+    }
+
+    #[test]
+    fn test_ratio_monitor_skips_small_files_and_needs_a_median() {
+        let mut monitor = RatioMonitor::new(3.0);
+        // The first file has no median to deviate from
+        assert_eq!(monitor.observe("a", RATIO_CHECK_MIN_BYTES, 1.0), None);
+        assert_eq!(monitor.observe("b", RATIO_CHECK_MIN_BYTES, 8.0), Some(1.0));
+        assert_eq!(monitor.observe("c", RATIO_CHECK_MIN_BYTES, 8.0), None);
+        // Median of 1, 8 and 8 is 8: a tail part of a few rows is not judged, a large stored one is
+        assert_eq!(monitor.observe("d", 500, 1.2), None);
+        assert_eq!(monitor.observe("e", RATIO_CHECK_MIN_BYTES, 2.0), Some(8.0));
+        assert_eq!(monitor.alerts, ["b", "e"]);
+        // Factor 0 turns the check off
+        let mut off = RatioMonitor::new(0.0);
+        off.observe("a", RATIO_CHECK_MIN_BYTES, 8.0);
+        assert_eq!(off.observe("b", RATIO_CHECK_MIN_BYTES, 1.0), None);
+    }
+
+    #[test]
+    fn test_msisdn_rendering() {
+        let mut buf = [0u8; 24];
+        let national = MsisdnRendering::new(MsisdnFormat::National, "31");
+        assert_eq!(national.render(31_612_345_678, &mut buf), "0612345678");
+        assert_eq!(national.render(4_930_123_456, &mut buf), "004930123456");
+        assert_eq!(national.render(31, &mut buf), "0031");
+        let plus = MsisdnRendering::new(MsisdnFormat::E164Plus, "31");
+        assert_eq!(plus.render(31_612_345_678, &mut buf), "+31612345678");
+        assert_eq!(MsisdnRendering::default().render(u64::MAX, &mut buf), u64::MAX.to_string());
+        // A country code that is not 1-3 digits falls back to E.164
+        assert_eq!(MsisdnRendering::new(MsisdnFormat::National, "3x").render(31_612_345_678, &mut buf), "31612345678");
+    }
+
+    #[test]
+    fn test_serialize_row_renders_msisdns_per_call() {
+        // Writers of different formats interleaved on one thread each keep their own
+        let row = EventRow { event_type: "SMS", msisdn_src: 31_612_345_678, msisdn_dst: 4_930_123_456, ..EventRow::default() };
+        let line = |format: MsisdnFormat| {
+            let options = WriterOptions { msisdn: MsisdnRendering::new(format, "31"), ..WriterOptions::default() };
+            let mut serializer = row_serializer(Vec::new());
+            serialize_row(&mut serializer, &row, &options).unwrap();
+            String::from_utf8(serializer.into_inner().unwrap()).unwrap()
+        };
+        for _ in 0..2 {
+            assert!(line(MsisdnFormat::National).starts_with("SMS;0612345678;004930123456;"));
+            assert!(line(MsisdnFormat::E164Plus).starts_with("SMS;+31612345678;+4930123456;"));
+            assert!(line(MsisdnFormat::E164).starts_with("SMS;31612345678;4930123456;"));
+        }
+        let columns = header_columns(&WriterOptions::default());
+        assert_eq!(line(MsisdnFormat::E164).trim_end().split(';').count(), columns.len());
+    }
+
+    #[test]
+    fn test_record_seq_resets_on_rotation() {
+        let dir = tempdir().unwrap();
+        let options = WriterOptions { emit_record_seq: true, ..WriterOptions::default() };
+        // Tiny rotation limit: every row lands in a part with a few neighbours at most
+        let mut writer = EventWriter::new(dir.path(), "2025-01-01", 1_000, 0, CompressionType::None, None, options).unwrap();
+        for i in 0..20 {
+            writer.write_row(&sample_row(i)).unwrap();
+        }
+        writer.close().unwrap();
+
+        let day_dir = dir.path().join("2025-01-01");
+        let manifest = read_manifest(&day_dir);
+        assert!(manifest.files.len() > 1);
+        let mut total = 0;
+        for (i, entry) in manifest.files.iter().enumerate() {
+            assert_eq!(entry.file_seq as usize, i + 1);
+            let (headers, rows) = read_csv(&day_dir.join(&entry.file));
+            let seq_col = headers.iter().position(|h| h == "record_seq").unwrap();
+            let seqs: Vec<u64> = rows.iter().map(|r| r[seq_col].parse().unwrap()).collect();
+            assert_eq!(seqs, (1..=rows.len() as u64).collect::<Vec<_>>());
+            assert_eq!(entry.records, rows.len() as u64);
+            total += rows.len();
+        }
+        assert_eq!(total, 20);
+    }
+
+    #[test]
+    fn test_rotation_splits_parts_by_event_type() {
+        let dir = tempdir().unwrap();
+        let mut writer = EventWriter::new(dir.path(), "2025-01-01", 1 << 20, 0, CompressionType::None, None, WriterOptions::default()).unwrap();
+        let mut rotation = Rotation::bytes(1 << 20);
+        rotation.by_event_type.insert("CALL".to_string(), RotationPolicy { bytes: 1 << 20, rows: 4, interval_sec: 0 });
+        writer.set_rotation(rotation);
+        for i in 0..10 {
+            writer.write_row(&sample_row(i)).unwrap();
+            writer.write_row(&EventRow { event_type: "SMS", ..sample_row(i) }).unwrap();
+        }
+        writer.close().unwrap();
+
+        let day_dir = dir.path().join("2025-01-01");
+        let manifest = read_manifest(&day_dir);
+        let calls: Vec<_> = manifest.files.iter().filter(|e| e.event_type.as_deref() == Some("CALL")).collect();
+        let sms: Vec<_> = manifest.files.iter().filter(|e| e.event_type.as_deref() == Some("SMS")).collect();
+        assert_eq!(calls.iter().map(|e| e.records).collect::<Vec<_>>(), [4, 4, 2]);
+        assert_eq!(calls[0].file, "cdr_2025-01-01_writer000_call_part001.csv");
+        assert_eq!(sms.len(), 1);
+        assert_eq!(sms[0].records, 10);
+        let (_, rows) = read_csv(&day_dir.join(&sms[0].file));
+        assert!(rows.iter().all(|r| &r[0] == "SMS"));
+
+        // SMS has no policy of its own and takes the default
+        assert_eq!(manifest.rotation["CALL"].rows, 4);
+        assert_eq!(manifest.rotation["SMS"], RotationPolicy { bytes: 1 << 20, ..RotationPolicy::default() });
+        assert!(!manifest.rotation.contains_key(MIXED_STREAM));
+    }
+
+    #[test]
+    fn test_zero_durations_render_as_zero() {
+        let dir = tempdir().unwrap();
+        let mut writer = EventWriter::new(dir.path(), "2025-01-01", 1 << 20, 0, CompressionType::None, None, WriterOptions::default()).unwrap();
+        let failed_call = EventRow { start_ts_ms: 1_000, end_ts_ms: 1_000, ..sample_row(0) };
+        let empty_session = EventRow {
+            event_type: "DATA",
+            data_bytes_in: Some(0),
+            data_bytes_out: Some(0),
+            data_duration_sec: Some(0),
+            ..sample_row(1)
+        };
+        writer.write_row(&failed_call).unwrap();
+        writer.write_row(&empty_session).unwrap();
+        writer.close().unwrap();
+
+        let (headers, rows) = read_csv(&dir.path().join("2025-01-01/cdr_2025-01-01_writer000_part001.csv"));
+        let col = |name: &str| headers.iter().position(|h| h == name).unwrap();
+        assert_eq!(&rows[0][col("duration_sec")], "0");
+        assert_eq!(rows[0][col("end_ts_ms")], rows[0][col("start_ts_ms")]);
+        // Columns of other event types stay empty, a measured zero does not
+        for name in ["data_bytes_in", "data_bytes_out", "data_duration_sec", "sms_segments"] {
+            assert_eq!(&rows[0][col(name)], "", "{}", name);
+        }
+        for name in ["data_bytes_in", "data_bytes_out", "data_duration_sec"] {
+            assert_eq!(&rows[1][col(name)], "0", "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_record_seq_column_off_by_default() {
+        let dir = tempdir().unwrap();
+        let mut writer = EventWriter::new(dir.path(), "2025-01-01", 1 << 20, 0, CompressionType::None, None, WriterOptions::default()).unwrap();
+        writer.write_row(&sample_row(0)).unwrap();
+        writer.close().unwrap();
+
+        let day_dir = dir.path().join("2025-01-01");
+        let (headers, rows) = read_csv(&day_dir.join("cdr_2025-01-01_writer000_part001.csv"));
+        assert!(!headers.iter().any(|h| h == "record_seq"));
+        assert_eq!(rows.len(), 1);
+        assert!(!day_dir.join("duplicates_writer000.jsonl").exists());
+    }
+
+    #[test]
+    fn test_no_rows_no_part_files() {
+        let dir = tempdir().unwrap();
+        let mut writer = EventWriter::new(dir.path(), "2025-01-01", 1 << 20, 0, CompressionType::Gzip, None, WriterOptions::default()).unwrap();
+        writer.close().unwrap();
+
+        let day_dir = dir.path().join("2025-01-01");
+        assert_eq!(writer.files_written(), 0);
+        assert!(!day_dir.join("cdr_2025-01-01_writer000_part001.csv.gz").exists());
+        assert!(read_manifest(&day_dir).files.is_empty());
+    }
+
+    #[test]
+    fn test_out_of_space_names_writer_and_part() {
+        let dir = tempdir().unwrap();
+        let options = WriterOptions { fail_writes_after_bytes: 1_000, ..WriterOptions::default() };
+        let mut writer = EventWriter::new(dir.path(), "2025-01-01", 1 << 20, 3, CompressionType::None, None, options).unwrap();
+        let err = (0..1_000).try_for_each(|i| writer.write_row(&sample_row(i))).unwrap_err();
+        assert_eq!(err.to_string(), "Out of space while writing writer 3 part 1");
+        assert!(format!("{:#}", err).contains("No space left on device"));
+
+        // The unfinished part is discarded and the manifest still gets written
+        writer.abort();
+        let day_dir = dir.path().join("2025-01-01");
+        assert!(!day_dir.join("cdr_2025-01-01_writer003_part001.csv").exists());
+        assert!(!day_dir.join("cdr_2025-01-01_writer003_part001.csv.tmp").exists());
+        assert!(day_dir.join("manifest_writer003.json").exists());
+    }
+
+    #[test]
+    fn test_parts_renamed_only_when_complete() {
+        let dir = tempdir().unwrap();
+        let day_dir = dir.path().join("2025-01-01");
+        let mut writer = EventWriter::new(dir.path(), "2025-01-01", 1_000, 0, CompressionType::None, None, WriterOptions::default()).unwrap();
+        for i in 0..50 {
+            writer.write_row(&sample_row(i)).unwrap();
+        }
+        // Rotated parts are final, the open one is not
+        let part = |n: u32| day_dir.join(format!("cdr_2025-01-01_writer000_part{:03}.csv", n));
+        let open = writer.streams[0].part_num;
+        assert!(open > 1);
+        assert!((1..open).all(|n| part(n).exists()));
+        assert!(!part(open).exists());
+
+        // A crash skips close(): the open part is left under its temporary name only
+        std::mem::forget(writer);
+        assert!(!part(open).exists());
+        assert!(day_dir.join(format!("cdr_2025-01-01_writer000_part{:03}.csv{}", open, PART_TMP_SUFFIX)).exists());
+    }
+
+    #[test]
+    fn test_strict_ascii_rejects_quoted_fields() {
+        let dir = tempdir().unwrap();
+        let options = WriterOptions { strict_ascii: true, ..WriterOptions::default() };
+        let mut writer = EventWriter::new(dir.path(), "2025-01-01", 1 << 20, 2, CompressionType::None, None, options).unwrap();
+        writer.write_row(&sample_row(1)).unwrap();
+
+        let row = EventRow { apn: "internet;\"x\"", ..sample_row(2) };
+        let err = writer.write_row(&row).unwrap_err().to_string();
+        assert!(err.starts_with("strict_ascii: byte 0x22 in row for writer 2 part 1"), "{}", err);
+
+        let mut batch = EventBatch::serialized(4);
+        batch.push(&EventRow { apn: "caf\u{e9}", ..sample_row(3) });
+        let crate::async_writer::WriterMessage::Serialized(serialized) = batch.into_message() else { unreachable!() };
+        assert!(writer.write_serialized(&serialized).unwrap_err().to_string().contains("byte 0xc3"));
+        writer.close().unwrap();
+    }
+
+    #[test]
+    fn test_serialized_batches_match_row_batches() {
+        let options = WriterOptions { emit_record_seq: true, duplicate_injection_rate: 0.1, seed: 3, ..WriterOptions::default() };
+        let write_day = |out_dir: &Path, serialized: bool| {
+            let mut writer = EventWriter::new(out_dir, "2025-01-01", 4_000, 0, CompressionType::None, None, options).unwrap();
+            for chunk in 0..5 {
+                let mut batch = if serialized { EventBatch::serialized(20) } else { EventBatch::new(20) };
+                (0..20).for_each(|i| batch.push(&sample_row(chunk * 20 + i)));
+                match batch.into_message() {
+                    crate::async_writer::WriterMessage::Serialized(bytes) => writer.write_serialized(&bytes).unwrap(),
+                    crate::async_writer::WriterMessage::Batch(rows) => writer.write_batch(&rows).unwrap(),
+                    crate::async_writer::WriterMessage::Close => unreachable!(),
+                }
+            }
+            writer.close().unwrap();
+        };
+
+        let (rows_dir, bytes_dir) = (tempdir().unwrap(), tempdir().unwrap());
+        write_day(rows_dir.path(), false);
+        write_day(bytes_dir.path(), true);
+
+        let manifest = read_manifest(&rows_dir.path().join("2025-01-01"));
+        assert!(manifest.files.len() > 1);
+        for entry in &manifest.files {
+            let rows = std::fs::read(rows_dir.path().join("2025-01-01").join(&entry.file)).unwrap();
+            let bytes = std::fs::read(bytes_dir.path().join("2025-01-01").join(&entry.file)).unwrap();
+            assert_eq!(rows, bytes, "{} differs", entry.file);
+        }
+    }
+
+    #[test]
+    fn test_duplicate_injection_ground_truth() {
+        let dir = tempdir().unwrap();
+        let options = WriterOptions { emit_record_seq: true, duplicate_injection_rate: 0.2, seed: 7, ..WriterOptions::default() };
+        let mut writer = EventWriter::new(dir.path(), "2025-01-01", 1 << 20, 0, CompressionType::None, None, options).unwrap();
+        for i in 0..500 {
+            writer.write_row(&sample_row(i)).unwrap();
+        }
+        writer.close().unwrap();
+
+        let day_dir = dir.path().join("2025-01-01");
+        let file = "cdr_2025-01-01_writer000_part001.csv";
+        let (headers, rows) = read_csv(&day_dir.join(file));
+        let seq_col = headers.iter().position(|h| h == "record_seq").unwrap();
+
+        let log = std::fs::read_to_string(day_dir.join("duplicates_writer000.jsonl")).unwrap();
+        let logged: Vec<u64> = log
+            .lines()
+            .map(|line| {
+                let v: serde_json::Value = serde_json::from_str(line).unwrap();
+                assert_eq!(v["file"], file);
+                v["record_seq"].as_u64().unwrap()
+            })
+            .collect();
+        assert!(!logged.is_empty());
+        assert_eq!(rows.len(), 500 + logged.len());
+
+        // Each logged seq appears twice with identical content
+        for seq in &logged {
+            let copies: Vec<_> = rows.iter().filter(|r| r[seq_col] == seq.to_string()).collect();
+            assert_eq!(copies.len(), 2);
+            assert_eq!(copies[0], copies[1]);
+        }
+
+        let manifest = read_manifest(&day_dir);
+        assert_eq!(manifest.files[0].duplicates, logged.len() as u64);
+        assert_eq!(manifest.files[0].records, rows.len() as u64);
+    }
+}
@@ -0,0 +1,200 @@
+// Integration tests for output_format: arrow, read back with the arrow crate against a CSV run of the same seed
+#![cfg(feature = "arrow")]
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Int32Type, Int64Type, UInt32Type, UInt64Type};
+use arrow_array::{Array, ArrayRef, RecordBatch};
+use arrow_ipc::reader::FileReader;
+use arrow_schema::DataType;
+use chrono::NaiveDate;
+use rs_cdr_generator::api::{DayGenerator, DaySummary};
+use rs_cdr_generator::arrow_writer::{cdr_arrow_schema, DICTIONARY_COLUMNS};
+use rs_cdr_generator::config::{Config, OutputFormat};
+use rs_cdr_generator::writer::ShardManifest;
+use std::path::Path;
+use tempfile::TempDir;
+
+/// Columns compared between the two runs, named as in both the CSV header and the Arrow schema
+const COMPARED: [&str; 15] = [
+    "event_type",
+    "msisdn_src",
+    "msisdn_dst",
+    "direction",
+    "start_ts_ms",
+    "end_ts_ms",
+    "duration_sec",
+    "imsi",
+    "imei",
+    "cell_id",
+    "record_type",
+    "sms_status",
+    "data_bytes_in",
+    "apn",
+    "rat",
+];
+
+fn config(output_format: OutputFormat, arrow_rotate_rows: u64) -> Config {
+    Config {
+        subscribers: 500,
+        workers: 4,
+        writer_tasks: 2,
+        compression_type: "none".to_string(),
+        output_format,
+        arrow_rotate_rows,
+        ..Config::default()
+    }
+}
+
+fn generate(out_dir: &Path, cfg: Config) -> DaySummary {
+    let mut builder = DayGenerator::builder().config(cfg.clone()).seed(7).out_dir(out_dir);
+    if cfg.output_format == OutputFormat::Csv {
+        builder = builder.bundle(false);
+    }
+    builder.build().unwrap().generate(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap()).unwrap()
+}
+
+fn rows(summary: &DaySummary) -> usize {
+    summary.total_calls + summary.total_sms + summary.sms_retry_rows + summary.total_data
+}
+
+/// Manifests of the day, in writer task order
+fn manifests(out_dir: &Path) -> Vec<ShardManifest> {
+    let day_dir = out_dir.join("2025-01-01");
+    let mut paths: Vec<_> = std::fs::read_dir(&day_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.file_name().unwrap().to_string_lossy().starts_with("manifest_writer"))
+        .collect();
+    paths.sort();
+    paths.iter().map(|path| serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()).collect()
+}
+
+/// A cell as the CSV writes it: nulls are empty
+fn cell(column: &ArrayRef, idx: usize) -> String {
+    if column.is_null(idx) {
+        return String::new();
+    }
+    match column.data_type() {
+        DataType::Dictionary(..) => {
+            let dictionary = column.as_dictionary::<Int32Type>();
+            let key = dictionary.keys().value(idx) as usize;
+            dictionary.values().as_string::<i32>().value(key).to_string()
+        }
+        DataType::Utf8 => column.as_string::<i32>().value(idx).to_string(),
+        DataType::UInt64 => column.as_primitive::<UInt64Type>().value(idx).to_string(),
+        DataType::UInt32 => column.as_primitive::<UInt32Type>().value(idx).to_string(),
+        DataType::Int64 => column.as_primitive::<Int64Type>().value(idx).to_string(),
+        DataType::Int32 => column.as_primitive::<Int32Type>().value(idx).to_string(),
+        other => panic!("unexpected column type {}", other),
+    }
+}
+
+/// Rows of the Arrow files in `COMPARED` columns, and the record count of each file
+fn arrow_rows(out_dir: &Path) -> (Vec<Vec<String>>, Vec<u64>) {
+    let mut rows = Vec::new();
+    let mut file_records = Vec::new();
+    for manifest in manifests(out_dir) {
+        for entry in &manifest.files {
+            let file = std::fs::File::open(out_dir.join("2025-01-01").join(&entry.file)).unwrap();
+            let reader = FileReader::try_new(file, None).unwrap();
+            assert_eq!(reader.schema().as_ref(), &cdr_arrow_schema());
+            let batches: Vec<RecordBatch> = reader.map(|batch| batch.unwrap()).collect();
+            let records: usize = batches.iter().map(|batch| batch.num_rows()).sum();
+            assert_eq!(records as u64, entry.records);
+            file_records.push(entry.records);
+            for batch in &batches {
+                let columns: Vec<&ArrayRef> = COMPARED.iter().map(|name| batch.column_by_name(name).unwrap()).collect();
+                for idx in 0..batch.num_rows() {
+                    rows.push(columns.iter().map(|column| cell(column, idx)).collect());
+                }
+            }
+        }
+    }
+    (rows, file_records)
+}
+
+/// Rows of the CSV part files in `COMPARED` columns
+fn csv_rows(out_dir: &Path) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    for manifest in manifests(out_dir) {
+        for entry in &manifest.files {
+            let mut reader = csv::ReaderBuilder::new()
+                .delimiter(b';')
+                .from_path(out_dir.join("2025-01-01").join(&entry.file))
+                .unwrap();
+            let headers = reader.headers().unwrap().clone();
+            let positions: Vec<usize> =
+                COMPARED.iter().map(|name| headers.iter().position(|header| header == *name).unwrap()).collect();
+            for record in reader.records() {
+                let record = record.unwrap();
+                rows.push(positions.iter().map(|&pos| record[pos].to_string()).collect());
+            }
+        }
+    }
+    rows
+}
+
+#[test]
+fn test_arrow_files_match_the_csv_of_the_same_seed() {
+    let csv_dir = TempDir::new().unwrap();
+    let arrow_dir = TempDir::new().unwrap();
+    let csv_summary = generate(csv_dir.path(), config(OutputFormat::Csv, 0));
+    let arrow_summary = generate(arrow_dir.path(), config(OutputFormat::Arrow, 2_000));
+    assert_eq!(rows(&arrow_summary), rows(&csv_summary));
+
+    let (mut arrow, file_records) = arrow_rows(arrow_dir.path());
+    let mut csv = csv_rows(csv_dir.path());
+    assert_eq!(arrow.len(), rows(&csv_summary));
+    assert_eq!(arrow.len(), csv.len());
+    // Rotation by rows: full files of 2,000, and more than one per writer task
+    assert!(file_records.len() > 2, "{:?}", file_records);
+    assert!(file_records.iter().all(|&records| records <= 2_000), "{:?}", file_records);
+
+    // Writer tasks interleave worker batches differently in each run; compare as sorted rows
+    arrow.sort();
+    csv.sort();
+    for idx in [0, arrow.len() / 3, arrow.len() / 2, arrow.len() - 1] {
+        assert_eq!(arrow[idx], csv[idx], "row {}", idx);
+    }
+    assert!(arrow == csv);
+    for event_type in ["CALL", "SMS", "DATA"] {
+        assert!(arrow.iter().any(|row| row[0] == event_type), "no {} rows", event_type);
+    }
+
+    // Arrow part files are the whole output: no CSV parts
+    let day_files: Vec<String> = std::fs::read_dir(arrow_dir.path().join("2025-01-01"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name.starts_with("cdr_"))
+        .collect();
+    assert!(day_files.iter().all(|name| name.ends_with(".arrow")), "{:?}", day_files);
+    // They carry their own schema, so there is no schema.json for them
+    let generator = DayGenerator::builder()
+        .config(config(OutputFormat::Arrow, 2_000))
+        .seed(7)
+        .out_dir(arrow_dir.path())
+        .build()
+        .unwrap();
+    assert!(generator.write_schema().is_err());
+    assert!(!arrow_dir.path().join("schema.json").exists());
+}
+
+#[test]
+fn test_arrow_columns_are_typed_and_dictionary_encoded() {
+    let schema = cdr_arrow_schema();
+    for name in DICTIONARY_COLUMNS {
+        assert!(matches!(schema.field_with_name(name).unwrap().data_type(), DataType::Dictionary(..)), "{}", name);
+    }
+    assert_eq!(schema.field_with_name("msisdn_src").unwrap().data_type(), &DataType::UInt64);
+    assert_eq!(schema.field_with_name("start_ts_ms").unwrap().data_type(), &DataType::Int64);
+    assert_eq!(schema.field_with_name("cell_id").unwrap().data_type(), &DataType::UInt32);
+
+    // Arrow files are not bundled
+    let error = DayGenerator::builder()
+        .config(config(OutputFormat::Arrow, 0))
+        .out_dir(TempDir::new().unwrap().path())
+        .bundle(false)
+        .build()
+        .err()
+        .unwrap();
+    assert!(error.to_string().contains("not bundled"), "{}", error);
+}
@@ -0,0 +1,84 @@
+// Integration tests for worker batch flushing: by age with batch_max_age_ms, and at redb chunk boundaries
+use chrono::NaiveDate;
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::config::Config;
+use rs_cdr_generator::event_pool::min_event_pool_size;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+mod common;
+
+fn date() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2025, 3, 10).unwrap()
+}
+
+/// Rows of a batch and its age when the worker sent it
+type BatchSeen = (usize, Option<Duration>);
+/// (msisdn_src, start_ts_ms, event_type)
+type RowKey = (u64, i64, String);
+
+/// Every batch as seen by the sink, and the sorted rows
+fn generate(cfg: Config, subscriber_db: Option<&std::path::Path>) -> (Vec<BatchSeen>, Vec<RowKey>) {
+    let batches: Arc<Mutex<Vec<BatchSeen>>> = Arc::default();
+    let rows: Arc<Mutex<Vec<RowKey>>> = Arc::default();
+    let (sink_batches, sink_rows) = (batches.clone(), rows.clone());
+    let mut builder = DayGenerator::builder().config(cfg).sink(move |batch| {
+        sink_batches.lock().unwrap().push((batch.events.len(), batch.sent_age()));
+        let mut rows = sink_rows.lock().unwrap();
+        rows.extend(batch.events.iter().map(|row| (row.msisdn_src, row.start_ts_ms, row.event_type.to_string())));
+    });
+    if let Some(path) = subscriber_db {
+        builder = builder.subscriber_db(path);
+    }
+    builder.build().unwrap().generate(date()).unwrap();
+    let mut rows = rows.lock().unwrap().clone();
+    rows.sort();
+    let batches = batches.lock().unwrap().clone();
+    (batches, rows)
+}
+
+/// Batches far larger than a day of these tests, with a pool that holds one
+fn never_full() -> Config {
+    let batch_size_bytes = 1 << 25;
+    Config { batch_size_bytes, event_pool_size: min_event_pool_size(batch_size_bytes), ..Config::default() }
+}
+
+#[test]
+fn test_no_batch_exceeds_the_age_bound() {
+    // Batches never fill up, so without an age bound each worker sends a single batch at the end of its range
+    let cfg = Config { subscribers: 4_000, workers: 2, ..never_full() };
+    let (whole, expected_rows) = generate(cfg.clone(), None);
+    assert_eq!(whole.len(), 2);
+
+    let max_age = Duration::from_millis(10);
+    let (batches, rows) = generate(Config { batch_max_age_ms: 10, ..cfg }, None);
+    // Generating a worker's range takes far longer than 10 ms, so rows go out in many small batches
+    assert!(batches.len() > 4, "{} batches", batches.len());
+    assert!(batches.iter().all(|(len, age)| *len > 0 && age.is_some()), "age is tracked with batch_max_age_ms");
+    // None fills up, so every send but the last of each worker was triggered by the age bound
+    let sent_by_age = batches.iter().filter(|(_, age)| age.unwrap() >= max_age).count();
+    assert!(sent_by_age >= batches.len() - 2, "{} of {} batches sent by age", sent_by_age, batches.len());
+    assert_eq!(rows, expected_rows);
+}
+
+#[cfg(feature = "redb")]
+#[test]
+fn test_partial_batch_is_sent_at_each_chunk_boundary() {
+    const PREFIX: u64 = 31612;
+    let dir = tempfile::TempDir::new().unwrap();
+    let db_path = dir.path().join("subs.redb");
+    common::build_indexed_db(&db_path, PREFIX, 0..1_000);
+
+    let cfg = Config {
+        prefixes: vec![PREFIX.to_string()],
+        workers: 1,
+        chunk_size: 100,
+        daily_inactive_rate: 0.0,
+        ..never_full()
+    };
+    let (per_chunk, rows) = generate(cfg.clone(), Some(&db_path));
+    assert_eq!(per_chunk.len(), 10, "one batch per chunk of 100 subscribers");
+    let (whole, whole_rows) = generate(Config { flush_batch_per_chunk: false, ..cfg }, Some(&db_path));
+    assert_eq!(whole.len(), 1);
+    assert_eq!(rows, whole_rows);
+}
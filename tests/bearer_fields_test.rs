@@ -0,0 +1,114 @@
+// Integration tests for emit_bearer_fields: DATA rows carry the QCI of their APN and a per-subscriber bearer id
+use chrono::NaiveDate;
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::config::Config;
+use rs_cdr_generator::reader::{open_cdr_reader, ParsedEventRow};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+fn date() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2025, 3, 10).unwrap()
+}
+
+fn part_files(day_dir: &Path) -> Vec<PathBuf> {
+    let mut parts: Vec<PathBuf> = std::fs::read_dir(day_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            let name = path.file_name().unwrap().to_string_lossy();
+            name.starts_with("cdr_") && name.ends_with(".csv")
+        })
+        .collect();
+    parts.sort();
+    parts
+}
+
+fn header(path: &Path) -> Vec<String> {
+    let mut reader = csv::ReaderBuilder::new().delimiter(b';').from_path(path).unwrap();
+    reader.headers().unwrap().iter().map(str::to_string).collect()
+}
+
+/// Header and rows of every part file of a day generated with `cfg`
+fn generate(cfg: Config) -> anyhow::Result<(Vec<String>, Vec<ParsedEventRow>)> {
+    let temp_dir = TempDir::new()?;
+    DayGenerator::builder().config(cfg).out_dir(temp_dir.path()).bundle(false).build()?.generate(date())?;
+    let parts = part_files(&temp_dir.path().join("2025-03-10"));
+    assert!(!parts.is_empty());
+    let mut rows = Vec::new();
+    for part in &parts {
+        assert_eq!(header(part), header(&parts[0]));
+        for row in open_cdr_reader(part)? {
+            rows.push(row?);
+        }
+    }
+    Ok((header(&parts[0]), rows))
+}
+
+fn config() -> Config {
+    Config {
+        subscribers: 300,
+        workers: 2,
+        compression_type: "none".to_string(),
+        avg_data_sessions_per_user: 6.0,
+        emit_bearer_fields: true,
+        ..Config::default()
+    }
+}
+
+#[test]
+fn test_ims_gets_its_configured_qci() -> anyhow::Result<()> {
+    // Both serialization paths must agree on the layout
+    for serialize_in_workers in [true, false] {
+        let cfg = Config {
+            apn_mix: BTreeMap::from([("ims".to_string(), 0.5), ("internet".to_string(), 0.5)]),
+            qci_by_apn: BTreeMap::from([("ims".to_string(), BTreeMap::from([(5, 1.0)]))]),
+            nr_5qi_by_apn: BTreeMap::from([("ims".to_string(), BTreeMap::from([(5, 1.0)]))]),
+            serialize_in_workers,
+            ..config()
+        };
+        let (columns, rows) = generate(cfg)?;
+        let rat = columns.iter().position(|c| c == "rat").unwrap();
+        assert_eq!(columns[rat + 1..rat + 3], ["qci", "bearer_id"]);
+
+        let data: Vec<&ParsedEventRow> = rows.iter().filter(|row| row.event_type == "DATA").collect();
+        let ims = data.iter().filter(|row| row.apn == "ims").count();
+        assert!(ims > 100, "{} ims sessions", ims);
+        for row in &data {
+            // internet has no table here and gets the default QCI 9
+            let expected = if row.apn == "ims" { 5 } else { 9 };
+            assert_eq!(row.qci, expected, "{} session on {}", row.apn, row.rat);
+        }
+        // CALL and SMS rows leave both cells empty
+        assert!(rows.iter().filter(|row| row.event_type != "DATA").all(|row| row.qci == 0 && row.bearer_id == 0));
+
+        // Each subscriber's bearers are numbered 1..=n over the day
+        let mut bearers: HashMap<u64, Vec<u32>> = HashMap::new();
+        for row in &data {
+            bearers.entry(row.imsi).or_default().push(row.bearer_id);
+        }
+        for (imsi, mut ids) in bearers {
+            ids.sort_unstable();
+            assert_eq!(ids, (1..=ids.len() as u32).collect::<Vec<_>>(), "bearers of {}", imsi);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_bearer_columns_off_by_default() -> anyhow::Result<()> {
+    let (columns, rows) = generate(Config { emit_bearer_fields: false, ..config() })?;
+    assert!(!columns.iter().any(|c| c == "qci" || c == "bearer_id"), "{:?}", columns);
+    assert!(rows.iter().all(|row| row.qci == 0 && row.bearer_id == 0));
+    Ok(())
+}
+
+#[test]
+fn test_out_of_range_qci_is_rejected() {
+    let cfg = Config {
+        qci_by_apn: BTreeMap::from([("internet".to_string(), BTreeMap::from([(10, 1.0)]))]),
+        ..config()
+    };
+    let err = DayGenerator::builder().config(cfg).sink(|_| {}).build().err().unwrap().to_string();
+    assert_eq!(err, "Config key qci_by_apn: APN internet has QCI 10, expected 1..=9");
+}
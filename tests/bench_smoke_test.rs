@@ -0,0 +1,36 @@
+// Smoke checks that keep the benchmarks in sync with the library API
+use rs_cdr_generator::compression::CompressionType;
+use rs_cdr_generator::config::load_config;
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn test_benchmark_micro_config_loads() -> anyhow::Result<()> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("benches/configs/benchmark_micro.yaml");
+    assert!(path.exists(), "missing {:?}", path);
+
+    let cfg = load_config(Some(&path))?;
+    assert_eq!(cfg.subscribers, 1000);
+    assert_eq!(cfg.workers, 1);
+    assert_eq!(CompressionType::from_str(&cfg.compression_type), Some(CompressionType::None));
+
+    Ok(())
+}
+
+/// Equivalent of `cargo bench` without the release/LTO build cost: criterion runs each benchmark once under
+/// `cargo test`, so benches/ can neither drift from the current API nor fail at run time
+#[test]
+fn test_benches_run_once() {
+    let output = Command::new(env!("CARGO"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(["test", "--benches", "--quiet"])
+        .output()
+        .expect("failed to run cargo");
+
+    assert!(
+        output.status.success(),
+        "benches do not compile or fail to run:\n{}\n{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
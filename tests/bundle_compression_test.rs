@@ -0,0 +1,77 @@
+// Integration test for compression_by_target: gzip part files, a zstd day bundle re-encoded from them
+#![cfg(feature = "zstd")]
+use chrono::NaiveDate;
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::config::{CompressionByTarget, CompressionTarget, Config};
+use rs_cdr_generator::reader::open_decompressed;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+fn target(compression_type: &str, level: Option<i32>) -> Option<CompressionTarget> {
+    Some(CompressionTarget { compression_type: compression_type.to_string(), level })
+}
+
+fn decompress(path: &Path) -> Vec<u8> {
+    let mut content = Vec::new();
+    open_decompressed(path).unwrap().read_to_end(&mut content).unwrap();
+    content
+}
+
+#[test]
+fn test_zstd_bundle_of_gzip_parts() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let cfg = Config {
+        subscribers: 300,
+        workers: 2,
+        rotate_bytes: 50_000,
+        compression_by_target: CompressionByTarget {
+            parts: target("gzip", None),
+            bundle: target("zstd", Some(19)),
+        },
+        ..Config::default()
+    };
+    // Keep the parts to compare against
+    let mut generator = DayGenerator::builder().config(cfg).out_dir(temp_dir.path()).bundle(false).build()?;
+    generator.generate(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap())?;
+
+    let mut parts: Vec<PathBuf> = std::fs::read_dir(temp_dir.path().join("2025-01-01"))?
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.file_name().unwrap().to_string_lossy().starts_with("cdr_"))
+        .collect();
+    parts.sort();
+    assert!(parts.len() >= 2);
+    assert!(parts.iter().all(|path| path.to_string_lossy().ends_with(".csv.gz")));
+
+    let bundle = temp_dir.path().join("cdr_2025-01-01.csv.zst");
+    assert!(!temp_dir.path().join("cdr_2025-01-01.csv.gz").exists());
+    // A single zstd frame, not concatenated gzip members
+    assert_eq!(std::fs::read(&bundle)?[..4], [0x28, 0xB5, 0x2F, 0xFD]);
+
+    let expected: Vec<u8> = parts.iter().flat_map(|path| decompress(path)).collect();
+    assert_eq!(decompress(&bundle), expected);
+    Ok(())
+}
+
+#[test]
+fn test_bundle_level_change_reencodes() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let cfg = Config {
+        subscribers: 200,
+        workers: 2,
+        compression_type: "zstd".to_string(),
+        compression_level: Some(1),
+        compression_by_target: CompressionByTarget { parts: None, bundle: target("zstd", Some(19)) },
+        ..Config::default()
+    };
+    let mut generator = DayGenerator::builder().config(cfg).out_dir(temp_dir.path()).bundle(true).build()?;
+    generator.generate(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap())?;
+
+    // Re-encoded into one frame instead of one frame per part
+    let bundle = std::fs::read(temp_dir.path().join("cdr_2025-01-01.csv.zst"))?;
+    let frames = bundle.windows(4).filter(|w| *w == [0x28, 0xB5, 0x2F, 0xFD]).count();
+    assert_eq!(frames, 1);
+    let content = String::from_utf8(decompress(&temp_dir.path().join("cdr_2025-01-01.csv.zst")))?;
+    assert!(content.starts_with("event_type;"));
+    Ok(())
+}
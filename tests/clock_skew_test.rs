@@ -0,0 +1,202 @@
+// Integration tests for clock_skew: node clocks applied to written timestamps, with the true times in a sidecar
+use chrono::NaiveDate;
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::config::{ClockSkewConfig, Config, NodeClockSkew};
+use rs_cdr_generator::writer::ShardManifest;
+use std::collections::HashMap;
+use std::path::Path;
+use tempfile::TempDir;
+
+fn config(clock_skew: ClockSkewConfig) -> Config {
+    Config {
+        subscribers: 400,
+        workers: 2,
+        compression_type: "none".to_string(),
+        clock_skew,
+        ..Config::default()
+    }
+}
+
+fn skewed() -> ClockSkewConfig {
+    ClockSkewConfig {
+        msc: NodeClockSkew { mean_ms: 1_500.0, sigma_ms: 800.0, drift_ms_per_hour: 20.0, nodes: 4 },
+        pgw: NodeClockSkew { mean_ms: -3_000.0, sigma_ms: 0.0, drift_ms_per_hour: 0.0, nodes: 1 },
+        ground_truth: true,
+        ..ClockSkewConfig::default()
+    }
+}
+
+fn generate(out_dir: &Path, cfg: Config) {
+    DayGenerator::builder()
+        .config(cfg)
+        .seed(11)
+        .out_dir(out_dir)
+        .bundle(false)
+        .build()
+        .unwrap()
+        .generate(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap())
+        .unwrap();
+}
+
+/// Rows as (event_type, direction, msisdn_src, record_type, start_ts_ms, end_ts_ms)
+fn read_rows(out_dir: &Path) -> Vec<(String, String, String, String, i64, i64)> {
+    let day_dir = out_dir.join("2025-01-01");
+    let mut rows = Vec::new();
+    for entry in std::fs::read_dir(&day_dir).unwrap() {
+        let name = entry.unwrap().file_name().to_string_lossy().into_owned();
+        if !name.starts_with("manifest_writer") {
+            continue;
+        }
+        let manifest: ShardManifest = serde_json::from_str(&std::fs::read_to_string(day_dir.join(&name)).unwrap()).unwrap();
+        for part in &manifest.files {
+            let mut reader = csv::ReaderBuilder::new().delimiter(b';').from_path(day_dir.join(&part.file)).unwrap();
+            let headers = reader.headers().unwrap().clone();
+            let col = |name: &str| headers.iter().position(|header| header == name).unwrap();
+            let cols = [col("event_type"), col("direction"), col("msisdn_src"), col("record_type"), col("start_ts_ms"), col("end_ts_ms")];
+            for record in reader.records() {
+                let record = record.unwrap();
+                rows.push((
+                    record[cols[0]].to_string(),
+                    record[cols[1]].to_string(),
+                    record[cols[2]].to_string(),
+                    record[cols[3]].to_string(),
+                    record[cols[4]].parse().unwrap(),
+                    record[cols[5]].parse().unwrap(),
+                ));
+            }
+        }
+    }
+    rows.sort();
+    rows
+}
+
+/// Lines of every `clock_skew_shardNNN.jsonl`
+fn ground_truth(out_dir: &Path) -> Vec<serde_json::Value> {
+    let day_dir = out_dir.join("2025-01-01");
+    let mut lines = Vec::new();
+    for entry in std::fs::read_dir(&day_dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.file_name().unwrap().to_string_lossy().starts_with("clock_skew_shard") {
+            for line in std::fs::read_to_string(&path).unwrap().lines() {
+                lines.push(serde_json::from_str(line).unwrap());
+            }
+        }
+    }
+    lines
+}
+
+#[test]
+fn test_skewed_rows_map_back_to_the_true_times() {
+    let true_dir = TempDir::new().unwrap();
+    let skew_dir = TempDir::new().unwrap();
+    generate(true_dir.path(), config(ClockSkewConfig::default()));
+    generate(skew_dir.path(), config(skewed()));
+    let true_rows = read_rows(true_dir.path());
+    let skew_rows = read_rows(skew_dir.path());
+    assert_eq!(true_rows.len(), skew_rows.len());
+    assert!(ground_truth(true_dir.path()).is_empty());
+
+    // Written start -> true start, per record key
+    let truth = ground_truth(skew_dir.path());
+    let mut true_start: HashMap<(String, String, String, String, i64), i64> = HashMap::new();
+    for line in &truth {
+        let key = (
+            line["event_type"].as_str().unwrap().to_string(),
+            line["direction"].as_str().unwrap().to_string(),
+            line["msisdn_src"].to_string(),
+            line["record_type"].as_str().unwrap().to_string(),
+            line["start_ts_ms"].as_i64().unwrap(),
+        );
+        true_start.insert(key, line["true_start_ts_ms"].as_i64().unwrap());
+        assert_eq!(line["start_ts_ms"].as_i64().unwrap() - line["true_start_ts_ms"].as_i64().unwrap(), line["skew_ms"].as_i64().unwrap());
+        match line["pool"].as_str().unwrap() {
+            "msc" => assert!(line["event_type"] == "CALL" || line["event_type"] == "SMS", "{}", line),
+            "pgw" => assert_eq!(line["skew_ms"], -3_000),
+            other => panic!("no skew configured for {}", other),
+        }
+    }
+    assert!(truth.iter().any(|line| line["pool"] == "msc") && truth.iter().any(|line| line["pool"] == "pgw"));
+
+    // Undoing the skew gives back the run without it; SGSN sessions were never moved
+    let mut restored: Vec<_> = skew_rows
+        .into_iter()
+        .map(|(event_type, direction, msisdn, record_type, start, end)| {
+            let key = (event_type.clone(), direction.clone(), msisdn.clone(), record_type.clone(), start);
+            let shift = true_start.get(&key).map_or(0, |true_start| start - true_start);
+            (event_type, direction, msisdn, record_type, start - shift, end - shift)
+        })
+        .collect();
+    restored.sort();
+    assert_eq!(restored, true_rows);
+}
+
+#[test]
+fn test_call_legs_differ_by_the_relative_skew() {
+    let dir = TempDir::new().unwrap();
+    generate(dir.path(), config(skewed()));
+
+    // Legs of one call share the true start; the parties' MSC elements stamp them differently
+    let mut legs: HashMap<i64, Vec<(String, i64)>> = HashMap::new();
+    for line in ground_truth(dir.path()).iter().filter(|line| line["event_type"] == "CALL") {
+        legs.entry(line["true_start_ts_ms"].as_i64().unwrap())
+            .or_default()
+            .push((line["direction"].as_str().unwrap().to_string(), line["skew_ms"].as_i64().unwrap()));
+    }
+    let pairs: Vec<&Vec<(String, i64)>> = legs
+        .values()
+        .filter(|legs| legs.len() == 2 && legs.iter().any(|leg| leg.0 == "MO") && legs.iter().any(|leg| leg.0 == "MT"))
+        .collect();
+    assert!(!pairs.is_empty());
+    assert!(pairs.iter().any(|legs| legs[0].1 != legs[1].1));
+}
+
+#[test]
+fn test_node_id_names_the_writing_element() {
+    // Elements without skew: only the column is added, and the legs of a call keep their shared start
+    let dir = TempDir::new().unwrap();
+    generate(dir.path(), Config { emit_node_id: true, ..config(ClockSkewConfig::default()) });
+
+    let mut legs: HashMap<i64, Vec<(String, String)>> = HashMap::new();
+    let mut rows = 0;
+    for path in part_files(&dir.path().join("2025-01-01")) {
+        let mut reader = csv::ReaderBuilder::new().delimiter(b';').from_path(&path).unwrap();
+        let headers = reader.headers().unwrap().clone();
+        let col = |name: &str| headers.iter().position(|header| header == name).unwrap();
+        assert_eq!(col("node_id"), col("record_type") + 1);
+        let [event_type, direction, record_type, node_id, start] =
+            ["event_type", "direction", "record_type", "node_id", "start_ts_ms"].map(col);
+        for record in reader.records() {
+            let record = record.unwrap();
+            let pool = match (&record[event_type], &record[record_type]) {
+                ("DATA", "pgwRecord") => "pgw",
+                ("DATA", _) => "sgsn",
+                _ => "msc",
+            };
+            let node = &record[node_id];
+            assert!(node.starts_with(pool) && node.len() == pool.len() + 2, "{:?}", record);
+            rows += 1;
+            if &record[event_type] == "CALL" {
+                legs.entry(record[start].parse().unwrap()).or_default().push((record[direction].to_string(), node.to_string()));
+            }
+        }
+    }
+    assert!(rows > 0);
+    // The parties of a call are homed on their own MSCs, so some pairs of legs come from different elements
+    assert!(legs.values().any(|legs| legs.len() == 2 && legs[0].0 != legs[1].0 && legs[0].1 != legs[1].1));
+
+    // No column without the flag
+    let plain = TempDir::new().unwrap();
+    generate(plain.path(), config(skewed()));
+    for path in part_files(&plain.path().join("2025-01-01")) {
+        let mut reader = csv::ReaderBuilder::new().delimiter(b';').from_path(&path).unwrap();
+        assert!(!reader.headers().unwrap().iter().any(|header| header == "node_id"));
+    }
+}
+
+fn part_files(day_dir: &Path) -> Vec<std::path::PathBuf> {
+    std::fs::read_dir(day_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.file_name().unwrap().to_string_lossy().starts_with("cdr_"))
+        .collect()
+}
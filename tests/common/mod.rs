@@ -0,0 +1,39 @@
+// Fixtures shared by the integration test suites; each suite uses only some of them
+#![allow(dead_code)]
+
+#[cfg(feature = "redb")]
+use rs_cdr_generator::identity::SubscriberIdentity;
+#[cfg(feature = "redb")]
+use rs_cdr_generator::subscriber_db::SubscriberSnapshot;
+#[cfg(feature = "redb")]
+use rs_cdr_generator::subscriber_db_redb::SubscriberDbRedb;
+#[cfg(feature = "redb")]
+use std::path::Path;
+#[cfg(all(feature = "arrow", feature = "redb", feature = "async", feature = "zstd"))]
+use std::process::Command;
+
+/// The CLI binary, which is only built with every optional feature, logging at `log_level` (`RUST_LOG`)
+#[cfg(all(feature = "arrow", feature = "redb", feature = "async", feature = "zstd"))]
+pub fn cli(log_level: &str) -> Command {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_rs_cdr_generator"));
+    cmd.env("RUST_LOG", log_level);
+    cmd
+}
+
+/// Subscriber `idx` under `prefix`, numbered as the workers derive MSISDNs (prefix × 10^7 + index), with IMSI
+/// and IMEI derived from the index and one snapshot valid from the epoch on
+#[cfg(feature = "redb")]
+pub fn indexed_subscriber(prefix: u64, idx: u64) -> (u64, Vec<SubscriberSnapshot>) {
+    let msisdn = prefix * 10_000_000 + idx;
+    let identity = SubscriberIdentity::from_numbers(msisdn, 204080000000000 + idx, 350000000000000 + idx, 20408).unwrap();
+    (msisdn, vec![SubscriberSnapshot { identity, valid_from: 0, valid_to: None }])
+}
+
+/// redb database at `path` with the `indexed_subscriber`s of `indexes` under `prefix`
+#[cfg(feature = "redb")]
+pub fn build_indexed_db(path: &Path, prefix: u64, indexes: impl IntoIterator<Item = u64>) -> SubscriberDbRedb {
+    let db = SubscriberDbRedb::new(path).unwrap();
+    let batch: Vec<(u64, Vec<SubscriberSnapshot>)> = indexes.into_iter().map(|idx| indexed_subscriber(prefix, idx)).collect();
+    db.insert_snapshots_batch(&batch).unwrap();
+    db
+}
@@ -0,0 +1,137 @@
+// Integration test for config precedence: YAML from stdin < CDRGEN_* environment < CLI flags
+use chrono::NaiveDate;
+use rs_cdr_generator::subscriber_db_redb::SubscriberDbRedb;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::Stdio;
+use tempfile::TempDir;
+
+mod common;
+
+/// Run generate-cdr with `yaml` on stdin, returning the names of the day bundle and the files in the day folder
+fn generate_cdr(db_path: &Path, out_dir: &Path, yaml: &str, envs: &[(&str, &str)], args: &[&str]) -> Vec<String> {
+    let mut cmd = common::cli("error");
+    cmd.arg("generate-cdr")
+        .arg("--subscriber-db")
+        .arg(db_path)
+        .arg("--out")
+        .arg(out_dir)
+        .args(["--config", "-"])
+        .args(args)
+        .stdin(Stdio::piped());
+    for (key, value) in envs {
+        cmd.env(key, value);
+    }
+
+    let mut child = cmd.spawn().unwrap();
+    child.stdin.take().unwrap().write_all(yaml.as_bytes()).unwrap();
+    let status = child.wait().unwrap();
+    assert!(status.success());
+
+    let mut names: Vec<String> = std::fs::read_dir(out_dir.join("2025-01-01"))
+        .unwrap()
+        .chain(std::fs::read_dir(out_dir).unwrap())
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name != "2025-01-01")
+        .collect();
+    names.sort();
+    names
+}
+
+/// Data rows of the day bundle, decompressed by its extension
+fn bundle_rows(out_dir: &Path, extension: &str) -> usize {
+    let bytes = std::fs::read(out_dir.join(format!("cdr_2025-01-01.csv{}", extension))).unwrap();
+    let csv = match extension {
+        ".zst" => zstd::decode_all(bytes.as_slice()).unwrap(),
+        ".gz" => {
+            let mut csv = Vec::new();
+            flate2::read::MultiGzDecoder::new(bytes.as_slice()).read_to_end(&mut csv).unwrap();
+            csv
+        }
+        _ => bytes,
+    };
+    String::from_utf8(csv).unwrap().lines().count().saturating_sub(1)
+}
+
+fn count(names: &[String], prefix: &str, suffix: &str) -> usize {
+    names.iter().filter(|n| n.starts_with(prefix) && n.ends_with(suffix)).count()
+}
+
+#[test]
+fn test_config_precedence_stdin_env_cli() {
+    let dir = TempDir::new().unwrap();
+    let db_path = dir.path().join("subs.redb");
+    let status = common::cli("error")
+        .arg("generate-subscribers")
+        .arg("--output")
+        .arg(&db_path)
+        .args(["--size", "200", "--history-days", "30", "--start-date", "2024-12-01"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    // The generated numbers are random, not index-derived: list the subscribers of 2025-01-01 for msisdn_file
+    let day_ms = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap().and_hms_opt(12, 0, 0).unwrap().and_utc().timestamp_millis();
+    let mut msisdns = String::new();
+    SubscriberDbRedb::open(&db_path)
+        .unwrap()
+        .iter_all(|msisdn, snapshots| {
+            if SubscriberDbRedb::find_snapshot_at(&snapshots, day_ms).is_some() {
+                msisdns.push_str(&format!("{}\n", msisdn));
+            }
+            Ok(())
+        })
+        .unwrap();
+    assert!(msisdns.lines().count() > 150, "{} subscribers on 2025-01-01", msisdns.lines().count());
+    std::fs::write(db_path.with_extension("txt"), msisdns).unwrap();
+
+    let msisdn_file = db_path.with_extension("txt");
+    let yaml = format!("workers: 2\nrotate_bytes: 100000000\ncompression_type: zstd\nmsisdn_file: {:?}\n", msisdn_file);
+    let yaml = yaml.as_str();
+
+    // YAML from stdin alone: 2 workers -> 1 writer task, zstd bundle
+    let yaml_dir = dir.path().join("yaml");
+    let names = generate_cdr(&db_path, &yaml_dir, yaml, &[], &[]);
+    assert_eq!(count(&names, "manifest_writer", ".json"), 1);
+    assert_eq!(count(&names, "cdr_2025-01-01.", ".csv.zst"), 1);
+    assert!(bundle_rows(&yaml_dir, ".zst") > 0);
+
+    // Environment beats YAML: 4 workers -> 2 writer tasks, uncompressed
+    let envs = [
+        ("CDRGEN_WORKERS", "4"),
+        ("CDRGEN_ROTATE_BYTES", "1"),
+        ("CDRGEN_COMPRESSION_TYPE", "none"),
+    ];
+    let env_dir = dir.path().join("env");
+    let names = generate_cdr(&db_path, &env_dir, yaml, &envs, &[]);
+    assert_eq!(count(&names, "manifest_writer", ".json"), 2);
+    assert_eq!(count(&names, "cdr_", ".zst"), 0);
+    assert_eq!(count(&names, "cdr_2025-01-01.", ".csv"), 1);
+    // rotate_bytes 1 starts a new part file after every row
+    assert!(count(&names, "cdr_2025-01-01_writer", ".csv") >= 2, "{:?}", names);
+    assert!(bundle_rows(&env_dir, "") > 0);
+
+    // CLI flags beat the environment; --closed-user-group goes with the YAML's msisdn_file
+    let cli_dir = dir.path().join("cli");
+    let cli_args = ["--workers", "2", "--compression", "gzip", "--rotate-bytes", "100000000", "--closed-user-group"];
+    let names = generate_cdr(&db_path, &cli_dir, yaml, &envs, &cli_args);
+    assert_eq!(count(&names, "manifest_writer", ".json"), 1);
+    assert_eq!(count(&names, "cdr_2025-01-01.", ".csv.gz"), 1);
+    assert!(bundle_rows(&cli_dir, ".gz") > 0);
+}
+
+#[test]
+fn test_bad_env_override_is_reported() {
+    let dir = TempDir::new().unwrap();
+    let output = common::cli("error")
+        .arg("generate-subscribers")
+        .arg("--output")
+        .arg(dir.path().join("subs.redb"))
+        .args(["--size", "10"])
+        .env("CDRGEN_WORKERS", "lots")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("CDRGEN_WORKERS=lots: expected an unsigned integer"), "{}", stderr);
+}
@@ -0,0 +1,86 @@
+// Integration tests for contact_call_share and the contacts block: share of CALL/SMS counterparts among the subscribers
+use chrono::NaiveDate;
+use rs_cdr_generator::api::{DayGenerator, DayGeneratorBuilder};
+use rs_cdr_generator::config::{Config, ContactsConfig};
+#[cfg(feature = "redb")]
+use rs_cdr_generator::generators::DB_CONTACT_CALL_SHARE;
+use rs_cdr_generator::writer::EventRow;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+mod common;
+
+fn config(contact_call_share: Option<f64>) -> Config {
+    Config {
+        subscribers: 400,
+        workers: 2,
+        daily_inactive_rate: 0.0,
+        avg_data_sessions_per_user: 30.0,
+        contact_call_share,
+        ..Config::default()
+    }
+}
+
+fn generate(builder: DayGeneratorBuilder) -> Vec<EventRow> {
+    let rows: Arc<Mutex<Vec<EventRow>>> = Arc::default();
+    let sink_rows = rows.clone();
+    let mut generator = builder.sink(move |batch| sink_rows.lock().unwrap().extend(batch.events)).build().unwrap();
+    generator.generate(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap()).unwrap();
+    let rows = rows.lock().unwrap();
+    rows.clone()
+}
+
+/// Share of MO CALL/SMS rows whose counterpart is in `subscribers`
+fn on_net_share(rows: &[EventRow], subscribers: &HashSet<u64>) -> f64 {
+    let mo: Vec<&EventRow> = rows.iter().filter(|r| r.event_type != "DATA" && r.direction == "MO").collect();
+    assert!(mo.len() > 1000, "only {} MO rows", mo.len());
+    mo.iter().filter(|r| subscribers.contains(&r.msisdn_dst)).count() as f64 / mo.len() as f64
+}
+
+/// Every subscriber has DATA sessions, so their MSISDNs are the DATA sources
+fn served_msisdns(rows: &[EventRow]) -> HashSet<u64> {
+    rows.iter().filter(|r| r.event_type == "DATA").map(|r| r.msisdn_src).collect()
+}
+
+#[test]
+fn test_random_subscribers_follow_contact_call_share() {
+    // Unset: every counterpart is a contact, as before the share was configurable
+    for (share, expected) in [(Some(0.2), 0.2), (Some(0.8), 0.8), (None, 1.0)] {
+        let rows = generate(DayGenerator::builder().config(config(share)));
+        let subscribers = served_msisdns(&rows);
+        assert_eq!(subscribers.len(), 400);
+        let measured = on_net_share(&rows, &subscribers);
+        assert!((measured - expected).abs() < 0.05, "contact_call_share {:?}: measured {:.3}", share, measured);
+    }
+}
+
+#[test]
+fn test_contacts_max_zero_leaves_external_numbers_only() {
+    let cfg = Config {
+        contacts: ContactsConfig { max: Some(0), ..ContactsConfig::default() },
+        ..config(Some(1.0))
+    };
+    let rows = generate(DayGenerator::builder().config(cfg));
+    assert!(on_net_share(&rows, &served_msisdns(&rows)) < 0.01);
+}
+
+#[cfg(feature = "redb")]
+#[test]
+fn test_redb_subscribers_follow_contact_call_share() {
+    // The worker's index-derived MSISDNs, all in the database
+    let dir = tempfile::TempDir::new().unwrap();
+    let db_path = dir.path().join("subs.redb");
+    let prefix = 31612u64;
+    common::build_indexed_db(&db_path, prefix, 0..400);
+    let subscribers: HashSet<u64> = (0..400).map(|idx| prefix * 10_000_000 + idx).collect();
+
+    for (share, expected) in [(Some(0.2), 0.2), (Some(0.8), 0.8), (None, DB_CONTACT_CALL_SHARE)] {
+        let cfg = Config {
+            prefixes: vec![prefix.to_string()],
+            ..config(share)
+        };
+        let rows = generate(DayGenerator::builder().config(cfg).subscriber_db(db_path.as_path()));
+        let measured = on_net_share(&rows, &subscribers);
+        assert!((measured - expected).abs() < 0.05, "contact_call_share {:?}: measured {:.3}", share, measured);
+    }
+}
@@ -0,0 +1,184 @@
+// Integration test for the export-crm subcommand: a generated day joins the CRM extract on msisdn_src
+use chrono::{NaiveDate, TimeZone, Utc};
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::config::load_config;
+use rs_cdr_generator::identity::SubscriberIdentity;
+use rs_cdr_generator::subscriber_db::SubscriberSnapshot;
+use rs_cdr_generator::subscriber_db_redb::SubscriberDbRedb;
+use rs_cdr_generator::writer::EventRow;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+
+mod common;
+
+// Customers of `silent` never originate traffic, so a segment mix-up between CDRs and CRM shows up as rows
+const CONFIG: &str = "\
+prefixes: ['31612']
+tz_name: UTC
+workers: 2
+daily_inactive_rate: 0.0
+user_segments:
+  regular: { share: 0.7, plans: [basic, plus] }
+  silent: { share: 0.3, activity: 0.0, plans: [dormant] }
+";
+
+const PREFIX: u64 = 31612;
+const SUBSCRIBERS: u64 = 300;
+
+fn utc_ms(y: i32, m: u32, d: u32) -> i64 {
+    Utc.with_ymd_and_hms(y, m, d, 0, 0, 0).unwrap().timestamp_millis()
+}
+
+/// Database with the worker's index-derived MSISDNs. Every 3rd number changed device on 2024-03-01 (same customer),
+/// every 5th was taken over by a new IMSI on 2024-04-01 and every 7th was released on 2024-04-10
+fn build_db(path: &Path) {
+    let db = SubscriberDbRedb::new(path).unwrap();
+    let batch: Vec<(u64, Vec<SubscriberSnapshot>)> = (0..SUBSCRIBERS)
+        .map(|idx| {
+            let msisdn = PREFIX * 10_000_000 + idx;
+            let identity = |imsi: u64, imei: u64| SubscriberIdentity::from_numbers(msisdn, imsi, imei, 20408).unwrap();
+            let snapshot = |identity, valid_from, valid_to| SubscriberSnapshot { identity, valid_from, valid_to };
+            let imsi = 204080000000000 + idx;
+            let mut snapshots = vec![snapshot(identity(imsi, 350000000000000 + idx), utc_ms(2024, 1, 1), None)];
+            if idx % 3 == 0 {
+                snapshots[0].valid_to = Some(utc_ms(2024, 3, 1));
+                snapshots.push(snapshot(identity(imsi, 351000000000000 + idx), utc_ms(2024, 3, 1), None));
+            }
+            if idx % 5 == 0 {
+                snapshots.last_mut().unwrap().valid_to = Some(utc_ms(2024, 4, 1));
+                snapshots.push(snapshot(identity(imsi + 100_000, 352000000000000 + idx), utc_ms(2024, 4, 1), None));
+            }
+            if idx % 7 == 0 {
+                snapshots.last_mut().unwrap().valid_to = Some(utc_ms(2024, 4, 10));
+            }
+            (msisdn, snapshots)
+        })
+        .collect();
+    db.insert_snapshots_batch(&batch).unwrap();
+}
+
+/// CRM rows by MSISDN: (customer_id, activation_date, plan, segment)
+fn read_crm(path: &Path) -> HashMap<u64, (String, String, String, String)> {
+    let mut reader = csv::Reader::from_path(path).unwrap();
+    assert_eq!(
+        reader.headers().unwrap(),
+        vec!["msisdn", "customer_id", "activation_date", "plan", "segment"]
+    );
+    reader
+        .records()
+        .map(|record| {
+            let record = record.unwrap();
+            let field = |i: usize| record[i].to_string();
+            (record[0].parse().unwrap(), (field(1), field(2), field(3), field(4)))
+        })
+        .collect()
+}
+
+/// Run export-crm for 2024-04-15 with `config_path` and extra `args`, writing `crm_path`
+fn export_crm(db_path: &Path, config_path: &Path, crm_path: &Path, args: &[&str]) -> HashMap<u64, (String, String, String, String)> {
+    let status = common::cli("warn")
+        .arg("export-crm")
+        .arg("--subscriber-db")
+        .arg(db_path)
+        .args(["--at", "2024-04-15"])
+        .arg("--output")
+        .arg(crm_path)
+        .arg("--config")
+        .arg(config_path)
+        .args(args)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    read_crm(crm_path)
+}
+
+/// Generate 2024-04-15 with the config at `config_path`, returning its rows
+fn generate_day(db_path: &Path, config_path: &Path) -> Vec<EventRow> {
+    let rows: Arc<Mutex<Vec<EventRow>>> = Arc::default();
+    let sink_rows = rows.clone();
+    let mut generator = DayGenerator::builder()
+        .config(load_config(Some(config_path)).unwrap())
+        .subscriber_db(db_path)
+        .sink(move |batch| sink_rows.lock().unwrap().extend(batch.events))
+        .build()
+        .unwrap();
+    generator.generate(NaiveDate::from_ymd_opt(2024, 4, 15).unwrap()).unwrap();
+    let rows = rows.lock().unwrap().clone();
+    rows
+}
+
+#[test]
+fn test_generated_day_joins_crm_export() {
+    let dir = TempDir::new().unwrap();
+    let db_path = dir.path().join("subs.redb");
+    let config_path = dir.path().join("config.yaml");
+    std::fs::write(&config_path, CONFIG).unwrap();
+    let date = NaiveDate::from_ymd_opt(2024, 4, 15).unwrap();
+
+    build_db(&db_path);
+    let crm = export_crm(&db_path, &config_path, &dir.path().join("crm.csv"), &[]);
+
+    // One row per MSISDN with a subscriber on the day
+    let (day_start, day_end) = (
+        Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap()).timestamp_millis(),
+        Utc.from_utc_datetime(&date.succ_opt().unwrap().and_hms_opt(0, 0, 0).unwrap()).timestamp_millis(),
+    );
+    let mut active = HashSet::new();
+    SubscriberDbRedb::open(&db_path)
+        .unwrap()
+        .iter_all(|msisdn, snapshots| {
+            if SubscriberDbRedb::find_snapshot_in_day(&snapshots, day_start, day_end).is_some() {
+                active.insert(msisdn);
+            }
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(crm.keys().copied().collect::<HashSet<_>>(), active);
+    assert_eq!(active.len(), SUBSCRIBERS as usize - SUBSCRIBERS.div_ceil(7) as usize);
+
+    // A device change keeps the customer, a new IMSI on the number is a new customer activated that day
+    let row = |idx: u64| &crm[&(PREFIX * 10_000_000 + idx)];
+    assert_eq!(row(3).1, "2024-01-01");
+    assert_eq!(row(5).1, "2024-04-01");
+    assert_eq!(row(15).1, "2024-04-01");
+    assert_ne!(row(5).0, row(10).0);
+    assert!(crm.values().all(|(id, _, _, _)| id.starts_with('C')));
+
+    // Generate the day with the same config and seed
+    let rows = generate_day(&db_path, &config_path);
+    assert!(!rows.is_empty());
+
+    // Every in-database msisdn_src joins
+    let in_db: Vec<&EventRow> = rows.iter().filter(|row| active.contains(&row.msisdn_src)).collect();
+    assert!(!in_db.is_empty());
+    let unmatched = in_db.iter().filter(|row| !crm.contains_key(&row.msisdn_src)).count();
+    assert_eq!(unmatched, 0, "{} of {} in-database rows have no CRM row", unmatched, in_db.len());
+
+    // Rows served to the subscriber come from its segment's activity: none from `silent` customers
+    let served: Vec<&EventRow> = rows.iter().filter(|row| row.event_type == "DATA" || row.direction == "MO").collect();
+    assert!(served.iter().all(|row| crm[&row.msisdn_src].3 == "regular"));
+    let silent = crm.values().filter(|(_, _, plan, segment)| segment == "silent" && plan == "dormant").count();
+    assert!(silent > 0, "no silent customers among {}", crm.len());
+}
+
+#[test]
+fn test_crm_export_uses_the_config_seed() {
+    let dir = TempDir::new().unwrap();
+    let db_path = dir.path().join("subs.redb");
+    let config_path = dir.path().join("config.yaml");
+    std::fs::write(&config_path, format!("{}seed: 7\n", CONFIG)).unwrap();
+    build_db(&db_path);
+
+    // Without --seed the export takes the YAML seed, as generate-cdr does
+    let crm = export_crm(&db_path, &config_path, &dir.path().join("crm.csv"), &[]);
+    assert_eq!(crm, export_crm(&db_path, &config_path, &dir.path().join("crm_7.csv"), &["--seed", "7"]));
+    assert_ne!(crm, export_crm(&db_path, &config_path, &dir.path().join("crm_42.csv"), &["--seed", "42"]));
+
+    // The CDRs of the seed-7 run join on segment
+    let rows = generate_day(&db_path, &config_path);
+    let served: Vec<&EventRow> = rows.iter().filter(|row| row.event_type == "DATA" || row.direction == "MO").collect();
+    assert!(!served.is_empty());
+    assert!(served.iter().all(|row| crm.get(&row.msisdn_src).is_none_or(|customer| customer.3 == "regular")));
+}
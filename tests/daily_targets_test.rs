@@ -0,0 +1,101 @@
+// Integration tests for daily_targets: day totals of a small population land on the configured targets
+use chrono::NaiveDate;
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::config::{Config, DailyTargets, TimeWindow, VolumeTarget};
+use tempfile::TempDir;
+
+/// SMS only between 08:00 and 20:00, twice as many in the evening hours
+fn sms_hours() -> Vec<f64> {
+    (0..24).map(|hour| match hour {
+        8..=15 => 500.0,
+        16..=19 => 1000.0,
+        _ => 0.0,
+    })
+    .collect()
+}
+
+fn config() -> Config {
+    Config {
+        subscribers: 2000,
+        workers: 2,
+        compression_type: "none".to_string(),
+        daily_inactive_rate: 0.1,
+        callback_prob: 0.5,
+        // Calls are never dropped for an overlap, so every drawn call is written
+        allow_concurrent_calls: true,
+        daily_targets: DailyTargets {
+            calls: Some(VolumeTarget::Daily(30_000.0)),
+            sms: Some(VolumeTarget::Hourly(sms_hours())),
+            data_sessions: Some(VolumeTarget::Daily(5_000.0)),
+        },
+        ..Config::default()
+    }
+}
+
+/// `achieved` within 5 standard deviations of a Poisson count with mean `target`, plus 2% for the MT legs
+/// and callbacks the means average over
+fn assert_close(name: &str, achieved: usize, target: f64) {
+    let tolerance = 5.0 * target.sqrt() + 0.02 * target;
+    assert!(
+        (achieved as f64 - target).abs() <= tolerance,
+        "{}: {} generated, target {:.0} (tolerance {:.0})",
+        name,
+        achieved,
+        target,
+        tolerance
+    );
+}
+
+#[test]
+fn test_day_totals_follow_targets() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let summary = DayGenerator::builder()
+        .config(config())
+        .out_dir(temp_dir.path())
+        .build()?
+        .generate(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap())?;
+
+    assert_close("calls", summary.total_calls, 30_000.0);
+    assert_close("sms", summary.total_sms, 8_000.0);
+    assert_close("data", summary.total_data, 5_000.0);
+
+    // Hourly SMS targets replace the diurnal curve
+    assert!(summary.sms_by_hour[..8].iter().chain(&summary.sms_by_hour[20..]).all(|&n| n == 0), "{:?}", summary.sms_by_hour);
+    let (day, evening): (usize, usize) = (summary.sms_by_hour[8..16].iter().sum(), summary.sms_by_hour[16..20].iter().sum());
+    assert_close("evening sms", evening, 4_000.0);
+    assert_close("daytime sms", day, 4_000.0);
+
+    let report = &summary.daily_targets;
+    assert_eq!(report.len(), 3);
+    assert_eq!(report["calls"].achieved, summary.total_calls);
+    assert_eq!(report["sms"].target, 8_000.0);
+    let deviation = (summary.total_data as f64 - 5_000.0) / 5_000.0 * 100.0;
+    assert!((report["data_sessions"].deviation_pct - deviation).abs() < 1e-9);
+
+    // Written to summary.json too
+    let json = std::fs::read_to_string(temp_dir.path().join("2025-01-01").join("summary.json"))?;
+    assert!(json.contains("\"deviation_pct\""));
+    Ok(())
+}
+
+#[test]
+fn test_targets_follow_window_and_sample_stride() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let cfg = Config {
+        time_window: Some(TimeWindow::parse("16:00-20:00")?),
+        sample_stride: 2,
+        ..config()
+    };
+    let summary = DayGenerator::builder()
+        .config(cfg)
+        .out_dir(temp_dir.path())
+        .build()?
+        .generate(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap())?;
+
+    // The window holds half of the SMS target, and every second subscriber is generated
+    let sms = summary.daily_targets["sms"];
+    assert!((sms.target - 2_000.0).abs() < 1e-6, "{}", sms.target);
+    assert_close("windowed sms", summary.total_sms, sms.target);
+    assert_close("windowed calls", summary.total_calls, summary.daily_targets["calls"].target);
+    Ok(())
+}
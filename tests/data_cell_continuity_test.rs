@@ -0,0 +1,52 @@
+// Integration test for data_handover_prob: a subscriber's DATA sessions of a day stay on the serving cell
+use chrono::NaiveDate;
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::config::Config;
+use rs_cdr_generator::writer::EventRow;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+/// Median number of distinct DATA cells per subscriber with at least two sessions
+fn median_distinct_cells(data_handover_prob: f64) -> usize {
+    let cfg = Config {
+        subscribers: 2000,
+        workers: 2,
+        avg_data_sessions_per_user: 8.0,
+        data_handover_prob,
+        ..Config::default()
+    };
+    let rows: Arc<Mutex<Vec<EventRow>>> = Arc::default();
+    let sink_rows = rows.clone();
+    DayGenerator::builder()
+        .config(cfg)
+        .sink(move |batch| sink_rows.lock().unwrap().extend(batch.events))
+        .build()
+        .unwrap()
+        .generate(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap())
+        .unwrap();
+
+    let mut sessions: HashMap<u64, Vec<u32>> = HashMap::new();
+    for row in rows.lock().unwrap().iter().filter(|row| row.event_type == "DATA") {
+        sessions.entry(row.msisdn_src).or_default().push(row.cell_id);
+    }
+    let mut distinct: Vec<usize> = sessions
+        .values()
+        .filter(|cells| cells.len() >= 2)
+        .map(|cells| cells.iter().collect::<HashSet<_>>().len())
+        .collect();
+    assert!(distinct.len() > 500, "{} subscribers with several sessions", distinct.len());
+    distinct.sort_unstable();
+    distinct[distinct.len() / 2]
+}
+
+#[test]
+fn test_data_sessions_stay_on_serving_cell() {
+    // A handover every session: every session on its own cell
+    let uniform = median_distinct_cells(1.0);
+    let sticky = median_distinct_cells(0.2);
+    assert!(uniform >= 6, "{}", uniform);
+    assert!(sticky * 2 <= uniform, "median {} distinct cells vs {} without continuity", sticky, uniform);
+
+    // No handovers: one cell all day
+    assert_eq!(median_distinct_cells(0.0), 1);
+}
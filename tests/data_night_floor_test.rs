@@ -0,0 +1,103 @@
+// Integration tests for data_nighttime_floor: DATA keeps small keep-alive sessions overnight while voice sleeps
+use chrono::{NaiveDate, Timelike};
+use rs_cdr_generator::api::{DayGenerator, DaySummary};
+use rs_cdr_generator::config::{Config, TimeWindow};
+use rs_cdr_generator::generators::{placement_window, placement_window_floored};
+use rs_cdr_generator::reader::open_cdr_reader;
+use rs_cdr_generator::timezone_utils::tz_from_name;
+use std::path::Path;
+use tempfile::TempDir;
+
+const FLOOR: f64 = 0.5;
+
+fn date() -> NaiveDate {
+    // A Wednesday: the weekday curve has its minimum of 0.1 at 03:00
+    NaiveDate::from_ymd_opt(2025, 1, 8).unwrap()
+}
+
+fn generate(out_dir: &Path, data_nighttime_floor: f64) -> anyhow::Result<DaySummary> {
+    let cfg = Config {
+        subscribers: 3_000,
+        workers: 2,
+        compression_type: "none".to_string(),
+        data_nighttime_floor,
+        ..Config::default()
+    };
+    let mut generator = DayGenerator::builder().config(cfg).out_dir(out_dir).bundle(false).build()?;
+    generator.generate(date())?;
+    // Check what was written to disk, not just the returned value
+    let text = std::fs::read_to_string(out_dir.join("2025-01-08").join("summary.json"))?;
+    Ok(serde_json::from_str(&text)?)
+}
+
+fn share_at_3am(by_hour: &[usize; 24]) -> f64 {
+    by_hour[3] as f64 / by_hour.iter().sum::<usize>() as f64
+}
+
+#[test]
+fn test_data_share_at_3am_above_floor() -> anyhow::Result<()> {
+    let dir = TempDir::new()?;
+    let summary = generate(&dir.path().join("floor"), FLOOR)?;
+    let baseline = generate(&dir.path().join("curve"), 0.0)?;
+
+    // A flat day puts 1/24 of the sessions in each hour; the floor keeps 03:00 at least FLOOR of that
+    let data_share = share_at_3am(&summary.data_by_hour);
+    assert!(data_share > FLOOR / 24.0, "DATA share at 03:00 {:.4}", data_share);
+    // Voice still follows the curve, as does DATA without a floor
+    assert!(share_at_3am(&summary.calls_by_hour) < FLOOR / 24.0);
+    assert!(share_at_3am(&baseline.data_by_hour) < FLOOR / 24.0);
+
+    // The floor moves sessions into the night without changing the daily volume much
+    let ratio = summary.total_data as f64 / baseline.total_data as f64;
+    assert!((ratio - 1.0).abs() < 0.03, "total DATA ratio {:.3}", ratio);
+    Ok(())
+}
+
+#[test]
+fn test_night_sessions_are_small() -> anyhow::Result<()> {
+    let dir = TempDir::new()?;
+    generate(dir.path(), FLOOR)?;
+
+    // (sessions, bytes) at 03:00 and at noon, local time
+    let (mut night, mut noon) = ((0.0, 0.0), (0.0, 0.0));
+    for entry in std::fs::read_dir(dir.path().join("2025-01-08"))? {
+        let path = entry?.path();
+        if !path.file_name().unwrap().to_string_lossy().starts_with("cdr_") {
+            continue;
+        }
+        for row in open_cdr_reader(&path)? {
+            let row = row?;
+            if row.event_type != "DATA" {
+                continue;
+            }
+            let start = chrono::DateTime::from_timestamp_millis(row.start_ts_ms).unwrap()
+                + chrono::Duration::minutes(row.tz_offset_min as i64);
+            let bytes = (row.data_bytes_in.unwrap() + row.data_bytes_out.unwrap()) as f64;
+            match start.hour() {
+                3 => night = (night.0 + 1.0, night.1 + bytes),
+                12 => noon = (noon.0 + 1.0, noon.1 + bytes),
+                _ => {}
+            }
+        }
+    }
+    let (night_mean, noon_mean) = (night.1 / night.0, noon.1 / noon.0);
+    assert!(night_mean < noon_mean / 5.0, "night {:.0} B, noon {:.0} B", night_mean, noon_mean);
+    Ok(())
+}
+
+#[test]
+fn test_night_window_share_with_floor() -> anyhow::Result<()> {
+    let cfg = Config {
+        time_window: Some(TimeWindow::parse("03:00-04:00")?),
+        ..Config::default()
+    };
+    let tz = tz_from_name(&cfg.tz_name);
+    let (_, _, share) = placement_window(&cfg, tz, date())?;
+    let (_, _, data_share) = placement_window_floored(&cfg, tz, date(), FLOOR)?;
+    // DATA scales its mean by the floored curve, so a night window keeps its keep-alive sessions
+    assert!(data_share > 3.0 * share, "share {:.4}, DATA share {:.4}", share, data_share);
+    // A floor of 1 flattens the curve to an even split of the day
+    let (_, _, flat) = placement_window_floored(&cfg, tz, date(), 1.0)?;
+    assert!((flat - 1.0 / 24.0).abs() < 1e-9, "flat share {}", flat);
+    Ok(())
+}
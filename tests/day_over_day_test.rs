@@ -0,0 +1,110 @@
+// Integration tests for day_over_day in summary.json: active MSISDNs against the previous generated day
+use chrono::NaiveDate;
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::config::{Config, DayOverDayConfig};
+use rs_cdr_generator::day_over_day::DayOverDay;
+use rs_cdr_generator::utils::DailySummary;
+use rs_cdr_generator::writer::EventRow;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+
+fn config() -> Config {
+    Config {
+        subscribers: 600,
+        workers: 3,
+        // Plenty of day-to-day churn
+        daily_inactive_rate: 0.3,
+        compression_type: "none".to_string(),
+        ..Config::default()
+    }
+}
+
+/// MSISDNs that originated a row (MO CALL/SMS, DATA) on each of `days` consecutive days, with the summaries
+fn generate_days(cfg: Config, days: u32) -> (Vec<HashSet<u64>>, Vec<DailySummary>) {
+    let rows: Arc<Mutex<Vec<EventRow>>> = Arc::default();
+    let sink_rows = rows.clone();
+    let mut generator = DayGenerator::builder()
+        .config(cfg)
+        .sink(move |batch| sink_rows.lock().unwrap().extend(batch.events))
+        .build()
+        .unwrap();
+    let mut active = Vec::new();
+    let mut summaries = Vec::new();
+    for d in 0..days {
+        summaries.push(generator.generate(NaiveDate::from_ymd_opt(2025, 1, 1 + d).unwrap()).unwrap());
+        let day_rows = std::mem::take(&mut *rows.lock().unwrap());
+        active.push(day_rows.iter().filter(|r| r.direction == "MO").map(|r| r.msisdn_src).collect());
+    }
+    (active, summaries)
+}
+
+fn expected(previous: &HashSet<u64>, today: &HashSet<u64>) -> DayOverDay {
+    let repeat = today.intersection(previous).count();
+    DayOverDay {
+        repeat,
+        new: today.len() - repeat,
+        gone: previous.len() - repeat,
+        approximate: false,
+    }
+}
+
+#[test]
+fn test_exact_counts_match_rows() {
+    let (active, summaries) = generate_days(config(), 3);
+    assert_eq!(summaries[0].day_over_day, None);
+    for day in 1..3 {
+        let overlap = summaries[day].day_over_day.unwrap();
+        assert_eq!(overlap, expected(&active[day - 1], &active[day]), "day {}", day + 1);
+        // Inactivity churns a real share of the subscribers
+        assert!(overlap.new > 0 && overlap.gone > 0 && overlap.repeat > overlap.new, "{:?}", overlap);
+    }
+}
+
+#[test]
+fn test_bloom_counts_are_close() {
+    let cfg = Config {
+        day_over_day: DayOverDayConfig {
+            exact_max_subscribers: 100,
+            bloom_fpr: 0.001,
+            ..DayOverDayConfig::default()
+        },
+        ..config()
+    };
+    let (active, summaries) = generate_days(cfg, 2);
+    let overlap = summaries[1].day_over_day.unwrap();
+    let exact = expected(&active[0], &active[1]);
+    assert!(overlap.approximate);
+    for (measured, exact) in [(overlap.repeat, exact.repeat), (overlap.new, exact.new), (overlap.gone, exact.gone)] {
+        assert!(measured.abs_diff(exact) <= 3, "{:?} vs {:?}", overlap, exact);
+    }
+}
+
+#[test]
+fn test_summary_json_from_the_second_day() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let mut generator = DayGenerator::builder().config(config()).out_dir(temp_dir.path()).bundle(false).build()?;
+    for d in 1..=2 {
+        generator.generate(NaiveDate::from_ymd_opt(2025, 1, d).unwrap())?;
+    }
+    let summary = |day: &str| -> anyhow::Result<serde_json::Value> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(temp_dir.path().join(day).join("summary.json"))?)?)
+    };
+    assert!(summary("2025-01-01")?.get("day_over_day").is_none());
+    let overlap = &summary("2025-01-02")?["day_over_day"];
+    for key in ["repeat", "new", "gone"] {
+        assert!(overlap[key].as_u64().is_some(), "{}", overlap);
+    }
+
+    // Off: no overlap at all
+    let cfg = Config {
+        day_over_day: DayOverDayConfig {
+            enabled: false,
+            ..DayOverDayConfig::default()
+        },
+        ..config()
+    };
+    let (_, summaries) = generate_days(cfg, 2);
+    assert_eq!(summaries[1].day_over_day, None);
+    Ok(())
+}
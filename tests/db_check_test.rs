@@ -0,0 +1,50 @@
+// Integration test for check_db: the startup lookup of index-derived MSISDNs in a redb database
+#![cfg(feature = "redb")]
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::config::Config;
+use std::path::Path;
+use tempfile::TempDir;
+
+mod common;
+
+const SUBSCRIBERS: u64 = 200;
+
+/// Database of `SUBSCRIBERS` MSISDNs numbered as the workers derive them under `prefix`
+fn build_db(path: &Path, prefix: u64) {
+    common::build_indexed_db(path, prefix, 0..SUBSCRIBERS);
+}
+
+fn generator(db_path: &Path, prefix: &str, check_db: bool) -> DayGenerator {
+    let cfg = Config { prefixes: vec![prefix.to_string()], workers: 2, check_db, ..Config::default() };
+    DayGenerator::builder().config(cfg).subscriber_db(db_path).sink(|_| {}).build().unwrap()
+}
+
+#[test]
+fn test_matching_database_passes() {
+    let dir = TempDir::new().unwrap();
+    let db_path = dir.path().join("subs.redb");
+    build_db(&db_path, 31612);
+
+    let report = generator(&db_path, "31612", true).db_check().cloned().unwrap();
+    // 64 samples from each of the two 100-subscriber ranges
+    assert_eq!((report.sampled, report.hits), (128, 128));
+    assert!(report.misses.is_empty());
+
+    // check_db off: no lookups
+    assert!(generator(&db_path, "31612", false).db_check().is_none());
+}
+
+#[test]
+fn test_prefix_mismatch_shows_nearest_keys() {
+    let dir = TempDir::new().unwrap();
+    let db_path = dir.path().join("subs.redb");
+    build_db(&db_path, 31613);
+
+    // The run's prefix is not the database's: every lookup misses, the nearest key is the database's first MSISDN
+    let report = generator(&db_path, "31612", true).db_check().cloned().unwrap();
+    assert_eq!(report.hits, 0);
+    assert_eq!(report.hit_rate(), 0.0);
+    assert_eq!(report.misses.len(), 5);
+    assert_eq!(report.misses[0], (316120000000, Some(316130000000)));
+    assert!(report.to_string().contains("316120000000 missing, nearest key 316130000000"), "{}", report);
+}
@@ -0,0 +1,85 @@
+// Integration test for dirty_data: broken fields in the part files match the counts and the ground truth
+use chrono::NaiveDate;
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::config::{Config, DirtyDataConfig};
+use rs_cdr_generator::reader::{open_cdr_reader, ParsedEventRow};
+use std::path::Path;
+use tempfile::TempDir;
+
+fn generate(out_dir: &Path, dirty_data: DirtyDataConfig) -> anyhow::Result<(usize, Vec<ParsedEventRow>)> {
+    let cfg = Config {
+        subscribers: 500,
+        workers: 2,
+        compression_type: "gzip".to_string(),
+        dirty_data,
+        ..Config::default()
+    };
+    let mut generator = DayGenerator::builder().config(cfg).out_dir(out_dir).bundle(false).build()?;
+    let summary = generator.generate(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap())?;
+
+    let mut rows = Vec::new();
+    let mut paths: Vec<_> = std::fs::read_dir(out_dir.join("2025-01-01"))?.map(|e| e.map(|e| e.path())).collect::<Result<_, _>>()?;
+    paths.sort();
+    for path in paths.iter().filter(|p| p.file_name().unwrap().to_string_lossy().starts_with("cdr_")) {
+        for row in open_cdr_reader(path)? {
+            rows.push(row?);
+        }
+    }
+    Ok((summary.dirty_rows, rows))
+}
+
+fn ground_truth_lines(day_dir: &Path) -> anyhow::Result<Vec<serde_json::Value>> {
+    let mut lines = Vec::new();
+    for entry in std::fs::read_dir(day_dir)? {
+        let path = entry?.path();
+        if path.file_name().unwrap().to_string_lossy().starts_with("dirty_shard") {
+            for line in std::fs::read_to_string(&path)?.lines() {
+                lines.push(serde_json::from_str(line)?);
+            }
+        }
+    }
+    Ok(lines)
+}
+
+#[test]
+fn test_dirty_rows_counted_and_listed() -> anyhow::Result<()> {
+    let dir = TempDir::new()?;
+    let dirty_data = DirtyDataConfig {
+        missing_imei_rate: 0.02,
+        missing_cell_rate: 0.02,
+        truncated_msisdn_rate: 0.02,
+        invalid_duration_rate: 0.02,
+        ground_truth: true,
+    };
+    let (dirty_rows, rows) = generate(dir.path(), dirty_data)?;
+
+    let missing_imei = rows.iter().filter(|r| r.imei == 0).count();
+    let missing_cell = rows.iter().filter(|r| r.cell_id == 0).count();
+    let invalid_duration = rows.iter().filter(|r| r.end_ts_ms < r.start_ts_ms).count();
+    // Generated B-numbers have 12 digits (5-digit prefix + 7)
+    let truncated = rows.iter().filter(|r| r.msisdn_dst != 0 && r.msisdn_dst < 100_000_000_000).count();
+    for (name, count) in [("imei", missing_imei), ("cell", missing_cell), ("duration", invalid_duration), ("msisdn", truncated)] {
+        assert!(count > 0, "no rows with a broken {}", name);
+    }
+    assert!(dirty_rows >= missing_imei.max(missing_cell).max(invalid_duration));
+    assert!(dirty_rows <= missing_imei + missing_cell + invalid_duration + truncated, "{} {} {} {} {}", dirty_rows, missing_imei, missing_cell, invalid_duration, truncated);
+
+    let logged = ground_truth_lines(&dir.path().join("2025-01-01"))?;
+    assert_eq!(logged.len(), dirty_rows);
+    let logged_faults = |fault: &str| logged.iter().filter(|l| l["faults"].as_array().unwrap().contains(&fault.into())).count();
+    assert_eq!(logged_faults("missing_imei"), missing_imei);
+    assert_eq!(logged_faults("missing_cell"), missing_cell);
+    assert_eq!(logged_faults("invalid_duration"), invalid_duration);
+    assert_eq!(logged_faults("truncated_msisdn"), truncated);
+    Ok(())
+}
+
+#[test]
+fn test_zero_rates_leave_output_clean() -> anyhow::Result<()> {
+    let dir = TempDir::new()?;
+    let (dirty_rows, rows) = generate(dir.path(), DirtyDataConfig { ground_truth: true, ..DirtyDataConfig::default() })?;
+    assert_eq!(dirty_rows, 0);
+    assert!(rows.iter().all(|r| r.imei != 0 && r.cell_id != 0 && r.end_ts_ms >= r.start_ts_ms));
+    assert!(ground_truth_lines(&dir.path().join("2025-01-01"))?.is_empty());
+    Ok(())
+}
@@ -0,0 +1,108 @@
+// Integration tests for estimate_run: the estimate against what a small run actually writes
+use chrono::NaiveDate;
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::config::Config;
+use rs_cdr_generator::estimate::estimate_run;
+use tempfile::TempDir;
+
+fn config() -> Config {
+    Config {
+        subscribers: 2000,
+        workers: 2,
+        compression_type: "none".to_string(),
+        daily_inactive_rate: 0.1,
+        callback_prob: 0.5,
+        sms_unreachable_rate: 0.3,
+        // Calls are never dropped for an overlap, so every drawn call is written
+        allow_concurrent_calls: true,
+        ..Config::default()
+    }
+}
+
+/// `actual` within 5 standard deviations of a Poisson count with mean `expected`, plus 2% for the
+/// draws the estimate averages over (MT legs, callbacks and retry chains per event)
+fn assert_close(name: &str, actual: usize, expected: f64) {
+    let tolerance = 5.0 * expected.sqrt() + 0.02 * expected;
+    assert!(
+        (actual as f64 - expected).abs() <= tolerance,
+        "{}: {} generated, {:.0} estimated (tolerance {:.0})",
+        name,
+        actual,
+        expected,
+        tolerance
+    );
+}
+
+#[test]
+fn test_event_counts_match_a_small_run() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let cfg = config();
+    let estimate = estimate_run(&cfg, 2, cfg.subscribers);
+
+    let mut generator = DayGenerator::builder().config(cfg).out_dir(temp_dir.path()).build()?;
+    let (mut calls, mut sms, mut retries, mut data, mut bytes) = (0, 0, 0, 0, 0);
+    for day in 1..=2 {
+        let summary = generator.generate(NaiveDate::from_ymd_opt(2025, 1, day).unwrap())?;
+        calls += summary.total_calls;
+        sms += summary.total_sms;
+        retries += summary.sms_retry_rows;
+        data += summary.total_data;
+        let day_dir = temp_dir.path().join(format!("2025-01-0{}", day));
+        for entry in std::fs::read_dir(day_dir)? {
+            let path = entry?.path();
+            if path.file_name().unwrap().to_string_lossy().starts_with("cdr_") {
+                bytes += std::fs::metadata(&path)?.len();
+            }
+        }
+    }
+
+    assert_close("calls", calls, estimate.calls);
+    assert_close("sms", sms, estimate.sms);
+    assert_close("sms_retry_rows", retries, estimate.sms_retry_rows);
+    assert_close("data", data, estimate.data);
+    // Bytes come from a typical row per event type
+    let ratio = bytes as f64 / estimate.uncompressed_bytes as f64;
+    assert!((0.9..1.1).contains(&ratio), "{} bytes written, {} estimated", bytes, estimate.uncompressed_bytes);
+    Ok(())
+}
+
+#[test]
+fn test_estimate_scales_with_days_and_subscribers() {
+    let cfg = config();
+    let one = estimate_run(&cfg, 1, 1000);
+    let many = estimate_run(&cfg, 7, 10_000);
+    assert!((many.rows() / one.rows() - 70.0).abs() < 1e-6);
+    assert!(many.uncompressed_bytes > 60 * one.uncompressed_bytes);
+    assert!(many.part_files >= one.part_files);
+}
+
+/// Runs the CLI binary, which is only built with every optional feature
+#[cfg(all(feature = "arrow", feature = "redb", feature = "async", feature = "zstd"))]
+#[test]
+fn test_dry_run_prints_estimate_without_output() -> anyhow::Result<()> {
+    use rs_cdr_generator::estimate::RunEstimate;
+    use std::process::Command;
+
+    let temp_dir = TempDir::new()?;
+    let out_dir = temp_dir.path().join("out");
+    let config_path = temp_dir.path().join("config.yaml");
+    std::fs::write(&config_path, "subscribers: 5000\n")?;
+    let output = Command::new(env!("CARGO_BIN_EXE_rs_cdr_generator"))
+        .env("RUST_LOG", "warn")
+        .args(["generate-cdr", "--dry-run", "--days", "3", "--compression", "gzip", "--workers", "4"])
+        .arg("--subscriber-db")
+        .arg(temp_dir.path().join("missing.redb"))
+        .arg("--config")
+        .arg(&config_path)
+        .arg("--out")
+        .arg(&out_dir)
+        .output()?;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    // Without a database the estimate uses `subscribers`
+    let estimate: RunEstimate = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(estimate, estimate_run(&Config { workers: 4, compression_type: "gzip".to_string(), subscribers: 5000, ..Config::default() }, 3, 5000));
+    assert_eq!(estimate.compression, "gzip");
+    assert!(!out_dir.join("2025-01-01").exists());
+    Ok(())
+}
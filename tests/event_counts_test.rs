@@ -1,9 +1,10 @@
 // Integration test for validating event generation counts
 use chrono::TimeZone;
-use rs_cdr_generator::cells::{ensure_cells_catalog, load_cells_catalog};
+use rs_cdr_generator::cells::{default_rat_mix, ensure_cells_catalog, load_cells_catalog, CellsMeta, CELLS_SCHEME_VERSION};
 use rs_cdr_generator::config::{Config, parse_prefixes};
-use rs_cdr_generator::generators::worker_generate;
+use rs_cdr_generator::generators::generate_day_to_dir;
 use rs_cdr_generator::timezone_utils::tz_from_name;
+use rs_cdr_generator::utils::create_daily_summary;
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use tempfile::TempDir;
@@ -11,6 +12,7 @@ use tempfile::TempDir;
 #[derive(Debug)]
 struct EventCounts {
     total_calls: usize,
+    total_calls_mo: usize,
     total_sms: usize,
     total_data: usize,
     unique_src_msisdn_all: usize,
@@ -19,6 +21,26 @@ struct EventCounts {
     unique_src_msisdn_sms_mo: usize,
 }
 
+/// Cells catalog parameters around Amsterdam with the default RAT mix
+fn cells_meta(cells: usize, seed: u64) -> CellsMeta {
+    CellsMeta {
+        cells,
+        center_lat: 52.37,
+        center_lon: 4.895,
+        radius_km: 50.0,
+        seed,
+        rat_mix: default_rat_mix(),
+        scheme: CELLS_SCHEME_VERSION,
+    }
+}
+
+/// Local hour of an event from its `start_ts_ms` and `tz_offset_min` CSV fields
+fn local_hour(start_ts_ms: &str, tz_offset_min: &str) -> usize {
+    let start_ts_ms: i64 = start_ts_ms.parse().unwrap();
+    let tz_offset_min: i64 = tz_offset_min.parse().unwrap();
+    let local_secs = start_ts_ms / 1000 + tz_offset_min * 60;
+    (local_secs.rem_euclid(86_400) / 3600) as usize
+}
 
 #[test]
 fn test_event_generation_counts() -> anyhow::Result<()> {
@@ -32,24 +54,20 @@ fn test_event_generation_counts() -> anyhow::Result<()> {
     let out_dir = temp_dir.path().to_path_buf();
 
     // Setup configuration
-    let mut cfg = Config::default();
-    cfg.prefixes = parse_prefixes("31612,31613")?;
-    cfg.mccmnc_pool = vec!["20408".to_string(), "20416".to_string()];
-    cfg.avg_calls_per_user = 3.5;
-    cfg.avg_sms_per_user = 5.2;
-    cfg.avg_data_sessions_per_user = 12.0;
-    cfg.workers = num_workers;
-    cfg.rotate_bytes = 100_000_000; // 100MB - no rotation for this test
+    let cfg = Config {
+        prefixes: parse_prefixes("31612,31613")?,
+        mccmnc_pool: vec!["20408".to_string(), "20416".to_string()],
+        avg_calls_per_user: 3.5,
+        avg_sms_per_user: 5.2,
+        avg_data_sessions_per_user: 12.0,
+        workers: num_workers,
+        rotate_bytes: 100_000_000, // 100MB - no rotation for this test
+        compression_type: "none".to_string(), // Plain CSV so the test can read it back
+        ..Config::default()
+    };
 
     // Ensure cells catalog
-    let _cells_path = ensure_cells_catalog(
-        &out_dir,
-        2000,
-        52.37,
-        4.895,
-        50.0,
-        seed,
-    )?;
+    let _cells_path = ensure_cells_catalog(&out_dir, &cells_meta(2000, seed), false)?;
 
     let (_cells_all, _cells_by_rat) = load_cells_catalog(&_cells_path)?;
 
@@ -75,13 +93,13 @@ fn test_event_generation_counts() -> anyhow::Result<()> {
     }
 
     // Generate events for each shard
-    for (shard_id, &(lo, hi)) in ranges.iter().enumerate() {
-        worker_generate(day, shard_id, (lo, hi), &cfg, &out_dir, None)?;
-    }
+    let shard_stats = generate_day_to_dir(&cfg, day, &ranges, &out_dir)?;
+    assert_eq!(shard_stats.len(), num_workers);
 
     // Read and aggregate all CSV files
     let mut total_counts = EventCounts {
         total_calls: 0,
+        total_calls_mo: 0,
         total_sms: 0,
         total_data: 0,
         unique_src_msisdn_all: 0,
@@ -94,6 +112,9 @@ fn test_event_generation_counts() -> anyhow::Result<()> {
     let mut data_src_msisdn = HashSet::new();
     let mut call_mo_src_msisdn = HashSet::new();
     let mut sms_mo_src_msisdn = HashSet::new();
+    let mut calls_by_hour = [0usize; 24];
+    let mut sms_by_hour = [0usize; 24];
+    let mut data_by_hour = [0usize; 24];
 
     // Find all CSV files
     for entry in fs::read_dir(&day_dir)? {
@@ -115,24 +136,29 @@ fn test_event_generation_counts() -> anyhow::Result<()> {
                 let event_type = fields[0];
                 let src_msisdn = fields[1];
                 let direction = fields[3];
+                let hour = local_hour(fields[4], fields[7]);
 
                 all_src_msisdn.insert(src_msisdn.to_string());
 
                 match event_type {
                     "CALL" => {
                         total_counts.total_calls += 1;
+                        calls_by_hour[hour] += 1;
                         if direction == "MO" {
+                            total_counts.total_calls_mo += 1;
                             call_mo_src_msisdn.insert(src_msisdn.to_string());
                         }
                     }
                     "SMS" => {
                         total_counts.total_sms += 1;
+                        sms_by_hour[hour] += 1;
                         if direction == "MO" {
                             sms_mo_src_msisdn.insert(src_msisdn.to_string());
                         }
                     }
                     "DATA" => {
                         total_counts.total_data += 1;
+                        data_by_hour[hour] += 1;
                         data_src_msisdn.insert(src_msisdn.to_string());
                     }
                     _ => {}
@@ -141,6 +167,18 @@ fn test_event_generation_counts() -> anyhow::Result<()> {
         }
     }
 
+    // Returned shard stats must agree with what was written
+    assert_eq!(shard_stats.iter().map(|s| s.calls).sum::<usize>(), total_counts.total_calls);
+    assert_eq!(shard_stats.iter().map(|s| s.sms).sum::<usize>(), total_counts.total_sms);
+    assert_eq!(shard_stats.iter().map(|s| s.data).sum::<usize>(), total_counts.total_data);
+
+    // Aggregated hourly histograms must match the hours derived from the CSVs
+    let summary = create_daily_summary(&shard_stats, &day_dir, num_workers)?;
+    assert_eq!(summary.calls_by_hour, calls_by_hour);
+    assert_eq!(summary.sms_by_hour, sms_by_hour);
+    assert_eq!(summary.data_by_hour, data_by_hour);
+    assert_eq!(summary.calls_by_hour.iter().sum::<usize>(), total_counts.total_calls);
+
     total_counts.unique_src_msisdn_all = all_src_msisdn.len();
     total_counts.unique_src_msisdn_data = data_src_msisdn.len();
     total_counts.unique_src_msisdn_call_mo = call_mo_src_msisdn.len();
@@ -159,7 +197,7 @@ fn test_event_generation_counts() -> anyhow::Result<()> {
     println!("  SMS events: ~{}", (num_subs as f64 * cfg.avg_sms_per_user) as usize);
     println!("  DATA events: ~{}", (num_subs as f64 * cfg.avg_data_sessions_per_user) as usize);
     println!("\nActual results:");
-    println!("  CALL events: {}", total_counts.total_calls);
+    println!("  CALL events: {} ({} MO)", total_counts.total_calls, total_counts.total_calls_mo);
     println!("  SMS events: {}", total_counts.total_sms);
     println!("  DATA events: {}", total_counts.total_data);
     println!("\nUnique subscribers (src_msisdn):");
@@ -175,13 +213,14 @@ fn test_event_generation_counts() -> anyhow::Result<()> {
 
     let tolerance = 0.20; // 20% tolerance for Poisson distribution
 
-    // Check CALL events
+    // Check CALL events: every call attempt produces exactly one MO record,
+    // plus a correlated MT record when the callee is one of our subscribers
     let call_lower = (expected_calls as f64 * (1.0 - tolerance)) as usize;
     let call_upper = (expected_calls as f64 * (1.0 + tolerance)) as usize;
     assert!(
-        total_counts.total_calls >= call_lower && total_counts.total_calls <= call_upper,
-        "CALL events {} not in expected range [{}, {}]",
-        total_counts.total_calls,
+        total_counts.total_calls_mo >= call_lower && total_counts.total_calls_mo <= call_upper,
+        "MO CALL events {} not in expected range [{}, {}]",
+        total_counts.total_calls_mo,
         call_lower,
         call_upper
     );
@@ -245,14 +284,17 @@ fn test_no_duplicate_subscribers_across_shards() -> anyhow::Result<()> {
     let temp_dir = TempDir::new()?;
     let out_dir = temp_dir.path().to_path_buf();
 
-    let mut cfg = Config::default();
-    cfg.prefixes = parse_prefixes("31612")?;
-    cfg.mccmnc_pool = vec!["20408".to_string()];
-    cfg.avg_data_sessions_per_user = 5.0; // Lower to ensure most subs have events
-    cfg.workers = num_workers;
-    cfg.rotate_bytes = 100_000_000;
+    let cfg = Config {
+        prefixes: parse_prefixes("31612")?,
+        mccmnc_pool: vec!["20408".to_string()],
+        avg_data_sessions_per_user: 5.0, // Lower to ensure most subs have events
+        workers: num_workers,
+        rotate_bytes: 100_000_000,
+        compression_type: "none".to_string(),
+        ..Config::default()
+    };
 
-    let _cells_path = ensure_cells_catalog(&out_dir, 1000, 52.37, 4.895, 50.0, seed)?;
+    let _cells_path = ensure_cells_catalog(&out_dir, &cells_meta(1000, seed), false)?;
     let (_cells_all, _cells_by_rat) = load_cells_catalog(&_cells_path)?;
 
     let tz = tz_from_name(&cfg.tz_name);
@@ -274,9 +316,7 @@ fn test_no_duplicate_subscribers_across_shards() -> anyhow::Result<()> {
         s = e;
     }
 
-    for (shard_id, &(lo, hi)) in ranges.iter().enumerate() {
-        worker_generate(day, shard_id, (lo, hi), &cfg, &out_dir, None)?;
-    }
+    generate_day_to_dir(&cfg, day, &ranges, &out_dir)?;
 
     // Collect DATA event subscribers per shard
     let mut shard_subscribers: HashMap<usize, HashSet<String>> = HashMap::new();
@@ -286,11 +326,11 @@ fn test_no_duplicate_subscribers_across_shards() -> anyhow::Result<()> {
         let path = entry.path();
         let filename = path.file_name().unwrap().to_str().unwrap();
 
-        // Extract shard_id from filename
-        if filename.starts_with("cdr_") && filename.contains("_shard") {
+        // Extract the writer id from the filename
+        if filename.starts_with("cdr_") && filename.contains("_writer") {
             let parts: Vec<&str> = filename.split('_').collect();
             if parts.len() >= 4 {
-                let shard_str = parts[3].replace("shard", "");
+                let shard_str = parts[3].replace("writer", "");
                 if let Ok(shard_id) = shard_str.parse::<usize>() {
                     let content = fs::read_to_string(&path)?;
                     let mut subs = HashSet::new();
@@ -0,0 +1,57 @@
+// Integration test for generate-subscribers --also-export-events
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+fn generate_subscribers(db_path: &Path, export: &Path) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_rs_cdr_generator"))
+        .env("RUST_LOG", "info")
+        .arg("generate-subscribers")
+        .arg("--output")
+        .arg(db_path)
+        .args(["--size", "300", "--history-days", "90", "--seed", "11"])
+        .arg("--also-export-events")
+        .arg(export)
+        .output()
+        .unwrap()
+}
+
+/// Event count from the completion summary line
+fn summary_events(output: &std::process::Output) -> usize {
+    let log = String::from_utf8_lossy(&output.stderr);
+    let line = log.lines().find(|line| line.contains("Events: ")).unwrap_or_else(|| panic!("no summary in {}", log));
+    let count = line.split("Events: ").nth(1).unwrap().split(',').next().unwrap();
+    count.trim().parse().unwrap()
+}
+
+#[test]
+fn test_arrow_export_matches_csv_export_and_summary() {
+    let dir = TempDir::new().unwrap();
+
+    let arrow_path = dir.path().join("events.arrow");
+    let output = generate_subscribers(&dir.path().join("a.redb"), &arrow_path);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let summary = summary_events(&output);
+
+    let reader = arrow_ipc::reader::FileReader::try_new(std::fs::File::open(&arrow_path).unwrap(), None).unwrap();
+    let arrow_rows: usize = reader.map(|batch| batch.unwrap().num_rows()).sum();
+    assert_eq!(arrow_rows, summary);
+
+    // Same seed exported as CSV: one line per event after the header
+    let csv_path = dir.path().join("events.csv");
+    let output = generate_subscribers(&dir.path().join("b.redb"), &csv_path);
+    assert!(output.status.success());
+    let csv_rows = std::fs::read_to_string(&csv_path).unwrap().lines().count() - 1;
+    assert_eq!(csv_rows, arrow_rows);
+    assert!(dir.path().join("b.redb").exists());
+}
+
+#[test]
+fn test_unknown_export_extension_fails_before_generation() {
+    let dir = TempDir::new().unwrap();
+    let db_path = dir.path().join("subs.redb");
+    let output = generate_subscribers(&db_path, &dir.path().join("events.parquet"));
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains(".arrow or .csv"));
+    assert!(!db_path.exists());
+}
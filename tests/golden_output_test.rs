@@ -0,0 +1,135 @@
+// Golden-file tests: the output format and the day bundle of a fixed seed must stay byte for byte the same.
+// After an intended output change, regenerate the files with `UPDATE_GOLDEN=1 cargo test --test golden_output_test`
+use chrono::NaiveDate;
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::config::{Config, DirtyDataConfig};
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+mod common;
+
+/// One worker and one writer task, so rows reach the bundle in generation order; every optional column on
+fn config() -> Config {
+    Config {
+        subscribers: 25,
+        workers: 1,
+        compression_type: "none".to_string(),
+        callback_prob: 0.5,
+        sms_unreachable_rate: 0.2,
+        emit_record_seq: true,
+        emit_prev_event_ts: true,
+        emit_imeisv: true,
+        dirty_data: DirtyDataConfig {
+            missing_imei_rate: 0.02,
+            truncated_msisdn_rate: 0.02,
+            ..DirtyDataConfig::default()
+        },
+        ..Config::default()
+    }
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(name)
+}
+
+/// The day bundle in `out_dir`
+fn read_bundle(out_dir: &Path) -> Vec<u8> {
+    std::fs::read(out_dir.join("cdr_2025-01-01.csv")).unwrap()
+}
+
+/// Compare `bundle` with `tests/golden/<name>`, or rewrite the file with `UPDATE_GOLDEN`
+fn check_golden(bundle: &[u8], name: &str) {
+    let path = golden_path(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, bundle).unwrap();
+        return;
+    }
+    let golden = std::fs::read(&path).unwrap();
+    if bundle != golden {
+        let (bundle, golden) = (String::from_utf8_lossy(bundle), String::from_utf8_lossy(&golden));
+        let line = bundle.lines().zip(golden.lines()).position(|(a, b)| a != b);
+        panic!(
+            "{} differs from the golden file ({} vs {} lines), first at line {:?}",
+            name,
+            bundle.lines().count(),
+            golden.lines().count(),
+            line.map(|idx| idx + 1)
+        );
+    }
+}
+
+#[test]
+fn test_random_subscribers_match_golden_output() {
+    let temp_dir = TempDir::new().unwrap();
+    DayGenerator::builder()
+        .config(config())
+        .seed(42)
+        .out_dir(temp_dir.path())
+        .bundle(false)
+        .build()
+        .unwrap()
+        .generate(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap())
+        .unwrap();
+    check_golden(&read_bundle(temp_dir.path()), "random_seed42.csv");
+}
+
+/// The output spec downstream consumers parse: column order, empty-value conventions and delimiter of the
+/// default configuration. Rows are sorted, so only the format and the generated values are pinned
+#[test]
+fn test_default_output_format_matches_golden() {
+    let temp_dir = TempDir::new().unwrap();
+    let cfg = Config {
+        subscribers: 100,
+        workers: 1,
+        compression_type: "none".to_string(),
+        ..Config::default()
+    };
+    DayGenerator::builder()
+        .config(cfg)
+        .seed(1)
+        .out_dir(temp_dir.path())
+        .bundle(false)
+        .build()
+        .unwrap()
+        .generate(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap())
+        .unwrap();
+
+    let bundle = String::from_utf8(read_bundle(temp_dir.path())).unwrap();
+    let mut lines = bundle.lines();
+    let header = lines.next().unwrap();
+    let mut rows: Vec<&str> = lines.collect();
+    rows.sort_unstable();
+    let mut sorted = format!("{}\n", header);
+    for row in rows {
+        sorted.push_str(row);
+        sorted.push('\n');
+    }
+    check_golden(sorted.as_bytes(), "format_seed1.csv");
+}
+
+#[cfg(feature = "redb")]
+#[test]
+fn test_redb_subscribers_match_golden_output() {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().join("subs.redb");
+    // The worker's index-derived MSISDNs, all in the database
+    common::build_indexed_db(&db_path, 31612, 0..25);
+
+    let out_dir = temp_dir.path().join("out");
+    let cfg = Config {
+        prefixes: vec!["31612".to_string()],
+        ..config()
+    };
+    DayGenerator::builder()
+        .config(cfg)
+        .seed(42)
+        .subscriber_db(db_path)
+        .out_dir(&out_dir)
+        .bundle(false)
+        .build()
+        .unwrap()
+        .generate(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap())
+        .unwrap();
+    check_golden(&read_bundle(&out_dir), "redb_seed42.csv");
+}
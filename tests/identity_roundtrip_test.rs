@@ -0,0 +1,187 @@
+// Integration test for subscriber identities: leading-zero IMEIs and their IMEISVs survive CSV -> Arrow -> redb -> CDR,
+// and CDRs generated straight from the Arrow export
+// Goes through an Arrow export and a redb database
+#![cfg(all(feature = "arrow", feature = "redb"))]
+use chrono::NaiveDate;
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::config::Config;
+use rs_cdr_generator::identity::Mccmnc;
+use rs_cdr_generator::subscriber_db_generator::{generate_database, write_database_redb, EventExportFormat, GeneratorConfig};
+use rs_cdr_generator::subscriber_db_redb::{DbWriteOptions, SubscriberDbRedb};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+const SUBSCRIBERS: u64 = 40;
+
+/// Subscriber history CSV with MSISDNs on the worker index scheme (prefix 31612) and IMEIs starting with 0.
+/// Every other subscriber gets a software update before the CDR day. Returns MSISDN -> (IMEI, IMEISV on the day)
+fn write_subscriber_csv(path: &Path) -> HashMap<String, (String, String)> {
+    let mut devices = HashMap::new();
+    let mut csv = String::from("timestamp_ms,event_type,imsi,msisdn,imei,mccmnc,sv\n");
+    let mut updates = String::new();
+    for idx in 0..SUBSCRIBERS {
+        let msisdn = format!("31612{:07}", idx);
+        let imei = format!("0{:014}", 12_345_678_000_000 + idx);
+        let imsi = format!("2040800000{:05}", idx);
+        let mut sv = idx % 10;
+        csv.push_str(&format!("1704067200000,NEW_SUBSCRIBER,{},{},{},20408,{:02}\n", imsi, msisdn, imei, sv));
+        if idx % 2 == 0 {
+            sv += 1;
+            updates.push_str(&format!("1710000000000,SOFTWARE_UPDATE,{},{},{},20408,{:02}\n", imsi, msisdn, imei, sv));
+        }
+        let imeisv = format!("{}{:02}", &imei[..14], sv);
+        devices.insert(msisdn, (imei, imeisv));
+    }
+    csv.push_str(&updates);
+    std::fs::write(path, csv).unwrap();
+    devices
+}
+
+/// MO CALL/SMS and DATA rows: (msisdn_src, event_type, imei, imeisv) as written
+fn cdr_identities(day_dir: &Path) -> Vec<(String, String, String, String)> {
+    let mut identities = Vec::new();
+    for entry in std::fs::read_dir(day_dir).unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        if !(name.starts_with("cdr_") && name.ends_with(".csv")) {
+            continue;
+        }
+        let mut reader = csv::ReaderBuilder::new().delimiter(b';').from_path(&path).unwrap();
+        let headers = reader.headers().unwrap().clone();
+        let column = |name: &str| headers.iter().position(|h| h == name).unwrap();
+        let (direction, src, event_type) = (column("direction"), column("msisdn_src"), column("event_type"));
+        let (imei, imeisv) = (column("imei"), column("imeisv"));
+        for record in reader.records() {
+            let record = record.unwrap();
+            if &record[direction] != "MT" {
+                identities.push((
+                    record[src].to_string(),
+                    record[event_type].to_string(),
+                    record[imei].to_string(),
+                    record[imeisv].to_string(),
+                ));
+            }
+        }
+    }
+    identities
+}
+
+fn generate(out_dir: &Path, subscriber_db: PathBuf) -> PathBuf {
+    let cfg = Config {
+        workers: 2,
+        subscribers: SUBSCRIBERS as usize,
+        prefixes: vec!["31612".to_string()],
+        compression_type: "none".to_string(),
+        emit_imeisv: true,
+        ..Config::default()
+    };
+    let mut generator = DayGenerator::builder()
+        .config(cfg)
+        .subscriber_db(subscriber_db)
+        .out_dir(out_dir)
+        .bundle(false)
+        .build()
+        .unwrap();
+    generator.generate(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap()).unwrap();
+    out_dir.join("2025-01-01")
+}
+
+#[test]
+fn test_leading_zero_imei_survives_csv_arrow_redb_cdr() {
+    let dir = TempDir::new().unwrap();
+    let csv_path = dir.path().join("subscribers.csv");
+    let devices = write_subscriber_csv(&csv_path);
+
+    // CSV -> Arrow -> redb
+    let events = EventExportFormat::Csv.read(&csv_path).unwrap();
+    let arrow_path = dir.path().join("subscribers.arrow");
+    EventExportFormat::Arrow.write(&events, &arrow_path).unwrap();
+    let from_arrow = EventExportFormat::Arrow.read(&arrow_path).unwrap();
+    assert_eq!(from_arrow, events);
+    let redb_path = dir.path().join("subscribers.redb");
+    let stats = write_database_redb(from_arrow, &redb_path, &DbWriteOptions::default()).unwrap();
+    assert_eq!(stats.total_msisdns, SUBSCRIBERS);
+
+    // Every database kind feeds the same identities into the CDRs
+    let mut by_kind = HashMap::new();
+    for (label, db_path) in [("redb", redb_path), ("csv", csv_path), ("arrow", arrow_path)] {
+        let identities = cdr_identities(&generate(&dir.path().join(label), db_path));
+        by_kind.insert(label, identities.clone());
+        assert!(identities.len() > 100, "{}: {} rows", label, identities.len());
+        for (msisdn, event_type, imei, imeisv) in identities {
+            assert_eq!(imei.len(), 15, "{}: IMEI {:?}", label, imei);
+            let (expected_imei, expected_imeisv) = &devices[&msisdn];
+            assert_eq!(&imei, expected_imei, "{}: MSISDN {}", label, msisdn);
+            // Only DATA records carry the IMEISV; voice and SMS records keep the plain IMEI
+            if event_type == "DATA" {
+                assert_eq!(&imeisv, expected_imeisv, "{}: MSISDN {}", label, msisdn);
+            } else {
+                assert_eq!(imeisv, "", "{}: {} row of MSISDN {}", label, event_type, msisdn);
+            }
+        }
+    }
+    // The Arrow export, read per worker range, gives the workers the same histories as the CSV
+    let sorted = |label| {
+        let mut identities: Vec<_> = by_kind[label].clone();
+        identities.sort();
+        identities
+    };
+    assert_eq!(sorted("arrow"), sorted("csv"));
+}
+
+#[test]
+fn test_six_digit_mccmnc_survives_csv_arrow_redb() {
+    let dir = TempDir::new().unwrap();
+    // A 3-digit MNC leaves a 9-digit MSIN, so every IMSI stays 15 digits
+    let config = GeneratorConfig {
+        initial_subscribers: 300,
+        history_days: 60,
+        seed: 3,
+        mccmnc_pool: vec!["310012".to_string(), "20408".to_string()],
+        ..GeneratorConfig::default()
+    };
+    let events = generate_database(&config).unwrap();
+    let six_digit = Mccmnc::new(310_012).unwrap();
+    assert!(events.iter().any(|e| e.mccmnc == six_digit));
+    for event in &events {
+        assert_eq!(event.imsi.to_string().len(), 15, "{:?}", event);
+        assert!(event.imsi.has_mccmnc(event.mccmnc), "{:?}", event);
+    }
+
+    for format in [EventExportFormat::Csv, EventExportFormat::Arrow] {
+        let path = dir.path().join(format!("events.{:?}", format).to_lowercase());
+        format.write(&events, &path).unwrap();
+        assert_eq!(format.read(&path).unwrap(), events, "{:?}", format);
+    }
+
+    let redb_path = dir.path().join("subscribers.redb");
+    write_database_redb(events.clone(), &redb_path, &DbWriteOptions::default()).unwrap();
+    let imsis: HashSet<_> = events.iter().map(|e| e.imsi).collect();
+    let mut six_digit_snapshots = 0;
+    SubscriberDbRedb::open(&redb_path)
+        .unwrap()
+        .iter_all(|_, snapshots| {
+            for snapshot in snapshots {
+                let identity = snapshot.identity;
+                assert!(imsis.contains(&identity.imsi) && identity.imsi.has_mccmnc(identity.mccmnc), "{:?}", identity);
+                six_digit_snapshots += (identity.mccmnc == six_digit) as usize;
+            }
+            Ok(())
+        })
+        .unwrap();
+    assert!(six_digit_snapshots > 0);
+}
+
+#[test]
+fn test_malformed_subscriber_csv_is_an_error() {
+    let dir = TempDir::new().unwrap();
+    let csv_path = dir.path().join("subscribers.csv");
+    std::fs::write(
+        &csv_path,
+        "timestamp_ms,event_type,imsi,msisdn,imei,mccmnc\n1704067200000,NEW_SUBSCRIBER,204080000000001,31612000000,12345678901234X,20408\n",
+    )
+    .unwrap();
+    let err = EventExportFormat::Csv.read(&csv_path).unwrap_err();
+    assert!(format!("{:#}", err).contains("Invalid IMEI"), "{:#}", err);
+}
@@ -0,0 +1,79 @@
+// Integration test for non-DB runs: subscriber identities carry over between days and IMEIs change only on change days
+use chrono::NaiveDate;
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::config::Config;
+use rs_cdr_generator::reader::open_cdr_reader;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use tempfile::TempDir;
+
+const DAYS: u32 = 5;
+
+/// IMEIs seen per MSISDN on MO CALL/SMS and DATA rows of one day
+fn imeis_by_msisdn(day_dir: &Path) -> anyhow::Result<HashMap<u64, Vec<u64>>> {
+    let mut imeis: HashMap<u64, Vec<u64>> = HashMap::new();
+    for entry in std::fs::read_dir(day_dir)? {
+        let path = entry?.path();
+        if !path.file_name().unwrap().to_string_lossy().starts_with("cdr_") {
+            continue;
+        }
+        for row in open_cdr_reader(&path)? {
+            let row = row?;
+            if row.direction != "MT" {
+                let seen = imeis.entry(row.msisdn_src).or_default();
+                if !seen.contains(&row.imei) {
+                    seen.push(row.imei);
+                }
+            }
+        }
+    }
+    Ok(imeis)
+}
+
+#[test]
+fn test_imei_changes_persist_across_a_five_day_run() -> anyhow::Result<()> {
+    let dir = TempDir::new()?;
+    let cfg = Config {
+        subscribers: 1000,
+        workers: 2,
+        compression_type: "none".to_string(),
+        imei_daily_change_prob: 0.1,
+        daily_inactive_rate: 0.0,
+        ..Config::default()
+    };
+    let mut generator = DayGenerator::builder().config(cfg).out_dir(dir.path()).bundle(false).build()?;
+
+    // MSISDN -> IMEI per day
+    let mut history: BTreeMap<u64, Vec<u64>> = BTreeMap::new();
+    for d in 0..DAYS {
+        let date = NaiveDate::from_ymd_opt(2025, 3, 10 + d).unwrap();
+        generator.generate(date)?;
+        let day = imeis_by_msisdn(&dir.path().join(date.format("%Y-%m-%d").to_string()))?;
+        assert!(day.len() > 950, "day {}: {} subscribers", d, day.len());
+        for (msisdn, imeis) in day {
+            assert_eq!(imeis.len(), 1, "MSISDN {} has IMEIs {:?} on day {}", msisdn, imeis, d);
+            history.entry(msisdn).or_default().push(imeis[0]);
+        }
+    }
+
+    // The same subscribers every day
+    let complete = history.values().filter(|imeis| imeis.len() == DAYS as usize).count();
+    assert!(complete as f64 > history.len() as f64 * 0.95, "{} of {} seen every day", complete, history.len());
+
+    // A changed IMEI stays: the days before a change share one IMEI and it never comes back
+    let mut changed = 0;
+    for imeis in history.values().filter(|imeis| imeis.len() == DAYS as usize) {
+        let switches = imeis.windows(2).filter(|pair| pair[0] != pair[1]).count();
+        let mut distinct = imeis.clone();
+        distinct.dedup();
+        let mut unique = distinct.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), distinct.len(), "IMEI came back: {:?}", imeis);
+        changed += (switches > 0) as usize;
+    }
+    // 1 - 0.9^4 of the subscribers change at least once within the run
+    let share = changed as f64 / complete as f64;
+    assert!((0.25..0.45).contains(&share), "{} of {} changed", changed, complete);
+    Ok(())
+}
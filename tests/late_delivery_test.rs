@@ -0,0 +1,89 @@
+// Integration test for late file staging and the deliver-late subcommand
+use chrono::TimeZone;
+use rs_cdr_generator::cells::{default_rat_mix, ensure_cells_catalog, CellsMeta, CELLS_SCHEME_VERSION};
+use rs_cdr_generator::config::{parse_prefixes, Config};
+use rs_cdr_generator::generators::generate_day_to_dir;
+use rs_cdr_generator::late_delivery::{stage_late_files, LATE_DIR};
+use rs_cdr_generator::timezone_utils::tz_from_name;
+use rs_cdr_generator::writer::ShardManifest;
+use std::path::Path;
+use tempfile::TempDir;
+
+mod common;
+
+/// All files listed in the day's manifests, sorted
+fn manifest_files(day_dir: &Path) -> Vec<String> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(day_dir).unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        if name.starts_with("manifest_writer") {
+            let manifest: ShardManifest = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+            files.extend(manifest.files.into_iter().map(|entry| entry.file));
+        }
+    }
+    files.sort();
+    files
+}
+
+#[test]
+fn test_deliver_late_cli() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let out_dir = temp_dir.path().to_path_buf();
+
+    let cfg = Config {
+        prefixes: parse_prefixes("31612")?,
+        mccmnc_pool: vec!["20408".to_string()],
+        workers: 4,
+        rotate_bytes: 20_000, // Several parts per shard
+        compression_type: "none".to_string(),
+        ..Config::default()
+    };
+    let meta = CellsMeta {
+        cells: 200,
+        center_lat: 52.37,
+        center_lon: 4.895,
+        radius_km: 50.0,
+        seed: 42,
+        rat_mix: default_rat_mix(),
+        scheme: CELLS_SCHEME_VERSION,
+    };
+    ensure_cells_catalog(&out_dir, &meta, false)?;
+
+    let tz = tz_from_name(&cfg.tz_name);
+    let day = tz.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+    let day_dir = out_dir.join("2025-01-01");
+    generate_day_to_dir(&cfg, day, &[(0, 50), (50, 100), (100, 150), (150, 200)], &out_dir)?;
+    let all_files = manifest_files(&day_dir);
+
+    // The day ends at 2025-01-01T23:00:00Z in Europe/Amsterdam; files are due 12 hours later
+    let ready_at_ms = (day + chrono::Duration::days(1)).timestamp_millis();
+    let staged = stage_late_files(&out_dir, "2025-01-01", cfg.partition_layout, 0.5, 12, ready_at_ms, 7)?;
+    assert!(!staged.is_empty());
+    assert_eq!(manifest_files(&day_dir).len() + staged.len(), all_files.len());
+
+    let status = common::cli("warn")
+        .arg("deliver-late")
+        .arg("--out")
+        .arg(&out_dir)
+        .args(["--as-of", "2025-01-02T10:00:00Z"])
+        .status()?;
+    assert!(status.success());
+    assert!(out_dir.join(LATE_DIR).join("2025-01-01").exists());
+    assert_eq!(manifest_files(&day_dir).len() + staged.len(), all_files.len());
+
+    let status = common::cli("warn")
+        .arg("deliver-late")
+        .arg("--out")
+        .arg(&out_dir)
+        .args(["--as-of", "2025-01-02 11:00:00"])
+        .status()?;
+    assert!(status.success());
+    assert!(!out_dir.join(LATE_DIR).join("2025-01-01").exists());
+    assert_eq!(manifest_files(&day_dir), all_files);
+    for file in &all_files {
+        assert!(day_dir.join(file).exists(), "missing {}", file);
+    }
+
+    Ok(())
+}
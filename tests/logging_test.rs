@@ -0,0 +1,55 @@
+// Integration test for log level filtering of the CLI binary
+use std::process::Command;
+use tempfile::TempDir;
+
+fn run_generate_subscribers(dir: &TempDir, extra_env: &[(&str, &str)], extra_args: &[&str]) -> std::process::Output {
+    let db_path = dir.path().join("subs.redb");
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_rs_cdr_generator"));
+    cmd.env_remove("RUST_LOG")
+        .args(extra_args)
+        .arg("generate-subscribers")
+        .arg("--output")
+        .arg(&db_path)
+        .args(["--size", "50", "--history-days", "5"]);
+    for (k, v) in extra_env {
+        cmd.env(k, v);
+    }
+    cmd.output().expect("failed to run rs_cdr_generator")
+}
+
+#[test]
+fn test_rust_log_warn_suppresses_info() {
+    let dir = TempDir::new().unwrap();
+    let output = run_generate_subscribers(&dir, &[("RUST_LOG", "warn")], &[]);
+
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty(), "unexpected stdout: {}", String::from_utf8_lossy(&output.stdout));
+    assert!(output.stderr.is_empty(), "unexpected stderr: {}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn test_default_logs_info_to_stderr() {
+    let dir = TempDir::new().unwrap();
+    let output = run_generate_subscribers(&dir, &[], &[]);
+
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("INFO"), "expected info logs, got: {}", stderr);
+}
+
+#[test]
+fn test_log_json_emits_json_lines() {
+    let dir = TempDir::new().unwrap();
+    let output = run_generate_subscribers(&dir, &[], &["--log-level", "info", "--log-json"]);
+
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let mut lines = 0;
+    for line in stderr.lines() {
+        let v: serde_json::Value = serde_json::from_str(line).expect("log line is not JSON");
+        assert!(v.get("level").is_some());
+        lines += 1;
+    }
+    assert!(lines > 0);
+}
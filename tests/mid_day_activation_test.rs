@@ -0,0 +1,84 @@
+// Integration test for subscribers activated during a generated day: no events before activation
+// Reads subscribers from a redb database
+#![cfg(feature = "redb")]
+use chrono::{NaiveDate, TimeZone};
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::config::Config;
+use rs_cdr_generator::subscriber_db_redb::SubscriberDbRedb;
+use rs_cdr_generator::writer::EventRow;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+
+mod common;
+
+const PREFIX: u64 = 31612;
+const SUBSCRIBERS: u64 = 200;
+// Subscriber index whose SIM is activated at 15:00 on 2025-03-10
+const LATE: u64 = 7;
+
+/// Database with the worker's index-derived MSISDNs, all active from the epoch except `LATE`
+fn build_db(path: &Path, activation_ms: i64) {
+    let db = SubscriberDbRedb::new(path).unwrap();
+    let batch: Vec<_> = (0..SUBSCRIBERS)
+        .map(|idx| {
+            let (msisdn, mut snapshots) = common::indexed_subscriber(PREFIX, idx);
+            if idx == LATE {
+                snapshots[0].valid_from = activation_ms;
+            }
+            (msisdn, snapshots)
+        })
+        .collect();
+    db.insert_snapshots_batch(&batch).unwrap();
+}
+
+/// Start times of all rows served to subscriber `idx` (its IMSI: MO, MT, SMS or DATA)
+fn served_starts(db_path: &Path, date: NaiveDate, idx: u64) -> (usize, usize, Vec<i64>) {
+    let rows: Arc<Mutex<Vec<EventRow>>> = Arc::default();
+    let sink_rows = rows.clone();
+    let cfg = Config {
+        prefixes: vec![PREFIX.to_string()],
+        tz_name: "Europe/Amsterdam".to_string(),
+        workers: 2,
+        avg_calls_per_user: 20.0,
+        avg_sms_per_user: 10.0,
+        avg_data_sessions_per_user: 10.0,
+        daily_inactive_rate: 0.0,
+        ..Config::default()
+    };
+    let mut generator = DayGenerator::builder()
+        .config(cfg)
+        .subscriber_db(db_path)
+        .sink(move |batch| sink_rows.lock().unwrap().extend(batch.events))
+        .build()
+        .unwrap();
+    let summary = generator.generate(date).unwrap();
+
+    let rows = rows.lock().unwrap();
+    let imsi = 204080000000000 + idx;
+    let starts = rows.iter().filter(|r| r.imsi == imsi).map(|r| r.start_ts_ms).collect();
+    (summary.active_subscribers, summary.skipped_subscribers, starts)
+}
+
+#[test]
+fn test_no_events_before_mid_day_activation() {
+    let dir = TempDir::new().unwrap();
+    let db_path = dir.path().join("subs.redb");
+    let activation = chrono_tz::Europe::Amsterdam.with_ymd_and_hms(2025, 3, 10, 15, 0, 0).unwrap().timestamp_millis();
+    build_db(&db_path, activation);
+
+    // The day before activation the subscriber does not exist yet
+    let (active, skipped, starts) = served_starts(&db_path, NaiveDate::from_ymd_opt(2025, 3, 9).unwrap(), LATE);
+    assert_eq!((active, skipped), (SUBSCRIBERS as usize - 1, 1));
+    assert!(starts.is_empty(), "{} rows before activation day", starts.len());
+
+    // On the activation day it is found through its mid-day snapshot and only active from 15:00
+    let (active, skipped, starts) = served_starts(&db_path, NaiveDate::from_ymd_opt(2025, 3, 10).unwrap(), LATE);
+    assert_eq!((active, skipped), (SUBSCRIBERS as usize, 0));
+    assert!(!starts.is_empty(), "no events after activation");
+    assert!(starts.iter().all(|&ts| ts >= activation), "event before 15:00: {:?}", starts.iter().min());
+
+    // Activation day rows of everyone else still start from midnight
+    let (_, _, other) = served_starts(&db_path, NaiveDate::from_ymd_opt(2025, 3, 10).unwrap(), LATE + 1);
+    assert!(other.iter().any(|&ts| ts < activation));
+}
@@ -0,0 +1,100 @@
+// Integration test for msisdn_format: every format renders the same subscribers, consistently across MO/MT legs
+use chrono::NaiveDate;
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::config::{Config, MsisdnFormat};
+use std::collections::HashSet;
+use std::path::Path;
+use tempfile::TempDir;
+
+/// (event_type, direction, msisdn_src, msisdn_dst, start_ts_ms) of every row, in file order
+fn rows(day_dir: &Path) -> Vec<[String; 5]> {
+    let mut files: Vec<_> = std::fs::read_dir(day_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.file_name().unwrap().to_string_lossy().starts_with("cdr_"))
+        .collect();
+    files.sort();
+    let mut rows = Vec::new();
+    for path in files {
+        let mut reader = csv::ReaderBuilder::new().delimiter(b';').from_path(&path).unwrap();
+        for record in reader.records() {
+            let record = record.unwrap();
+            rows.push([0, 3, 1, 2, 4].map(|i| record[i].to_string()));
+        }
+    }
+    rows
+}
+
+fn generate(out_dir: &Path, format: MsisdnFormat, serialize_in_workers: bool) -> Vec<[String; 5]> {
+    let cfg = Config {
+        subscribers: 500,
+        workers: 2,
+        compression_type: "none".to_string(),
+        msisdn_format: format,
+        country_code: "31".to_string(),
+        serialize_in_workers,
+        ..Config::default()
+    };
+    let mut generator = DayGenerator::builder().config(cfg).out_dir(out_dir).bundle(false).build().unwrap();
+    generator.generate(NaiveDate::from_ymd_opt(2025, 2, 3).unwrap()).unwrap();
+    rows(&out_dir.join("2025-02-03"))
+}
+
+fn national_of(number: &str) -> String {
+    match number.strip_prefix("31") {
+        Some(rest) => format!("0{}", rest),
+        None => format!("00{}", number),
+    }
+}
+
+/// `formatted` is `e164` row for row with every MSISDN passed through `render`
+fn assert_renders(e164: &[[String; 5]], formatted: &[[String; 5]], render: impl Fn(&str) -> String) {
+    assert_eq!(formatted.len(), e164.len());
+    for (e164_row, row) in e164.iter().zip(formatted) {
+        assert_eq!(e164_row[4], row[4]);
+        for col in 2..4 {
+            if e164_row[col].is_empty() {
+                // DATA rows have no B-number in any format
+                assert!(row[col].is_empty());
+            } else {
+                assert_eq!(row[col], render(&e164_row[col]));
+            }
+        }
+    }
+}
+
+/// MT rows whose MO leg (same start, swapped numbers) is in the file
+fn matched_legs(rows: &[[String; 5]]) -> usize {
+    let mo: HashSet<(&str, &str, &str)> = rows
+        .iter()
+        .filter(|row| row[1] == "MO")
+        .map(|row| (row[2].as_str(), row[3].as_str(), row[4].as_str()))
+        .collect();
+    rows.iter()
+        .filter(|row| row[1] == "MT" && mo.contains(&(row[3].as_str(), row[2].as_str(), row[4].as_str())))
+        .count()
+}
+
+#[test]
+fn test_msisdn_formats_render_the_same_numbers() {
+    let dir = TempDir::new().unwrap();
+    let e164 = generate(&dir.path().join("e164"), MsisdnFormat::E164, true);
+    let plus = generate(&dir.path().join("plus"), MsisdnFormat::E164Plus, true);
+    let national = generate(&dir.path().join("national"), MsisdnFormat::National, true);
+
+    assert!(e164.len() > 1000, "{} rows", e164.len());
+
+    assert_renders(&e164, &plus, |number| format!("+{}", number));
+    assert_renders(&e164, &national, national_of);
+
+    // Rows serialized by the writer tasks instead of the workers
+    let e164_in_writer = generate(&dir.path().join("e164_writer"), MsisdnFormat::E164, false);
+    let national_in_writer = generate(&dir.path().join("national_writer"), MsisdnFormat::National, false);
+    assert_renders(&e164_in_writer, &national_in_writer, national_of);
+
+    // Correlated legs still pair up by number in every format
+    let legs = matched_legs(&e164);
+    assert!(legs > 100, "{} matched legs", legs);
+    assert_eq!(matched_legs(&plus), legs);
+    assert_eq!(matched_legs(&national), legs);
+}
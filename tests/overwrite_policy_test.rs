@@ -0,0 +1,271 @@
+// Integration tests for overwrite_policy: a second run into a day directory that already holds part files
+use chrono::NaiveDate;
+use rs_cdr_generator::api::{DayGenerator, DaySummary};
+use rs_cdr_generator::config::{ClockSkewConfig, Config, DirtyDataConfig, NodeClockSkew, OverwritePolicy};
+use rs_cdr_generator::utils::WriterShards;
+use rs_cdr_generator::writer::ShardManifest;
+use std::path::Path;
+use tempfile::TempDir;
+
+mod common;
+
+fn date() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2025, 1, 1).unwrap()
+}
+
+/// `workers` workers feed `workers / 2` writer tasks
+fn config(workers: usize, overwrite_policy: OverwritePolicy) -> Config {
+    Config {
+        subscribers: 400,
+        workers,
+        compression_type: "none".to_string(),
+        write_shard_stats: true,
+        overwrite_policy,
+        ..Config::default()
+    }
+}
+
+fn generate(out_dir: &Path, cfg: Config) -> anyhow::Result<DaySummary> {
+    DayGenerator::builder().config(cfg).out_dir(out_dir).bundle(false).build()?.generate(date())
+}
+
+fn generate_unbundled(out_dir: &Path, cfg: Config) -> anyhow::Result<DaySummary> {
+    DayGenerator::builder().config(cfg).out_dir(out_dir).build()?.generate(date())
+}
+
+fn rows(summary: &DaySummary) -> usize {
+    summary.total_calls + summary.total_sms + summary.sms_retry_rows + summary.total_data
+}
+
+/// Data rows of the day bundle (one header per part)
+fn bundle_rows(out_dir: &Path) -> usize {
+    let bundle = std::fs::read_to_string(out_dir.join("cdr_2025-01-01.csv")).unwrap();
+    let header = bundle.lines().next().unwrap().to_string();
+    bundle.lines().filter(|line| *line != header).count()
+}
+
+/// Sorted names of the day directory's files starting with `prefix`
+fn day_files(out_dir: &Path, prefix: &str) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(out_dir.join("2025-01-01"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name.starts_with(prefix))
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_fail_refuses_a_day_with_part_files() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    generate(temp_dir.path(), config(4, OverwritePolicy::Fail))?;
+    let parts = day_files(temp_dir.path(), "cdr_");
+    let summary = std::fs::read_to_string(temp_dir.path().join("2025-01-01/summary.json"))?;
+
+    let error = generate(temp_dir.path(), config(2, OverwritePolicy::Fail)).unwrap_err();
+    assert!(error.to_string().contains("clean or merge"), "{}", error);
+    // Nothing of the earlier run is touched
+    assert_eq!(day_files(temp_dir.path(), "cdr_"), parts);
+    assert_eq!(std::fs::read_to_string(temp_dir.path().join("2025-01-01/summary.json"))?, summary);
+
+    // The next day is a fresh directory
+    let mut generator = DayGenerator::builder().config(config(2, OverwritePolicy::Fail)).out_dir(temp_dir.path()).build()?;
+    generator.generate(date().succ_opt().unwrap())?;
+    Ok(())
+}
+
+#[test]
+fn test_writer_files_map_to_worker_shards() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let summary = generate(temp_dir.path(), config(4, OverwritePolicy::Fail))?;
+    // Worker shards are dealt round-robin to the two writer tasks
+    let expected = vec![
+        WriterShards { writer: 0, worker_shards: vec![0, 2] },
+        WriterShards { writer: 1, worker_shards: vec![1, 3] },
+    ];
+    assert_eq!(summary.writers, expected);
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(temp_dir.path().join("2025-01-01/summary.json"))?)?;
+    assert_eq!(json["writers"][1]["worker_shards"], serde_json::json!([1, 3]));
+
+    // Manifests and their entries carry both identifiers
+    for mapping in &expected {
+        let name = format!("manifest_writer{:03}.json", mapping.writer);
+        let manifest: ShardManifest = serde_json::from_str(&std::fs::read_to_string(temp_dir.path().join("2025-01-01").join(name))?)?;
+        assert_eq!(manifest.writer, mapping.writer);
+        assert_eq!(manifest.worker_shards, mapping.worker_shards);
+        assert!(!manifest.files.is_empty());
+        for entry in &manifest.files {
+            assert!(entry.file.contains(&format!("_writer{:03}_", mapping.writer)), "{}", entry.file);
+            assert_eq!((entry.writer, &entry.worker_shards), (mapping.writer, &mapping.worker_shards));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_clean_replaces_the_earlier_run() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    generate(temp_dir.path(), config(4, OverwritePolicy::Fail))?;
+    assert!(day_files(temp_dir.path(), "cdr_").iter().any(|name| name.contains("_writer001_")));
+
+    let summary = generate(temp_dir.path(), config(2, OverwritePolicy::Clean))?;
+    // One writer task now: the second writer's parts and manifest and the stale shard stats are gone
+    assert!(day_files(temp_dir.path(), "cdr_").iter().all(|name| name.contains("_writer000_")));
+    assert_eq!(day_files(temp_dir.path(), "manifest_"), vec!["manifest_writer000.json"]);
+    assert_eq!(day_files(temp_dir.path(), "stats_"), vec!["stats_shard000.json", "stats_shard001.json"]);
+    assert_eq!(bundle_rows(temp_dir.path()), rows(&summary));
+    assert!(summary.warnings.is_empty(), "{:?}", summary.warnings);
+    Ok(())
+}
+
+#[test]
+fn test_merge_bundles_both_runs() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    generate(temp_dir.path(), config(4, OverwritePolicy::Fail))?;
+    // The new run's single writer task rewrites the writer000 parts; writer001 is left from the earlier run
+    let (replaced, kept): (Vec<String>, Vec<String>) =
+        day_files(temp_dir.path(), "cdr_").into_iter().partition(|name| name.contains("_writer000_"));
+    let day_dir = temp_dir.path().join("2025-01-01");
+    let kept_rows: usize = kept.iter().map(|name| std::fs::read_to_string(day_dir.join(name)).unwrap().lines().count() - 1).sum();
+    assert!(kept_rows > 0);
+
+    let summary = generate(temp_dir.path(), config(2, OverwritePolicy::Merge))?;
+    assert_eq!(bundle_rows(temp_dir.path()), rows(&summary) + kept_rows);
+    // Only the surviving parts count as merged; the replaced ones are named
+    let expected = vec![
+        format!("merged {} part files of an earlier run", kept.len()),
+        format!("replaced {} part files of an earlier run: {}", replaced.len(), replaced.join(", ")),
+    ];
+    assert_eq!(summary.warnings, expected);
+    Ok(())
+}
+
+#[test]
+fn test_bundle_of_an_earlier_run_is_not_silently_replaced() -> anyhow::Result<()> {
+    // The first run removes its parts after bundling, so only the bundle at the root is left
+    let temp_dir = TempDir::new()?;
+    let bundled = |policy| DayGenerator::builder().config(config(2, policy)).out_dir(temp_dir.path()).bundle(true).build();
+    bundled(OverwritePolicy::Fail)?.generate(date())?;
+    assert!(day_files(temp_dir.path(), "cdr_").is_empty());
+    let bundle = temp_dir.path().join("cdr_2025-01-01.csv");
+    let earlier = std::fs::read(&bundle)?;
+
+    let error = bundled(OverwritePolicy::Fail)?.generate(date()).unwrap_err();
+    assert!(error.to_string().contains("bundle of an earlier run") && error.to_string().contains("clean or merge"), "{}", error);
+    assert_eq!(std::fs::read(&bundle)?, earlier);
+
+    // Merge replaces it loudly; clean removes it first
+    let summary = bundled(OverwritePolicy::Merge)?.generate(date())?;
+    assert_eq!(summary.warnings.len(), 1);
+    assert!(summary.warnings[0].starts_with("replaced the bundle of an earlier run"), "{:?}", summary.warnings);
+    let summary = bundled(OverwritePolicy::Clean)?.generate(date())?;
+    assert!(summary.warnings.is_empty(), "{:?}", summary.warnings);
+    assert_eq!(bundle_rows(temp_dir.path()), rows(&summary));
+
+    // A run that does not bundle leaves the bundle alone
+    generate_unbundled(temp_dir.path(), config(2, OverwritePolicy::Fail))?;
+    assert_eq!(bundle_rows(temp_dir.path()), rows(&summary));
+    Ok(())
+}
+
+/// Every row damaged, each worker shard logging it in dirty_shardNNN.jsonl
+fn dirty_config(workers: usize, overwrite_policy: OverwritePolicy) -> Config {
+    let dirty_data = DirtyDataConfig { missing_imei_rate: 1.0, ground_truth: true, ..DirtyDataConfig::default() };
+    Config { dirty_data, ..config(workers, overwrite_policy) }
+}
+
+#[test]
+fn test_ground_truth_of_an_earlier_run_is_cleaned_or_refused() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    generate(temp_dir.path(), dirty_config(4, OverwritePolicy::Fail))?;
+    assert_eq!(day_files(temp_dir.path(), "dirty_").len(), 4);
+
+    // Clean drops the logs of the shards the new run does not have
+    generate(temp_dir.path(), dirty_config(2, OverwritePolicy::Clean))?;
+    assert_eq!(day_files(temp_dir.path(), "dirty_"), vec!["dirty_shard000.jsonl", "dirty_shard001.jsonl"]);
+
+    // With the parts already taken away, the ground truth alone still refuses the day
+    let day_dir = temp_dir.path().join("2025-01-01");
+    for name in day_files(temp_dir.path(), "cdr_") {
+        std::fs::remove_file(day_dir.join(name))?;
+    }
+    let error = generate(temp_dir.path(), config(2, OverwritePolicy::Fail)).unwrap_err();
+    assert!(error.to_string().contains("ground truth dirty_shard000.jsonl"), "{}", error);
+    Ok(())
+}
+
+#[test]
+fn test_clean_removes_stale_clock_skew_logs() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let skewed = |workers, overwrite_policy| {
+        let msc = NodeClockSkew { mean_ms: 1_500.0, sigma_ms: 0.0, drift_ms_per_hour: 0.0, nodes: 1 };
+        let clock_skew = ClockSkewConfig { msc, ground_truth: true, ..ClockSkewConfig::default() };
+        Config { clock_skew, ..config(workers, overwrite_policy) }
+    };
+    generate(temp_dir.path(), skewed(4, OverwritePolicy::Fail))?;
+    assert_eq!(day_files(temp_dir.path(), "clock_skew_").len(), 4);
+
+    generate(temp_dir.path(), skewed(2, OverwritePolicy::Clean))?;
+    assert_eq!(day_files(temp_dir.path(), "clock_skew_"), vec!["clock_skew_shard000.jsonl", "clock_skew_shard001.jsonl"]);
+    Ok(())
+}
+
+#[test]
+fn test_late_files_of_an_earlier_run_are_cleaned_or_refused() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    // Every part file is staged in late/2025-01-01/ with its sidecar
+    let late = |overwrite_policy| Config { late_file_rate: 1.0, ..config(2, overwrite_policy) };
+    generate(temp_dir.path(), late(OverwritePolicy::Fail))?;
+    let late_dir = temp_dir.path().join("late/2025-01-01");
+    let staged = std::fs::read_dir(&late_dir)?.count();
+    assert!(staged > 0);
+    assert!(day_files(temp_dir.path(), "cdr_").is_empty());
+
+    let error = generate(temp_dir.path(), config(2, OverwritePolicy::Fail)).unwrap_err();
+    assert!(error.to_string().contains(&format!("staged {} late files", staged)), "{}", error);
+    assert_eq!(std::fs::read_dir(&late_dir)?.count(), staged);
+
+    // Merge keeps them for deliver-late, loudly; clean removes them with their sidecars
+    let summary = generate(temp_dir.path(), config(2, OverwritePolicy::Merge))?;
+    assert!(summary.warnings.contains(&format!("kept {} late files an earlier run staged", staged)), "{:?}", summary.warnings);
+    assert!(late_dir.exists());
+    generate(temp_dir.path(), config(2, OverwritePolicy::Clean))?;
+    assert!(!late_dir.exists());
+    Ok(())
+}
+
+/// Runs the CLI binary, which is only built with every optional feature
+#[cfg(all(feature = "arrow", feature = "redb", feature = "async", feature = "zstd"))]
+#[test]
+fn test_generate_cdr_overwrite_policy_flag() -> anyhow::Result<()> {
+    use std::process::Command;
+
+    let temp_dir = TempDir::new()?;
+    let db_path = temp_dir.path().join("subs.redb");
+    let out = temp_dir.path().join("out");
+    // The worker's index-derived MSISDNs, all in the database
+    common::build_indexed_db(&db_path, 31612, 0..300);
+
+    let generate_cdr = |policy: Option<&str>| {
+        let mut cmd = Command::new(env!("CARGO_BIN_EXE_rs_cdr_generator"));
+        cmd.env("RUST_LOG", "warn")
+            .args(["generate-cdr", "--start", "2025-01-01", "--days", "1", "--workers", "2", "--compression", "none"])
+            .args(["--prefixes", "31612"])
+            .arg("--subscriber-db")
+            .arg(&db_path)
+            .arg("--out")
+            .arg(&out);
+        if let Some(policy) = policy {
+            cmd.args(["--overwrite-policy", policy]);
+        }
+        cmd.output().unwrap()
+    };
+
+    assert!(generate_cdr(None).status.success());
+    let rerun = generate_cdr(None);
+    assert!(!rerun.status.success());
+    assert!(String::from_utf8_lossy(&rerun.stderr).contains("already holds"));
+    assert!(generate_cdr(Some("clean")).status.success());
+    assert!(!generate_cdr(Some("append")).status.success());
+    Ok(())
+}
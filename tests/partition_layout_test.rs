@@ -0,0 +1,113 @@
+// Integration tests for the partition_layout option: hive-style day and event type directories
+use chrono::NaiveDate;
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::config::{Config, PartitionLayout};
+use rs_cdr_generator::writer::ShardManifest;
+use std::path::Path;
+use tempfile::TempDir;
+
+fn config(layout: PartitionLayout) -> Config {
+    Config {
+        subscribers: 200,
+        workers: 2,
+        compression_type: "none".to_string(),
+        partition_layout: layout,
+        write_shard_stats: true,
+        ..Config::default()
+    }
+}
+
+/// Every manifest in the day directory
+fn read_manifests(day_dir: &Path) -> Vec<ShardManifest> {
+    let mut manifests: Vec<ShardManifest> = std::fs::read_dir(day_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.file_name().unwrap().to_string_lossy().starts_with("manifest_writer"))
+        .map(|path| serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap())
+        .collect();
+    manifests.sort_by_key(|manifest| manifest.writer);
+    manifests
+}
+
+/// Data rows of a CSV part file (header skipped)
+fn data_lines(path: &Path) -> Vec<String> {
+    std::fs::read_to_string(path).unwrap().lines().skip(1).map(str::to_string).collect()
+}
+
+#[test]
+fn test_hive_date_puts_day_under_date_partition() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let mut generator =
+        DayGenerator::builder().config(config(PartitionLayout::HiveDate)).out_dir(temp_dir.path()).bundle(false).build()?;
+    generator.generate(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap())?;
+
+    let day_dir = temp_dir.path().join("date=2025-01-01");
+    assert!(!temp_dir.path().join("2025-01-01").exists());
+    assert!(day_dir.join("summary.json").exists());
+    assert!(day_dir.join("stats_shard000.json").exists());
+    let manifest = &read_manifests(&day_dir)[0];
+    assert_eq!(manifest.files[0].file, "cdr_2025-01-01_writer000_part001.csv");
+    assert!(day_dir.join(&manifest.files[0].file).exists());
+    Ok(())
+}
+
+#[test]
+fn test_hive_date_type_splits_parts_by_event_type() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let mut generator =
+        DayGenerator::builder().config(config(PartitionLayout::HiveDateType)).out_dir(temp_dir.path()).bundle(false).build()?;
+    generator.generate(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap())?;
+
+    // Manifests, stats and summary stay in the date partition
+    let day_dir = temp_dir.path().join("date=2025-01-01");
+    assert!(day_dir.join("summary.json").exists());
+    assert!(day_dir.join("stats_shard001.json").exists());
+
+    let mut event_types = Vec::new();
+    for manifest in read_manifests(&day_dir) {
+        let writer = manifest.writer;
+        for entry in manifest.files {
+            let event_type = entry.event_type.clone().unwrap();
+            // File names keep the writer/part scheme, under type=<EVENT_TYPE>/
+            let name = format!("cdr_2025-01-01_writer{:03}_{}_part{:03}.csv", writer, event_type.to_lowercase(), entry.file_seq);
+            assert_eq!(entry.file, format!("type={}/{}", event_type, name));
+            let lines = data_lines(&day_dir.join(&entry.file));
+            assert_eq!(lines.len() as u64, entry.records);
+            assert!(lines.iter().all(|line| line.starts_with(&format!("{};", event_type))), "{}", entry.file);
+            event_types.push(event_type);
+        }
+    }
+    event_types.sort();
+    event_types.dedup();
+    assert_eq!(event_types, ["CALL", "DATA", "SMS"]);
+    Ok(())
+}
+
+#[test]
+fn test_bundle_collects_every_partition() -> anyhow::Result<()> {
+    let date = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+
+    let flat_dir = TempDir::new()?;
+    let mut generator = DayGenerator::builder().config(config(PartitionLayout::Flat)).out_dir(flat_dir.path()).bundle(false).build()?;
+    let flat_summary = generator.generate(date)?;
+
+    let hive_dir = TempDir::new()?;
+    let mut generator =
+        DayGenerator::builder().config(config(PartitionLayout::HiveDateType)).out_dir(hive_dir.path()).bundle(true).build()?;
+    generator.generate(date)?;
+
+    let bundle = hive_dir.path().join("cdr_2025-01-01.csv");
+    let rows = std::fs::read_to_string(&bundle)?.lines().filter(|line| !line.starts_with("event_type;")).count();
+    let expected = flat_summary.total_calls + flat_summary.total_sms + flat_summary.sms_retry_rows + flat_summary.total_data;
+    assert_eq!(rows, expected);
+
+    // Cleanup removes the parts and the emptied type directories, but keeps the date partition's metadata
+    let day_dir = hive_dir.path().join("date=2025-01-01");
+    let leftovers: Vec<String> = std::fs::read_dir(&day_dir)?
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name.starts_with("type=") || name.starts_with("cdr_"))
+        .collect();
+    assert!(leftovers.is_empty(), "{:?}", leftovers);
+    assert!(day_dir.join("summary.json").exists());
+    Ok(())
+}
@@ -0,0 +1,146 @@
+// Integration tests for emit_prev_event_ts: every row carries the start of its subscriber's previous event
+use chrono::NaiveDate;
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::config::Config;
+use rs_cdr_generator::reader::open_cdr_reader;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+mod common;
+
+fn date() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2025, 3, 10).unwrap()
+}
+
+fn part_files(day_dir: &Path) -> Vec<PathBuf> {
+    let mut parts: Vec<PathBuf> = std::fs::read_dir(day_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            let name = path.file_name().unwrap().to_string_lossy();
+            name.starts_with("cdr_") && name.ends_with(".csv")
+        })
+        .collect();
+    parts.sort();
+    parts
+}
+
+fn header(path: &Path) -> Vec<String> {
+    let mut reader = csv::ReaderBuilder::new().delimiter(b';').from_path(path).unwrap();
+    reader.headers().unwrap().iter().map(str::to_string).collect()
+}
+
+/// (imsi, start_ts_ms, prev_event_ts_ms) with 0 for an empty previous timestamp
+fn check_timelines(rows: &[(u64, i64, i64)]) {
+    let mut by_imsi: HashMap<u64, Vec<(i64, i64)>> = HashMap::new();
+    for &(imsi, start, prev) in rows {
+        by_imsi.entry(imsi).or_default().push((start, prev));
+    }
+    assert!(rows.iter().any(|&(_, _, prev)| prev > 0), "no row carries a previous timestamp");
+
+    for (imsi, mut events) in by_imsi {
+        events.sort();
+        // The subscriber's first event of the day has nothing before it
+        assert_eq!(events[0].1, 0, "first event of {} has prev {}", imsi, events[0].1);
+
+        let mut last_prev = 0;
+        for &(start, prev) in events.iter().filter(|(_, prev)| *prev > 0) {
+            assert!(prev <= start, "{}: prev {} after start {}", imsi, prev, start);
+            assert!(prev >= last_prev, "{}: prev went back from {} to {}", imsi, last_prev, prev);
+            assert!(events.iter().any(|&(s, _)| s == prev), "{}: no event starts at prev {}", imsi, prev);
+            last_prev = prev;
+        }
+    }
+}
+
+#[test]
+fn test_prev_event_ts_in_part_files() -> anyhow::Result<()> {
+    // Both serialization paths must agree on the layout
+    for serialize_in_workers in [true, false] {
+        let temp_dir = TempDir::new()?;
+        let cfg = Config {
+            subscribers: 300,
+            workers: 2,
+            compression_type: "none".to_string(),
+            rotate_bytes: 50_000,
+            emit_prev_event_ts: true,
+            serialize_in_workers,
+            ..Config::default()
+        };
+        let mut generator = DayGenerator::builder().config(cfg).out_dir(temp_dir.path()).bundle(false).build()?;
+        generator.generate(date())?;
+
+        let parts = part_files(&temp_dir.path().join("2025-03-10"));
+        assert!(!parts.is_empty());
+        let mut rows = Vec::new();
+        for part in &parts {
+            assert!(header(part).iter().any(|c| c == "prev_event_ts_ms"), "{:?} lacks the column", part);
+            for row in open_cdr_reader(part)? {
+                let row = row?;
+                rows.push((row.imsi, row.start_ts_ms, row.prev_event_ts_ms));
+            }
+        }
+        check_timelines(&rows);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_prev_event_ts_column_off_by_default() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let cfg = Config {
+        subscribers: 100,
+        workers: 1,
+        compression_type: "none".to_string(),
+        ..Config::default()
+    };
+    let mut generator = DayGenerator::builder().config(cfg).out_dir(temp_dir.path()).bundle(false).build()?;
+    generator.generate(date())?;
+
+    for part in part_files(&temp_dir.path().join("2025-03-10")) {
+        assert!(!header(&part).iter().any(|c| c == "prev_event_ts_ms"));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "redb")]
+#[test]
+fn test_prev_event_ts_with_subscriber_db() {
+    use rs_cdr_generator::writer::{EventRow, PREV_EVENT_TS_NONE};
+    use std::sync::{Arc, Mutex};
+    const PREFIX: u64 = 31612;
+    const SUBSCRIBERS: u64 = 200;
+
+    let dir = TempDir::new().unwrap();
+    let db_path = dir.path().join("subs.redb");
+    common::build_indexed_db(&db_path, PREFIX, 0..SUBSCRIBERS);
+
+    let rows: Arc<Mutex<Vec<EventRow>>> = Arc::default();
+    let sink_rows = rows.clone();
+    let cfg = Config {
+        prefixes: vec![PREFIX.to_string()],
+        workers: 2,
+        avg_calls_per_user: 10.0,
+        avg_sms_per_user: 5.0,
+        avg_data_sessions_per_user: 5.0,
+        daily_inactive_rate: 0.0,
+        emit_prev_event_ts: true,
+        ..Config::default()
+    };
+    let mut generator = DayGenerator::builder()
+        .config(cfg)
+        .subscriber_db(db_path.as_path())
+        .sink(move |batch| sink_rows.lock().unwrap().extend(batch.events))
+        .build()
+        .unwrap();
+    generator.generate(date()).unwrap();
+
+    let rows = rows.lock().unwrap();
+    assert!(rows.iter().all(|r| r.prev_event_ts_ms != 0), "row left unstamped");
+    let rows: Vec<_> = rows
+        .iter()
+        .map(|r| (r.imsi, r.start_ts_ms, if r.prev_event_ts_ms == PREV_EVENT_TS_NONE { 0 } else { r.prev_event_ts_ms }))
+        .collect();
+    check_timelines(&rows);
+}
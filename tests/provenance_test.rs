@@ -0,0 +1,119 @@
+// Integration tests for run provenance in summary.json and run_config.yaml, and the `stats` subcommand
+use chrono::NaiveDate;
+use rs_cdr_generator::api::{DayGenerator, DaySummary};
+use rs_cdr_generator::config::Config;
+use rs_cdr_generator::provenance::{config_sha256, RunConfig, RUN_CONFIG_FILE};
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+mod common;
+
+const PREFIX: u64 = 31612;
+
+/// redb database of `size` index-derived MSISDNs
+fn build_db(path: &Path, size: u64) {
+    common::build_indexed_db(path, PREFIX, 0..size).set_earliest_snapshot_ms(0).unwrap();
+}
+
+fn config() -> Config {
+    Config { prefixes: vec![PREFIX.to_string()], workers: 2, compression_type: "none".to_string(), seed: 7, ..Config::default() }
+}
+
+/// Generate `day` into `out_dir` and read back its summary.json
+fn generate(out_dir: &Path, db: &Path, cfg: Config, day: u32) -> DaySummary {
+    let date = NaiveDate::from_ymd_opt(2025, 1, day).unwrap();
+    DayGenerator::builder().config(cfg).subscriber_db(db).out_dir(out_dir).bundle(false).build().unwrap().generate(date).unwrap();
+    let json = std::fs::read_to_string(out_dir.join(date.to_string()).join("summary.json")).unwrap();
+    serde_json::from_str(&json).unwrap()
+}
+
+#[test]
+fn test_summary_and_run_config_record_provenance() {
+    let dir = TempDir::new().unwrap();
+    let db_path = dir.path().join("subs.redb");
+    build_db(&db_path, 300);
+    let out = dir.path().join("out");
+    generate(&out, &db_path, config(), 1);
+
+    // Every field is written, even where there is nothing to record
+    let day_dir = out.join("2025-01-01");
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(day_dir.join("summary.json")).unwrap()).unwrap();
+    let provenance = &json["provenance"];
+    for key in ["crate_version", "git_hash", "config_sha256", "seed", "subscriber_db", "subscriber_db_fingerprint"] {
+        assert!(provenance.get(key).is_some(), "summary.json provenance without {}: {}", key, provenance);
+    }
+    assert_eq!(provenance["crate_version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(provenance["seed"], 7);
+    assert_eq!(provenance["subscriber_db"], db_path.to_string_lossy().as_ref());
+    for key in ["config_sha256", "subscriber_db_fingerprint"] {
+        let hash = provenance[key].as_str().unwrap();
+        assert!(hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()), "{}: {}", key, hash);
+    }
+
+    // run_config.yaml holds the same provenance and the config it fingerprints
+    let run_config: RunConfig = serde_yaml::from_str(&std::fs::read_to_string(day_dir.join(RUN_CONFIG_FILE)).unwrap()).unwrap();
+    assert_eq!(serde_json::to_value(&run_config.provenance).unwrap(), *provenance);
+    assert_eq!(config_sha256(&run_config.config).unwrap(), run_config.provenance.config_sha256);
+    assert_eq!(run_config.config.subscriber_db_redb_path.as_deref(), Some(db_path.as_path()));
+}
+
+#[test]
+fn test_fingerprints_follow_config_and_database() {
+    let dir = TempDir::new().unwrap();
+    let db_path = dir.path().join("subs.redb");
+    build_db(&db_path, 300);
+    let provenance = |name: &str, cfg: Config| generate(&dir.path().join(name), &db_path, cfg, 1).provenance.unwrap();
+
+    let base = provenance("base", config());
+    // Same config and database, same fingerprints
+    assert_eq!(provenance("again", config()), base);
+    for (name, cfg) in [
+        ("sms", Config { avg_sms_per_user: 3.0, ..config() }),
+        ("tz", Config { tz_name: "Europe/Berlin".to_string(), ..config() }),
+        ("workers", Config { workers: 3, ..config() }),
+        ("diurnal", Config { diurnal_weekday: vec![1.0; 24], ..config() }),
+    ] {
+        let other = provenance(name, cfg);
+        assert_ne!(other.config_sha256, base.config_sha256, "{}", name);
+        assert_eq!(other.subscriber_db_fingerprint, base.subscriber_db_fingerprint, "{}", name);
+    }
+    let reseeded = provenance("seed", Config { seed: 8, ..config() });
+    assert_eq!(reseeded.seed, 8);
+    assert_ne!(reseeded.config_sha256, base.config_sha256);
+
+    // A database with more subscribers has another fingerprint
+    std::fs::remove_file(&db_path).unwrap();
+    build_db(&db_path, 301);
+    assert_ne!(provenance("bigger_db", config()).subscriber_db_fingerprint, base.subscriber_db_fingerprint);
+}
+
+#[test]
+fn test_stats_prints_provenance_of_old_runs() {
+    let dir = TempDir::new().unwrap();
+    let db_path = dir.path().join("subs.redb");
+    build_db(&db_path, 300);
+    let out = dir.path().join("out");
+    let first = generate(&out, &db_path, config(), 1).provenance.unwrap();
+    let second = generate(&out, &db_path, Config { avg_calls_per_user: 2.0, ..config() }, 2).provenance.unwrap();
+    // A day written before provenance was recorded
+    std::fs::create_dir_all(out.join("2024-12-31")).unwrap();
+    std::fs::write(out.join("2024-12-31").join("summary.json"), serde_json::to_string(&DaySummary::default()).unwrap()).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rs_cdr_generator"))
+        .env("RUST_LOG", "warn")
+        .args(["stats", "--input"])
+        .arg(&out)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert!(lines[0].starts_with("2024-12-31: 0 calls"), "{}", stdout);
+    assert_eq!(lines[1], "  no provenance recorded");
+    assert!(lines[2].starts_with("2025-01-01: "), "{}", stdout);
+    assert_eq!(lines[3], format!("  {}", first.describe()));
+    assert_eq!(lines[5], format!("  {}", second.describe()));
+    assert!(lines[3].contains(&format!("config {}, seed 7", &first.config_sha256[..12])), "{}", lines[3]);
+    assert_eq!(lines[6], "3 days from 2 different configs");
+}
@@ -0,0 +1,165 @@
+// Integration tests for the provisioning delta feed: export-provisioning and generate-cdr --with-provisioning
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::config::load_config;
+use rs_cdr_generator::subscriber_db::SubscriberEvent;
+use rs_cdr_generator::subscriber_db_generator::{generate_database, write_database_redb, GeneratorConfig};
+use rs_cdr_generator::subscriber_db_redb::DbWriteOptions;
+use std::path::Path;
+use tempfile::TempDir;
+
+mod common;
+
+const CONFIG: &str = "\
+prefixes: ['31612']
+tz_name: UTC
+workers: 2
+";
+
+// 2024-03-01, day 60 of the history
+const DATE: &str = "2024-03-01";
+const DAY_START_MS: i64 = 1_709_251_200_000;
+const DAY_MS: i64 = 86_400_000;
+
+/// Database with a history busy enough to have every kind of event on most days; returns the history
+fn build_db(path: &Path) -> Vec<SubscriberEvent> {
+    let config = GeneratorConfig {
+        initial_subscribers: 3_000,
+        history_days: 90,
+        device_change_rate: 0.9,
+        number_release_rate: 0.5,
+        cooldown_days: 5,
+        prefixes: vec!["31612".to_string()],
+        ..GeneratorConfig::default()
+    };
+    let events = generate_database(&config).unwrap();
+    write_database_redb(events.clone(), path, &DbWriteOptions::default()).unwrap();
+    events
+}
+
+/// Rows as (event_type, timestamp_ms, msisdn, imsi, old_imei, new_imei)
+fn read_rows(path: &Path) -> Vec<(String, i64, u64, u64, String, String)> {
+    let mut reader = csv::Reader::from_path(path).unwrap();
+    assert_eq!(
+        reader.headers().unwrap(),
+        vec!["event_type", "timestamp_ms", "msisdn", "imsi", "old_imei", "new_imei"]
+    );
+    reader
+        .records()
+        .map(|record| {
+            let record = record.unwrap();
+            (
+                record[0].to_string(),
+                record[1].parse().unwrap(),
+                record[2].parse().unwrap(),
+                record[3].parse().unwrap(),
+                record[4].to_string(),
+                record[5].to_string(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_export_matches_the_day_of_history() {
+    let dir = TempDir::new().unwrap();
+    let db_path = dir.path().join("subs.redb");
+    let config_path = dir.path().join("config.yaml");
+    let output = dir.path().join("provisioning.csv");
+    std::fs::write(&config_path, CONFIG).unwrap();
+    let history = build_db(&db_path);
+
+    let status = common::cli("warn")
+        .arg("export-provisioning")
+        .arg("--subscriber-db")
+        .arg(&db_path)
+        .args(["--date", DATE])
+        .arg("--output")
+        .arg(&output)
+        .arg("--config")
+        .arg(&config_path)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let rows = read_rows(&output);
+
+    // One row per event of the day, in MSISDN order
+    let mut expected: Vec<(String, i64, u64, u64)> = history
+        .iter()
+        .filter(|e| (DAY_START_MS..DAY_START_MS + DAY_MS).contains(&e.timestamp_ms))
+        .map(|e| (e.event_type.to_str().to_string(), e.timestamp_ms, e.msisdn.unwrap().get(), e.imsi.get()))
+        .collect();
+    let mut exported: Vec<(String, i64, u64, u64)> =
+        rows.iter().map(|(t, ts, msisdn, imsi, _, _)| (t.clone(), *ts, *msisdn, *imsi)).collect();
+    assert!(exported.windows(2).all(|pair| pair[0].2 <= pair[1].2));
+    expected.sort();
+    exported.sort();
+    assert_eq!(exported, expected);
+    for kind in ["CHANGE_DEVICE", "RELEASE_NUMBER", "ASSIGN_NUMBER"] {
+        assert!(rows.iter().any(|row| row.0 == kind), "no {} on {}", kind, DATE);
+    }
+
+    // Old and new IMEI: the device changed, appeared or went away
+    for (event_type, _, _, _, old_imei, new_imei) in &rows {
+        match event_type.as_str() {
+            "CHANGE_DEVICE" => assert!(!old_imei.is_empty() && !new_imei.is_empty() && old_imei != new_imei),
+            "SOFTWARE_UPDATE" => assert_eq!(old_imei, new_imei),
+            "NEW_SUBSCRIBER" | "ASSIGN_NUMBER" => assert!(old_imei.is_empty() && !new_imei.is_empty()),
+            "RELEASE_NUMBER" => assert!(!old_imei.is_empty() && new_imei.is_empty()),
+            other => panic!("unexpected event type {}", other),
+        }
+    }
+}
+
+#[test]
+fn test_generate_cdr_writes_provisioning_per_day() {
+    let dir = TempDir::new().unwrap();
+    let db_path = dir.path().join("subs.redb");
+    let config_path = dir.path().join("config.yaml");
+    let out_dir = dir.path().join("out");
+    std::fs::write(&config_path, CONFIG).unwrap();
+    build_db(&db_path);
+
+    let status = common::cli("warn")
+        .arg("generate-cdr")
+        .arg("--subscriber-db")
+        .arg(&db_path)
+        .args(["--start", DATE, "--days", "2", "--with-provisioning"])
+        .arg("--config")
+        .arg(&config_path)
+        .arg("--out")
+        .arg(&out_dir)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    // Each day's file is the export of that day, next to the CDR bundle
+    for date in [DATE, "2024-03-02"] {
+        let export = dir.path().join(format!("export_{}.csv", date));
+        let status = common::cli("warn")
+            .arg("export-provisioning")
+            .arg("--subscriber-db")
+            .arg(&db_path)
+            .args(["--date", date])
+            .arg("--output")
+            .arg(&export)
+            .arg("--config")
+            .arg(&config_path)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let written = read_rows(&out_dir.join(date).join("provisioning.csv"));
+        assert!(!written.is_empty());
+        assert_eq!(written, read_rows(&export));
+        assert!(out_dir.join(format!("cdr_{}.csv.gz", date)).exists());
+    }
+
+    // Without a redb database there is nothing to derive the feed from
+    let error = DayGenerator::builder()
+        .config(load_config(Some(&config_path)).unwrap())
+        .out_dir(&out_dir)
+        .provisioning()
+        .build()
+        .err()
+        .unwrap();
+    assert!(error.to_string().contains("redb"), "{}", error);
+}
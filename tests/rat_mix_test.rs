@@ -0,0 +1,79 @@
+// Integration test for the configured RAT mix: cells catalog and DATA sessions share one distribution
+use chrono::NaiveDate;
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::config::Config;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use tempfile::TempDir;
+
+fn csv_reader(path: &Path) -> csv::Reader<std::fs::File> {
+    csv::ReaderBuilder::new().delimiter(b';').from_path(path).unwrap()
+}
+
+/// Share of each RAT among `rats`
+fn shares<'a>(rats: impl Iterator<Item = &'a str>) -> BTreeMap<String, f64> {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    let mut total = 0;
+    for rat in rats {
+        *counts.entry(rat.to_string()).or_default() += 1;
+        total += 1;
+    }
+    counts.into_iter().map(|(rat, n)| (rat, n as f64 / total as f64)).collect()
+}
+
+#[test]
+fn test_rat_mix_shared_by_cells_and_data() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let out_dir = temp_dir.path();
+
+    let cfg = Config {
+        subscribers: 500,
+        workers: 2,
+        cells: 2000,
+        compression_type: "none".to_string(),
+        rat_mix: BTreeMap::from([("LTE".to_string(), 0.7), ("NR".to_string(), 0.3)]),
+        ..Config::default()
+    };
+    let mut generator = DayGenerator::builder().config(cfg).out_dir(out_dir).bundle(false).build()?;
+    generator.generate(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap())?;
+
+    // Cells catalog follows the mix
+    let mut cell_rat: HashMap<u32, String> = HashMap::new();
+    let cells_csv = std::fs::read_to_string(out_dir.join("cells.csv"))?;
+    for line in cells_csv.lines().skip(1) {
+        let fields: Vec<&str> = line.split(',').collect();
+        cell_rat.insert(fields[0].parse()?, fields[3].to_string());
+    }
+    let cell_shares = shares(cell_rat.values().map(String::as_str));
+    assert_eq!(cell_shares.keys().collect::<Vec<_>>(), ["LTE", "NR"]);
+    assert!((cell_shares["LTE"] - 0.7).abs() < 0.05, "{:?}", cell_shares);
+
+    // DATA sessions follow the same mix and only use cells of their own RAT
+    let mut data_rats = Vec::new();
+    for entry in std::fs::read_dir(out_dir.join("2025-01-01"))? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        if !(name.starts_with("cdr_") && name.ends_with(".csv")) {
+            continue;
+        }
+        let mut reader = csv_reader(&path);
+        let headers = reader.headers()?.clone();
+        let column = |name: &str| headers.iter().position(|h| h == name).unwrap();
+        let (event_type, cell_id, rat) = (column("event_type"), column("cell_id"), column("rat"));
+        for record in reader.records() {
+            let record = record?;
+            if &record[event_type] != "DATA" {
+                continue;
+            }
+            let cell: u32 = record[cell_id].parse()?;
+            assert_eq!(cell_rat[&cell], &record[rat], "cell {} used for {}", cell, &record[rat]);
+            data_rats.push(record[rat].to_string());
+        }
+    }
+    assert!(data_rats.len() > 1000);
+    let data_shares = shares(data_rats.iter().map(String::as_str));
+    assert_eq!(data_shares.keys().collect::<Vec<_>>(), ["LTE", "NR"]);
+    assert!((data_shares["LTE"] - 0.7).abs() < 0.05, "{:?}", data_shares);
+
+    Ok(())
+}
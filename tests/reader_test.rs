@@ -0,0 +1,48 @@
+// Integration test for reader::open_cdr_reader on the part files of a generated day
+use chrono::NaiveDate;
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::compression::CompressionType;
+use rs_cdr_generator::config::Config;
+use rs_cdr_generator::reader::open_cdr_reader;
+use tempfile::TempDir;
+
+#[test]
+fn test_reads_every_compression_of_a_generated_day() -> anyhow::Result<()> {
+    // Codecs whose cargo feature is off are skipped
+    let compressions = ["none", "gzip", "zstd"].into_iter().filter(|c| CompressionType::from_str(c).is_some_and(|c| c.is_available()));
+    for compression in compressions {
+        let dir = TempDir::new()?;
+        let cfg = Config {
+            subscribers: 300,
+            workers: 2,
+            compression_type: compression.to_string(),
+            rotate_bytes: 64 * 1024,
+            gzip_member_flush_bytes: 16 * 1024,
+            ..Config::default()
+        };
+        let mut generator = DayGenerator::builder().config(cfg).out_dir(dir.path()).bundle(false).build()?;
+        let summary = generator.generate(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap())?;
+
+        let (mut parts, mut calls, mut sms, mut data) = (0, 0, 0, 0);
+        for entry in std::fs::read_dir(dir.path().join("2025-01-01"))? {
+            let path = entry?.path();
+            if !path.file_name().unwrap().to_string_lossy().starts_with("cdr_") {
+                continue;
+            }
+            parts += 1;
+            for row in open_cdr_reader(&path)? {
+                let row = row?;
+                assert!(row.msisdn_src > 0 && !row.tz_name.is_empty(), "{}: {:?}", compression, row);
+                match row.event_type.as_str() {
+                    "CALL" => calls += 1,
+                    "SMS" => sms += 1,
+                    "DATA" => data += 1,
+                    other => panic!("{}: unexpected event_type {:?}", compression, other),
+                }
+            }
+        }
+        assert!(parts > 2, "{}: {} part files", compression, parts);
+        assert_eq!((calls, sms, data), (summary.total_calls, summary.total_sms, summary.total_data), "{}", compression);
+    }
+    Ok(())
+}
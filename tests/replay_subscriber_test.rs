@@ -0,0 +1,98 @@
+// Integration test for replay-subscriber: one subscriber's rows regenerated alone match the full-day run
+use std::process::Command;
+use tempfile::TempDir;
+
+mod common;
+
+const RUN_ARGS: [&str; 6] = ["--workers", "2", "--prefixes", "31612", "--seed", "9"];
+
+fn stdout_of(cmd: &mut Command) -> String {
+    let output = cmd.output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Data rows of `csv` with `msisdn` in one of its MSISDN columns
+fn rows_of(csv: &str, msisdn: u64) -> Vec<String> {
+    let mut lines = csv.lines();
+    let header: Vec<&str> = lines.next().unwrap().split(';').collect();
+    let src = header.iter().position(|&column| column == "msisdn_src").unwrap();
+    let dst = header.iter().position(|&column| column == "msisdn_dst").unwrap();
+    let msisdn = msisdn.to_string();
+    lines
+        .filter(|line| {
+            let fields: Vec<&str> = line.split(';').collect();
+            fields[src] == msisdn || fields[dst] == msisdn
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn test_replay_matches_full_day_rows() {
+    let dir = TempDir::new().unwrap();
+    let db_path = dir.path().join("subs.redb");
+    // The workers' index-derived MSISDNs, all in the database
+    common::build_indexed_db(&db_path, 31612, 0..200);
+
+    let out_dir = dir.path().join("out");
+    stdout_of(
+        common::cli("warn")
+            .arg("generate-cdr")
+            .arg("--subscriber-db")
+            .arg(&db_path)
+            .arg("--out")
+            .arg(&out_dir)
+            .args(["--compression", "none"])
+            .args(RUN_ARGS),
+    );
+    let bundle = std::fs::read_to_string(out_dir.join("cdr_2025-01-01.csv")).unwrap();
+
+    // One subscriber of each worker range
+    for msisdn in [316_120_000_007u64, 316_120_000_150] {
+        let replay = stdout_of(
+            common::cli("warn")
+                .arg("replay-subscriber")
+                .arg("--subscriber-db")
+                .arg(&db_path)
+                .arg("--out")
+                .arg(&out_dir)
+                .args(["--date", "2025-01-01", "--msisdn", &msisdn.to_string()])
+                .args(RUN_ARGS),
+        );
+        let expected = rows_of(&bundle, msisdn);
+        assert!(!expected.is_empty(), "{} has no rows in the full-day run", msisdn);
+        assert_eq!(replay.lines().next(), bundle.lines().next(), "same header");
+        assert_eq!(rows_of(&replay, msisdn), expected, "rows of {}", msisdn);
+    }
+
+    // The run's seed from a config file, without --seed
+    let config_path = dir.path().join("seed.yaml");
+    std::fs::write(&config_path, "seed: 9\n").unwrap();
+    let replay = stdout_of(
+        common::cli("warn")
+            .arg("replay-subscriber")
+            .arg("--subscriber-db")
+            .arg(&db_path)
+            .arg("--out")
+            .arg(&out_dir)
+            .arg("--config")
+            .arg(&config_path)
+            .args(["--date", "2025-01-01", "--msisdn", "316120000007"])
+            .args(&RUN_ARGS[..4]),
+    );
+    assert_eq!(rows_of(&replay, 316_120_000_007), rows_of(&bundle, 316_120_000_007), "replay with the YAML seed");
+
+    // Not a subscriber of the run
+    let output = common::cli("warn")
+        .arg("replay-subscriber")
+        .arg("--subscriber-db")
+        .arg(&db_path)
+        .arg("--out")
+        .arg(&out_dir)
+        .args(["--date", "2025-01-01", "--msisdn", "316120009999"])
+        .args(RUN_ARGS)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
@@ -0,0 +1,78 @@
+// Integration tests for per-event-type rotation: each event type gets its own part files and limits
+use chrono::NaiveDate;
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::config::{Config, RotationPolicy};
+use rs_cdr_generator::writer::ShardManifest;
+use std::collections::BTreeMap;
+use tempfile::TempDir;
+
+const CALL_BYTES: u64 = 50_000;
+const DATA_BYTES: u64 = 500_000;
+
+#[test]
+fn test_voice_and_data_parts_rotate_independently() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let cfg = Config {
+        subscribers: 2_000,
+        workers: 1,
+        compression_type: "none".to_string(),
+        rotate_bytes: 1_000_000,
+        avg_data_sessions_per_user: 20.0,
+        rotation: BTreeMap::from([
+            ("CALL".to_string(), RotationPolicy { bytes: CALL_BYTES, ..RotationPolicy::default() }),
+            ("DATA".to_string(), RotationPolicy { bytes: DATA_BYTES, ..RotationPolicy::default() }),
+        ]),
+        ..Config::default()
+    };
+    let mut generator = DayGenerator::builder().config(cfg).out_dir(temp_dir.path()).bundle(false).build()?;
+    generator.generate(NaiveDate::from_ymd_opt(2025, 3, 10).unwrap())?;
+
+    let day_dir = temp_dir.path().join("2025-03-10");
+    let manifest: ShardManifest = serde_json::from_str(&std::fs::read_to_string(day_dir.join("manifest_writer000.json"))?)?;
+    assert_eq!(manifest.rotation["CALL"].bytes, CALL_BYTES);
+    assert_eq!(manifest.rotation["DATA"].bytes, DATA_BYTES);
+    // Event types without a policy of their own fall back to rotate_bytes
+    assert_eq!(manifest.rotation["SMS"].bytes, 1_000_000);
+
+    let sizes = |event_type: &str| -> Vec<u64> {
+        manifest.files.iter().filter(|e| e.event_type.as_deref() == Some(event_type)).map(|e| e.bytes).collect()
+    };
+    let (calls, data) = (sizes("CALL"), sizes("DATA"));
+    assert!(calls.len() > 1, "voice never rotated: {:?}", calls);
+    assert!(data.len() > 1, "data never rotated: {:?}", data);
+
+    // A part closes on the row that crosses its limit, so it overshoots by at most a row or a buffer
+    assert!(calls.iter().all(|&b| b <= CALL_BYTES + CALL_BYTES / 10), "voice part over limit: {:?}", calls);
+    let largest_data = data.iter().copied().max().unwrap();
+    assert!((DATA_BYTES * 9 / 10..=DATA_BYTES + DATA_BYTES / 10).contains(&largest_data), "data parts: {:?}", data);
+
+    for entry in &manifest.files {
+        let event_type = entry.event_type.as_deref().unwrap();
+        assert!(entry.file.contains(&format!("_{}_part", event_type.to_lowercase())), "{}", entry.file);
+        let mut reader = csv::ReaderBuilder::new().delimiter(b';').from_path(day_dir.join(&entry.file))?;
+        for row in reader.records() {
+            assert_eq!(&row?[0], event_type);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_single_stream_without_rotation_map() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let cfg = Config {
+        subscribers: 100,
+        workers: 1,
+        compression_type: "none".to_string(),
+        ..Config::default()
+    };
+    let mut generator = DayGenerator::builder().config(cfg).out_dir(temp_dir.path()).bundle(false).build()?;
+    generator.generate(NaiveDate::from_ymd_opt(2025, 3, 10).unwrap())?;
+
+    let day_dir = temp_dir.path().join("2025-03-10");
+    let manifest: ShardManifest = serde_json::from_str(&std::fs::read_to_string(day_dir.join("manifest_writer000.json"))?)?;
+    assert_eq!(manifest.rotation.keys().collect::<Vec<_>>(), ["*"]);
+    assert_eq!(manifest.files[0].file, "cdr_2025-03-10_writer000_part001.csv");
+    assert!(manifest.files.iter().all(|e| e.event_type.is_none()));
+    Ok(())
+}
@@ -0,0 +1,114 @@
+// Integration test for generate-cdr --sample: a deterministic subset of the full run, written to out/sample
+use chrono::NaiveDate;
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::config::Config;
+use std::collections::HashSet;
+use std::path::Path;
+use tempfile::TempDir;
+
+mod common;
+
+#[cfg(all(feature = "arrow", feature = "redb", feature = "async", feature = "zstd"))]
+fn generate_cdr(db_path: &Path, out_dir: &Path, args: &[&str]) -> std::process::Output {
+    common::cli("info")
+        .arg("generate-cdr")
+        .arg("--subscriber-db")
+        .arg(db_path)
+        .arg("--out")
+        .arg(out_dir)
+        .args(["--workers", "2", "--compression", "none"])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// Distinct subscribers (`msisdn_src`) of DATA events in the day folder
+fn data_subscribers(day_dir: &Path) -> HashSet<String> {
+    let mut msisdns = HashSet::new();
+    for entry in std::fs::read_dir(day_dir).unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        if !(name.starts_with("cdr_") && name.ends_with(".csv")) {
+            continue;
+        }
+        let mut reader = csv::ReaderBuilder::new().delimiter(b';').from_path(&path).unwrap();
+        let headers = reader.headers().unwrap().clone();
+        let column = |name: &str| headers.iter().position(|h| h == name).unwrap();
+        let (event_type, src) = (column("event_type"), column("msisdn_src"));
+        for record in reader.records() {
+            let record = record.unwrap();
+            if &record[event_type] == "DATA" {
+                msisdns.insert(record[src].to_string());
+            }
+        }
+    }
+    msisdns
+}
+
+#[test]
+fn test_sample_is_subset_of_full_run() -> anyhow::Result<()> {
+    let dir = TempDir::new()?;
+    let run = |out_dir: &Path, sample_stride: usize| -> anyhow::Result<HashSet<String>> {
+        let cfg = Config {
+            subscribers: 2000,
+            workers: 2,
+            compression_type: "none".to_string(),
+            sample_stride,
+            ..Config::default()
+        };
+        let mut generator = DayGenerator::builder().config(cfg).out_dir(out_dir).bundle(false).build()?;
+        generator.generate(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap())?;
+        Ok(data_subscribers(&out_dir.join("2025-01-01")))
+    };
+
+    let full = run(&dir.path().join("full"), 1)?;
+    let sampled = run(&dir.path().join("sample"), 10)?;
+    assert!(sampled.is_subset(&full), "sampled subscribers missing from the full run");
+    let share = sampled.len() as f64 / full.len() as f64;
+    assert!((0.08..0.12).contains(&share), "sampled {} of {}", sampled.len(), full.len());
+    Ok(())
+}
+
+#[cfg(all(feature = "arrow", feature = "redb", feature = "async", feature = "zstd"))]
+#[test]
+fn test_cli_sample_writes_apart_and_extrapolates() {
+    let dir = TempDir::new().unwrap();
+    let db_path = dir.path().join("subs.redb");
+    let status = common::cli("error")
+        .arg("generate-subscribers")
+        .arg("--output")
+        .arg(&db_path)
+        .args(["--size", "200", "--history-days", "30"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let out_dir = dir.path().join("out");
+    let output = generate_cdr(&db_path, &out_dir, &["--sample", "0.01"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(out_dir.join("sample").join("2025-01-01").join("summary.json").exists());
+    assert!(!out_dir.join("2025-01-01").exists());
+
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert!(log.contains("Sample 1/100:"), "{}", log);
+    assert!(log.contains("Estimated full run from an effective fraction of 0.0100:"), "{}", log);
+    assert!(!log.contains("keeps 1 in"), "{}", log);
+
+    // Every 3rd subscriber is not 30%: the fraction actually sampled is logged next to the estimate
+    let output = generate_cdr(&db_path, &dir.path().join("out_03"), &["--sample", "0.3"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert!(log.contains("--sample 0.3 keeps 1 in 3 subscribers, an effective fraction of 0.3333"), "{}", log);
+    assert!(log.contains("Estimated full run from an effective fraction of 0.3333:"), "{}", log);
+}
+
+#[cfg(all(feature = "arrow", feature = "redb", feature = "async", feature = "zstd"))]
+#[test]
+fn test_sample_fraction_out_of_range_is_rejected() {
+    let dir = TempDir::new().unwrap();
+    let db_path = dir.path().join("subs.redb");
+    std::fs::write(&db_path, b"").unwrap();
+    let output = generate_cdr(&db_path, &dir.path().join("out"), &["--sample", "1.5"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--sample must be in (0, 1]"));
+}
@@ -0,0 +1,124 @@
+// Integration tests for schema.json and the schema subcommand
+use chrono::NaiveDate;
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::config::Config;
+use rs_cdr_generator::schema::OutputSchema;
+use rs_cdr_generator::writer::ShardManifest;
+use std::path::Path;
+use tempfile::TempDir;
+
+fn config() -> Config {
+    Config {
+        subscribers: 300,
+        workers: 2,
+        compression_type: "none".to_string(),
+        emit_imeisv: true,
+        emit_prev_event_ts: true,
+        emit_record_seq: true,
+        sms_unreachable_rate: 0.3,
+        ..Config::default()
+    }
+}
+
+fn read_schema(out_dir: &Path) -> OutputSchema {
+    serde_json::from_str(&std::fs::read_to_string(out_dir.join("schema.json")).unwrap()).unwrap()
+}
+
+#[test]
+fn test_schema_json_describes_part_files() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let mut generator = DayGenerator::builder().config(config()).out_dir(temp_dir.path()).bundle(false).build()?;
+    generator.write_schema()?;
+    generator.generate(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap())?;
+
+    let schema = read_schema(temp_dir.path());
+    let names: Vec<&str> = schema.columns.iter().map(|c| c.name.as_str()).collect();
+    assert!(names.contains(&"imeisv") && names.contains(&"correlation_id") && names.contains(&"record_seq"));
+
+    let day_dir = temp_dir.path().join("2025-01-01");
+    let mut rows = 0;
+    for entry in std::fs::read_dir(&day_dir)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        if name.starts_with("manifest_writer") {
+            // Every manifest carries the same column definitions
+            let manifest: ShardManifest = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+            assert_eq!(manifest.schema, schema.columns);
+            continue;
+        }
+        if !name.starts_with("cdr_") {
+            continue;
+        }
+
+        let content = std::fs::read_to_string(&path)?;
+        let mut lines = content.lines();
+        assert_eq!(lines.next().unwrap().split(';').collect::<Vec<_>>(), names);
+        for line in lines {
+            let cells: Vec<&str> = line.split(';').collect();
+            assert_eq!(cells.len(), schema.columns.len());
+            let event_type = cells[0];
+            for (cell, column) in cells.iter().zip(&schema.columns) {
+                // Columns of other event types stay empty, non-nullable ones are always filled
+                if !column.event_types.iter().any(|t| t == event_type) {
+                    assert!(cell.is_empty(), "{} on {}: {:?}", column.name, event_type, cell);
+                }
+                if !column.nullable {
+                    assert!(!cell.is_empty(), "{} empty on {}", column.name, event_type);
+                }
+            }
+            rows += 1;
+        }
+    }
+    assert!(rows > 0);
+    Ok(())
+}
+
+#[test]
+fn test_schema_follows_config() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let cfg = Config {
+        emit_timezone_columns: false,
+        ..config()
+    };
+    let generator = DayGenerator::builder().config(cfg).out_dir(temp_dir.path()).build()?;
+
+    // Only written on request, and then before any day
+    assert!(!temp_dir.path().join("schema.json").exists());
+    assert_eq!(generator.write_schema()?, temp_dir.path().join("schema.json"));
+    let schema = read_schema(temp_dir.path());
+    assert!(!schema.columns.iter().any(|c| c.name == "tz_name" || c.name == "tz_offset_min"));
+    assert_eq!(schema.delimiter, ";");
+    Ok(())
+}
+
+/// Runs the CLI binary, which is only built with every optional feature
+#[cfg(all(feature = "arrow", feature = "redb", feature = "async", feature = "zstd"))]
+#[test]
+fn test_schema_subcommand() -> anyhow::Result<()> {
+    use std::process::Command;
+
+    let temp_dir = TempDir::new()?;
+    let config_path = temp_dir.path().join("config.yaml");
+    std::fs::write(&config_path, "emit_imeisv: true\nmsisdn_format: e164_plus\n")?;
+    let schema = |format: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_rs_cdr_generator"))
+            .env("RUST_LOG", "warn")
+            .args(["schema", "--format", format, "--config"])
+            .arg(&config_path)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let json: OutputSchema = serde_json::from_str(&schema("json"))?;
+    let imeisv = json.columns.iter().find(|c| c.name == "imeisv").unwrap();
+    assert_eq!(imeisv.event_types, ["DATA"]);
+    assert!(json.columns[1].example.starts_with('+'));
+
+    let markdown = schema("markdown");
+    for column in &json.columns {
+        assert!(markdown.contains(&format!("| `{}` |", column.name)), "{} missing", column.name);
+    }
+    Ok(())
+}
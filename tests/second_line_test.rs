@@ -0,0 +1,111 @@
+// Integration test for second lines: a released number reassigned to an existing customer's IMSI, which then
+// serves CDRs on both of its numbers
+#![cfg(feature = "redb")]
+use chrono::NaiveDate;
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::config::Config;
+use rs_cdr_generator::identity::SubscriberIdentity;
+use rs_cdr_generator::subscriber_db_generator::{generate_database, write_database_redb, GeneratorConfig};
+use rs_cdr_generator::subscriber_db_redb::{DbWriteOptions, SubscriberDbRedb};
+use rs_cdr_generator::writer::EventRow;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+
+#[test]
+fn test_dual_line_imsi_serves_cdrs_on_both_numbers() {
+    let dir = TempDir::new().unwrap();
+    // 120 days from 2024-01-01; every reassigned number becomes a second line
+    let config = GeneratorConfig {
+        initial_subscribers: 400,
+        history_days: 120,
+        device_change_rate: 0.3,
+        number_release_rate: 0.5,
+        cooldown_days: 10,
+        reassign_to_existing_rate: 1.0,
+        prefixes: vec!["31612".to_string()],
+        mccmnc_pool: vec!["20408".to_string()],
+        ..GeneratorConfig::default()
+    };
+    let db_path = dir.path().join("subs.redb");
+    write_database_redb(generate_database(&config).unwrap(), &db_path, &DbWriteOptions::default()).unwrap();
+
+    // Lines still open at the end of the history, by IMSI
+    let mut lines: BTreeMap<u64, Vec<SubscriberIdentity>> = BTreeMap::new();
+    let redb = SubscriberDbRedb::open(&db_path).unwrap();
+    redb.validate(1).unwrap();
+    redb.iter_all(|_, snapshots| {
+        for snapshot in snapshots.into_iter().filter(|s| s.valid_to.is_none()) {
+            lines.entry(snapshot.identity.imsi.get()).or_default().push(snapshot.identity);
+        }
+        Ok(())
+    })
+    .unwrap();
+    drop(redb);
+    let dual: Vec<&Vec<SubscriberIdentity>> = lines.values().filter(|identities| identities.len() > 1).collect();
+    assert!(dual.len() > 5, "{} IMSIs with several lines", dual.len());
+
+    // Targeted run over every open line
+    let mut list = std::fs::File::create(dir.path().join("msisdns.txt")).unwrap();
+    for identity in lines.values().flatten() {
+        writeln!(list, "{}", identity.msisdn).unwrap();
+    }
+    let cfg = Config {
+        prefixes: vec!["31612".to_string()],
+        workers: 2,
+        daily_inactive_rate: 0.0,
+        avg_calls_per_user: 6.0,
+        avg_sms_per_user: 6.0,
+        msisdn_file: Some(dir.path().join("msisdns.txt")),
+        ..Config::default()
+    };
+    let generate = |cfg: Config| {
+        let rows: Arc<Mutex<Vec<EventRow>>> = Arc::default();
+        let sink_rows = rows.clone();
+        DayGenerator::builder()
+            .config(cfg)
+            .subscriber_db(db_path.as_path())
+            .sink(move |batch| sink_rows.lock().unwrap().extend(batch.events))
+            .build()
+            .unwrap()
+            .generate(NaiveDate::from_ymd_opt(2024, 6, 1).unwrap())
+            .unwrap();
+        let rows = rows.lock().unwrap().clone();
+        rows
+    };
+    let rows = generate(cfg.clone());
+
+    // Each served number carries the IMSI and device of its line, so both lines of a dual-line IMSI share them
+    let mut served: HashMap<u64, (u64, u64)> = HashMap::new();
+    for row in rows.iter().filter(|row| row.direction != "MT") {
+        let identity = (row.imsi, row.imei);
+        assert_eq!(*served.entry(row.msisdn_src).or_insert(identity), identity, "MSISDN {}", row.msisdn_src);
+    }
+    for &identities in &dual {
+        assert!(identities.iter().all(|identity| identity.imei == identities[0].imei), "{:?}", identities);
+        for identity in identities {
+            let expected = (identity.imsi.get(), identity.imei.get());
+            assert_eq!(served.get(&identity.msisdn.get()), Some(&expected), "{:?}", identity);
+        }
+    }
+    // One worker serves both lines of every IMSI, so their calls are scheduled together and never overlap
+    let rows = generate(Config { workers: 1, avg_calls_per_user: 30.0, ..cfg });
+    let mut calls: HashMap<u64, Vec<(i64, i64, [u64; 2])>> = HashMap::new();
+    for row in rows.iter().filter(|row| row.event_type == "CALL") {
+        calls.entry(row.imsi).or_default().push((row.start_ts_ms, row.end_ts_ms, [row.msisdn_src, row.msisdn_dst]));
+    }
+    let mut both_lines_called = 0;
+    for &identities in &dual {
+        let imsi_calls = calls.get(&identities[0].imsi.get()).map_or(&[][..], Vec::as_slice);
+        both_lines_called += identities
+            .iter()
+            .all(|identity| imsi_calls.iter().any(|call| call.2.contains(&identity.msisdn.get())))
+            as usize;
+    }
+    assert!(both_lines_called > 0, "no dual-line IMSI called on both numbers");
+    for (imsi, imsi_calls) in &mut calls {
+        imsi_calls.sort();
+        assert!(imsi_calls.windows(2).all(|pair| pair[0].1 < pair[1].0), "IMSI {} has overlapping calls", imsi);
+    }
+}
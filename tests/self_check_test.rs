@@ -0,0 +1,97 @@
+// Integration tests for self_check: generated rows pass the inline validator in strict mode
+use chrono::NaiveDate;
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::config::{Config, OverwritePolicy, SelfCheckMode};
+use tempfile::TempDir;
+
+mod common;
+
+fn config() -> Config {
+    Config {
+        subscribers: 500,
+        workers: 3,
+        compression_type: "none".to_string(),
+        callback_prob: 0.5,
+        sms_unreachable_rate: 0.2,
+        prefix_operator_map: [("31612".to_string(), "20416".to_string())].into(),
+        self_check: SelfCheckMode::Strict,
+        ..Config::default()
+    }
+}
+
+#[test]
+fn test_random_subscribers_pass_strict_self_check() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let mut generator = DayGenerator::builder().config(config()).out_dir(temp_dir.path()).build()?;
+    let summary = generator.generate(NaiveDate::from_ymd_opt(2025, 3, 30).unwrap())?;
+
+    let rows = summary.total_calls + summary.total_sms + summary.sms_retry_rows + summary.total_data;
+    assert_eq!(summary.self_check_rows, rows);
+    assert_eq!(summary.self_check_violations, 0);
+    Ok(())
+}
+
+#[test]
+fn test_self_check_samples_and_stays_off_by_default() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let cfg = Config {
+        self_check_sample: 10,
+        ..config()
+    };
+    let summary = DayGenerator::builder().config(cfg).out_dir(temp_dir.path()).build()?.generate(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap())?;
+    let rows = summary.total_calls + summary.total_sms + summary.sms_retry_rows + summary.total_data;
+    // 1 in 10 of each shard's rows, rounded up per shard
+    assert!(summary.self_check_rows >= rows / 10 && summary.self_check_rows <= rows / 10 + 3);
+
+    let cfg = Config {
+        self_check: SelfCheckMode::Off,
+        // Same day directory as the run above
+        overwrite_policy: OverwritePolicy::Clean,
+        ..config()
+    };
+    let summary = DayGenerator::builder().config(cfg).out_dir(temp_dir.path()).build()?.generate(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap())?;
+    assert_eq!(summary.self_check_rows, 0);
+    Ok(())
+}
+
+/// Runs the CLI binary, which is only built with every optional feature
+#[cfg(all(feature = "arrow", feature = "redb", feature = "async", feature = "zstd"))]
+#[test]
+fn test_generate_cdr_self_check_strict() -> anyhow::Result<()> {
+    use rs_cdr_generator::utils::DailySummary;
+    use std::process::Command;
+
+    let temp_dir = TempDir::new()?;
+    let db_path = temp_dir.path().join("subs.redb");
+    let out = temp_dir.path().join("out");
+    let cli = || {
+        let mut cmd = Command::new(env!("CARGO_BIN_EXE_rs_cdr_generator"));
+        cmd.env("RUST_LOG", "warn");
+        cmd
+    };
+
+    // The worker's index-derived MSISDNs, all in the database
+    common::build_indexed_db(&db_path, 31612, 0..300);
+
+    let output = cli()
+        .args(["generate-cdr", "--self-check=strict", "--workers", "2", "--compression", "none", "--prefixes", "31612", "--subscriber-db"])
+        .arg(&db_path)
+        .arg("--out")
+        .arg(&out)
+        .output()?;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let summary: DailySummary = serde_json::from_str(&std::fs::read_to_string(out.join("2025-01-01").join("summary.json"))?)?;
+    assert!(summary.self_check_rows > 0);
+    assert_eq!(summary.self_check_violations, 0);
+
+    let output = cli()
+        .args(["generate-cdr", "--self-check=sometimes", "--subscriber-db"])
+        .arg(&db_path)
+        .arg("--out")
+        .arg(&out)
+        .output()?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--self-check"));
+    Ok(())
+}
@@ -0,0 +1,145 @@
+// Integration tests for sms_unreachable_rate: MT SMS to powered-off handsets become SMSC retry chains
+use chrono::NaiveDate;
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::config::Config;
+use rs_cdr_generator::reader::open_cdr_reader;
+use rs_cdr_generator::writer::EventRow;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+
+const RATE: f64 = 0.3;
+
+fn date() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2025, 3, 10).unwrap()
+}
+
+fn config() -> Config {
+    Config {
+        subscribers: 2_000,
+        workers: 2,
+        avg_sms_per_user: 10.0,
+        sms_unreachable_rate: RATE,
+        ..Config::default()
+    }
+}
+
+#[test]
+fn test_retry_chains_follow_schedule() -> anyhow::Result<()> {
+    let rows: Arc<Mutex<Vec<EventRow>>> = Arc::default();
+    let sink_rows = rows.clone();
+    let cfg = config();
+    let schedule_ms: Vec<i64> = cfg.sms_retry_schedule_min.iter().map(|&m| m as i64 * 60_000).collect();
+    let mut generator = DayGenerator::builder()
+        .config(cfg)
+        .sink(move |batch| sink_rows.lock().unwrap().extend(batch.events))
+        .build()?;
+    let summary = generator.generate(date())?;
+    let rows = rows.lock().unwrap();
+
+    let sms: Vec<&EventRow> = rows.iter().filter(|r| r.event_type == "SMS").collect();
+    assert_eq!(sms.len(), summary.total_sms + summary.sms_retry_rows);
+    assert!(sms.iter().all(|r| r.correlation_id != 0), "SMS row without a correlation id");
+    assert!(rows.iter().filter(|r| r.event_type != "SMS").all(|r| r.correlation_id == 0));
+
+    let mut by_id: HashMap<u64, Vec<&EventRow>> = HashMap::new();
+    for row in &sms {
+        by_id.entry(row.correlation_id).or_default().push(row);
+    }
+    assert_eq!(by_id.len(), summary.total_sms);
+
+    let mut mt_messages = 0;
+    let mut chains = 0;
+    let (mut delivered, mut expired) = (0, 0);
+    for chain in by_id.values_mut() {
+        chain.sort_by_key(|r| r.start_ts_ms);
+        mt_messages += (chain[0].direction == "MT") as usize;
+        if chain.len() == 1 {
+            assert_ne!(chain[0].sms_status, "ATTEMPT");
+            continue;
+        }
+        chains += 1;
+        let first = chain[0];
+        assert_eq!(first.direction, "MT");
+        for (idx, row) in chain.iter().enumerate() {
+            assert_eq!((row.msisdn_src, row.msisdn_dst, row.imsi), (first.msisdn_src, first.msisdn_dst, first.imsi));
+            assert_eq!(row.sms_segments, first.sms_segments);
+            assert_eq!(row.start_ts_ms - first.start_ts_ms, schedule_ms[idx]);
+            if idx + 1 < chain.len() {
+                assert_eq!(row.sms_status, "ATTEMPT");
+            }
+        }
+        match chain.last().unwrap().sms_status {
+            "DELIVERED" => delivered += 1,
+            "EXPIRED" => {
+                assert_eq!(chain.len(), schedule_ms.len());
+                expired += 1;
+            }
+            status => panic!("chain ends with {}", status),
+        }
+    }
+
+    // The summary counts retried messages and their extra rows apart from total_sms
+    assert_eq!(chains, summary.sms_unreachable);
+    assert_eq!(sms.len() - by_id.len(), summary.sms_retry_rows);
+    let rate = chains as f64 / mt_messages as f64;
+    assert!((rate - RATE).abs() < 0.03, "unreachable rate {}", rate);
+    assert!(delivered > 0 && expired > 0, "delivered {} expired {}", delivered, expired);
+    Ok(())
+}
+
+#[test]
+fn test_correlation_id_column_in_part_files() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let cfg = Config {
+        subscribers: 200,
+        compression_type: "none".to_string(),
+        ..config()
+    };
+    let mut generator = DayGenerator::builder().config(cfg).out_dir(temp_dir.path()).bundle(false).build()?;
+    let summary = generator.generate(date())?;
+    assert!(summary.sms_unreachable > 0);
+
+    let day_dir = temp_dir.path().join("2025-03-10");
+    let mut sms_rows = 0;
+    for entry in std::fs::read_dir(&day_dir)? {
+        let path = entry?.path();
+        if !path.file_name().unwrap().to_string_lossy().starts_with("cdr_") {
+            continue;
+        }
+        let mut reader = csv::ReaderBuilder::new().delimiter(b';').from_path(&path)?;
+        let headers = reader.headers()?.clone();
+        let col = headers.iter().position(|h| h == "correlation_id").expect("correlation_id column");
+        for record in reader.records() {
+            let record = record?;
+            // Empty on CALL and DATA rows, a number on SMS rows
+            assert_eq!(record[col].is_empty(), &record[0] != "SMS", "{:?}", record);
+        }
+        for row in open_cdr_reader(&path)? {
+            let row = row?;
+            sms_rows += (row.event_type == "SMS") as usize;
+            assert_eq!(row.correlation_id == 0, row.event_type != "SMS");
+        }
+    }
+    assert_eq!(sms_rows, summary.total_sms + summary.sms_retry_rows);
+    Ok(())
+}
+
+#[test]
+fn test_no_correlation_id_by_default() -> anyhow::Result<()> {
+    let temp_dir = TempDir::new()?;
+    let cfg = Config {
+        subscribers: 100,
+        workers: 1,
+        compression_type: "none".to_string(),
+        ..Config::default()
+    };
+    let mut generator = DayGenerator::builder().config(cfg).out_dir(temp_dir.path()).bundle(false).build()?;
+    let summary = generator.generate(date())?;
+    assert_eq!((summary.sms_unreachable, summary.sms_retry_rows), (0, 0));
+
+    let part = temp_dir.path().join("2025-03-10").join("cdr_2025-03-10_writer000_part001.csv");
+    let mut reader = csv::ReaderBuilder::new().delimiter(b';').from_path(part)?;
+    assert!(!reader.headers()?.iter().any(|h| h == "correlation_id"));
+    Ok(())
+}
@@ -0,0 +1,105 @@
+// Integration tests for sparse_threshold: IoT-like traffic drawn as range totals matches the per-subscriber draws
+use chrono::NaiveDate;
+use rs_cdr_generator::api::{DayGenerator, DayGeneratorBuilder, DaySummary};
+use rs_cdr_generator::config::Config;
+use rs_cdr_generator::writer::EventRow;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+mod common;
+
+const SUBSCRIBERS: usize = 40_000;
+
+fn date() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2025, 1, 1).unwrap()
+}
+
+/// Well below the default threshold of 0.1 events per subscriber and type
+fn config(sparse_threshold: f64) -> Config {
+    Config {
+        subscribers: SUBSCRIBERS,
+        workers: 4,
+        prefixes: vec!["31612".to_string()],
+        avg_calls_per_user: 0.02,
+        avg_sms_per_user: 0.03,
+        avg_data_sessions_per_user: 0.05,
+        sparse_threshold,
+        ..Config::default()
+    }
+}
+
+/// Summary and rows of one day
+fn generate(builder: DayGeneratorBuilder) -> (DaySummary, Vec<EventRow>) {
+    let rows: Arc<Mutex<Vec<EventRow>>> = Arc::default();
+    let sink_rows = rows.clone();
+    let summary = builder
+        .sink(move |batch| sink_rows.lock().unwrap().extend(batch.events))
+        .build()
+        .unwrap()
+        .generate(date())
+        .unwrap();
+    let rows = std::mem::take(&mut *rows.lock().unwrap());
+    (summary, rows)
+}
+
+/// `a` and `b` within 5 standard deviations of the difference of two Poisson counts
+fn assert_close(name: &str, a: usize, b: usize) {
+    let tolerance = 5.0 * ((a + b) as f64).sqrt() + 5.0;
+    assert!((a as f64 - b as f64).abs() <= tolerance, "{}: {} vs {} (tolerance {:.0})", name, a, b, tolerance);
+}
+
+/// Subscribers by their number of DATA sessions (0 = none, 2 = two or more), out of `SUBSCRIBERS`
+fn data_histogram(rows: &[EventRow]) -> [usize; 3] {
+    let mut per_subscriber: HashMap<u64, usize> = HashMap::new();
+    for row in rows.iter().filter(|row| row.event_type == "DATA") {
+        *per_subscriber.entry(row.msisdn_src).or_default() += 1;
+    }
+    let mut histogram = [0; 3];
+    for &count in per_subscriber.values() {
+        histogram[count.min(2)] += 1;
+    }
+    histogram[0] = SUBSCRIBERS - histogram[1] - histogram[2];
+    histogram
+}
+
+#[test]
+fn test_sparse_matches_dense_distribution() {
+    let (dense, dense_rows) = generate(DayGenerator::builder().config(config(0.0)));
+    let (sparse, sparse_rows) = generate(DayGenerator::builder().config(config(0.1)));
+
+    assert!(dense.total_data > 1_000, "{}", dense.total_data);
+    assert_close("calls", dense.total_calls, sparse.total_calls);
+    assert_close("sms", dense.total_sms, sparse.total_sms);
+    assert_close("data", dense.total_data, sparse.total_data);
+
+    // Subscribers with one and with several sessions in the same proportions
+    let (dense_hist, sparse_hist) = (data_histogram(&dense_rows), data_histogram(&sparse_rows));
+    assert_close("one session", dense_hist[1], sparse_hist[1]);
+    assert_close("several sessions", dense_hist[2], sparse_hist[2]);
+
+    // Only subscribers with events are visited
+    assert_eq!(dense.active_subscribers + dense.inactive_subscribers, SUBSCRIBERS);
+    assert!(sparse.active_subscribers + sparse.inactive_subscribers < SUBSCRIBERS / 5);
+    assert!(sparse.active_subscribers >= sparse_hist[1] + sparse_hist[2]);
+}
+
+#[cfg(feature = "redb")]
+#[test]
+fn test_sparse_fetches_database_subscribers_on_demand() {
+    // Index-derived MSISDNs, every second one missing from the database
+    let dir = tempfile::TempDir::new().unwrap();
+    let db_path = dir.path().join("subs.redb");
+    common::build_indexed_db(&db_path, 31612, (0..SUBSCRIBERS as u64).step_by(2));
+
+    let builder = |sparse_threshold| DayGenerator::builder().config(config(sparse_threshold)).subscriber_db(db_path.as_path());
+    let (dense, _) = generate(builder(0.0));
+    let (sparse, sparse_rows) = generate(builder(0.1));
+
+    assert!(sparse.total_data > 500, "{}", sparse.total_data);
+    assert_close("calls", dense.total_calls, sparse.total_calls);
+    assert_close("data", dense.total_data, sparse.total_data);
+    // Missing subscribers have no events; about half of the visited ones are missing
+    assert!(sparse_rows.iter().filter(|row| row.event_type == "DATA").all(|row| row.msisdn_src % 2 == 0));
+    assert!(sparse.skipped_subscribers > 0);
+    assert!((sparse.miss_rate() - 0.5).abs() < 0.1, "{}", sparse.miss_rate());
+}
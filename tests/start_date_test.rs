@@ -0,0 +1,84 @@
+// Integration test for generate-subscribers --start-date: the database history and the CDR days line up
+use chrono::NaiveDate;
+use rs_cdr_generator::subscriber_db_redb::SubscriberDbRedb;
+use std::path::Path;
+use tempfile::TempDir;
+
+mod common;
+
+fn generate_subscribers(db_path: &Path, extra_args: &[&str]) {
+    let status = common::cli("warn")
+        .arg("generate-subscribers")
+        .arg("--output")
+        .arg(db_path)
+        .args(["--size", "300", "--history-days", "60"])
+        .args(extra_args)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+fn utc_ms(y: i32, m: u32, d: u32) -> i64 {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap().and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp_millis()
+}
+
+/// MSISDNs with a snapshot valid at `ts_ms`
+fn resolved_at(db_path: &Path, ts_ms: i64) -> usize {
+    let db = SubscriberDbRedb::open(db_path).unwrap();
+    let mut resolved = 0;
+    db.iter_all(|_, snapshots| {
+        resolved += usize::from(SubscriberDbRedb::find_snapshot_at(&snapshots, ts_ms).is_some());
+        Ok(())
+    })
+    .unwrap();
+    resolved
+}
+
+#[test]
+fn test_start_date_moves_history_and_cdr_days_resolve() {
+    let dir = TempDir::new().unwrap();
+    let db_2022 = dir.path().join("subs_2022.redb");
+    let db_default = dir.path().join("subs_default.redb");
+    generate_subscribers(&db_2022, &["--start-date", "2022-06-01"]);
+    generate_subscribers(&db_default, &[]);
+
+    let earliest = |path: &Path| SubscriberDbRedb::open(path).unwrap().earliest_snapshot_ms().unwrap();
+    assert_eq!(earliest(&db_2022), Some(utc_ms(2022, 6, 1)));
+    assert_eq!(earliest(&db_default), Some(utc_ms(2024, 1, 1)));
+
+    // Subscribers of the 2022 database are there on 2022-07-01, the default database has none yet
+    let day = utc_ms(2022, 7, 1);
+    assert!(resolved_at(&db_2022, day) >= 290, "{} resolved", resolved_at(&db_2022, day));
+    assert_eq!(resolved_at(&db_default, day), 0);
+
+    let run_cdr = |start: &str| {
+        let output = common::cli("warn")
+            .arg("generate-cdr")
+            .arg("--subscriber-db")
+            .arg(&db_2022)
+            .arg("--out")
+            .arg(dir.path().join("out"))
+            .args(["--start", start, "--workers", "2", "--compression", "none"])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stderr).into_owned()
+    };
+    assert!(!run_cdr("2022-07-01").contains("earliest snapshot"));
+    let stderr = run_cdr("2022-05-31");
+    assert!(stderr.contains("2022-05-31 starts before the subscriber database's earliest snapshot (2022-06-01 00:00 UTC)"), "{}", stderr);
+}
+
+#[test]
+fn test_invalid_start_date_is_rejected() {
+    let dir = TempDir::new().unwrap();
+    let output = common::cli("warn")
+        .arg("generate-subscribers")
+        .arg("--output")
+        .arg(dir.path().join("subs.redb"))
+        .args(["--size", "10", "--start-date", "2022-13-01"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid --start-date"));
+}
@@ -0,0 +1,123 @@
+// Integration test for validate-subscribers --repair: messy CSV histories become databases that pass the strict checks
+use rs_cdr_generator::subscriber_db::SubscriberDatabase;
+use rs_cdr_generator::subscriber_db_generator::write_database_redb;
+use rs_cdr_generator::subscriber_db_redb::{DbWriteOptions, SubscriberDbRedb};
+use std::path::Path;
+use tempfile::TempDir;
+
+mod common;
+
+const HEADER: &str = "timestamp_ms,event_type,imsi,msisdn,imei,mccmnc";
+
+/// Histories that `build_snapshots` turns into overlapping or empty windows
+const PATHOLOGICAL: [(&str, &[&str]); 4] = [
+    (
+        "reassigned without a release",
+        &[
+            "1000,NEW_SUBSCRIBER,204080000000001,31612000001,111111111111111,20408",
+            "2000,ASSIGN_NUMBER,204080000000002,31612000001,222222222222222,20408",
+        ],
+    ),
+    (
+        "retroactive assignment before the release",
+        &[
+            "1000,NEW_SUBSCRIBER,204080000000001,31612000001,111111111111111,20408",
+            "5000,RELEASE_NUMBER,204080000000001,31612000001,,20408",
+            "3000,ASSIGN_NUMBER,204080000000002,31612000001,222222222222222,20408",
+        ],
+    ),
+    (
+        "SIM change leaving the old IMSI's window open",
+        &[
+            "1000,NEW_SUBSCRIBER,204080000000001,31612000001,111111111111111,20408",
+            "2000,CHANGE_SIM,204080000000001,31612000001,111111111111111,20408",
+            "2000,NEW_SUBSCRIBER,204080000000002,31612000001,111111111111111,20408",
+        ],
+    ),
+    (
+        "device change at the joining instant",
+        &[
+            "1000,NEW_SUBSCRIBER,204080000000001,31612000001,111111111111111,20408",
+            "1000,CHANGE_DEVICE,204080000000001,31612000001,222222222222222,20408",
+            "4000,SOFTWARE_UPDATE,204080000000001,31612000001,222222222222222,20408",
+        ],
+    ),
+];
+
+fn write_csv(path: &Path, rows: &[&str]) {
+    let mut csv = vec![HEADER];
+    csv.extend_from_slice(rows);
+    std::fs::write(path, csv.join("\n") + "\n").unwrap();
+}
+
+#[test]
+fn test_repaired_histories_pass_strict_validation() {
+    let dir = TempDir::new().unwrap();
+    for (i, (case, rows)) in PATHOLOGICAL.iter().enumerate() {
+        let csv_path = dir.path().join(format!("history{}.csv", i));
+        write_csv(&csv_path, rows);
+
+        // Imported as is, the windows fail the strict checks
+        let raw_path = dir.path().join(format!("raw{}.redb", i));
+        let events = SubscriberDatabase::load_from_csv(&csv_path).unwrap().events;
+        write_database_redb(events, &raw_path, &DbWriteOptions::default()).unwrap();
+        assert!(SubscriberDbRedb::open(&raw_path).unwrap().validate_windows().is_err(), "{}: raw import passed", case);
+        let output = common::cli("warn")
+            .arg("validate-subscribers")
+            .arg("--input")
+            .arg(&raw_path)
+            .arg("--strict")
+            .output()
+            .unwrap();
+        assert!(!output.status.success(), "{}: strict validation passed", case);
+
+        let fixed_path = dir.path().join(format!("fixed{}.redb", i));
+        let output = common::cli("warn")
+            .arg("validate-subscribers")
+            .arg("--input")
+            .arg(&csv_path)
+            .arg("--repair")
+            .arg("--output")
+            .arg(&fixed_path)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}: {}", case, String::from_utf8_lossy(&output.stderr));
+
+        let fixed = SubscriberDbRedb::open(&fixed_path).unwrap();
+        fixed.validate(2).unwrap();
+        fixed.validate_windows().unwrap();
+        drop(fixed);
+        let status = common::cli("warn")
+            .arg("validate-subscribers")
+            .arg("--input")
+            .arg(&fixed_path)
+            .arg("--strict")
+            .status()
+            .unwrap();
+        assert!(status.success(), "{}", case);
+    }
+}
+
+#[test]
+fn test_repair_logs_adjustments_and_needs_output() {
+    let dir = TempDir::new().unwrap();
+    let csv_path = dir.path().join("history.csv");
+    write_csv(&csv_path, PATHOLOGICAL[1].1);
+
+    let output = common::cli("warn")
+        .arg("validate-subscribers")
+        .arg("--input")
+        .arg(&csv_path)
+        .arg("--repair")
+        .arg("--output")
+        .arg(dir.path().join("fixed.redb"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert!(log.contains("released 31612000001 from 204080000000001 at 3000"), "{}", log);
+    assert!(log.contains("dropped release of 31612000001"), "{}", log);
+
+    let output = common::cli("warn").arg("validate-subscribers").arg("--input").arg(&csv_path).arg("--repair").output().unwrap();
+    assert!(!output.status.success());
+}
@@ -0,0 +1,178 @@
+// Integration test for the subscriber-stats subcommand
+use tempfile::TempDir;
+
+mod common;
+
+#[test]
+fn test_subscriber_stats_table_and_json() {
+    let dir = TempDir::new().unwrap();
+    let db_path = dir.path().join("subs.redb");
+
+    let status = common::cli("warn")
+        .arg("generate-subscribers")
+        .arg("--output")
+        .arg(&db_path)
+        .args(["--size", "200", "--history-days", "120", "--cooldown-days", "10"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let output = common::cli("warn").arg("subscriber-stats").arg("--input").arg(&db_path).output().unwrap();
+    assert!(output.status.success());
+    let table = String::from_utf8_lossy(&output.stdout);
+    assert!(table.contains("Snapshots per MSISDN"), "unexpected output: {}", table);
+    assert!(table.contains("Active subscribers"));
+
+    let output = common::cli("warn")
+        .arg("subscriber-stats")
+        .arg("--input")
+        .arg(&db_path)
+        .arg("--json")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stats: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let msisdns = stats["total_msisdns"].as_u64().unwrap();
+    let snapshots = stats["total_snapshots"].as_u64().unwrap();
+    assert!(msisdns >= 200);
+    assert!(snapshots >= msisdns);
+
+    // Histogram buckets add up to the totals
+    let per_msisdn = stats["snapshots_per_msisdn"].as_object().unwrap();
+    let counted: u64 = per_msisdn.values().map(|v| v.as_u64().unwrap()).sum();
+    assert_eq!(counted, msisdns);
+    let weighted: u64 = per_msisdn
+        .iter()
+        .map(|(k, v)| k.parse::<u64>().unwrap() * v.as_u64().unwrap())
+        .sum();
+    assert_eq!(weighted, snapshots);
+    assert!(!stats["active_by_month"].as_array().unwrap().is_empty());
+}
+
+#[test]
+fn test_validate_subscribers_with_shared_devices() {
+    let dir = TempDir::new().unwrap();
+    let db_path = dir.path().join("subs.redb");
+
+    let status = common::cli("warn")
+        .arg("generate-subscribers")
+        .arg("--output")
+        .arg(&db_path)
+        .args(["--size", "500", "--history-days", "120", "--device-change-rate", "0.5"])
+        .args(["--shared-device-rate", "0.5"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let status = common::cli("warn").arg("validate-subscribers").arg("--input").arg(&db_path).status().unwrap();
+    assert!(status.success());
+
+    // Dual-SIM devices exceed a limit of one IMSI per IMEI
+    let status = common::cli("warn")
+        .arg("validate-subscribers")
+        .arg("--input")
+        .arg(&db_path)
+        .args(["--max-imsis-per-imei", "1"])
+        .status()
+        .unwrap();
+    assert!(!status.success());
+}
+
+#[test]
+fn test_compact_subscribers_keeps_contents() {
+    let dir = TempDir::new().unwrap();
+    let db_path = dir.path().join("subs.redb");
+    let msisdns = |path: &std::path::Path| {
+        let output = common::cli("warn").arg("subscriber-stats").arg("--input").arg(path).arg("--json").output().unwrap();
+        assert!(output.status.success());
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()["total_msisdns"].as_u64().unwrap()
+    };
+
+    // Small transactions leave the file larger than its contents
+    let status = common::cli("warn")
+        .arg("generate-subscribers")
+        .arg("--output")
+        .arg(&db_path)
+        .args(["--size", "2000", "--history-days", "30", "--db-batch-size", "10"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let before = (msisdns(&db_path), std::fs::metadata(&db_path).unwrap().len());
+
+    let output = common::cli("warn")
+        .arg("compact-subscribers")
+        .arg("--input")
+        .arg(&db_path)
+        .args(["--spot-checks", "100"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("MB ->") && stdout.contains("100 of"), "{}", stdout);
+    assert_eq!(msisdns(&db_path), before.0);
+    assert!(std::fs::metadata(&db_path).unwrap().len() <= before.1);
+
+    // The same step at the end of generation
+    let compacted_path = dir.path().join("compacted.redb");
+    let status = common::cli("warn")
+        .arg("generate-subscribers")
+        .arg("--output")
+        .arg(&compacted_path)
+        .args(["--size", "2000", "--history-days", "30", "--db-batch-size", "10", "--compact"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert_eq!(msisdns(&compacted_path), before.0);
+}
+
+#[test]
+fn test_diff_subscribers() {
+    let dir = TempDir::new().unwrap();
+    let generate = |name: &str, device_change_rate: &str| {
+        let path = dir.path().join(name);
+        let status = common::cli("warn")
+            .arg("generate-subscribers")
+            .arg("--output")
+            .arg(&path)
+            .args(["--size", "300", "--history-days", "120", "--device-change-rate", device_change_rate])
+            .status()
+            .unwrap();
+        assert!(status.success());
+        path
+    };
+    let old_path = generate("old.redb", "0.1");
+    let new_path = generate("new.redb", "0.9");
+    let diff_json = |a: &std::path::Path, b: &std::path::Path, extra: &[&str]| {
+        let output =
+            common::cli("warn").arg("diff-subscribers").arg("--a").arg(a).arg("--b").arg(b).arg("--json").args(extra).output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()
+    };
+
+    // A database matches a copy of itself
+    let copy_path = dir.path().join("copy.redb");
+    std::fs::copy(&old_path, &copy_path).unwrap();
+    let same = diff_json(&old_path, &copy_path, &[]);
+    assert_eq!(same["only_in_a"], 0);
+    assert_eq!(same["only_in_b"], 0);
+    assert_eq!(same["differing"], 0);
+
+    let csv_path = dir.path().join("diff.csv");
+    let diff = diff_json(&old_path, &new_path, &["--output", csv_path.to_str().unwrap()]);
+    let count = |key: &str| diff[key].as_u64().unwrap();
+    assert!(count("imei_differs") > 0, "{}", diff);
+    assert!(!diff["snapshot_count_deltas"].as_object().unwrap().is_empty());
+
+    // One CSV row per MSISDN that is missing on a side or differs
+    let mut reader = csv::Reader::from_path(&csv_path).unwrap();
+    let kinds: Vec<String> = reader.records().map(|r| r.unwrap()[1].to_string()).collect();
+    assert_eq!(kinds.len() as u64, count("only_in_a") + count("only_in_b") + count("differing"));
+    assert_eq!(kinds.iter().filter(|k| *k == "changed").count() as u64, count("differing"));
+
+    // Comparing at the start of the history: devices have not changed yet
+    let at_start = diff_json(&old_path, &new_path, &["--at", "2024-01-01 00:00:00"]);
+    assert!(at_start["imei_differs"].as_u64().unwrap() < count("imei_differs"), "{}", at_start);
+
+    let output = common::cli("warn").arg("diff-subscribers").arg("--a").arg(&old_path).arg("--b").arg(&new_path).output().unwrap();
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Common MSISDNs"));
+}
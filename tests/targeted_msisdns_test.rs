@@ -0,0 +1,119 @@
+// Integration tests for msisdn_file: a targeted run over listed MSISDNs of a big redb database
+#![cfg(feature = "redb")]
+use chrono::NaiveDate;
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::config::Config;
+use rs_cdr_generator::writer::EventRow;
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+
+mod common;
+
+const DB_SIZE: u64 = 2_000;
+const PREFIX: u64 = 31612;
+
+fn date() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2025, 3, 10).unwrap()
+}
+
+/// Database of `DB_SIZE` MSISDNs numbered as the workers derive them
+fn build_db(path: &Path) -> BTreeSet<u64> {
+    common::build_indexed_db(path, PREFIX, 0..DB_SIZE);
+    (0..DB_SIZE).map(|idx| PREFIX * 10_000_000 + idx).collect()
+}
+
+/// 50 MSISDNs spread over the database, written one per line
+fn write_list(path: &Path) -> BTreeSet<u64> {
+    let listed: BTreeSet<u64> = (0..50).map(|i| PREFIX * 10_000_000 + i * 37 + 11).collect();
+    let mut file = std::fs::File::create(path).unwrap();
+    writeln!(file, "# QA test numbers").unwrap();
+    for msisdn in &listed {
+        writeln!(file, "{}", msisdn).unwrap();
+    }
+    listed
+}
+
+fn config(dir: &Path, closed_user_group: bool) -> Config {
+    Config {
+        prefixes: vec![PREFIX.to_string()],
+        workers: 4,
+        daily_inactive_rate: 0.0,
+        avg_calls_per_user: 6.0,
+        avg_sms_per_user: 6.0,
+        msisdn_file: Some(dir.join("msisdns.txt")),
+        closed_user_group,
+        ..Config::default()
+    }
+}
+
+fn generate(dir: &Path, cfg: Config) -> Vec<EventRow> {
+    let rows: Arc<Mutex<Vec<EventRow>>> = Arc::default();
+    let sink_rows = rows.clone();
+    let mut generator = DayGenerator::builder()
+        .config(cfg)
+        .subscriber_db(dir.join("subs.redb"))
+        .sink(move |batch| sink_rows.lock().unwrap().extend(batch.events))
+        .build()
+        .unwrap();
+    generator.generate(date()).unwrap();
+    let rows = rows.lock().unwrap();
+    rows.clone()
+}
+
+#[test]
+fn test_closed_user_group_only_has_listed_numbers() {
+    let dir = TempDir::new().unwrap();
+    let in_db = build_db(&dir.path().join("subs.redb"));
+    let listed = write_list(&dir.path().join("msisdns.txt"));
+
+    let rows = generate(dir.path(), config(dir.path(), true));
+    // Every listed MSISDN and no other database subscriber is the source of a row
+    let sources: BTreeSet<u64> = rows.iter().map(|row| row.msisdn_src).filter(|msisdn| in_db.contains(msisdn)).collect();
+    assert_eq!(sources, listed);
+    // Counterparts stay in the list too
+    assert!(rows.iter().filter(|row| row.event_type != "DATA").all(|row| listed.contains(&row.msisdn_dst)));
+    assert!(rows.iter().any(|row| row.direction == "MT" && row.event_type == "CALL"), "no MT legs between listed numbers");
+}
+
+#[test]
+fn test_listed_numbers_are_the_served_subscribers() {
+    let dir = TempDir::new().unwrap();
+    let in_db = build_db(&dir.path().join("subs.redb"));
+    let listed = write_list(&dir.path().join("msisdns.txt"));
+
+    let rows = generate(dir.path(), config(dir.path(), false));
+    // Outside a closed group counterparts may be external, but the subscribers served are the listed ones
+    let served: BTreeSet<u64> = rows.iter().filter(|row| row.direction != "MT").map(|row| row.msisdn_src).collect();
+    assert_eq!(served, listed);
+    assert!(rows.iter().any(|row| row.direction == "MO" && !in_db.contains(&row.msisdn_dst)), "no external counterparts");
+}
+
+#[test]
+fn test_targeted_run_writes_a_normal_day() {
+    let dir = TempDir::new().unwrap();
+    build_db(&dir.path().join("subs.redb"));
+    write_list(&dir.path().join("msisdns.txt"));
+    let out = dir.path().join("out");
+
+    let mut generator = DayGenerator::builder()
+        .config(Config { compression_type: "none".to_string(), ..config(dir.path(), true) })
+        .subscriber_db(dir.path().join("subs.redb"))
+        .out_dir(&out)
+        .build()
+        .unwrap();
+    let summary = generator.generate(date()).unwrap();
+    assert_eq!(summary.active_subscribers, 50);
+    assert_eq!(summary.skipped_subscribers, 0);
+    assert!(out.join("2025-03-10").join("summary.json").exists());
+}
+
+#[test]
+fn test_msisdn_file_needs_a_redb_database() {
+    let dir = TempDir::new().unwrap();
+    write_list(&dir.path().join("msisdns.txt"));
+    let err = DayGenerator::builder().config(config(dir.path(), false)).sink(|_| {}).build().err().unwrap();
+    assert!(err.to_string().contains("msisdn_file needs a redb subscriber database"), "{}", err);
+}
@@ -0,0 +1,115 @@
+// Integration test for the throughput report in summary.json and its --no-metrics switch
+use chrono::NaiveDate;
+use rs_cdr_generator::api::{DayGenerator, DaySummary};
+use rs_cdr_generator::config::Config;
+use std::path::Path;
+// The CLI binary is only built with every optional feature
+#[cfg(all(feature = "arrow", feature = "redb", feature = "async", feature = "zstd"))]
+use std::process::Command;
+use tempfile::TempDir;
+
+fn generate_days(out_dir: &Path, metrics: bool, days: u32) -> anyhow::Result<DayGenerator> {
+    let cfg = Config {
+        subscribers: 400,
+        workers: 4,
+        writer_tasks: 2,
+        compression_type: "gzip".to_string(),
+        metrics,
+        ..Config::default()
+    };
+    let mut generator = DayGenerator::builder().config(cfg).out_dir(out_dir).bundle(false).build()?;
+    for day in 1..=days {
+        generator.generate(NaiveDate::from_ymd_opt(2025, 1, day).unwrap())?;
+    }
+    Ok(generator)
+}
+
+fn read_summary(day_dir: &Path) -> anyhow::Result<(DaySummary, serde_json::Value)> {
+    let json = std::fs::read_to_string(day_dir.join("summary.json"))?;
+    Ok((serde_json::from_str(&json)?, serde_json::from_str(&json)?))
+}
+
+#[test]
+fn test_summary_reports_throughput() -> anyhow::Result<()> {
+    let dir = TempDir::new()?;
+    let generator = generate_days(dir.path(), true, 2)?;
+
+    let mut days = Vec::new();
+    for day in ["2025-01-01", "2025-01-02"] {
+        let day_dir = dir.path().join(day);
+        let (summary, _) = read_summary(&day_dir)?;
+        let throughput = summary.throughput.expect("summary.json without throughput");
+
+        // Events and on-disk bytes match the summary and the part files
+        assert_eq!(throughput.events, summary.total_calls + summary.total_sms + summary.total_data);
+        let part_bytes: u64 = std::fs::read_dir(&day_dir)?
+            .map(|e| e.unwrap().path())
+            .filter(|p| p.file_name().unwrap().to_string_lossy().starts_with("cdr_"))
+            .map(|p| std::fs::metadata(p).unwrap().len())
+            .sum();
+        assert_eq!(throughput.compressed_bytes, part_bytes);
+        assert!(throughput.uncompressed_bytes > throughput.compressed_bytes);
+        let ratio = throughput.uncompressed_bytes as f64 / throughput.compressed_bytes as f64;
+        assert!((throughput.compression_ratio - ratio).abs() < 1e-9);
+        assert!(throughput.wall_clock_sec > 0.0 && throughput.events_per_sec > 0.0);
+
+        assert_eq!(throughput.writers.len(), 2);
+        for writer in &throughput.writers {
+            assert!(writer.events > 0 && writer.busy_sec > 0.0 && writer.peak_queue_depth >= 1);
+        }
+        days.push(throughput);
+    }
+
+    // The run totals add up the days
+    let run = generator.run_throughput().expect("no run throughput");
+    assert_eq!(run.events, days[0].events + days[1].events);
+    assert_eq!(run.compressed_bytes, days[0].compressed_bytes + days[1].compressed_bytes);
+    assert_eq!(run.writers.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_metrics_off_leaves_summary_unchanged() -> anyhow::Result<()> {
+    let dir = TempDir::new()?;
+    let generator = generate_days(dir.path(), false, 1)?;
+    let (summary, json) = read_summary(&dir.path().join("2025-01-01"))?;
+    assert!(summary.throughput.is_none());
+    assert!(json.get("throughput").is_none());
+    assert!(generator.run_throughput().is_none());
+    Ok(())
+}
+
+#[cfg(all(feature = "arrow", feature = "redb", feature = "async", feature = "zstd"))]
+#[test]
+fn test_no_metrics_flag() -> anyhow::Result<()> {
+    let dir = TempDir::new()?;
+    let db_path = dir.path().join("subs.redb");
+    let status = Command::new(env!("CARGO_BIN_EXE_rs_cdr_generator"))
+        .env("RUST_LOG", "warn")
+        .arg("generate-subscribers")
+        .arg("--output")
+        .arg(&db_path)
+        .args(["--size", "200"])
+        .status()?;
+    assert!(status.success());
+
+    for (flag, out) in [(None, "with"), (Some("--no-metrics"), "without")] {
+        let out_dir = dir.path().join(out);
+        let status = Command::new(env!("CARGO_BIN_EXE_rs_cdr_generator"))
+            .env("RUST_LOG", "warn")
+            .arg("generate-cdr")
+            .arg("--subscriber-db")
+            .arg(&db_path)
+            .arg("--out")
+            .arg(&out_dir)
+            .args(["--start", "2025-01-01", "--workers", "2", "--compression", "none"])
+            .args(flag)
+            .status()?;
+        assert!(status.success());
+
+        // Bundled days keep summary.json next to the bundle
+        let (_, json) = read_summary(&out_dir.join("2025-01-01"))?;
+        assert_eq!(json.get("throughput").is_some(), flag.is_none(), "{}", json);
+    }
+    Ok(())
+}
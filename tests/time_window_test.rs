@@ -0,0 +1,123 @@
+// Integration test for time windows: a partial day gets its share of the daily volume, in its own folder
+use chrono::NaiveDate;
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::config::{Config, TimeWindow};
+use rs_cdr_generator::generators::placement_window;
+use rs_cdr_generator::reader::open_cdr_reader;
+use rs_cdr_generator::timezone_utils::tz_from_name;
+use std::path::Path;
+// The CLI binary is only built with every optional feature
+#[cfg(all(feature = "arrow", feature = "redb", feature = "async", feature = "zstd"))]
+use std::process::Command;
+use tempfile::TempDir;
+
+fn day() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2025, 1, 8).unwrap()
+}
+
+fn config(time_window: Option<TimeWindow>) -> Config {
+    Config {
+        subscribers: 4000,
+        workers: 2,
+        compression_type: "gzip".to_string(),
+        time_window,
+        ..Config::default()
+    }
+}
+
+fn generate(out_dir: &Path, cfg: Config) -> anyhow::Result<usize> {
+    let mut generator = DayGenerator::builder().config(cfg).out_dir(out_dir).bundle(false).build()?;
+    let summary = generator.generate(day())?;
+    Ok(summary.total_calls + summary.total_sms + summary.total_data)
+}
+
+#[test]
+fn test_hour_window_gets_its_share_of_the_day() -> anyhow::Result<()> {
+    let dir = TempDir::new()?;
+    let full = generate(&dir.path().join("full"), config(None))?;
+
+    let window = TimeWindow::parse("13:00-14:00")?;
+    let cfg = config(Some(window));
+    let (start, end, share) = placement_window(&cfg, tz_from_name(&cfg.tz_name), day())?;
+    assert!(share > 0.03 && share < 0.1, "share {}", share);
+    let windowed = generate(&dir.path().join("window"), cfg)?;
+
+    let expected = full as f64 * share;
+    assert!(
+        (windowed as f64 - expected).abs() < expected * 0.15,
+        "window {} events, expected ~{:.0} of {}",
+        windowed,
+        expected,
+        full
+    );
+
+    // Folder and part files carry the window; every event starts inside it
+    let day_dir = dir.path().join("window").join("2025-01-08_1300-1400");
+    assert!(day_dir.join("summary.json").exists());
+    let mut rows = 0;
+    for entry in std::fs::read_dir(&day_dir)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        if !name.starts_with("cdr_") {
+            continue;
+        }
+        assert!(name.starts_with("cdr_2025-01-08_1300-1400_writer"), "{}", name);
+        for row in open_cdr_reader(&path)? {
+            let row = row?;
+            assert!((start.timestamp_millis()..end.timestamp_millis()).contains(&row.start_ts_ms), "{:?}", row);
+            rows += 1;
+        }
+    }
+    assert_eq!(rows, windowed);
+    Ok(())
+}
+
+#[cfg(all(feature = "arrow", feature = "redb", feature = "async", feature = "zstd"))]
+#[test]
+fn test_cli_window_bundling_is_optional() {
+    let dir = TempDir::new().unwrap();
+    let db_path = dir.path().join("subs.redb");
+    let status = Command::new(env!("CARGO_BIN_EXE_rs_cdr_generator"))
+        .env("RUST_LOG", "error")
+        .arg("generate-subscribers")
+        .arg("--output")
+        .arg(&db_path)
+        .args(["--size", "100", "--history-days", "30"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let run = |out_dir: &Path, args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_rs_cdr_generator"))
+            .env("RUST_LOG", "error")
+            .arg("generate-cdr")
+            .arg("--subscriber-db")
+            .arg(&db_path)
+            .arg("--out")
+            .arg(out_dir)
+            .args(["--workers", "2", "--compression", "gzip"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    };
+
+    let parts = dir.path().join("parts");
+    run(&parts, &["--window", "13:00-14:00"]);
+    assert!(parts.join("2025-01-01_1300-1400").join("summary.json").exists());
+    assert!(!parts.join("cdr_2025-01-01_1300-1400.csv.gz").exists());
+
+    let bundled = dir.path().join("bundled");
+    run(&bundled, &["--start-time", "22:00", "--bundle-window"]);
+    assert!(bundled.join("cdr_2025-01-01_2200-2400.csv.gz").exists());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rs_cdr_generator"))
+        .arg("generate-cdr")
+        .arg("--subscriber-db")
+        .arg(&db_path)
+        .args(["--window", "14:00-13:00"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("is empty"));
+}
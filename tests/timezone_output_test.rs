@@ -0,0 +1,163 @@
+// Integration tests for UTC-only output: dropped timezone columns, UTC event placement and UTC day folders
+use chrono::{NaiveDate, TimeZone, Utc};
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::config::{Config, DayFolderBasis, EventTimeBasis};
+use rs_cdr_generator::writer::ShardManifest;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+fn generate(out_dir: &Path, cfg: Config) -> anyhow::Result<PathBuf> {
+    let mut generator = DayGenerator::builder().config(cfg).out_dir(out_dir).bundle(false).build()?;
+    generator.generate(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap())?;
+    Ok(out_dir.join("2025-01-01"))
+}
+
+fn part_files(day_dir: &Path) -> Vec<PathBuf> {
+    let mut parts: Vec<PathBuf> = std::fs::read_dir(day_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            let name = path.file_name().unwrap().to_string_lossy();
+            name.starts_with("cdr_") && name.ends_with(".csv")
+        })
+        .collect();
+    parts.sort();
+    parts
+}
+
+fn csv_reader(path: &Path) -> csv::Reader<std::fs::File> {
+    csv::ReaderBuilder::new().delimiter(b';').from_path(path).unwrap()
+}
+
+#[test]
+fn test_timezone_columns_omitted_in_every_part() -> anyhow::Result<()> {
+    // Both serialization paths must agree on the layout
+    for serialize_in_workers in [true, false] {
+        let temp_dir = TempDir::new()?;
+        let cfg = Config {
+            subscribers: 300,
+            workers: 2,
+            compression_type: "none".to_string(),
+            rotate_bytes: 50_000,
+            emit_timezone_columns: false,
+            emit_record_seq: true,
+            serialize_in_workers,
+            ..Config::default()
+        };
+        let day_dir = generate(temp_dir.path(), cfg)?;
+
+        let parts = part_files(&day_dir);
+        assert!(parts.len() > 2, "expected rotated parts, got {:?}", parts);
+        for part in &parts {
+            let mut reader = csv_reader(part);
+            let headers = reader.headers()?.clone();
+            assert!(!headers.iter().any(|h| h == "tz_name" || h == "tz_offset_min"), "{:?}: {:?}", part, headers);
+            assert_eq!(headers.iter().next_back(), Some("record_seq"));
+            for record in reader.records() {
+                assert_eq!(record?.len(), headers.len());
+            }
+        }
+
+        // The manifests describe the same layout
+        let manifests: Vec<PathBuf> = std::fs::read_dir(&day_dir)?
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.file_name().unwrap().to_string_lossy().starts_with("manifest_"))
+            .collect();
+        assert!(!manifests.is_empty());
+        for path in manifests {
+            let manifest: ShardManifest = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+            let headers = csv_reader(&day_dir.join(&manifest.files[0].file)).headers()?.clone();
+            assert_eq!(manifest.columns, headers.iter().collect::<Vec<_>>());
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_event_time_basis_bounds_the_day() -> anyhow::Result<()> {
+    let utc_midnight = |day: u32| Utc.with_ymd_and_hms(2025, 1, day, 0, 0, 0).unwrap().timestamp_millis();
+    let utc_day = utc_midnight(1)..utc_midnight(2);
+
+    let mut before_utc_midnight = Vec::new();
+    for basis in [EventTimeBasis::Utc, EventTimeBasis::Local] {
+        let temp_dir = TempDir::new()?;
+        let cfg = Config {
+            subscribers: 300,
+            workers: 1,
+            compression_type: "none".to_string(),
+            // Flat curve so the late evening and the first local hour are as busy as noon
+            diurnal_weekday: vec![1.0; 24],
+            event_time_basis: basis,
+            ..Config::default()
+        };
+        let day_dir = generate(temp_dir.path(), cfg)?;
+
+        let mut early = 0;
+        for part in part_files(&day_dir) {
+            let mut reader = csv_reader(&part);
+            let headers = reader.headers()?.clone();
+            let column = |name: &str| headers.iter().position(|h| h == name).unwrap();
+            let (start, offset) = (column("start_ts_ms"), column("tz_offset_min"));
+            for record in reader.records() {
+                let record = record?;
+                let start_ts: i64 = record[start].parse()?;
+                // Timezone columns stay local either way
+                assert_eq!(&record[offset], "60");
+                if basis == EventTimeBasis::Utc {
+                    assert!(utc_day.contains(&start_ts), "{} outside the UTC day", start_ts);
+                }
+                early += (start_ts < utc_day.start) as usize;
+            }
+        }
+        before_utc_midnight.push(early);
+    }
+
+    // Local placement starts at Amsterdam midnight, an hour before UTC midnight
+    assert_eq!(before_utc_midnight[0], 0);
+    assert!(before_utc_midnight[1] > 100, "{:?}", before_utc_midnight);
+    Ok(())
+}
+
+#[test]
+fn test_day_folder_basis_names_and_bounds_the_day() -> anyhow::Result<()> {
+    // Brisbane is UTC+10 all year
+    let utc_midnight = |day: u32| Utc.with_ymd_and_hms(2025, 1, day, 0, 0, 0).unwrap().timestamp_millis();
+    let utc_day = utc_midnight(1)..utc_midnight(2);
+    let hours_10 = 10 * 3_600_000;
+    let local_day = utc_day.start - hours_10..utc_day.end - hours_10;
+
+    for (basis, day) in [(DayFolderBasis::Utc, &utc_day), (DayFolderBasis::Local, &local_day)] {
+        let temp_dir = TempDir::new()?;
+        let cfg = Config {
+            subscribers: 300,
+            workers: 1,
+            compression_type: "none".to_string(),
+            tz_name: "Australia/Brisbane".to_string(),
+            day_folder_basis: basis,
+            ..Config::default()
+        };
+        // The folder is named by the date of the requested basis
+        let day_dir = generate(temp_dir.path(), cfg)?;
+        assert!(day_dir.join("summary.json").exists(), "{:?}", day_dir);
+
+        let mut by_local_hour = [0usize; 24];
+        for part in part_files(&day_dir) {
+            assert!(part.file_name().unwrap().to_string_lossy().contains("2025-01-01"), "{:?}", part);
+            let mut reader = csv_reader(&part);
+            let headers = reader.headers()?.clone();
+            let column = |name: &str| headers.iter().position(|h| h == name).unwrap();
+            let (start, offset) = (column("start_ts_ms"), column("tz_offset_min"));
+            for record in reader.records() {
+                let record = record?;
+                let start_ts: i64 = record[start].parse()?;
+                assert!(day.contains(&start_ts), "{:?}: {} outside the day", basis, start_ts);
+                // Events keep local time
+                assert_eq!(&record[offset], "600");
+                by_local_hour[((start_ts + hours_10) / 3_600_000 % 24) as usize] += 1;
+            }
+        }
+        // The diurnal curve is still read in local hours: local noon is busy, 03:00 quiet
+        assert!(by_local_hour[12] > 3 * by_local_hour[3], "{:?}: {:?}", basis, by_local_hour);
+    }
+    Ok(())
+}
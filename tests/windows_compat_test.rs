@@ -0,0 +1,69 @@
+// Windows-only integration test: a small day is generated, part files rotate while open,
+// late files are moved aside and the rest are bundled and deleted
+#![cfg(windows)]
+use chrono::NaiveDate;
+use rs_cdr_generator::api::DayGenerator;
+use rs_cdr_generator::config::Config;
+use rs_cdr_generator::late_delivery::LATE_DIR;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+fn files_in(dir: &Path) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir).unwrap().map(|e| e.unwrap().path()).collect();
+    paths.sort();
+    paths
+}
+
+fn is_part_file(path: &Path) -> bool {
+    let name = path.file_name().unwrap().to_string_lossy();
+    name.starts_with("cdr_") && !name.ends_with(".late.json")
+}
+
+#[test]
+fn test_generate_bundle_and_cleanup() -> anyhow::Result<()> {
+    let dir = TempDir::new()?;
+    let cfg = Config {
+        subscribers: 600,
+        workers: 2,
+        compression_type: "gzip".to_string(),
+        // Small parts so rotation checks the size of files that are still open
+        rotate_bytes: 20_000,
+        late_file_rate: 0.3,
+        ..Config::default()
+    };
+    let mut generator = DayGenerator::builder().config(cfg).out_dir(dir.path()).bundle(true).build()?;
+    let summary = generator.generate(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap())?;
+    assert!(summary.total_calls + summary.total_sms + summary.total_data > 0);
+
+    let day_dir = dir.path().join("2025-01-01");
+    let bundle = dir.path().join("cdr_2025-01-01.csv.gz");
+    assert!(std::fs::metadata(&bundle)?.len() > 0);
+    assert!(day_dir.join("summary.json").exists());
+
+    // Part files were closed before the bundle deleted them
+    assert!(!files_in(&day_dir).iter().any(|p| is_part_file(p)));
+
+    // Manifests name bare files, with no separator of either kind
+    let mut listed = 0;
+    for path in files_in(&day_dir) {
+        if !path.file_name().unwrap().to_string_lossy().starts_with("manifest_writer") {
+            continue;
+        }
+        let manifest: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+        for entry in manifest["files"].as_array().unwrap() {
+            let file = entry["file"].as_str().unwrap();
+            assert!(!file.contains('/') && !file.contains('\\'), "{}", file);
+            listed += 1;
+        }
+    }
+    assert!(listed > 1, "expected rotated part files, got {}", listed);
+
+    // Late files were moved while no handle was left open on them
+    let late_dir = dir.path().join(LATE_DIR).join("2025-01-01");
+    if late_dir.exists() {
+        for path in files_in(&late_dir).iter().filter(|p| is_part_file(p)) {
+            std::fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}